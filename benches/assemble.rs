@@ -0,0 +1,68 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Library assembly cost.
+//!
+//! Benchmarks named `constants/<count>` assemble programs putting `<count>` distinct 256-bit
+//! constants into registers, such that the data segment is searched for a duplicate of each of
+//! the constants. Benchmarks named `constants-no-dedup/<count>` assemble the same programs with
+//! the deduplication disabled, providing the baseline.
+
+use aluvm::data::MaybeNumber;
+use aluvm::isa::{Instr, PutOp};
+use aluvm::library::{AssembleOpts, Lib};
+use aluvm::reg::{Reg32, RegA};
+use amplify::num::u256;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+/// Numbers of constants in the benchmarked programs
+const COUNTS: [usize; 3] = [100, 500, 2000];
+
+/// Generates code putting `count` distinct constants into `a256` registers.
+fn constants_code(count: usize) -> Vec<Instr> {
+    (0..count)
+        .map(|no| {
+            let val = u256::from(no as u64 + 1) * u256::from(0x9E37_79B9_7F4A_7C15u64);
+            let idx = Reg32::ALL[no % 32];
+            Instr::Put(PutOp::PutA(RegA::A256, idx, Box::new(MaybeNumber::from(val))))
+        })
+        .collect()
+}
+
+fn constants(c: &mut Criterion) {
+    for (name, dedup) in [("constants", true), ("constants-no-dedup", false)] {
+        let mut group = c.benchmark_group(name);
+        for count in COUNTS {
+            let code = constants_code(count);
+            let opts = AssembleOpts { dedup, ..AssembleOpts::default() };
+            group.throughput(Throughput::Elements(count as u64));
+            group.bench_function(count.to_string(), |b| {
+                b.iter(|| black_box(Lib::assemble_with_opts(&code, opts).expect("invalid code")))
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, constants);
+criterion_main!(benches);
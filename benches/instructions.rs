@@ -0,0 +1,150 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-instruction execution and decoding cost.
+//!
+//! Each benchmark runs a library repeating a single instruction [`REPEAT`] times over registers
+//! of the same bit width. Benchmarks are named `exec/<class>/<mnemonic>/<width>` and
+//! `decode/<class>/<mnemonic>/<width>`, where `<class>` is the [`InstrClass`] of the instruction,
+//! such that the results may be mapped back onto the cost model constants.
+//!
+//! Benchmarks named `straight-line/<stage>` decode and execute a long program mixing all the
+//! benchmarked instructions, measuring instruction dispatch throughput.
+
+use aluvm::isa::{Bytecode, Instr};
+use aluvm::library::{InstrClass, Lib};
+use aluvm::reg::CoreRegs;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+/// Number of times the benchmarked instruction is repeated in the library code
+const REPEAT: usize = 64;
+
+/// Number of instructions in the straight-line program
+const STRAIGHT_LINE_LEN: usize = 2048;
+
+/// Bit widths of the `A` registers used by the benchmarks
+const WIDTHS: [u16; 8] = [8, 16, 32, 64, 128, 256, 512, 1024];
+
+/// Benchmarked instructions, with `{}` being replaced by the register bit width
+const INSTRS: [&str; 9] = [
+    "put    a{}[1],5",
+    "cpy    a{}[1],a{}[3]",
+    "eq.n   a{}[0],a{}[1]",
+    "add.uw a{}[1],a{}[0]",
+    "mul.uw a{}[1],a{}[0]",
+    "div.uc a{}[1],a{}[0]",
+    "inc    a{}[0]",
+    "xor    a{}[0],a{}[1],a{}[2]",
+    "shl    a8[0],a{}[1]",
+];
+
+/// Assembles library initializing registers and repeating the instruction [`REPEAT`] times.
+fn bench_lib(instr: &str, width: u16) -> (Lib<'static>, InstrClass, String) {
+    let instr = instr.replace("{}", &width.to_string());
+    let mut source =
+        format!("put a8[0],1\nput a{w}[0],3\nput a{w}[1],5\nput a{w}[2],7\n", w = width);
+    for _ in 0..REPEAT {
+        source.push_str(&instr);
+        source.push('\n');
+    }
+    source.push_str("ret\n");
+
+    let lib = Lib::assemble_source::<Instr>(&source).expect("invalid benchmark code");
+    let class = InstrClass::of(&lib.disassemble::<Instr>().expect("invalid benchmark code")[4]);
+    let mnemonic = instr.split_whitespace().next().unwrap_or_default().to_owned();
+    (lib, class, mnemonic)
+}
+
+fn exec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("exec");
+    group.throughput(Throughput::Elements(REPEAT as u64));
+    for instr in INSTRS {
+        for width in WIDTHS {
+            let (lib, class, mnemonic) = bench_lib(instr, width);
+            group.bench_function(format!("{class}/{mnemonic}/{width}"), |b| {
+                b.iter_batched_ref(
+                    CoreRegs::default,
+                    |regs| lib.run::<Instr>(0, regs, &()).expect("run limit exceeded"),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(REPEAT as u64));
+    for instr in INSTRS {
+        for width in WIDTHS {
+            let (lib, class, mnemonic) = bench_lib(instr, width);
+            group.bench_function(format!("{class}/{mnemonic}/{width}"), |b| {
+                b.iter(|| {
+                    for item in lib.disassemble_iter::<Instr>() {
+                        let (_, instr) = item.expect("invalid benchmark code");
+                        black_box(instr.instr_byte());
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Assembles library cycling through all benchmarked instructions over 64-bit registers, up to
+/// the [`STRAIGHT_LINE_LEN`] instructions.
+fn straight_line_lib() -> Lib<'static> {
+    let mut source = String::from("put a8[0],1\nput a64[0],3\nput a64[1],5\nput a64[2],7\n");
+    for instr in INSTRS.iter().cycle().take(STRAIGHT_LINE_LEN) {
+        source.push_str(&instr.replace("{}", "64"));
+        source.push('\n');
+    }
+    source.push_str("ret\n");
+    Lib::assemble_source::<Instr>(&source).expect("invalid benchmark code")
+}
+
+fn straight_line(c: &mut Criterion) {
+    let lib = straight_line_lib();
+    let mut group = c.benchmark_group("straight-line");
+    group.throughput(Throughput::Elements(STRAIGHT_LINE_LEN as u64));
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for item in lib.disassemble_iter::<Instr>() {
+                let (_, instr) = item.expect("invalid benchmark code");
+                black_box(instr.instr_byte());
+            }
+        })
+    });
+    group.bench_function("exec", |b| {
+        b.iter_batched_ref(
+            CoreRegs::default,
+            |regs| lib.run::<Instr>(0, regs, &()).expect("run limit exceeded"),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, exec, decode, straight_line);
+criterion_main!(benches);
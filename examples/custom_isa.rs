@@ -0,0 +1,135 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Template of a custom instruction set extending the AluVM core instructions.
+//!
+//! An ISA extension is an enum of its instructions implementing:
+//! - [`Bytecode`] for the binary encoding, which may be generated with [`aluvm::bytecode!`] macro
+//!   from the table of opcodes and operand bit sizes; the opcodes must be taken from the ISA
+//!   extension range `0x80..=0xFE` and must not be used by the standard extensions (see
+//!   [`aluvm::isa::opcodes`]), which is checked by [`Bytecode::opcode_collisions`];
+//! - [`InstructionSet`] for the execution, declaring the ISA extension id put into the libraries
+//!   using it;
+//! - [`Display`] and [`FromStr`] for the text assembly, which may reuse [`Line`] and [`operand`]
+//!   parsers to follow the syntax of the core instructions.
+//!
+//! The extension is composed with the core instructions as `Instr<Extension>`. Several
+//! extensions may be composed with [`aluvm::isa::Extensions`].
+//!
+//! Run with `cargo run --example custom_isa`.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use aluvm::isa::{operand, Bytecode, ExecStep, Instr, InstructionSet, Line, ParseInstrError};
+use aluvm::library::{Lib, LibSite};
+use aluvm::reg::{CoreRegs, Reg, Reg32, RegA};
+
+/// Instructions counting bits in the arithmetic registers
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum CountOp {
+    /// Puts zero into the register
+    Zero(RegA, Reg32),
+    /// Replaces register value with the number of its bits set to one
+    Popcnt(RegA, Reg32),
+}
+
+aluvm::bytecode! {
+    CountOp {
+        0xE0 => Zero(reg, idx) [reg: u3, idx: u5],
+        0xE1 => Popcnt(reg, idx) [reg: u3, idx: u5],
+    }
+}
+
+impl Display for CountOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CountOp::Zero(reg, idx) => write!(f, "zero    {}{}", reg, idx),
+            CountOp::Popcnt(reg, idx) => write!(f, "popcnt  {}{}", reg, idx),
+        }
+    }
+}
+
+impl FromStr for CountOp {
+    type Err = ParseInstrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(s)?;
+        let constructor = match line.mnemonic() {
+            "zero" => CountOp::Zero,
+            "popcnt" => CountOp::Popcnt,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        line.no_flags()?;
+        let [op] = line.ops()?;
+        let (reg, idx) = operand::reg_a(op).ok_or_else(|| line.invalid())?;
+        Ok(constructor(reg, idx))
+    }
+}
+
+impl InstructionSet for CountOp {
+    type Context<'ctx> = ();
+
+    fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::from(["COUNT"]) }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            CountOp::Zero(..) => BTreeSet::new(),
+            CountOp::Popcnt(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            CountOp::Zero(reg, idx) | CountOp::Popcnt(reg, idx) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _ctx: &()) -> ExecStep {
+        match *self {
+            CountOp::Zero(reg, idx) => regs.set(reg, idx, 0u8),
+            CountOp::Popcnt(reg, idx) => {
+                let count = regs.get(reg, idx).map(|value| value.count_ones());
+                regs.set(reg, idx, count)
+            }
+        };
+        ExecStep::Next
+    }
+}
+
+fn main() {
+    assert!(Instr::<CountOp>::opcode_collisions().is_empty());
+
+    let source = "put a64[0],0xF0F0\npopcnt a64[0]\nzero a8[1]\nsucc\n";
+    let lib = Lib::assemble_source::<Instr<CountOp>>(source).expect("invalid program");
+    println!("{}", lib.disassemble_listing::<Instr<CountOp>>().expect("broken library"));
+
+    let mut regs = CoreRegs::default();
+    let res = lib.run::<Instr<CountOp>>(0, &mut regs, &()).expect("execution failed");
+    assert!(res.is_success());
+    assert_eq!(regs.get_a(RegA::A64, Reg32::Reg0), Some(8u64.into()));
+    assert_eq!(regs.get_a(RegA::A8, Reg32::Reg1), Some(0u8.into()));
+}
@@ -0,0 +1,174 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Audit of program execution for the sources of non-determinism.
+//!
+//! With the `deterministic-audit` feature, each executed instruction is checked before its
+//! execution, and the findings are collected by the [`Audit`] kept with the VM registers (see
+//! [`CoreRegs::audit`]) as structured [`Violation`]s. The audit is intended to be run on the test
+//! suites of the programs before they are deployed in consensus-critical environments; a program
+//! which completes its runs without violations is safe to be used by independent implementations
+//! of the VM.
+//!
+//! The audit relies on the registers reported by [`InstructionSet::src_regs`] and
+//! [`InstructionSet::dst_regs`], thus it does not detect violations by extension instructions
+//! which do not report their registers.
+
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::isa::InstructionSet;
+use crate::library::LibSite;
+use crate::reg::{CoreRegs, Reg, Reg32, RegA, RegF, RegR, RegS};
+
+/// Source of non-determinism detected by the execution audit.
+///
+/// Programs must not rely on the registers which were neither initialized by the host before the
+/// execution nor written by the program, since their state may differ between the runtimes. Float
+/// operation results depend on the rounding and handling of NaN values and subnormals, which are
+/// not guaranteed to be the same across VM implementations.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum Violation {
+    /// register {reg} is read by instruction at {site} before any value was written into it
+    UninitializedRead {
+        /// Location of the instruction reading the register
+        site: LibSite,
+        /// Uninitialized register
+        reg: Reg,
+    },
+
+    /// instruction `{instr}` at {site} operates on floating-point registers
+    FloatOp {
+        /// Location of the instruction
+        site: LibSite,
+        /// Instruction in assembly format
+        instr: String,
+    },
+}
+
+/// Findings of the execution audit
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Audit {
+    /// Registers which were set by the host before the execution or written by the program; `None`
+    /// before the first audited instruction.
+    initialized: Option<BTreeSet<Reg>>,
+    violations: BTreeSet<Violation>,
+}
+
+/// Enumerates registers which have a value.
+fn set_regs(regs: &CoreRegs) -> BTreeSet<Reg> {
+    let mut all = Vec::with_capacity(Reg32::ALL.len() * 24 + 16);
+    for idx in Reg32::ALL {
+        all.extend(RegA::ALL.iter().map(|a| Reg::A(*a, idx)));
+        all.extend(RegF::ALL.iter().map(|f| Reg::F(*f, idx)));
+        all.extend(RegR::ALL.iter().map(|r| Reg::R(*r, idx)));
+    }
+    all.extend((0..16u8).map(|idx| Reg::S(RegS::from(idx))));
+    all.into_iter().filter(|reg| regs.is_set(*reg)).collect()
+}
+
+impl Audit {
+    /// Returns detected violations, ordered by their kind and location.
+    #[inline]
+    pub fn violations(&self) -> &BTreeSet<Violation> { &self.violations }
+
+    /// Detects whether no violations were found.
+    #[inline]
+    pub fn is_clean(&self) -> bool { self.violations.is_empty() }
+
+    /// Checks instruction at the given location before its execution with the registers.
+    ///
+    /// Registers which have values at the moment of the first check are considered initialized by
+    /// the host.
+    pub(crate) fn check<Isa>(&mut self, instr: &Isa, site: LibSite, regs: &CoreRegs)
+    where
+        Isa: InstructionSet,
+    {
+        let initialized = self.initialized.get_or_insert_with(|| set_regs(regs));
+        let src = instr.src_regs();
+        let dst = instr.dst_regs();
+        for reg in &src {
+            if !initialized.contains(reg) {
+                self.violations.insert(Violation::UninitializedRead { site, reg: *reg });
+            }
+        }
+        if src.iter().chain(&dst).any(|reg| matches!(reg, Reg::F(..))) {
+            self.violations.insert(Violation::FloatOp { site, instr: instr.to_string() });
+        }
+        initialized.extend(dst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::Instr;
+    use crate::library::Lib;
+    use crate::{Prog, Vm};
+
+    #[test]
+    fn violations() {
+        let lib = Lib::assemble_source::<Instr>(
+            "
+            put     a8[1],1
+            add.uc  a8[0],a8[1]
+            add.uc  a8[2],a8[1]
+            put     f32[0],1.5
+            neg     f32[0]
+            ret
+        ",
+        )
+        .unwrap();
+        let id = lib.id();
+        let prog = Prog::<Instr>::new(lib);
+        let mut vm = Vm::<Instr>::new();
+        vm.registers.set(RegA::A8, Reg32::Reg2, 5u8);
+        assert!(vm.run(&prog, &()));
+
+        let audit = vm.registers.audit();
+        assert!(!audit.is_clean());
+        assert_eq!(audit.violations().iter().cloned().collect::<Vec<_>>(), [
+            Violation::UninitializedRead {
+                site: LibSite::with(4, id),
+                reg: Reg::A(RegA::A8, Reg32::Reg0)
+            },
+            Violation::FloatOp { site: LibSite::with(10, id), instr: s!("put     f32[0],1.5") },
+            Violation::FloatOp { site: LibSite::with(14, id), instr: s!("neg     f32[0]") },
+        ]);
+        assert_eq!(
+            audit.violations().iter().next().unwrap().to_string(),
+            format!(
+                "register a8[0] is read by instruction at 4 @ {id} before any value was written \
+                 into it"
+            )
+        );
+
+        // Each run is audited separately, with registers set before the run being considered
+        // initialized by the host
+        vm.registers.set(RegA::A8, Reg32::Reg0, 1u8);
+        assert!(vm.run(&prog, &()));
+        assert_eq!(vm.registers.audit().violations().len(), 2);
+    }
+}
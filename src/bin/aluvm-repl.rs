@@ -0,0 +1,214 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interactive line-based REPL for AluVM.
+//!
+//! Each line typed in the assembly syntax is executed immediately against the registers kept
+//! for the whole session and is appended to the session code. Lines starting with `:` are the
+//! REPL commands (see `:help`), allowing to inspect registers, re-run the session code with
+//! breakpoints and save it as a library.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::{fs, mem};
+
+use aluvm::data::encoding::Encode;
+use aluvm::isa::{Bytecode, ControlFlowOp, Instr};
+use aluvm::library::{Lib, LibSite};
+use aluvm::reg::CoreRegs;
+use aluvm::{Execution, Prog, RunLimits, Vm, VmState};
+
+/// Maximal number of instructions executed by a single `:run` or `:continue` command, preventing
+/// infinite loops from hanging the session.
+const MAX_STEPS: u64 = 1_000_000;
+
+const HELP: &str = "Type instructions in the assembly syntax to execute them and append them to \
+                    the session code, which is always terminated with `ret` instruction.
+
+Commands:
+  :regs               print the registers
+  :list               print the session code
+  :run                reset the registers and run the session code from the beginning
+  :continue           continue running the session code paused at a breakpoint
+  :break [OFFSET]     set breakpoint at the code offset, or list the breakpoints
+  :clear OFFSET       remove breakpoint from the code offset
+  :reset              reset the registers, keeping the session code
+  :save FILE          save the session code as a binary library
+  :help               print this help
+  :quit               exit the REPL";
+
+struct Session {
+    code: Vec<Instr>,
+    vm: Vm<Instr>,
+    breakpoints: BTreeSet<u16>,
+    paused: Option<u16>,
+}
+
+impl Session {
+    fn new() -> Self {
+        let mut session =
+            Session { code: vec![], vm: Vm::new(), breakpoints: BTreeSet::new(), paused: None };
+        session.reset();
+        session
+    }
+
+    fn reset(&mut self) {
+        *self.vm.registers = CoreRegs::new();
+        self.vm.registers.set_limits(RunLimits::unlimited().with_max_steps(MAX_STEPS));
+        self.paused = None;
+    }
+
+    /// Assembles the session code, terminating it with `ret` instruction, such that the code
+    /// doesn't fall through to the end of the code segment.
+    fn lib(&self) -> Result<Lib<'static>, String> { assemble(self.code.iter().cloned()) }
+
+    fn command(&mut self, cmd: &str, arg: Option<&str>) -> Result<String, String> {
+        match (cmd, arg) {
+            ("help", None) => Ok(HELP.to_owned()),
+            ("regs", None) => Ok(format!("{:?}", self.vm.registers)),
+            ("list", None) => {
+                self.lib()?.disassemble_listing::<Instr>().map_err(|err| err.to_string())
+            }
+            ("run", None) => {
+                self.reset();
+                self.proceed(0, true)
+            }
+            ("continue", None) => match self.paused {
+                Some(pos) => self.proceed(pos, false),
+                None => Err("the code is not paused at a breakpoint".to_owned()),
+            },
+            ("break", None) => Ok(self
+                .breakpoints
+                .iter()
+                .map(|pos| format!("{:#06X}", pos))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            ("break", Some(pos)) => {
+                self.breakpoints.insert(parse_pos(pos)?);
+                Ok(String::new())
+            }
+            ("clear", Some(pos)) => match self.breakpoints.remove(&parse_pos(pos)?) {
+                true => Ok(String::new()),
+                false => Err(format!("no breakpoint at {}", pos)),
+            },
+            ("reset", None) => {
+                self.reset();
+                Ok(String::new())
+            }
+            ("save", Some(file)) => {
+                let lib = self.lib()?;
+                fs::write(file, lib.serialize()).map_err(|err| err.to_string())?;
+                Ok(format!("library {} saved to {}", lib.id(), file))
+            }
+            (cmd, _) => Err(format!("invalid command `:{}`; type `:help` for the list", cmd)),
+        }
+    }
+
+    /// Runs the session code from the offset `pos` until it completes or reaches a breakpoint.
+    /// The breakpoint at `pos` itself is not checked when the code is resumed from it.
+    fn proceed(&mut self, pos: u16, check_first: bool) -> Result<String, String> {
+        let lib = self.lib()?;
+        let site = LibSite::with(pos, lib.id());
+        let prog = Prog::<Instr>::new(lib);
+        let mut state = VmState { site, registers: mem::take(&mut self.vm.registers) };
+        let mut check = check_first;
+        self.paused = None;
+        loop {
+            if check && self.breakpoints.contains(&state.site.pos) {
+                self.vm.registers = state.registers;
+                self.paused = Some(state.site.pos);
+                return Ok(format!("paused at breakpoint {:#06X}", state.site.pos));
+            }
+            check = true;
+            match self.vm.resume(&prog, state, 1, &()) {
+                Ok(Execution::Suspended(next) | Execution::Yielded { state: next, .. }) => {
+                    state = next
+                }
+                Ok(Execution::Pending(_)) => {
+                    return Err("host function calls are not supported".to_owned())
+                }
+                Ok(Execution::Complete(st0)) => {
+                    return Ok(format!("program completed with st0={}", st0));
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+    }
+
+    fn exec(&mut self, line: &str) -> Result<String, String> {
+        let instr = Instr::from_str(line).map_err(|err| err.to_string())?;
+        let lib = assemble([instr.clone()])?;
+        let pos = self.code.iter().map(|instr| instr.byte_count() as usize).sum::<usize>();
+        let res =
+            lib.run::<Instr>(0, &mut self.vm.registers, &()).map_err(|err| err.to_string())?;
+        self.code.push(instr);
+        Ok(format!("{:#06X}: {:?}, st0={}", pos, res.outcome, res.is_success()))
+    }
+}
+
+fn assemble(code: impl IntoIterator<Item = Instr>) -> Result<Lib<'static>, String> {
+    let mut code = code.into_iter().collect::<Vec<_>>();
+    code.push(Instr::ControlFlow(ControlFlowOp::Ret));
+    Lib::assemble(&code).map_err(|err| err.to_string())
+}
+
+fn parse_pos(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => u16::from_str(s),
+    }
+    .map_err(|_| format!("invalid code offset `{}`", s))
+}
+
+fn main() {
+    let mut session = Session::new();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    println!("AluVM REPL; type `:help` for the list of commands");
+    loop {
+        print!("alu> ");
+        stdout.flush().expect("unable to write to stdout");
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).expect("unable to read from stdin") == 0 {
+            break;
+        }
+        let line = line.trim();
+        let res = match line.strip_prefix(':') {
+            None if line.is_empty() || line.starts_with(';') => continue,
+            None => session.exec(line),
+            Some("quit") => break,
+            Some(cmd) => {
+                let mut parts = cmd.splitn(2, ' ');
+                let cmd = parts.next().unwrap_or_default();
+                session.command(cmd, parts.next().map(str::trim))
+            }
+        };
+        match res {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
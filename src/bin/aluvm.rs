@@ -0,0 +1,178 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Command-line tool for working with AluVM libraries.
+//!
+//! Commands:
+//! - `aluvm diff OLD NEW` prints instruction-level and data segment differences between two binary
+//!   libraries, exiting with code 1 if they differ.
+//! - `aluvm info LIB` prints library id, metadata and segments.
+//! - `aluvm inspect LIB` prints statistics on the library code composition and data segment
+//!   utilization.
+//! - `aluvm lint LIB` prints diagnostics of the default lint rules for the library code, exiting
+//!   with code 1 if any of them is an error.
+//! - `aluvm cfg LIB` prints control flow graph of the library code in Graphviz DOT format.
+//!
+//! The library code is decoded with the core instruction set, or with the core instruction set
+//! extended with one of the ISA extensions provided by this crate, according to the ISAE segment
+//! of the libraries.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::process::exit;
+
+use aluvm::data::encoding::Decode;
+use aluvm::export::Cfg;
+#[cfg(feature = "bitcoin")]
+use aluvm::isa::BitcoinOp;
+use aluvm::isa::{
+    CtOp, DataOp, EncOp, EnvOp, FieldOp, FixedOp, FrameOp, HostOp, ImmOp, Instr, InstructionSet,
+    LoopOp, ModOp, PrngOp, RelJumpOp, StackOp, StepOp, SwitchOp, XferOp,
+};
+use aluvm::library::diff::diff;
+use aluvm::library::lint::{is_denied, Linter};
+use aluvm::library::{IsaId, IsaSeg, Lib};
+
+const USAGE: &str = "Usage: aluvm diff OLD NEW
+       aluvm info LIB
+       aluvm inspect LIB
+       aluvm lint LIB
+       aluvm cfg LIB";
+
+fn load(path: &str) -> Result<Lib<'static>, String> {
+    let data = fs::read(path).map_err(|err| format!("unable to read {}: {}", path, err))?;
+    Lib::deserialize(data).map_err(|err| format!("invalid library {}: {}", path, err))
+}
+
+fn isa_seg<Isa: InstructionSet>() -> BTreeSet<String> {
+    Isa::isa_set().iter().map(IsaId::to_string).collect()
+}
+
+/// Detects whether the instruction set implements all ISA extensions used by the libraries
+fn covers<Isa: InstructionSet>(libs: &[Lib]) -> bool {
+    let isa = isa_seg::<Isa>();
+    libs.iter().all(|lib| lib.isae.iter().all(|id| isa.contains(id)))
+}
+
+/// Performs the command with the first instruction set implementing all ISA extensions used by
+/// the libraries
+fn dispatch(cmd: &str, libs: &[Lib]) -> Result<bool, String> {
+    macro_rules! try_isa {
+        ($($isa:ty),+ $(,)?) => {
+            $( if covers::<$isa>(libs) {
+                return exec::<$isa>(cmd, libs);
+            } )+
+        };
+    }
+    try_isa!(
+        Instr,
+        Instr<EnvOp>,
+        Instr<CtOp>,
+        Instr<PrngOp>,
+        Instr<ModOp>,
+        Instr<EncOp>,
+        Instr<RelJumpOp>,
+        Instr<ImmOp>,
+        Instr<FrameOp>,
+        Instr<StackOp>,
+        Instr<FieldOp>,
+        Instr<HostOp>,
+        Instr<XferOp>,
+        Instr<StepOp>,
+        Instr<DataOp>,
+        Instr<SwitchOp>,
+        Instr<LoopOp>,
+        Instr<FixedOp>,
+    );
+    #[cfg(feature = "bitcoin")]
+    try_isa!(Instr<BitcoinOp>);
+    let isae = libs.iter().flat_map(|lib| lib.isae.iter()).collect::<BTreeSet<_>>();
+    Err(format!(
+        "no supported instruction set implements all of the ISA extensions {}",
+        isae.into_iter().cloned().collect::<Vec<_>>().join(" ")
+    ))
+}
+
+/// Performs the command on the libraries decoded with the instruction set `Isa`
+fn exec<Isa: InstructionSet>(cmd: &str, libs: &[Lib]) -> Result<bool, String> {
+    match (cmd, libs) {
+        ("diff", [old, new]) => {
+            let changes = diff::<Isa>(old, new).map_err(|err| err.to_string())?;
+            print!("{}", changes);
+            Ok(changes.is_empty())
+        }
+        ("info", [lib]) => {
+            println!("ID:     {}", lib.id());
+            println!("{}", lib);
+            // Library display includes data references only for the core instruction set
+            let core = IsaSeg::from_iter(isa_seg::<Instr>()).ok();
+            if core.as_ref() != Some(&lib.isae) {
+                match lib.data_map::<Isa>() {
+                    Ok(map) if !map.is_empty() => print!("XREF:\n{}", map),
+                    _ => {}
+                }
+            }
+            Ok(true)
+        }
+        ("inspect", [lib]) => {
+            let stats = lib.statistics::<Isa>().map_err(|err| err.to_string())?;
+            print!("{}", stats);
+            Ok(true)
+        }
+        ("lint", [lib]) => {
+            let diagnostics = Linter::new().check::<Isa>(lib).map_err(|err| err.to_string())?;
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            Ok(!is_denied(&diagnostics))
+        }
+        ("cfg", [lib]) => {
+            let dot = Cfg::to_dot::<Isa>(lib).map_err(|err| err.to_string())?;
+            print!("{}", dot);
+            Ok(true)
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+fn run(args: &[String]) -> Result<bool, String> {
+    match args {
+        [cmd, old, new] if cmd == "diff" => dispatch(cmd, &[load(old)?, load(new)?]),
+        [cmd, path] if ["info", "inspect", "lint", "cfg"].contains(&cmd.as_str()) => {
+            dispatch(cmd, &[load(path)?])
+        }
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            exit(2);
+        }
+    }
+}
@@ -22,16 +22,177 @@
 // limitations under the License.
 
 use core::cmp::Ordering;
-use core::convert::TryFrom;
-use core::ops::{Neg, Rem};
+use core::fmt::{self, Display, Formatter, Write};
+use core::ops::{BitOr, BitOrAssign, Neg, Rem};
 
-use amplify::num::apfloat::{ieee, Float};
+use amplify::num::apfloat::{ieee, ExpInt, Float, Status, StatusAnd};
+use amplify::num::{i1024, i256, u1024, u256};
 use half::bf16;
 
-use super::{FloatLayout, IntLayout, Layout, Number, NumberLayout};
+use super::{FixedLayout, FloatLayout, IntLayout, Layout, Number, NumberLayout};
 use crate::data::MaybeNumber;
 use crate::isa::{IntFlags, RoundingFlag};
 
+/// IEEE-754 exception flags raised by float arithmetic operations.
+///
+/// In order to provide bit-exact results across all platforms, float arithmetic in AluVM is
+/// performed in software and its results are canonicalized:
+/// - operations raising `invalid`, `div_by_zero`, `overflow` or `underflow` flags produce no value
+///   (`None`), i.e. `NaN` values (of any sign and payload), infinities resulting from overflow and
+///   subnormal results are never written into registers;
+/// - negative zero is always replaced with positive zero;
+/// - inexact results are rounded according to the [`RoundingFlag`] provided by the instruction,
+///   including `bfloat16` layout.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct FloatFlags {
+    /// Invalid operation, which would have produced `NaN` value (like `0/0` or `∞-∞`)
+    pub invalid: bool,
+    /// Division of a finite non-zero value by zero
+    pub div_by_zero: bool,
+    /// Result magnitude exceeds the largest finite value representable by the layout
+    pub overflow: bool,
+    /// Result is too small to be represented as a normal value of the layout
+    pub underflow: bool,
+    /// Result was rounded
+    pub inexact: bool,
+}
+
+impl FloatFlags {
+    /// Detects whether any of the flags which prevent operation from producing value is set.
+    #[inline]
+    pub fn is_exceptional(self) -> bool {
+        self.invalid || self.div_by_zero || self.overflow || self.underflow
+    }
+}
+
+/// Ordering used for comparing float numbers with [`Number::cmp_float`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+pub enum FloatOrder {
+    /// IEEE-754 comparison predicates: negative and positive zeros are equal, and `NaN` values
+    /// are unordered with any value, including themselves.
+    #[display("ieee")]
+    Ieee,
+
+    /// IEEE-754 `totalOrder` predicate: negative `NaN`s are less than negative infinity,
+    /// negative zero is less than positive zero, and positive `NaN`s are greater than positive
+    /// infinity. `NaN`s are ordered by their payload.
+    #[display("total")]
+    TotalOrder,
+}
+
+impl From<Status> for FloatFlags {
+    fn from(status: Status) -> Self {
+        FloatFlags {
+            invalid: status.contains(Status::INVALID_OP),
+            div_by_zero: status.contains(Status::DIV_BY_ZERO),
+            overflow: status.contains(Status::OVERFLOW),
+            underflow: status.contains(Status::UNDERFLOW),
+            inexact: status.contains(Status::INEXACT),
+        }
+    }
+}
+
+impl BitOr for FloatFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        FloatFlags {
+            invalid: self.invalid | rhs.invalid,
+            div_by_zero: self.div_by_zero | rhs.div_by_zero,
+            overflow: self.overflow | rhs.overflow,
+            underflow: self.underflow | rhs.underflow,
+            inexact: self.inexact | rhs.inexact,
+        }
+    }
+}
+
+impl BitOrAssign for FloatFlags {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: Self) { *self = *self | rhs }
+}
+
+impl Display for FloatFlags {
+    /// Displays set flags as a sequence of letters: `i` for invalid operation, `z` for division
+    /// by zero, `o` for overflow, `u` for underflow and `x` for inexact result; or `-` if no
+    /// flags are set.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if *self == FloatFlags::default() {
+            return f.write_str("-");
+        }
+        for (set, c) in [
+            (self.invalid, 'i'),
+            (self.div_by_zero, 'z'),
+            (self.overflow, 'o'),
+            (self.underflow, 'u'),
+            (self.inexact, 'x'),
+        ] {
+            if set {
+                f.write_char(c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Semantics of the `bfloat16` format for the software float implementation, which (unlike
+/// `half` crate relying on the native `f32` arithmetic) honors rounding flags and does not depend
+/// on the platform.
+struct BFloat16S;
+
+impl ieee::Semantics for BFloat16S {
+    const BITS: usize = 16;
+    const PRECISION: usize = 8;
+    const MAX_EXP: ExpInt = 127;
+}
+
+type BFloat16 = ieee::IeeeFloat<BFloat16S>;
+
+#[inline]
+fn bfloat16(val: Number) -> BFloat16 { BFloat16::from_bits(u256::from(bf16::from(val).to_bits())) }
+
+#[inline]
+fn float_res<T: Into<MaybeNumber>>(res: StatusAnd<T>) -> (MaybeNumber, FloatFlags) {
+    let flags = FloatFlags::from(res.status);
+    (res.into(), flags)
+}
+
+macro_rules! float_op {
+    ($op:ident, $lhs:ident, $rhs:ident, $flag:ident, $verb:literal, $noun:literal) => {{
+        let layout = $lhs.layout();
+        assert_eq!(layout, $rhs.layout(), concat!($verb, " numbers with different layout"));
+        let round = $flag.into();
+        match layout {
+            Layout::Float(FloatLayout::BFloat16) => float_res(
+                bfloat16($lhs)
+                    .$op(bfloat16($rhs), round)
+                    .map(|val| bf16::from_bits(val.to_bits().low_u32() as u16)),
+            ),
+            Layout::Float(FloatLayout::IeeeHalf) => {
+                float_res(ieee::Half::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::IeeeSingle) => {
+                float_res(ieee::Single::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::IeeeDouble) => {
+                float_res(ieee::Double::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::IeeeQuad) => {
+                float_res(ieee::Quad::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::X87DoubleExt) => {
+                float_res(ieee::X87DoubleExtended::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::IeeeOct) => {
+                float_res(ieee::Oct::from($lhs).$op($rhs.into(), round))
+            }
+            Layout::Float(FloatLayout::FloatTapered) => {
+                todo!(concat!("(#5) tapered float ", $noun))
+            }
+            Layout::Integer(_) => panic!(concat!("float ", $noun, " of integer numbers")),
+        }
+    }};
+}
+
 impl PartialEq for Number {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
@@ -54,35 +215,12 @@ impl Ord for Number {
     fn cmp(&self, other: &Self) -> Ordering {
         assert_eq!(self.layout(), other.layout(), "comparing numbers with different layout");
         match self.layout() {
-            Layout::Integer(_) => match (self.is_positive(), other.is_positive()) {
-                (true, false) => Ordering::Greater,
-                (false, true) => Ordering::Less,
+            Layout::Integer(_) => match (self.is_negative(), other.is_negative()) {
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
                 _ => self.to_u1024_bytes().cmp(&other.to_u1024_bytes()),
             },
-            Layout::Float(FloatLayout::BFloat16) => {
-                bf16::from(self).partial_cmp(&bf16::from(other)).expect("number value contains NaN")
-            }
-            Layout::Float(FloatLayout::IeeeHalf) => ieee::Half::from(self)
-                .partial_cmp(&ieee::Half::from(other))
-                .expect("number value contains NaN"),
-            Layout::Float(FloatLayout::IeeeSingle) => ieee::Single::from(self)
-                .partial_cmp(&ieee::Single::from(other))
-                .expect("number value contains NaN"),
-            Layout::Float(FloatLayout::IeeeDouble) => ieee::Double::from(self)
-                .partial_cmp(&ieee::Double::from(other))
-                .expect("number value contains NaN"),
-            Layout::Float(FloatLayout::X87DoubleExt) => ieee::X87DoubleExtended::from(self)
-                .partial_cmp(&ieee::X87DoubleExtended::from(other))
-                .expect("number value contains NaN"),
-            Layout::Float(FloatLayout::IeeeQuad) => ieee::Quad::from(self)
-                .partial_cmp(&ieee::Quad::from(other))
-                .expect("number value contains NaN"),
-            Layout::Float(FloatLayout::IeeeOct) => {
-                unimplemented!("IEEE-754 256-bit floats are not yet supported")
-            }
-            Layout::Float(FloatLayout::FloatTapered) => {
-                unimplemented!("512-bit tapered floats are not yet supported")
-            }
+            Layout::Float(_) => self.ieee_cmp(other).expect("number value contains NaN"),
         }
     }
 }
@@ -116,6 +254,113 @@ impl Number {
     pub fn rounding_eq(&self, other: &Self) -> bool { self.rounding_cmp(other) == Ordering::Equal }
 }
 
+impl Number {
+    /// Compares two integers interpreting their bits as unsigned values, regardless of the sign
+    /// of their layouts. This matches `lt.u` and `gt.u` instructions.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers have layouts of different bit dimension.
+    pub fn cmp_unsigned(&self, other: &Self) -> Ordering {
+        self.int_layouts(other);
+        self.into_unsigned().cmp(&other.into_unsigned())
+    }
+
+    /// Compares two integers interpreting their bits as two's complement signed values,
+    /// regardless of the sign of their layouts. This matches `lt.s` and `gt.s` instructions.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers have layouts of different bit dimension.
+    pub fn cmp_signed(&self, other: &Self) -> Ordering {
+        self.int_layouts(other);
+        self.into_signed().cmp(&other.into_signed())
+    }
+
+    /// Compares two float numbers using the provided [`FloatOrder`].
+    ///
+    /// With [`FloatOrder::Ieee`] returns `None` if any of the numbers is `NaN`, matching `lt.e`,
+    /// `gt.e` and `eq.e` instructions (which can never get `NaN` values from the registers). With
+    /// [`FloatOrder::TotalOrder`] always returns a value. Both orders are identical for the
+    /// values which may be stored in the registers, since negative zeros and `NaN`s are never
+    /// written there.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to integer number layouts
+    /// - if numbers have different layouts
+    /// - for tapered float layout and, with [`FloatOrder::Ieee`], for 256-bit float layout.
+    pub fn cmp_float(&self, other: &Self, order: FloatOrder) -> Option<Ordering> {
+        assert_eq!(self.layout(), other.layout(), "comparing numbers with different layout");
+        match (self.layout(), order) {
+            (Layout::Integer(_), _) => panic!("float comparison of integer numbers"),
+            (Layout::Float(FloatLayout::FloatTapered), _) => {
+                unimplemented!("512-bit tapered floats are not yet supported")
+            }
+            (Layout::Float(_), FloatOrder::Ieee) => self.ieee_cmp(other),
+            (Layout::Float(_), FloatOrder::TotalOrder) => {
+                let key = |num: &Number, idx: u16| {
+                    let sign = num[num.len() - 1] & 0x80 != 0;
+                    match (sign, idx + 1 == num.len()) {
+                        (true, _) => !num[idx],
+                        (false, true) => num[idx] | 0x80,
+                        (false, false) => num[idx],
+                    }
+                };
+                let ord = (0..self.len())
+                    .rev()
+                    .map(|idx| key(self, idx).cmp(&key(other, idx)))
+                    .find(|ord| *ord != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal);
+                Some(ord)
+            }
+        }
+    }
+
+    fn int_layouts(&self, other: &Self) {
+        match (self.layout(), other.layout()) {
+            (
+                Layout::Integer(IntLayout { bytes: bytes1, .. }),
+                Layout::Integer(IntLayout { bytes: bytes2, .. }),
+            ) => {
+                assert_eq!(bytes1, bytes2, "comparing numbers with different layout")
+            }
+            _ => panic!("integer comparison of float numbers"),
+        }
+    }
+
+    fn ieee_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.layout() {
+            Layout::Integer(_) => panic!("float comparison of integer numbers"),
+            Layout::Float(FloatLayout::BFloat16) => {
+                bf16::from(self).partial_cmp(&bf16::from(other))
+            }
+            Layout::Float(FloatLayout::IeeeHalf) => {
+                ieee::Half::from(self).partial_cmp(&ieee::Half::from(other))
+            }
+            Layout::Float(FloatLayout::IeeeSingle) => {
+                ieee::Single::from(self).partial_cmp(&ieee::Single::from(other))
+            }
+            Layout::Float(FloatLayout::IeeeDouble) => {
+                ieee::Double::from(self).partial_cmp(&ieee::Double::from(other))
+            }
+            Layout::Float(FloatLayout::X87DoubleExt) => ieee::X87DoubleExtended::from(self)
+                .partial_cmp(&ieee::X87DoubleExtended::from(other)),
+            Layout::Float(FloatLayout::IeeeQuad) => {
+                ieee::Quad::from(self).partial_cmp(&ieee::Quad::from(other))
+            }
+            Layout::Float(FloatLayout::IeeeOct) => {
+                unimplemented!("IEEE-754 256-bit floats are not yet supported")
+            }
+            Layout::Float(FloatLayout::FloatTapered) => {
+                unimplemented!("512-bit tapered floats are not yet supported")
+            }
+        }
+    }
+}
+
 impl Number {
     /// Addition of two integers with configuration flags for overflow and signed format.
     /// If `signed` flag is inconsistent with Number layout,
@@ -240,72 +485,180 @@ impl Number {
         }
     }
 
-    /// Addition of two floats with configuration flags for rounding.
+    /// Multiplication of two fixed-point numbers having `frac` fractional bits, with
+    /// configuration flags for overflow and signed format. The product is scaled back by `2^frac`
+    /// with the fractional bits beyond the precision of the layout being truncated towards zero.
+    /// The intermediary product is computed with double width, such that numbers of all layouts,
+    /// including 1024-bit ones, are multiplied without overflow unless the scaled product does not
+    /// fit the layout.
+    ///
+    /// Returns `None` if the number of fractional bits is not valid for the number layout (see
+    /// [`FixedLayout::is_valid`]).
     ///
     /// # Panics
     ///
     /// - if applied to float number layouts
     /// - if numbers in arguments has different layout.
-    pub fn float_add(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
+    pub fn fixed_mul(self, rhs: Self, frac: u16, flags: IntFlags) -> Option<Number> {
         let layout = self.layout();
-        assert_eq!(layout, rhs.layout(), "adding numbers with different layout");
-        match layout {
-            Layout::Float(FloatLayout::BFloat16) => (bf16::from(self) + bf16::from(rhs)).into(),
-            Layout::Float(FloatLayout::IeeeHalf) => {
-                ieee::Half::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeSingle) => {
-                ieee::Single::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeDouble) => {
-                ieee::Double::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeQuad) => {
-                ieee::Quad::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::X87DoubleExt) => {
-                ieee::X87DoubleExtended::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeOct) => {
-                ieee::Oct::from(self).add_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::FloatTapered) => todo!("(#5) tapered float addition"),
-            Layout::Integer(_) => panic!("float addition of integer numbers"),
+        assert_eq!(layout, rhs.layout(), "multiplying numbers with different layout");
+        let fixed = match layout {
+            Layout::Integer(int) => FixedLayout { int, frac },
+            Layout::Float(_) => panic!("fixed-point multiplication of float numbers"),
+        };
+        if !fixed.is_valid() {
+            return None;
         }
+        let (neg1, mag1) = self.fixed_magnitude(flags.signed);
+        let (neg2, mag2) = rhs.fixed_magnitude(flags.signed);
+        let (mag, overflow) = Self::fixed_mul_wide(mag1, mag2, frac);
+        Self::fixed_result(neg1 ^ neg2, mag, overflow, fixed.bytes(), flags.signed, flags.wrap)
     }
 
-    /// Subtraction of two floats with configuration flags for rounding.
+    /// Division of two fixed-point numbers having `frac` fractional bits, with configuration
+    /// flags for Euclidean division and signed format. The dividend is scaled by `2^frac` with
+    /// double width before the division, such that the quotient keeps the same number of
+    /// fractional bits.
+    ///
+    /// Like for [`Number::int_div`], `flags.wrap` selects Euclidean division of signed numbers
+    /// and has no effect on unsigned ones; a quotient not fitting the layout is never wrapped.
+    ///
+    /// Returns `None` if the divisor is zero, the quotient does not fit the number layout or the
+    /// number of fractional bits is not valid for the number layout (see
+    /// [`FixedLayout::is_valid`]).
     ///
     /// # Panics
     ///
     /// - if applied to float number layouts
     /// - if numbers in arguments has different layout.
-    pub fn float_sub(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
+    pub fn fixed_div(self, rhs: Self, frac: u16, flags: IntFlags) -> Option<Number> {
         let layout = self.layout();
-        assert_eq!(layout, rhs.layout(), "subtracting numbers with different layout");
-        match layout {
-            Layout::Float(FloatLayout::BFloat16) => (bf16::from(self) - bf16::from(rhs)).into(),
-            Layout::Float(FloatLayout::IeeeHalf) => {
-                ieee::Half::from(self).sub_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeSingle) => {
-                ieee::Single::from(self).sub_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeDouble) => {
-                ieee::Double::from(self).sub_r(rhs.into(), flag.into()).into()
+        assert_eq!(layout, rhs.layout(), "dividing numbers with different layout");
+        let fixed = match layout {
+            Layout::Integer(int) => FixedLayout { int, frac },
+            Layout::Float(_) => panic!("fixed-point division of float numbers"),
+        };
+        if !fixed.is_valid() || rhs.is_zero() {
+            return None;
+        }
+        let (neg1, mag1) = self.fixed_magnitude(flags.signed);
+        let (neg2, mag2) = rhs.fixed_magnitude(flags.signed);
+        let (mut mag, mut overflow, rem) = Self::fixed_div_wide(mag1, mag2, frac);
+        if neg1 && rem && flags.wrap {
+            // Euclidean division keeps the remainder non-negative, which for a negative dividend
+            // moves the quotient away from zero
+            let (inc, carry) = mag.overflowing_add(u1024::ONE);
+            mag = inc;
+            overflow |= carry;
+        }
+        Self::fixed_result(neg1 ^ neg2, mag, overflow, fixed.bytes(), flags.signed, false)
+    }
+
+    /// Splits a fixed-point operand into its sign and magnitude; the magnitude is exact for all
+    /// layouts, including the minimal value of `i1024`.
+    fn fixed_magnitude(self, signed: bool) -> (bool, u1024) {
+        if !signed {
+            return (false, self.to_u1024_bytes());
+        }
+        let val = self.to_i1024_bytes();
+        let neg = val.is_negative();
+        let val = if neg { val.wrapping_neg() } else { val };
+        (neg, u1024::from_inner(val.into_inner()))
+    }
+
+    /// Computes `a * b / 2^frac` using a 2048-bit intermediary product, returning the lower 1024
+    /// bits of the result and whether the result overflows them.
+    fn fixed_mul_wide(a: u1024, b: u1024, frac: u16) -> (u1024, bool) {
+        let (a, b) = (a.into_inner(), b.into_inner());
+        let mut prod = [0u64; 32];
+        for (i, x) in a.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, y) in b.iter().enumerate() {
+                let acc = *x as u128 * *y as u128 + prod[i + j] as u128 + carry;
+                prod[i + j] = acc as u64;
+                carry = acc >> 64;
             }
-            Layout::Float(FloatLayout::IeeeQuad) => {
-                ieee::Quad::from(self).sub_r(rhs.into(), flag.into()).into()
+            prod[i + 16] = carry as u64;
+        }
+        let (words, bits) = (frac as usize / 64, frac as u32 % 64);
+        let mut res = [0u64; 32];
+        for (i, word) in res.iter_mut().enumerate().take(32 - words) {
+            *word = prod[i + words] >> bits;
+            if bits > 0 && i + words + 1 < 32 {
+                *word |= prod[i + words + 1] << (64 - bits);
             }
-            Layout::Float(FloatLayout::X87DoubleExt) => {
-                ieee::X87DoubleExtended::from(self).sub_r(rhs.into(), flag.into()).into()
+        }
+        let mut low = [0u64; 16];
+        low.copy_from_slice(&res[..16]);
+        (u1024::from_inner(low), res[16..].iter().any(|word| *word != 0))
+    }
+
+    /// Computes `a * 2^frac / b` using a 2048-bit intermediary dividend, returning the lower 1024
+    /// bits of the quotient, whether the quotient overflows them and whether the division leaves
+    /// a non-zero remainder.
+    fn fixed_div_wide(a: u1024, b: u1024, frac: u16) -> (u1024, bool, bool) {
+        let frac = frac as usize;
+        let mut quot = [0u64; 32];
+        let mut rem = u1024::ZERO;
+        for pos in (0..1024 + frac).rev() {
+            let carry = rem.bit(1023);
+            rem <<= 1;
+            if pos >= frac && a.bit(pos - frac) {
+                rem |= u1024::ONE;
             }
-            Layout::Float(FloatLayout::IeeeOct) => {
-                ieee::Oct::from(self).sub_r(rhs.into(), flag.into()).into()
+            if carry || rem >= b {
+                rem = rem.wrapping_sub(b);
+                quot[pos / 64] |= 1 << (pos % 64);
             }
-            Layout::Float(FloatLayout::FloatTapered) => todo!("(#5) tapered float subtraction"),
-            Layout::Integer(_) => panic!("float subtraction of integer numbers"),
         }
+        let mut low = [0u64; 16];
+        low.copy_from_slice(&quot[..16]);
+        (u1024::from_inner(low), quot[16..].iter().any(|word| *word != 0), !rem.is_zero())
+    }
+
+    /// Composes the result of fixed-point arithmetics from its sign and magnitude, reshaped to
+    /// the layout of `bytes` length. Returns `None` if the result overflows the layout, unless
+    /// `wrap` is set.
+    fn fixed_result(
+        neg: bool,
+        mag: u1024,
+        mut overflow: bool,
+        bytes: u16,
+        signed: bool,
+        wrap: bool,
+    ) -> Option<Number> {
+        if !signed {
+            let mut n = Number::from(mag);
+            return ((n.reshape(Layout::unsigned(bytes)) && !overflow) || wrap).then(|| n);
+        }
+        let limit = u1024::ONE << 1023;
+        overflow |= if neg { mag > limit } else { mag >= limit };
+        let val = i1024::from_inner(mag.into_inner());
+        let val = if neg { val.wrapping_neg() } else { val };
+        let mut n = Number::from(val);
+        ((n.reshape(Layout::signed(bytes)) && !overflow) || wrap).then(|| n)
+    }
+
+    /// Addition of two floats with configuration flags for rounding.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    #[inline]
+    pub fn float_add(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
+        self.float_add_flags(rhs, flag).0
+    }
+
+    /// Subtraction of two floats with configuration flags for rounding.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    #[inline]
+    pub fn float_sub(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
+        self.float_sub_flags(rhs, flag).0
     }
 
     /// Multiplication of two floats with configuration flags for rounding.
@@ -314,32 +667,9 @@ impl Number {
     ///
     /// - if applied to float number layouts
     /// - if numbers in arguments has different layout.
+    #[inline]
     pub fn float_mul(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
-        let layout = self.layout();
-        assert_eq!(layout, rhs.layout(), "multiplying numbers with different layout");
-        match layout {
-            Layout::Float(FloatLayout::BFloat16) => (bf16::from(self) * bf16::from(rhs)).into(),
-            Layout::Float(FloatLayout::IeeeHalf) => {
-                ieee::Half::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeSingle) => {
-                ieee::Single::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeDouble) => {
-                ieee::Double::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeQuad) => {
-                ieee::Quad::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::X87DoubleExt) => {
-                ieee::X87DoubleExtended::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeOct) => {
-                ieee::Oct::from(self).mul_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::FloatTapered) => todo!("(#5) tapered float multiplication"),
-            Layout::Integer(_) => panic!("float multiplication of integer numbers"),
-        }
+        self.float_mul_flags(rhs, flag).0
     }
 
     /// Division of two floats with configuration flags for rounding.
@@ -348,32 +678,93 @@ impl Number {
     ///
     /// - if applied to float number layouts
     /// - if numbers in arguments has different layout.
+    #[inline]
     pub fn float_div(self, rhs: Self, flag: RoundingFlag) -> MaybeNumber {
-        let layout = self.layout();
-        assert_eq!(layout, rhs.layout(), "dividing numbers with different layout");
-        match layout {
-            Layout::Float(FloatLayout::BFloat16) => (bf16::from(self) / bf16::from(rhs)).into(),
-            Layout::Float(FloatLayout::IeeeHalf) => {
-                ieee::Half::from(self).div_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeSingle) => {
-                ieee::Single::from(self).div_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeDouble) => {
-                ieee::Double::from(self).div_r(rhs.into(), flag.into()).into()
-            }
-            Layout::Float(FloatLayout::IeeeQuad) => {
-                ieee::Quad::from(self).div_r(rhs.into(), flag.into()).into()
-            }
+        self.float_div_flags(rhs, flag).0
+    }
+
+    /// Addition of two floats with configuration flags for rounding, returning IEEE-754
+    /// exception flags raised by the operation together with the result.
+    ///
+    /// See [`FloatFlags`] for the description of the result canonicalization rules.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    pub fn float_add_flags(self, rhs: Self, flag: RoundingFlag) -> (MaybeNumber, FloatFlags) {
+        float_op!(add_r, self, rhs, flag, "adding", "addition")
+    }
+
+    /// Addition of a small integer `step` to a float with configuration flags for rounding,
+    /// returning IEEE-754 exception flags raised by the operation together with the result.
+    ///
+    /// The step is exactly representable in all float layouts, thus the result is the same as of
+    /// [`Number::float_add_flags`] with the step converted into the float layout. Tapered floats
+    /// are not supported yet, and produce no value.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to integer number layouts
+    pub fn float_step_flags(self, step: i8, flag: RoundingFlag) -> (MaybeNumber, FloatFlags) {
+        let int = i256::from(step);
+        let rhs = match self.layout() {
+            Layout::Float(FloatLayout::BFloat16) => MaybeNumber::from(bf16::from_f32(step.into())),
+            Layout::Float(FloatLayout::IeeeHalf) => ieee::Half::from_i256(int).value.into(),
+            Layout::Float(FloatLayout::IeeeSingle) => ieee::Single::from_i256(int).value.into(),
+            Layout::Float(FloatLayout::IeeeDouble) => ieee::Double::from_i256(int).value.into(),
+            Layout::Float(FloatLayout::IeeeQuad) => ieee::Quad::from_i256(int).value.into(),
             Layout::Float(FloatLayout::X87DoubleExt) => {
-                ieee::X87DoubleExtended::from(self).div_r(rhs.into(), flag.into()).into()
+                ieee::X87DoubleExtended::from_i256(int).value.into()
             }
-            Layout::Float(FloatLayout::IeeeOct) => {
-                ieee::Oct::from(self).div_r(rhs.into(), flag.into()).into()
+            Layout::Float(FloatLayout::IeeeOct) => ieee::Oct::from_i256(int).value.into(),
+            // Tapered float arithmetic is not implemented (#5), so the step produces no value
+            Layout::Float(FloatLayout::FloatTapered) => {
+                return (MaybeNumber::none(), FloatFlags::default())
             }
-            Layout::Float(FloatLayout::FloatTapered) => todo!("(#5) tapered float division"),
-            Layout::Integer(_) => panic!("float division of integer numbers"),
-        }
+            Layout::Integer(_) => panic!("float addition of integer numbers"),
+        };
+        let rhs = rhs.expect("integer step is representable in all IEEE float layouts");
+        self.float_add_flags(rhs, flag)
+    }
+
+    /// Subtraction of two floats with configuration flags for rounding, returning IEEE-754
+    /// exception flags raised by the operation together with the result.
+    ///
+    /// See [`FloatFlags`] for the description of the result canonicalization rules.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    pub fn float_sub_flags(self, rhs: Self, flag: RoundingFlag) -> (MaybeNumber, FloatFlags) {
+        float_op!(sub_r, self, rhs, flag, "subtracting", "subtraction")
+    }
+
+    /// Multiplication of two floats with configuration flags for rounding, returning IEEE-754
+    /// exception flags raised by the operation together with the result.
+    ///
+    /// See [`FloatFlags`] for the description of the result canonicalization rules.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    pub fn float_mul_flags(self, rhs: Self, flag: RoundingFlag) -> (MaybeNumber, FloatFlags) {
+        float_op!(mul_r, self, rhs, flag, "multiplying", "multiplication")
+    }
+
+    /// Division of two floats with configuration flags for rounding, returning IEEE-754
+    /// exception flags raised by the operation together with the result.
+    ///
+    /// See [`FloatFlags`] for the description of the result canonicalization rules.
+    ///
+    /// # Panics
+    ///
+    /// - if applied to float number layouts
+    /// - if numbers in arguments has different layout.
+    pub fn float_div_flags(self, rhs: Self, flag: RoundingFlag) -> (MaybeNumber, FloatFlags) {
+        float_op!(div_r, self, rhs, flag, "dividing", "division")
     }
 
     /// Adds or removes negative sign to the number (negates negative or positive number, depending
@@ -436,12 +827,14 @@ impl Rem for Number {
                 val1.rem(val2).into()
             }
             Layout::Integer(IntLayout { signed: false, .. }) if layout.bits() <= 128 => {
-                let val1 = i128::try_from(self).expect("integer layout is broken");
-                let val2 = i128::try_from(rhs).expect("integer layout is broken");
+                let val1 = i128::from(self);
+                let val2 = i128::from(rhs);
                 val1.rem(val2).into()
             }
             Layout::Integer(IntLayout { .. }) => {
-                todo!("(#11) implement large signed number modulo division algorithm")
+                // The remainder is always less than the divisor, thus fits its layout
+                let res = Number::from(self.to_u1024_bytes().rem(rhs.to_u1024_bytes()));
+                res.reshaped(rhs.layout(), false).expect("remainder exceeds divisor")
             }
             Layout::Float(_) => panic!("modulo division of float number"),
         })
@@ -465,6 +858,88 @@ impl Number {
     }
 }
 
+impl Number {
+    /// Adds a small signed `step` to the integer, treating its bits as a signed (two's complement)
+    /// or unsigned value according to `flags` regardless of the number layout.
+    ///
+    /// Returns the result together with the overflow flag. In case of overflow the result is
+    /// wrapped around the bit dimension of the layout if [`IntFlags::wrap`] is set, and is `None`
+    /// otherwise.
+    ///
+    /// # Panics
+    ///
+    /// If applied to float number layouts.
+    pub fn int_step(self, step: i8, flags: IntFlags) -> (Option<Number>, bool) {
+        let (val, sign) = int_bits(self, "integer increment of float numbers");
+        let delta = u1024::from(step.unsigned_abs());
+        let res = if step < 0 { val.wrapping_sub(delta) } else { val.wrapping_add(delta) };
+        let res = truncated(res, self.layout());
+        let overflow = match flags.signed {
+            true => val.bit(sign) == (step < 0) && res.bit(sign) != val.bit(sign),
+            false if step < 0 => val < delta,
+            false => res < val,
+        };
+        int_res(res, self.layout(), overflow, flags)
+    }
+
+    /// Negates the integer, treating its bits as a signed (two's complement) or unsigned value
+    /// according to `flags` regardless of the number layout. Unsigned negation overflows for all
+    /// values except zero.
+    ///
+    /// Returns the result together with the overflow flag, like [`Number::int_step`].
+    ///
+    /// # Panics
+    ///
+    /// If applied to float number layouts.
+    pub fn int_neg(self, flags: IntFlags) -> (Option<Number>, bool) {
+        let (val, _) = int_bits(self, "integer negation of float numbers");
+        let res = truncated(u1024::ZERO.wrapping_sub(val), self.layout());
+        // The minimal signed value is the only non-zero value which is equal to its negation
+        let overflow = !val.is_zero() && (!flags.signed || res == val);
+        int_res(res, self.layout(), overflow, flags)
+    }
+
+    /// Computes absolute value of the integer, treating its bits as a signed (two's complement)
+    /// or unsigned value according to `flags` regardless of the number layout. Only the minimal
+    /// signed value overflows; unsigned values are returned unchanged.
+    ///
+    /// Returns the result together with the overflow flag, like [`Number::int_step`].
+    ///
+    /// # Panics
+    ///
+    /// If applied to float number layouts.
+    pub fn int_abs(self, flags: IntFlags) -> (Option<Number>, bool) {
+        let (val, sign) = int_bits(self, "integer absolute value of float numbers");
+        if flags.signed && val.bit(sign) {
+            self.int_neg(flags)
+        } else {
+            (Some(self), false)
+        }
+    }
+}
+
+/// Returns bits of the integer as an unsigned value together with the index of its sign bit.
+fn int_bits(n: Number, msg: &'static str) -> (u1024, usize) {
+    assert!(n.layout().is_integer(), "{}", msg);
+    (n.into_unsigned().to_u1024_bytes(), (n.layout().bytes() as usize * 8).saturating_sub(1))
+}
+
+/// Truncates the value to the bit dimension of the layout.
+fn truncated(val: u1024, layout: Layout) -> u1024 {
+    let bytes = val.to_le_bytes();
+    let mut res = [0u8; 128];
+    res[..layout.bytes() as usize].copy_from_slice(&bytes[..layout.bytes() as usize]);
+    u1024::from_le_bytes(res)
+}
+
+/// Constructs the result of integer operation, which is `None` on overflow unless wrapping is
+/// requested.
+fn int_res(val: u1024, layout: Layout, overflow: bool, flags: IntFlags) -> (Option<Number>, bool) {
+    let res =
+        Number::with(&val.to_le_bytes()[..layout.bytes() as usize], layout).expect("layout length");
+    (if overflow && !flags.wrap { None } else { Some(res) }, overflow)
+}
+
 #[cfg(test)]
 mod tests {
     use core::str::FromStr;
@@ -487,6 +962,50 @@ mod tests {
         assert!(x < y);
     }
 
+    #[test]
+    fn compare_layouts() {
+        let zero = Number::from(0i8);
+        let minus_one = Number::from(-1i8);
+        assert_eq!(zero.cmp(&minus_one), Ordering::Greater);
+        assert_eq!(zero.cmp_signed(&minus_one), Ordering::Greater);
+        assert_eq!(zero.cmp_unsigned(&minus_one), Ordering::Less);
+        assert_eq!(Number::from(0xFFu8).cmp_signed(&Number::from(1u8)), Ordering::Less);
+        assert_eq!(Number::from(0xFFu8).cmp_unsigned(&Number::from(1u8)), Ordering::Greater);
+        assert_eq!(Number::from(-128i8).cmp_signed(&Number::from(127u8)), Ordering::Less);
+
+        let float = |val: f32| Number::with(val.to_le_bytes(), FloatLayout::IeeeSingle).unwrap();
+        let (one, two) = (float(1.0), float(2.0));
+        assert_eq!(one.cmp_float(&two, FloatOrder::Ieee), Some(Ordering::Less));
+        assert_eq!(one.cmp_float(&two, FloatOrder::TotalOrder), Some(Ordering::Less));
+        let (minus_one, minus_two) = (float(-1.0), float(-2.0));
+        assert_eq!(minus_one.cmp_float(&minus_two, FloatOrder::Ieee), Some(Ordering::Greater));
+        assert_eq!(
+            minus_one.cmp_float(&minus_two, FloatOrder::TotalOrder),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(minus_one.cmp_float(&one, FloatOrder::TotalOrder), Some(Ordering::Less));
+
+        let (zero, neg_zero) = (float(0.0), float(-0.0));
+        assert_eq!(neg_zero.cmp_float(&zero, FloatOrder::Ieee), Some(Ordering::Equal));
+        assert_eq!(neg_zero.cmp_float(&zero, FloatOrder::TotalOrder), Some(Ordering::Less));
+
+        let (nan, neg_nan, inf) = (float(f32::NAN), float(-f32::NAN), float(f32::INFINITY));
+        assert_eq!(nan.cmp_float(&nan, FloatOrder::Ieee), None);
+        assert_eq!(nan.cmp_float(&inf, FloatOrder::TotalOrder), Some(Ordering::Greater));
+        assert_eq!(
+            neg_nan.cmp_float(&float(f32::NEG_INFINITY), FloatOrder::TotalOrder),
+            Some(Ordering::Less)
+        );
+        assert_eq!(nan.cmp_float(&nan, FloatOrder::TotalOrder), Some(Ordering::Equal));
+    }
+
+    #[test]
+    #[should_panic(expected = "integer comparison of float numbers")]
+    fn compare_float_as_int() {
+        let one = Number::with(1.0f32.to_le_bytes(), FloatLayout::IeeeSingle).unwrap();
+        one.cmp_signed(&one);
+    }
+
     #[test]
     fn int_add() {
         let x = Number::from(1);
@@ -592,6 +1111,106 @@ mod tests {
         assert_eq!(x.int_div(y, IntFlags { signed: false, wrap: true }), Some(z));
     }
 
+    #[test]
+    fn fixed_mul() {
+        let x = Number::from(0x18000u32);
+        let y = Number::from(0x24000u32);
+        let z = Number::from(0x36000u32);
+        assert_eq!(x.fixed_mul(y, 16, IntFlags { signed: false, wrap: false }), Some(z));
+        let x = Number::from(-0x18000i32);
+        let z = Number::from(-0x36000i32);
+        assert_eq!(
+            x.fixed_mul(y.into_signed(), 16, IntFlags { signed: true, wrap: false }),
+            Some(z)
+        );
+        let y = Number::from(1i32);
+        let z = Number::from(-1i32);
+        assert_eq!(x.fixed_mul(y, 16, IntFlags { signed: true, wrap: false }), Some(z));
+        let x = Number::from(200u16 << 8);
+        let y = Number::from(2u16 << 8);
+        let z = Number::from(36864u16);
+        assert_eq!(x.fixed_mul(y, 8, IntFlags { signed: false, wrap: false }), None);
+        assert_eq!(x.fixed_mul(y, 8, IntFlags { signed: false, wrap: true }), Some(z));
+        assert_eq!(x.fixed_mul(y, 16, IntFlags { signed: false, wrap: true }), None);
+    }
+
+    #[test]
+    fn fixed_mul_wide() {
+        let x = Number::from(u1024::ONE << 1020);
+        let y = Number::from(u1024::ONE << 1001);
+        let z = Number::from(u1024::ONE << 1021);
+        assert_eq!(x.fixed_mul(y, 1000, IntFlags { signed: false, wrap: false }), Some(z));
+        let x = Number::from(u1024::MAX);
+        let z = Number::from(u1024::MAX - u1024::ONE);
+        assert_eq!(x.fixed_mul(y, 1000, IntFlags { signed: false, wrap: false }), None);
+        assert_eq!(x.fixed_mul(y, 1000, IntFlags { signed: false, wrap: true }), Some(z));
+        let x = Number::from(i1024::from(3u8) << 1000);
+        let y = Number::from(-(i1024::ONE << 999));
+        let z = Number::from(-(i1024::from(3u8) << 999));
+        assert_eq!(x.fixed_mul(y, 1000, IntFlags { signed: true, wrap: false }), Some(z));
+    }
+
+    #[test]
+    fn fixed_div() {
+        let x = Number::from(0x36000u32);
+        let y = Number::from(0x18000u32);
+        let z = Number::from(0x24000u32);
+        assert_eq!(x.fixed_div(y, 16, IntFlags { signed: false, wrap: false }), Some(z));
+        let x = Number::from(0x10000u32);
+        let y = Number::from(0x30000u32);
+        let z = Number::from(0x5555u32);
+        assert_eq!(x.fixed_div(y, 16, IntFlags { signed: false, wrap: false }), Some(z));
+        let y = Number::from(0u32);
+        assert_eq!(x.fixed_div(y, 16, IntFlags { signed: false, wrap: false }), None);
+        assert_eq!(x.fixed_div(y, 16, IntFlags { signed: true, wrap: true }), None);
+        let x = Number::from(-0x70i16);
+        let y = Number::from(0x40i16);
+        let z = Number::from(-0x1Ci16);
+        assert_eq!(x.fixed_div(y, 4, IntFlags { signed: true, wrap: false }), Some(z));
+        let x = Number::from(-7i8);
+        let y = Number::from(4i8);
+        let z = Number::from(-1i8);
+        let w = Number::from(-2i8);
+        assert_eq!(x.fixed_div(y, 0, IntFlags { signed: true, wrap: false }), Some(z));
+        assert_eq!(x.fixed_div(y, 0, IntFlags { signed: true, wrap: true }), Some(w));
+        let x = Number::from(200u16 << 8);
+        let y = Number::from(1u16 << 7);
+        assert_eq!(x.fixed_div(y, 8, IntFlags { signed: false, wrap: false }), None);
+    }
+
+    #[test]
+    fn fixed_div_wide() {
+        let x = Number::from(u1024::ONE << 1021);
+        let y = Number::from(u1024::ONE << 1001);
+        let z = Number::from(u1024::ONE << 1020);
+        assert_eq!(x.fixed_div(y, 1000, IntFlags { signed: false, wrap: false }), Some(z));
+        let y = Number::from(u1024::ONE << 997);
+        assert_eq!(x.fixed_div(y, 1000, IntFlags { signed: false, wrap: true }), None);
+        let x = Number::from(-(i1024::from(3u8) << 999));
+        let y = Number::from(i1024::ONE << 1001);
+        let z = Number::from(-(i1024::from(3u8) << 998));
+        assert_eq!(x.fixed_div(y, 1000, IntFlags { signed: true, wrap: false }), Some(z));
+        let y = Number::from(i1024::from(3u8) << 1000);
+        let z = Number::from(-(i1024::ONE << 999));
+        assert_eq!(x.fixed_div(y, 1000, IntFlags { signed: true, wrap: true }), Some(z));
+        let x = Number::from(-i1024::ONE);
+        let z = Number::from(-i1024::ONE);
+        assert_eq!(
+            x.fixed_div(y, 1000, IntFlags { signed: true, wrap: false }),
+            Some(Number::from(i1024::ZERO))
+        );
+        assert_eq!(x.fixed_div(y, 1000, IntFlags { signed: true, wrap: true }), Some(z));
+    }
+
+    #[test]
+    fn int_rem_wide() {
+        let x = Number::from(u256::MAX);
+        let y = Number::from(u256::from(1_000_000u32));
+        let z = Number::from(u256::from(639_935u32));
+        assert_eq!(x % y, Some(z));
+        assert_eq!(x % Number::from(u256::ZERO), None);
+    }
+
     #[test]
     fn applying_sign() {
         let x = Number::from(1i8);
@@ -730,10 +1349,11 @@ mod tests {
         let y = MaybeNumber::from(bf16::ZERO);
         assert_eq!(x.float_add((-x).unwrap(), RoundingFlag::Ceil), y);
 
-        // will not overflow
+        // will not overflow when rounding to the nearest, but will when rounding up
         let x = MaybeNumber::from(bf16::MAX).unwrap();
         let y = MaybeNumber::from(bf16::from_f32(1.)).unwrap();
-        assert_eq!(x.float_add(y, RoundingFlag::Ceil), MaybeNumber::from(x));
+        assert_eq!(x.float_add(y, RoundingFlag::TowardsNearest), MaybeNumber::from(x));
+        assert_eq!(x.float_add(y, RoundingFlag::Ceil), MaybeNumber::none());
     }
 
     #[test]
@@ -760,7 +1380,103 @@ mod tests {
         assert_eq!(x.float_div(y, RoundingFlag::Ceil), z);
         let x = MaybeNumber::from(bf16::from_f32(6.)).unwrap();
         let y = MaybeNumber::from(bf16::ZERO).unwrap();
-        let z = MaybeNumber::from(bf16::INFINITY);
-        assert_eq!(x.float_div(y, RoundingFlag::Ceil), z);
+        assert_eq!(x.float_div(y, RoundingFlag::Ceil), MaybeNumber::none());
+    }
+
+    #[test]
+    fn float_rounding_vectors() {
+        let one = MaybeNumber::from(ieee::Single::from_bits(u256::from(0x3F80_0000u32))).unwrap();
+        let three = MaybeNumber::from(ieee::Single::from_bits(u256::from(0x4040_0000u32))).unwrap();
+        for (flag, bits) in [
+            (RoundingFlag::TowardsNearest, 0x3EAA_AAABu32),
+            (RoundingFlag::TowardsZero, 0x3EAA_AAAA),
+            (RoundingFlag::Ceil, 0x3EAA_AAAB),
+            (RoundingFlag::Floor, 0x3EAA_AAAA),
+        ] {
+            let (res, flags) = one.float_div_flags(three, flag);
+            assert_eq!(res, MaybeNumber::from(ieee::Single::from_bits(u256::from(bits))));
+            assert_eq!(flags, FloatFlags { inexact: true, ..default!() });
+        }
+        let (res, _) = (-one).unwrap().float_div_flags(three, RoundingFlag::Floor);
+        assert_eq!(res, MaybeNumber::from(ieee::Single::from_bits(u256::from(0xBEAA_AAABu32))));
+
+        let x = MaybeNumber::from(ieee::Double::from_bits(u256::from(0x3FB9_9999_9999_999Au64)))
+            .unwrap();
+        let y = MaybeNumber::from(ieee::Double::from_bits(u256::from(0x3FC9_9999_9999_999Au64)))
+            .unwrap();
+        let z = MaybeNumber::from(ieee::Double::from_bits(u256::from(0x3FD3_3333_3333_3334u64)));
+        assert_eq!(x.float_add(y, RoundingFlag::TowardsNearest), z);
+
+        // bfloat16 is computed in software and honors rounding: 1 + 2^-8 is a tie
+        let x = MaybeNumber::from(bf16::from_bits(0x3F80)).unwrap();
+        let y = MaybeNumber::from(bf16::from_bits(0x3B80)).unwrap();
+        let (res, flags) = x.float_add_flags(y, RoundingFlag::TowardsNearest);
+        assert_eq!(res, MaybeNumber::from(bf16::from_bits(0x3F80)));
+        assert!(flags.inexact);
+        let (res, _) = x.float_add_flags(y, RoundingFlag::Ceil);
+        assert_eq!(res, MaybeNumber::from(bf16::from_bits(0x3F81)));
+    }
+
+    #[test]
+    fn float_signed_zero() {
+        let zero = MaybeNumber::from(ieee::Double::ZERO);
+        let neg_zero =
+            Number::with(0x8000_0000_0000_0000u64.to_le_bytes(), FloatLayout::IeeeDouble).unwrap();
+        assert_eq!(neg_zero[7], 0x80);
+        assert_eq!(neg_zero.canonicalized(), *zero);
+
+        assert_eq!(neg_zero.float_add(neg_zero, RoundingFlag::TowardsNearest), zero);
+        assert_eq!(neg_zero.float_mul(zero.unwrap(), RoundingFlag::Ceil), zero);
+        // IEEE-754 produces -0 for exact zero differences when rounding towards -∞
+        let one = MaybeNumber::from(ieee::Double::from_bits(u256::from(0x3FF0_0000_0000_0000u64)))
+            .unwrap();
+        assert_eq!(one.float_sub(one, RoundingFlag::Floor), zero);
+
+        let neg_zero = Number::with(0x8000u16.to_le_bytes(), FloatLayout::BFloat16).unwrap();
+        assert_eq!(neg_zero.canonicalized(), *MaybeNumber::from(bf16::ZERO));
+        assert_eq!(
+            neg_zero.float_add(neg_zero, RoundingFlag::Floor),
+            MaybeNumber::from(bf16::ZERO)
+        );
+    }
+
+    #[test]
+    fn float_nan_and_flags() {
+        let zero = MaybeNumber::from(ieee::Double::ZERO).unwrap();
+        let one = MaybeNumber::from(ieee::Double::from_bits(u256::from(0x3FF0_0000_0000_0000u64)))
+            .unwrap();
+        let nan =
+            Number::with(0x7FF8_0000_0000_0001u64.to_le_bytes(), FloatLayout::IeeeDouble).unwrap();
+        assert_eq!(nan.canonicalized(), None);
+        assert_eq!(nan.float_add(one, RoundingFlag::TowardsNearest), MaybeNumber::none());
+
+        let (res, flags) = zero.float_div_flags(zero, RoundingFlag::TowardsNearest);
+        assert_eq!(res, MaybeNumber::none());
+        assert_eq!(flags, FloatFlags { invalid: true, ..default!() });
+
+        let (res, flags) = one.float_div_flags(zero, RoundingFlag::TowardsNearest);
+        assert_eq!(res, MaybeNumber::none());
+        assert_eq!(flags, FloatFlags { div_by_zero: true, ..default!() });
+        assert_eq!(flags.to_string(), "z");
+
+        let max = MaybeNumber::from(ieee::Double::largest()).unwrap();
+        let (res, flags) = max.float_add_flags(max, RoundingFlag::TowardsNearest);
+        assert_eq!(res, MaybeNumber::none());
+        assert!(flags.overflow && flags.inexact && flags.is_exceptional());
+        assert_eq!(flags.to_string(), "ox");
+
+        let min = MaybeNumber::from(ieee::Double::smallest_normalized()).unwrap();
+        let three =
+            MaybeNumber::from(ieee::Double::from_bits(u256::from(0x4008_0000_0000_0000u64)))
+                .unwrap();
+        let (res, flags) = min.float_div_flags(three, RoundingFlag::TowardsNearest);
+        assert_eq!(res, MaybeNumber::none());
+        assert!(flags.underflow);
+
+        let mut acc = FloatFlags::default();
+        assert_eq!(acc.to_string(), "-");
+        acc |= FloatFlags { invalid: true, ..default!() };
+        acc |= FloatFlags { inexact: true, ..default!() };
+        assert_eq!(acc.to_string(), "ix");
     }
 }
@@ -0,0 +1,318 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Textual encodings of binary data: base16, base32, base58, base64 and bech32m.
+//!
+//! Decoders accept only canonical encodings, such that each binary value has a single textual
+//! representation (except the letter case of base16), and return `None` for any malformed input.
+
+use alloc::vec::Vec;
+
+const BASE16_CHARS: &[u8; 16] = b"0123456789abcdef";
+const BASE32_CHARS: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const BASE58_CHARS: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BECH32_CHARS: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Constant used by bech32m checksum (BIP-350)
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+/// Regroups sequence of `from`-bit values into `to`-bit values, most significant bits first.
+///
+/// With `pad`, the last incomplete group is padded with zero bits; otherwise, the incomplete group
+/// must be shorter than `from` bits and contain only zero bits, which guarantees that the input
+/// is a canonical encoding of the output.
+fn regroup(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let max = (1u32 << to) - 1;
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mut res = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for val in data {
+        if (*val as u32) >> from != 0 {
+            return None;
+        }
+        acc = ((acc << from) | *val as u32) & 0xFFFF;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            res.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            res.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max != 0 {
+        return None;
+    }
+    Some(res)
+}
+
+fn to_chars(vals: Vec<u8>, alphabet: &[u8]) -> Vec<u8> {
+    vals.into_iter().map(|val| alphabet[val as usize]).collect()
+}
+
+fn from_chars(text: &[u8], alphabet: &[u8]) -> Option<Vec<u8>> {
+    text.iter().map(|c| alphabet.iter().position(|a| a == c).map(|pos| pos as u8)).collect()
+}
+
+/// Encodes data into RFC 4648 alphabet with `=` padding to a multiple of `group` characters.
+fn encode_padded(data: &[u8], bits: u32, group: usize, alphabet: &[u8]) -> Vec<u8> {
+    let mut text = to_chars(regroup(data, 8, bits, true).expect("bytes fit 8 bits"), alphabet);
+    text.resize((text.len() + group - 1) / group * group, b'=');
+    text
+}
+
+fn decode_padded(text: &[u8], bits: u32, group: usize, alphabet: &[u8]) -> Option<Vec<u8>> {
+    if text.len() % group != 0 {
+        return None;
+    }
+    let len = text.iter().rposition(|c| *c != b'=').map(|pos| pos + 1).unwrap_or_default();
+    if text.len() - len >= group || (group - len % group) % group != text.len() - len {
+        return None;
+    }
+    regroup(&from_chars(&text[..len], alphabet)?, bits, 8, false)
+}
+
+/// Encodes data as lowercase hexadecimal string.
+pub(crate) fn base16_encode(data: &[u8]) -> Vec<u8> {
+    to_chars(regroup(data, 8, 4, true).expect("bytes fit 8 bits"), BASE16_CHARS)
+}
+
+/// Decodes hexadecimal string, in either letter case.
+pub(crate) fn base16_decode(text: &[u8]) -> Option<Vec<u8>> {
+    regroup(&from_chars(&text.to_ascii_lowercase(), BASE16_CHARS)?, 4, 8, false)
+}
+
+/// Encodes data with RFC 4648 base32 alphabet and padding.
+pub(crate) fn base32_encode(data: &[u8]) -> Vec<u8> { encode_padded(data, 5, 8, BASE32_CHARS) }
+
+/// Decodes RFC 4648 base32 string with padding.
+pub(crate) fn base32_decode(text: &[u8]) -> Option<Vec<u8>> {
+    decode_padded(text, 5, 8, BASE32_CHARS)
+}
+
+/// Encodes data with RFC 4648 base64 alphabet and padding.
+pub(crate) fn base64_encode(data: &[u8]) -> Vec<u8> { encode_padded(data, 6, 4, BASE64_CHARS) }
+
+/// Decodes RFC 4648 base64 string with padding.
+pub(crate) fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+    decode_padded(text, 6, 4, BASE64_CHARS)
+}
+
+/// Encodes data with base58 bitcoin alphabet, where each leading zero byte is represented by `1`.
+pub(crate) fn base58_encode(data: &[u8]) -> Vec<u8> {
+    let zeros = data.iter().take_while(|byte| **byte == 0).count();
+    // Little-endian base58 digits of the value
+    let mut digits = Vec::<u8>::with_capacity(data.len() * 138 / 100 + 1);
+    for byte in &data[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut text = vec![BASE58_CHARS[0]; zeros];
+    text.extend(digits.iter().rev().map(|digit| BASE58_CHARS[*digit as usize]));
+    text
+}
+
+/// Decodes base58 string with bitcoin alphabet.
+pub(crate) fn base58_decode(text: &[u8]) -> Option<Vec<u8>> {
+    let zeros = text.iter().take_while(|c| **c == BASE58_CHARS[0]).count();
+    // Little-endian bytes of the value
+    let mut bytes = Vec::<u8>::with_capacity(text.len() * 733 / 1000 + 1);
+    for digit in from_chars(&text[zeros..], BASE58_CHARS)? {
+        let mut carry = digit as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut data = vec![0u8; zeros];
+    data.extend(bytes.iter().rev());
+    Some(data)
+}
+
+fn bech32_polymod(vals: impl IntoIterator<Item = u8>) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+    vals.into_iter().fold(1u32, |chk, val| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ val as u32;
+        GEN.iter()
+            .enumerate()
+            .filter(|(no, _)| (top >> no) & 1 == 1)
+            .fold(chk, |chk, (_, g)| chk ^ g)
+    })
+}
+
+fn bech32_hrp_expand(hrp: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    hrp.iter().map(|c| c >> 5).chain([0]).chain(hrp.iter().map(|c| c & 0x1f))
+}
+
+/// Checks that the human-readable part is non-empty and consists of lowercase printable ASCII
+/// characters.
+fn is_valid_hrp(hrp: &[u8]) -> bool {
+    !hrp.is_empty() && hrp.iter().all(|c| (33..=126).contains(c) && !c.is_ascii_uppercase())
+}
+
+/// Encodes data as a bech32m string (BIP-350) with the given human-readable part. Returns `None`
+/// if the human-readable part is empty or contains characters other than lowercase printable
+/// ASCII.
+pub(crate) fn bech32m_encode(hrp: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if !is_valid_hrp(hrp) {
+        return None;
+    }
+    let vals = regroup(data, 8, 5, true).expect("bytes fit 8 bits");
+    let polymod = bech32_polymod(bech32_hrp_expand(hrp).chain(vals.iter().copied()).chain([0; 6]))
+        ^ BECH32M_CONST;
+    let checksum = (0..6).map(|no| ((polymod >> (5 * (5 - no))) & 0x1f) as u8);
+
+    let mut text = hrp.to_vec();
+    text.push(b'1');
+    text.extend(to_chars(vals.into_iter().chain(checksum).collect(), BECH32_CHARS));
+    Some(text)
+}
+
+/// Decodes bech32m string (BIP-350), returning lowercase human-readable part and the data. The
+/// string must not mix lowercase and uppercase letters.
+pub(crate) fn bech32m_decode(text: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    if text.iter().any(u8::is_ascii_lowercase) && text.iter().any(u8::is_ascii_uppercase) {
+        return None;
+    }
+    let text = text.to_ascii_lowercase();
+    let sep = text.iter().rposition(|c| *c == b'1')?;
+    let (hrp, vals) = (&text[..sep], &text[sep + 1..]);
+    if !is_valid_hrp(hrp) || vals.len() < 6 {
+        return None;
+    }
+    let vals = from_chars(vals, BECH32_CHARS)?;
+    if bech32_polymod(bech32_hrp_expand(hrp).chain(vals.iter().copied())) != BECH32M_CONST {
+        return None;
+    }
+    let data = regroup(&vals[..vals.len() - 6], 5, 8, false)?;
+    Some((hrp.to_vec(), data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rfc4648() {
+        // RFC 4648, section 10
+        for (data, b16, b32, b64) in [
+            ("", "", "", ""),
+            ("f", "66", "MY======", "Zg=="),
+            ("fo", "666f", "MZXQ====", "Zm8="),
+            ("foo", "666f6f", "MZXW6===", "Zm9v"),
+            ("foob", "666f6f62", "MZXW6YQ=", "Zm9vYg=="),
+            ("fooba", "666f6f6261", "MZXW6YTB", "Zm9vYmE="),
+            ("foobar", "666f6f626172", "MZXW6YTBOI======", "Zm9vYmFy"),
+        ] {
+            let data = data.as_bytes();
+            assert_eq!(base16_encode(data), b16.as_bytes());
+            assert_eq!(base32_encode(data), b32.as_bytes());
+            assert_eq!(base64_encode(data), b64.as_bytes());
+            assert_eq!(base16_decode(b16.as_bytes()).as_deref(), Some(data));
+            assert_eq!(base16_decode(b16.to_uppercase().as_bytes()).as_deref(), Some(data));
+            assert_eq!(base32_decode(b32.as_bytes()).as_deref(), Some(data));
+            assert_eq!(base64_decode(b64.as_bytes()).as_deref(), Some(data));
+        }
+        for invalid in ["6", "6g", "666"] {
+            assert_eq!(base16_decode(invalid.as_bytes()), None, "{invalid}");
+        }
+        for invalid in ["MY=====", "MY======M", "MZ======", "M=======", "MZXQ=====", "my======"] {
+            assert_eq!(base32_decode(invalid.as_bytes()), None, "{invalid}");
+        }
+        for invalid in ["Zg=", "Zh==", "Z===", "Zg==Zg==", "Zm9v=", "Zm-v", "===="] {
+            assert_eq!(base64_decode(invalid.as_bytes()), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn base58() {
+        for (data, text) in [
+            (&b""[..], ""),
+            (&[0u8][..], "1"),
+            (&[0, 0, 0x28, 0x7f, 0xb4, 0xcd][..], "11233QC4"),
+            (b"Hello World!", "2NEpo7TZRRrLZSi2U"),
+            (&[0xff; 4][..], "7YXq9G"),
+        ] {
+            assert_eq!(base58_encode(data), text.as_bytes());
+            assert_eq!(base58_decode(text.as_bytes()).as_deref(), Some(data));
+        }
+        assert_eq!(base58_decode(b"0OIl"), None);
+    }
+
+    #[test]
+    fn bech32m() {
+        // BIP-350 test vectors
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            let (hrp, data) = bech32m_decode(valid.as_bytes()).expect(valid);
+            assert_eq!(bech32m_encode(&hrp, &data), Some(valid.to_lowercase().into_bytes()));
+        }
+        for invalid in [
+            "1xj0phk",
+            "qyrz8wqd2c9m",
+            "y1b0jsk6g",
+            "lt1igcx5c0",
+            "in1muywd",
+            "mm1crxm3i",
+            "au1s5cgom",
+            "M1VUXWEZ",
+            "16plkw9",
+            "1p2gdwpf",
+            "A1LqFN3A",
+        ] {
+            assert_eq!(bech32m_decode(invalid.as_bytes()), None, "{invalid}");
+        }
+        // Bech32 (BIP-173) checksum is not accepted
+        assert_eq!(bech32m_decode(b"a12uel5l"), None);
+
+        let data = [0x08, 0x00, 0x01, 0x02, 0x03, 0x04];
+        let text = bech32m_encode(b"bc", &data).unwrap();
+        assert_eq!(
+            bech32m_decode(&text.to_ascii_uppercase()),
+            Some((b"bc".to_vec(), data.to_vec()))
+        );
+        assert_eq!(bech32m_encode(b"BC", &data), None);
+        assert_eq!(bech32m_encode(b"", &data), None);
+    }
+}
@@ -21,12 +21,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::convert::TryFrom;
 use core::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
 
-use amplify::num::{i1024, u1024};
+use amplify::num::u1024;
 
-use super::{MaybeNumber, Number};
+use super::{Layout, MaybeNumber, Number, NumberLayout};
 
 impl Not for MaybeNumber {
     type Output = MaybeNumber;
@@ -50,97 +49,156 @@ impl Not for Number {
 impl BitAnd for Number {
     type Output = Number;
 
+    /// Computes bitwise AND of the numbers, returning the value with the layout of `self`.
     #[inline]
     fn bitand(self, rhs: Self) -> Self::Output {
-        self.to_u1024_bytes().bitand(rhs.to_u1024_bytes()).into()
+        with_layout(bit_repr(self).bitand(bit_repr(rhs)), self.layout())
     }
 }
 
 impl BitOr for Number {
     type Output = Number;
 
+    /// Computes bitwise OR of the numbers, returning the value with the layout of `self`.
     #[inline]
     fn bitor(self, rhs: Self) -> Self::Output {
-        self.to_u1024_bytes().bitor(rhs.to_u1024_bytes()).into()
+        with_layout(bit_repr(self).bitor(bit_repr(rhs)), self.layout())
     }
 }
 
 impl BitXor for Number {
     type Output = Number;
 
+    /// Computes bitwise XOR of the numbers, returning the value with the layout of `self`.
     #[inline]
     fn bitxor(self, rhs: Self) -> Self::Output {
-        self.to_u1024_bytes().bitxor(rhs.to_u1024_bytes()).into()
+        with_layout(bit_repr(self).bitxor(bit_repr(rhs)), self.layout())
     }
 }
 
 impl Shl for Number {
     type Output = Number;
 
+    /// Shifts integer left by the number of bits given by `rhs`, see [`Number::shift_left`].
+    ///
+    /// # Panics
+    ///
+    /// If the number is not an integer.
     #[inline]
     fn shl(self, rhs: Self) -> Self::Output {
-        let layout = self.layout();
-        assert!(layout.is_integer(), "bit shifting float number");
-        let rhs = u16::try_from(rhs).expect("attempt to bitshift lhs for more than 2^16 bits");
-        let mut n = match layout.is_signed_int() {
-            true => {
-                Number::from(self.to_i1024_bytes().checked_shl(rhs as u32).unwrap_or(i1024::ZERO))
-            }
-            false => {
-                Number::from(self.to_u1024_bytes().checked_shl(rhs as u32).unwrap_or(u1024::ZERO))
-            }
-        };
-        n.reshape(layout);
-        n
+        self.shift_left(shift_count(rhs)).expect("bit shifting float number")
     }
 }
 
 impl Shr for Number {
     type Output = Number;
 
+    /// Shifts integer right by the number of bits given by `rhs`, see [`Number::shift_right`].
+    ///
+    /// # Panics
+    ///
+    /// If the number is not an integer.
     #[inline]
     fn shr(self, rhs: Self) -> Self::Output {
-        let layout = self.layout();
-        assert!(layout.is_integer(), "bit shifting float number");
-        let rhs = u16::try_from(rhs).expect("attempt to bitshift right for more than 2^16 bits");
-        let mut n = match layout.is_signed_int() {
-            true => {
-                Number::from(self.to_i1024_bytes().checked_shr(rhs as u32).unwrap_or(i1024::ZERO))
-            }
-            false => {
-                Number::from(self.to_u1024_bytes().checked_shr(rhs as u32).unwrap_or(u1024::ZERO))
-            }
-        };
-        n.reshape(layout);
-        n
+        self.shift_right(shift_count(rhs)).expect("bit shifting float number")
+    }
+}
+
+/// Returns bits of the number as an unsigned integer, without extending the sign of signed values.
+fn bit_repr(n: Number) -> u1024 { n.into_unsigned().to_u1024_bytes() }
+
+/// Constructs number with the given layout from the lowest bytes of the value.
+fn with_layout(value: u1024, layout: Layout) -> Number {
+    let value = Number::from(value);
+    Number::with(&value[..layout.bytes()], layout).expect("layout length")
+}
+
+/// Converts shift count into `u32`, saturating the values which do not fit. Signed counts are
+/// treated as their unsigned bit representation.
+fn shift_count(shift: Number) -> u32 {
+    let shift = shift.into_unsigned().to_u1024_bytes();
+    if shift > u1024::from(u32::MAX) {
+        u32::MAX
+    } else {
+        shift.low_u64() as u32
     }
 }
 
+/// Reduces rotation count given as a number modulo the bit width.
+fn rotation_count(shift: Number, bits: u16) -> u32 {
+    (shift.into_unsigned().to_u1024_bytes() % u1024::from(bits)).low_u64() as u32
+}
+
 impl Number {
-    /// Cyclic bit shift left. Panics if the number is not an integer.
-    pub fn scl(self, shift: Number) -> Number {
+    /// Shifts integer left by `count` bits, filling the vacated bits with zeros. Shifting by the
+    /// bit width of the number or more produces zero.
+    ///
+    /// Returns `None` if the number is not an integer.
+    pub fn shift_left(self, count: u32) -> Option<Number> {
         let layout = self.layout();
-        let bits = self.len() * 8;
-        let lhs = self.into_unsigned();
-        assert!(layout.is_integer(), "bit shifting float number");
-        let excess = u16::try_from(shift).map(|v| v % bits).expect(
-            "shift value in `scl` operation must always be from either `a8` or `a16` registry",
-        );
-        let residue = lhs >> Number::from(bits - excess);
-        ((lhs << Number::from(excess)) | residue).reshaped(layout, true).expect("restoring layout")
+        if !layout.is_integer() {
+            return None;
+        }
+        Some(with_layout(bit_repr(self).checked_shl(count).unwrap_or(u1024::ZERO), layout))
     }
 
-    /// Cyclic bit shift right. Panics if the number is not an integer.
-    pub fn scr(self, shift: Number) -> Number {
+    /// Shifts integer right by `count` bits. Signed integers are shifted arithmetically, filling
+    /// the vacated bits with the sign bit, and unsigned - logically, filling them with zeros.
+    /// Shifting by the bit width of the number or more produces zero, or minus one for negative
+    /// signed integers.
+    ///
+    /// Returns `None` if the number is not an integer.
+    pub fn shift_right(self, count: u32) -> Option<Number> {
         let layout = self.layout();
-        let bits = self.len() * 8;
+        if !layout.is_integer() {
+            return None;
+        }
+        // Negative values are shifted as their complement, which has no sign bit set
+        let negative = layout.is_signed_int() && self.is_negative();
+        let n = if negative { !self } else { self };
+        let n = with_layout(bit_repr(n).checked_shr(count).unwrap_or(u1024::ZERO), layout);
+        Some(if negative { !n } else { n })
+    }
+
+    /// Rotates bits of the integer left by `count` bits, such that the truncated bits appear at
+    /// the end. The count is taken modulo the bit width of the number.
+    ///
+    /// Returns `None` if the number is not an integer.
+    pub fn rotate_left(self, count: u32) -> Option<Number> {
+        let bits = self.len() as u32 * 8;
+        if bits == 0 {
+            return self.layout().is_integer().then_some(self);
+        }
+        let count = count % bits;
         let lhs = self.into_unsigned();
-        assert!(layout.is_integer(), "bit shifting float number");
-        let excess = u16::try_from(shift).map(|v| v % bits).expect(
-            "shift value in `scl` operation must always be from either `a8` or `a16` registry",
-        );
-        let residue = lhs << Number::from(bits - excess);
-        ((lhs >> Number::from(excess)) | residue).reshaped(layout, true).expect("restoring layout")
+        let rotated = lhs.shift_left(count)? | lhs.shift_right(bits - count)?;
+        Some(with_layout(bit_repr(rotated), self.layout()))
+    }
+
+    /// Rotates bits of the integer right by `count` bits, such that the truncated bits appear
+    /// at the beginning. The count is taken modulo the bit width of the number.
+    ///
+    /// Returns `None` if the number is not an integer.
+    pub fn rotate_right(self, count: u32) -> Option<Number> {
+        let bits = self.len() as u32 * 8;
+        if bits == 0 {
+            return self.layout().is_integer().then_some(self);
+        }
+        self.rotate_left(bits - count % bits)
+    }
+
+    /// Cyclic bit shift left, see [`Number::rotate_left`]. Panics if the number is not an
+    /// integer.
+    pub fn scl(self, shift: Number) -> Number {
+        let count = rotation_count(shift, self.len() * 8);
+        self.rotate_left(count).expect("bit shifting float number")
+    }
+
+    /// Cyclic bit shift right, see [`Number::rotate_right`]. Panics if the number is not an
+    /// integer.
+    pub fn scr(self, shift: Number) -> Number {
+        let count = rotation_count(shift, self.len() * 8);
+        self.rotate_right(count).expect("bit shifting float number")
     }
 
     /// Reverses the order of bits in the integer. The least significant bit becomes the most
@@ -157,6 +215,7 @@ impl Number {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::data::FloatLayout;
 
     #[test]
     fn shl_test() {
@@ -207,4 +266,86 @@ mod tests {
         let y = Number::from(-128i8);
         assert_eq!(x.reverse_bits(), y);
     }
+
+    /// Reference implementation of the shifts operating on the individual bits
+    fn bits(n: Number) -> Vec<bool> {
+        (0..n.len() * 8).map(|i| n[i / 8] & (1 << (i % 8)) != 0).collect()
+    }
+
+    fn from_bits(bits: &[bool], layout: Layout) -> Number {
+        let mut bytes = vec![0u8; bits.len() / 8];
+        for (i, bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= (*bit as u8) << (i % 8);
+        }
+        Number::with(bytes, layout).unwrap()
+    }
+
+    fn sample(bytes: u16, signed: bool) -> Number {
+        let data =
+            (0..bytes).map(|i| (i as u8).wrapping_mul(73).wrapping_add(0x9d)).collect::<Vec<_>>();
+        let layout = if signed { Layout::signed(bytes) } else { Layout::unsigned(bytes) };
+        Number::with(data, layout).unwrap()
+    }
+
+    #[test]
+    fn wide_shifts() {
+        const COUNTS: [u32; 16] =
+            [0, 1, 7, 8, 63, 255, 256, 257, 511, 512, 513, 1023, 1024, 1500, 65535, u32::MAX];
+        for bytes in [32u16, 64, 128] {
+            let width = bytes as usize * 8;
+            for signed in [false, true] {
+                let n = sample(bytes, signed);
+                let layout = n.layout();
+                let src = bits(n);
+                let sign = signed && src[width - 1];
+                for count in COUNTS {
+                    let c = count as usize;
+                    let shl = (0..width).map(|i| i >= c && src[i - c]).collect::<Vec<_>>();
+                    let shr = (0..width)
+                        .map(|i| if c < width - i { src[i + c] } else { sign })
+                        .collect::<Vec<_>>();
+                    let r = c % width;
+                    let rotl = (0..width).map(|i| src[(i + width - r) % width]).collect::<Vec<_>>();
+                    let rotr = (0..width).map(|i| src[(i + r) % width]).collect::<Vec<_>>();
+
+                    assert_eq!(n.shift_left(count), Some(from_bits(&shl, layout)));
+                    assert_eq!(n.shift_right(count), Some(from_bits(&shr, layout)));
+                    assert_eq!(n.rotate_left(count), Some(from_bits(&rotl, layout)));
+                    assert_eq!(n.rotate_right(count), Some(from_bits(&rotr, layout)));
+                    assert_eq!(n.scl(Number::from(count)), from_bits(&rotl, layout));
+                    assert_eq!(n.scr(Number::from(count)), from_bits(&rotr, layout));
+                    assert_eq!(n << Number::from(count), from_bits(&shl, layout));
+                    assert_eq!(n >> Number::from(count), from_bits(&shr, layout));
+                }
+                // Shift counts exceeding 32 bits saturate, while rotation counts are reduced
+                // modulo the bit width
+                let huge = Number::from(u128::MAX);
+                assert!((n << huge).is_zero());
+                assert_eq!((n >> huge).is_zero(), !sign);
+                assert_eq!(n.scl(huge), n.rotate_left((u128::MAX % width as u128) as u32).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn wide_logic() {
+        for bytes in [32u16, 64, 128] {
+            let a = sample(bytes, false);
+            let b = sample(bytes, false).rotate_left(13).unwrap();
+            let (x, y) = (bits(a), bits(b));
+            let map = |f: fn(bool, bool) -> bool| {
+                from_bits(&x.iter().zip(&y).map(|(x, y)| f(*x, *y)).collect::<Vec<_>>(), a.layout())
+            };
+            assert_eq!(a & b, map(|x, y| x & y));
+            assert_eq!(a | b, map(|x, y| x | y));
+            assert_eq!(a ^ b, map(|x, y| x ^ y));
+            assert_eq!(!a, map(|x, _| !x));
+            assert_eq!(a.count_ones() + a.count_zeros(), bytes * 8);
+            assert_eq!((a ^ a).count_zeros(), bytes * 8);
+        }
+        // Result preserves the layout of the left operand
+        assert_eq!((Number::from(0x1234u16) & Number::from(0xFFu8)).layout(), Layout::unsigned(2));
+        assert_eq!(Number::from(0x1234u16) & Number::from(0xFFu8), Number::from(0x34u16));
+        assert_eq!(Number::zero(FloatLayout::IeeeSingle.into()).shift_left(1), None);
+    }
 }
@@ -25,10 +25,11 @@ use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::borrow::{Borrow, BorrowMut};
 use core::convert::TryFrom;
-use core::fmt::{self, Debug, Display, Formatter};
-use core::ops::Range;
+use core::fmt::{self, Debug, Display, Formatter, LowerHex, UpperHex};
+use core::ops::{Deref, DerefMut, Range};
 
 use amplify::confinement::{SmallBlob, TinyBlob};
+use amplify::hex::{self, FromHex};
 use amplify::num::error::OverflowError;
 
 /// Large binary bytestring object.
@@ -56,6 +57,18 @@ impl AsMut<[u8]> for ByteStr {
     fn as_mut(&mut self) -> &mut [u8] { &mut self.bytes[..self.len as usize] }
 }
 
+impl Deref for ByteStr {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target { self.as_ref() }
+}
+
+impl DerefMut for ByteStr {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target { self.as_mut() }
+}
+
 impl Borrow<[u8]> for ByteStr {
     #[inline]
     fn borrow(&self) -> &[u8] { &self.bytes[..self.len as usize] }
@@ -147,6 +160,10 @@ impl ByteStr {
 
     /// Fills range within a string with the provided byte value, increasing string length if
     /// necessary
+    ///
+    /// # Panics
+    ///
+    /// If the range start is greater than the range end.
     pub fn fill(&mut self, range: Range<u16>, val: u8) {
         let start = range.start;
         let end = range.end;
@@ -154,9 +171,88 @@ impl ByteStr {
         self.bytes[start as usize..end as usize].fill(val);
     }
 
+    /// Returns bytes within the range, or `None` if the range is reversed or exceeds the length of
+    /// the string.
+    pub fn slice(&self, range: Range<u16>) -> Option<&[u8]> {
+        if range.start > range.end || range.end > self.len {
+            return None;
+        }
+        Some(&self.bytes[range.start as usize..range.end as usize])
+    }
+
+    /// Constructs new string from the bytes within the range, or returns `None` if the range is
+    /// reversed or exceeds the length of the string.
+    pub fn substr(&self, range: Range<u16>) -> Option<ByteStr> {
+        self.slice(range).map(ByteStr::with)
+    }
+
+    /// Appends bytes to the end of the string.
+    ///
+    /// # Errors
+    ///
+    /// If the resulting string would exceed `u16::MAX` bytes; in this case the string is not
+    /// modified.
+    pub fn append(&mut self, data: impl AsRef<[u8]>) -> Result<(), OverflowError> {
+        let data = data.as_ref();
+        let start = self.len as usize;
+        let end = start + data.len();
+        if end > u16::MAX as usize {
+            return Err(OverflowError { max: u16::MAX as usize, value: end });
+        }
+        self.bytes[start..end].copy_from_slice(data);
+        self.len = end as u16;
+        Ok(())
+    }
+
+    /// Constructs new string by concatenating this string with other bytes.
+    ///
+    /// # Errors
+    ///
+    /// If the resulting string would exceed `u16::MAX` bytes.
+    pub fn concat(&self, other: impl AsRef<[u8]>) -> Result<ByteStr, OverflowError> {
+        let mut res = self.clone();
+        res.append(other)?;
+        Ok(res)
+    }
+
+    /// Returns offset of the first occurrence of the pattern in the string, or `None` if the
+    /// string doesn't contain the pattern. Empty pattern is found at the offset zero.
+    pub fn find(&self, pattern: impl AsRef<[u8]>) -> Option<u16> {
+        let pattern = pattern.as_ref();
+        if pattern.is_empty() {
+            return Some(0);
+        }
+        self.as_ref()
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .map(|pos| pos as u16)
+    }
+
     /// Returns vector representation of the contained bytecode
     #[inline]
     pub fn to_vec(&self) -> Vec<u8> { self.as_ref().to_vec() }
+
+    /// Writes byte string as AluVM assembly data literal: double-quoted string where printable
+    /// ASCII characters are kept as is, and the rest of bytes are escaped with `\\`, `\"`,
+    /// `\0`, `\t`, `\n`, `\r` or `\xHH` sequences.
+    fn fmt_literal(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use core::fmt::Write;
+
+        f.write_char('"')?;
+        for byte in self.as_ref() {
+            match byte {
+                b'\\' => f.write_str("\\\\")?,
+                b'"' => f.write_str("\\\"")?,
+                b'\0' => f.write_str("\\0")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\r' => f.write_str("\\r")?,
+                0x20..=0x7E => f.write_char(*byte as char)?,
+                _ => write!(f, "\\x{:02x}", byte)?,
+            }
+        }
+        f.write_char('"')
+    }
 }
 
 #[cfg(not(feature = "std"))]
@@ -178,9 +274,6 @@ impl Display for ByteStr {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         use std::fmt::Write;
 
-        use amplify::hex::ToHex;
-
-        let vec = Vec::from(&self.bytes[..self.len as usize]);
         if f.alternate() {
             for (line, slice) in self.as_ref().chunks(16).enumerate() {
                 write!(f, "\x1B[0;35m{:>1$x}0  |  \x1B[0m", line, f.width().unwrap_or(1) - 1)?;
@@ -214,19 +307,82 @@ impl Display for ByteStr {
             Ok(())
             // write!(f, "{}..{}", self.bytes[..4].to_hex(), self.bytes[(self.len() -
             // 4)..].to_hex())
-        } else if let Ok(s) = String::from_utf8(vec) {
-            f.write_str("\"")?;
-            f.write_str(&s)?;
-            f.write_str("\"")
         } else {
-            f.write_str(&self.as_ref().to_hex())
+            self.fmt_literal(f)
         }
     }
 }
 
 #[cfg(not(feature = "std"))]
 impl Display for ByteStr {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { write!(f, "{:#04X?}", self.as_ref()) }
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { self.fmt_literal(f) }
+}
+
+impl LowerHex for ByteStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        self.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl UpperHex for ByteStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        self.iter().try_for_each(|byte| write!(f, "{:02X}", byte))
+    }
+}
+
+impl FromHex for ByteStr {
+    fn from_byte_iter<I>(iter: I) -> Result<Self, hex::Error>
+    where
+        I: Iterator<Item = Result<u8, hex::Error>> + ExactSizeIterator + DoubleEndedIterator,
+    {
+        if iter.len() > u16::MAX as usize {
+            return Err(hex::Error::InvalidLength(u16::MAX as usize, iter.len()));
+        }
+        let mut s = ByteStr::default();
+        for byte in iter {
+            s.bytes[s.len as usize] = byte?;
+            s.len += 1;
+        }
+        Ok(s)
+    }
+}
+
+/// Errors parsing byte strings from base64 encoding
+#[cfg(feature = "base64")]
+#[derive(Clone, Eq, PartialEq, Debug, Display, From)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum Base64Error {
+    /// invalid base64 encoding: {0}
+    #[from]
+    Decode(base64::DecodeError),
+
+    /// decoded byte string has {0} bytes, while at most 65535 bytes are allowed
+    TooLong(usize),
+}
+
+#[cfg(feature = "base64")]
+impl ByteStr {
+    /// Encodes the string using the standard base64 alphabet with padding.
+    pub fn to_base64(&self) -> alloc::string::String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(self.as_ref())
+    }
+
+    /// Decodes the string from the standard base64 encoding with padding.
+    pub fn from_base64(s: &str) -> Result<ByteStr, Base64Error> {
+        use base64::Engine;
+
+        let data = base64::engine::general_purpose::STANDARD.decode(s)?;
+        ByteStr::try_from(data.as_slice()).map_err(|_| Base64Error::TooLong(data.len()))
+    }
 }
 
 /*
@@ -255,6 +411,81 @@ mod _strict_encoding {
 }
  */
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slicing() {
+        let s = ByteStr::with(b"hello world");
+        assert_eq!(s.slice(6..11), Some(&b"world"[..]));
+        assert_eq!(s.slice(6..12), None);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = s.slice(6..5);
+        assert_eq!(reversed, None);
+        assert_eq!(s.substr(0..5), Some(ByteStr::with(b"hello")));
+        assert_eq!(&s[..5], b"hello");
+        assert_eq!(s.iter().filter(|b| **b == b'o').count(), 2);
+    }
+
+    #[test]
+    fn find() {
+        let s = ByteStr::with(b"abcabc");
+        assert_eq!(s.find(b"ca"), Some(2));
+        assert_eq!(s.find(b"abc"), Some(0));
+        assert_eq!(s.find(b"abd"), None);
+        assert_eq!(s.find(b""), Some(0));
+        assert_eq!(ByteStr::default().find(b"a"), None);
+    }
+
+    #[test]
+    fn hex() {
+        let s = ByteStr::with(b"\x00\xAB\x10");
+        assert_eq!(format!("{:x}", s), "00ab10");
+        assert_eq!(format!("{:#X}", s), "0x00AB10");
+        assert_eq!(ByteStr::from_hex("00ab10"), Ok(s));
+        assert_eq!(ByteStr::from_hex("00AB10"), ByteStr::from_hex("00ab10"));
+        assert_eq!(ByteStr::from_hex(""), Ok(ByteStr::default()));
+        assert_eq!(ByteStr::from_hex("0"), Err(hex::Error::OddLengthString(1)));
+        assert_eq!(ByteStr::from_hex("0g"), Err(hex::Error::InvalidChar(b'g')));
+
+        let max = ByteStr::with([0xA5u8; u16::MAX as usize]);
+        assert_eq!(ByteStr::from_hex(&format!("{:x}", max)), Ok(max));
+        assert_eq!(
+            ByteStr::from_hex(&"00".repeat(u16::MAX as usize + 1)),
+            Err(hex::Error::InvalidLength(u16::MAX as usize, u16::MAX as usize + 1))
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64() {
+        let s = ByteStr::with(b"hello world");
+        assert_eq!(s.to_base64(), "aGVsbG8gd29ybGQ=");
+        assert_eq!(ByteStr::from_base64("aGVsbG8gd29ybGQ="), Ok(s));
+        assert!(matches!(ByteStr::from_base64("aGVsbG8*"), Err(Base64Error::Decode(_))));
+
+        let max = ByteStr::with([0xA5u8; u16::MAX as usize]);
+        assert_eq!(ByteStr::from_base64(&max.to_base64()), Ok(max));
+        let long = ByteStr::with([0u8; 3]).to_base64().repeat(u16::MAX as usize / 3 + 1);
+        assert_eq!(ByteStr::from_base64(&long), Err(Base64Error::TooLong(65538)));
+    }
+
+    #[test]
+    fn append() {
+        let mut s = ByteStr::with(b"abc");
+        s.append(b"def").unwrap();
+        assert_eq!(s.as_ref(), b"abcdef");
+        assert_eq!(s.concat(b"g").unwrap().as_ref(), b"abcdefg");
+
+        let mut s = ByteStr::with([0u8; u16::MAX as usize - 1]);
+        assert_eq!(s.append(b"ab"), Err(OverflowError { max: u16::MAX as usize, value: 65536 }));
+        assert_eq!(s.len(), u16::MAX - 1);
+        s.append(b"a").unwrap();
+        assert_eq!(s.len(), u16::MAX);
+    }
+}
+
 #[cfg(feature = "serde")]
 mod _serde {
     use std::convert::TryFrom;
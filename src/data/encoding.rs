@@ -23,18 +23,23 @@
 
 //! Helper traits and default implementations for encoding elements of AliVM container types
 
+use std::convert::TryFrom;
 use std::io::{self, Read, Write};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::string::FromUtf8Error;
 
+use amplify::num::{u3, u5};
 use amplify::{IoError, Wrapper};
 
 use crate::data::encoding::DecodeError::InvalidBool;
 use crate::data::{ByteStr, FloatLayout, IntLayout, Layout, MaybeNumber, Number, NumberLayout};
 use crate::library::{
-    IsaSeg, IsaSegError, Lib, LibId, LibSeg, LibSegOverflow, LibSite, SegmentError,
+    Abi, AbiError, AbiParam, DebugInfo, IsaSeg, IsaSegError, Lib, LibId, LibMeta, LibSeg,
+    LibSegOverflow, LibSite, RoutineError, SegmentError, SemVer, SourceSpan,
 };
+use crate::reg::{CoreRegs, Reg, Reg32, RegA, RegF, RegR, RegS, SnapshotError};
+use crate::replay::{HostResult, Recording};
 
 /// Trait for encodable container data structures used by AluVM and runtime environments
 pub trait Encode {
@@ -118,6 +123,9 @@ pub enum DecodeError {
     /// unknown float layout type `{0}`
     FloatLayout(u8),
 
+    /// invalid register code `{0}`
+    InvalidRegister(u8),
+
     /// Library construction errors
     #[display(inner)]
     #[from]
@@ -132,6 +140,24 @@ pub enum DecodeError {
     #[display(inner)]
     #[from]
     IsaSeg(IsaSegError),
+
+    /// Library routine table error
+    #[display(inner)]
+    #[from]
+    Routine(RoutineError),
+
+    /// Routine ABI descriptor error
+    #[display(inner)]
+    #[from]
+    Abi(AbiError),
+
+    /// Register state error
+    #[display(inner)]
+    #[from]
+    Snapshot(SnapshotError),
+
+    /// unsupported version `{0}` of the library tables format
+    LibVersion(u8),
 }
 
 /// Wrapper around collections which may contain at most [`u8::MAX`] elements
@@ -239,11 +265,35 @@ impl Decode for u16 {
     }
 }
 
+impl Encode for u32 {
+    type Error = io::Error;
+
+    #[inline]
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        writer.write_all(&self.to_le_bytes())?;
+        Ok(4)
+    }
+}
+
+impl Decode for u32 {
+    type Error = io::Error;
+
+    #[inline]
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        Ok(u32::from_le_bytes(word))
+    }
+}
+
 impl Encode for String {
     type Error = EncodeError;
 
     fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
-        let len = self.as_bytes().len();
+        let len = self.len();
         if len > u8::MAX as usize {
             return Err(EncodeError::StringTooLong(len));
         }
@@ -505,8 +555,8 @@ impl Encode for MaybeNumber {
 
     fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
         match **self {
-            Some(number) => Ok(1u16.encode(&mut writer)? + number.encode(&mut writer)?),
-            None => 0u16.encode(writer),
+            Some(number) => Ok(1u8.encode(&mut writer)? + number.encode(&mut writer)?),
+            None => 0u8.encode(writer),
         }
     }
 }
@@ -677,29 +727,450 @@ impl Decode for LibSite {
     }
 }
 
-impl Encode for Lib {
+impl Encode for Reg {
+    type Error = io::Error;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        let (family, reg, idx) = match self {
+            Reg::A(a, idx) => (0u8, u3::from(a).to_u8(), Some(idx)),
+            Reg::F(f, idx) => (1u8, u3::from(f).to_u8(), Some(idx)),
+            Reg::R(r, idx) => (2u8, u3::from(r).to_u8(), Some(idx)),
+            Reg::S(s) => (3u8, s.as_u8(), None),
+        };
+        let mut count = family.encode(&mut writer)? + reg.encode(&mut writer)?;
+        if let Some(idx) = idx {
+            count += u8::from(idx).encode(&mut writer)?;
+        }
+        Ok(count)
+    }
+}
+
+impl Decode for Reg {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let family = u8::decode(&mut reader)?;
+        let reg = u8::decode(&mut reader)?;
+        let reg3 = || u3::try_from(reg).map_err(|_| DecodeError::InvalidRegister(reg));
+        let mut idx = || -> Result<Reg32, DecodeError> {
+            let idx = u8::decode(&mut reader)?;
+            Ok(u5::try_from(idx).map_err(|_| DecodeError::InvalidRegister(idx))?.into())
+        };
+        Ok(match family {
+            0 => Reg::A(RegA::from(reg3()?), idx()?),
+            1 => Reg::F(RegF::from(reg3()?), idx()?),
+            2 => Reg::R(RegR::from(reg3()?), idx()?),
+            3 if reg < 16 => Reg::S(RegS::from(reg)),
+            3 => return Err(DecodeError::InvalidRegister(reg)),
+            _ => return Err(DecodeError::InvalidRegister(family)),
+        })
+    }
+}
+
+impl Encode for AbiParam {
+    type Error = io::Error;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        let mut count =
+            self.reg.encode(&mut writer)? + self.layout.is_some().encode(&mut writer)?;
+        if let Some(layout) = self.layout {
+            count += layout.encode(&mut writer)?;
+        }
+        Ok(count)
+    }
+}
+
+impl Decode for AbiParam {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let reg = Reg::decode(&mut reader)?;
+        let layout = if bool::decode(&mut reader)? { Some(Layout::decode(reader)?) } else { None };
+        Ok(AbiParam { reg, layout })
+    }
+}
+
+/// Encodes list of routine parameters as their number followed by the parameters.
+fn encode_params(params: &[AbiParam], mut writer: impl Write) -> Result<usize, EncodeError> {
+    if params.len() > u16::MAX as usize {
+        return Err(EncodeError::WordLimitExceeded(params.len()));
+    }
+    let mut count = (params.len() as u16).encode(&mut writer)?;
+    for param in params {
+        count += param.encode(&mut writer)?;
+    }
+    Ok(count)
+}
+
+/// Decodes list of routine parameters encoded with [`encode_params`].
+fn decode_params(mut reader: impl Read) -> Result<Vec<AbiParam>, DecodeError> {
+    (0..u16::decode(&mut reader)?).map(|_| AbiParam::decode(&mut reader)).collect()
+}
+
+impl Encode for Abi {
+    type Error = EncodeError;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        Ok(encode_params(self.inputs(), &mut writer)? + encode_params(self.outputs(), writer)?)
+    }
+}
+
+impl Decode for Abi {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let inputs = decode_params(&mut reader)?;
+        let outputs = decode_params(reader)?;
+        Ok(Abi::new(inputs, outputs)?)
+    }
+}
+
+impl Encode for DebugInfo {
+    type Error = EncodeError;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        // Tables keyed by the code offsets may have one entry more than fits the 16-bit length
+        let len = |len: usize| u16::try_from(len).map_err(|_| EncodeError::WordLimitExceeded(len));
+        let mut count = len(self.lines.len())?.encode(&mut writer)?;
+        for (pos, line) in &self.lines {
+            count += pos.encode(&mut writer)? + line.encode(&mut writer)?;
+        }
+        count += len(self.labels.len())?.encode(&mut writer)?;
+        for (pos, name) in &self.labels {
+            count += pos.encode(&mut writer)? + name.encode(&mut writer)?;
+        }
+        count += len(self.vars.len())?.encode(&mut writer)?;
+        for (reg, name) in &self.vars {
+            count += reg.encode(&mut writer)? + name.encode(&mut writer)?;
+        }
+        count += len(self.spans.len())?.encode(&mut writer)?;
+        for (pos, span) in self.spans.iter() {
+            count += pos.encode(&mut writer)? + span.encode(&mut writer)?;
+        }
+        Ok(count)
+    }
+}
+
+impl Decode for DebugInfo {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let mut debug = DebugInfo::new();
+        for _ in 0..u16::decode(&mut reader)? {
+            let pos = u16::decode(&mut reader)?;
+            debug.lines.insert(pos, u32::decode(&mut reader)?);
+        }
+        for _ in 0..u16::decode(&mut reader)? {
+            let pos = u16::decode(&mut reader)?;
+            debug.labels.insert(pos, String::decode(&mut reader)?);
+        }
+        for _ in 0..u16::decode(&mut reader)? {
+            let reg = Reg::decode(&mut reader)?;
+            debug.vars.insert(reg, String::decode(&mut reader)?);
+        }
+        for _ in 0..u16::decode(&mut reader)? {
+            let pos = u16::decode(&mut reader)?;
+            debug.spans.insert(pos, SourceSpan::decode(&mut reader)?);
+        }
+        Ok(debug)
+    }
+}
+
+impl Encode for SourceSpan {
+    type Error = EncodeError;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        Ok(self.file.encode(&mut writer)?
+            + self.line.encode(&mut writer)?
+            + self.column.encode(&mut writer)?
+            + self.len.encode(&mut writer)?)
+    }
+}
+
+impl Decode for SourceSpan {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        Ok(SourceSpan {
+            file: String::decode(&mut reader)?,
+            line: u32::decode(&mut reader)?,
+            column: u32::decode(&mut reader)?,
+            len: u32::decode(&mut reader)?,
+        })
+    }
+}
+
+impl Encode for SemVer {
+    type Error = io::Error;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        Ok(self.major.encode(&mut writer)?
+            + self.minor.encode(&mut writer)?
+            + self.patch.encode(&mut writer)?)
+    }
+}
+
+impl Decode for SemVer {
+    type Error = io::Error;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        Ok(SemVer {
+            major: u16::decode(&mut reader)?,
+            minor: u16::decode(&mut reader)?,
+            patch: u16::decode(&mut reader)?,
+        })
+    }
+}
+
+impl Encode for LibMeta {
+    type Error = EncodeError;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        let mut count =
+            self.name.encode(&mut writer)? + self.version.is_some().encode(&mut writer)?;
+        if let Some(version) = self.version {
+            count += version.encode(&mut writer)?;
+        }
+        Ok(count + self.author.encode(&mut writer)? + self.description.encode(&mut writer)?)
+    }
+}
+
+impl Decode for LibMeta {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let name = Option::<String>::decode(&mut reader)?;
+        let version =
+            if bool::decode(&mut reader)? { Some(SemVer::decode(&mut reader)?) } else { None };
+        Ok(LibMeta {
+            name,
+            version,
+            author: Option::<String>::decode(&mut reader)?,
+            description: Option::<String>::decode(&mut reader)?,
+        })
+    }
+}
+
+/// Encodes library code or data segment in the same way as [`ByteStr`].
+fn encode_segment(segment: &[u8], mut writer: impl Write) -> Result<usize, io::Error> {
+    let len = segment.len() as u16;
+    len.encode(&mut writer)?;
+    writer.write_all(segment)?;
+    Ok(len as usize + 2)
+}
+
+/// Version of the format of the library tables following the library segments.
+const LIB_TABLES_VERSION: u8 = 1;
+
+/// Libraries are encoded as their segments, optionally followed by a `LIB_TABLES_VERSION` byte
+/// and the tables which are not committed by the library id: routines, ABI descriptors, debug
+/// information and metadata. Libraries without any of the tables are encoded just as the
+/// segments, matching the format of the library encoding before the tables were introduced.
+impl Encode for Lib<'_> {
     type Error = EncodeError;
 
     fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
-        Ok(self.isae_segment().encode(&mut writer)?
-            + self.code.encode(&mut writer)?
-            + self.data.encode(&mut writer)?
-            + self.libs.encode(&mut writer)?)
+        let count = self.isae_segment().encode(&mut writer)?
+            + encode_segment(&self.code, &mut writer)?
+            + encode_segment(&self.data, &mut writer)?
+            + self.libs.encode(&mut writer)?;
+        if self.routines.is_empty()
+            && self.abis.is_empty()
+            && self.debug.is_none()
+            && self.meta.is_none()
+        {
+            return Ok(count);
+        }
+        Ok(count
+            + LIB_TABLES_VERSION.encode(&mut writer)?
+            + encode_routines(self, &mut writer)?
+            + encode_abis(self, &mut writer)?
+            + self.debug.is_some().encode(&mut writer)?
+            + self.debug.as_ref().map(|debug| debug.encode(&mut writer)).transpose()?.unwrap_or(0)
+            + self.meta.is_some().encode(&mut writer)?
+            + self.meta.as_ref().map(|meta| meta.encode(&mut writer)).transpose()?.unwrap_or(0))
+    }
+}
+
+/// Encodes routine table of the library as a number of routines followed by their names and
+/// code offsets.
+fn encode_routines(lib: &Lib, mut writer: impl Write) -> Result<usize, EncodeError> {
+    let len = lib.routines.len();
+    if len > u16::MAX as usize {
+        return Err(EncodeError::WordLimitExceeded(len));
+    }
+    let mut count = (len as u16).encode(&mut writer)?;
+    for (name, pos) in &lib.routines {
+        count += name.encode(&mut writer)?;
+        count += pos.encode(&mut writer)?;
+    }
+    Ok(count)
+}
+
+/// Encodes routine ABI descriptors of the library as a number of descriptors followed by the
+/// routine names and the descriptors.
+fn encode_abis(lib: &Lib, mut writer: impl Write) -> Result<usize, EncodeError> {
+    // Each descriptor is attached to an exported routine, so the routine table limit applies
+    let mut count = (lib.abis.len() as u16).encode(&mut writer)?;
+    for (name, abi) in &lib.abis {
+        count += name.encode(&mut writer)?;
+        count += abi.encode(&mut writer)?;
     }
+    Ok(count)
 }
 
-impl Decode for Lib {
+impl Decode for Lib<'static> {
     type Error = DecodeError;
 
     fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
     where
         Self: Sized,
     {
-        Ok(Lib::with(
+        let mut lib = Lib::with(
             String::decode(&mut reader)?.as_str(),
             ByteStr::decode(&mut reader)?.to_vec(),
             ByteStr::decode(&mut reader)?.to_vec(),
             LibSeg::decode(&mut reader)?,
-        )?)
+        )?;
+        let mut version = [0u8; 1];
+        loop {
+            match reader.read(&mut version) {
+                Ok(0) => return Ok(lib),
+                Ok(_) => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if version[0] != LIB_TABLES_VERSION {
+            return Err(DecodeError::LibVersion(version[0]));
+        }
+        for _ in 0..u16::decode(&mut reader)? {
+            let name = String::decode(&mut reader)?;
+            lib.export(name, u16::decode(&mut reader)?)?;
+        }
+        for _ in 0..u16::decode(&mut reader)? {
+            let name = String::decode(&mut reader)?;
+            lib.set_abi(name, Abi::decode(&mut reader)?)?;
+        }
+        if bool::decode(&mut reader)? {
+            lib.debug = Some(DebugInfo::decode(&mut reader)?);
+        }
+        if bool::decode(&mut reader)? {
+            lib.meta = Some(LibMeta::decode(&mut reader)?);
+        }
+        Ok(lib)
+    }
+}
+
+impl Encode for HostResult {
+    type Error = io::Error;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        Ok(self.site.encode(&mut writer)?
+            + self.func.encode(&mut writer)?
+            + self.value.encode(&mut writer)?)
+    }
+}
+
+impl Decode for HostResult {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        Ok(HostResult {
+            site: LibSite::decode(&mut reader)?,
+            func: u16::decode(&mut reader)?,
+            value: MaybeNumber::decode(&mut reader)?,
+        })
+    }
+}
+
+/// Recordings are encoded as the entry point, final `st0` value and register digest, followed by
+/// 32-bit number of host function results, the results themselves, and the initial registers as
+/// [`CoreRegs::snapshot`] data prefixed with their 32-bit length.
+impl Encode for Recording {
+    type Error = EncodeError;
+
+    fn encode(&self, mut writer: impl Write) -> Result<usize, Self::Error> {
+        let mut count = self.method.encode(&mut writer)? + self.status.encode(&mut writer)?;
+        writer.write_all(&self.digest)?;
+        count += self.digest.len();
+        count += (self.host_results.len() as u32).encode(&mut writer)?;
+        for res in &self.host_results {
+            count += res.encode(&mut writer)?;
+        }
+        let registers = self.registers.snapshot();
+        count += (registers.len() as u32).encode(&mut writer)?;
+        writer.write_all(&registers)?;
+        Ok(count + registers.len())
+    }
+}
+
+impl Decode for Recording {
+    type Error = DecodeError;
+
+    fn decode(mut reader: impl Read) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let method = LibSite::decode(&mut reader)?;
+        let status = bool::decode(&mut reader)?;
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+        let host_results = (0..u32::decode(&mut reader)?)
+            .map(|_| HostResult::decode(&mut reader))
+            .collect::<Result<_, _>>()?;
+        // The data are read without allocating the buffer of the declared length upfront
+        let len = u32::decode(&mut reader)? as u64;
+        let mut registers = vec![];
+        (&mut reader).take(len).read_to_end(&mut registers)?;
+        if registers.len() as u64 != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let registers = Box::new(CoreRegs::restore(&registers)?);
+        Ok(Recording { method, registers, host_results, status, digest })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn maybe_number() {
+        let none = MaybeNumber::none();
+        assert_eq!(none.serialize(), vec![0]);
+        assert_eq!(MaybeNumber::deserialize(none.serialize()).unwrap(), none);
+
+        let some = MaybeNumber::from(0xCAFEu16);
+        let data = some.serialize();
+        assert_eq!(data[0], 1);
+        assert_eq!(data[1..], Number::from(0xCAFEu16).serialize()[..]);
+        assert_eq!(MaybeNumber::deserialize(&data).unwrap(), some);
+
+        assert!(matches!(MaybeNumber::deserialize([2u8]), Err(DecodeError::InvalidBool(2))));
     }
 }
@@ -24,13 +24,21 @@
 //! Internal data representations and operations on data used by AluVM
 
 mod arithm;
+pub(crate) mod basen;
 mod bitwise;
 mod byte_str;
 #[cfg(feature = "std")]
 pub mod encoding;
+pub(crate) mod modular;
 mod number;
 
+pub use arithm::{FloatFlags, FloatOrder};
+#[cfg(feature = "base64")]
+pub use byte_str::Base64Error;
 pub use byte_str::ByteStr;
+#[cfg(feature = "strict_types")]
+pub use number::StrictValError;
 pub use number::{
-    FloatLayout, IntLayout, Layout, LiteralParseError, MaybeNumber, Number, NumberLayout, Step,
+    FixedLayout, FloatLayout, IntLayout, Layout, LiteralParseError, MaybeNumber, Number,
+    NumberLayout, Step,
 };
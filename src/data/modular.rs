@@ -0,0 +1,300 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Modular arithmetic on unsigned integers of arbitrary length.
+//!
+//! Values are represented as little-endian byte strings, as they are stored in the registers, and
+//! are processed as sequences of 32-bit limbs.
+
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Converts little-endian bytes into limbs without the most significant zero limbs.
+fn limbs(bytes: &[u8]) -> Vec<u32> {
+    let mut limbs = bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut limb = [0u8; 4];
+            limb[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(limb)
+        })
+        .collect();
+    trim(&mut limbs);
+    limbs
+}
+
+/// Converts limbs into little-endian bytes of the given length. The value must fit the length.
+fn to_bytes(limbs: &[u32], len: usize) -> Vec<u8> {
+    let mut bytes = limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect::<Vec<_>>();
+    bytes.resize(len, 0);
+    bytes
+}
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+/// Compares values without the most significant zero limbs.
+fn cmp(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u64;
+    for (no, limb) in a.iter().enumerate() {
+        let s = *limb as u64 + b.get(no).copied().unwrap_or_default() as u64 + carry;
+        sum.push(s as u32);
+        carry = s >> 32;
+    }
+    sum.push(carry as u32);
+    trim(&mut sum);
+    sum
+}
+
+fn mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut prod = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let p = *x as u64 * *y as u64 + prod[i + j] as u64 + carry;
+            prod[i + j] = p as u32;
+            carry = p >> 32;
+        }
+        prod[i + b.len()] = carry as u32;
+    }
+    trim(&mut prod);
+    prod
+}
+
+/// Shifts value left by less than 32 bits, returning it with an additional most significant limb.
+fn shl(a: &[u32], shift: u32) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0u32;
+    for limb in a {
+        res.push((limb << shift) | carry);
+        carry = if shift == 0 { 0 } else { limb >> (32 - shift) };
+    }
+    res.push(carry);
+    res
+}
+
+/// Computes remainder of the division of `u` by non-zero `v` using Knuth's algorithm D (The Art
+/// of Computer Programming, vol. 2, section 4.3.1). Both values must not have most significant
+/// zero limbs.
+fn rem(u: &[u32], v: &[u32]) -> Vec<u32> {
+    let n = v.len();
+    debug_assert!(n > 0 && v[n - 1] != 0);
+    if cmp(u, v) == Ordering::Less {
+        return u.to_vec();
+    }
+    if n == 1 {
+        let d = v[0] as u64;
+        let r = u.iter().rev().fold(0u64, |r, limb| ((r << 32) | *limb as u64) % d);
+        let mut r = vec![r as u32];
+        trim(&mut r);
+        return r;
+    }
+
+    // Normalization makes the most significant bit of the divisor set
+    let shift = v[n - 1].leading_zeros();
+    let v = &shl(v, shift)[..n];
+    let mut u = shl(u, shift);
+    let (vh, vl) = (v[n - 1] as u64, v[n - 2] as u64);
+    for j in (0..u.len() - n).rev() {
+        let num = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = num / vh;
+        let mut rhat = num % vh;
+        while qhat > u32::MAX as u64 || qhat * vl > ((rhat << 32) | u[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += vh;
+            if rhat > u32::MAX as u64 {
+                break;
+            }
+        }
+
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64 + carry;
+            carry = p >> 32;
+            let t = u[i + j] as i64 - borrow - (p & 0xFFFF_FFFF) as i64;
+            u[i + j] = t as u32;
+            borrow = (t < 0) as i64;
+        }
+        let t = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = t as u32;
+
+        // The estimated quotient digit was one too large: adding the divisor back
+        if t < 0 {
+            let mut carry = 0u64;
+            for i in 0..n {
+                let s = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = s as u32;
+                carry = s >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+    }
+
+    let mut r = (0..n)
+        .map(|i| match shift {
+            0 => u[i],
+            _ => (u[i] >> shift) | (u[i + 1] << (32 - shift)),
+        })
+        .collect();
+    trim(&mut r);
+    r
+}
+
+/// Computes `(a + b) mod m`, returning the result with the byte length of the modulus, or `None`
+/// if the modulus is zero.
+pub(crate) fn add_mod(a: &[u8], b: &[u8], m: &[u8]) -> Option<Vec<u8>> {
+    let m_limbs = limbs(m);
+    if m_limbs.is_empty() {
+        return None;
+    }
+    let sum = add(&rem(&limbs(a), &m_limbs), &rem(&limbs(b), &m_limbs));
+    Some(to_bytes(&rem(&sum, &m_limbs), m.len()))
+}
+
+/// Computes `(a * b) mod m`, returning the result with the byte length of the modulus, or `None`
+/// if the modulus is zero.
+pub(crate) fn mul_mod(a: &[u8], b: &[u8], m: &[u8]) -> Option<Vec<u8>> {
+    let m_limbs = limbs(m);
+    if m_limbs.is_empty() {
+        return None;
+    }
+    let prod = mul(&rem(&limbs(a), &m_limbs), &rem(&limbs(b), &m_limbs));
+    Some(to_bytes(&rem(&prod, &m_limbs), m.len()))
+}
+
+/// Computes `base ^ exp mod m` by binary exponentiation, returning the result with the byte
+/// length of the modulus, or `None` if the modulus is zero. Zero power of any value, including
+/// zero, is one.
+pub(crate) fn pow_mod(base: &[u8], exp: &[u8], m: &[u8]) -> Option<Vec<u8>> {
+    let m_limbs = limbs(m);
+    if m_limbs.is_empty() {
+        return None;
+    }
+    let base = rem(&limbs(base), &m_limbs);
+    let mut acc = rem(&[1], &m_limbs);
+    for byte in exp.iter().rev() {
+        for bit in (0..8).rev() {
+            acc = rem(&mul(&acc, &acc), &m_limbs);
+            if byte & (1 << bit) != 0 {
+                acc = rem(&mul(&acc, &base), &m_limbs);
+            }
+        }
+    }
+    Some(to_bytes(&acc, m.len()))
+}
+
+/// Checks whether the value is less than the modulus, i.e. is a canonical residue.
+pub(crate) fn is_reduced(a: &[u8], m: &[u8]) -> bool { cmp(&limbs(a), &limbs(m)) == Ordering::Less }
+
+/// Computes multiplicative inverse of `a` modulo prime `m` as `a ^ (m - 2) mod m`, returning the
+/// result with the byte length of the modulus, or `None` if `a` is divisible by `m` or the modulus
+/// is less than two. The result is meaningless if the modulus is not a prime.
+pub(crate) fn inv_mod_prime(a: &[u8], m: &[u8]) -> Option<Vec<u8>> {
+    let m_limbs = limbs(m);
+    if cmp(&m_limbs, &[2]) == Ordering::Less || rem(&limbs(a), &m_limbs).is_empty() {
+        return None;
+    }
+    let mut exp = m.to_vec();
+    let mut borrow = 2u16;
+    for byte in &mut exp {
+        let (val, overflow) = byte.overflowing_sub(borrow as u8);
+        *byte = val;
+        borrow = overflow as u16;
+    }
+    pow_mod(a, &exp, m)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn num(val: u128) -> Vec<u8> { val.to_le_bytes().to_vec() }
+
+    #[test]
+    fn small_values() {
+        let m = num(1_000_000_007);
+        assert_eq!(add_mod(&num(1_000_000_000), &num(10), &m), Some(num(3)));
+        assert_eq!(mul_mod(&num(123_456_789), &num(987_654_321), &m), Some(num(259_106_859)));
+        assert_eq!(pow_mod(&num(2), &num(1_000_000_006), &m), Some(num(1)));
+        assert_eq!(pow_mod(&num(0), &num(0), &m), Some(num(1)));
+        assert_eq!(pow_mod(&num(5), &num(0), &num(1)), Some(num(0)));
+        assert_eq!(add_mod(&num(1), &num(1), &num(0)), None);
+        assert_eq!(inv_mod_prime(&num(3), &m), Some(num(333_333_336)));
+        assert_eq!(inv_mod_prime(&num(1_000_000_007), &m), None);
+        assert_eq!(inv_mod_prime(&num(0x100), &num(0x101)), Some(num(0x100)));
+        assert!(is_reduced(&num(1_000_000_006), &m));
+        assert!(!is_reduced(&num(1_000_000_007), &m));
+        assert_eq!(mul_mod(&num(1), &num(1), &[0u8; 32]), None);
+    }
+
+    #[test]
+    fn wide_values() {
+        // Reference computed with 128-bit arithmetic for moduli below 2^64
+        let m = 0xFFFF_FFFF_FFFF_FFC5u128;
+        let (a, b) = (0xDEAD_BEEF_0123_4567_89AB_CDEF_u128, 0xFEDC_BA98_7654_3210_u128);
+        assert_eq!(mul_mod(&num(a), &num(b), &num(m)), Some(num((a % m) * (b % m) % m)));
+        assert_eq!(
+            add_mod(&num(a), &num(u128::MAX), &num(m)),
+            Some(num((a % m + u128::MAX % m) % m))
+        );
+
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            seed >> (seed % 48) as u32
+        };
+        for _ in 0..1000 {
+            let (a, b, m) = (next() as u128, next() as u128, next() as u128 | 1);
+            assert_eq!(mul_mod(&num(a), &num(b), &num(m)), Some(num(a * b % m)));
+        }
+
+        // Fermat's little theorem for the secp256k1 field prime
+        let mut p = [0xFFu8; 32];
+        p[..4].copy_from_slice(&[0x2F, 0xFC, 0xFF, 0xFF]);
+        p[4] = 0xFE;
+        let mut exp = p;
+        exp[0] -= 1;
+        let mut one = vec![0u8; 32];
+        one[0] = 1;
+        let base = [0x5Au8; 32];
+        assert_eq!(pow_mod(&base, &exp, &p), Some(one));
+
+        // (p - 1)^2 = 1 mod p
+        assert_eq!(mul_mod(&exp, &exp, &p).map(|r| r[0]), Some(1));
+        assert_eq!(add_mod(&exp, &exp, &p), {
+            let mut r = exp.to_vec();
+            r[0] -= 1;
+            Some(r)
+        });
+    }
+}
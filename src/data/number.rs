@@ -247,6 +247,66 @@ impl From<&IntLayout> for Layout {
     fn from(layout: &IntLayout) -> Self { Layout::Integer(*layout) }
 }
 
+/// Layout of the fixed-point value encoding.
+///
+/// Fixed-point numbers are stored in registers as integers scaled by `2^frac`, i.e. the lowest
+/// `frac` bits keep the fractional part of the value. Since the register representation is the
+/// same as for the underlying integer layout, fixed-point values are converted into
+/// [`Layout::Integer`] once put into a register; the number of fractional bits is provided by the
+/// instructions performing fixed-point arithmetics.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[display("{int}.{frac}")]
+pub struct FixedLayout {
+    /// Layout of the underlying integer
+    pub int: IntLayout,
+
+    /// Number of bits used by the fractional part
+    pub frac: u16,
+}
+
+impl FixedLayout {
+    /// Returns signed fixed-point layout
+    #[inline]
+    pub fn signed(bytes: u16, frac: u16) -> FixedLayout {
+        FixedLayout { int: IntLayout::signed(bytes), frac }
+    }
+
+    /// Returns unsigned fixed-point layout
+    #[inline]
+    pub fn unsigned(bytes: u16, frac: u16) -> FixedLayout {
+        FixedLayout { int: IntLayout::unsigned(bytes), frac }
+    }
+
+    /// Detects whether the number of fractional bits is less than the bit dimension of the
+    /// underlying integer layout.
+    #[inline]
+    pub fn is_valid(self) -> bool { self.frac < self.int.bits() }
+}
+
+impl NumberLayout for FixedLayout {
+    #[inline]
+    fn bytes(self) -> u16 { self.int.bytes() }
+
+    #[inline]
+    fn is_signed(self) -> bool { self.int.is_signed() }
+
+    #[inline]
+    fn sign_bit(self) -> u16 { self.int.sign_bit() }
+
+    #[inline]
+    fn sign_byte(self) -> u16 { self.int.sign_byte() }
+}
+
+impl From<FixedLayout> for Layout {
+    #[inline]
+    fn from(layout: FixedLayout) -> Self { Layout::Integer(layout.int) }
+}
+
+impl From<&FixedLayout> for Layout {
+    #[inline]
+    fn from(layout: &FixedLayout) -> Self { Layout::Integer(layout.int) }
+}
+
 /// Layout of the float value encoding.
 ///
 /// Defines bit dimensionality and encoding format for float types.
@@ -663,7 +723,7 @@ impl Number {
 
     /// Returns the number of zeros in the binary representation of `self`.
     #[inline]
-    pub fn count_zeros(&self) -> u16 { self.len() - self.count_ones() }
+    pub fn count_zeros(&self) -> u16 { self.len() * 8 - self.count_ones() }
 
     /// Returns the number of ones in the binary representation of `self`.
     pub fn count_ones(&self) -> u16 {
@@ -736,6 +796,18 @@ impl Number {
         }
     }
 
+    /// Brings float value into the canonical form used by AluVM registers: `NaN` values are
+    /// converted into `None` and negative zero is replaced with positive zero. Integer and tapered
+    /// float values are returned unchanged.
+    pub fn canonicalized(self) -> Option<Number> {
+        match self.layout {
+            Layout::Integer(_) | Layout::Float(FloatLayout::FloatTapered) => Some(self),
+            Layout::Float(_) if self.is_nan() => None,
+            Layout::Float(_) if self.is_zero() => self.without_sign(),
+            Layout::Float(_) => Some(self),
+        }
+    }
+
     /// Detects if the value is equal to the maximum possible value for the used layout. For floats,
     /// always `false`.
     pub fn is_max(self) -> bool {
@@ -880,6 +952,32 @@ impl Number {
         self.reshape(to).then(|| self).or(if wrap { Some(self) } else { None })
     }
 
+    /// Converts integer value into another integer layout, extending the sign of negative values.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the value is a float, or if it can't be represented with the layout (including
+    /// negative values converted into unsigned layouts).
+    pub fn fitted(self, to: IntLayout) -> Option<Number> {
+        if !self.layout.is_integer() {
+            return None;
+        }
+        let negative = self.is_negative();
+        if negative && !to.signed {
+            return None;
+        }
+        let fill = if negative { 0xFF } else { 0x00 };
+        let (bytes, len) = (self.as_ref(), to.bytes as usize);
+        let common = len.min(bytes.len());
+        let mut data = [fill; 1024];
+        data[..common].copy_from_slice(&bytes[..common]);
+        let sign_kept = !to.signed || len == 0 || (data[len - 1] & 0x80 != 0) == negative;
+        if !sign_kept || bytes[common..].iter().any(|byte| *byte != fill) {
+            return None;
+        }
+        Number::with(&data[..len], to)
+    }
+
     #[doc(hidden)]
     /// Converts the value into `u1024` integer with the bytes corresponding to the internal
     /// representation.
@@ -913,6 +1011,25 @@ pub enum LiteralParseError {
     UnknownLiteral(String),
 }
 
+/// Errors converting numbers to and from strict values
+#[cfg(feature = "strict_types")]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum StrictValError {
+    /// float number of {0} layout has no strict value representation
+    Float(FloatLayout),
+
+    /// strict value is not a number
+    NotNumber,
+
+    /// strict value is not an optional number
+    NotOptional,
+
+    /// strict value doesn't fit into {0} integer layout
+    Overflow(IntLayout),
+}
+
 impl FromStr for Number {
     type Err = LiteralParseError;
 
@@ -1132,7 +1249,9 @@ macro_rules! impl_number_bytes_conv {
                     len <= $len,
                     "attempt to convert number into a byte array with incorrect length",
                 );
-                let mut bytes = [0u8; $len];
+                let mut bytes =
+                    [if val.layout.is_signed_int() && val.is_negative() { 0xFF } else { 0x00 };
+                        $len];
                 bytes[..len].copy_from_slice(&val.bytes[..len]);
                 bytes
             }
@@ -1315,7 +1434,11 @@ impl_number_int_conv!(i256, 32, true);
 impl_number_int_conv!(i512, 64, true);
 impl_number_int_conv!(i1024, 128, true);
 
-/// Value for step instructions which can be displayed as a part of operation mnemonic
+/// Value for step instructions which can be displayed as a part of operation mnemonic.
+///
+/// Alternative formatting (`{:#}`) produces the mnemonic (`inc`, `dec`, `add` or `sub`), while the
+/// normal formatting produces an operand suffix (`,` followed by the absolute step value), which is
+/// empty for `inc` and `dec` mnemonics.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, From)]
 pub struct Step(#[from] i8);
 
@@ -1343,15 +1466,125 @@ impl Display for Step {
                 x if x >= 0 => f.write_str("add"),
                 _ => unreachable!(),
             }
-        } else if val.abs() > 1 {
-            Display::fmt(&if val >= 0 { val } else { -val }, f)?;
-            f.write_char(',')
+        } else if val.unsigned_abs() != 1 {
+            f.write_char(',')?;
+            Display::fmt(&val.unsigned_abs(), f)
         } else {
             Ok(())
         }
     }
 }
 
+#[cfg(feature = "strict_types")]
+mod _strict_types {
+    use core::convert::TryFrom;
+
+    use strict_types::value::{EnumTag, StrictNum};
+    use strict_types::StrictVal;
+
+    use super::*;
+
+    impl TryFrom<Number> for StrictNum {
+        type Error = StrictValError;
+
+        /// Converts integer number into the smallest of 128- or 1024-bit strict number
+        /// representations matching its signedness.
+        fn try_from(num: Number) -> Result<Self, Self::Error> {
+            let layout = match num.layout {
+                Layout::Integer(layout) => layout,
+                Layout::Float(layout) => return Err(StrictValError::Float(layout)),
+            };
+            let (short, long) = match layout.signed {
+                true => (IntLayout::signed(16), IntLayout::signed(128)),
+                false => (IntLayout::unsigned(16), IntLayout::unsigned(128)),
+            };
+            Ok(match (num.fitted(short), num.fitted(long), layout.signed) {
+                (Some(n), _, true) => StrictNum::Int(n.into()),
+                (Some(n), _, false) => StrictNum::Uint(n.into()),
+                (None, Some(n), true) => StrictNum::BigInt(n.into()),
+                (None, Some(n), false) => StrictNum::BigUint(n.into()),
+                (None, None, _) => return Err(StrictValError::Overflow(long)),
+            })
+        }
+    }
+
+    impl TryFrom<Number> for StrictVal {
+        type Error = StrictValError;
+
+        fn try_from(num: Number) -> Result<Self, Self::Error> {
+            StrictNum::try_from(num).map(StrictVal::Number)
+        }
+    }
+
+    impl TryFrom<MaybeNumber> for StrictVal {
+        type Error = StrictValError;
+
+        /// Converts the value into a strict optional, using `None` for unset values.
+        fn try_from(num: MaybeNumber) -> Result<Self, Self::Error> {
+            Ok(match num.0 {
+                Some(num) => StrictVal::some(StrictVal::try_from(num)?),
+                None => StrictVal::none(),
+            })
+        }
+    }
+
+    impl Number {
+        /// Constructs integer number of a given layout from a strict number, failing if the value
+        /// doesn't fit the layout.
+        pub fn from_strict_num(
+            num: StrictNum,
+            layout: IntLayout,
+        ) -> Result<Number, StrictValError> {
+            let num = match num {
+                StrictNum::Uint(val) => Number::from(val),
+                StrictNum::BigUint(val) => Number::from(val),
+                StrictNum::Int(val) => Number::from(val),
+                StrictNum::BigInt(val) => Number::from(val),
+                _ => return Err(StrictValError::NotNumber),
+            };
+            num.fitted(layout).ok_or(StrictValError::Overflow(layout))
+        }
+
+        /// Constructs integer number of a given layout from a strict value holding a number,
+        /// which may be wrapped into newtypes.
+        pub fn from_strict_val(
+            val: &StrictVal,
+            layout: IntLayout,
+        ) -> Result<Number, StrictValError> {
+            match val.skip_wrapper() {
+                StrictVal::Number(num) => Number::from_strict_num(*num, layout),
+                _ => Err(StrictValError::NotNumber),
+            }
+        }
+    }
+
+    impl MaybeNumber {
+        /// Constructs register value of a given integer layout from a strict optional number.
+        pub fn from_strict_val(
+            val: &StrictVal,
+            layout: IntLayout,
+        ) -> Result<MaybeNumber, StrictValError> {
+            match val.skip_wrapper() {
+                StrictVal::Union(EnumTag::Ord(0), unit) if **unit == StrictVal::Unit => {
+                    Ok(MaybeNumber::none())
+                }
+                StrictVal::Union(EnumTag::Name(name), unit)
+                    if name.as_str() == "None" && **unit == StrictVal::Unit =>
+                {
+                    Ok(MaybeNumber::none())
+                }
+                StrictVal::Union(EnumTag::Ord(1), val) => {
+                    Number::from_strict_val(val, layout).map(MaybeNumber::some)
+                }
+                StrictVal::Union(EnumTag::Name(name), val) if name.as_str() == "Some" => {
+                    Number::from_strict_val(val, layout).map(MaybeNumber::some)
+                }
+                _ => Err(StrictValError::NotOptional),
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1359,6 +1592,7 @@ mod tests {
     #[test]
     fn bytes_conv_test() {
         assert_eq!([255u8], <[u8; 1]>::from(Number::from(255u8)));
+        assert_eq!([0x80, 0xFF, 0xFF, 0xFF], <[u8; 4]>::from(Number::from(-128i32)));
     }
 
     #[test]
@@ -1391,6 +1625,17 @@ mod tests {
         assert_eq!(float_layout.bytes(), 2);
     }
 
+    #[test]
+    fn fixed_layout() {
+        let layout = FixedLayout::signed(8, 32);
+        assert!(layout.is_valid());
+        assert_eq!(layout.bytes(), 8);
+        assert_eq!(layout.to_string(), "i64.32");
+        assert_eq!(Layout::from(layout), Layout::signed(8));
+        assert!(FixedLayout::unsigned(1, 7).is_valid());
+        assert!(!FixedLayout::unsigned(1, 8).is_valid());
+    }
+
     #[test]
     fn is_zero_test() {
         let num = Number::from(0);
@@ -1467,4 +1712,71 @@ mod tests {
         assert_eq!(z.into_unsigned(), z);
         assert_eq!(z.into_signed(), z);
     }
+
+    #[cfg(feature = "strict_types")]
+    #[test]
+    fn strict_values() {
+        use core::convert::TryFrom;
+
+        use strict_types::value::StrictNum;
+        use strict_types::StrictVal;
+
+        assert_eq!(StrictNum::try_from(Number::from(-5i8)), Ok(StrictNum::Int(-5)));
+        assert_eq!(StrictNum::try_from(Number::from(250u8)), Ok(StrictNum::Uint(250)));
+        assert_eq!(
+            StrictNum::try_from(Number::from(u256::MAX)),
+            Ok(StrictNum::BigUint(u256::MAX.into()))
+        );
+        let big = i1024::from(i128::MIN) - i1024::from(1);
+        assert_eq!(StrictNum::try_from(Number::from(big)), Ok(StrictNum::BigInt(big)));
+        assert_eq!(
+            StrictNum::try_from(MaybeNumber::from(ieee::Single::SMALLEST).unwrap()),
+            Err(StrictValError::Float(FloatLayout::IeeeSingle))
+        );
+        let huge = Number::masked_bit(1100, Layout::unsigned(256));
+        assert_eq!(
+            StrictNum::try_from(huge),
+            Err(StrictValError::Overflow(IntLayout::unsigned(128)))
+        );
+
+        let val = StrictVal::newtype(StrictVal::num(-5i8));
+        assert_eq!(Number::from_strict_val(&val, IntLayout::signed(2)), Ok(Number::from(-5i16)));
+        assert_eq!(
+            Number::from_strict_val(&val, IntLayout::unsigned(2)),
+            Err(StrictValError::Overflow(IntLayout::unsigned(2)))
+        );
+        assert_eq!(
+            Number::from_strict_val(&StrictVal::num(256u16), IntLayout::unsigned(1)),
+            Err(StrictValError::Overflow(IntLayout::unsigned(1)))
+        );
+        assert_eq!(
+            Number::from_strict_val(&StrictVal::str("1"), IntLayout::unsigned(1)),
+            Err(StrictValError::NotNumber)
+        );
+        for num in [Number::from(u256::MAX), Number::from(big), Number::from(-1i64)] {
+            let layout = match num.layout() {
+                Layout::Integer(layout) => layout,
+                Layout::Float(_) => unreachable!(),
+            };
+            let val = StrictVal::try_from(num).unwrap();
+            assert_eq!(Number::from_strict_val(&val, layout), Ok(num));
+        }
+
+        let some = StrictVal::try_from(MaybeNumber::from(7u32)).unwrap();
+        assert_eq!(some, StrictVal::some(StrictVal::num(7u32)));
+        assert_eq!(
+            MaybeNumber::from_strict_val(&some, IntLayout::unsigned(4)),
+            Ok(MaybeNumber::from(7u32))
+        );
+        let none = StrictVal::try_from(MaybeNumber::none()).unwrap();
+        assert_eq!(none, StrictVal::none());
+        assert_eq!(
+            MaybeNumber::from_strict_val(&none, IntLayout::unsigned(4)),
+            Ok(MaybeNumber::none())
+        );
+        assert_eq!(
+            MaybeNumber::from_strict_val(&StrictVal::num(7u32), IntLayout::unsigned(4)),
+            Err(StrictValError::NotOptional)
+        );
+    }
 }
@@ -0,0 +1,454 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured representations of library code and program execution for external tooling.
+//!
+//! [`Listing`] describes the disassembled library code, [`Cfg`] its control flow graph and
+//! [`Trace`] the sequence of instructions executed by a program run. With the `serde` feature all
+//! of them can be serialized with serde, and with the `json` feature exported as JSON with their
+//! `to_json` methods, such that IDEs and code explorers don't need to parse the text listings.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::data::MaybeNumber;
+use crate::isa::InstructionSet;
+use crate::library::{DisassembleError, Lib, LibId, LibSite, SourceSpan};
+use crate::{Execution, Program, RunError, Vm};
+
+/// Disassembled instruction
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct InstrInfo {
+    /// Offset of the instruction in the code segment
+    pub pos: u16,
+
+    /// Number of bytes occupied by the instruction in the code segment
+    pub len: u16,
+
+    /// Instruction opcode
+    pub opcode: u8,
+
+    /// Instruction in assembly format
+    pub asm: String,
+
+    /// Offset in the code segment to which the instruction jumps, if any
+    pub jump_to: Option<u16>,
+
+    /// Location in other library called by the instruction, if any
+    pub call: Option<LibSite>,
+}
+
+impl InstrInfo {
+    fn with<Isa>(pos: u16, instr: &Isa) -> Self
+    where
+        Isa: InstructionSet,
+    {
+        InstrInfo {
+            pos,
+            len: instr.byte_count(),
+            opcode: instr.instr_byte(),
+            asm: instr.to_string(),
+            jump_to: instr.jump_destination(pos),
+            call: instr.call_site(),
+        }
+    }
+}
+
+/// Disassembled library code
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct Listing {
+    /// Id of the disassembled library
+    pub lib: LibId,
+
+    /// ISA extensions used by the library
+    pub isae: String,
+
+    /// Instructions ordered by their offsets
+    pub code: Vec<InstrInfo>,
+}
+
+impl Listing {
+    /// Disassembles library code with the instruction set `Isa`.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set.
+    pub fn with<Isa>(lib: &Lib) -> Result<Self, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let code = lib
+            .disassemble_iter::<Isa>()
+            .map(|res| res.map(|(pos, instr)| InstrInfo::with(pos, &instr)))
+            .collect::<Result<_, _>>()?;
+        Ok(Listing { lib: lib.id(), isae: lib.isae_segment(), code })
+    }
+}
+
+/// Sequence of instructions which is entered only at its first instruction and left only after
+/// its last instruction.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct BasicBlock {
+    /// Offset of the first instruction of the block
+    pub start: u16,
+
+    /// Offset following the last instruction of the block
+    pub end: u16,
+
+    /// Offsets of the blocks to which the execution may pass after the block. Offsets outside of
+    /// the code segment are not included.
+    pub successors: BTreeSet<u16>,
+
+    /// Locations in other libraries called by the block instructions
+    pub calls: Vec<LibSite>,
+}
+
+/// Control flow graph of library code
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct Cfg {
+    /// Id of the library
+    pub lib: LibId,
+
+    /// Basic blocks ordered by their offsets
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    /// Splits library code, decoded with the instruction set `Isa`, into basic blocks.
+    ///
+    /// Blocks start at the code offset 0, at jump destinations and after the instructions which
+    /// jump or never pass the execution to the following instruction.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set.
+    pub fn with<Isa>(lib: &Lib) -> Result<Self, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let code = lib.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        let code_len = lib.code.len();
+
+        let mut leaders = BTreeSet::from([0u16]);
+        for (pos, instr) in &code {
+            let next = pos.saturating_add(instr.byte_count());
+            let targets = instr.jump_destinations(*pos);
+            if !targets.is_empty() {
+                leaders.extend(targets);
+                leaders.insert(next);
+            } else if instr.is_terminal() {
+                leaders.insert(next);
+            }
+        }
+
+        let mut blocks = Vec::<BasicBlock>::new();
+        for (pos, instr) in &code {
+            let end = pos.saturating_add(instr.byte_count());
+            match blocks.last_mut() {
+                Some(block) if !leaders.contains(pos) => block.end = end,
+                _ => blocks.push(BasicBlock {
+                    start: *pos,
+                    end,
+                    successors: BTreeSet::new(),
+                    calls: Vec::new(),
+                }),
+            }
+            let block = blocks.last_mut().expect("block is created above");
+            block.calls.extend(instr.call_site());
+            block.successors.extend(instr.jump_destinations(*pos));
+            if !instr.is_terminal() && leaders.contains(&end) {
+                block.successors.insert(end);
+            }
+        }
+        for block in &mut blocks {
+            block.successors.retain(|pos| (*pos as usize) < code_len);
+        }
+
+        Ok(Cfg { lib: lib.id(), blocks })
+    }
+
+    /// Renders control flow graph of library code, decoded with the instruction set `Isa`, in the
+    /// Graphviz DOT format for documentation and audit reports.
+    ///
+    /// Each basic block is a node listing its instructions; edges taken by jumps are labelled
+    /// `jump`, and calls to other libraries are shown as dashed edges to the called locations.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set.
+    pub fn to_dot<Isa>(lib: &Lib) -> Result<String, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let code = lib.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        let cfg = Cfg::with::<Isa>(lib)?;
+
+        let mut dot = format!("digraph \"{}\" {{\n", dot_escape(&cfg.lib.to_string()));
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        let mut calls = BTreeSet::new();
+        for block in &cfg.blocks {
+            let instrs = code.iter().filter(|(pos, _)| (block.start..block.end).contains(pos));
+            let mut label = String::new();
+            let mut jumps = Vec::new();
+            for (pos, instr) in instrs {
+                label.push_str(&format!("{:04X}: {}\\l", pos, dot_escape(&instr.to_string())));
+                jumps = instr.jump_destinations(*pos);
+            }
+            dot.push_str(&format!("    b{:04X} [label=\"{}\"];\n", block.start, label));
+            for to in &block.successors {
+                let attrs = if jumps.contains(to) { " [label=\"jump\"]" } else { "" };
+                dot.push_str(&format!("    b{:04X} -> b{:04X}{};\n", block.start, to, attrs));
+            }
+            for site in &block.calls {
+                let site = dot_escape(&site.to_string());
+                dot.push_str(&format!(
+                    "    b{:04X} -> \"{}\" [style=dashed];\n",
+                    block.start, site
+                ));
+                calls.insert(site);
+            }
+        }
+        for site in calls {
+            dot.push_str(&format!("    \"{}\" [shape=ellipse];\n", site));
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+/// Escapes the string for use inside of a quoted Graphviz DOT identifier.
+fn dot_escape(s: &str) -> String { s.replace('\\', "\\\\").replace('"', "\\\"") }
+
+/// Instruction executed by a program
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct TraceStep {
+    /// Location of the instruction
+    pub site: LibSite,
+
+    /// Instruction in assembly format
+    pub asm: String,
+
+    /// Value of `st0` register after the instruction execution
+    pub st0: bool,
+
+    /// Span of the instruction source, if known from the library
+    /// [`SourceMap`](crate::library::SourceMap)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Option<SourceSpan>,
+}
+
+/// Instructions executed by a program run
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct Trace {
+    /// Executed instructions in the order of their execution
+    pub steps: Vec<TraceStep>,
+
+    /// Value of `st0` register at the program completion
+    pub st0: bool,
+}
+
+impl Trace {
+    /// Runs the program from the `method` location, recording each of the executed instructions.
+    ///
+    /// The program is executed step by step with [`Vm::start`] and [`Vm::resume`], and the run
+    /// limits set in the virtual machine registers are respected, thus they should be used to
+    /// bound the length of the traces of untrusted programs. Host function calls (see
+    /// [`HostOp`](crate::isa::HostOp)) are not serviced and return no value. Steps are annotated
+    /// with the source spans of the instructions kept in the library debug information.
+    ///
+    /// # Errors
+    ///
+    /// Errors returned by [`Vm::start`] and [`Vm::resume`].
+    pub fn record<Isa>(
+        vm: &mut Vm<Isa>,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        context: &Isa::Context<'_>,
+    ) -> Result<Self, RunError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut steps = Vec::new();
+        let mut site = method;
+        let mut execution = vm.start(program, method, 1, context)?;
+        loop {
+            let lib = program.lib(site.lib);
+            let asm = lib
+                .and_then(|lib| lib.instr_at::<Isa>(site.pos))
+                .map(|instr| instr.to_string())
+                .unwrap_or_default();
+            let span = lib
+                .and_then(|lib| lib.debug.as_ref())
+                .and_then(|debug| debug.spans.span_at(site.pos))
+                .cloned();
+            match execution {
+                Execution::Complete(st0) => {
+                    steps.push(TraceStep { site, asm, st0, span });
+                    return Ok(Trace { steps, st0 });
+                }
+                Execution::Suspended(state) | Execution::Yielded { state, .. } => {
+                    steps.push(TraceStep { site, asm, st0: state.registers.st0, span });
+                    site = state.site;
+                    execution = vm.resume(program, state, 1, context)?;
+                }
+                Execution::Pending(call) => {
+                    let state = call.complete(MaybeNumber::none());
+                    steps.push(TraceStep { site, asm, st0: state.registers.st0, span });
+                    site = state.site;
+                    execution = vm.resume(program, state, 1, context)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+macro_rules! impl_to_json {
+    ($($ty:ty),+) => { $(
+        impl $ty {
+            /// Serializes the value into JSON.
+            pub fn to_json(&self) -> String {
+                serde_json::to_string(self).expect("serialization into JSON never fails")
+            }
+        }
+    )+ };
+}
+
+#[cfg(feature = "json")]
+impl_to_json!(Listing, Cfg, Trace);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::Instr;
+    use crate::Prog;
+
+    const SOURCE: &str = "
+        put     a8[0],3
+        dec     a8[0]
+        put     a8[1],0
+        eq.n    a8[0],a8[1]
+        jif     0x0014
+        jmp     0x0004
+        succ
+    ";
+
+    #[test]
+    fn listing() {
+        let lib = Lib::assemble_source::<Instr>(SOURCE).unwrap();
+        let listing = Listing::with::<Instr>(&lib).unwrap();
+        assert_eq!(listing.lib, lib.id());
+        assert_eq!(listing.code.len(), 7);
+        assert_eq!(listing.code[4].asm, "jif     0x0014");
+        assert_eq!(listing.code[4].jump_to, Some(0x0014));
+        assert_eq!(
+            listing.code.iter().map(|instr| instr.len).sum::<u16>() as usize,
+            lib.code.len()
+        );
+    }
+
+    #[test]
+    fn cfg() {
+        let lib = Lib::assemble_source::<Instr>(SOURCE).unwrap();
+        let cfg = Cfg::with::<Instr>(&lib).unwrap();
+        let blocks = cfg
+            .blocks
+            .iter()
+            .map(|block| (block.start, block.end, block.successors.iter().copied().collect()))
+            .collect::<Vec<(u16, u16, Vec<u16>)>>();
+        assert_eq!(blocks, [
+            (0x0000, 0x0004, vec![0x0004]),
+            (0x0004, 0x0011, vec![0x0011, 0x0014]),
+            (0x0011, 0x0014, vec![0x0004]),
+            (0x0014, 0x0015, vec![]),
+        ]);
+    }
+
+    #[test]
+    fn dot() {
+        let lib = Lib::assemble_source::<Instr>(SOURCE).unwrap();
+        let dot = Cfg::to_dot::<Instr>(&lib).unwrap();
+        assert!(dot.starts_with(&format!("digraph \"{}\" {{\n", lib.id())));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    b0000 [label=\"0000: put     a8[0],3\\l\"];\n"));
+        assert!(dot.contains("    b0004 -> b0011;\n"));
+        assert!(dot.contains("    b0004 -> b0014 [label=\"jump\"];\n"));
+        assert!(dot.contains("    b0011 -> b0004 [label=\"jump\"];\n"));
+        assert!(!dot.contains("b0014 ->"));
+
+        let lib = Lib::assemble_source::<Instr>("put s16[0],\"a\\\"b\"\nsucc").unwrap();
+        let dot = Cfg::to_dot::<Instr>(&lib).unwrap();
+        assert!(dot.contains(r#"s16[0],\"a\\\"b\"\l"#), "{}", dot);
+    }
+
+    #[test]
+    fn trace() {
+        let lib = Lib::assemble_source::<Instr>(SOURCE).unwrap();
+        let method = LibSite::with(0, lib.id());
+        let prog = Prog::<Instr>::new(lib);
+        let trace = Trace::record(&mut Vm::new(), &prog, method, &()).unwrap();
+        assert!(trace.st0);
+        assert_eq!(trace.steps.len(), 1 + 3 * 5 - 1 + 1);
+        assert_eq!(trace.steps[0].site, method);
+        assert_eq!(trace.steps.last().unwrap().asm, "succ");
+    }
+
+    #[test]
+    fn trace_spans() {
+        use crate::isa::ControlFlowOp;
+        use crate::library::{LibBuilder, SourceSpan};
+
+        let lib = LibBuilder::<Instr>::new()
+            .push(Instr::Nop)
+            .push_spanned(
+                Instr::ControlFlow(ControlFlowOp::Fail),
+                SourceSpan::with("lib.src", 7, 3, 5),
+            )
+            .build()
+            .unwrap();
+        let method = LibSite::with(0, lib.id());
+        let prog = Prog::<Instr>::new(lib);
+        let trace = Trace::record(&mut Vm::new(), &prog, method, &()).unwrap();
+        assert!(!trace.st0);
+        assert_eq!(trace.steps[0].span, None);
+        assert_eq!(trace.steps[1].span.as_ref().map(ToString::to_string).unwrap(), "lib.src:7:3");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        let lib = Lib::assemble_source::<Instr>("put a8[0],1\nsucc").unwrap();
+        let listing = Listing::with::<Instr>(&lib).unwrap();
+        let json = listing.to_json();
+        assert!(json.contains(r#""asm":"succ""#));
+        assert_eq!(serde_json::from_str::<Listing>(&json).unwrap(), listing);
+    }
+}
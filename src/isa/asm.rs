@@ -0,0 +1,2613 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! AluVM assembly syntax.
+//!
+//! Each instruction is represented by a single line of text, produced by `Display` implementation
+//! of [`Instr`] and parsed by its `FromStr` implementation. The grammar is the following:
+//!
+//! ```text
+//! instr    := mnemonic [ '.' flags ] [ ws+ operand *( ',' operand ) ]
+//! operand  := register | number | data | offset | shift | site | bool
+//! register := family '[' index ']'             ; a8[0], f32[3], r256[31], s16[15]
+//! number   := '~' | int | float                ; values for `put` and `putif`
+//! int      := [ '-' ] ( dec | '0x' hex | '0o' oct | '0b' bin )
+//! float    := decimal or hexadecimal (`0x1.8p+1`) floating point | 'inf' | '-inf' | 'nan'
+//! data     := '"' *( printable ascii | escape ) '"'
+//! escape   := '\\' | '\"' | '\0' | '\t' | '\n' | '\r' | '\x' hex hex
+//! offset   := dec | '0x' hex                   ; code offsets for jumps
+//! shift    := ( '+' | '-' ) offset             ; displacements for relative jumps
+//! site     := offset ws* '@' ws* lib-id        ; library site for calls
+//! bool     := 'true' | 'false'
+//! ```
+//!
+//! Whitespaces around operands are ignored; canonical form produced by `Display` pads mnemonic
+//! with its flags to 8 characters and puts no whitespaces between operands.
+//!
+//! Integer values are displayed in decimal form if they fit into 64 bits, and as hexadecimal
+//! otherwise. Float values are displayed using the shortest decimal representation which is
+//! parsed back into the same value, except 512-bit tapered floats, which are displayed as a
+//! hexadecimal representation of their bits. When parsed, a hexadecimal literal put into a float
+//! register without an exponent (`p`) is always treated as a raw bit representation of the value.
+//!
+//! Byte strings (the data put into `s16` registers) are displayed using [`ByteStr`] `Display`
+//! implementation, which produces `data` literal. The flag of [`BytesOp::Put`] indicating that the
+//! string was not completely read from the data segment has no representation in the assembly (as
+//! well as in the bytecode) and is always parsed as `false`.
+//!
+//! # Assembly source
+//!
+//! Multi-line assembly source is parsed with [`LiteralPool::parse_source`]. Each non-empty line
+//! contains either an instruction, a comment starting with `;`, or a string literal declaration:
+//!
+//! ```text
+//! decl     := '.str' ws+ name ws+ data         ; .str greeting "hello"
+//! name     := ( alpha | '_' ) *( alnum | '_' )
+//! operand  := ... | '$' name                   ; put s16[0],$greeting
+//! ```
+//!
+//! A declared literal may be referenced by any number of instructions in place of a `data`
+//! operand. Since the assembler writes each distinct byte string into the library data segment
+//! only once, all instructions referencing the same literal share a single data segment offset.
+//!
+//! A literal may also be declared as a constant table, which is placed into the data segment at an
+//! offset aligned to the given number of bytes; all instructions referencing the literal use the
+//! table (see [`Lib::assemble_with_tables`](crate::library::Lib::assemble_with_tables)):
+//!
+//! ```text
+//! table    := '.table' ws+ name ws+ align ws+ data   ; .table key 32 0x0102...
+//! ```
+//!
+//! A line may also export a routine starting at the instruction which follows it, adding the
+//! routine to the library routine table (see [`Lib::entrypoint`](crate::library::Lib::entrypoint)):
+//!
+//! ```text
+//! entry    := '.entry' ws+ name                ; .entry verify
+//! ```
+//!
+//! Jump destinations may be given by labels, which mark the instruction following them. A label
+//! may be referenced before its declaration, and is resolved into the code offset for absolute
+//! jumps or into the displacement from the jump instruction for relative ones:
+//!
+//! ```text
+//! label    := name ':'                         ; loop:
+//! operand  := ... | name                       ; jif loop
+//! ```
+//!
+//! Registers may be given names of the variables they keep. The names don't affect the assembled
+//! code and are only kept in the debug information of the instrumented builds (see
+//! [`Lib::assemble_instrumented`](crate::library::Lib::assemble_instrumented)):
+//!
+//! ```text
+//! var      := '.var' ws+ name ws+ register      ; .var counter a8[0]
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Display, Formatter, Write};
+use core::str::FromStr;
+
+use amplify::num::apfloat::ieee;
+
+use super::opcodes::{INSTR_RESV_FROM, INSTR_RESV_TO};
+#[cfg(feature = "bitcoin")]
+use super::BitcoinOp;
+#[cfg(feature = "curve25519")]
+use super::Curve25519Op;
+#[cfg(feature = "secp256k1")]
+use super::Secp256k1Op;
+use super::{
+    ArithmeticOp, BitwiseOp, Bytecode, BytesOp, CmpOp, ControlFlowOp, CtOp, DataOp, DigestOp,
+    EncOp, EnvOp, Extensions, FieldOp, FixedOp, Flag, FrameOp, HostOp, ImmOp, Instr,
+    InstructionSet, LoopOp, ModOp, MoveOp, ParseFlagError, PrngOp, PutOp, RelJumpOp, ReservedOp,
+    StackOp, StepOp, SwitchOp, XferOp,
+};
+use crate::data::{
+    ByteStr, FloatLayout, Layout, LiteralParseError, MaybeNumber, Number, NumberLayout, Step,
+};
+use crate::library::{AssemblerError, DataTable, DebugInfo, LibId, LibSite, RoutineError};
+#[cfg(any(feature = "secp256k1", feature = "curve25519"))]
+use crate::reg::RegBlockAR;
+use crate::reg::{
+    NumericRegister, Reg, Reg16, Reg32, Reg8, RegA, RegA2, RegAF, RegAR, RegF, RegR, RegS,
+};
+
+/// Errors parsing AluVM assembly instructions
+#[derive(Clone, Eq, PartialEq, Debug, Display, From)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum ParseInstrError {
+    /// empty instruction string
+    Empty,
+
+    /// unknown instruction mnemonic `{0}`
+    UnknownMnemonic(String),
+
+    /// instruction `{0}` does not take flags
+    UnexpectedFlags(String),
+
+    /// instruction `{mnemonic}` requires {expected} operands, while {found} operands are provided
+    OperandCount {
+        /// Instruction mnemonic
+        mnemonic: String,
+        /// Number of operands required by the instruction
+        expected: usize,
+        /// Number of provided operands
+        found: usize,
+    },
+
+    /// invalid operands `{1}` for the instruction `{0}`
+    InvalidOperands(String, String),
+
+    /// invalid operand `{0}`
+    InvalidOperand(String),
+
+    /// invalid data literal `{0}`
+    InvalidData(String),
+
+    /// value `{0}` does not fit into {1}-bit register
+    ValueOverflow(String, u16),
+
+    /// invalid library site `{0}`
+    InvalidSite(String),
+
+    /// invalid instruction flags. Details: {0}
+    #[from]
+    Flag(ParseFlagError),
+
+    /// invalid number literal. Details: {0}
+    #[from]
+    Literal(LiteralParseError),
+
+    /// invalid ISA extension instruction. Details: {0}
+    Extension(String),
+
+    /// invalid string literal name `{0}`
+    InvalidLiteralName(String),
+
+    /// string literal `${0}` is not declared
+    UnknownLiteral(String),
+
+    /// string literal `${0}` is already declared
+    DuplicateLiteral(String),
+
+    /// invalid routine name `{0}`
+    InvalidRoutineName(String),
+
+    /// routine `{0}` is already exported
+    DuplicateRoutine(String),
+
+    /// invalid label name `{0}`
+    InvalidLabelName(String),
+
+    /// label `{0}` is already declared
+    DuplicateLabel(String),
+
+    /// label `{0}` is used as an operand of instruction which does not jump
+    LabelOperand(String),
+
+    /// label `{0}` is out of range of the jump
+    LabelOutOfRange(String),
+
+    /// invalid register variable declaration `{0}`
+    InvalidVariable(String),
+}
+
+/// Errors parsing and assembling multi-line AluVM assembly source
+#[derive(Clone, Eq, PartialEq, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum SourceError {
+    /// error at line {line}: {source}
+    Parse {
+        /// Line number, starting from 1
+        line: usize,
+        /// Underlying parse error
+        source: ParseInstrError,
+    },
+
+    /// {0}
+    #[from]
+    Assembler(AssemblerError),
+
+    /// {0}
+    #[from]
+    Routine(RoutineError),
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for SourceError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            SourceError::Parse { source, .. } => Some(source),
+            SourceError::Assembler(err) => Some(err),
+            SourceError::Routine(err) => Some(err),
+        }
+    }
+}
+
+/// Pool of named string literals, which can be referenced by multiple `put` instructions of an
+/// assembly source using `$name` operand.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct LiteralPool {
+    literals: BTreeMap<String, ByteStr>,
+    tables: Vec<DataTable>,
+}
+
+impl LiteralPool {
+    /// Constructs empty literal pool
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Declares new named literal.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseInstrError::InvalidLiteralName`] if the name is not an identifier and
+    /// [`ParseInstrError::DuplicateLiteral`] if a literal with the same name is already declared.
+    pub fn declare(
+        &mut self,
+        name: impl Into<String>,
+        data: ByteStr,
+    ) -> Result<(), ParseInstrError> {
+        let name = name.into();
+        if !is_identifier(&name) {
+            return Err(ParseInstrError::InvalidLiteralName(name));
+        }
+        if self.literals.contains_key(&name) {
+            return Err(ParseInstrError::DuplicateLiteral(name));
+        }
+        self.literals.insert(name, data);
+        Ok(())
+    }
+
+    /// Declares new named literal, which is placed into the data segment as a constant table
+    /// aligned to `align` bytes (see
+    /// [`Lib::assemble_with_tables`](crate::library::Lib::assemble_with_tables)).
+    ///
+    /// # Errors
+    ///
+    /// The same as for [`LiteralPool::declare`].
+    pub fn declare_table(
+        &mut self,
+        name: impl Into<String>,
+        data: ByteStr,
+        align: u16,
+    ) -> Result<(), ParseInstrError> {
+        self.declare(name, data.clone())?;
+        self.tables.push(DataTable { data, align });
+        Ok(())
+    }
+
+    /// Returns literal with the given name, if it was declared.
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&ByteStr> { self.literals.get(name) }
+
+    /// Returns constant tables declared in the pool, in the order of their declaration.
+    #[inline]
+    pub fn tables(&self) -> &[DataTable] { &self.tables }
+
+    /// Constructs instruction putting named literal into a string register.
+    pub fn put<Extension>(&self, reg: RegS, name: &str) -> Result<Instr<Extension>, ParseInstrError>
+    where
+        Extension: InstructionSet,
+    {
+        let data =
+            self.get(name).ok_or_else(|| ParseInstrError::UnknownLiteral(name.to_owned()))?;
+        Ok(Instr::Bytes(BytesOp::Put(reg, Box::new(data.clone()), false)))
+    }
+
+    /// Parses single instruction, resolving `$name` operands into the declared literals.
+    pub fn parse_instr<Isa>(&self, s: &str) -> Result<Isa, ParseInstrError>
+    where
+        Isa: FromStr<Err = ParseInstrError>,
+    {
+        let line = Line::parse(s)?;
+        if !line.operands.iter().any(|op| op.starts_with('$')) {
+            return Isa::from_str(s);
+        }
+        let text = line.rewrite(|op| match op.strip_prefix('$') {
+            Some(name) => self
+                .get(name)
+                .map(ByteStr::to_string)
+                .ok_or_else(|| ParseInstrError::UnknownLiteral(name.to_owned())),
+            None => Ok(op.to_owned()),
+        })?;
+        Isa::from_str(&text)
+    }
+
+    /// Parses multi-line assembly source, adding literals declared by the source to the pool.
+    ///
+    /// Routines exported by the source with `.entry` declarations are ignored; use
+    /// [`LiteralPool::parse_routines`] to collect them.
+    pub fn parse_source<Isa>(&mut self, source: &str) -> Result<Vec<Isa>, SourceError>
+    where
+        Isa: Bytecode + FromStr<Err = ParseInstrError>,
+    {
+        self.parse_routines(source).map(|(code, _)| code)
+    }
+
+    /// Parses multi-line assembly source, adding literals declared by the source to the pool.
+    ///
+    /// # Returns
+    ///
+    /// Parsed instructions and the routines exported by the source, with the index of the
+    /// instruction each routine starts at.
+    pub fn parse_routines<Isa>(
+        &mut self,
+        source: &str,
+    ) -> Result<(Vec<Isa>, BTreeMap<String, usize>), SourceError>
+    where
+        Isa: Bytecode + FromStr<Err = ParseInstrError>,
+    {
+        self.parse_instrumented(source).map(|(code, routines, _)| (code, routines))
+    }
+
+    /// Parses multi-line assembly source, adding literals declared by the source to the pool.
+    ///
+    /// # Returns
+    ///
+    /// Parsed instructions, the routines exported by the source with the index of the instruction
+    /// each routine starts at, and debug information with the source lines of the instructions,
+    /// the labels and the register variables declared by the source.
+    #[allow(clippy::type_complexity)]
+    pub fn parse_instrumented<Isa>(
+        &mut self,
+        source: &str,
+    ) -> Result<(Vec<Isa>, BTreeMap<String, usize>, DebugInfo), SourceError>
+    where
+        Isa: Bytecode + FromStr<Err = ParseInstrError>,
+    {
+        let declared = source.lines().filter_map(label_decl).collect::<BTreeSet<_>>();
+        let mut code = Vec::<Isa>::new();
+        let mut routines = BTreeMap::new();
+        let mut labels = BTreeMap::new();
+        let mut debug = DebugInfo::new();
+        // Source line of each instruction
+        let mut lines = Vec::new();
+        // Line number, instruction index, number of operands and labels with their operand
+        // positions for each instruction jumping to labels
+        let mut jumps = Vec::new();
+        for (no, line) in source.lines().enumerate() {
+            let err = |source| SourceError::Parse { line: no + 1, source };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let directive = |name: &str| {
+                line.strip_prefix(name).filter(|rest| rest.starts_with(char::is_whitespace))
+            };
+            if let Some(decl) = directive(".str") {
+                let decl = decl.trim_start();
+                let (name, val) = decl
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| err(ParseInstrError::InvalidData(decl.to_owned())))?;
+                let data = data(val.trim()).map_err(err)?;
+                self.declare(name, data).map_err(err)?;
+            } else if let Some(decl) = directive(".table") {
+                let decl = decl.trim_start();
+                let invalid = || err(ParseInstrError::InvalidData(decl.to_owned()));
+                let (name, rest) = decl.split_once(char::is_whitespace).ok_or_else(invalid)?;
+                let (align, val) =
+                    rest.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+                let align = align
+                    .parse::<u16>()
+                    .map_err(|_| err(ParseInstrError::InvalidOperand(align.to_owned())))?;
+                let data = data(val.trim()).map_err(err)?;
+                self.declare_table(name, data, align).map_err(err)?;
+            } else if let Some(name) = directive(".entry") {
+                let name = name.trim();
+                if !is_identifier(name) {
+                    return Err(err(ParseInstrError::InvalidRoutineName(name.to_owned())));
+                }
+                if routines.insert(name.to_owned(), code.len()).is_some() {
+                    return Err(err(ParseInstrError::DuplicateRoutine(name.to_owned())));
+                }
+            } else if let Some(decl) = directive(".var") {
+                let invalid = || err(ParseInstrError::InvalidVariable(decl.trim().to_owned()));
+                let (name, reg) =
+                    decl.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+                let reg = register(reg.trim()).ok_or_else(invalid)?;
+                if !is_identifier(name) {
+                    return Err(invalid());
+                }
+                debug.vars.insert(reg, name.to_owned());
+            } else if let Some(name) = label_decl(line) {
+                if !is_identifier(name) || KEYWORDS.contains(&name) {
+                    return Err(err(ParseInstrError::InvalidLabelName(name.to_owned())));
+                }
+                if labels.insert(name, code.len()).is_some() {
+                    return Err(err(ParseInstrError::DuplicateLabel(name.to_owned())));
+                }
+            } else {
+                lines.push(no as u32 + 1);
+                let parsed = Line::parse(line).map_err(err)?;
+                let names = parsed
+                    .operands
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, op)| declared.contains(*op))
+                    .map(|(no, op)| (no, *op))
+                    .collect::<Vec<_>>();
+                if names.is_empty() {
+                    code.push(self.parse_instr(line).map_err(err)?);
+                } else {
+                    jumps.push((no + 1, code.len(), parsed.operands.len(), names));
+                    // Jump offsets are written once the code layout is known
+                    let text = parsed
+                        .rewrite(|op| {
+                            Ok(if declared.contains(op) { s!("0") } else { op.to_owned() })
+                        })
+                        .map_err(err)?;
+                    code.push(self.parse_instr(&text).map_err(err)?);
+                }
+            }
+        }
+
+        let mut offsets = Vec::with_capacity(code.len() + 1);
+        offsets.push(0u32);
+        for instr in &code {
+            offsets.push(offsets[offsets.len() - 1] + instr.byte_count() as u32);
+        }
+        for (line, index, count, names) in jumps {
+            let err = |source| SourceError::Parse { line, source };
+            for (no, (operand, name)) in names.into_iter().enumerate() {
+                let out_of_range = || err(ParseInstrError::LabelOutOfRange(name.to_owned()));
+                let (pos, target) = (offsets[index], offsets[labels[name]]);
+                let instr = &mut code[index];
+                let target = u16::try_from(target).map_err(|_| out_of_range())?;
+                // Jump table entries are the trailing operands of the instruction
+                if let Some(table) = instr.jump_table_mut() {
+                    let entry = (operand + table.len())
+                        .checked_sub(count)
+                        .and_then(|entry| table.get_mut(entry))
+                        .ok_or_else(|| err(ParseInstrError::LabelOperand(name.to_owned())))?;
+                    *entry = target;
+                } else if no > 0 {
+                    return Err(err(ParseInstrError::LabelOperand(name.to_owned())));
+                } else if let Some(offset) = instr.jump_target_mut() {
+                    *offset = target;
+                } else if let Some(shift) = instr.jump_displacement_mut() {
+                    *shift =
+                        i16::try_from(target as i64 - pos as i64).map_err(|_| out_of_range())?;
+                } else {
+                    return Err(err(ParseInstrError::LabelOperand(name.to_owned())));
+                }
+            }
+        }
+
+        debug.lines = lines.into_iter().enumerate().map(|(i, l)| (offsets[i] as u16, l)).collect();
+        debug.labels =
+            labels.into_iter().map(|(name, i)| (offsets[i] as u16, name.to_owned())).collect();
+
+        Ok((code, routines, debug))
+    }
+}
+
+/// Parses register of any family.
+fn register(op: &str) -> Option<Reg> {
+    a(op)
+        .map(|(reg, idx)| Reg::A(reg, idx))
+        .or_else(|| f(op).map(|(reg, idx)| Reg::F(reg, idx)))
+        .or_else(|| r(op).map(|(reg, idx)| Reg::R(reg, idx)))
+        .or_else(|| s(op).map(Reg::S))
+}
+
+/// Operands which can't be used as label names
+const KEYWORDS: [&str; 4] = ["true", "false", "inf", "nan"];
+
+/// Extracts name of the label declared by the source line, if the line is a label declaration.
+fn label_decl(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.starts_with(';') || line.starts_with('.') {
+        return None;
+    }
+    line.strip_suffix(':').map(str::trim_end)
+}
+
+/// Checks whether the name of a string literal or routine is a valid identifier, fitting into
+/// the 255-byte limit of the string encoding.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    name.len() <= u8::MAX as usize
+        && chars.next().map(|ch| ch.is_ascii_alphabetic() || ch == '_').unwrap_or_default()
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// Instruction text split into mnemonic, flags and operands.
+///
+/// Used for implementing [`FromStr`] for ISA extensions, such that their instructions follow the
+/// syntax of the core instructions (see the [`operand`] parsers for the operand syntax).
+///
+/// ```
+/// use aluvm::isa::Line;
+///
+/// let line = Line::parse("add.uc a8[1],a8[2]").unwrap();
+/// assert_eq!(line.mnemonic(), "add");
+/// assert_eq!(line.flags(), Some("uc"));
+/// assert_eq!(line.ops::<2>().unwrap(), ["a8[1]", "a8[2]"]);
+/// assert!(line.no_flags().is_err());
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Line<'s> {
+    mnemonic: &'s str,
+    flags: Option<&'s str>,
+    operands: Vec<&'s str>,
+}
+
+impl<'s> Line<'s> {
+    /// Splits instruction text into mnemonic, flags (following the mnemonic after a dot) and
+    /// comma-separated operands. Commas inside quoted string operands and inside square brackets
+    /// do not separate operands.
+    ///
+    /// # Errors
+    ///
+    /// If the text is empty.
+    pub fn parse(s: &'s str) -> Result<Self, ParseInstrError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseInstrError::Empty);
+        }
+        let (head, rest) = match s.find(char::is_whitespace) {
+            Some(pos) => (&s[..pos], s[pos..].trim()),
+            None => (s, ""),
+        };
+        let (mnemonic, flags) = match head.split_once('.') {
+            Some((mnemonic, flags)) => (mnemonic, Some(flags)),
+            None => (head, None),
+        };
+
+        let mut operands = Vec::new();
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut brackets = 0usize;
+        let mut start = 0usize;
+        for (pos, ch) in rest.char_indices() {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '[' if !quoted => brackets += 1,
+                ']' if !quoted => brackets = brackets.saturating_sub(1),
+                ',' if !quoted && brackets == 0 => {
+                    operands.push(rest[start..pos].trim());
+                    start = pos + 1;
+                }
+                _ => {}
+            }
+        }
+        if !rest.is_empty() {
+            operands.push(rest[start..].trim());
+        }
+
+        Ok(Line { mnemonic, flags, operands })
+    }
+
+    /// Returns instruction mnemonic
+    #[inline]
+    pub fn mnemonic(&self) -> &'s str { self.mnemonic }
+
+    /// Returns instruction flags, if any
+    #[inline]
+    pub fn flags(&self) -> Option<&'s str> { self.flags }
+
+    /// Returns instruction operands
+    #[inline]
+    pub fn operands(&self) -> &[&'s str] { &self.operands }
+
+    /// Returns instruction operands, checking that there are exactly `LEN` of them.
+    pub fn ops<const LEN: usize>(&self) -> Result<[&'s str; LEN], ParseInstrError> {
+        <[&str; LEN]>::try_from(self.operands.as_slice()).map_err(|_| {
+            ParseInstrError::OperandCount {
+                mnemonic: self.mnemonic.to_owned(),
+                expected: LEN,
+                found: self.operands.len(),
+            }
+        })
+    }
+
+    /// Checks that the instruction has no flags.
+    pub fn no_flags(&self) -> Result<(), ParseInstrError> {
+        match self.flags {
+            None => Ok(()),
+            Some(_) => Err(ParseInstrError::UnexpectedFlags(self.mnemonic.to_owned())),
+        }
+    }
+
+    /// Parses instruction flags, treating absent flags as an empty string.
+    pub fn flag<F: Flag>(&self) -> Result<F, ParseInstrError> {
+        F::from_str(self.flags.unwrap_or_default()).map_err(ParseInstrError::from)
+    }
+
+    /// Constructs error reporting invalid operands of the instruction.
+    pub fn invalid(&self) -> ParseInstrError {
+        ParseInstrError::InvalidOperands(self.mnemonic.to_owned(), self.operands.join(","))
+    }
+
+    /// Reconstructs instruction text with each operand replaced by the provided function.
+    fn rewrite(
+        &self,
+        mut f: impl FnMut(&'s str) -> Result<String, ParseInstrError>,
+    ) -> Result<String, ParseInstrError> {
+        let mut text = self.mnemonic.to_owned();
+        if let Some(flags) = self.flags {
+            text.push('.');
+            text.push_str(flags);
+        }
+        for (no, op) in self.operands.iter().enumerate() {
+            text.push(if no == 0 { ' ' } else { ',' });
+            text.push_str(&f(op)?);
+        }
+        Ok(text)
+    }
+}
+
+/// Parsers of the instruction operands following the syntax of the core instructions, for use by
+/// the [`FromStr`] implementations of ISA extensions together with [`Line`].
+pub mod operand {
+    use super::*;
+
+    /// Parses arithmetic integer register, like `a64[3]`.
+    #[inline]
+    pub fn reg_a(op: &str) -> Option<(RegA, Reg32)> { a(op) }
+
+    /// Parses arithmetic float register, like `f32[3]`.
+    #[inline]
+    pub fn reg_f(op: &str) -> Option<(RegF, Reg32)> { f(op) }
+
+    /// Parses general non-arithmetic register, like `r256[3]`.
+    #[inline]
+    pub fn reg_r(op: &str) -> Option<(RegR, Reg32)> { r(op) }
+
+    /// Parses string register, like `s16[3]`.
+    #[inline]
+    pub fn reg_s(op: &str) -> Option<RegS> { s(op) }
+
+    /// Parses `true` or `false`.
+    #[inline]
+    pub fn bool(op: &str) -> Result<bool, ParseInstrError> { boolean(op) }
+
+    /// Parses code offset, given either as a decimal or as a `0x`-prefixed hex number.
+    #[inline]
+    pub fn code_offset(op: &str) -> Result<u16, ParseInstrError> { offset(op) }
+
+    /// Parses signed jump displacement, like `-0x10` or `+8`.
+    #[inline]
+    pub fn jump_displacement(op: &str) -> Result<i16, ParseInstrError> { displacement(op) }
+
+    /// Parses library call site given as `<code offset> @ <library id>`.
+    #[inline]
+    pub fn lib_site(op: &str) -> Result<LibSite, ParseInstrError> { site(op) }
+
+    /// Parses quoted byte string with escapes, like `"abc\n"`.
+    #[inline]
+    pub fn byte_str(op: &str) -> Result<ByteStr, ParseInstrError> { data(op) }
+}
+
+fn select<T: Copy + Display>(s: &str, all: impl IntoIterator<Item = T>) -> Option<T> {
+    all.into_iter().find(|item| item.to_string() == s)
+}
+
+fn reg<F, I>(
+    op: &str,
+    families: impl IntoIterator<Item = F>,
+    indexes: impl IntoIterator<Item = I>,
+) -> Option<(F, I)>
+where
+    F: Copy + Display,
+    I: Copy + Display,
+{
+    let pos = op.find('[')?;
+    Some((select(&op[..pos], families)?, select(&op[pos..], indexes)?))
+}
+
+fn fixed<I: Copy + Display>(
+    op: &str,
+    family: &str,
+    indexes: impl IntoIterator<Item = I>,
+) -> Option<I> {
+    select(op.strip_prefix(family)?, indexes)
+}
+
+fn a(op: &str) -> Option<(RegA, Reg32)> { reg(op, RegA::ALL, Reg32::ALL) }
+fn f(op: &str) -> Option<(RegF, Reg32)> { reg(op, RegF::ALL, Reg32::ALL) }
+fn r(op: &str) -> Option<(RegR, Reg32)> { reg(op, RegR::ALL, Reg32::ALL) }
+fn a2(op: &str) -> Option<(RegA2, Reg32)> { reg(op, [RegA2::A8, RegA2::A16], Reg32::ALL) }
+fn s(op: &str) -> Option<RegS> { select(op, RegS::ALL) }
+
+/// Parses set of registers of the same family, like `a64[1-4,7]`.
+fn reg_set<F: Copy + Display>(
+    op: &str,
+    families: impl IntoIterator<Item = F>,
+) -> Option<(F, BTreeSet<Reg32>)> {
+    let (family, items) = op.strip_suffix(']')?.split_once('[')?;
+    let family = select(family, families)?;
+    let mut set = BTreeSet::new();
+    for item in items.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let (first, last) = item.split_once('-').unwrap_or((item, item));
+        let (first, last) = (u8::from_str(first.trim()).ok()?, u8::from_str(last.trim()).ok()?);
+        if first > last {
+            return None;
+        }
+        for idx in first..=last {
+            set.insert(Reg32::try_from(idx).ok()?);
+        }
+    }
+    Some((family, set))
+}
+
+fn af(op: &str) -> Option<(RegAF, Reg16)> {
+    let families = RegA::ALL.iter().copied().map(RegAF::A);
+    reg(op, families.chain(RegF::ALL.iter().copied().map(RegAF::F)), Reg16::ALL)
+}
+
+fn ar<I: Copy + Display>(op: &str, indexes: impl IntoIterator<Item = I>) -> Option<(RegAR, I)> {
+    let families = RegA::ALL.iter().copied().map(RegAR::A);
+    reg(op, families.chain(RegR::ALL.iter().copied().map(RegAR::R)), indexes)
+}
+
+fn same<F: Eq, I>(op1: Option<(F, I)>, op2: Option<(F, I)>) -> Option<(F, I, I)> {
+    match (op1, op2) {
+        (Some((reg1, idx1)), Some((reg2, idx2))) if reg1 == reg2 => Some((reg1, idx1, idx2)),
+        _ => None,
+    }
+}
+
+fn boolean(op: &str) -> Result<bool, ParseInstrError> {
+    match op {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(ParseInstrError::InvalidOperand(op.to_owned())),
+    }
+}
+
+fn offset(op: &str) -> Result<u16, ParseInstrError> {
+    match op.strip_prefix("0x").or_else(|| op.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => u16::from_str(op),
+    }
+    .map_err(|_| ParseInstrError::InvalidOperand(op.to_owned()))
+}
+
+fn displacement(op: &str) -> Result<i16, ParseInstrError> {
+    let err = || ParseInstrError::InvalidOperand(op.to_owned());
+    let (neg, abs) = match op.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, op.strip_prefix('+').unwrap_or(op)),
+    };
+    let abs = offset(abs).map_err(|_| err())? as i32;
+    i16::try_from(if neg { -abs } else { abs }).map_err(|_| err())
+}
+
+fn site(op: &str) -> Result<LibSite, ParseInstrError> {
+    let err = || ParseInstrError::InvalidSite(op.to_owned());
+    let (pos, lib) = op.split_once('@').ok_or_else(err)?;
+    let pos = offset(pos.trim()).map_err(|_| err())?;
+    let lib = LibId::from_str(lib.trim()).map_err(|_| err())?;
+    Ok(LibSite::with(pos, lib))
+}
+
+fn data(op: &str) -> Result<ByteStr, ParseInstrError> {
+    let err = || ParseInstrError::InvalidData(op.to_owned());
+    let inner = op.strip_prefix('"').and_then(|s| s.strip_suffix('"')).ok_or_else(err)?;
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        let byte = match ch {
+            '\\' => match chars.next().ok_or_else(err)? {
+                '\\' => b'\\',
+                '"' => b'"',
+                '0' => b'\0',
+                't' => b'\t',
+                'n' => b'\n',
+                'r' => b'\r',
+                'x' => {
+                    let hi = chars.next().and_then(|c| c.to_digit(16)).ok_or_else(err)?;
+                    let lo = chars.next().and_then(|c| c.to_digit(16)).ok_or_else(err)?;
+                    (hi << 4 | lo) as u8
+                }
+                _ => return Err(err()),
+            },
+            '"' => return Err(err()),
+            ' '..='~' => ch as u8,
+            _ => return Err(err()),
+        };
+        bytes.push(byte);
+    }
+    ByteStr::try_from(bytes.as_slice()).map_err(|_| err())
+}
+
+fn step(mnemonic: &str, op: Option<&str>) -> Result<Step, ParseInstrError> {
+    let val = match (mnemonic, op) {
+        ("inc", None) => 1,
+        ("dec", None) => -1,
+        ("add", Some(op)) => {
+            i8::from_str(op).map_err(|_| ParseInstrError::InvalidOperand(op.to_owned()))?
+        }
+        ("sub", Some(op)) => u8::from_str(op)
+            .ok()
+            .and_then(|val| 0i8.checked_sub_unsigned(val))
+            .ok_or_else(|| ParseInstrError::InvalidOperand(op.to_owned()))?,
+        _ => unreachable!("step instruction {}", mnemonic),
+    };
+    Ok(Step::with(val))
+}
+
+/// Parses number literal into the layout of a given register.
+fn number(op: &str, reg: impl NumericRegister) -> Result<MaybeNumber, ParseInstrError> {
+    if op == "~" {
+        return Ok(MaybeNumber::none());
+    }
+    let layout = reg.layout();
+    let is_hex = op.starts_with("0x") || op.starts_with("0X");
+    match layout {
+        Layout::Integer(_) => int(op, layout),
+        Layout::Float(_) if is_hex && !op.contains(['p', 'P']) => int(op, layout),
+        Layout::Float(float) => {
+            let op = match op {
+                "+Inf" => "inf",
+                "-Inf" => "-inf",
+                op => op,
+            };
+            let unknown = || LiteralParseError::UnknownLiteral(op.to_owned());
+            Ok(match float {
+                FloatLayout::BFloat16 => half::bf16::from_str(op).map_err(|_| unknown())?.into(),
+                FloatLayout::IeeeHalf => {
+                    ieee::Half::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::IeeeSingle => {
+                    ieee::Single::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::IeeeDouble => {
+                    ieee::Double::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::X87DoubleExt => {
+                    ieee::X87DoubleExtended::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::IeeeQuad => {
+                    ieee::Quad::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::IeeeOct => {
+                    ieee::Oct::from_str(op).map_err(LiteralParseError::from)?.into()
+                }
+                FloatLayout::FloatTapered => return Err(unknown().into()),
+            })
+        }
+    }
+}
+
+/// Parses integer literal of arbitrary length into a given layout; for float layouts the value
+/// represents raw bits.
+fn int(op: &str, layout: Layout) -> Result<MaybeNumber, ParseInstrError> {
+    let unknown = || LiteralParseError::UnknownLiteral(op.to_owned());
+    let (neg, digits) = match op.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, op),
+    };
+    let (radix, digits) =
+        if let Some(digits) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            (16u32, digits)
+        } else if let Some(digits) = digits.strip_prefix("0o") {
+            (8, digits)
+        } else if let Some(digits) = digits.strip_prefix("0b") {
+            (2, digits)
+        } else {
+            (10, digits)
+        };
+    if digits.is_empty() {
+        return Err(unknown().into());
+    }
+
+    let mut number = Number::zero(layout);
+    for ch in digits.chars() {
+        let mut carry = ch.to_digit(radix).ok_or_else(unknown)?;
+        for byte in number.as_mut() {
+            let val = *byte as u32 * radix + carry;
+            *byte = val as u8;
+            carry = val >> 8;
+        }
+        if carry != 0 {
+            return Err(ParseInstrError::ValueOverflow(op.to_owned(), layout.bits()));
+        }
+    }
+    if neg {
+        let mut carry = true;
+        for byte in number.as_mut() {
+            let (val, overflow) = (!*byte).overflowing_add(carry as u8);
+            *byte = val;
+            carry = overflow;
+        }
+    }
+    Ok(number.into())
+}
+
+/// Wrapper for displaying numbers in assembly literal format
+pub(super) struct NumberLiteral<'n>(pub &'n MaybeNumber);
+
+impl Display for NumberLiteral<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let number = match **self.0 {
+            None => return f.write_char('~'),
+            Some(number) => number,
+        };
+        let bytes = number.as_ref();
+        match number.layout() {
+            Layout::Integer(_) if bytes.iter().skip(8).all(|byte| *byte == 0) => {
+                let mut le = [0u8; 8];
+                let len = bytes.len().min(8);
+                le[..len].copy_from_slice(&bytes[..len]);
+                Display::fmt(&u64::from_le_bytes(le), f)
+            }
+            Layout::Integer(_) | Layout::Float(FloatLayout::FloatTapered) => {
+                f.write_str("0x")?;
+                let mut digits = bytes.iter().rev().skip_while(|byte| **byte == 0);
+                match digits.next() {
+                    Some(byte) => write!(f, "{:X}", byte)?,
+                    None => f.write_char('0')?,
+                }
+                for byte in digits {
+                    write!(f, "{:02X}", byte)?;
+                }
+                Ok(())
+            }
+            Layout::Float(FloatLayout::BFloat16) => Display::fmt(&half::bf16::from(number), f),
+            Layout::Float(FloatLayout::IeeeHalf) => Display::fmt(&ieee::Half::from(number), f),
+            Layout::Float(FloatLayout::IeeeSingle) => Display::fmt(&ieee::Single::from(number), f),
+            Layout::Float(FloatLayout::IeeeDouble) => Display::fmt(&ieee::Double::from(number), f),
+            Layout::Float(FloatLayout::X87DoubleExt) => {
+                Display::fmt(&ieee::X87DoubleExtended::from(number), f)
+            }
+            Layout::Float(FloatLayout::IeeeQuad) => Display::fmt(&ieee::Quad::from(number), f),
+            Layout::Float(FloatLayout::IeeeOct) => Display::fmt(&ieee::Oct::from(number), f),
+        }
+    }
+}
+
+impl FromStr for ReservedOp {
+    type Err = ParseInstrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(s)?;
+        let op = line
+            .mnemonic
+            .strip_prefix("rsrv:")
+            .and_then(|code| u8::from_str_radix(code, 16).ok())
+            .map(ReservedOp)
+            .ok_or_else(|| ParseInstrError::UnknownMnemonic(line.mnemonic.to_owned()))?;
+        line.no_flags()?;
+        line.ops::<0>()?;
+        Ok(op)
+    }
+}
+
+impl FromStr for EnvOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let op = match line.mnemonic {
+            "envlib" | "envpos" | "envisa" | "envctx" => line.ops::<1>()?[0],
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        match line.mnemonic {
+            "envlib" => fixed(op, "r256", Reg32::ALL).map(EnvOp::Lib),
+            "envpos" => fixed(op, "a16", Reg32::ALL).map(EnvOp::Pos),
+            "envisa" => s(op).map(EnvOp::Isa),
+            _ => fixed(op, "r256", Reg32::ALL).map(EnvOp::Ctx),
+        }
+        .ok_or_else(|| line.invalid())
+    }
+}
+
+impl FromStr for CtOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [op1, op2] = match line.mnemonic {
+            "cteq" | "ctgt" | "ctlt" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        if let (Some(reg1), Some(reg2), "cteq") = (s(op1), s(op2), line.mnemonic) {
+            return Ok(CtOp::EqS(reg1, reg2));
+        }
+        let (reg, idx1, idx2) = same(r(op1), r(op2)).ok_or_else(|| line.invalid())?;
+        Ok(match line.mnemonic {
+            "cteq" => CtOp::EqR(reg, idx1, idx2),
+            "ctgt" => CtOp::GtR(reg, idx1, idx2),
+            _ => CtOp::LtR(reg, idx1, idx2),
+        })
+    }
+}
+
+impl FromStr for PrngOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [dst, key, counter] = match line.mnemonic {
+            "rnd" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let key = fixed(key, "r256", Reg32::ALL).ok_or_else(|| line.invalid())?;
+        let counter = fixed(counter, "a32", Reg32::ALL).ok_or_else(|| line.invalid())?;
+        match (a(dst), r(dst)) {
+            (Some((reg, idx)), _) => Ok(PrngOp::RndA(reg, idx, key, counter)),
+            (_, Some((reg, idx))) => Ok(PrngOp::RndR(reg, idx, key, counter)),
+            _ => Err(line.invalid()),
+        }
+    }
+}
+
+impl FromStr for ModOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [op1, op2, op3, op4] = match line.mnemonic {
+            "addm" | "mulm" | "expm" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let (reg, src1, src2) = same(r(op1), r(op2)).ok_or_else(|| line.invalid())?;
+        let (_, modulus, dst) =
+            same(r(op3), r(op4)).filter(|(reg2, ..)| *reg2 == reg).ok_or_else(|| line.invalid())?;
+        Ok(match line.mnemonic {
+            "addm" => ModOp::AddMod(reg, src1, src2, modulus, dst),
+            "mulm" => ModOp::MulMod(reg, src1, src2, modulus, dst),
+            _ => ModOp::ExpMod(reg, src1, src2, modulus, dst),
+        })
+    }
+}
+
+impl FromStr for EncOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            "benc" | "bdec" => {
+                let base = line.flag()?;
+                let [src, dst] = line.ops()?;
+                let (src, dst) = s(src).zip(s(dst)).ok_or_else(|| line.invalid())?;
+                Ok(match line.mnemonic {
+                    "benc" => EncOp::Enc(base, src, dst),
+                    _ => EncOp::Dec(base, src, dst),
+                })
+            }
+            "bechenc" | "bechdec" => {
+                line.no_flags()?;
+                let [op1, op2, op3] = line.ops()?;
+                let (reg1, reg2, reg3) = match (s(op1), s(op2), s(op3)) {
+                    (Some(reg1), Some(reg2), Some(reg3)) => (reg1, reg2, reg3),
+                    _ => return Err(line.invalid()),
+                };
+                Ok(match line.mnemonic {
+                    "bechenc" => EncOp::Bech32Enc(reg1, reg2, reg3),
+                    _ => EncOp::Bech32Dec(reg1, reg2, reg3),
+                })
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for RelJumpOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [op] = match line.mnemonic {
+            "rjmp" | "rjif" | "rroutine" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let shift = displacement(op)?;
+        Ok(match line.mnemonic {
+            "rjmp" => RelJumpOp::Jmp(shift),
+            "rjif" => RelJumpOp::Jif(shift),
+            _ => RelJumpOp::Routine(shift),
+        })
+    }
+}
+
+impl FromStr for ImmOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        if !matches!(line.mnemonic, "put" | "putif") {
+            return Err(ParseInstrError::UnknownMnemonic(line.mnemonic.to_owned()));
+        }
+        match parse_core::<ReservedOp>(&line)? {
+            Some(Instr::Put(op)) => ImmOp::with_put(&op).ok_or_else(|| {
+                ParseInstrError::Extension(format!("value of `{}` does not fit into 8 bytes", op))
+            }),
+            _ => Err(line.invalid()),
+        }
+    }
+}
+
+impl FromStr for FrameOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [first, last] = match line.mnemonic {
+            "pushr" | "popr" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let push = line.mnemonic == "pushr";
+        if let Some((reg, first, last)) = same(a(first), a(last)) {
+            return Ok(if push {
+                FrameOp::PushA(reg, first, last)
+            } else {
+                FrameOp::PopA(reg, first, last)
+            });
+        }
+        if let Some((reg, first, last)) = same(f(first), f(last)) {
+            return Ok(if push {
+                FrameOp::PushF(reg, first, last)
+            } else {
+                FrameOp::PopF(reg, first, last)
+            });
+        }
+        match same(r(first), r(last)) {
+            Some((reg, first, last)) if push => Ok(FrameOp::PushR(reg, first, last)),
+            Some((reg, first, last)) => Ok(FrameOp::PopR(reg, first, last)),
+            None => Err(line.invalid()),
+        }
+    }
+}
+
+impl FromStr for XferOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let [set1, set2] = match line.mnemonic {
+            "movm" | "swpm" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let swap = line.mnemonic == "swpm";
+        if let Some((reg, set1, set2)) = same(reg_set(set1, RegA::ALL), reg_set(set2, RegA::ALL)) {
+            return Ok(if swap {
+                XferOp::SwpA(reg, set1, set2)
+            } else {
+                XferOp::MovA(reg, set1, set2)
+            });
+        }
+        if let Some((reg, set1, set2)) = same(reg_set(set1, RegF::ALL), reg_set(set2, RegF::ALL)) {
+            return Ok(if swap {
+                XferOp::SwpF(reg, set1, set2)
+            } else {
+                XferOp::MovF(reg, set1, set2)
+            });
+        }
+        match same(reg_set(set1, RegR::ALL), reg_set(set2, RegR::ALL)) {
+            Some((reg, set1, set2)) if swap => Ok(XferOp::SwpR(reg, set1, set2)),
+            Some((reg, set1, set2)) => Ok(XferOp::MovR(reg, set1, set2)),
+            None => Err(line.invalid()),
+        }
+    }
+}
+
+impl FromStr for StepOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        let step =
+            |op: &str| i8::from_str(op).map_err(|_| ParseInstrError::InvalidOperand(op.to_owned()));
+        match line.mnemonic {
+            "stpa" => {
+                let [op, val] = line.ops()?;
+                let (reg, idx) = a(op).ok_or_else(|| line.invalid())?;
+                Ok(StepOp::StpA(line.flag()?, reg, idx, step(val)?))
+            }
+            "stpf" => {
+                let [op, val] = line.ops()?;
+                let (reg, idx) = f(op).ok_or_else(|| line.invalid())?;
+                Ok(StepOp::StpF(line.flag()?, reg, idx, step(val)?))
+            }
+            "nega" | "absa" => {
+                let [op] = line.ops()?;
+                let (reg, idx) = a(op).ok_or_else(|| line.invalid())?;
+                Ok(match line.mnemonic {
+                    "nega" => StepOp::NegA(line.flag()?, reg, idx),
+                    _ => StepOp::AbsA(line.flag()?, reg, idx),
+                })
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for DataOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            "ldlen" => {
+                line.no_flags()?;
+                let [dst] = line.ops()?;
+                let (reg, idx) = a(dst).ok_or_else(|| line.invalid())?;
+                Ok(DataOp::Len(reg, idx))
+            }
+            "ldd" => {
+                line.no_flags()?;
+                let [dst, off] = line.ops()?;
+                let (off_reg, off) = a(off).ok_or_else(|| line.invalid())?;
+                if let Some((reg, idx)) = a(dst) {
+                    return Ok(DataOp::LoadA(reg, idx, off_reg, off));
+                }
+                r(dst)
+                    .map(|(reg, idx)| DataOp::LoadR(reg, idx, off_reg, off))
+                    .ok_or_else(|| line.invalid())
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for SwitchOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            "switch" => {
+                line.no_flags()?;
+                let Some((op, table)) = line.operands.split_first() else {
+                    return Err(ParseInstrError::OperandCount {
+                        mnemonic: line.mnemonic.to_owned(),
+                        expected: 1,
+                        found: 0,
+                    });
+                };
+                let (reg, idx) = a(op).ok_or_else(|| line.invalid())?;
+                let table = table.iter().map(|op| offset(op)).collect::<Result<_, _>>()?;
+                Ok(SwitchOp::Switch(reg, idx, table))
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for LoopOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            "loop" => {
+                line.no_flags()?;
+                let [counter, max, pos] = line.ops()?;
+                let idx = fixed(counter, "a16", Reg32::ALL).ok_or_else(|| line.invalid())?;
+                Ok(LoopOp::Loop(idx, offset(max)?, offset(pos)?))
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for FixedOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            mnemonic @ ("xmul" | "xdiv") => {
+                let [op1, op2, frac] = line.ops()?;
+                let (reg, src, srcdst) = same(a(op1), a(op2)).ok_or_else(|| line.invalid())?;
+                let (flags, frac) = (line.flag()?, offset(frac)?);
+                Ok(match mnemonic {
+                    "xmul" => FixedOp::MulA(flags, reg, src, srcdst, frac),
+                    _ => FixedOp::DivA(flags, reg, src, srcdst, frac),
+                })
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for FieldOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        let r256 = |op| fixed(op, "r256", Reg32::ALL);
+        match line.mnemonic {
+            "addf" | "mulf" | "expf" => {
+                let field = line.flag()?;
+                let [op1, op2, op3] = line.ops()?;
+                let (src1, src2, dst) = match (r256(op1), r256(op2), r256(op3)) {
+                    (Some(src1), Some(src2), Some(dst)) => (src1, src2, dst),
+                    _ => return Err(line.invalid()),
+                };
+                Ok(match line.mnemonic {
+                    "addf" => FieldOp::Add(field, src1, src2, dst),
+                    "mulf" => FieldOp::Mul(field, src1, src2, dst),
+                    _ => FieldOp::Exp(field, src1, src2, dst),
+                })
+            }
+            "invf" => {
+                let field = line.flag()?;
+                let [src, dst] = line.ops()?;
+                let (src, dst) = r256(src).zip(r256(dst)).ok_or_else(|| line.invalid())?;
+                Ok(FieldOp::Inv(field, src, dst))
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for HostOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        match line.mnemonic {
+            "hcall" => {
+                line.no_flags()?;
+                let [func, dst] = line.ops()?;
+                let func = offset(func)?;
+                if let Some((reg, idx)) = a(dst) {
+                    return Ok(HostOp::CallA(func, reg, idx));
+                }
+                r(dst).map(|(reg, idx)| HostOp::CallR(func, reg, idx)).ok_or_else(|| line.invalid())
+            }
+            mnemonic => Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        }
+    }
+}
+
+impl FromStr for StackOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        if line.mnemonic == "sdepth" {
+            let [stack, elem, dst] = line.ops()?;
+            let (stack, dst) =
+                s(stack).zip(fixed(dst, "a16", Reg32::ALL)).ok_or_else(|| line.invalid())?;
+            if let Some(reg) = select(elem, RegA::ALL) {
+                return Ok(StackOp::DepthA(stack, reg, dst));
+            }
+            return select(elem, RegR::ALL)
+                .map(|reg| StackOp::DepthR(stack, reg, dst))
+                .ok_or_else(|| line.invalid());
+        }
+        let [stack, op] = match line.mnemonic {
+            "spush" | "spop" | "speek" => line.ops()?,
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        let stack = s(stack).ok_or_else(|| line.invalid())?;
+        if let Some((reg, idx)) = a(op) {
+            return Ok(match line.mnemonic {
+                "spush" => StackOp::PushA(stack, reg, idx),
+                "spop" => StackOp::PopA(stack, reg, idx),
+                _ => StackOp::PeekA(stack, reg, idx),
+            });
+        }
+        let (reg, idx) = r(op).ok_or_else(|| line.invalid())?;
+        Ok(match line.mnemonic {
+            "spush" => StackOp::PushR(stack, reg, idx),
+            "spop" => StackOp::PopR(stack, reg, idx),
+            _ => StackOp::PeekR(stack, reg, idx),
+        })
+    }
+}
+
+#[cfg(feature = "bitcoin")]
+impl FromStr for BitcoinOp {
+    type Err = ParseInstrError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(text)?;
+        line.no_flags()?;
+        let op = match line.mnemonic {
+            "chkh160" => {
+                let [src, hash] = line.ops()?;
+                s(src).zip(fixed(hash, "r160", Reg32::ALL)).map(|(s, h)| BitcoinOp::Hash160(s, h))
+            }
+            "chkh256" => {
+                let [src, hash] = line.ops()?;
+                s(src).zip(fixed(hash, "r256", Reg32::ALL)).map(|(s, h)| BitcoinOp::Hash256(s, h))
+            }
+            "chksig" => {
+                let [pk, sig, msg] = line.ops()?;
+                match (s(pk), s(sig), fixed(msg, "r256", Reg32::ALL)) {
+                    (Some(pk), Some(sig), Some(msg)) => Some(BitcoinOp::CheckSig(pk, sig, msg)),
+                    _ => None,
+                }
+            }
+            "chkschn" => {
+                let [pk, sig, msg] = line.ops()?;
+                match (fixed(pk, "r256", Reg32::ALL), s(sig), fixed(msg, "r256", Reg32::ALL)) {
+                    (Some(pk), Some(sig), Some(msg)) => Some(BitcoinOp::CheckSchnorr(pk, sig, msg)),
+                    _ => None,
+                }
+            }
+            mnemonic => return Err(ParseInstrError::UnknownMnemonic(mnemonic.to_owned())),
+        };
+        op.ok_or_else(|| line.invalid())
+    }
+}
+
+impl<First, Second> FromStr for Extensions<First, Second>
+where
+    First: InstructionSet + FromStr<Err = ParseInstrError>,
+    Second: InstructionSet + FromStr<Err = ParseInstrError>,
+{
+    type Err = ParseInstrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match First::from_str(s) {
+            Err(ParseInstrError::UnknownMnemonic(_)) => Second::from_str(s).map(Extensions::Second),
+            res => res.map(Extensions::First),
+        }
+    }
+}
+
+impl<Extension> FromStr for Instr<Extension>
+where
+    Extension: InstructionSet + FromStr<Err = ParseInstrError>,
+{
+    type Err = ParseInstrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = Line::parse(s)?;
+        match parse_core(&line)? {
+            Some(instr) => Ok(instr),
+            None => Extension::from_str(s.trim()).map(Instr::ExtensionCodes),
+        }
+    }
+}
+
+fn parse_core<Extension: InstructionSet>(
+    line: &Line,
+) -> Result<Option<Instr<Extension>>, ParseInstrError> {
+    let mnemonic = line.mnemonic;
+    let has_flags = line.flags.is_some();
+    let invalid = || line.invalid();
+
+    Ok(Some(match mnemonic {
+        "nop" => {
+            line.no_flags()?;
+            line.ops::<0>()?;
+            Instr::Nop
+        }
+        rsrv if rsrv.starts_with("rsrv:") => match ReservedOp::from_str(rsrv)? {
+            op if (INSTR_RESV_FROM..=INSTR_RESV_TO).contains(&op.0) => {
+                line.no_flags()?;
+                line.ops::<0>()?;
+                Instr::ReservedInstruction(op)
+            }
+            _ => return Ok(None),
+        },
+
+        "fail" | "succ" | "ret" => {
+            line.no_flags()?;
+            line.ops::<0>()?;
+            Instr::ControlFlow(match mnemonic {
+                "fail" => ControlFlowOp::Fail,
+                "succ" => ControlFlowOp::Succ,
+                _ => ControlFlowOp::Ret,
+            })
+        }
+        "jmp" | "jif" | "routine" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let pos = offset(op)?;
+            Instr::ControlFlow(match mnemonic {
+                "jmp" => ControlFlowOp::Jmp(pos),
+                "jif" => ControlFlowOp::Jif(pos),
+                _ => ControlFlowOp::Routine(pos),
+            })
+        }
+        "call" | "exec" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let site = site(op)?;
+            Instr::ControlFlow(match mnemonic {
+                "call" => ControlFlowOp::Call(site),
+                _ => ControlFlowOp::Exec(site),
+            })
+        }
+
+        "clr" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            Instr::Put(if let Some((reg, idx)) = a(op) {
+                PutOp::ClrA(reg, idx)
+            } else if let Some((reg, idx)) = f(op) {
+                PutOp::ClrF(reg, idx)
+            } else if let Some((reg, idx)) = r(op) {
+                PutOp::ClrR(reg, idx)
+            } else {
+                return Err(invalid());
+            })
+        }
+        "put" | "putif" => {
+            line.no_flags()?;
+            let [dst, val] = line.ops()?;
+            let cond = mnemonic == "putif";
+            if let Some(reg) = s(dst).filter(|_| !cond) {
+                Instr::Bytes(BytesOp::Put(reg, Box::new(data(val)?), false))
+            } else if let Some((reg, idx)) = a(dst) {
+                let val = Box::new(number(val, reg)?);
+                Instr::Put(if cond {
+                    PutOp::PutIfA(reg, idx, val)
+                } else {
+                    PutOp::PutA(reg, idx, val)
+                })
+            } else if let Some((reg, idx)) = r(dst) {
+                let val = Box::new(number(val, reg)?);
+                Instr::Put(if cond {
+                    PutOp::PutIfR(reg, idx, val)
+                } else {
+                    PutOp::PutR(reg, idx, val)
+                })
+            } else if let Some((reg, idx)) = f(dst).filter(|_| !cond) {
+                Instr::Put(PutOp::PutF(reg, idx, Box::new(number(val, reg)?)))
+            } else {
+                return Err(invalid());
+            }
+        }
+
+        "mov" | "dup" | "swp" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            if let (Some(src), Some(dst), false) = (s(op1), s(op2), mnemonic == "dup") {
+                Instr::Bytes(match mnemonic {
+                    "mov" => BytesOp::Mov(src, dst),
+                    _ => BytesOp::Swp(src, dst),
+                })
+            } else if let Some((reg, idx1, idx2)) = same(a(op1), a(op2)) {
+                Instr::Move(match mnemonic {
+                    "mov" => MoveOp::MovA(reg, idx1, idx2),
+                    "dup" => MoveOp::DupA(reg, idx1, idx2),
+                    _ => MoveOp::SwpA(reg, idx1, idx2),
+                })
+            } else if let Some((reg, idx1, idx2)) = same(f(op1), f(op2)) {
+                Instr::Move(match mnemonic {
+                    "mov" => MoveOp::MovF(reg, idx1, idx2),
+                    "dup" => MoveOp::DupF(reg, idx1, idx2),
+                    _ => MoveOp::SwpF(reg, idx1, idx2),
+                })
+            } else if let (Some((reg, idx1, idx2)), false) =
+                (same(r(op1), r(op2)), mnemonic == "swp")
+            {
+                Instr::Move(match mnemonic {
+                    "mov" => MoveOp::MovR(reg, idx1, idx2),
+                    _ => MoveOp::DupR(reg, idx1, idx2),
+                })
+            } else {
+                return Err(invalid());
+            }
+        }
+        "cpy" | "cnv" | "spy" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            Instr::Move(match (mnemonic, a(op1), f(op1), a(op2), f(op2), r(op1), r(op2)) {
+                ("cpy", Some((r1, i1)), _, Some((r2, i2)), ..) => MoveOp::CpyA(r1, i1, r2, i2),
+                ("cpy", .., Some((r1, i1)), Some((r2, i2))) => MoveOp::CpyR(r1, i1, r2, i2),
+                ("cnv", Some((r1, i1)), _, Some((r2, i2)), ..) => MoveOp::CnvA(r1, i1, r2, i2),
+                ("cnv", _, Some((r1, i1)), _, Some((r2, i2)), ..) => MoveOp::CnvF(r1, i1, r2, i2),
+                ("cnv", Some((r1, i1)), _, _, Some((r2, i2)), ..) => MoveOp::CnvAF(r1, i1, r2, i2),
+                ("cnv", _, Some((r1, i1)), Some((r2, i2)), ..) => MoveOp::CnvFA(r1, i1, r2, i2),
+                ("spy", Some((r1, i1)), .., Some((r2, i2))) => MoveOp::SpyAR(r1, i1, r2, i2),
+                _ => return Err(invalid()),
+            })
+        }
+
+        "gt" | "lt" if has_flags => {
+            let [op1, op2] = line.ops()?;
+            let gt = mnemonic == "gt";
+            Instr::Cmp(if let Some((reg, idx1, idx2)) = same(a(op1), a(op2)) {
+                let flag = line.flag()?;
+                if gt {
+                    CmpOp::GtA(flag, reg, idx1, idx2)
+                } else {
+                    CmpOp::LtA(flag, reg, idx1, idx2)
+                }
+            } else if let Some((reg, idx1, idx2)) = same(f(op1), f(op2)) {
+                let flag = line.flag()?;
+                if gt {
+                    CmpOp::GtF(flag, reg, idx1, idx2)
+                } else {
+                    CmpOp::LtF(flag, reg, idx1, idx2)
+                }
+            } else {
+                return Err(invalid());
+            })
+        }
+        "gt" | "lt" => {
+            let [op1, op2] = line.ops()?;
+            let (reg, idx1, idx2) = same(r(op1), r(op2)).ok_or_else(invalid)?;
+            Instr::Cmp(match mnemonic {
+                "gt" => CmpOp::GtR(reg, idx1, idx2),
+                _ => CmpOp::LtR(reg, idx1, idx2),
+            })
+        }
+        "eq" if has_flags => {
+            let [op1, op2] = line.ops()?;
+            Instr::Cmp(if let Some((reg, idx1, idx2)) = same(a(op1), a(op2)) {
+                CmpOp::EqA(line.flag()?, reg, idx1, idx2)
+            } else if let Some((reg, idx1, idx2)) = same(f(op1), f(op2)) {
+                CmpOp::EqF(line.flag()?, reg, idx1, idx2)
+            } else if let Some((reg, idx1, idx2)) = same(r(op1), r(op2)) {
+                CmpOp::EqR(line.flag()?, reg, idx1, idx2)
+            } else {
+                return Err(invalid());
+            })
+        }
+        "eq" => {
+            let [op1, op2] = line.ops()?;
+            Instr::Bytes(BytesOp::Eq(s(op1).ok_or_else(invalid)?, s(op2).ok_or_else(invalid)?))
+        }
+        "ifz" | "ifn" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let ifz = mnemonic == "ifz";
+            Instr::Cmp(if let Some((reg, idx)) = a(op) {
+                if ifz {
+                    CmpOp::IfZA(reg, idx)
+                } else {
+                    CmpOp::IfNA(reg, idx)
+                }
+            } else if let Some((reg, idx)) = r(op) {
+                if ifz {
+                    CmpOp::IfZR(reg, idx)
+                } else {
+                    CmpOp::IfNR(reg, idx)
+                }
+            } else {
+                return Err(invalid());
+            })
+        }
+        "st" => {
+            let [op] = line.ops()?;
+            let (reg, idx) = reg(op, RegA::ALL, Reg8::ALL).ok_or_else(invalid)?;
+            Instr::Cmp(CmpOp::St(line.flag()?, reg, idx))
+        }
+        "stinv" => {
+            line.no_flags()?;
+            line.ops::<0>()?;
+            Instr::Cmp(CmpOp::StInv)
+        }
+
+        "add" | "sub" | "mul" | "div" if has_flags => {
+            let [op1, op2] = line.ops()?;
+            Instr::Arithmetic(if let Some((reg, idx1, idx2)) = same(a(op1), a(op2)) {
+                let flags = line.flag()?;
+                match mnemonic {
+                    "add" => ArithmeticOp::AddA(flags, reg, idx1, idx2),
+                    "sub" => ArithmeticOp::SubA(flags, reg, idx1, idx2),
+                    "mul" => ArithmeticOp::MulA(flags, reg, idx1, idx2),
+                    _ => ArithmeticOp::DivA(flags, reg, idx1, idx2),
+                }
+            } else if let Some((reg, idx1, idx2)) = same(f(op1), f(op2)) {
+                let flag = line.flag()?;
+                match mnemonic {
+                    "add" => ArithmeticOp::AddF(flag, reg, idx1, idx2),
+                    "sub" => ArithmeticOp::SubF(flag, reg, idx1, idx2),
+                    "mul" => ArithmeticOp::MulF(flag, reg, idx1, idx2),
+                    _ => ArithmeticOp::DivF(flag, reg, idx1, idx2),
+                }
+            } else {
+                return Err(invalid());
+            })
+        }
+        "add" | "sub" => {
+            let [op, val] = line.ops()?;
+            let (reg, idx) = a(op).ok_or_else(invalid)?;
+            Instr::Arithmetic(ArithmeticOp::Stp(reg, idx, step(mnemonic, Some(val))?))
+        }
+        "inc" | "dec" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let (reg, idx) = a(op).ok_or_else(invalid)?;
+            Instr::Arithmetic(ArithmeticOp::Stp(reg, idx, step(mnemonic, None)?))
+        }
+        "rem" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            let (reg1, idx1) = a(op1).ok_or_else(invalid)?;
+            let (reg2, idx2) = a(op2).ok_or_else(invalid)?;
+            Instr::Arithmetic(ArithmeticOp::Rem(reg1, idx1, reg2, idx2))
+        }
+        "neg" | "abs" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let (reg, idx) = af(op).ok_or_else(invalid)?;
+            Instr::Arithmetic(match mnemonic {
+                "neg" => ArithmeticOp::Neg(reg, idx),
+                _ => ArithmeticOp::Abs(reg, idx),
+            })
+        }
+
+        "and" | "or" | "xor" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            let (reg, idx1, idx2) =
+                same(ar(op1, Reg16::ALL), ar(op2, Reg16::ALL)).ok_or_else(invalid)?;
+            let (_, _, idx3) = same(Some((reg, idx1)), ar(op3, Reg16::ALL)).ok_or_else(invalid)?;
+            Instr::Bitwise(match mnemonic {
+                "and" => BitwiseOp::And(reg, idx1, idx2, idx3),
+                "or" => BitwiseOp::Or(reg, idx1, idx2, idx3),
+                _ => BitwiseOp::Xor(reg, idx1, idx2, idx3),
+            })
+        }
+        "not" => {
+            line.no_flags()?;
+            let [op] = line.ops()?;
+            let (reg, idx) = ar(op, Reg16::ALL).ok_or_else(invalid)?;
+            Instr::Bitwise(BitwiseOp::Not(reg, idx))
+        }
+        "shl" | "scl" | "scr" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            let (reg1, idx1) = a2(op1).ok_or_else(invalid)?;
+            let (reg2, idx2) = ar(op2, Reg32::ALL).ok_or_else(invalid)?;
+            Instr::Bitwise(match mnemonic {
+                "shl" => BitwiseOp::Shl(reg1, idx1, reg2, idx2),
+                "scl" => BitwiseOp::Scl(reg1, idx1, reg2, idx2),
+                _ => BitwiseOp::Scr(reg1, idx1, reg2, idx2),
+            })
+        }
+        "shr" if has_flags => {
+            let [op1, op2] = line.ops()?;
+            let (reg1, idx1) = reg(op1, [RegA2::A8, RegA2::A16], Reg16::ALL).ok_or_else(invalid)?;
+            let (reg2, idx2) = a(op2).ok_or_else(invalid)?;
+            Instr::Bitwise(BitwiseOp::ShrA(line.flag()?, reg1, idx1, reg2, idx2))
+        }
+        "shr" => {
+            let [op1, op2] = line.ops()?;
+            let (reg1, idx1) = a2(op1).ok_or_else(invalid)?;
+            let (reg2, idx2) = r(op2).ok_or_else(invalid)?;
+            Instr::Bitwise(BitwiseOp::ShrR(reg1, idx1, reg2, idx2))
+        }
+        "rev" => {
+            line.no_flags()?;
+            match line.operands[..] {
+                [op1, op2] => Instr::Bytes(BytesOp::Rev(
+                    s(op1).ok_or_else(invalid)?,
+                    s(op2).ok_or_else(invalid)?,
+                )),
+                [op] => Instr::Bitwise(if let Some((reg, idx)) = a(op) {
+                    BitwiseOp::RevA(reg, idx)
+                } else if let Some((reg, idx)) = r(op) {
+                    BitwiseOp::RevR(reg, idx)
+                } else {
+                    return Err(invalid());
+                }),
+                _ => return Err(invalid()),
+            }
+        }
+
+        "fill" => {
+            let [op1, op2, op3, op4] = line.ops()?;
+            Instr::Bytes(BytesOp::Fill(
+                s(op1).ok_or_else(invalid)?,
+                fixed(op2, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op3, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op4, "a8", Reg32::ALL).ok_or_else(invalid)?,
+                line.flag()?,
+            ))
+        }
+        "len" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            let (reg, idx) = a(op2).ok_or_else(invalid)?;
+            Instr::Bytes(BytesOp::Len(s(op1).ok_or_else(invalid)?, reg, idx))
+        }
+        "cnt" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            Instr::Bytes(BytesOp::Cnt(
+                s(op1).ok_or_else(invalid)?,
+                fixed(op2, "a8", Reg16::ALL).ok_or_else(invalid)?,
+                fixed(op3, "a16", Reg16::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        "con" => {
+            line.no_flags()?;
+            let [op1, op2, op3, op4, op5] = line.ops()?;
+            Instr::Bytes(BytesOp::Con(
+                s(op1).ok_or_else(invalid)?,
+                s(op2).ok_or_else(invalid)?,
+                fixed(op3, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op4, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op5, "a16", Reg32::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        "find" => {
+            line.no_flags()?;
+            let [dst, op1, op2] = line.ops()?;
+            if dst != "a16[0]" {
+                return Err(invalid());
+            }
+            Instr::Bytes(BytesOp::Find(s(op1).ok_or_else(invalid)?, s(op2).ok_or_else(invalid)?))
+        }
+        "extr" | "inj" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            let src = s(op1).ok_or_else(invalid)?;
+            let (reg, idx) = reg(op2, RegR::ALL, Reg16::ALL).ok_or_else(invalid)?;
+            let offset = fixed(op3, "a16", Reg16::ALL).ok_or_else(invalid)?;
+            Instr::Bytes(match mnemonic {
+                "extr" => BytesOp::Extr(src, reg, idx, offset),
+                _ => BytesOp::Inj(src, reg, idx, offset),
+            })
+        }
+        "join" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            Instr::Bytes(BytesOp::Join(
+                s(op1).ok_or_else(invalid)?,
+                s(op2).ok_or_else(invalid)?,
+                s(op3).ok_or_else(invalid)?,
+            ))
+        }
+        "splt" => {
+            let [op1, op2, op3, op4] = line.ops()?;
+            Instr::Bytes(BytesOp::Splt(
+                line.flag()?,
+                fixed(op2, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                s(op1).ok_or_else(invalid)?,
+                s(op3).ok_or_else(invalid)?,
+                s(op4).ok_or_else(invalid)?,
+            ))
+        }
+        "ins" => {
+            let [op1, op2, op3] = line.ops()?;
+            Instr::Bytes(BytesOp::Ins(
+                line.flag()?,
+                fixed(op3, "a16", Reg32::ALL).ok_or_else(invalid)?,
+                s(op1).ok_or_else(invalid)?,
+                s(op2).ok_or_else(invalid)?,
+            ))
+        }
+        "del" => {
+            let [op1, op2, op3, op4, op5, op6] = line.ops()?;
+            let (reg1, idx1) = a2(op3).ok_or_else(invalid)?;
+            let (reg2, idx2) = a2(op4).ok_or_else(invalid)?;
+            Instr::Bytes(BytesOp::Del(
+                line.flag()?,
+                reg1,
+                idx1,
+                reg2,
+                idx2,
+                boolean(op5)?,
+                boolean(op6)?,
+                s(op1).ok_or_else(invalid)?,
+                s(op2).ok_or_else(invalid)?,
+            ))
+        }
+
+        "ripemd" | "sha2" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            let src = s(op1).ok_or_else(invalid)?;
+            Instr::Digest(match mnemonic {
+                "ripemd" => {
+                    DigestOp::Ripemd(src, fixed(op2, "r160", Reg16::ALL).ok_or_else(invalid)?)
+                }
+                _ => match (fixed(op2, "r256", Reg16::ALL), fixed(op2, "r512", Reg16::ALL)) {
+                    (Some(dst), _) => DigestOp::Sha256(src, dst),
+                    (_, Some(dst)) => DigestOp::Sha512(src, dst),
+                    _ => return Err(invalid()),
+                },
+            })
+        }
+
+        #[cfg(feature = "secp256k1")]
+        "secpgen" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            Instr::Secp256k1(Secp256k1Op::Gen(
+                fixed(op1, "r256", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op2, "r512", Reg8::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        #[cfg(feature = "secp256k1")]
+        "secpmul" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            let (block, idx) = scalar(op1).ok_or_else(invalid)?;
+            Instr::Secp256k1(Secp256k1Op::Mul(
+                block,
+                idx,
+                fixed(op2, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op3, "r512", Reg32::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        #[cfg(feature = "secp256k1")]
+        "secpadd" | "secpneg" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            let src = fixed(op1, "r512", Reg32::ALL).ok_or_else(invalid)?;
+            let dst = fixed(op2, "r512", Reg8::ALL).ok_or_else(invalid)?;
+            Instr::Secp256k1(match mnemonic {
+                "secpadd" => Secp256k1Op::Add(src, dst),
+                _ => Secp256k1Op::Neg(src, dst),
+            })
+        }
+
+        #[cfg(feature = "curve25519")]
+        "edgen" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            Instr::Curve25519(Curve25519Op::Gen(
+                fixed(op1, "r256", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op2, "r512", Reg8::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        #[cfg(feature = "curve25519")]
+        "edmul" => {
+            line.no_flags()?;
+            let [op1, op2, op3] = line.ops()?;
+            let (block, idx) = scalar(op1).ok_or_else(invalid)?;
+            Instr::Curve25519(Curve25519Op::Mul(
+                block,
+                idx,
+                fixed(op2, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op3, "r512", Reg32::ALL).ok_or_else(invalid)?,
+            ))
+        }
+        #[cfg(feature = "curve25519")]
+        "edadd" => {
+            line.no_flags()?;
+            let [op1, op2, op3, op4] = line.ops()?;
+            Instr::Curve25519(Curve25519Op::Add(
+                fixed(op1, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op2, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op3, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                boolean(op4)?,
+            ))
+        }
+        #[cfg(feature = "curve25519")]
+        "edneg" => {
+            line.no_flags()?;
+            let [op1, op2] = line.ops()?;
+            Instr::Curve25519(Curve25519Op::Neg(
+                fixed(op1, "r512", Reg32::ALL).ok_or_else(invalid)?,
+                fixed(op2, "r512", Reg8::ALL).ok_or_else(invalid)?,
+            ))
+        }
+
+        _ => return Ok(None),
+    }))
+}
+
+/// Parses scalar operand of elliptic curve multiplication, which may be either `a256` or `r256`
+/// register.
+#[cfg(any(feature = "secp256k1", feature = "curve25519"))]
+fn scalar(op: &str) -> Option<(RegBlockAR, Reg32)> {
+    match op.get(..1)? {
+        "a" => fixed(&op[1..], "256", Reg32::ALL).map(|idx| (RegBlockAR::A, idx)),
+        "r" => fixed(&op[1..], "256", Reg32::ALL).map(|idx| (RegBlockAR::R, idx)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isa::IntFlags;
+    use crate::test_utils::{
+        core_instrs, ct_ops, data_ops, enc_ops, env_ops, field_ops, fixed_ops, frame_ops, host_ops,
+        imm_ops, loop_ops, mod_ops, prng_ops, rel_jump_ops, stack_ops, step_ops, switch_ops,
+        xfer_ops,
+    };
+
+    #[test]
+    fn core_text_roundtrip() {
+        for instr in core_instrs::<ReservedOp>() {
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+    }
+
+    #[test]
+    fn env_text_roundtrip() {
+        for op in env_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            EnvOp::from_str("envpos r256[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("envpos"), s!("r256[1]")))
+        );
+    }
+
+    #[test]
+    fn ct_text_roundtrip() {
+        for op in ct_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            CtOp::from_str("ctgt s16[1],s16[2]"),
+            Err(ParseInstrError::InvalidOperands(s!("ctgt"), s!("s16[1],s16[2]")))
+        );
+    }
+
+    #[test]
+    fn xfer_text_roundtrip() {
+        for op in xfer_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            XferOp::from_str("movm a64[1, 3-4],a64[5-6,8]"),
+            Ok(XferOp::MovA(RegA::A64, bset![Reg32::Reg1, Reg32::Reg3, Reg32::Reg4], bset![
+                Reg32::Reg5,
+                Reg32::Reg6,
+                Reg32::Reg8
+            ]))
+        );
+        assert_eq!(
+            XferOp::from_str("swpm a64[1-4],r128[5-8]"),
+            Err(ParseInstrError::InvalidOperands(s!("swpm"), s!("a64[1-4],r128[5-8]")))
+        );
+        assert_eq!(
+            XferOp::from_str("movm a64[4-1],a64[]"),
+            Err(ParseInstrError::InvalidOperands(s!("movm"), s!("a64[4-1],a64[]")))
+        );
+    }
+
+    #[test]
+    fn step_text_roundtrip() {
+        for op in step_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            StepOp::from_str("stpa.sw a16[3],-128"),
+            Ok(StepOp::StpA(IntFlags::signed_wrapped(), RegA::A16, Reg32::Reg3, -128))
+        );
+        assert_eq!(
+            StepOp::from_str("stpf.n f32[0],0.5").unwrap_err(),
+            ParseInstrError::InvalidOperand(s!("0.5"))
+        );
+        assert_eq!(
+            StepOp::from_str("nega a8[0]"),
+            Err(ParseInstrError::Flag(ParseFlagError::RequiredFlagAbsent("integer serialization")))
+        );
+    }
+
+    #[test]
+    fn prng_text_roundtrip() {
+        for op in prng_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            PrngOp::from_str("rnd a64[1],r512[0],a32[0]"),
+            Err(ParseInstrError::InvalidOperands(s!("rnd"), s!("a64[1],r512[0],a32[0]")))
+        );
+    }
+
+    #[test]
+    fn mod_text_roundtrip() {
+        for op in mod_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            ModOp::from_str("expm r256[1],r256[2],r512[3],r256[4]"),
+            Err(ParseInstrError::InvalidOperands(
+                s!("expm"),
+                s!("r256[1],r256[2],r512[3],r256[4]")
+            ))
+        );
+    }
+
+    #[test]
+    fn enc_text_roundtrip() {
+        for op in enc_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            EncOp::from_str("benc.36 s16[0],s16[1]"),
+            Err(ParseInstrError::Flag(ParseFlagError::UnknownFlags("base encoding", s!("36"))))
+        );
+        assert_eq!(
+            EncOp::from_str("bdec.64 r256[0],s16[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("bdec"), s!("r256[0],s16[1]")))
+        );
+    }
+
+    #[test]
+    fn rel_jump_text_roundtrip() {
+        for op in rel_jump_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(RelJumpOp::from_str("rjmp -0x10"), Ok(RelJumpOp::Jmp(-16)));
+        assert_eq!(RelJumpOp::from_str("rjif 16"), Ok(RelJumpOp::Jif(16)));
+        assert_eq!(
+            RelJumpOp::from_str("rjmp 32768"),
+            Err(ParseInstrError::InvalidOperand(s!("32768")))
+        );
+    }
+
+    #[test]
+    fn imm_text_roundtrip() {
+        for op in imm_ops() {
+            let text = op.to_string();
+            assert_eq!(ImmOp::from_str(&text), Ok(op), "instruction `{text}`");
+        }
+        assert_eq!(
+            ImmOp::from_str("put a16[1],-1"),
+            Ok(ImmOp::PutA(RegA::A16, Reg32::Reg1, 0xFFFF))
+        );
+        assert_eq!(
+            ImmOp::from_str("putif r256[0],0x10000000000000000"),
+            Err(ParseInstrError::Extension(s!(
+                "value of `putif   r256[0],0x10000000000000000` does not fit into 8 bytes"
+            )))
+        );
+        assert_eq!(ImmOp::from_str("clr a8[0]"), Err(ParseInstrError::UnknownMnemonic(s!("clr"))));
+    }
+
+    #[test]
+    fn frame_text_roundtrip() {
+        for op in frame_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            FrameOp::from_str("pushr a8[0],a16[3]"),
+            Err(ParseInstrError::InvalidOperands(s!("pushr"), s!("a8[0],a16[3]")))
+        );
+    }
+
+    #[test]
+    fn field_text_roundtrip() {
+        for op in field_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            FieldOp::from_str("addf r256[0],r256[1],r256[2]"),
+            Err(ParseInstrError::Flag(ParseFlagError::RequiredFlagAbsent("prime field")))
+        );
+        assert_eq!(
+            FieldOp::from_str("invf.254 r256[0],r128[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("invf"), s!("r256[0],r128[1]")))
+        );
+    }
+
+    #[test]
+    fn data_text_roundtrip() {
+        for op in data_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            DataOp::from_str("ldd r128[1],a16[0]"),
+            Ok(DataOp::LoadR(RegR::R128, Reg32::Reg1, RegA::A16, Reg32::Reg0))
+        );
+        assert_eq!(
+            DataOp::from_str("ldlen r128[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("ldlen"), s!("r128[1]")))
+        );
+        assert_eq!(
+            DataOp::from_str("ldd a8[1],r128[0]"),
+            Err(ParseInstrError::InvalidOperands(s!("ldd"), s!("a8[1],r128[0]")))
+        );
+    }
+
+    #[test]
+    fn switch_text_roundtrip() {
+        for op in switch_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            SwitchOp::from_str("switch a8[1],4,0x0010"),
+            Ok(SwitchOp::Switch(RegA::A8, Reg32::Reg1, vec![4, 0x10]))
+        );
+        assert_eq!(
+            SwitchOp::from_str("switch"),
+            Err(ParseInstrError::OperandCount { mnemonic: s!("switch"), expected: 1, found: 0 })
+        );
+        assert_eq!(
+            SwitchOp::from_str("switch a8[1],-1").unwrap_err(),
+            ParseInstrError::InvalidOperand(s!("-1"))
+        );
+    }
+
+    #[test]
+    fn loop_text_roundtrip() {
+        for op in loop_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            LoopOp::from_str("loop a16[2],100,0x10"),
+            Ok(LoopOp::Loop(Reg32::Reg2, 100, 0x10))
+        );
+        assert_eq!(
+            LoopOp::from_str("loop a8[2],100,0x10"),
+            Err(ParseInstrError::InvalidOperands(s!("loop"), s!("a8[2],100,0x10")))
+        );
+    }
+
+    #[test]
+    fn fixed_text_roundtrip() {
+        for op in fixed_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            FixedOp::from_str("xmul.sc a64[1],a64[2],0x20"),
+            Ok(FixedOp::MulA(
+                IntFlags { signed: true, wrap: false },
+                RegA::A64,
+                Reg32::Reg1,
+                Reg32::Reg2,
+                32
+            ))
+        );
+        assert_eq!(
+            FixedOp::from_str("xdiv.uc a64[1],a32[2],16"),
+            Err(ParseInstrError::InvalidOperands(s!("xdiv"), s!("a64[1],a32[2],16")))
+        );
+    }
+
+    #[test]
+    fn host_text_roundtrip() {
+        for op in host_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            HostOp::from_str("hcall 12,a16[3]"),
+            Ok(HostOp::CallA(12, RegA::A16, Reg32::Reg3))
+        );
+        assert_eq!(
+            HostOp::from_str("hcall 0x0001,f32[0]"),
+            Err(ParseInstrError::InvalidOperands(s!("hcall"), s!("0x0001,f32[0]")))
+        );
+    }
+
+    #[test]
+    fn stack_text_roundtrip() {
+        for op in stack_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            StackOp::from_str("spush s16[0],f32[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("spush"), s!("s16[0],f32[1]")))
+        );
+        assert_eq!(
+            StackOp::from_str("sdepth s16[0],a8,a8[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("sdepth"), s!("s16[0],a8,a8[1]")))
+        );
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn bitcoin_text_roundtrip() {
+        use crate::test_utils::bitcoin_ops;
+
+        for op in bitcoin_ops() {
+            let instr = Instr::ExtensionCodes(op);
+            let text = instr.to_string();
+            assert_eq!(Instr::from_str(&text), Ok(instr), "instruction `{text}`");
+        }
+        assert_eq!(
+            BitcoinOp::from_str("chkh160 s16[0],r256[1]"),
+            Err(ParseInstrError::InvalidOperands(s!("chkh160"), s!("s16[0],r256[1]")))
+        );
+    }
+
+    #[test]
+    fn syntax() {
+        let instr = Instr::<ReservedOp>::from_str("  put   a64[3] , 0x10 ").unwrap();
+        assert_eq!(instr, Instr::Put(PutOp::PutA(RegA::A64, Reg32::Reg3, Box::new(16u64.into()))));
+        assert_eq!(instr.to_string(), "put     a64[3],16");
+
+        let instr = Instr::<ReservedOp>::from_str("put a8[0],-1").unwrap();
+        assert_eq!(instr, Instr::Put(PutOp::PutA(RegA::A8, Reg32::Reg0, Box::new(255u8.into()))));
+
+        let instr = Instr::<ReservedOp>::from_str("inc a16[1]").unwrap();
+        assert_eq!(
+            instr,
+            Instr::Arithmetic(ArithmeticOp::Stp(RegA::A16, Reg32::Reg1, Step::with(1)))
+        );
+        assert_eq!(instr.to_string(), "inc     a16[1]");
+        let instr = ArithmeticOp::Stp(RegA::A16, Reg32::Reg1, Step::with(-5));
+        assert_eq!(instr.to_string(), "sub     a16[1],5");
+
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("jmp 18"),
+            Ok(Instr::ControlFlow(ControlFlowOp::Jmp(0x12)))
+        );
+        assert_eq!(Instr::<ReservedOp>::from_str("nop"), Ok(Instr::Nop));
+        assert_eq!(Instr::<ReservedOp>::Nop.to_string(), "nop");
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("rsrv:40"),
+            Ok(Instr::ReservedInstruction(ReservedOp(0x40)))
+        );
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("rsrv:80"),
+            Ok(Instr::ExtensionCodes(ReservedOp(0x80)))
+        );
+    }
+
+    #[test]
+    fn number_literals() {
+        for (reg, text) in [
+            (RegF::F16B, "1.5"),
+            (RegF::F16, "-2.25"),
+            (RegF::F32, "0.1"),
+            (RegF::F64, "3.141592653589793"),
+            (RegF::F128, "1e-300"),
+            (RegF::F128, "+Inf"),
+            (RegF::F256, "-12.75"),
+            (RegF::F512, "0x12AB"),
+        ] {
+            let val = number(text, reg).unwrap();
+            assert!(val.is_some(), "{}: {}", reg, text);
+            let instr = Instr::<ReservedOp>::Put(PutOp::PutF(reg, Reg32::Reg7, Box::new(val)));
+            assert_eq!(Instr::from_str(&instr.to_string()), Ok(instr));
+        }
+
+        let text = "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF";
+        let instr = Instr::<ReservedOp>::from_str(&format!("put r512[1],{text}")).unwrap();
+        assert_eq!(instr.to_string(), format!("put     r512[1],{text}"));
+        assert_eq!(
+            Instr::<ReservedOp>::from_str(&format!("put r256[1],{text}")),
+            Err(ParseInstrError::ValueOverflow(text.to_owned(), 256))
+        );
+        let instr =
+            Instr::<ReservedOp>::from_str("put r128[1],340282366920938463463374607431768211455")
+                .unwrap();
+        assert_eq!(instr.to_string(), "put     r128[1],0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF");
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("put a8[1],256"),
+            Err(ParseInstrError::ValueOverflow(s!("256"), 8))
+        );
+    }
+
+    #[test]
+    fn data_literals() {
+        let bytes = ByteStr::with(b"a\\b\"c\0\t\n\r\x7f\xff ~");
+        let instr =
+            Instr::<ReservedOp>::Bytes(BytesOp::Put(RegS::from(3u8), Box::new(bytes), false));
+        let text = instr.to_string();
+        assert_eq!(text, r#"put     s16[3],"a\\b\"c\0\t\n\r\x7f\xff ~""#);
+        assert_eq!(Instr::from_str(&text), Ok(instr));
+
+        let instr = Instr::<ReservedOp>::from_str(r#"put s16[1],"a,b""#).unwrap();
+        assert_eq!(
+            instr,
+            Instr::Bytes(BytesOp::Put(RegS::from(1u8), Box::new(ByteStr::with("a,b")), false))
+        );
+        assert!(Instr::<ReservedOp>::from_str(r#"put s16[1],"a"b""#).is_err());
+        assert!(Instr::<ReservedOp>::from_str(r#"put s16[1],"\x1""#).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Instr::<ReservedOp>::from_str("  "), Err(ParseInstrError::Empty));
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("foo a8[1]"),
+            Err(ParseInstrError::UnknownMnemonic(s!("foo")))
+        );
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("succ.e"),
+            Err(ParseInstrError::UnexpectedFlags(s!("succ")))
+        );
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("mov a8[1]"),
+            Err(ParseInstrError::OperandCount { mnemonic: s!("mov"), expected: 2, found: 1 })
+        );
+        assert_eq!(
+            Instr::<ReservedOp>::from_str("mov a8[1],f32[2]"),
+            Err(ParseInstrError::InvalidOperands(s!("mov"), s!("a8[1],f32[2]")))
+        );
+        assert!(matches!(
+            Instr::<ReservedOp>::from_str("add.x a8[1],a8[2]"),
+            Err(ParseInstrError::Flag(_))
+        ));
+    }
+
+    #[test]
+    fn constant_tables() {
+        use crate::library::{DataTable, Lib, WriteError};
+
+        let source = r#"
+            .str   plain "abcd"
+            .table first 0 "xyz"
+            .table key   8 "bc"
+            put     s16[0],$plain
+            put     s16[1],$key
+            ret
+        "#;
+        let lib = Lib::assemble_source::<Instr>(source).unwrap();
+        assert_eq!(lib.data_segment(), b"xyz\0\0\0\0\0bcabcd");
+        assert_eq!(lib.disassemble::<Instr>().unwrap()[1].to_string(), "put     s16[1],\"bc\"");
+
+        let mut pool = LiteralPool::new();
+        pool.parse_source::<Instr>(source).unwrap();
+        assert_eq!(pool.tables(), [DataTable::with(b"xyz", 0), DataTable::with(b"bc", 8)]);
+        assert_eq!(pool.get("key"), Some(&ByteStr::with(b"bc")));
+
+        assert!(matches!(
+            Lib::assemble_source::<Instr>(".table key x \"bc\""),
+            Err(SourceError::Parse { line: 1, source: ParseInstrError::InvalidOperand(_) })
+        ));
+        assert!(matches!(
+            Lib::assemble_source::<Instr>(".table key 8"),
+            Err(SourceError::Parse { line: 1, source: ParseInstrError::InvalidData(_) })
+        ));
+        // Table references are resolved by the table data, which therefore must be unique
+        assert_eq!(
+            Lib::assemble_source::<Instr>(".table a 0 \"bc\"\n.table b 8 \"bc\"\nret").unwrap_err(),
+            SourceError::Assembler(AssemblerError::Table {
+                index: 1,
+                source: WriteError::DuplicateTable(0)
+            })
+        );
+    }
+
+    #[test]
+    fn literal_pool() {
+        use crate::library::Lib;
+
+        let source = r#"
+            ; shared literals
+            .str greeting "hello\n"
+            .str other    "world"
+            put     s16[0],$greeting
+            put     s16[1],$other
+            put     s16[2], $greeting
+            ret
+        "#;
+        let lib = Lib::assemble_source::<Instr>(source).unwrap();
+        assert_eq!(lib.data_segment(), b"hello\nworld");
+
+        let code = lib.disassemble::<Instr>().unwrap();
+        let mut pool = LiteralPool::new();
+        pool.declare("greeting", ByteStr::with(b"hello\n")).unwrap();
+        assert_eq!(code[0], pool.put(RegS::from(0), "greeting").unwrap());
+        assert_eq!(code[2], pool.put(RegS::from(2), "greeting").unwrap());
+        assert_eq!(code[0].to_string(), "put     s16[0],\"hello\\n\"");
+
+        assert_eq!(
+            pool.declare("greeting", ByteStr::default()),
+            Err(ParseInstrError::DuplicateLiteral(s!("greeting")))
+        );
+        assert_eq!(
+            pool.declare("1st", ByteStr::default()),
+            Err(ParseInstrError::InvalidLiteralName(s!("1st")))
+        );
+        assert_eq!(
+            Lib::assemble_source::<Instr>("ret\nput s16[0],$missing"),
+            Err(SourceError::Parse {
+                line: 2,
+                source: ParseInstrError::UnknownLiteral(s!("missing"))
+            })
+        );
+    }
+
+    #[test]
+    fn labels() {
+        use crate::library::Lib;
+
+        let source = "
+            start:
+            put     a8[0],1
+            loop:
+            add.uc  a8[0],a8[0]
+            rjif    done
+            jmp     loop
+            done:
+            rroutine start
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<RelJumpOp>>(source).unwrap();
+        let code = lib.disassemble::<Instr<RelJumpOp>>().unwrap();
+        assert_eq!(code[2], Instr::ExtensionCodes(RelJumpOp::Jif(6)));
+        assert_eq!(code[3], Instr::ControlFlow(ControlFlowOp::Jmp(0x0004)));
+        assert_eq!(code[4], Instr::ExtensionCodes(RelJumpOp::Routine(-0x0D)));
+
+        let source = "
+            switch  a8[0],zero,0x0000,one
+            ret
+            zero:
+            ret
+            one:
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<SwitchOp>>(source).unwrap();
+        let code = lib.disassemble::<Instr<SwitchOp>>().unwrap();
+        assert_eq!(
+            code[0],
+            Instr::ExtensionCodes(SwitchOp::Switch(RegA::A8, Reg32::Reg0, vec![7, 0, 8]))
+        );
+        assert_eq!(
+            Lib::assemble_source::<Instr<SwitchOp>>("switch zero,0x0000\nzero:\nret"),
+            Err(SourceError::Parse {
+                line: 1,
+                source: ParseInstrError::InvalidOperands(s!("switch"), s!("0,0x0000"))
+            })
+        );
+
+        let source = "
+            body:
+            add.uc  a8[0],a8[0]
+            loop    a16[1],10,body
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<LoopOp>>(source).unwrap();
+        let code = lib.disassemble::<Instr<LoopOp>>().unwrap();
+        assert_eq!(code[1], Instr::ExtensionCodes(LoopOp::Loop(Reg32::Reg1, 10, 0)));
+
+        for (source, line, err) in [
+            ("loop:\nret\nloop:", 3, ParseInstrError::DuplicateLabel(s!("loop"))),
+            ("nan:\nret", 1, ParseInstrError::InvalidLabelName(s!("nan"))),
+            ("put a8[0],end\nend:", 1, ParseInstrError::LabelOperand(s!("end"))),
+        ] {
+            assert_eq!(
+                Lib::assemble_source::<Instr>(source),
+                Err(SourceError::Parse { line, source: err })
+            );
+        }
+    }
+}
@@ -24,16 +24,24 @@
 //! Instruction serialization and deserialization from bytecode.
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::type_name;
+use core::fmt::{self, Display, Formatter};
 use core::ops::RangeInclusive;
 
-use amplify::num::{u1, u2, u3, u5};
+use amplify::num::{u1, u2, u3, u4, u5, u6};
 
 use super::opcodes::*;
+#[cfg(feature = "bitcoin")]
+use super::BitcoinOp;
 use super::{
-    ArithmeticOp, BitwiseOp, BytesOp, CmpOp, ControlFlowOp, Curve25519Op, DigestOp, Instr,
-    InstructionSet, MoveOp, PutOp, ReservedOp, Secp256k1Op,
+    ArithmeticOp, BitwiseOp, BytesOp, CmpOp, ControlFlowOp, CtOp, Curve25519Op, DataOp, DigestOp,
+    EncOp, EnvOp, Extensions, FieldOp, FixedOp, FrameOp, HostOp, ImmOp, Instr, InstructionSet,
+    LoopOp, ModOp, MoveOp, PrngOp, PutOp, RelJumpOp, ReservedOp, Secp256k1Op, StackOp, StepOp,
+    SwitchOp, XferOp,
 };
 use crate::data::{ByteStr, MaybeNumber};
+use crate::library::constants::{DATA_REF_LEN, LIB_REF_LEN, NUMBER_REF_LEN};
 use crate::library::{CodeEofError, LibSite, Read, Write, WriteError};
 use crate::reg::RegBlockAR;
 
@@ -61,6 +69,157 @@ impl ::std::error::Error for BytecodeError {
     }
 }
 
+/// Opcodes claimed by an instruction set which are already decoded by some other instruction
+/// set it is composed with, such that the claimant never receives them.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct OpcodeCollision {
+    /// Range of the colliding opcodes
+    pub opcodes: RangeInclusive<u8>,
+
+    /// Name of the instruction set which decodes the opcodes
+    pub owner: &'static str,
+
+    /// Name of the instruction set which claims the opcodes, but never receives them
+    pub claimant: &'static str,
+}
+
+impl Display for OpcodeCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.opcodes.start() == self.opcodes.end() {
+            write!(f, "opcode {:#04X}", self.opcodes.start())?;
+        } else {
+            write!(f, "opcodes {:#04X}..={:#04X}", self.opcodes.start(), self.opcodes.end())?;
+        }
+        write!(f, " claimed by `{}` are already used by `{}`", self.claimant, self.owner)
+    }
+}
+
+impl OpcodeCollision {
+    /// Collects collisions for all opcodes for which `owner` returns the name of the instruction
+    /// set decoding them while `claimant` also claims them, merging adjacent opcodes into ranges.
+    ///
+    /// Used for implementing [`Bytecode::opcode_collisions`] by the instruction sets composed from
+    /// other instruction sets, where `owner` reports the instruction set receiving each opcode
+    /// before the `claimant` (see [`core_opcode_owner`] for the core instruction set).
+    ///
+    /// ```
+    /// use aluvm::isa::{core_opcode_owner, OpcodeCollision};
+    ///
+    /// let claims = |opcode| (0x7E..=0x81).contains(&opcode);
+    /// let collisions = OpcodeCollision::scan("MyOp", claims, core_opcode_owner);
+    /// // Opcodes reserved by the core instruction set and used by the digest operations
+    /// assert_eq!(collisions.len(), 2);
+    /// assert_eq!(collisions[0].opcodes, 0x7E..=0x7F);
+    /// assert_eq!(collisions[1].opcodes, 0x80..=0x81);
+    /// ```
+    pub fn scan(
+        claimant: &'static str,
+        claims: impl Fn(u8) -> bool,
+        owner: impl Fn(u8) -> Option<&'static str>,
+    ) -> Vec<OpcodeCollision> {
+        let mut collisions = Vec::<OpcodeCollision>::new();
+        for opcode in (0..=u8::MAX).filter(|opcode| claims(*opcode)) {
+            let Some(owner) = owner(opcode) else {
+                continue;
+            };
+            match collisions.last_mut() {
+                Some(last)
+                    if last.owner == owner && last.opcodes.end().checked_add(1) == Some(opcode) =>
+                {
+                    last.opcodes = *last.opcodes.start()..=opcode
+                }
+                _ => collisions.push(OpcodeCollision { opcodes: opcode..=opcode, owner, claimant }),
+            }
+        }
+        collisions
+    }
+}
+
+/// Part of the core instruction set decoding an opcode, resolved with [`CORE_DISPATCH`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum CoreSet {
+    ControlFlow,
+    Put,
+    Move,
+    Cmp,
+    Arithmetic,
+    Bitwise,
+    Bytes,
+    Digest,
+    #[cfg(feature = "secp256k1")]
+    Secp256k1,
+    #[cfg(feature = "curve25519")]
+    Curve25519,
+    Reserved,
+    Nop,
+    Extension,
+}
+
+impl CoreSet {
+    /// Classifies the opcode; the order of the ranges defines which instruction set decodes the
+    /// opcode if the ranges overlap.
+    const fn classify(opcode: u8) -> CoreSet {
+        match opcode {
+            INSTR_FAIL..=INSTR_RET => CoreSet::ControlFlow,
+            INSTR_CLRA..=INSTR_PUTIFR => CoreSet::Put,
+            INSTR_MOV..=INSTR_CFA => CoreSet::Move,
+            INSTR_LGT..=INSTR_STINV => CoreSet::Cmp,
+            INSTR_ADD..=INSTR_REM => CoreSet::Arithmetic,
+            INSTR_AND..=INSTR_REVR => CoreSet::Bitwise,
+            INSTR_PUT..=INSTR_REV => CoreSet::Bytes,
+            INSTR_RIPEMD..=INSTR_SHA512 => CoreSet::Digest,
+            #[cfg(feature = "secp256k1")]
+            INSTR_SECP_GEN..=INSTR_SECP_NEG => CoreSet::Secp256k1,
+            #[cfg(feature = "curve25519")]
+            INSTR_ED_GEN..=INSTR_ED_NEG => CoreSet::Curve25519,
+            INSTR_RESV_FROM..=INSTR_RESV_TO => CoreSet::Reserved,
+            INSTR_NOP => CoreSet::Nop,
+            INSTR_ISAE_FROM..=INSTR_ISAE_TO => CoreSet::Extension,
+        }
+    }
+
+    /// Returns the name of the instruction set, or `None` for the ISA extension opcodes.
+    fn owner(self) -> Option<&'static str> {
+        Some(match self {
+            CoreSet::ControlFlow => type_name::<ControlFlowOp>(),
+            CoreSet::Put => type_name::<PutOp>(),
+            CoreSet::Move => type_name::<MoveOp>(),
+            CoreSet::Cmp => type_name::<CmpOp>(),
+            CoreSet::Arithmetic => type_name::<ArithmeticOp>(),
+            CoreSet::Bitwise => type_name::<BitwiseOp>(),
+            CoreSet::Bytes => type_name::<BytesOp>(),
+            CoreSet::Digest => type_name::<DigestOp>(),
+            #[cfg(feature = "secp256k1")]
+            CoreSet::Secp256k1 => type_name::<Secp256k1Op>(),
+            #[cfg(feature = "curve25519")]
+            CoreSet::Curve25519 => type_name::<Curve25519Op>(),
+            CoreSet::Reserved => type_name::<ReservedOp>(),
+            CoreSet::Nop => "nop",
+            CoreSet::Extension => return None,
+        })
+    }
+}
+
+/// Lookup table dispatching decoding of each opcode to the part of the core instruction set
+/// (or to the ISA extensions), computed at compile time from the opcode ranges, such that
+/// decoding doesn't need to test the ranges one by one.
+const CORE_DISPATCH: [CoreSet; 256] = {
+    let mut table = [CoreSet::Nop; 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = CoreSet::classify(opcode as u8);
+        opcode += 1;
+    }
+    table
+};
+
+/// Returns the name of the core instruction set decoding the opcode before it may reach an ISA
+/// extension, if any.
+#[inline]
+pub fn core_opcode_owner(opcode: u8) -> Option<&'static str> {
+    CORE_DISPATCH[opcode as usize].owner()
+}
+
 /// Non-failiable byte encoding for the instruction set. We can't use `io` since
 /// (1) we are no_std, (2) it operates data with unlimited length (while we are
 /// bound by u16), (3) it provides too many fails in situations when we can't
@@ -75,18 +234,117 @@ pub trait Bytecode {
     /// Returns byte representing instruction code (without its arguments)
     fn instr_byte(&self) -> u8;
 
+    /// Checks whether the opcode is decoded by the instruction set. Defaults to the opcode being
+    /// within [`Bytecode::instr_range`].
+    #[inline]
+    fn claims_opcode(opcode: u8) -> bool { Self::instr_range().contains(&opcode) }
+
+    /// Reports opcodes claimed by the ISA extensions which are already decoded by the core
+    /// instruction set or by other extensions, and thus would never reach the extension decoder.
+    ///
+    /// The check is performed by [`Vm::new`](crate::Vm::new); instruction sets which are not
+    /// composed from other instruction sets have no collisions.
+    #[inline]
+    fn opcode_collisions() -> Vec<OpcodeCollision> { Vec::new() }
+
     /// If the instruction call or references any external library, returns the call site in that
     /// library.
     #[inline]
     fn call_site(&self) -> Option<LibSite> { None }
 
-    /// Writes the instruction as bytecode
+    /// If the instruction jumps to an offset within the same library code segment, returns
+    /// mutable reference to that offset, allowing to rewrite it when the code is relocated.
+    #[inline]
+    fn jump_target_mut(&mut self) -> Option<&mut u16> { None }
+
+    /// If the instruction jumps to an offset within the same library code segment, returns that
+    /// offset.
+    #[inline]
+    fn jump_target(&self) -> Option<u16> { None }
+
+    /// If the instruction jumps by a displacement relative to its own offset within the same
+    /// library code segment, returns mutable reference to that displacement, allowing to rewrite
+    /// it when the code around the instruction is patched.
+    #[inline]
+    fn jump_displacement_mut(&mut self) -> Option<&mut i16> { None }
+
+    /// If the instruction jumps by a displacement relative to its own offset within the same
+    /// library code segment, returns that displacement.
+    #[inline]
+    fn jump_displacement(&self) -> Option<i16> { None }
+
+    /// If the instruction jumps to one of the offsets within the same library code segment listed
+    /// in a table, returns mutable reference to that table, allowing to rewrite the offsets when
+    /// the code is relocated.
+    #[inline]
+    fn jump_table_mut(&mut self) -> Option<&mut [u16]> { None }
+
+    /// If the instruction jumps to one of the offsets within the same library code segment listed
+    /// in a table, returns that table.
+    #[inline]
+    fn jump_table(&self) -> Option<&[u16]> { None }
+
+    /// Returns offsets in the same library code segment to which the instruction located at `pos`
+    /// may jump: its [jump destination](Bytecode::jump_destination) followed by the entries of
+    /// its [jump table](Bytecode::jump_table).
+    fn jump_destinations(&self, pos: u16) -> Vec<u16> {
+        let mut destinations = self.jump_destination(pos).into_iter().collect::<Vec<_>>();
+        destinations.extend(self.jump_table().unwrap_or_default());
+        destinations
+    }
+
+    /// Returns offset in the same library code segment to which the instruction located at `pos`
+    /// jumps, resolving both absolute [jump targets](Bytecode::jump_target) and relative
+    /// [displacements](Bytecode::jump_displacement). Returns `None` if the instruction does not
+    /// jump or if the displacement leads outside of the addressable offsets.
+    #[inline]
+    fn jump_destination(&self, pos: u16) -> Option<u16> {
+        self.jump_target().or_else(|| pos.checked_add_signed(self.jump_displacement()?))
+    }
+
+    /// If the instruction closes a loop jumping back to its start only a statically limited number
+    /// of times, returns the maximal number of the loop iterations, allowing static analysis to
+    /// bound the execution cost of the loop.
+    ///
+    /// The bound holds only if the instruction jumps backward and the loop body between the jump
+    /// destination and the instruction does not write the loop counter. These conditions are not
+    /// enforced by the virtual machine and must be verified by the analysis relying on the bound,
+    /// like [`LintRule::UnboundedLoop`](crate::library::lint::LintRule::UnboundedLoop) does.
+    #[inline]
+    fn loop_bound(&self) -> Option<u16> { None }
+
+    /// Returns whether the instruction never passes execution to the instruction which follows it
+    /// in the code segment (like unconditional jumps, returns and program completion).
+    #[inline]
+    fn is_terminal(&self) -> bool { false }
+
+    /// Returns whether the instruction calls a routine in the same library code segment at its
+    /// [jump destination](Bytecode::jump_destination), returning to the following instruction.
+    #[inline]
+    fn is_routine_call(&self) -> bool { false }
+
+    /// Returns whether the instruction returns from a routine call.
+    #[inline]
+    fn is_return(&self) -> bool { false }
+
+    /// Returns instruction of the instruction set which puts the same value into the same register
+    /// as the core `put` instruction, but keeps the value inline in the code segment. Used by
+    /// [`Instr`] to encode its `put` instructions, which are replaced when `Some` is returned.
+    #[inline]
+    fn inline_put(_op: &PutOp) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+
+    /// Writes the instruction as bytecode. On failure the writer is left unchanged, see
+    /// [`Write::write_instr`].
     fn encode<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
     where
         W: Write,
     {
-        writer.write_u8(self.instr_byte())?;
-        self.encode_args(writer)
+        writer.write_instr(self)
     }
 
     /// Writes instruction arguments as bytecode, omitting instruction code byte
@@ -101,6 +359,17 @@ pub trait Bytecode {
         R: Read;
 }
 
+impl<Extension> Instr<Extension>
+where
+    Extension: InstructionSet,
+{
+    /// Returns number of bytes which instruction and its arguments occupy in the code segment.
+    ///
+    /// Same as [`Bytecode::byte_count`], provided for the use without importing the trait.
+    #[inline]
+    pub fn encoded_len(&self) -> u16 { self.byte_count() }
+}
+
 impl<Extension> Bytecode for Instr<Extension>
 where
     Extension: InstructionSet,
@@ -108,7 +377,10 @@ where
     fn byte_count(&self) -> u16 {
         match self {
             Instr::ControlFlow(instr) => instr.byte_count(),
-            Instr::Put(instr) => instr.byte_count(),
+            Instr::Put(instr) => match Extension::inline_put(instr) {
+                Some(instr) => instr.byte_count(),
+                None => instr.byte_count(),
+            },
             Instr::Move(instr) => instr.byte_count(),
             Instr::Cmp(instr) => instr.byte_count(),
             Instr::Arithmetic(instr) => instr.byte_count(),
@@ -131,7 +403,10 @@ where
     fn instr_byte(&self) -> u8 {
         match self {
             Instr::ControlFlow(instr) => instr.instr_byte(),
-            Instr::Put(instr) => instr.instr_byte(),
+            Instr::Put(instr) => match Extension::inline_put(instr) {
+                Some(instr) => instr.instr_byte(),
+                None => instr.instr_byte(),
+            },
             Instr::Move(instr) => instr.instr_byte(),
             Instr::Cmp(instr) => instr.instr_byte(),
             Instr::Arithmetic(instr) => instr.instr_byte(),
@@ -144,10 +419,20 @@ where
             Instr::Curve25519(instr) => instr.instr_byte(),
             Instr::ExtensionCodes(instr) => instr.instr_byte(),
             Instr::ReservedInstruction(instr) => instr.instr_byte(),
-            Instr::Nop => 1,
+            Instr::Nop => INSTR_NOP,
         }
     }
 
+    fn opcode_collisions() -> Vec<OpcodeCollision> {
+        let mut collisions = OpcodeCollision::scan(
+            type_name::<Extension>(),
+            Extension::claims_opcode,
+            core_opcode_owner,
+        );
+        collisions.extend(Extension::opcode_collisions());
+        collisions
+    }
+
     fn call_site(&self) -> Option<LibSite> {
         match self {
             Instr::ControlFlow(instr) => instr.call_site(),
@@ -168,13 +453,216 @@ where
         }
     }
 
+    fn jump_target_mut(&mut self) -> Option<&mut u16> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_target_mut(),
+            Instr::Put(instr) => instr.jump_target_mut(),
+            Instr::Move(instr) => instr.jump_target_mut(),
+            Instr::Cmp(instr) => instr.jump_target_mut(),
+            Instr::Arithmetic(instr) => instr.jump_target_mut(),
+            Instr::Bitwise(instr) => instr.jump_target_mut(),
+            Instr::Bytes(instr) => instr.jump_target_mut(),
+            Instr::Digest(instr) => instr.jump_target_mut(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_target_mut(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_target_mut(),
+            Instr::ExtensionCodes(instr) => instr.jump_target_mut(),
+            Instr::ReservedInstruction(instr) => instr.jump_target_mut(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn jump_target(&self) -> Option<u16> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_target(),
+            Instr::Put(instr) => instr.jump_target(),
+            Instr::Move(instr) => instr.jump_target(),
+            Instr::Cmp(instr) => instr.jump_target(),
+            Instr::Arithmetic(instr) => instr.jump_target(),
+            Instr::Bitwise(instr) => instr.jump_target(),
+            Instr::Bytes(instr) => instr.jump_target(),
+            Instr::Digest(instr) => instr.jump_target(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_target(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_target(),
+            Instr::ExtensionCodes(instr) => instr.jump_target(),
+            Instr::ReservedInstruction(instr) => instr.jump_target(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn jump_table_mut(&mut self) -> Option<&mut [u16]> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_table_mut(),
+            Instr::Put(instr) => instr.jump_table_mut(),
+            Instr::Move(instr) => instr.jump_table_mut(),
+            Instr::Cmp(instr) => instr.jump_table_mut(),
+            Instr::Arithmetic(instr) => instr.jump_table_mut(),
+            Instr::Bitwise(instr) => instr.jump_table_mut(),
+            Instr::Bytes(instr) => instr.jump_table_mut(),
+            Instr::Digest(instr) => instr.jump_table_mut(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_table_mut(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_table_mut(),
+            Instr::ExtensionCodes(instr) => instr.jump_table_mut(),
+            Instr::ReservedInstruction(instr) => instr.jump_table_mut(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn jump_table(&self) -> Option<&[u16]> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_table(),
+            Instr::Put(instr) => instr.jump_table(),
+            Instr::Move(instr) => instr.jump_table(),
+            Instr::Cmp(instr) => instr.jump_table(),
+            Instr::Arithmetic(instr) => instr.jump_table(),
+            Instr::Bitwise(instr) => instr.jump_table(),
+            Instr::Bytes(instr) => instr.jump_table(),
+            Instr::Digest(instr) => instr.jump_table(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_table(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_table(),
+            Instr::ExtensionCodes(instr) => instr.jump_table(),
+            Instr::ReservedInstruction(instr) => instr.jump_table(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn jump_displacement_mut(&mut self) -> Option<&mut i16> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_displacement_mut(),
+            Instr::Put(instr) => instr.jump_displacement_mut(),
+            Instr::Move(instr) => instr.jump_displacement_mut(),
+            Instr::Cmp(instr) => instr.jump_displacement_mut(),
+            Instr::Arithmetic(instr) => instr.jump_displacement_mut(),
+            Instr::Bitwise(instr) => instr.jump_displacement_mut(),
+            Instr::Bytes(instr) => instr.jump_displacement_mut(),
+            Instr::Digest(instr) => instr.jump_displacement_mut(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_displacement_mut(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_displacement_mut(),
+            Instr::ExtensionCodes(instr) => instr.jump_displacement_mut(),
+            Instr::ReservedInstruction(instr) => instr.jump_displacement_mut(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn jump_displacement(&self) -> Option<i16> {
+        match self {
+            Instr::ControlFlow(instr) => instr.jump_displacement(),
+            Instr::Put(instr) => instr.jump_displacement(),
+            Instr::Move(instr) => instr.jump_displacement(),
+            Instr::Cmp(instr) => instr.jump_displacement(),
+            Instr::Arithmetic(instr) => instr.jump_displacement(),
+            Instr::Bitwise(instr) => instr.jump_displacement(),
+            Instr::Bytes(instr) => instr.jump_displacement(),
+            Instr::Digest(instr) => instr.jump_displacement(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.jump_displacement(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.jump_displacement(),
+            Instr::ExtensionCodes(instr) => instr.jump_displacement(),
+            Instr::ReservedInstruction(instr) => instr.jump_displacement(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn loop_bound(&self) -> Option<u16> {
+        match self {
+            Instr::ControlFlow(instr) => instr.loop_bound(),
+            Instr::Put(instr) => instr.loop_bound(),
+            Instr::Move(instr) => instr.loop_bound(),
+            Instr::Cmp(instr) => instr.loop_bound(),
+            Instr::Arithmetic(instr) => instr.loop_bound(),
+            Instr::Bitwise(instr) => instr.loop_bound(),
+            Instr::Bytes(instr) => instr.loop_bound(),
+            Instr::Digest(instr) => instr.loop_bound(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.loop_bound(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.loop_bound(),
+            Instr::ExtensionCodes(instr) => instr.loop_bound(),
+            Instr::ReservedInstruction(instr) => instr.loop_bound(),
+            Instr::Nop => None,
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        match self {
+            Instr::ControlFlow(instr) => instr.is_terminal(),
+            Instr::Put(instr) => instr.is_terminal(),
+            Instr::Move(instr) => instr.is_terminal(),
+            Instr::Cmp(instr) => instr.is_terminal(),
+            Instr::Arithmetic(instr) => instr.is_terminal(),
+            Instr::Bitwise(instr) => instr.is_terminal(),
+            Instr::Bytes(instr) => instr.is_terminal(),
+            Instr::Digest(instr) => instr.is_terminal(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.is_terminal(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.is_terminal(),
+            Instr::ExtensionCodes(instr) => instr.is_terminal(),
+            Instr::ReservedInstruction(instr) => instr.is_terminal(),
+            Instr::Nop => false,
+        }
+    }
+
+    fn is_routine_call(&self) -> bool {
+        match self {
+            Instr::ControlFlow(instr) => instr.is_routine_call(),
+            Instr::Put(instr) => instr.is_routine_call(),
+            Instr::Move(instr) => instr.is_routine_call(),
+            Instr::Cmp(instr) => instr.is_routine_call(),
+            Instr::Arithmetic(instr) => instr.is_routine_call(),
+            Instr::Bitwise(instr) => instr.is_routine_call(),
+            Instr::Bytes(instr) => instr.is_routine_call(),
+            Instr::Digest(instr) => instr.is_routine_call(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.is_routine_call(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.is_routine_call(),
+            Instr::ExtensionCodes(instr) => instr.is_routine_call(),
+            Instr::ReservedInstruction(instr) => instr.is_routine_call(),
+            Instr::Nop => false,
+        }
+    }
+
+    fn is_return(&self) -> bool {
+        match self {
+            Instr::ControlFlow(instr) => instr.is_return(),
+            Instr::Put(instr) => instr.is_return(),
+            Instr::Move(instr) => instr.is_return(),
+            Instr::Cmp(instr) => instr.is_return(),
+            Instr::Arithmetic(instr) => instr.is_return(),
+            Instr::Bitwise(instr) => instr.is_return(),
+            Instr::Bytes(instr) => instr.is_return(),
+            Instr::Digest(instr) => instr.is_return(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.is_return(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.is_return(),
+            Instr::ExtensionCodes(instr) => instr.is_return(),
+            Instr::ReservedInstruction(instr) => instr.is_return(),
+            Instr::Nop => false,
+        }
+    }
+
     fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
     where
         W: Write,
     {
         match self {
             Instr::ControlFlow(instr) => instr.encode_args(writer),
-            Instr::Put(instr) => instr.encode_args(writer),
+            Instr::Put(instr) => match Extension::inline_put(instr) {
+                Some(instr) => instr.encode_args(writer),
+                None => instr.encode_args(writer),
+            },
             Instr::Move(instr) => instr.encode_args(writer),
             Instr::Cmp(instr) => instr.encode_args(writer),
             Instr::Arithmetic(instr) => instr.encode_args(writer),
@@ -195,40 +683,25 @@ where
     where
         R: Read,
     {
-        let instr = reader.peek_u8()?;
-        Ok(match instr {
-            instr if ControlFlowOp::instr_range().contains(&instr) => {
-                Instr::ControlFlow(ControlFlowOp::decode(reader)?)
-            }
-            instr if PutOp::instr_range().contains(&instr) => Instr::Put(PutOp::decode(reader)?),
-            instr if MoveOp::instr_range().contains(&instr) => Instr::Move(MoveOp::decode(reader)?),
-            instr if CmpOp::instr_range().contains(&instr) => Instr::Cmp(CmpOp::decode(reader)?),
-            instr if ArithmeticOp::instr_range().contains(&instr) => {
-                Instr::Arithmetic(ArithmeticOp::decode(reader)?)
-            }
-            instr if BitwiseOp::instr_range().contains(&instr) => {
-                Instr::Bitwise(BitwiseOp::decode(reader)?)
-            }
-            instr if BytesOp::instr_range().contains(&instr) => {
-                Instr::Bytes(BytesOp::decode(reader)?)
-            }
-            instr if DigestOp::instr_range().contains(&instr) => {
-                Instr::Digest(DigestOp::decode(reader)?)
-            }
+        Ok(match CORE_DISPATCH[reader.peek_u8()? as usize] {
+            CoreSet::ControlFlow => Instr::ControlFlow(ControlFlowOp::decode(reader)?),
+            CoreSet::Put => Instr::Put(PutOp::decode(reader)?),
+            CoreSet::Move => Instr::Move(MoveOp::decode(reader)?),
+            CoreSet::Cmp => Instr::Cmp(CmpOp::decode(reader)?),
+            CoreSet::Arithmetic => Instr::Arithmetic(ArithmeticOp::decode(reader)?),
+            CoreSet::Bitwise => Instr::Bitwise(BitwiseOp::decode(reader)?),
+            CoreSet::Bytes => Instr::Bytes(BytesOp::decode(reader)?),
+            CoreSet::Digest => Instr::Digest(DigestOp::decode(reader)?),
             #[cfg(feature = "secp256k1")]
-            instr if Secp256k1Op::instr_range().contains(&instr) => {
-                Instr::Secp256k1(Secp256k1Op::decode(reader)?)
-            }
+            CoreSet::Secp256k1 => Instr::Secp256k1(Secp256k1Op::decode(reader)?),
             #[cfg(feature = "curve25519")]
-            instr if Curve25519Op::instr_range().contains(&instr) => {
-                Instr::Curve25519(Curve25519Op::decode(reader)?)
+            CoreSet::Curve25519 => Instr::Curve25519(Curve25519Op::decode(reader)?),
+            CoreSet::Reserved => Instr::ReservedInstruction(ReservedOp::decode(reader)?),
+            CoreSet::Nop => {
+                reader.read_u8()?;
+                Instr::Nop
             }
-            INSTR_RESV_FROM..=INSTR_RESV_TO => {
-                Instr::ReservedInstruction(ReservedOp::decode(reader)?)
-            }
-            INSTR_NOP => Instr::Nop,
-            INSTR_ISAE_FROM..=INSTR_ISAE_TO => Instr::ExtensionCodes(Extension::decode(reader)?),
-            x => unreachable!("unable to classify instruction {:#010b}", x),
+            CoreSet::Extension => Instr::ExtensionCodes(Extension::decode(reader)?),
         })
     }
 }
@@ -242,13 +715,50 @@ impl Bytecode for ControlFlowOp {
         }
     }
 
+    #[inline]
+    fn jump_target_mut(&mut self) -> Option<&mut u16> {
+        match self {
+            ControlFlowOp::Jmp(pos) | ControlFlowOp::Jif(pos) | ControlFlowOp::Routine(pos) => {
+                Some(pos)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn jump_target(&self) -> Option<u16> {
+        match self {
+            ControlFlowOp::Jmp(pos) | ControlFlowOp::Jif(pos) | ControlFlowOp::Routine(pos) => {
+                Some(*pos)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            ControlFlowOp::Fail
+                | ControlFlowOp::Succ
+                | ControlFlowOp::Jmp(_)
+                | ControlFlowOp::Exec(_)
+                | ControlFlowOp::Ret
+        )
+    }
+
+    #[inline]
+    fn is_routine_call(&self) -> bool { matches!(self, ControlFlowOp::Routine(_)) }
+
+    #[inline]
+    fn is_return(&self) -> bool { matches!(self, ControlFlowOp::Ret) }
+
     fn byte_count(&self) -> u16 {
         match self {
             ControlFlowOp::Fail | ControlFlowOp::Succ => 1,
             ControlFlowOp::Jmp(_) | ControlFlowOp::Jif(_) => 3,
             ControlFlowOp::Routine(_) => 3,
-            ControlFlowOp::Call(_) => 4,
-            ControlFlowOp::Exec(_) => 4,
+            ControlFlowOp::Call(_) | ControlFlowOp::Exec(_) => 3 + LIB_REF_LEN,
             ControlFlowOp::Ret => 1,
         }
     }
@@ -314,7 +824,7 @@ impl Bytecode for PutOp {
             | PutOp::PutIfA(_, _, _)
             | PutOp::PutF(_, _, _)
             | PutOp::PutR(_, _, _)
-            | PutOp::PutIfR(_, _, _) => 3,
+            | PutOp::PutIfR(_, _, _) => 2 + NUMBER_REF_LEN,
         }
     }
 
@@ -1066,9 +1576,9 @@ impl Bytecode for BitwiseOp {
 impl Bytecode for BytesOp {
     fn byte_count(&self) -> u16 {
         match self {
-            BytesOp::Put(_, _, _) => 6,
+            BytesOp::Put(_, _, _) => 2 + DATA_REF_LEN,
             BytesOp::Mov(_, _) | BytesOp::Swp(_, _) => 2,
-            BytesOp::Fill(_, _, _, _, _) => 3,
+            BytesOp::Fill(_, _, _, _, _) => 4,
             BytesOp::Len(_, _, _) | BytesOp::Cnt(_, _, _) => 3,
             BytesOp::Eq(_, _) => 2,
             BytesOp::Con(_, _, _, _, _) => 4,
@@ -1100,8 +1610,8 @@ impl Bytecode for BytesOp {
             BytesOp::Inj(_, _, _, _) => INSTR_INJ,
             BytesOp::Join(_, _, _) => INSTR_JOIN,
             BytesOp::Splt(_, _, _, _, _) => INSTR_SPLT,
-            BytesOp::Ins(_, _, _, _) => INSTR_DEL,
-            BytesOp::Del(_, _, _, _, _, _, _, _, _) => INSTR_INS,
+            BytesOp::Ins(_, _, _, _) => INSTR_INS,
+            BytesOp::Del(_, _, _, _, _, _, _, _, _) => INSTR_DEL,
             BytesOp::Rev(_, _) => INSTR_REV,
         }
     }
@@ -1282,7 +1792,7 @@ impl Bytecode for BytesOp {
 
 impl Bytecode for DigestOp {
     #[inline]
-    fn byte_count(&self) -> u16 { 3 }
+    fn byte_count(&self) -> u16 { 2 }
 
     #[inline]
     fn instr_range() -> RangeInclusive<u8> { INSTR_RIPEMD..=INSTR_SHA512 }
@@ -1470,29 +1980,780 @@ impl Bytecode for Curve25519Op {
     }
 }
 
-impl Bytecode for ReservedOp {
+impl Bytecode for EnvOp {
     #[inline]
-    fn byte_count(&self) -> u16 { 1 }
+    fn byte_count(&self) -> u16 { 2 }
 
     #[inline]
-    fn instr_range() -> RangeInclusive<u8> { INSTR_RESV_FROM..=INSTR_ISAE_TO }
+    fn instr_range() -> RangeInclusive<u8> { INSTR_ENV_LIB..=INSTR_ENV_CTX }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            EnvOp::Lib(_) => INSTR_ENV_LIB,
+            EnvOp::Pos(_) => INSTR_ENV_POS,
+            EnvOp::Isa(_) => INSTR_ENV_ISA,
+            EnvOp::Ctx(_) => INSTR_ENV_CTX,
+        }
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        match self {
+            EnvOp::Lib(dst) | EnvOp::Pos(dst) | EnvOp::Ctx(dst) => {
+                writer.write_u5(dst)?;
+                writer.write_u3(u3::with(0))?;
+            }
+            EnvOp::Isa(dst) => {
+                writer.write_u4(dst)?;
+                writer.write_u4(u4::with(0))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        let op = match reader.read_u8()? {
+            INSTR_ENV_LIB => Self::Lib(reader.read_u5()?.into()),
+            INSTR_ENV_POS => Self::Pos(reader.read_u5()?.into()),
+            INSTR_ENV_CTX => Self::Ctx(reader.read_u5()?.into()),
+            INSTR_ENV_ISA => {
+                let op = Self::Isa(reader.read_u4()?.into());
+                reader.read_u4()?;
+                return Ok(op);
+            }
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            _ => return Err(CodeEofError),
+        };
+        reader.read_u3()?;
+        Ok(op)
+    }
+}
+
+bytecode! {
+    CtOp {
+        INSTR_CT_EQR => EqR(reg, idx1, idx2) [idx1: u5, idx2: u5, reg: u3],
+        INSTR_CT_GTR => GtR(reg, idx1, idx2) [idx1: u5, idx2: u5, reg: u3],
+        INSTR_CT_LTR => LtR(reg, idx1, idx2) [idx1: u5, idx2: u5, reg: u3],
+        INSTR_CT_EQS => EqS(reg1, reg2) [reg1: u4, reg2: u4],
+    }
+}
+
+bytecode! {
+    PrngOp {
+        INSTR_RND_A => RndA(reg, dst, key, counter) [reg: u3, dst: u5, key: u5, counter: u5],
+        INSTR_RND_R => RndR(reg, dst, key, counter) [reg: u3, dst: u5, key: u5, counter: u5],
+    }
+}
+
+bytecode! {
+    ModOp {
+        INSTR_MOD_ADD => AddMod(reg, src1, src2, modulus, dst)
+            [reg: u3, src1: u5, src2: u5, modulus: u5, dst: u5],
+        INSTR_MOD_MUL => MulMod(reg, src1, src2, modulus, dst)
+            [reg: u3, src1: u5, src2: u5, modulus: u5, dst: u5],
+        INSTR_MOD_EXP => ExpMod(reg, base, exp, modulus, dst)
+            [reg: u3, base: u5, exp: u5, modulus: u5, dst: u5],
+    }
+}
+
+bytecode! {
+    EncOp {
+        INSTR_ENC => Enc(base, src, dst) [base: u2, src: u4, dst: u4],
+        INSTR_DEC => Dec(base, src, dst) [base: u2, src: u4, dst: u4],
+        INSTR_BECH32_ENC => Bech32Enc(hrp, data, dst) [hrp: u4, data: u4, dst: u4],
+        INSTR_BECH32_DEC => Bech32Dec(src, hrp, data) [src: u4, hrp: u4, data: u4],
+    }
+}
+
+bytecode! {
+    FrameOp {
+        INSTR_PUSHR_A => PushA(reg, first, last) [reg: u3, first: u5, last: u5],
+        INSTR_PUSHR_F => PushF(reg, first, last) [reg: u3, first: u5, last: u5],
+        INSTR_PUSHR_R => PushR(reg, first, last) [reg: u3, first: u5, last: u5],
+        INSTR_POPR_A => PopA(reg, first, last) [reg: u3, first: u5, last: u5],
+        INSTR_POPR_F => PopF(reg, first, last) [reg: u3, first: u5, last: u5],
+        INSTR_POPR_R => PopR(reg, first, last) [reg: u3, first: u5, last: u5],
+    }
+}
+
+bytecode! {
+    StackOp {
+        INSTR_SPUSH_A => PushA(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SPUSH_R => PushR(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SPOP_A => PopA(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SPOP_R => PopR(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SPEEK_A => PeekA(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SPEEK_R => PeekR(stack, reg, idx) [stack: u4, reg: u3, idx: u5],
+        INSTR_SDEPTH_A => DepthA(stack, reg, dst) [stack: u4, reg: u3, dst: u5],
+        INSTR_SDEPTH_R => DepthR(stack, reg, dst) [stack: u4, reg: u3, dst: u5],
+    }
+}
+
+bytecode! {
+    FieldOp {
+        INSTR_ADDF => Add(field, src1, src2, dst) [field: u1, src1: u5, src2: u5, dst: u5],
+        INSTR_MULF => Mul(field, src1, src2, dst) [field: u1, src1: u5, src2: u5, dst: u5],
+        INSTR_INVF => Inv(field, src, dst) [field: u1, src: u5, dst: u5],
+        INSTR_EXPF => Exp(field, base, exp, dst) [field: u1, base: u5, exp: u5, dst: u5],
+    }
+}
+
+bytecode! {
+    HostOp {
+        INSTR_HCALL_A => CallA(func, reg, dst) [func: u16, reg: u3, dst: u5],
+        INSTR_HCALL_R => CallR(func, reg, dst) [func: u16, reg: u3, dst: u5],
+    }
+}
+
+bytecode! {
+    DataOp {
+        INSTR_LDLEN => Len(reg, dst) [reg: u3, dst: u5],
+        INSTR_LDD_A => LoadA(reg, dst, off_reg, off) [reg: u3, dst: u5, off_reg: u3, off: u5],
+        INSTR_LDD_R => LoadR(reg, dst, off_reg, off) [reg: u3, dst: u5, off_reg: u3, off: u5],
+    }
+}
+
+bytecode! {
+    FixedOp {
+        INSTR_FMUL => MulA(flags, reg, src, srcdst, frac)
+            [frac: u16, flags: u2, reg: u3, src: u5, srcdst: u5],
+        INSTR_FDIV => DivA(flags, reg, src, srcdst, frac)
+            [frac: u16, flags: u2, reg: u3, src: u5, srcdst: u5],
+    }
+}
 
+impl Bytecode for XferOp {
+    /// Opcode, register family followed by zero padding, and two 32-bit register masks.
     #[inline]
-    fn instr_byte(&self) -> u8 { self.0 }
+    fn byte_count(&self) -> u16 { 10 }
 
     #[inline]
-    fn encode_args<W>(&self, _writer: &mut W) -> Result<(), BytecodeError>
+    fn instr_range() -> RangeInclusive<u8> { INSTR_MOVM_A..=INSTR_SWPM_R }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            XferOp::MovA(..) => INSTR_MOVM_A,
+            XferOp::MovF(..) => INSTR_MOVM_F,
+            XferOp::MovR(..) => INSTR_MOVM_R,
+            XferOp::SwpA(..) => INSTR_SWPM_A,
+            XferOp::SwpF(..) => INSTR_SWPM_F,
+            XferOp::SwpR(..) => INSTR_SWPM_R,
+        }
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
     where
         W: Write,
     {
+        let (reg, set1, set2) = match self {
+            XferOp::MovA(reg, set1, set2) | XferOp::SwpA(reg, set1, set2) => {
+                (u3::from(reg), set1, set2)
+            }
+            XferOp::MovF(reg, set1, set2) | XferOp::SwpF(reg, set1, set2) => {
+                (u3::from(reg), set1, set2)
+            }
+            XferOp::MovR(reg, set1, set2) | XferOp::SwpR(reg, set1, set2) => {
+                (u3::from(reg), set1, set2)
+            }
+        };
+        writer.write_u3(reg)?;
+        writer.write_u5(u5::MIN)?;
+        writer.write_reg_set(set1.iter().copied())?;
+        writer.write_reg_set(set2.iter().copied())?;
         Ok(())
     }
 
-    #[inline]
     fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
     where
         R: Read,
     {
-        Ok(ReservedOp(reader.read_u8()?))
+        let instr = reader.read_u8()?;
+        let reg = reader.read_u3()?;
+        if reader.read_u5()? != u5::MIN {
+            return Err(CodeEofError);
+        }
+        let set1 = reader.read_reg_set()?;
+        let set2 = reader.read_reg_set()?;
+        Ok(match instr {
+            INSTR_MOVM_A => XferOp::MovA(reg.into(), set1, set2),
+            INSTR_MOVM_F => XferOp::MovF(reg.into(), set1, set2),
+            INSTR_MOVM_R => XferOp::MovR(reg.into(), set1, set2),
+            INSTR_SWPM_A => XferOp::SwpA(reg.into(), set1, set2),
+            INSTR_SWPM_F => XferOp::SwpF(reg.into(), set1, set2),
+            INSTR_SWPM_R => XferOp::SwpR(reg.into(), set1, set2),
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            _ => return Err(CodeEofError),
+        })
+    }
+}
+
+impl Bytecode for SwitchOp {
+    /// Opcode, register family and index, and reference to the table in the data segment.
+    #[inline]
+    fn byte_count(&self) -> u16 { 2 + DATA_REF_LEN }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_SWITCH..=INSTR_SWITCH }
+
+    #[inline]
+    fn instr_byte(&self) -> u8 { INSTR_SWITCH }
+
+    fn jump_table_mut(&mut self) -> Option<&mut [u16]> {
+        let SwitchOp::Switch(_, _, table) = self;
+        Some(table)
+    }
+
+    fn jump_table(&self) -> Option<&[u16]> {
+        let SwitchOp::Switch(_, _, table) = self;
+        Some(table)
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        let SwitchOp::Switch(reg, idx, table) = self;
+        writer.write_u3(reg)?;
+        writer.write_u5(idx)?;
+        let data = table.iter().flat_map(|offset| offset.to_le_bytes()).collect::<Vec<_>>();
+        writer.write_data(data)?;
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        if reader.read_u8()? != INSTR_SWITCH {
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            return Err(CodeEofError);
+        }
+        let reg = reader.read_u3()?;
+        let idx = reader.read_u5()?;
+        let (data, truncated) = reader.read_data()?;
+        if truncated || data.len() % 2 != 0 {
+            return Err(CodeEofError);
+        }
+        let table = data.chunks(2).map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]])).collect();
+        Ok(SwitchOp::Switch(reg.into(), idx.into(), table))
+    }
+}
+
+impl Bytecode for LoopOp {
+    /// Opcode, counter register index followed by zero padding, maximal number of iterations and
+    /// the loop start offset.
+    #[inline]
+    fn byte_count(&self) -> u16 { 6 }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_LOOP..=INSTR_LOOP }
+
+    #[inline]
+    fn instr_byte(&self) -> u8 { INSTR_LOOP }
+
+    #[inline]
+    fn jump_target_mut(&mut self) -> Option<&mut u16> {
+        let LoopOp::Loop(_, _, pos) = self;
+        Some(pos)
+    }
+
+    #[inline]
+    fn jump_target(&self) -> Option<u16> {
+        let LoopOp::Loop(_, _, pos) = *self;
+        Some(pos)
+    }
+
+    #[inline]
+    fn loop_bound(&self) -> Option<u16> {
+        let LoopOp::Loop(_, max, _) = *self;
+        Some(max)
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        let LoopOp::Loop(idx, max, pos) = *self;
+        writer.write_u5(idx)?;
+        writer.write_u3(u3::MIN)?;
+        writer.write_u16(max)?;
+        writer.write_u16(pos)?;
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        if reader.read_u8()? != INSTR_LOOP {
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            return Err(CodeEofError);
+        }
+        let idx = reader.read_u5()?;
+        if reader.read_u3()? != u3::MIN {
+            return Err(CodeEofError);
+        }
+        Ok(LoopOp::Loop(idx.into(), reader.read_u16()?, reader.read_u16()?))
+    }
+}
+
+impl Bytecode for StepOp {
+    /// Opcode, register family and index, step (for the increments) and flags followed by zero
+    /// padding.
+    #[inline]
+    fn byte_count(&self) -> u16 {
+        match self {
+            StepOp::StpA(..) | StepOp::StpF(..) => 4,
+            StepOp::NegA(..) | StepOp::AbsA(..) => 3,
+        }
+    }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_STP_A..=INSTR_ABS_A }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            StepOp::StpA(..) => INSTR_STP_A,
+            StepOp::StpF(..) => INSTR_STP_F,
+            StepOp::NegA(..) => INSTR_NEG_A,
+            StepOp::AbsA(..) => INSTR_ABS_A,
+        }
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        match *self {
+            StepOp::StpA(flags, reg, idx, step) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+                writer.write_i8(step)?;
+                writer.write_u2(flags)?;
+            }
+            StepOp::StpF(flag, reg, idx, step) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+                writer.write_i8(step)?;
+                writer.write_u2(flag)?;
+            }
+            StepOp::NegA(flags, reg, idx) | StepOp::AbsA(flags, reg, idx) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+                writer.write_u2(flags)?;
+            }
+        }
+        writer.write_u6(u6::MIN)?;
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        let instr = reader.read_u8()?;
+        let reg = reader.read_u3()?;
+        let idx = reader.read_u5()?.into();
+        let op = match instr {
+            INSTR_STP_A | INSTR_STP_F => {
+                let step = reader.read_i8()?;
+                let flags = reader.read_u2()?;
+                match instr {
+                    INSTR_STP_A => StepOp::StpA(flags.into(), reg.into(), idx, step),
+                    _ => StepOp::StpF(flags.into(), reg.into(), idx, step),
+                }
+            }
+            INSTR_NEG_A => StepOp::NegA(reader.read_u2()?.into(), reg.into(), idx),
+            INSTR_ABS_A => StepOp::AbsA(reader.read_u2()?.into(), reg.into(), idx),
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            _ => return Err(CodeEofError),
+        };
+        if reader.read_u6()? != u6::MIN {
+            return Err(CodeEofError);
+        }
+        Ok(op)
+    }
+}
+
+#[cfg(feature = "bitcoin")]
+bytecode! {
+    BitcoinOp {
+        INSTR_CHKH160 => Hash160(src, hash) [src: u4, hash: u5],
+        INSTR_CHKH256 => Hash256(src, hash) [src: u4, hash: u5],
+        INSTR_CHKSIG => CheckSig(pk, sig, msg) [pk: u4, sig: u4, msg: u5],
+        INSTR_CHKSCHN => CheckSchnorr(pk, sig, msg) [pk: u5, sig: u4, msg: u5],
+    }
+}
+
+impl Bytecode for RelJumpOp {
+    #[inline]
+    fn jump_displacement_mut(&mut self) -> Option<&mut i16> {
+        match self {
+            RelJumpOp::Jmp(shift) | RelJumpOp::Jif(shift) | RelJumpOp::Routine(shift) => {
+                Some(shift)
+            }
+        }
+    }
+
+    #[inline]
+    fn jump_displacement(&self) -> Option<i16> {
+        match self {
+            RelJumpOp::Jmp(shift) | RelJumpOp::Jif(shift) | RelJumpOp::Routine(shift) => {
+                Some(*shift)
+            }
+        }
+    }
+
+    #[inline]
+    fn is_terminal(&self) -> bool { matches!(self, RelJumpOp::Jmp(_)) }
+
+    #[inline]
+    fn is_routine_call(&self) -> bool { matches!(self, RelJumpOp::Routine(_)) }
+
+    #[inline]
+    fn byte_count(&self) -> u16 { 3 }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_RJMP..=INSTR_RROUTINE }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            RelJumpOp::Jmp(_) => INSTR_RJMP,
+            RelJumpOp::Jif(_) => INSTR_RJIF,
+            RelJumpOp::Routine(_) => INSTR_RROUTINE,
+        }
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        match self {
+            RelJumpOp::Jmp(shift) | RelJumpOp::Jif(shift) | RelJumpOp::Routine(shift) => {
+                writer.write_i16(*shift)?
+            }
+        }
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        Ok(match reader.read_u8()? {
+            INSTR_RJMP => Self::Jmp(reader.read_i16()?),
+            INSTR_RJIF => Self::Jif(reader.read_i16()?),
+            INSTR_RROUTINE => Self::Routine(reader.read_i16()?),
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            _ => return Err(CodeEofError),
+        })
+    }
+}
+
+impl Bytecode for ImmOp {
+    #[inline]
+    fn byte_count(&self) -> u16 { 3 + self.value_len() }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_IMM_A..=INSTR_IMMIF_R }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            ImmOp::PutA(_, _, _) => INSTR_IMM_A,
+            ImmOp::PutF(_, _, _) => INSTR_IMM_F,
+            ImmOp::PutR(_, _, _) => INSTR_IMM_R,
+            ImmOp::PutIfA(_, _, _) => INSTR_IMMIF_A,
+            ImmOp::PutIfR(_, _, _) => INSTR_IMMIF_R,
+        }
+    }
+
+    #[inline]
+    fn inline_put(op: &PutOp) -> Option<Self> { ImmOp::with_put(op) }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        match self {
+            ImmOp::PutA(reg, idx, _) | ImmOp::PutIfA(reg, idx, _) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+            }
+            ImmOp::PutF(reg, idx, _) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+            }
+            ImmOp::PutR(reg, idx, _) | ImmOp::PutIfR(reg, idx, _) => {
+                writer.write_u3(reg)?;
+                writer.write_u5(idx)?;
+            }
+        }
+        let len = self.value_len();
+        writer.write_u8(len as u8)?;
+        for byte in &self.value().to_le_bytes()[..len as usize] {
+            writer.write_u8(*byte)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        let instr = reader.read_u8()?;
+        let reg = reader.read_u3()?;
+        let idx = reader.read_u5()?.into();
+        let len = reader.read_u8()? as usize;
+        if !(1..=8).contains(&len) {
+            return Err(CodeEofError);
+        }
+        let mut le = [0u8; 8];
+        for byte in &mut le[..len] {
+            *byte = reader.read_u8()?;
+        }
+        let val = u64::from_le_bytes(le);
+        let op = match instr {
+            INSTR_IMM_A => ImmOp::PutA(reg.into(), idx, val),
+            INSTR_IMM_F => ImmOp::PutF(reg.into(), idx, val),
+            INSTR_IMM_R => ImmOp::PutR(reg.into(), idx, val),
+            INSTR_IMMIF_A => ImmOp::PutIfA(reg.into(), idx, val),
+            INSTR_IMMIF_R => ImmOp::PutIfR(reg.into(), idx, val),
+            // Being used as an ISA extension, the operation receives all extension opcodes, and
+            // we can't panic on the bytecode provided by a third party
+            _ => return Err(CodeEofError),
+        };
+        // Only the shortest encoding of the value which fits the register is valid, such that
+        // each instruction has a single bytecode representation
+        if op.value() != val || op.value_len() as usize != len {
+            return Err(CodeEofError);
+        }
+        Ok(op)
+    }
+}
+
+impl<First, Second> Bytecode for Extensions<First, Second>
+where
+    First: InstructionSet,
+    Second: InstructionSet,
+{
+    fn byte_count(&self) -> u16 {
+        match self {
+            Extensions::First(instr) => instr.byte_count(),
+            Extensions::Second(instr) => instr.byte_count(),
+        }
+    }
+
+    fn instr_range() -> RangeInclusive<u8> {
+        let (first, second) = (First::instr_range(), Second::instr_range());
+        *first.start().min(second.start())..=*first.end().max(second.end())
+    }
+
+    fn instr_byte(&self) -> u8 {
+        match self {
+            Extensions::First(instr) => instr.instr_byte(),
+            Extensions::Second(instr) => instr.instr_byte(),
+        }
+    }
+
+    #[inline]
+    fn claims_opcode(opcode: u8) -> bool {
+        First::claims_opcode(opcode) || Second::claims_opcode(opcode)
+    }
+
+    fn opcode_collisions() -> Vec<OpcodeCollision> {
+        let mut collisions = First::opcode_collisions();
+        collisions.extend(Second::opcode_collisions());
+        collisions.extend(OpcodeCollision::scan(
+            type_name::<Second>(),
+            Second::claims_opcode,
+            |opcode| First::claims_opcode(opcode).then(type_name::<First>),
+        ));
+        collisions
+    }
+
+    fn call_site(&self) -> Option<LibSite> {
+        match self {
+            Extensions::First(instr) => instr.call_site(),
+            Extensions::Second(instr) => instr.call_site(),
+        }
+    }
+
+    fn jump_target_mut(&mut self) -> Option<&mut u16> {
+        match self {
+            Extensions::First(instr) => instr.jump_target_mut(),
+            Extensions::Second(instr) => instr.jump_target_mut(),
+        }
+    }
+
+    fn jump_target(&self) -> Option<u16> {
+        match self {
+            Extensions::First(instr) => instr.jump_target(),
+            Extensions::Second(instr) => instr.jump_target(),
+        }
+    }
+
+    fn jump_table_mut(&mut self) -> Option<&mut [u16]> {
+        match self {
+            Extensions::First(instr) => instr.jump_table_mut(),
+            Extensions::Second(instr) => instr.jump_table_mut(),
+        }
+    }
+
+    fn jump_table(&self) -> Option<&[u16]> {
+        match self {
+            Extensions::First(instr) => instr.jump_table(),
+            Extensions::Second(instr) => instr.jump_table(),
+        }
+    }
+
+    fn jump_displacement_mut(&mut self) -> Option<&mut i16> {
+        match self {
+            Extensions::First(instr) => instr.jump_displacement_mut(),
+            Extensions::Second(instr) => instr.jump_displacement_mut(),
+        }
+    }
+
+    fn jump_displacement(&self) -> Option<i16> {
+        match self {
+            Extensions::First(instr) => instr.jump_displacement(),
+            Extensions::Second(instr) => instr.jump_displacement(),
+        }
+    }
+
+    fn loop_bound(&self) -> Option<u16> {
+        match self {
+            Extensions::First(instr) => instr.loop_bound(),
+            Extensions::Second(instr) => instr.loop_bound(),
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        match self {
+            Extensions::First(instr) => instr.is_terminal(),
+            Extensions::Second(instr) => instr.is_terminal(),
+        }
+    }
+
+    fn is_routine_call(&self) -> bool {
+        match self {
+            Extensions::First(instr) => instr.is_routine_call(),
+            Extensions::Second(instr) => instr.is_routine_call(),
+        }
+    }
+
+    fn is_return(&self) -> bool {
+        match self {
+            Extensions::First(instr) => instr.is_return(),
+            Extensions::Second(instr) => instr.is_return(),
+        }
+    }
+
+    fn inline_put(op: &PutOp) -> Option<Self> {
+        First::inline_put(op)
+            .map(Extensions::First)
+            .or_else(|| Second::inline_put(op).map(Extensions::Second))
+    }
+
+    fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        match self {
+            Extensions::First(instr) => instr.encode_args(writer),
+            Extensions::Second(instr) => instr.encode_args(writer),
+        }
+    }
+
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        let instr = reader.peek_u8()?;
+        if First::claims_opcode(instr) {
+            First::decode(reader).map(Extensions::First)
+        } else if Second::claims_opcode(instr) {
+            Second::decode(reader).map(Extensions::Second)
+        } else {
+            Err(CodeEofError)
+        }
+    }
+}
+
+impl Bytecode for ReservedOp {
+    #[inline]
+    fn byte_count(&self) -> u16 { 1 }
+
+    #[inline]
+    fn instr_range() -> RangeInclusive<u8> { INSTR_RESV_FROM..=INSTR_ISAE_TO }
+
+    #[inline]
+    fn instr_byte(&self) -> u8 { self.0 }
+
+    /// Reserved instructions are decoded from the opcodes which are not claimed by any other
+    /// instruction set, and thus do not claim any opcodes themselves.
+    #[inline]
+    fn claims_opcode(_opcode: u8) -> bool { false }
+
+    /// Reserved instructions are executed as [`ControlFlowOp::Fail`].
+    #[inline]
+    fn is_terminal(&self) -> bool { true }
+
+    #[inline]
+    fn encode_args<W>(&self, _writer: &mut W) -> Result<(), BytecodeError>
+    where
+        W: Write,
+    {
+        Ok(())
+    }
+
+    #[inline]
+    fn decode<R>(reader: &mut R) -> Result<Self, CodeEofError>
+    where
+        R: Read,
+    {
+        Ok(ReservedOp(reader.read_u8()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn core_dispatch() {
+        type Isa = Instr<ReservedOp>;
+        for opcode in 0..=u8::MAX {
+            let set = CORE_DISPATCH[opcode as usize];
+            let expected = match opcode {
+                op if ControlFlowOp::instr_range().contains(&op) => CoreSet::ControlFlow,
+                op if PutOp::instr_range().contains(&op) => CoreSet::Put,
+                op if MoveOp::instr_range().contains(&op) => CoreSet::Move,
+                op if CmpOp::instr_range().contains(&op) => CoreSet::Cmp,
+                op if ArithmeticOp::instr_range().contains(&op) => CoreSet::Arithmetic,
+                op if BitwiseOp::instr_range().contains(&op) => CoreSet::Bitwise,
+                op if BytesOp::instr_range().contains(&op) => CoreSet::Bytes,
+                op if DigestOp::instr_range().contains(&op) => CoreSet::Digest,
+                #[cfg(feature = "secp256k1")]
+                op if Secp256k1Op::instr_range().contains(&op) => CoreSet::Secp256k1,
+                #[cfg(feature = "curve25519")]
+                op if Curve25519Op::instr_range().contains(&op) => CoreSet::Curve25519,
+                INSTR_RESV_FROM..=INSTR_RESV_TO => CoreSet::Reserved,
+                INSTR_NOP => CoreSet::Nop,
+                _ => CoreSet::Extension,
+            };
+            assert_eq!(set, expected, "opcode {:#04x}", opcode);
+            assert!(Isa::claims_opcode(opcode));
+        }
     }
 }
@@ -23,21 +23,34 @@
 
 use alloc::boxed::Box;
 use alloc::collections::BTreeSet;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::cmp::Ordering;
+use core::convert::TryFrom;
 use core::ops::{BitAnd, BitOr, BitXor, Neg, Rem, Shl, Shr};
 
+use amplify::ByteArray;
 use sha2::Digest;
+use subtle::{Choice, ConstantTimeEq, ConstantTimeGreater};
 
+#[cfg(feature = "bitcoin")]
+use super::BitcoinOp;
 use super::{
-    ArithmeticOp, BitwiseOp, Bytecode, BytesOp, CmpOp, ControlFlowOp, Curve25519Op, DigestOp,
-    Instr, MoveOp, PutOp, ReservedOp, Secp256k1Op,
+    ArithmeticOp, BitwiseOp, Bytecode, BytesOp, CmpOp, ControlFlowOp, CtOp, Curve25519Op, DataOp,
+    DigestOp, EncOp, Env, EnvOp, Extensions, FieldOp, FixedOp, FrameOp, HostOp, ImmOp, Instr,
+    LoopOp, ModOp, MoveOp, PrngOp, PutOp, RelJumpOp, ReservedOp, Secp256k1Op, StackOp, StepOp,
+    SwitchOp, XferOp,
+};
+use crate::data::{
+    basen, modular, ByteStr, FloatOrder, IntLayout, MaybeNumber, Number, NumberLayout,
+};
+use crate::isa::{
+    BaseFlag, ExtendFlag, FieldFlag, FloatEqFlag, IntFlags, MergeFlag, NoneEqFlag, SignFlag,
+};
+use crate::library::{constants, IsaId, LibSite};
+use crate::reg::{
+    CoreRegs, NumericRegister, Reg, Reg32, RegA, RegA2, RegAFR, RegAR, RegF, RegR, RegS,
 };
-use crate::data::{ByteStr, MaybeNumber, Number, NumberLayout};
-use crate::isa::{ExtendFlag, FloatEqFlag, IntFlags, MergeFlag, NoneEqFlag, SignFlag};
-use crate::library::{constants, LibSite};
-use crate::reg::{CoreRegs, NumericRegister, Reg32, RegA, RegA2, RegAR, RegR};
 
 /// Turing machine movement after instruction execution
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
@@ -53,6 +66,13 @@ pub enum ExecStep {
 
     /// Jump to another code fragment
     Call(LibSite),
+
+    /// Suspend program execution after the instruction, passing the embed-defined code to the
+    /// host. The execution continues from the next instruction once resumed.
+    ///
+    /// Allows extension ISAs to implement coroutine-style yields or other host signaling. If the
+    /// program is not executed in chunks (see [`crate::Vm::start`]), the yield has no effect.
+    Yield(u16),
 }
 
 /// Trait for instructions
@@ -66,11 +86,27 @@ pub trait InstructionSet: Bytecode + core::fmt::Display + core::fmt::Debug {
     /// starting with non-number.
     fn isa_ids() -> BTreeSet<&'static str>;
 
+    /// Structured ISA Extension IDs, providing versions and features of the ISA extensions
+    /// implemented by the instruction set.
+    ///
+    /// Defaults to unversioned ids without features constructed from [`InstructionSet::isa_ids`].
+    /// The same ISA extension may be listed multiple times, if the instruction set implements
+    /// several of its versions.
+    #[inline]
+    fn isa_set() -> BTreeSet<IsaId> {
+        Self::isa_ids()
+            .into_iter()
+            .map(|id| IsaId::new(id).expect("instruction set contains incorrect ISA id"))
+            .collect()
+    }
+
     /// ISA Extension IDs represented as a standard string (space-separated)
     ///
     /// Concatenated length of the ISA IDs joined via ' ' character must not exceed 128 bytes.
     #[inline]
-    fn isa_string() -> String { Self::isa_ids().into_iter().collect::<Vec<_>>().join(" ") }
+    fn isa_string() -> String {
+        Self::isa_set().iter().map(IsaId::to_string).collect::<Vec<_>>().join(" ")
+    }
 
     /// ISA Extension IDs encoded in a standard way (space-separated)
     ///
@@ -78,14 +114,48 @@ pub trait InstructionSet: Bytecode + core::fmt::Display + core::fmt::Debug {
     #[inline]
     fn isa_id() -> Box<[u8]> { Self::isa_string().as_bytes().into() }
 
-    /// Checks whether provided ISA extension ID is supported by the current instruction set
+    /// Checks whether provided ISA extension ID is supported by the current instruction set.
+    ///
+    /// Ids which can't be parsed as [`IsaId`] are not supported.
+    #[inline]
+    fn is_supported(id: &str) -> bool {
+        id.parse().map(|id| Self::supports(&id)).unwrap_or_default()
+    }
+
+    /// Checks whether the code requiring the ISA extension `id` can be run by the current
+    /// instruction set, i.e. whether the id [is compatible](IsaId::is_compatible_with) with some
+    /// of the [ids provided by the instruction set](InstructionSet::isa_set).
     #[inline]
-    fn is_supported(id: &str) -> bool { Self::isa_ids().contains(id) }
+    fn supports(id: &IsaId) -> bool {
+        Self::isa_set().iter().any(|provided| id.is_compatible_with(provided))
+    }
 
     /// Returns computational complexity of the instruction
     #[inline]
     fn complexity(&self) -> u64 { 1 }
 
+    /// Lists all registers which are used by the instruction as a source of values.
+    ///
+    /// Instruction sets which do not provide this information report no registers, reducing the
+    /// precision of static code analysis.
+    #[inline]
+    fn src_regs(&self) -> BTreeSet<Reg> { BTreeSet::new() }
+
+    /// Lists all registers which values may be modified by the instruction.
+    ///
+    /// Instruction sets which do not provide this information report no registers, reducing the
+    /// precision of static code analysis.
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { BTreeSet::new() }
+
+    /// Rewrites the instruction into the canonical form among the semantically equivalent
+    /// instructions of the same encoded length, like ordering the operands of commutative
+    /// operations.
+    ///
+    /// Instruction sets which do not provide canonical forms leave the instructions unchanged.
+    #[inline]
+    fn canonicalize(&mut self) {}
+
     /// Executes given instruction taking all registers as input and output.
     ///
     /// # Arguments
@@ -98,6 +168,25 @@ pub trait InstructionSet: Bytecode + core::fmt::Display + core::fmt::Debug {
     /// Returns whether further execution should be stopped.
     // TODO: Take the instruction by reference
     fn exec(&self, regs: &mut CoreRegs, site: LibSite, context: &Self::Context<'_>) -> ExecStep;
+
+    /// Executes given instruction like [`InstructionSet::exec`], providing it with the `data`
+    /// segment of the library containing the instruction. Libraries execute their code with this
+    /// method.
+    ///
+    /// Defaults to [`InstructionSet::exec`]. Only the instructions reading the data segment at
+    /// runtime need to implement it, and the instruction sets composed from other instruction sets
+    /// must pass it on to them.
+    #[inline]
+    fn exec_with_data(
+        &self,
+        regs: &mut CoreRegs,
+        site: LibSite,
+        data: &[u8],
+        context: &Self::Context<'_>,
+    ) -> ExecStep {
+        let _ = data;
+        self.exec(regs, site, context)
+    }
 }
 
 impl<Extension> InstructionSet for Instr<Extension>
@@ -113,9 +202,67 @@ where
         set.extend(DigestOp::isa_ids());
         set.extend(Secp256k1Op::isa_ids());
         set.extend(Curve25519Op::isa_ids());
+        set.extend(Extension::isa_ids());
+        set
+    }
+
+    fn isa_set() -> BTreeSet<IsaId> {
+        let mut set = BTreeSet::new();
+        set.insert(IsaId::new(constants::ISA_ID_ALU).expect("incorrect ISA id"));
+        set.extend(DigestOp::isa_set());
+        set.extend(Secp256k1Op::isa_set());
+        set.extend(Curve25519Op::isa_set());
+        set.extend(Extension::isa_set());
         set
     }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Instr::ControlFlow(instr) => instr.src_regs(),
+            Instr::Put(instr) => instr.src_regs(),
+            Instr::Move(instr) => instr.src_regs(),
+            Instr::Cmp(instr) => instr.src_regs(),
+            Instr::Arithmetic(instr) => instr.src_regs(),
+            Instr::Bitwise(instr) => instr.src_regs(),
+            Instr::Bytes(instr) => instr.src_regs(),
+            Instr::Digest(instr) => instr.src_regs(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.src_regs(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.src_regs(),
+            Instr::ExtensionCodes(instr) => instr.src_regs(),
+            Instr::ReservedInstruction(_) | Instr::Nop => BTreeSet::new(),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Instr::ControlFlow(instr) => instr.dst_regs(),
+            Instr::Put(instr) => instr.dst_regs(),
+            Instr::Move(instr) => instr.dst_regs(),
+            Instr::Cmp(instr) => instr.dst_regs(),
+            Instr::Arithmetic(instr) => instr.dst_regs(),
+            Instr::Bitwise(instr) => instr.dst_regs(),
+            Instr::Bytes(instr) => instr.dst_regs(),
+            Instr::Digest(instr) => instr.dst_regs(),
+            #[cfg(feature = "secp256k1")]
+            Instr::Secp256k1(instr) => instr.dst_regs(),
+            #[cfg(feature = "curve25519")]
+            Instr::Curve25519(instr) => instr.dst_regs(),
+            Instr::ExtensionCodes(instr) => instr.dst_regs(),
+            Instr::ReservedInstruction(_) | Instr::Nop => BTreeSet::new(),
+        }
+    }
+
+    fn canonicalize(&mut self) {
+        match self {
+            Instr::Cmp(instr) => instr.canonicalize(),
+            Instr::Bitwise(instr) => instr.canonicalize(),
+            Instr::ExtensionCodes(instr) => instr.canonicalize(),
+            _ => {}
+        }
+    }
+
     #[inline]
     fn exec(&self, regs: &mut CoreRegs, site: LibSite, ctx: &Self::Context<'_>) -> ExecStep {
         match self {
@@ -136,6 +283,20 @@ where
             Instr::Nop => ExecStep::Next,
         }
     }
+
+    #[inline]
+    fn exec_with_data(
+        &self,
+        regs: &mut CoreRegs,
+        site: LibSite,
+        data: &[u8],
+        ctx: &Self::Context<'_>,
+    ) -> ExecStep {
+        match self {
+            Instr::ExtensionCodes(instr) => instr.exec_with_data(regs, site, data, ctx),
+            _ => self.exec(regs, site, ctx),
+        }
+    }
 }
 
 impl InstructionSet for ControlFlowOp {
@@ -148,6 +309,9 @@ impl InstructionSet for ControlFlowOp {
     fn complexity(&self) -> u64 { 2 }
 
     fn exec(&self, regs: &mut CoreRegs, site: LibSite, _: &()) -> ExecStep {
+        // Location of the instruction following the current one, to which `ret` returns
+        let ret_site =
+            site.pos.checked_add(self.byte_count()).map(|pos| LibSite::with(pos, site.lib));
         match self {
             ControlFlowOp::Fail => {
                 regs.st0 = false;
@@ -168,10 +332,10 @@ impl InstructionSet for ControlFlowOp {
                 }
             }
             ControlFlowOp::Routine(offset) => {
-                regs.call(site).map(|_| ExecStep::Jump(*offset)).unwrap_or(ExecStep::Stop)
+                regs.call(ret_site).map(|_| ExecStep::Jump(*offset)).unwrap_or(ExecStep::Stop)
             }
             ControlFlowOp::Call(site) => {
-                regs.call(*site).map(|_| ExecStep::Call(*site)).unwrap_or(ExecStep::Stop)
+                regs.call(ret_site).map(|_| ExecStep::Call(*site)).unwrap_or(ExecStep::Stop)
             }
             ControlFlowOp::Exec(site) => {
                 regs.jmp().map(|_| ExecStep::Call(*site)).unwrap_or(ExecStep::Stop)
@@ -190,6 +354,28 @@ impl InstructionSet for PutOp {
     #[inline]
     fn complexity(&self) -> u64 { 2 }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            PutOp::PutIfA(reg, idx, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            PutOp::PutIfR(reg, idx, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            PutOp::ClrA(reg, idx) | PutOp::PutA(reg, idx, _) | PutOp::PutIfA(reg, idx, _) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+            PutOp::ClrF(reg, idx) | PutOp::PutF(reg, idx, _) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+            PutOp::ClrR(reg, idx) | PutOp::PutR(reg, idx, _) | PutOp::PutIfR(reg, idx, _) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+        }
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
         match self {
             PutOp::ClrA(reg, index) => {
@@ -237,6 +423,63 @@ impl InstructionSet for MoveOp {
     #[inline]
     fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::default() }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            MoveOp::MovA(reg, idx1, _) | MoveOp::DupA(reg, idx1, _) => {
+                BTreeSet::from([Reg::new(reg, idx1)])
+            }
+            MoveOp::MovF(reg, idx1, _) | MoveOp::DupF(reg, idx1, _) => {
+                BTreeSet::from([Reg::new(reg, idx1)])
+            }
+            MoveOp::MovR(reg, idx1, _) | MoveOp::DupR(reg, idx1, _) => {
+                BTreeSet::from([Reg::new(reg, idx1)])
+            }
+            MoveOp::SwpA(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            MoveOp::SwpF(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            MoveOp::CpyA(reg, idx, _, _) | MoveOp::CnvA(reg, idx, _, _) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+            MoveOp::CnvF(reg, idx, _, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CpyR(reg, idx, _, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CnvAF(reg, idx, _, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CnvFA(reg, idx, _, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::SpyAR(reg1, idx1, reg2, idx2) => {
+                BTreeSet::from([Reg::new(reg1, idx1), Reg::new(reg2, idx2)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            MoveOp::MovA(reg, idx1, idx2) | MoveOp::SwpA(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            MoveOp::MovF(reg, idx1, idx2) | MoveOp::SwpF(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            MoveOp::MovR(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            MoveOp::DupA(reg, _, idx2) => BTreeSet::from([Reg::new(reg, idx2)]),
+            MoveOp::DupF(reg, _, idx2) => BTreeSet::from([Reg::new(reg, idx2)]),
+            MoveOp::DupR(reg, _, idx2) => BTreeSet::from([Reg::new(reg, idx2)]),
+            MoveOp::CpyA(_, _, reg, idx) | MoveOp::CnvA(_, _, reg, idx) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+            MoveOp::CnvF(_, _, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CpyR(_, _, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CnvAF(_, _, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::CnvFA(_, _, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            MoveOp::SpyAR(reg1, idx1, reg2, idx2) => {
+                BTreeSet::from([Reg::new(reg1, idx1), Reg::new(reg2, idx2)])
+            }
+        }
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
         match self {
             MoveOp::MovA(reg, idx1, idx2) => {
@@ -319,23 +562,74 @@ impl InstructionSet for CmpOp {
     #[inline]
     fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::default() }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            CmpOp::GtA(_, reg, idx1, idx2)
+            | CmpOp::LtA(_, reg, idx1, idx2)
+            | CmpOp::EqA(_, reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            CmpOp::GtF(_, reg, idx1, idx2)
+            | CmpOp::LtF(_, reg, idx1, idx2)
+            | CmpOp::EqF(_, reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            CmpOp::GtR(reg, idx1, idx2) | CmpOp::LtR(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            CmpOp::EqR(_, reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            CmpOp::IfZA(reg, idx) | CmpOp::IfNA(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            CmpOp::IfZR(reg, idx) | CmpOp::IfNR(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            CmpOp::St(MergeFlag::Set, _, _) | CmpOp::StInv => BTreeSet::new(),
+            CmpOp::St(_, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            CmpOp::St(_, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    fn canonicalize(&mut self) {
+        // Comparisons of floats are not rewritten, since their ordering may be not antisymmetric
+        // for NaN values
+        *self = match *self {
+            CmpOp::GtA(flag, reg, idx1, idx2) => CmpOp::LtA(flag, reg, idx2, idx1),
+            CmpOp::GtR(reg, idx1, idx2) => CmpOp::LtR(reg, idx2, idx1),
+            CmpOp::EqA(flag, reg, idx1, idx2) => {
+                CmpOp::EqA(flag, reg, idx1.min(idx2), idx1.max(idx2))
+            }
+            CmpOp::EqF(flag, reg, idx1, idx2) => {
+                CmpOp::EqF(flag, reg, idx1.min(idx2), idx1.max(idx2))
+            }
+            CmpOp::EqR(flag, reg, idx1, idx2) => {
+                CmpOp::EqR(flag, reg, idx1.min(idx2), idx1.max(idx2))
+            }
+            op => op,
+        };
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
         match self {
             CmpOp::GtA(sign_flag, reg, idx1, idx2) => {
                 regs.st0 =
                     regs.get_both(reg, idx1, reg, idx2).map(|(val1, val2)| {
                         match bool::from(sign_flag) {
-                            true => val1.into_signed().cmp(&val2.into_signed()),
-                            false => val1.cmp(&val2),
+                            true => val1.cmp_signed(&val2),
+                            false => val1.cmp_unsigned(&val2),
                         }
                     }) == Some(Ordering::Greater);
             }
             CmpOp::GtF(eq_flag, reg, idx1, idx2) => {
-                regs.st0 = regs.get_both(reg, idx1, reg, idx2).map(|(val1, val2)| {
+                regs.st0 = regs.get_both(reg, idx1, reg, idx2).and_then(|(val1, val2)| {
                     if *eq_flag == FloatEqFlag::Rounding {
-                        val1.rounding_cmp(&val2)
+                        Some(val1.rounding_cmp(&val2))
                     } else {
-                        val1.cmp(&val2)
+                        val1.cmp_float(&val2, FloatOrder::Ieee)
                     }
                 }) == Some(Ordering::Greater);
             }
@@ -347,17 +641,17 @@ impl InstructionSet for CmpOp {
                 regs.st0 =
                     regs.get_both(reg, idx1, reg, idx2).map(|(val1, val2)| {
                         match bool::from(sign_flag) {
-                            true => val1.into_signed().cmp(&val2.into_signed()),
-                            false => val1.cmp(&val2),
+                            true => val1.cmp_signed(&val2),
+                            false => val1.cmp_unsigned(&val2),
                         }
                     }) == Some(Ordering::Less);
             }
             CmpOp::LtF(eq_flag, reg, idx1, idx2) => {
-                regs.st0 = regs.get_both(reg, idx1, reg, idx2).map(|(val1, val2)| {
+                regs.st0 = regs.get_both(reg, idx1, reg, idx2).and_then(|(val1, val2)| {
                     if *eq_flag == FloatEqFlag::Rounding {
-                        val1.rounding_cmp(&val2)
+                        Some(val1.rounding_cmp(&val2))
                     } else {
-                        val1.cmp(&val2)
+                        val1.cmp_float(&val2, FloatOrder::Ieee)
                     }
                 }) == Some(Ordering::Less);
             }
@@ -442,90 +736,172 @@ impl InstructionSet for ArithmeticOp {
         }
     }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            ArithmeticOp::AddA(_, reg, src, srcdst)
+            | ArithmeticOp::SubA(_, reg, src, srcdst)
+            | ArithmeticOp::MulA(_, reg, src, srcdst)
+            | ArithmeticOp::DivA(_, reg, src, srcdst) => {
+                BTreeSet::from([Reg::new(reg, src), Reg::new(reg, srcdst)])
+            }
+            ArithmeticOp::AddF(_, reg, src, srcdst)
+            | ArithmeticOp::SubF(_, reg, src, srcdst)
+            | ArithmeticOp::MulF(_, reg, src, srcdst)
+            | ArithmeticOp::DivF(_, reg, src, srcdst) => {
+                BTreeSet::from([Reg::new(reg, src), Reg::new(reg, srcdst)])
+            }
+            ArithmeticOp::Rem(reg1, idx1, reg2, idx2) => {
+                BTreeSet::from([Reg::new(reg1, idx1), Reg::new(reg2, idx2)])
+            }
+            ArithmeticOp::Stp(reg, idx, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            ArithmeticOp::Neg(reg, idx) | ArithmeticOp::Abs(reg, idx) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            ArithmeticOp::AddA(_, reg, _, srcdst)
+            | ArithmeticOp::SubA(_, reg, _, srcdst)
+            | ArithmeticOp::MulA(_, reg, _, srcdst)
+            | ArithmeticOp::DivA(_, reg, _, srcdst) => BTreeSet::from([Reg::new(reg, srcdst)]),
+            ArithmeticOp::AddF(_, reg, _, srcdst)
+            | ArithmeticOp::SubF(_, reg, _, srcdst)
+            | ArithmeticOp::MulF(_, reg, _, srcdst)
+            | ArithmeticOp::DivF(_, reg, _, srcdst) => BTreeSet::from([Reg::new(reg, srcdst)]),
+            ArithmeticOp::Rem(_, _, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            ArithmeticOp::Stp(reg, idx, _) => BTreeSet::from([Reg::new(reg, idx)]),
+            ArithmeticOp::Neg(reg, idx) | ArithmeticOp::Abs(reg, idx) => {
+                BTreeSet::from([Reg::new(reg, idx)])
+            }
+        }
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
+        /// Performs integer operation, detecting whether its result does not fit the layout. If
+        /// wrapping is requested, computes the wrapped result in case of overflow.
+        fn overflowing(
+            op: fn(Number, Number, IntFlags) -> Option<Number>,
+            val1: Number,
+            val2: Number,
+            flags: IntFlags,
+        ) -> (Option<Number>, bool) {
+            match op(val1, val2, IntFlags { wrap: false, ..flags }) {
+                Some(res) => (Some(res), false),
+                None if flags.wrap => (op(val1, val2, flags), true),
+                None => (None, true),
+            }
+        }
+
         let is_some = match self {
             ArithmeticOp::Abs(reg, idx) => {
-                regs.set(reg, idx, regs.get(reg, idx).and_then(Number::abs))
+                let val = regs.get(reg, idx);
+                let res = val.and_then(Number::abs);
+                regs.set_carry(val.is_some() && res.is_none());
+                regs.set(reg, idx, res)
             }
             ArithmeticOp::AddA(flags, reg, src, srcdst) => {
-                let res = regs
+                let (res, carry) = regs
                     .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.int_add(val2, *flags));
+                    .map(|(val1, val2)| overflowing(Number::int_add, val1, val2, *flags))
+                    .unwrap_or_default();
+                regs.set_carry(carry);
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::AddF(flags, reg, src, srcdst) => {
-                let res: Option<Number> = regs
-                    .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.float_add(val2, *flags).into());
+                let res: Option<Number> =
+                    regs.get_both(reg, src, reg, srcdst).and_then(|(val1, val2)| {
+                        let (res, status) = val1.float_add_flags(val2, *flags);
+                        regs.fs0 |= status;
+                        res.into()
+                    });
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::SubA(flags, reg, src, srcdst) => {
-                let res = regs
+                let (res, carry) = regs
                     .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.int_sub(val2, *flags));
+                    .map(|(val1, val2)| overflowing(Number::int_sub, val1, val2, *flags))
+                    .unwrap_or_default();
+                regs.set_carry(carry);
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::SubF(flags, reg, src, srcdst) => {
-                let res: Option<Number> = regs
-                    .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.float_sub(val2, *flags).into());
+                let res: Option<Number> =
+                    regs.get_both(reg, src, reg, srcdst).and_then(|(val1, val2)| {
+                        let (res, status) = val1.float_sub_flags(val2, *flags);
+                        regs.fs0 |= status;
+                        res.into()
+                    });
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::MulA(flags, reg, src, srcdst) => {
-                let res = regs
+                let (res, carry) = regs
                     .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.int_mul(val2, *flags));
+                    .map(|(val1, val2)| overflowing(Number::int_mul, val1, val2, *flags))
+                    .unwrap_or_default();
+                regs.set_carry(carry);
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::MulF(flags, reg, src, srcdst) => {
-                let res: Option<Number> = regs
-                    .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.float_mul(val2, *flags).into());
+                let res: Option<Number> =
+                    regs.get_both(reg, src, reg, srcdst).and_then(|(val1, val2)| {
+                        let (res, status) = val1.float_mul_flags(val2, *flags);
+                        regs.fs0 |= status;
+                        res.into()
+                    });
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::DivA(flags, reg, src, srcdst) => {
-                let res = regs
+                let (res, carry) = regs
                     .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.int_div(val2, *flags));
+                    .map(|(val1, val2)| {
+                        let res = val1.int_div(val2, *flags);
+                        (res, res.is_none() && !val2.is_zero())
+                    })
+                    .unwrap_or_default();
+                regs.set_carry(carry);
                 regs.set(reg, srcdst, res)
             }
             ArithmeticOp::DivF(flags, reg, src, srcdst) => {
-                let res: Option<Number> = regs
-                    .get_both(reg, src, reg, srcdst)
-                    .and_then(|(val1, val2)| val1.float_div(val2, *flags).into());
-                regs.set(reg, srcdst, res) && !res.map(Number::is_nan).unwrap_or(false)
+                let res: Option<Number> =
+                    regs.get_both(reg, src, reg, srcdst).and_then(|(val1, val2)| {
+                        let (res, status) = val1.float_div_flags(val2, *flags);
+                        regs.fs0 |= status;
+                        res.into()
+                    });
+                regs.set(reg, srcdst, res)
             }
             ArithmeticOp::Rem(reg1, idx1, reg2, idx2) => {
                 let res =
                     regs.get_both(reg1, idx1, reg2, idx2).and_then(|(val1, val2)| val1.rem(val2));
+                regs.set_carry(false);
                 regs.set(reg2, idx2, res)
             }
-            ArithmeticOp::Stp(reg, idx, step) => regs.set(
-                reg,
-                idx,
-                regs.get(reg, idx).and_then(|val| {
+            ArithmeticOp::Stp(reg, idx, step) => {
+                let val = regs.get(reg, idx);
+                let res = val.and_then(|val| {
+                    let flags = IntFlags { signed: false, wrap: false };
+                    let mut n = Number::from(step.as_i8().unsigned_abs());
+                    let reshaped = n.reshape(val.layout());
+                    debug_assert!(reshaped, "reshape target byte length is always greater");
                     if step.as_i8() < 0 {
-                        let mut n = Number::from(-step.as_i8());
-                        debug_assert!(
-                            n.reshape(val.layout()),
-                            "reshape target byte length is always greater"
-                        );
-                        val.int_sub(n, IntFlags { signed: false, wrap: false })
+                        val.int_sub(n, flags)
                     } else {
-                        let mut n = Number::from(*step);
-                        debug_assert!(
-                            n.reshape(val.layout()),
-                            "reshape target byte length is always greater"
-                        );
-                        val.int_add(n, IntFlags { signed: false, wrap: false })
+                        val.int_add(n, flags)
                     }
-                }),
-            ),
+                });
+                regs.set_carry(val.is_some() && res.is_none());
+                regs.set(reg, idx, res)
+            }
             ArithmeticOp::Neg(reg, idx) => {
-                regs.set(reg, idx, regs.get(reg, idx).and_then(Number::neg))
+                let val = regs.get(reg, idx);
+                let res = val.and_then(Number::neg);
+                regs.set_carry(val.is_some() && res.is_none());
+                regs.set(reg, idx, res)
             }
         };
-        regs.st0 = is_some;
+        regs.set_status(is_some);
         ExecStep::Next
     }
 }
@@ -536,6 +912,61 @@ impl InstructionSet for BitwiseOp {
     #[inline]
     fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::default() }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            BitwiseOp::And(reg, src1, src2, _)
+            | BitwiseOp::Or(reg, src1, src2, _)
+            | BitwiseOp::Xor(reg, src1, src2, _) => {
+                BTreeSet::from([Reg::new(reg, src1), Reg::new(reg, src2)])
+            }
+            BitwiseOp::Not(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            BitwiseOp::Shl(reg1, shift, reg2, srcdst)
+            | BitwiseOp::Scl(reg1, shift, reg2, srcdst)
+            | BitwiseOp::Scr(reg1, shift, reg2, srcdst) => {
+                BTreeSet::from([Reg::new(reg1, shift), Reg::new(reg2, srcdst)])
+            }
+            BitwiseOp::ShrA(_, reg1, shift, reg2, srcdst) => {
+                BTreeSet::from([Reg::new(reg1, shift), Reg::new(reg2, srcdst)])
+            }
+            BitwiseOp::ShrR(reg1, shift, reg2, srcdst) => {
+                BTreeSet::from([Reg::new(reg1, shift), Reg::new(reg2, srcdst)])
+            }
+            BitwiseOp::RevA(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            BitwiseOp::RevR(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            BitwiseOp::And(reg, _, _, dst)
+            | BitwiseOp::Or(reg, _, _, dst)
+            | BitwiseOp::Xor(reg, _, _, dst) => BTreeSet::from([Reg::new(reg, dst)]),
+            BitwiseOp::Not(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            BitwiseOp::Shl(_, _, reg, srcdst)
+            | BitwiseOp::Scl(_, _, reg, srcdst)
+            | BitwiseOp::Scr(_, _, reg, srcdst) => BTreeSet::from([Reg::new(reg, srcdst)]),
+            BitwiseOp::ShrA(_, _, _, reg, srcdst) => BTreeSet::from([Reg::new(reg, srcdst)]),
+            BitwiseOp::ShrR(_, _, reg, srcdst) => BTreeSet::from([Reg::new(reg, srcdst)]),
+            BitwiseOp::RevA(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            BitwiseOp::RevR(reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+        }
+    }
+
+    fn canonicalize(&mut self) {
+        *self = match *self {
+            BitwiseOp::And(reg, src1, src2, dst) => {
+                BitwiseOp::And(reg, src1.min(src2), src1.max(src2), dst)
+            }
+            BitwiseOp::Or(reg, src1, src2, dst) => {
+                BitwiseOp::Or(reg, src1.min(src2), src1.max(src2), dst)
+            }
+            BitwiseOp::Xor(reg, src1, src2, dst) => {
+                BitwiseOp::Xor(reg, src1.min(src2), src1.max(src2), dst)
+            }
+            op => op,
+        };
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
         fn shl(original: &[u8], shift: usize, n_bytes: usize) -> [u8; 1024] {
             let mut ret = [0u8; 1024];
@@ -696,25 +1127,86 @@ impl InstructionSet for BytesOp {
     #[inline]
     fn complexity(&self) -> u64 { 5 }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            BytesOp::Put(_, _, _) => BTreeSet::new(),
+            BytesOp::Mov(src, _) | BytesOp::Rev(src, _) => BTreeSet::from([Reg::S(*src)]),
+            BytesOp::Swp(reg1, reg2) | BytesOp::Eq(reg1, reg2) | BytesOp::Find(reg1, reg2) => {
+                BTreeSet::from([Reg::S(*reg1), Reg::S(*reg2)])
+            }
+            BytesOp::Fill(reg, offset1, offset2, value, _) => BTreeSet::from([
+                Reg::S(*reg),
+                Reg::new(RegA::A16, offset1),
+                Reg::new(RegA::A16, offset2),
+                Reg::new(RegA::A8, value),
+            ]),
+            BytesOp::Len(src, _, _) => BTreeSet::from([Reg::S(*src)]),
+            BytesOp::Cnt(src, byte, _) => BTreeSet::from([Reg::S(*src), Reg::new(RegA::A8, byte)]),
+            BytesOp::Con(reg1, reg2, n, _, _) => {
+                BTreeSet::from([Reg::S(*reg1), Reg::S(*reg2), Reg::new(RegA::A16, n)])
+            }
+            BytesOp::Extr(src, _, _, offset) => {
+                BTreeSet::from([Reg::S(*src), Reg::new(RegA::A16, offset)])
+            }
+            BytesOp::Inj(src, dst, index, offset) => {
+                BTreeSet::from([Reg::S(*src), Reg::new(dst, index), Reg::new(RegA::A16, offset)])
+            }
+            BytesOp::Join(src1, src2, _) => BTreeSet::from([Reg::S(*src1), Reg::S(*src2)]),
+            BytesOp::Splt(_, offset, src, _, _) => {
+                BTreeSet::from([Reg::S(*src), Reg::new(RegA::A16, offset)])
+            }
+            BytesOp::Ins(_, offset, src, dst) => {
+                BTreeSet::from([Reg::S(*src), Reg::S(*dst), Reg::new(RegA::A16, offset)])
+            }
+            BytesOp::Del(_, reg1, offset1, reg2, offset2, _, _, src, _) => {
+                BTreeSet::from([Reg::S(*src), Reg::new(reg1, offset1), Reg::new(reg2, offset2)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            BytesOp::Put(dst, _, _)
+            | BytesOp::Fill(dst, _, _, _, _)
+            | BytesOp::Join(_, _, dst)
+            | BytesOp::Ins(_, _, _, dst)
+            | BytesOp::Del(_, _, _, _, _, _, _, _, dst)
+            | BytesOp::Rev(_, dst) => BTreeSet::from([Reg::S(*dst)]),
+            BytesOp::Mov(reg1, reg2) | BytesOp::Swp(reg1, reg2) => {
+                BTreeSet::from([Reg::S(*reg1), Reg::S(*reg2)])
+            }
+            BytesOp::Splt(_, _, _, dst1, dst2) => BTreeSet::from([Reg::S(*dst1), Reg::S(*dst2)]),
+            BytesOp::Len(_, reg, dst) => BTreeSet::from([Reg::new(reg, dst)]),
+            BytesOp::Cnt(_, _, dst) => BTreeSet::from([Reg::new(RegA::A16, dst)]),
+            BytesOp::Eq(_, _) => BTreeSet::new(),
+            BytesOp::Con(_, _, _, offset_dst, len_dst) => {
+                BTreeSet::from([Reg::new(RegA::A16, offset_dst), Reg::new(RegA::A16, len_dst)])
+            }
+            BytesOp::Find(_, _) => BTreeSet::from([Reg::new(RegA::A16, Reg32::Reg0)]),
+            BytesOp::Extr(_, dst, index, _) => BTreeSet::from([Reg::new(dst, index)]),
+            BytesOp::Inj(src, dst, index, _) => {
+                BTreeSet::from([Reg::S(*src), Reg::new(dst, index)])
+            }
+        }
+    }
+
     #[allow(warnings)]
     fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
         match self {
             BytesOp::Put(reg, bytes, st0) => {
-                regs.s16[reg.as_usize()] = Some(*bytes.clone());
-                if *st0 {
+                if !regs.put_s(*reg, Some(*bytes.clone())) || *st0 {
                     regs.st0 = false
                 }
             }
             BytesOp::Mov(reg1, reg2) => {
-                let bs = regs.s16[reg1.as_usize()].clone();
-                regs.s16[reg1.as_usize()] = None;
-                regs.s16[reg2.as_usize()] = bs;
+                let bs = regs.take_s(*reg1);
+                regs.put_s(*reg2, bs);
             }
             BytesOp::Swp(reg1, reg2) => {
-                let bs1 = regs.s16[reg1.as_usize()].clone();
-                let bs2 = regs.s16[reg2.as_usize()].clone();
-                regs.s16[reg1.as_usize()] = bs2;
-                regs.s16[reg2.as_usize()] = bs1;
+                let bs1 = regs.take_s(*reg1);
+                let bs2 = regs.take_s(*reg2);
+                regs.put_s(*reg1, bs2);
+                regs.put_s(*reg2, bs1);
             }
             BytesOp::Fill(reg, offset1, offset2, value, flag) => {
                 let mut f = || -> Option<()> {
@@ -722,18 +1214,13 @@ impl InstructionSet for BytesOp {
                     let o2 = regs.a16[offset2.to_usize()]?;
                     let range = o1..o2;
                     let val = regs.a8[value.to_usize()]?;
-                    let ref mut bs = regs.s16[reg.as_usize()];
-                    let bs = if let Some(s) = bs {
-                        s
-                    } else {
-                        *bs = Some(ByteStr::default());
-                        bs.as_mut().expect("rust optionals are broken")
-                    };
+                    let mut bs = regs.take_s(*reg).unwrap_or_default();
                     if bs.len() <= range.end && *flag == ExtendFlag::Fail {
+                        regs.put_s(*reg, Some(bs));
                         return None;
                     }
                     bs.fill(range, val);
-                    Some(())
+                    regs.put_s(*reg, Some(bs)).then_some(())
                 };
                 f().unwrap_or_else(|| regs.st0 = false);
             }
@@ -755,7 +1242,7 @@ impl InstructionSet for BytesOp {
             BytesOp::Cnt(src, byte, dst) => {
                 let mut f = || -> Option<()> {
                     let val = regs.a8[*byte as u8 as usize]?;
-                    let bs = regs.s16[src.as_usize()].as_ref()?;
+                    let bs = regs.get_s(*src)?;
                     let count = bs.as_ref().into_iter().filter(|b| **b == val).count();
                     if !RegA::A16.int_layout().fits_usize(count) {
                         return None;
@@ -797,12 +1284,11 @@ impl InstructionSet for BytesOp {
                     let mut s = regs.get_s(*reg1)?.clone();
                     let bs = s.as_mut();
                     bs.reverse();
-                    regs.s16[reg2.as_usize()] = Some(s);
-                    Some(())
+                    regs.put_s(*reg2, Some(s)).then_some(())
                 };
                 f().unwrap_or_else(|| {
                     regs.st0 = false;
-                    regs.s16[reg2.as_usize()] = None;
+                    regs.put_s(*reg2, None);
                 })
             }
             BytesOp::Con(reg1, reg2, n, offset_dst, len_dst) => {
@@ -866,8 +1352,7 @@ impl InstructionSet for BytesOp {
                     let end = offset.saturating_add(dst.layout().bytes() - 1);
                     s.adjust_len(end);
                     s.as_mut()[offset as usize..=end as usize].copy_from_slice(val.as_ref());
-                    regs.s16[src.as_usize()] = Some(s);
-                    Some(())
+                    regs.put_s(*src, Some(s)).then_some(())
                 };
                 f().unwrap_or_else(|| {
                     regs.st0 = false;
@@ -885,12 +1370,11 @@ impl InstructionSet for BytesOp {
                     d.adjust_len(len);
                     let mut d = ByteStr::with(s1);
                     d.as_mut()[s1.len() as usize..].copy_from_slice(s2.as_ref());
-                    regs.s16[dst.as_usize()] = Some(d);
-                    Some(())
+                    regs.put_s(*dst, Some(d)).then_some(())
                 };
                 f().unwrap_or_else(|| {
                     regs.st0 = false;
-                    regs.s16[dst.as_usize()] = None;
+                    regs.put_s(*dst, None);
                 })
             }
             BytesOp::Splt(flag, offset, src, dst1, dst2) => {
@@ -920,6 +1404,22 @@ impl InstructionSet for DigestOp {
     #[inline]
     fn complexity(&self) -> u64 { 100 }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            DigestOp::Ripemd(src, _) | DigestOp::Sha256(src, _) | DigestOp::Sha512(src, _) => {
+                BTreeSet::from([Reg::S(*src)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            DigestOp::Ripemd(_, dst) => BTreeSet::from([Reg::new(RegR::R160, dst)]),
+            DigestOp::Sha256(_, dst) => BTreeSet::from([Reg::new(RegR::R256, dst)]),
+            DigestOp::Sha512(_, dst) => BTreeSet::from([Reg::new(RegR::R512, dst)]),
+        }
+    }
+
     fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
         let none;
         match self {
@@ -972,6 +1472,30 @@ impl InstructionSet for Secp256k1Op {
     #[inline]
     fn complexity(&self) -> u64 { 1000 }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Secp256k1Op::Gen(src, _) => BTreeSet::from([Reg::new(RegR::R256, src)]),
+            Secp256k1Op::Mul(block, scal, src, _) => {
+                let reg = block.into_reg(256).expect("register set does not match standard");
+                BTreeSet::from([Reg::new(reg, scal), Reg::new(RegR::R512, src)])
+            }
+            Secp256k1Op::Add(src, srcdst) => {
+                BTreeSet::from([Reg::new(RegR::R512, src), Reg::new(RegR::R512, srcdst)])
+            }
+            Secp256k1Op::Neg(src, _) => BTreeSet::from([Reg::new(RegR::R512, src)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Secp256k1Op::Gen(_, dst) | Secp256k1Op::Neg(_, dst) => {
+                BTreeSet::from([Reg::new(RegR::R512, dst)])
+            }
+            Secp256k1Op::Mul(_, _, _, dst) => BTreeSet::from([Reg::new(RegR::R512, dst)]),
+            Secp256k1Op::Add(_, srcdst) => BTreeSet::from([Reg::new(RegR::R512, srcdst)]),
+        }
+    }
+
     #[cfg(not(feature = "secp256k1"))]
     fn exec(&self, _: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
         unimplemented!("AluVM runtime compiled without support for Secp256k1 instructions")
@@ -1082,6 +1606,31 @@ impl InstructionSet for Curve25519Op {
     #[inline]
     fn complexity(&self) -> u64 { 1000 }
 
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Curve25519Op::Gen(src, _) => BTreeSet::from([Reg::new(RegR::R256, src)]),
+            Curve25519Op::Mul(block, scal, src, _) => {
+                let reg = block.into_reg(256).expect("register set does not match standard");
+                BTreeSet::from([Reg::new(reg, scal), Reg::new(reg, src)])
+            }
+            Curve25519Op::Add(lhs, rhs, _, _) => {
+                BTreeSet::from([Reg::new(RegR::R512, lhs), Reg::new(RegR::R512, rhs)])
+            }
+            Curve25519Op::Neg(src, _) => BTreeSet::from([Reg::new(RegR::R512, src)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Curve25519Op::Gen(_, dst) | Curve25519Op::Neg(_, dst) => {
+                BTreeSet::from([Reg::new(RegR::R512, dst)])
+            }
+            Curve25519Op::Mul(_, _, _, dst) | Curve25519Op::Add(_, _, dst, _) => {
+                BTreeSet::from([Reg::new(RegR::R512, dst)])
+            }
+        }
+    }
+
     #[cfg(not(feature = "curve25519"))]
     fn exec(&self, _: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
         unimplemented!("AluVM runtime compiled without support for Curve25519 instructions")
@@ -1151,22 +1700,1937 @@ impl InstructionSet for Curve25519Op {
     }
 }
 
-impl InstructionSet for ReservedOp {
+impl InstructionSet for EnvOp {
+    type Context<'ctx> = Env;
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALURE);
+        set
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            EnvOp::Lib(dst) | EnvOp::Ctx(dst) => BTreeSet::from([Reg::new(RegR::R256, dst)]),
+            EnvOp::Pos(dst) => BTreeSet::from([Reg::new(RegA::A16, dst)]),
+            EnvOp::Isa(dst) => BTreeSet::from([Reg::S(*dst)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, env: &Env) -> ExecStep {
+        let is_some = match self {
+            EnvOp::Lib(dst) => regs.set(RegR::R256, dst, site.lib.to_byte_array()),
+            EnvOp::Pos(dst) => regs.set(RegA::A16, dst, site.pos),
+            EnvOp::Isa(dst) => regs.put_s(*dst, Some(ByteStr::with(&env.isae))),
+            EnvOp::Ctx(dst) => regs.set(RegR::R256, dst, env.value),
+        };
+        regs.set_status(is_some);
+        ExecStep::Next
+    }
+}
+
+/// Compares two little-endian unsigned numbers of the same length in constant time.
+fn ct_cmp(val1: &[u8], val2: &[u8]) -> Ordering {
+    debug_assert_eq!(val1.len(), val2.len());
+    let (mut gt, mut lt) = (Choice::from(0), Choice::from(0));
+    // Bytes are processed starting from the least significant one, such that each next byte
+    // overrides the result unless it is equal
+    for (byte1, byte2) in val1.iter().zip(val2) {
+        let byte_eq = byte1.ct_eq(byte2);
+        gt = byte1.ct_gt(byte2) | (byte_eq & gt);
+        lt = byte2.ct_gt(byte1) | (byte_eq & lt);
+    }
+    match (bool::from(gt), bool::from(lt)) {
+        (true, _) => Ordering::Greater,
+        (_, true) => Ordering::Less,
+        _ => Ordering::Equal,
+    }
+}
+
+/// Checks equality of two byte strings in constant time with respect to their content.
+fn ct_eq(val1: &[u8], val2: &[u8]) -> bool { bool::from(val1.ct_eq(val2)) }
+
+impl InstructionSet for CtOp {
     type Context<'ctx> = ();
 
     #[inline]
-    fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::default() }
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUCT);
+        set
+    }
 
-    fn exec(&self, regs: &mut CoreRegs, site: LibSite, ctx: &()) -> ExecStep {
-        ControlFlowOp::Fail.exec(regs, site, ctx)
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            CtOp::EqR(reg, idx1, idx2)
+            | CtOp::GtR(reg, idx1, idx2)
+            | CtOp::LtR(reg, idx1, idx2) => {
+                BTreeSet::from([Reg::new(reg, idx1), Reg::new(reg, idx2)])
+            }
+            CtOp::EqS(reg1, reg2) => BTreeSet::from([Reg::S(*reg1), Reg::S(*reg2)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        regs.st0 = match self {
+            CtOp::EqR(reg, idx1, idx2)
+            | CtOp::GtR(reg, idx1, idx2)
+            | CtOp::LtR(reg, idx1, idx2) => {
+                let expected = match self {
+                    CtOp::EqR(..) => Ordering::Equal,
+                    CtOp::GtR(..) => Ordering::Greater,
+                    _ => Ordering::Less,
+                };
+                regs.get_both(reg, idx1, reg, idx2)
+                    .map(|(val1, val2)| ct_cmp(val1.as_ref(), val2.as_ref()) == expected)
+                    .unwrap_or(false)
+            }
+            CtOp::EqS(reg1, reg2) => regs
+                .get_both_s(*reg1, *reg2)
+                .map(|(val1, val2)| ct_eq(val1.as_ref(), val2.as_ref()))
+                .unwrap_or(false),
+        };
+        ExecStep::Next
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(feature = "secp256k1")]
-    use crate::reg::{Reg8, RegBlockAR};
+/// Computes ChaCha20 block function as defined in RFC 8439.
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64] {
+    fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(16);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(12);
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(8);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(7);
+    }
+
+    let word = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mut init = [0u32; 16];
+    init[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for (no, chunk) in key.chunks(4).enumerate() {
+        init[4 + no] = word(chunk);
+    }
+    init[12] = counter;
+    for (no, chunk) in nonce.chunks(4).enumerate() {
+        init[13 + no] = word(chunk);
+    }
+
+    let mut state = init;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut block = [0u8; 64];
+    for (no, chunk) in block.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&state[no].wrapping_add(init[no]).to_le_bytes());
+    }
+    block
+}
+
+impl InstructionSet for PrngOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALURND);
+        set
+    }
+
+    #[inline]
+    fn complexity(&self) -> u64 { 100 }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            PrngOp::RndA(_, _, key, counter) | PrngOp::RndR(_, _, key, counter) => {
+                BTreeSet::from([Reg::new(RegR::R256, key), Reg::new(RegA::A32, counter)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            PrngOp::RndA(reg, dst, _, counter) => {
+                BTreeSet::from([Reg::new(reg, dst), Reg::new(RegA::A32, counter)])
+            }
+            PrngOp::RndR(reg, dst, _, counter) => {
+                BTreeSet::from([Reg::new(reg, dst), Reg::new(RegA::A32, counter)])
+            }
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let (reg, dst, key, counter) = match *self {
+            PrngOp::RndA(reg, dst, key, counter) => (RegAFR::A(reg), dst, key, counter),
+            PrngOp::RndR(reg, dst, key, counter) => (RegAFR::R(reg), dst, key, counter),
+        };
+        let len = reg.bytes() as usize;
+        let blocks = (len + 63) / 64;
+        let res = regs.get_both(RegR::R256, key, RegA::A32, counter).and_then(|(key, start)| {
+            let start = u32::from(start);
+            let end = start.checked_add(blocks as u32)?;
+            let mut k = [0u8; 32];
+            k.copy_from_slice(key.as_ref());
+            let mut stream = Vec::with_capacity(blocks * 64);
+            for no in start..end {
+                stream.extend_from_slice(&chacha20_block(&k, no, &[0u8; 12]));
+            }
+            Some((end, Number::from_slice(&stream[..len])))
+        });
+        match res {
+            Some((end, val)) => {
+                regs.set(RegA::A32, counter, end);
+                regs.set(reg, dst, val);
+            }
+            None => {
+                regs.set(reg, dst, MaybeNumber::none());
+                regs.st0 = false;
+            }
+        }
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for ModOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUMOD);
+        set
+    }
+
+    fn complexity(&self) -> u64 {
+        match self {
+            ModOp::AddMod(..) => 10,
+            ModOp::MulMod(..) => 100,
+            // Exponentiation performs two multiplications per exponent bit
+            ModOp::ExpMod(reg, ..) => 100 * reg.bits() as u64,
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            ModOp::AddMod(reg, src1, src2, modulus, _)
+            | ModOp::MulMod(reg, src1, src2, modulus, _)
+            | ModOp::ExpMod(reg, src1, src2, modulus, _) => {
+                BTreeSet::from([Reg::new(reg, src1), Reg::new(reg, src2), Reg::new(reg, modulus)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            ModOp::AddMod(reg, .., dst)
+            | ModOp::MulMod(reg, .., dst)
+            | ModOp::ExpMod(reg, .., dst) => BTreeSet::from([Reg::new(reg, dst)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let (reg, src1, src2, modulus, dst) = match *self {
+            ModOp::AddMod(reg, src1, src2, modulus, dst)
+            | ModOp::MulMod(reg, src1, src2, modulus, dst)
+            | ModOp::ExpMod(reg, src1, src2, modulus, dst) => (reg, src1, src2, modulus, dst),
+        };
+        let op = match self {
+            ModOp::AddMod(..) => modular::add_mod,
+            ModOp::MulMod(..) => modular::mul_mod,
+            ModOp::ExpMod(..) => modular::pow_mod,
+        };
+        let res = regs.get_both(reg, src1, reg, src2).and_then(|(val1, val2)| {
+            let modulus = (*regs.get(reg, modulus))?;
+            op(&val1[..], &val2[..], &modulus[..]).map(Number::from_slice)
+        });
+        match res {
+            Some(val) => {
+                regs.set(reg, dst, val);
+            }
+            None => {
+                regs.set(reg, dst, MaybeNumber::none());
+                regs.st0 = false;
+            }
+        }
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for EncOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUENC);
+        set
+    }
+
+    fn complexity(&self) -> u64 {
+        match self {
+            // Base58 conversion time is quadratic on the data length
+            EncOp::Enc(BaseFlag::Base58, ..) | EncOp::Dec(BaseFlag::Base58, ..) => 1000,
+            _ => 100,
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            EncOp::Enc(_, src, _) | EncOp::Dec(_, src, _) | EncOp::Bech32Dec(src, _, _) => {
+                BTreeSet::from([Reg::S(src)])
+            }
+            EncOp::Bech32Enc(hrp, data, _) => BTreeSet::from([Reg::S(hrp), Reg::S(data)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            EncOp::Enc(_, _, dst) | EncOp::Dec(_, _, dst) | EncOp::Bech32Enc(_, _, dst) => {
+                BTreeSet::from([Reg::S(dst)])
+            }
+            EncOp::Bech32Dec(_, hrp, data) => BTreeSet::from([Reg::S(hrp), Reg::S(data)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let (dst, res) = match *self {
+            EncOp::Enc(base, src, dst) => {
+                let res = regs.get_s(src).map(|src| match base {
+                    BaseFlag::Base16 => basen::base16_encode(src.as_ref()),
+                    BaseFlag::Base32 => basen::base32_encode(src.as_ref()),
+                    BaseFlag::Base58 => basen::base58_encode(src.as_ref()),
+                    BaseFlag::Base64 => basen::base64_encode(src.as_ref()),
+                });
+                (dst, res)
+            }
+            EncOp::Dec(base, src, dst) => {
+                let res = regs.get_s(src).and_then(|src| match base {
+                    BaseFlag::Base16 => basen::base16_decode(src.as_ref()),
+                    BaseFlag::Base32 => basen::base32_decode(src.as_ref()),
+                    BaseFlag::Base58 => basen::base58_decode(src.as_ref()),
+                    BaseFlag::Base64 => basen::base64_decode(src.as_ref()),
+                });
+                (dst, res)
+            }
+            EncOp::Bech32Enc(hrp, data, dst) => {
+                let res = regs
+                    .get_both_s(hrp, data)
+                    .and_then(|(hrp, data)| basen::bech32m_encode(hrp.as_ref(), data.as_ref()));
+                (dst, res)
+            }
+            EncOp::Bech32Dec(src, hrp_dst, dst) => {
+                let res = regs.get_s(src).and_then(|src| basen::bech32m_decode(src.as_ref()));
+                let (hrp, res) = match res {
+                    Some((hrp, data)) => (Some(hrp), Some(data)),
+                    None => (None, None),
+                };
+                if !regs.put_s(hrp_dst, hrp.map(ByteStr::with)) {
+                    regs.st0 = false;
+                }
+                (dst, res)
+            }
+        };
+        let res = res.and_then(|res| ByteStr::try_from(res.as_slice()).ok());
+        let is_some = res.is_some();
+        if !regs.put_s(dst, res) || !is_some {
+            regs.st0 = false;
+        }
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for RelJumpOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUREL);
+        set
+    }
+
+    #[inline]
+    fn complexity(&self) -> u64 { 2 }
+
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, _: &()) -> ExecStep {
+        if matches!(self, RelJumpOp::Jif(_)) && !regs.st0 {
+            return ExecStep::Next;
+        }
+        let Some(pos) = self.jump_destination(site.pos) else {
+            regs.st0 = false;
+            return ExecStep::Stop;
+        };
+        let res = match self {
+            RelJumpOp::Jmp(_) | RelJumpOp::Jif(_) => regs.jmp(),
+            RelJumpOp::Routine(_) => {
+                // Location of the instruction following the current one, to which `ret` returns
+                let ret_site =
+                    site.pos.checked_add(self.byte_count()).map(|pos| LibSite::with(pos, site.lib));
+                regs.call(ret_site)
+            }
+        };
+        res.map(|_| ExecStep::Jump(pos)).unwrap_or(ExecStep::Stop)
+    }
+}
+
+impl InstructionSet for ImmOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUIMM);
+        set
+    }
+
+    #[inline]
+    fn complexity(&self) -> u64 { 2 }
+
+    #[inline]
+    fn src_regs(&self) -> BTreeSet<Reg> { self.to_put().src_regs() }
+
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { self.to_put().dst_regs() }
+
+    #[inline]
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, _: &()) -> ExecStep {
+        self.to_put().exec(regs, site, &())
+    }
+}
+
+impl InstructionSet for FrameOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUFRM);
+        set
+    }
+
+    /// Complexity grows with the number of the registers in the range.
+    fn complexity(&self) -> u64 {
+        match self {
+            FrameOp::PushA(_, first, last)
+            | FrameOp::PushF(_, first, last)
+            | FrameOp::PushR(_, first, last)
+            | FrameOp::PopA(_, first, last)
+            | FrameOp::PopF(_, first, last)
+            | FrameOp::PopR(_, first, last) => {
+                2 + u8::from(last).saturating_sub(u8::from(first)) as u64
+            }
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FrameOp::PushA(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PushF(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PushR(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PopA(..) | FrameOp::PopF(..) | FrameOp::PopR(..) => BTreeSet::new(),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FrameOp::PopA(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PopF(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PopR(reg, first, last) => frame_regs(reg, first, last),
+            FrameOp::PushA(..) | FrameOp::PushF(..) | FrameOp::PushR(..) => BTreeSet::new(),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let success = match *self {
+            FrameOp::PushA(reg, first, last) => regs.push_frame(reg, first, last),
+            FrameOp::PushF(reg, first, last) => regs.push_frame(reg, first, last),
+            FrameOp::PushR(reg, first, last) => regs.push_frame(reg, first, last),
+            FrameOp::PopA(reg, first, last) => regs.pop_frame(reg, first, last),
+            FrameOp::PopF(reg, first, last) => regs.pop_frame(reg, first, last),
+            FrameOp::PopR(reg, first, last) => regs.pop_frame(reg, first, last),
+        };
+        if !success {
+            regs.st0 = false;
+        }
+        ExecStep::Next
+    }
+}
+
+/// Lists registers of the bank from `first` to `last` index (inclusive).
+fn frame_regs(reg: impl Into<RegAFR>, first: Reg32, last: Reg32) -> BTreeSet<Reg> {
+    let reg = reg.into();
+    Reg32::ALL
+        .iter()
+        .filter(|idx| (first..=last).contains(*idx))
+        .map(|idx| Reg::new(reg, idx))
+        .collect()
+}
+
+impl InstructionSet for XferOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUXFR);
+        set
+    }
+
+    /// Complexity grows with the number of the transferred values.
+    fn complexity(&self) -> u64 {
+        match self {
+            XferOp::MovA(_, set, _)
+            | XferOp::MovF(_, set, _)
+            | XferOp::MovR(_, set, _)
+            | XferOp::SwpA(_, set, _)
+            | XferOp::SwpF(_, set, _)
+            | XferOp::SwpR(_, set, _) => 1 + set.len() as u64,
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            XferOp::MovA(reg, src, _) => set_regs(*reg, src),
+            XferOp::MovF(reg, src, _) => set_regs(*reg, src),
+            XferOp::MovR(reg, src, _) => set_regs(*reg, src),
+            XferOp::SwpA(reg, set1, set2) => set_regs(*reg, set1.union(set2)),
+            XferOp::SwpF(reg, set1, set2) => set_regs(*reg, set1.union(set2)),
+            XferOp::SwpR(reg, set1, set2) => set_regs(*reg, set1.union(set2)),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            XferOp::MovA(reg, set1, set2) | XferOp::SwpA(reg, set1, set2) => {
+                set_regs(*reg, set1.union(set2))
+            }
+            XferOp::MovF(reg, set1, set2) | XferOp::SwpF(reg, set1, set2) => {
+                set_regs(*reg, set1.union(set2))
+            }
+            XferOp::MovR(reg, set1, set2) | XferOp::SwpR(reg, set1, set2) => {
+                set_regs(*reg, set1.union(set2))
+            }
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let (reg, set1, set2, swap) = match self {
+            XferOp::MovA(reg, set1, set2) => (RegAFR::A(*reg), set1, set2, false),
+            XferOp::MovF(reg, set1, set2) => (RegAFR::F(*reg), set1, set2, false),
+            XferOp::MovR(reg, set1, set2) => (RegAFR::R(*reg), set1, set2, false),
+            XferOp::SwpA(reg, set1, set2) => (RegAFR::A(*reg), set1, set2, true),
+            XferOp::SwpF(reg, set1, set2) => (RegAFR::F(*reg), set1, set2, true),
+            XferOp::SwpR(reg, set1, set2) => (RegAFR::R(*reg), set1, set2, true),
+        };
+        if set1.len() != set2.len() || (swap && !set1.is_disjoint(set2)) {
+            regs.st0 = false;
+            return ExecStep::Next;
+        }
+        let vals1 = set1.iter().map(|idx| regs.get(reg, *idx)).collect::<Vec<_>>();
+        let vals2 = set2.iter().map(|idx| regs.get(reg, *idx)).collect::<Vec<_>>();
+        for (idx, val) in set1.iter().zip(vals2) {
+            regs.set(reg, *idx, if swap { val } else { MaybeNumber::none() });
+        }
+        for (idx, val) in set2.iter().zip(vals1) {
+            regs.set(reg, *idx, val);
+        }
+        ExecStep::Next
+    }
+}
+
+fn set_regs<'a>(reg: impl Into<RegAFR>, set: impl IntoIterator<Item = &'a Reg32>) -> BTreeSet<Reg> {
+    let reg = reg.into();
+    set.into_iter().map(|idx| Reg::new(reg, *idx)).collect()
+}
+
+impl InstructionSet for StepOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUSTEP);
+        set
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            StepOp::StpA(_, reg, idx, _)
+            | StepOp::NegA(_, reg, idx)
+            | StepOp::AbsA(_, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            StepOp::StpF(_, reg, idx, _) => BTreeSet::from([Reg::new(reg, idx)]),
+        }
+    }
+
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { self.src_regs() }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let is_some = match *self {
+            StepOp::StpF(flag, reg, idx, step) => {
+                let res: Option<Number> = regs.get(reg, idx).and_then(|val| {
+                    // Registers keep tapered floats as raw bits, and tapered float arithmetic is
+                    // not implemented yet (#5), so the step produces no value
+                    if reg == RegF::F512 {
+                        return None;
+                    }
+                    let (res, status) = val.float_step_flags(step, flag);
+                    regs.fs0 |= status;
+                    res.into()
+                });
+                regs.set(reg, idx, res)
+            }
+            StepOp::StpA(flags, reg, idx, _)
+            | StepOp::NegA(flags, reg, idx)
+            | StepOp::AbsA(flags, reg, idx) => {
+                let (res, carry) = regs
+                    .get(reg, idx)
+                    .map(|val| match *self {
+                        StepOp::StpA(.., step) => val.int_step(step, flags),
+                        StepOp::NegA(..) => val.int_neg(flags),
+                        _ => val.int_abs(flags),
+                    })
+                    .unwrap_or_default();
+                regs.set_carry(carry);
+                regs.set(reg, idx, res)
+            }
+        };
+        regs.set_status(is_some);
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for DataOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUDATA);
+        set
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            DataOp::Len(..) => BTreeSet::new(),
+            DataOp::LoadA(_, _, off_reg, off) | DataOp::LoadR(_, _, off_reg, off) => {
+                BTreeSet::from([Reg::new(off_reg, off)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            DataOp::Len(reg, dst) | DataOp::LoadA(reg, dst, ..) => {
+                BTreeSet::from([Reg::new(reg, dst)])
+            }
+            DataOp::LoadR(reg, dst, ..) => BTreeSet::from([Reg::new(reg, dst)]),
+        }
+    }
+
+    /// Executes the instruction with an empty data segment, see [`DataOp`].
+    #[inline]
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, ctx: &()) -> ExecStep {
+        self.exec_with_data(regs, site, &[], ctx)
+    }
+
+    fn exec_with_data(&self, regs: &mut CoreRegs, _site: LibSite, data: &[u8], _: &()) -> ExecStep {
+        let (reg, dst) = match *self {
+            DataOp::Len(reg, dst) | DataOp::LoadA(reg, dst, ..) => (RegAR::A(reg), dst),
+            DataOp::LoadR(reg, dst, ..) => (RegAR::R(reg), dst),
+        };
+        let value = match *self {
+            DataOp::Len(..) => {
+                Number::from(data.len() as u64).fitted(IntLayout::unsigned(reg.bytes()))
+            }
+            DataOp::LoadA(_, _, off_reg, off) | DataOp::LoadR(_, _, off_reg, off) => {
+                let value = regs.get(off_reg, off).and_then(|offset| {
+                    let offset = Some(offset)
+                        .filter(|offset| offset.min_bit_len() <= 64)
+                        .and_then(|offset| usize::try_from(u64::from(offset)).ok())
+                        .unwrap_or(usize::MAX);
+                    let end = offset.checked_add(reg.bytes() as usize)?;
+                    Number::with(data.get(offset..end)?, reg.layout())
+                });
+                if value.is_some() {
+                    regs.acc_data_reads(1);
+                }
+                value
+            }
+        };
+        if value.is_none() {
+            regs.st0 = false;
+        }
+        regs.set(reg, dst, value);
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for SwitchOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUSWCH);
+        set
+    }
+
+    /// Jump complexity, increased by one for each 16 table entries read from the data segment.
+    fn complexity(&self) -> u64 {
+        let SwitchOp::Switch(_, _, table) = self;
+        2 + table.len() as u64 / 16
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        let SwitchOp::Switch(reg, idx, _) = *self;
+        BTreeSet::from([Reg::new(reg, idx)])
+    }
+
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { BTreeSet::new() }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let SwitchOp::Switch(reg, idx, table) = self;
+        let target = regs
+            .get(*reg, *idx)
+            .and_then(|index| Some(index).filter(|index| index.min_bit_len() <= 16))
+            .and_then(|index| table.get(u16::from(index) as usize));
+        match target {
+            Some(pos) => regs.jmp().map(|_| ExecStep::Jump(*pos)).unwrap_or(ExecStep::Stop),
+            None => {
+                regs.st0 = false;
+                ExecStep::Next
+            }
+        }
+    }
+}
+
+impl InstructionSet for LoopOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALULOOP);
+        set
+    }
+
+    #[inline]
+    fn complexity(&self) -> u64 { 2 }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        let LoopOp::Loop(idx, ..) = *self;
+        BTreeSet::from([Reg::new(RegA::A16, idx)])
+    }
+
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { self.src_regs() }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let LoopOp::Loop(idx, max, pos) = *self;
+        match regs.get(RegA::A16, idx).map(u16::from) {
+            Some(counter) if counter <= max => {
+                let counter = counter.saturating_sub(1);
+                regs.set(RegA::A16, idx, counter);
+                if counter == 0 {
+                    return ExecStep::Next;
+                }
+                regs.jmp().map(|_| ExecStep::Jump(pos)).unwrap_or(ExecStep::Stop)
+            }
+            _ => {
+                regs.st0 = false;
+                ExecStep::Next
+            }
+        }
+    }
+}
+
+impl InstructionSet for FixedOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUFIX);
+        set
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FixedOp::MulA(_, reg, src, srcdst, _) | FixedOp::DivA(_, reg, src, srcdst, _) => {
+                BTreeSet::from([Reg::new(reg, src), Reg::new(reg, srcdst)])
+            }
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FixedOp::MulA(_, reg, _, srcdst, _) | FixedOp::DivA(_, reg, _, srcdst, _) => {
+                BTreeSet::from([Reg::new(reg, srcdst)])
+            }
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let is_some = match *self {
+            FixedOp::MulA(flags, reg, src, srcdst, frac) => {
+                let (res, carry) = regs
+                    .get_both(reg, src, reg, srcdst)
+                    .filter(|(val1, _)| frac < val1.layout().bits())
+                    .map(|(val1, val2)| {
+                        match val1.fixed_mul(val2, frac, IntFlags { wrap: false, ..flags }) {
+                            Some(res) => (Some(res), false),
+                            None if flags.wrap => (val1.fixed_mul(val2, frac, flags), true),
+                            None => (None, true),
+                        }
+                    })
+                    .unwrap_or_default();
+                regs.set_carry(carry);
+                regs.set(reg, srcdst, res)
+            }
+            FixedOp::DivA(flags, reg, src, srcdst, frac) => {
+                let (res, carry) = regs
+                    .get_both(reg, src, reg, srcdst)
+                    .filter(|(val1, _)| frac < val1.layout().bits())
+                    .map(|(val1, val2)| {
+                        let res = val1.fixed_div(val2, frac, flags);
+                        (res, res.is_none() && !val2.is_zero())
+                    })
+                    .unwrap_or_default();
+                regs.set_carry(carry);
+                regs.set(reg, srcdst, res)
+            }
+        };
+        regs.set_status(is_some);
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for StackOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUSTK);
+        set
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            StackOp::PushA(stack, reg, idx) => BTreeSet::from([Reg::S(stack), Reg::new(reg, idx)]),
+            StackOp::PushR(stack, reg, idx) => BTreeSet::from([Reg::S(stack), Reg::new(reg, idx)]),
+            StackOp::PopA(stack, ..)
+            | StackOp::PopR(stack, ..)
+            | StackOp::PeekA(stack, ..)
+            | StackOp::PeekR(stack, ..)
+            | StackOp::DepthA(stack, ..)
+            | StackOp::DepthR(stack, ..) => BTreeSet::from([Reg::S(stack)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            StackOp::PushA(stack, ..) | StackOp::PushR(stack, ..) => {
+                BTreeSet::from([Reg::S(stack)])
+            }
+            StackOp::PopA(stack, reg, idx) => BTreeSet::from([Reg::S(stack), Reg::new(reg, idx)]),
+            StackOp::PopR(stack, reg, idx) => BTreeSet::from([Reg::S(stack), Reg::new(reg, idx)]),
+            StackOp::PeekA(_, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            StackOp::PeekR(_, reg, idx) => BTreeSet::from([Reg::new(reg, idx)]),
+            StackOp::DepthA(_, _, dst) | StackOp::DepthR(_, _, dst) => {
+                BTreeSet::from([Reg::new(RegA::A16, dst)])
+            }
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let success = match *self {
+            StackOp::PushA(stack, reg, idx) => stack_push(regs, stack, reg, idx),
+            StackOp::PushR(stack, reg, idx) => stack_push(regs, stack, reg, idx),
+            StackOp::PopA(stack, reg, idx) => stack_pop(regs, stack, reg, idx, true),
+            StackOp::PopR(stack, reg, idx) => stack_pop(regs, stack, reg, idx, true),
+            StackOp::PeekA(stack, reg, idx) => stack_pop(regs, stack, reg, idx, false),
+            StackOp::PeekR(stack, reg, idx) => stack_pop(regs, stack, reg, idx, false),
+            StackOp::DepthA(stack, reg, dst) => stack_depth(regs, stack, reg, dst),
+            StackOp::DepthR(stack, reg, dst) => stack_depth(regs, stack, reg, dst),
+        };
+        if !success {
+            regs.st0 = false;
+        }
+        ExecStep::Next
+    }
+}
+
+/// Appends value of the register to the stack kept in the string register. Returns `false` if
+/// the register is not set or on stack overflow, leaving the stack unchanged.
+fn stack_push(regs: &mut CoreRegs, stack: RegS, reg: impl Into<RegAFR>, idx: Reg32) -> bool {
+    let reg = reg.into();
+    let Some(val) = Option::<Number>::from(regs.get(reg, idx)) else {
+        return false;
+    };
+    let max = regs.limits().max_str_bytes.unwrap_or(u32::MAX);
+    if regs.str_bytes() + reg.bytes() as u32 > max {
+        return false;
+    }
+    let mut data = regs.take_s(stack).unwrap_or_default();
+    let res = data.append(val);
+    regs.put_s(stack, Some(data)) && res.is_ok()
+}
+
+/// Reads value from the top of the stack kept in the string register into the register, removing
+/// it from the stack if `pop` is set. Returns `false` on stack underflow, setting the register to
+/// `None`.
+fn stack_pop(
+    regs: &mut CoreRegs,
+    stack: RegS,
+    reg: impl Into<RegAFR>,
+    idx: Reg32,
+    pop: bool,
+) -> bool {
+    let reg = reg.into();
+    let bytes = reg.bytes();
+    let Some(top) = regs.get_s(stack).and_then(|data| data.len().checked_sub(bytes)) else {
+        regs.set(reg, idx, MaybeNumber::none());
+        return false;
+    };
+    let mut data = regs.take_s(stack).expect("stack presence is checked above");
+    let val = Number::with(&data.as_ref()[top as usize..], reg.layout());
+    if pop {
+        data.adjust_len(top);
+    }
+    regs.put_s(stack, Some(data));
+    regs.set(reg, idx, val)
+}
+
+/// Puts number of the values of the register size kept in the stack into `a16` register.
+fn stack_depth(regs: &mut CoreRegs, stack: RegS, reg: impl Into<RegAFR>, dst: Reg32) -> bool {
+    let len = regs.get_s(stack).map(ByteStr::len).unwrap_or_default();
+    regs.set(RegA::A16, dst, len / reg.into().bytes())
+}
+
+impl InstructionSet for FieldOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUFLD);
+        set
+    }
+
+    fn complexity(&self) -> u64 {
+        match self {
+            FieldOp::Add(..) => 10,
+            FieldOp::Mul(..) => 100,
+            // Exponentiation performs two multiplications per exponent bit
+            FieldOp::Inv(..) | FieldOp::Exp(..) => 100 * 256,
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FieldOp::Add(_, src1, src2, _)
+            | FieldOp::Mul(_, src1, src2, _)
+            | FieldOp::Exp(_, src1, src2, _) => {
+                BTreeSet::from([Reg::new(RegR::R256, src1), Reg::new(RegR::R256, src2)])
+            }
+            FieldOp::Inv(_, src, _) => BTreeSet::from([Reg::new(RegR::R256, src)]),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            FieldOp::Add(.., dst)
+            | FieldOp::Mul(.., dst)
+            | FieldOp::Inv(.., dst)
+            | FieldOp::Exp(.., dst) => BTreeSet::from([Reg::new(RegR::R256, dst)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        let element = |idx: Reg32, field: FieldFlag| {
+            Option::<Number>::from(regs.get(RegR::R256, idx))
+                .filter(|val| modular::is_reduced(&val[..], &field.modulus()))
+        };
+        let (res, dst) = match *self {
+            FieldOp::Add(field, src1, src2, dst) => {
+                let res = element(src1, field)
+                    .zip(element(src2, field))
+                    .and_then(|(a, b)| modular::add_mod(&a[..], &b[..], &field.modulus()));
+                (res, dst)
+            }
+            FieldOp::Mul(field, src1, src2, dst) => {
+                let res = element(src1, field)
+                    .zip(element(src2, field))
+                    .and_then(|(a, b)| modular::mul_mod(&a[..], &b[..], &field.modulus()));
+                (res, dst)
+            }
+            FieldOp::Inv(field, src, dst) => {
+                let res = element(src, field)
+                    .and_then(|a| modular::inv_mod_prime(&a[..], &field.modulus()));
+                (res, dst)
+            }
+            FieldOp::Exp(field, base, exp, dst) => {
+                let res = element(base, field)
+                    .zip(Option::<Number>::from(regs.get(RegR::R256, exp)))
+                    .and_then(|(a, e)| modular::pow_mod(&a[..], &e[..], &field.modulus()));
+                (res, dst)
+            }
+        };
+        match res {
+            Some(val) => {
+                regs.set(RegR::R256, dst, Number::from_slice(val));
+            }
+            None => {
+                regs.set(RegR::R256, dst, MaybeNumber::none());
+                regs.st0 = false;
+            }
+        }
+        ExecStep::Next
+    }
+}
+
+impl InstructionSet for HostOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_ALUHOST);
+        set
+    }
+
+    /// Accounts for the digest of the complete register state, which is computed for the
+    /// [receipt](crate::receipt::CallReceipt) of each host function call.
+    fn complexity(&self) -> u64 { 2000 }
+
+    /// Registers read by host functions are defined by the embedder and are not reported.
+    fn src_regs(&self) -> BTreeSet<Reg> { BTreeSet::new() }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            HostOp::CallA(_, reg, dst) => BTreeSet::from([Reg::new(reg, dst)]),
+            HostOp::CallR(_, reg, dst) => BTreeSet::from([Reg::new(reg, dst)]),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, _: &()) -> ExecStep {
+        let (func, reg, dst) = match *self {
+            HostOp::CallA(func, reg, dst) => (func, RegAR::A(reg), dst),
+            HostOp::CallR(func, reg, dst) => (func, RegAR::R(reg), dst),
+        };
+        if !regs.request_host_call(site, func, reg, dst) {
+            regs.set(reg, dst, MaybeNumber::none());
+            regs.st0 = false;
+        }
+        ExecStep::Next
+    }
+}
+
+#[cfg(feature = "bitcoin")]
+impl InstructionSet for BitcoinOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = BTreeSet::new();
+        set.insert(constants::ISA_ID_BITCOIN);
+        set
+    }
+
+    fn complexity(&self) -> u64 {
+        match self {
+            BitcoinOp::Hash160(..) | BitcoinOp::Hash256(..) => 200,
+            BitcoinOp::CheckSig(..) | BitcoinOp::CheckSchnorr(..) => 1000,
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match *self {
+            BitcoinOp::Hash160(src, hash) => {
+                BTreeSet::from([Reg::S(src), Reg::new(RegR::R160, hash)])
+            }
+            BitcoinOp::Hash256(src, hash) => {
+                BTreeSet::from([Reg::S(src), Reg::new(RegR::R256, hash)])
+            }
+            BitcoinOp::CheckSig(pk, sig, msg) => {
+                BTreeSet::from([Reg::S(pk), Reg::S(sig), Reg::new(RegR::R256, msg)])
+            }
+            BitcoinOp::CheckSchnorr(pk, sig, msg) => {
+                BTreeSet::from([Reg::new(RegR::R256, pk), Reg::S(sig), Reg::new(RegR::R256, msg)])
+            }
+        }
+    }
+
+    #[inline]
+    fn dst_regs(&self) -> BTreeSet<Reg> { BTreeSet::new() }
+
+    fn exec(&self, regs: &mut CoreRegs, _site: LibSite, _: &()) -> ExecStep {
+        use secp256k1::{ecdsa, schnorr, Message, PublicKey, XOnlyPublicKey, SECP256K1};
+
+        let get = |reg: RegR, idx: Reg32| Option::<Number>::from(regs.get(reg, idx));
+        let res = match *self {
+            BitcoinOp::Hash160(src, hash) => (|| {
+                let digest = sha2::Sha256::digest(regs.get_s(src)?.as_ref());
+                let mut hash160: [u8; 20] = ripemd::Ripemd160::digest(digest).into();
+                // RIPEMD-160 is big-endian
+                hash160.reverse();
+                Some(get(RegR::R160, hash)?.as_ref() == hash160)
+            })(),
+            BitcoinOp::Hash256(src, hash) => (|| {
+                let digest = sha2::Sha256::digest(regs.get_s(src)?.as_ref());
+                let hash256: [u8; 32] = sha2::Sha256::digest(digest).into();
+                Some(get(RegR::R256, hash)?.as_ref() == hash256)
+            })(),
+            BitcoinOp::CheckSig(pk, sig, msg) => (|| {
+                let pk = PublicKey::from_slice(regs.get_s(pk)?.as_ref()).ok()?;
+                // The last byte is the sighash type, which is not interpreted
+                let (_, sig) = regs.get_s(sig)?.as_ref().split_last()?;
+                let mut sig = ecdsa::Signature::from_der_lax(sig).ok()?;
+                sig.normalize_s();
+                let msg = Message::from_slice(get(RegR::R256, msg)?.as_ref()).ok()?;
+                Some(SECP256K1.verify_ecdsa(&msg, &sig, &pk).is_ok())
+            })(),
+            BitcoinOp::CheckSchnorr(pk, sig, msg) => (|| {
+                let pk = XOnlyPublicKey::from_slice(get(RegR::R256, pk)?.as_ref()).ok()?;
+                let sig = regs.get_s(sig)?.as_ref();
+                let sig = match sig.len() {
+                    64 => sig,
+                    65 => &sig[..64],
+                    _ => return None,
+                };
+                let sig = schnorr::Signature::from_slice(sig).ok()?;
+                let msg = Message::from_slice(get(RegR::R256, msg)?.as_ref()).ok()?;
+                Some(SECP256K1.verify_schnorr(&sig, &msg, &pk).is_ok())
+            })(),
+        };
+        regs.st0 = res.unwrap_or_default();
+        ExecStep::Next
+    }
+}
+
+impl<First, Second> InstructionSet for Extensions<First, Second>
+where
+    First: InstructionSet,
+    Second: InstructionSet,
+{
+    type Context<'ctx> = (First::Context<'ctx>, Second::Context<'ctx>);
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> {
+        let mut set = First::isa_ids();
+        set.extend(Second::isa_ids());
+        set
+    }
+
+    #[inline]
+    fn isa_set() -> BTreeSet<IsaId> {
+        let mut set = First::isa_set();
+        set.extend(Second::isa_set());
+        set
+    }
+
+    fn complexity(&self) -> u64 {
+        match self {
+            Extensions::First(instr) => instr.complexity(),
+            Extensions::Second(instr) => instr.complexity(),
+        }
+    }
+
+    fn src_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Extensions::First(instr) => instr.src_regs(),
+            Extensions::Second(instr) => instr.src_regs(),
+        }
+    }
+
+    fn dst_regs(&self) -> BTreeSet<Reg> {
+        match self {
+            Extensions::First(instr) => instr.dst_regs(),
+            Extensions::Second(instr) => instr.dst_regs(),
+        }
+    }
+
+    fn canonicalize(&mut self) {
+        match self {
+            Extensions::First(instr) => instr.canonicalize(),
+            Extensions::Second(instr) => instr.canonicalize(),
+        }
+    }
+
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, ctx: &Self::Context<'_>) -> ExecStep {
+        match self {
+            Extensions::First(instr) => instr.exec(regs, site, &ctx.0),
+            Extensions::Second(instr) => instr.exec(regs, site, &ctx.1),
+        }
+    }
+
+    fn exec_with_data(
+        &self,
+        regs: &mut CoreRegs,
+        site: LibSite,
+        data: &[u8],
+        ctx: &Self::Context<'_>,
+    ) -> ExecStep {
+        match self {
+            Extensions::First(instr) => instr.exec_with_data(regs, site, data, &ctx.0),
+            Extensions::Second(instr) => instr.exec_with_data(regs, site, data, &ctx.1),
+        }
+    }
+}
+
+impl InstructionSet for ReservedOp {
+    type Context<'ctx> = ();
+
+    #[inline]
+    fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::default() }
+
+    fn exec(&self, regs: &mut CoreRegs, site: LibSite, ctx: &()) -> ExecStep {
+        ControlFlowOp::Fail.exec(regs, site, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "secp256k1")]
+    use crate::reg::RegBlockAR;
+    use crate::reg::{Reg16, Reg8};
+    use crate::vm::RunLimits;
+
+    #[test]
+    fn reg_introspection() {
+        use crate::isa::IntFlags;
+        use crate::reg::RegS;
+
+        let a8 = |idx| Reg::new(RegA::A8, idx);
+        let add =
+            ArithmeticOp::AddA(IntFlags::unsigned_checked(), RegA::A8, Reg32::Reg0, Reg32::Reg1);
+        assert_eq!(add.src_regs(), BTreeSet::from([a8(Reg32::Reg0), a8(Reg32::Reg1)]));
+        assert_eq!(add.dst_regs(), BTreeSet::from([a8(Reg32::Reg1)]));
+
+        let mov = MoveOp::MovA(RegA::A8, Reg32::Reg0, Reg32::Reg1);
+        assert_eq!(mov.src_regs(), BTreeSet::from([a8(Reg32::Reg0)]));
+        assert_eq!(mov.dst_regs(), BTreeSet::from([a8(Reg32::Reg0), a8(Reg32::Reg1)]));
+
+        let st = CmpOp::St(MergeFlag::Set, RegA::A8, Reg8::Reg2);
+        assert_eq!(st.src_regs(), BTreeSet::new());
+        assert_eq!(st.dst_regs(), BTreeSet::from([a8(Reg32::Reg2)]));
+
+        let instr = Instr::<ReservedOp>::Digest(DigestOp::Sha256(RegS::from(3), Reg16::Reg1));
+        assert_eq!(instr.src_regs(), BTreeSet::from([Reg::S(RegS::from(3))]));
+        assert_eq!(instr.dst_regs(), BTreeSet::from([Reg::new(RegR::R256, Reg32::Reg1)]));
+        assert_eq!(instr.dst_regs().iter().next().unwrap().to_string(), "r256[1]");
+        assert_eq!(Instr::<ReservedOp>::Nop.src_regs(), BTreeSet::new());
+    }
+
+    #[test]
+    fn signed_cmp() {
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (zero, minus_one, one) = (Reg32::Reg0, Reg32::Reg1, Reg32::Reg2);
+        regs.set(RegA::A8, zero, 0u8);
+        regs.set(RegA::A8, minus_one, 0xFFu8);
+        regs.set(RegA::A8, one, 1u8);
+        for (op, st0) in [
+            (CmpOp::LtA(SignFlag::Signed, RegA::A8, zero, minus_one), false),
+            (CmpOp::GtA(SignFlag::Signed, RegA::A8, zero, minus_one), true),
+            (CmpOp::LtA(SignFlag::Signed, RegA::A8, minus_one, zero), true),
+            (CmpOp::GtA(SignFlag::Signed, RegA::A8, minus_one, zero), false),
+            (CmpOp::LtA(SignFlag::Signed, RegA::A8, zero, zero), false),
+            (CmpOp::GtA(SignFlag::Signed, RegA::A8, zero, zero), false),
+            (CmpOp::LtA(SignFlag::Signed, RegA::A8, zero, one), true),
+            (CmpOp::LtA(SignFlag::Signed, RegA::A8, minus_one, one), true),
+            (CmpOp::LtA(SignFlag::Unsigned, RegA::A8, zero, minus_one), true),
+            (CmpOp::GtA(SignFlag::Unsigned, RegA::A8, minus_one, one), true),
+        ] {
+            assert_eq!(op.exec(&mut regs, site, &()), ExecStep::Next);
+            assert_eq!(regs.status(), st0, "{op}");
+        }
+    }
+
+    #[test]
+    fn const_time_cmp() {
+        use crate::reg::RegS;
+
+        assert_eq!(ct_cmp(&[1, 2, 3], &[1, 2, 3]), Ordering::Equal);
+        assert_eq!(ct_cmp(&[0xFF, 0, 1], &[0, 0xFF, 0]), Ordering::Greater);
+        assert_eq!(ct_cmp(&[0, 0xFF, 0], &[0xFF, 0, 1]), Ordering::Less);
+        assert_eq!(ct_cmp(&[2, 7], &[1, 7]), Ordering::Greater);
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let mut val1 = [0u8; 32];
+        val1[31] = 1;
+        regs.set(RegR::R256, Reg32::Reg0, val1);
+        regs.set(RegR::R256, Reg32::Reg1, [0xFFu8; 16]);
+        for (op, st0) in [
+            (CtOp::EqR(RegR::R256, Reg32::Reg0, Reg32::Reg0), true),
+            (CtOp::EqR(RegR::R256, Reg32::Reg0, Reg32::Reg1), false),
+            (CtOp::GtR(RegR::R256, Reg32::Reg0, Reg32::Reg1), true),
+            (CtOp::LtR(RegR::R256, Reg32::Reg0, Reg32::Reg1), false),
+            (CtOp::LtR(RegR::R256, Reg32::Reg1, Reg32::Reg0), true),
+            (CtOp::EqR(RegR::R256, Reg32::Reg0, Reg32::Reg2), false),
+        ] {
+            assert_eq!(op.exec(&mut regs, site, &()), ExecStep::Next);
+            assert_eq!(regs.status(), st0, "{op}");
+        }
+
+        regs.set_s(RegS::from(0), Some(ByteStr::with(b"secret")));
+        regs.set_s(RegS::from(1), Some(ByteStr::with(b"secret")));
+        regs.set_s(RegS::from(2), Some(ByteStr::with(b"secreT")));
+        for (op, st0) in [
+            (CtOp::EqS(RegS::from(0), RegS::from(1)), true),
+            (CtOp::EqS(RegS::from(0), RegS::from(2)), false),
+            (CtOp::EqS(RegS::from(3), RegS::from(3)), false),
+        ] {
+            op.exec(&mut regs, site, &());
+            assert_eq!(regs.status(), st0, "{op}");
+        }
+    }
+
+    #[test]
+    fn prng() {
+        // RFC 8439, section 2.3.2
+        let mut key = [0u8; 32];
+        key.iter_mut().zip(0u8..).for_each(|(byte, val)| *byte = val);
+        let nonce = [0, 0, 0, 9, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let block = chacha20_block(&key, 1, &nonce);
+        assert_eq!(block[..16], [
+            0x10, 0xf1, 0xe7, 0xe4, 0xd1, 0x3b, 0x59, 0x15, 0x50, 0x0f, 0xdd, 0x1f, 0xa3, 0x20,
+            0x71, 0xc4
+        ]);
+        assert_eq!(block[60..], [0xa2, 0x50, 0x3c, 0x4e]);
+
+        // RFC 8439, appendix A.1, test vectors #1 and #2
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        regs.set(RegR::R256, Reg32::Reg0, [0u8; 32]);
+        regs.set(RegA::A32, Reg32::Reg0, 0u32);
+        PrngOp::RndR(RegR::R512, Reg32::Reg0, Reg32::Reg0, Reg32::Reg0).exec(&mut regs, site, &());
+        let val = regs.get(RegR::R512, Reg32::Reg0).unwrap();
+        assert_eq!(val[..8], [0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90]);
+        assert_eq!(regs.get(RegA::A32, Reg32::Reg0), MaybeNumber::from(1u32));
+        PrngOp::RndA(RegA::A64, Reg32::Reg1, Reg32::Reg0, Reg32::Reg0).exec(&mut regs, site, &());
+        let val = regs.get(RegA::A64, Reg32::Reg1).unwrap();
+        assert_eq!(val[..], [0x9f, 0x07, 0xe7, 0xbe, 0x55, 0x51, 0x38, 0x7a]);
+        PrngOp::RndR(RegR::R2048, Reg32::Reg0, Reg32::Reg0, Reg32::Reg0).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegA::A32, Reg32::Reg0), MaybeNumber::from(6u32));
+        assert!(regs.status());
+
+        regs.set(RegA::A32, Reg32::Reg0, u32::MAX);
+        PrngOp::RndA(RegA::A8, Reg32::Reg1, Reg32::Reg0, Reg32::Reg0).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegA::A8, Reg32::Reg1), MaybeNumber::none());
+        assert_eq!(regs.get(RegA::A32, Reg32::Reg0), MaybeNumber::from(u32::MAX));
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn modular() {
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (r0, r1, r2, r3) = (Reg32::Reg0, Reg32::Reg1, Reg32::Reg2, Reg32::Reg3);
+        regs.set(RegR::R128, r0, 1_000_000_000u128);
+        regs.set(RegR::R128, r1, 123_456_789u128);
+        regs.set(RegR::R128, r2, 1_000_000_007u128);
+        for (op, res) in [
+            (ModOp::AddMod(RegR::R128, r0, r0, r2, r3), 999_999_993u128),
+            (ModOp::MulMod(RegR::R128, r0, r1, r2, r3), 135_802_484),
+            (ModOp::ExpMod(RegR::R128, r1, r2, r2, r3), 123_456_789),
+            (ModOp::ExpMod(RegR::R128, r1, r3, r2, r3), 907_408_795),
+        ] {
+            assert_eq!(op.exec(&mut regs, site, &()), ExecStep::Next);
+            assert_eq!(regs.get(RegR::R128, r3), MaybeNumber::from(res), "{op}");
+            assert!(regs.status());
+        }
+
+        // Zero and unset modulus
+        for modulus in [r3, Reg32::Reg4] {
+            regs.set(RegR::R128, r3, 0u128);
+            regs.st0 = true;
+            ModOp::MulMod(RegR::R128, r0, r1, modulus, r3).exec(&mut regs, site, &());
+            assert_eq!(regs.get(RegR::R128, r3), MaybeNumber::none());
+            assert!(!regs.status());
+        }
+    }
+
+    #[test]
+    fn frames() {
+        use crate::reg::RegF;
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (r0, r1, r2) = (Reg32::Reg0, Reg32::Reg1, Reg32::Reg2);
+        regs.set(RegA::A64, r0, 1u64);
+        regs.set(RegA::A64, r1, 2u64);
+
+        assert_eq!(FrameOp::PushA(RegA::A64, r0, r2).exec(&mut regs, site, &()), ExecStep::Next);
+        assert_eq!(regs.frame_depth(), 1);
+        regs.set(RegA::A64, r0, 10u64);
+        regs.set(RegA::A64, r2, 30u64);
+        regs.set(RegA::A64, Reg32::Reg3, 40u64);
+
+        // Restoring different range fails
+        FrameOp::PopA(RegA::A64, r0, r1).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.frame_depth(), 1);
+
+        regs.st0 = true;
+        FrameOp::PopA(RegA::A64, r0, r2).exec(&mut regs, site, &());
+        assert!(regs.status());
+        assert_eq!(regs.frame_depth(), 0);
+        assert_eq!(regs.get(RegA::A64, r0), MaybeNumber::from(1u64));
+        assert_eq!(regs.get(RegA::A64, r1), MaybeNumber::from(2u64));
+        assert_eq!(regs.get(RegA::A64, r2), MaybeNumber::none());
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg3), MaybeNumber::from(40u64));
+
+        FrameOp::PopA(RegA::A64, r0, r2).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        regs.st0 = true;
+        FrameOp::PushR(RegR::R256, r2, r1).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.frame_depth(), 0);
+
+        regs.st0 = true;
+        for _ in 0..crate::reg::FRAME_STACK_SIZE {
+            FrameOp::PushF(RegF::F32, r0, r0).exec(&mut regs, site, &());
+        }
+        assert!(regs.status());
+        FrameOp::PushF(RegF::F32, r0, r0).exec(&mut regs, site, &());
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn fields() {
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (r0, r1, r2) = (Reg32::Reg0, Reg32::Reg1, Reg32::Reg2);
+        let field = FieldFlag::Bn254;
+        let mut minus_one = field.modulus();
+        minus_one[0] -= 1;
+        let elem = |val: u8| {
+            let mut elem = [0u8; 32];
+            elem[0] = val;
+            MaybeNumber::from(Number::from_slice(elem))
+        };
+
+        regs.set(RegR::R256, r0, minus_one);
+        regs.set(RegR::R256, r1, 3u64);
+        FieldOp::Add(field, r0, r1, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), elem(2));
+        FieldOp::Mul(field, r0, r0, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), elem(1));
+        FieldOp::Inv(field, r1, r2).exec(&mut regs, site, &());
+        FieldOp::Mul(field, r1, r2, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), elem(1));
+        regs.set(RegR::R256, r2, 4u64);
+        FieldOp::Exp(field, r1, r2, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), elem(81));
+        assert!(regs.status());
+
+        // Value of the field modulus is not canonical
+        regs.set(RegR::R256, r0, field.modulus());
+        FieldOp::Add(field, r0, r1, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), MaybeNumber::none());
+        assert!(!regs.status());
+
+        regs.st0 = true;
+        regs.set(RegR::R256, r0, 0u64);
+        FieldOp::Inv(FieldFlag::Bls12_381, r0, r2).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegR::R256, r2), MaybeNumber::none());
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn stacks() {
+        use crate::reg::RegS;
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let stack = RegS::from(2);
+        let (r0, r1) = (Reg32::Reg0, Reg32::Reg1);
+        regs.set(RegA::A16, r0, 0x1234u16);
+        regs.set(RegA::A8, r0, 0x56u8);
+
+        StackOp::PushA(stack, RegA::A16, r0).exec(&mut regs, site, &());
+        StackOp::PushA(stack, RegA::A8, r0).exec(&mut regs, site, &());
+        assert_eq!(regs.get_s(stack).unwrap().as_ref(), &[0x34, 0x12, 0x56]);
+        StackOp::DepthA(stack, RegA::A8, r1).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegA::A16, r1), MaybeNumber::from(3u16));
+
+        StackOp::PeekA(stack, RegA::A8, r1).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegA::A8, r1), MaybeNumber::from(0x56u8));
+        StackOp::PopA(stack, RegA::A8, r1).exec(&mut regs, site, &());
+        StackOp::PopA(stack, RegA::A16, r1).exec(&mut regs, site, &());
+        assert_eq!(regs.get(RegA::A16, r1), MaybeNumber::from(0x1234u16));
+        assert!(regs.status());
+        assert_eq!(regs.get_s(stack).unwrap().len(), 0);
+
+        // Underflow
+        StackOp::PopA(stack, RegA::A8, r1).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A8, r1), MaybeNumber::none());
+
+        // Pushing unset register
+        regs.st0 = true;
+        StackOp::PushR(stack, RegR::R128, r0).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.get_s(stack).unwrap().len(), 0);
+
+        // Overflow
+        regs.st0 = true;
+        regs.set_s(stack, Some(ByteStr::with([0u8; u16::MAX as usize - 1])));
+        StackOp::PushA(stack, RegA::A16, r0).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.get_s(stack).unwrap().len(), u16::MAX - 1);
+    }
+
+    #[test]
+    fn xfers() {
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let set = |idxs: &[u8]| -> BTreeSet<Reg32> {
+            idxs.iter().map(|idx| Reg32::try_from(*idx).unwrap()).collect()
+        };
+        for idx in 1u8..=4 {
+            regs.set(RegA::A64, Reg32::try_from(idx).unwrap(), idx as u64);
+        }
+
+        // Overlapping move shifts values and clears sources which are not overwritten
+        XferOp::MovA(RegA::A64, set(&[1, 2, 3, 4]), set(&[3, 4, 5, 6])).exec(&mut regs, site, &());
+        assert!(regs.status());
+        for (idx, val) in
+            [(1u8, None), (2, None), (3, Some(1u64)), (4, Some(2)), (5, Some(3)), (6, Some(4))]
+        {
+            let expected = val.map(MaybeNumber::from).unwrap_or_default();
+            assert_eq!(regs.get(RegA::A64, Reg32::try_from(idx).unwrap()), expected);
+        }
+
+        XferOp::SwpA(RegA::A64, set(&[1, 3]), set(&[5, 6])).exec(&mut regs, site, &());
+        assert!(regs.status());
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg1), MaybeNumber::from(3u64));
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg3), MaybeNumber::from(4u64));
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg5), MaybeNumber::none());
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg6), MaybeNumber::from(1u64));
+
+        // Mismatched set lengths and overlapping swaps leave registers intact
+        XferOp::MovA(RegA::A64, set(&[1, 3]), set(&[7])).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg7), MaybeNumber::none());
+        regs.st0 = true;
+        XferOp::SwpA(RegA::A64, set(&[1, 3]), set(&[3, 4])).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A64, Reg32::Reg1), MaybeNumber::from(3u64));
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn bitcoin_checks() {
+        use secp256k1::{KeyPair, Message, PublicKey, SecretKey, SECP256K1};
+
+        use crate::reg::RegS;
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (s0, s1) = (RegS::from(0), RegS::from(1));
+        let (r0, r1) = (Reg32::Reg0, Reg32::Reg1);
+
+        // Hash locks
+        regs.set_s(s0, Some(ByteStr::with(b"preimage")));
+        let hash: [u8; 32] = sha2::Sha256::digest(sha2::Sha256::digest(b"preimage")).into();
+        regs.set(RegR::R256, r0, hash);
+        BitcoinOp::Hash256(s0, r0).exec(&mut regs, site, &());
+        assert!(regs.status());
+        DigestOp::Sha256(s0, Reg16::Reg1).exec(&mut regs, site, &());
+        regs.set_s(s1, regs.get(RegR::R256, r1).map(ByteStr::with));
+        DigestOp::Ripemd(s1, Reg16::Reg1).exec(&mut regs, site, &());
+        BitcoinOp::Hash160(s0, r1).exec(&mut regs, site, &());
+        assert!(regs.status());
+        BitcoinOp::Hash256(s1, r0).exec(&mut regs, site, &());
+        assert!(!regs.status());
+
+        // Signatures
+        let sk = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let msg = Message::from_slice(&[0x17; 32]).unwrap();
+        regs.set(RegR::R256, r0, [0x17u8; 32]);
+
+        let mut sig = SECP256K1.sign_ecdsa(&msg, &sk).serialize_der().to_vec();
+        sig.push(0x01);
+        regs.set_s(s0, Some(ByteStr::with(PublicKey::from_secret_key(SECP256K1, &sk).serialize())));
+        regs.set_s(s1, Some(ByteStr::with(&sig)));
+        BitcoinOp::CheckSig(s0, s1, r0).exec(&mut regs, site, &());
+        assert!(regs.status());
+        sig[10] ^= 0xFF;
+        regs.set_s(s1, Some(ByteStr::with(sig)));
+        BitcoinOp::CheckSig(s0, s1, r0).exec(&mut regs, site, &());
+        assert!(!regs.status());
+
+        let keypair = KeyPair::from_secret_key(SECP256K1, &sk);
+        let sig = SECP256K1.sign_schnorr_no_aux_rand(&msg, &keypair);
+        regs.set(RegR::R256, r1, keypair.x_only_public_key().0.serialize());
+        regs.set_s(s1, Some(ByteStr::with(sig.as_ref())));
+        BitcoinOp::CheckSchnorr(r1, s1, r0).exec(&mut regs, site, &());
+        assert!(regs.status());
+        BitcoinOp::CheckSchnorr(r0, s1, r1).exec(&mut regs, site, &());
+        assert!(!regs.status());
+        BitcoinOp::CheckSchnorr(r1, s1, Reg32::Reg2).exec(&mut regs, site, &());
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn base_encodings() {
+        use crate::reg::RegS;
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let (s0, s1, s2, s3) = (RegS::from(0), RegS::from(1), RegS::from(2), RegS::from(3));
+        regs.set_s(s0, Some(ByteStr::with(b"foobar")));
+        for (base, text) in [
+            (BaseFlag::Base16, &b"666f6f626172"[..]),
+            (BaseFlag::Base32, b"MZXW6YTBOI======"),
+            (BaseFlag::Base58, b"t1Zv2yaZ"),
+            (BaseFlag::Base64, b"Zm9vYmFy"),
+        ] {
+            EncOp::Enc(base, s0, s1).exec(&mut regs, site, &());
+            assert_eq!(regs.get_s(s1), Some(&ByteStr::with(text)), "{base}");
+            EncOp::Dec(base, s1, s2).exec(&mut regs, site, &());
+            assert_eq!(regs.get_s(s2), Some(&ByteStr::with(b"foobar")), "{base}");
+        }
+        assert!(regs.status());
+
+        regs.set_s(s1, Some(ByteStr::with(b"bc")));
+        EncOp::Bech32Enc(s1, s0, s2).exec(&mut regs, site, &());
+        EncOp::Bech32Dec(s2, s3, s2).exec(&mut regs, site, &());
+        assert_eq!(regs.get_s(s2), Some(&ByteStr::with(b"foobar")));
+        assert_eq!(regs.get_s(s3), Some(&ByteStr::with(b"bc")));
+        assert!(regs.status());
+
+        EncOp::Dec(BaseFlag::Base64, s0, s1).exec(&mut regs, site, &());
+        assert_eq!(regs.get_s(s1), None);
+        assert!(!regs.status());
+        regs.st0 = true;
+        EncOp::Bech32Dec(s0, s3, s2).exec(&mut regs, site, &());
+        assert_eq!((regs.get_s(s2), regs.get_s(s3)), (None, None));
+        assert!(!regs.status());
+
+        // Encoding result exceeding the register capacity
+        regs.st0 = true;
+        regs.set_s(s0, Some(ByteStr::with([0xFFu8; 0x8000])));
+        EncOp::Enc(BaseFlag::Base16, s0, s1).exec(&mut regs, site, &());
+        assert_eq!(regs.get_s(s1), None);
+        assert!(!regs.status());
+
+        // Encoding result exceeding the string memory limit
+        regs.st0 = true;
+        regs.set_s(s0, Some(ByteStr::with(b"foobar")));
+        regs.set_limits(RunLimits::unlimited().with_max_str_bytes(12));
+        EncOp::Enc(BaseFlag::Base16, s0, s1).exec(&mut regs, site, &());
+        assert_eq!(regs.get_s(s1), None);
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn relative_jumps() {
+        let mut regs = CoreRegs::default();
+        let site = LibSite::with(0x10, LibSite::default().lib);
+        assert_eq!(RelJumpOp::Jmp(-0x10).exec(&mut regs, site, &()), ExecStep::Jump(0));
+        assert_eq!(RelJumpOp::Jif(0x20).exec(&mut regs, site, &()), ExecStep::Jump(0x30));
+        assert_eq!(RelJumpOp::Routine(4).exec(&mut regs, site, &()), ExecStep::Jump(0x14));
+        assert_eq!(regs.ret(), Some(LibSite::with(0x13, site.lib)));
+        assert!(regs.status());
+
+        regs.st0 = false;
+        assert_eq!(RelJumpOp::Jif(-0x20).exec(&mut regs, site, &()), ExecStep::Next);
+        regs.st0 = true;
+        assert_eq!(RelJumpOp::Jmp(-0x11).exec(&mut regs, site, &()), ExecStep::Stop);
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn arithmetic_flags() {
+        use amplify::num::apfloat::{ieee, Float};
+        use amplify::num::u256;
+
+        use crate::data::Step;
+        use crate::isa::RoundingFlag;
+        use crate::reg::{Reg16, RegAF, RegAFR, RegF};
+
+        let wrap = IntFlags { signed: false, wrap: true };
+        let nowrap = IntFlags { signed: false, wrap: false };
+        let near = RoundingFlag::TowardsNearest;
+        let a8 = RegAFR::A(RegA::A8);
+        let f32 = RegAFR::F(RegF::F32);
+        let a = MaybeNumber::from;
+        let f = |bits: u32| MaybeNumber::from(ieee::Single::from_bits(u256::from(bits)));
+        let none = MaybeNumber::none();
+        let (r0, r1) = (Reg32::Reg0, Reg32::Reg1);
+
+        // Operations take `src` from register 0 and `srcdst` from register 1, putting the result
+        // into register 1. `co0` is set to `true` before each operation, so float operations are
+        // expected to keep it.
+        #[rustfmt::skip]
+        let table: [(ArithmeticOp, RegAFR, MaybeNumber, MaybeNumber, MaybeNumber, bool, bool); 27] = [
+            // operation, register, src, srcdst, result, st0, co0
+            (ArithmeticOp::AddA(nowrap, RegA::A8, r0, r1), a8, a(1u8), a(2u8), a(3u8), true, false),
+            (ArithmeticOp::AddA(nowrap, RegA::A8, r0, r1), a8, a(200u8), a(100u8), none, false, true),
+            (ArithmeticOp::AddA(wrap, RegA::A8, r0, r1), a8, a(200u8), a(100u8), a(44u8), true, true),
+            (ArithmeticOp::AddA(wrap, RegA::A8, r0, r1), a8, none, a(100u8), none, false, false),
+            (ArithmeticOp::SubA(nowrap, RegA::A8, r0, r1), a8, a(5u8), a(2u8), a(3u8), true, false),
+            (ArithmeticOp::SubA(nowrap, RegA::A8, r0, r1), a8, a(1u8), a(2u8), none, false, true),
+            (ArithmeticOp::SubA(wrap, RegA::A8, r0, r1), a8, a(1u8), a(2u8), none, false, true),
+            (ArithmeticOp::MulA(nowrap, RegA::A8, r0, r1), a8, a(3u8), a(4u8), a(12u8), true, false),
+            (ArithmeticOp::MulA(nowrap, RegA::A8, r0, r1), a8, a(16u8), a(16u8), none, false, true),
+            (ArithmeticOp::MulA(wrap, RegA::A8, r0, r1), a8, a(16u8), a(17u8), a(16u8), true, true),
+            (ArithmeticOp::DivA(nowrap, RegA::A8, r0, r1), a8, a(6u8), a(3u8), a(2u8), true, false),
+            (ArithmeticOp::DivA(nowrap, RegA::A8, r0, r1), a8, a(6u8), a(0u8), none, false, false),
+            (ArithmeticOp::Rem(RegA::A8, r0, RegA::A8, r1), a8, a(7u8), a(3u8), a(1u8), true, false),
+            (ArithmeticOp::Rem(RegA::A8, r0, RegA::A8, r1), a8, a(7u8), a(0u8), none, false, false),
+            (ArithmeticOp::Stp(RegA::A8, r1, Step::with(1)), a8, none, a(1u8), a(2u8), true, false),
+            (ArithmeticOp::Stp(RegA::A8, r1, Step::with(1)), a8, none, a(255u8), none, false, true),
+            (ArithmeticOp::Stp(RegA::A8, r1, Step::with(-128)), a8, none, a(200u8), a(72u8), true, false),
+            (ArithmeticOp::Stp(RegA::A8, r1, Step::with(-1)), a8, none, a(0u8), none, false, true),
+            (ArithmeticOp::Stp(RegA::A8, r1, Step::with(-1)), a8, none, none, none, false, false),
+            (ArithmeticOp::Neg(RegAF::A(RegA::A8), Reg16::Reg1), a8, none, a(1u8), none, false, true),
+            (ArithmeticOp::Neg(RegAF::F(RegF::F32), Reg16::Reg1), f32, none, f(0x3F80_0000), f(0xBF80_0000), true, false),
+            (ArithmeticOp::Abs(RegAF::F(RegF::F32), Reg16::Reg1), f32, none, f(0xBF80_0000), f(0x3F80_0000), true, false),
+            (ArithmeticOp::AddF(near, RegF::F32, r0, r1), f32, f(0x3F80_0000), f(0x3F80_0000), f(0x4000_0000), true, true),
+            (ArithmeticOp::SubF(near, RegF::F32, r0, r1), f32, f(0x3F80_0000), f(0x3F80_0000), f(0), true, true),
+            (ArithmeticOp::MulF(near, RegF::F32, r0, r1), f32, f(0x7F7F_FFFF), f(0x4000_0000), none, false, true),
+            (ArithmeticOp::DivF(near, RegF::F32, r0, r1), f32, f(0x3F80_0000), f(0), none, false, true),
+            (ArithmeticOp::DivF(near, RegF::F32, r0, r1), f32, f(0), f(0), none, false, true),
+        ];
+
+        for (op, reg, src, srcdst, res, st0, co0) in table.iter().copied() {
+            let mut regs = CoreRegs::default();
+            regs.set(reg, r0, src);
+            regs.set(reg, r1, srcdst);
+            regs.set_carry(true);
+            regs.set_status(!st0);
+            op.exec(&mut regs, LibSite::default(), &());
+            assert_eq!(regs.get(reg, r1), res, "result of {op}");
+            assert_eq!(regs.status(), st0, "st0 after {op}");
+            assert_eq!(regs.carry(), co0, "co0 after {op}");
+        }
+    }
+
+    #[test]
+    fn arithmetic_from_bytecode() {
+        use crate::library::Lib;
+
+        let (r0, r1) = (Reg32::Reg0, Reg32::Reg1);
+        let code = (0..4u8)
+            .map(|flags| IntFlags { signed: flags & 0x01 != 0, wrap: flags & 0x02 != 0 })
+            .map(|flags| {
+                Instr::<ReservedOp>::Arithmetic(ArithmeticOp::MulA(flags, RegA::A8, r0, r1))
+            })
+            .collect::<Vec<_>>();
+        let lib = Lib::assemble(&code).unwrap();
+        assert_eq!(lib.disassemble::<Instr>().unwrap(), code);
+
+        let uw = IntFlags { signed: false, wrap: true };
+        let sc = IntFlags { signed: true, wrap: false };
+        let lib = Lib::assemble(&[
+            Instr::<ReservedOp>::Arithmetic(ArithmeticOp::MulA(uw, RegA::A8, r0, r1)),
+            Instr::Arithmetic(ArithmeticOp::SubA(sc, RegA::A64, r0, r1)),
+        ])
+        .unwrap();
+        let mut regs = CoreRegs::default();
+        regs.set(RegA::A8, r0, 16u8);
+        regs.set(RegA::A8, r1, 17u8);
+        regs.set(RegA::A64, r0, 1u64);
+        regs.set(RegA::A64, r1, 2u64);
+        lib.exec::<Instr>(0, &mut regs, &());
+        // Wrapping multiplication keeps the low bits of the product
+        assert_eq!(regs.get(RegA::A8, r1), MaybeNumber::from(16u8));
+        // Negative result is sign-extended to the full register width
+        assert_eq!(regs.get(RegA::A64, r1), MaybeNumber::from(u64::MAX));
+    }
+
+    #[test]
+    fn steps() {
+        use amplify::num::apfloat::{ieee, Float};
+        use amplify::num::u256;
+
+        use crate::data::FloatFlags;
+        use crate::isa::RoundingFlag;
+        use crate::reg::RegF;
+
+        let r1 = Reg32::Reg1;
+        let nf = IntFlags::unsigned_checked();
+        let stp = |step: i8| StepOp::StpA(nf, RegA::A8, r1, step);
+        let neg = StepOp::NegA(nf, RegA::A8, r1);
+        let abs = StepOp::AbsA(nf, RegA::A8, r1);
+        let flags = [
+            IntFlags::unsigned_checked(),
+            IntFlags::unsigned_wrapped(),
+            IntFlags::signed_checked(),
+            IntFlags::signed_wrapped(),
+        ];
+
+        // For each operation and input value the table lists the expected result and `co0` value
+        // for unsigned checked, unsigned wrapped, signed checked and signed wrapped flags. `st0`
+        // must be set whenever the result is defined.
+        type Expected = [(Option<u8>, bool); 4];
+        #[rustfmt::skip]
+        let table: [(StepOp, u8, Expected); 12] = [
+            (stp(1), 1, [(Some(2), false), (Some(2), false), (Some(2), false), (Some(2), false)]),
+            (stp(1), 127, [(Some(128), false), (Some(128), false), (None, true), (Some(128), true)]),
+            (stp(1), 255, [(None, true), (Some(0), true), (Some(0), false), (Some(0), false)]),
+            (stp(-1), 0, [(None, true), (Some(255), true), (Some(255), false), (Some(255), false)]),
+            (stp(-1), 128, [(Some(127), false), (Some(127), false), (None, true), (Some(127), true)]),
+            (stp(-128), 128, [(Some(0), false), (Some(0), false), (None, true), (Some(0), true)]),
+            (stp(127), 200, [(None, true), (Some(71), true), (Some(71), false), (Some(71), false)]),
+            (neg, 0, [(Some(0), false), (Some(0), false), (Some(0), false), (Some(0), false)]),
+            (neg, 1, [(None, true), (Some(255), true), (Some(255), false), (Some(255), false)]),
+            (neg, 128, [(None, true), (Some(128), true), (None, true), (Some(128), true)]),
+            (abs, 128, [(Some(128), false), (Some(128), false), (None, true), (Some(128), true)]),
+            (abs, 255, [(Some(255), false), (Some(255), false), (Some(1), false), (Some(1), false)]),
+        ];
+
+        for (op, val, expected) in table.iter().copied() {
+            for (flags, (res, co0)) in flags.iter().copied().zip(expected) {
+                let op = match op {
+                    StepOp::StpA(_, reg, idx, step) => StepOp::StpA(flags, reg, idx, step),
+                    StepOp::NegA(_, reg, idx) => StepOp::NegA(flags, reg, idx),
+                    StepOp::AbsA(_, reg, idx) => StepOp::AbsA(flags, reg, idx),
+                    StepOp::StpF(..) => unreachable!(),
+                };
+                let mut regs = CoreRegs::default();
+                regs.set(RegA::A8, r1, val);
+                regs.set_carry(!co0);
+                regs.set_status(res.is_none());
+                op.exec(&mut regs, LibSite::default(), &());
+                let res = res.map(MaybeNumber::from).unwrap_or_default();
+                assert_eq!(regs.get(RegA::A8, r1), res, "result of {op} on {val}");
+                assert_eq!(regs.status(), res.is_some(), "st0 after {op} on {val}");
+                assert_eq!(regs.carry(), co0, "co0 after {op} on {val}");
+            }
+        }
+
+        // Operations on unset registers fail without overflow
+        let mut regs = CoreRegs::default();
+        regs.set_carry(true);
+        StepOp::NegA(IntFlags::signed_wrapped(), RegA::A8, r1).exec(
+            &mut regs,
+            LibSite::default(),
+            &(),
+        );
+        assert!(!regs.status());
+        assert!(!regs.carry());
+
+        let f = |bits: u32| MaybeNumber::from(ieee::Single::from_bits(u256::from(bits)));
+        let near = RoundingFlag::TowardsNearest;
+        regs.set(RegF::F32, r1, f(0x3F80_0000));
+        StepOp::StpF(near, RegF::F32, r1, 1).exec(&mut regs, LibSite::default(), &());
+        assert!(regs.status());
+        assert_eq!(regs.get(RegF::F32, r1), f(0x4000_0000));
+        StepOp::StpF(near, RegF::F32, r1, -3).exec(&mut regs, LibSite::default(), &());
+        assert_eq!(regs.get(RegF::F32, r1), f(0xBF80_0000));
+        assert_eq!(regs.float_flags(), FloatFlags::default());
+        regs.set(RegF::F32, r1, f(0x7F7F_FFFF));
+        StepOp::StpF(near, RegF::F32, r1, 127).exec(&mut regs, LibSite::default(), &());
+        assert!(regs.status());
+        assert_eq!(regs.float_flags(), FloatFlags { inexact: true, ..default!() });
+
+        regs.set(RegF::F512, r1, Number::zero(RegF::F512.layout()));
+        StepOp::StpF(near, RegF::F512, r1, 1).exec(&mut regs, LibSite::default(), &());
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegF::F512, r1), MaybeNumber::none());
+    }
+
+    #[test]
+    fn data_access() {
+        let mut regs = CoreRegs::default();
+        let data = [0xAA, 0xBB, 0xCC];
+        let (r0, r1) = (Reg32::Reg0, Reg32::Reg1);
+        DataOp::Len(RegA::A16, r0).exec_with_data(&mut regs, LibSite::default(), &data, &());
+        assert_eq!(regs.get(RegA::A16, r0), 3u16.into());
+        DataOp::LoadA(RegA::A16, r1, RegA::A16, r0).exec(&mut regs, LibSite::default(), &());
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A16, r1), MaybeNumber::none());
+
+        regs.set(RegA::A16, r0, 1u16);
+        DataOp::LoadA(RegA::A16, r1, RegA::A16, r0).exec_with_data(
+            &mut regs,
+            LibSite::default(),
+            &data,
+            &(),
+        );
+        assert_eq!(regs.get(RegA::A16, r1), 0xCCBBu16.into());
+        DataOp::Len(RegA::A16, r0).exec(&mut regs, LibSite::default(), &());
+        assert_eq!(regs.get(RegA::A16, r0), 0u16.into());
+    }
+
+    #[test]
+    fn float_status_register() {
+        use amplify::num::apfloat::{ieee, Float};
+        use amplify::num::u256;
+
+        use crate::data::{FloatFlags, FloatLayout};
+        use crate::isa::RoundingFlag;
+        use crate::reg::RegF;
+
+        let mut regs = CoreRegs::default();
+        let site = LibSite::default();
+        let one = ieee::Double::from_bits(u256::from(0x3FF0_0000_0000_0000u64));
+        let three = ieee::Double::from_bits(u256::from(0x4008_0000_0000_0000u64));
+        PutOp::PutF(RegF::F64, Reg32::Reg0, MaybeNumber::from(one).into()).exec(
+            &mut regs,
+            site,
+            &(),
+        );
+        PutOp::PutF(RegF::F64, Reg32::Reg1, MaybeNumber::from(three).into()).exec(
+            &mut regs,
+            site,
+            &(),
+        );
+        assert_eq!(regs.float_flags(), FloatFlags::default());
+
+        ArithmeticOp::DivF(RoundingFlag::TowardsNearest, RegF::F64, Reg32::Reg0, Reg32::Reg1).exec(
+            &mut regs,
+            site,
+            &(),
+        );
+        assert!(regs.st0);
+        assert_eq!(regs.float_flags(), FloatFlags { inexact: true, ..default!() });
+
+        let neg_zero =
+            Number::with(0x8000_0000_0000_0000u64.to_le_bytes(), FloatLayout::IeeeDouble);
+        PutOp::PutF(RegF::F64, Reg32::Reg2, MaybeNumber::from(neg_zero).into()).exec(
+            &mut regs,
+            site,
+            &(),
+        );
+        assert_eq!(regs.get(RegF::F64, Reg32::Reg2), MaybeNumber::from(ieee::Double::ZERO));
+        ArithmeticOp::DivF(RoundingFlag::TowardsNearest, RegF::F64, Reg32::Reg0, Reg32::Reg2).exec(
+            &mut regs,
+            site,
+            &(),
+        );
+        assert!(!regs.st0);
+        assert_eq!(regs.get(RegF::F64, Reg32::Reg2), MaybeNumber::none());
+        assert_eq!(regs.float_flags(), FloatFlags {
+            div_by_zero: true,
+            inexact: true,
+            ..default!()
+        });
+
+        regs.clear_float_flags();
+        assert_eq!(regs.float_flags(), FloatFlags::default());
+    }
 
     #[test]
     fn bytes_con_test() {
@@ -447,7 +447,7 @@ impl IntFlags {
     /// Constructs integer arithmetic flags from `u2` value (used in bytecode serialization)
     pub fn from_u2(val: u2) -> Self {
         let val = val.to_u8();
-        IntFlags { signed: val & 0x01 == 1, wrap: val & 0x02 >> 1 == 1 }
+        IntFlags { signed: val & 0x01 == 0x01, wrap: val & 0x02 == 0x02 }
     }
 
     /// Returns `u2` representation of integer arithmetic flags (used in bytecode serialization).
@@ -852,11 +852,11 @@ impl FromStr for InsertFlag {
         if !filtered.is_empty() {
             return Err(ParseFlagError::UnknownFlags("insert operation", filtered));
         }
-        if filtered.len() > 1 {
-            return Err(ParseFlagError::DuplicatedFlags("insert operation", filtered));
+        if s.len() > 1 {
+            return Err(ParseFlagError::DuplicatedFlags("insert operation", s.to_owned()));
         }
 
-        Ok(match filtered.as_bytes()[0].into() {
+        Ok(match s.as_bytes()[0].into() {
             'l' => InsertFlag::FailOnLen,
             'o' => InsertFlag::FailOnOffset,
             'f' => InsertFlag::FailOnOffsetLen,
@@ -952,11 +952,11 @@ impl FromStr for DeleteFlag {
         if !filtered.is_empty() {
             return Err(ParseFlagError::UnknownFlags("delete operation", filtered));
         }
-        if filtered.len() > 1 {
-            return Err(ParseFlagError::DuplicatedFlags("delete operation", filtered));
+        if s.len() > 1 {
+            return Err(ParseFlagError::DuplicatedFlags("delete operation", s.to_owned()));
         }
 
-        Ok(match filtered.as_bytes()[0].into() {
+        Ok(match s.as_bytes()[0].into() {
             'n' => DeleteFlag::None,
             'z' => DeleteFlag::Zero,
             'c' => DeleteFlag::Cut,
@@ -993,3 +993,147 @@ impl From<&DeleteFlag> for u2 {
 impl From<DeleteFlag> for u2 {
     fn from(flag: DeleteFlag) -> u2 { flag.as_u2() }
 }
+
+/// Prime field used by [`FieldOp`](super::FieldOp) instructions
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum FieldFlag {
+    /// Scalar field of BLS12-381 curve
+    #[display("381")]
+    Bls12_381 = 0,
+
+    /// Scalar field of BN254 (alt_bn128) curve
+    #[display("254")]
+    Bn254 = 1,
+}
+
+impl Flag for FieldFlag {}
+
+impl Default for FieldFlag {
+    #[inline]
+    fn default() -> Self { Self::Bls12_381 }
+}
+
+impl FromStr for FieldFlag {
+    type Err = ParseFlagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(ParseFlagError::RequiredFlagAbsent("prime field")),
+            "381" => Ok(FieldFlag::Bls12_381),
+            "254" => Ok(FieldFlag::Bn254),
+            _ => Err(ParseFlagError::UnknownFlags("prime field", s.to_owned())),
+        }
+    }
+}
+
+impl FieldFlag {
+    /// Constructs prime field flag from `u1` value (used in bytecode serialization)
+    pub fn from_u1(val: u1) -> Self {
+        match val.into_u8() {
+            v if v == FieldFlag::Bls12_381 as u8 => FieldFlag::Bls12_381,
+            v if v == FieldFlag::Bn254 as u8 => FieldFlag::Bn254,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns `u1` representation of prime field flag (used in bytecode serialization).
+    pub fn as_u1(self) -> u1 { u1::with(self as u8) }
+
+    /// Returns modulus of the field as little-endian bytes.
+    pub fn modulus(self) -> [u8; 32] {
+        match self {
+            FieldFlag::Bls12_381 => [
+                0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0x02, 0xa4,
+                0xbd, 0x53, 0x05, 0xd8, 0xa1, 0x09, 0x08, 0xd8, 0x39, 0x33, 0x48, 0x7d, 0x9d, 0x29,
+                0x53, 0xa7, 0xed, 0x73,
+            ],
+            FieldFlag::Bn254 => [
+                0x01, 0x00, 0x00, 0xf0, 0x93, 0xf5, 0xe1, 0x43, 0x91, 0x70, 0xb9, 0x79, 0x48, 0xe8,
+                0x33, 0x28, 0x5d, 0x58, 0x81, 0x81, 0xb6, 0x45, 0x50, 0xb8, 0x29, 0xa0, 0x31, 0xe1,
+                0x72, 0x4e, 0x64, 0x30,
+            ],
+        }
+    }
+}
+
+impl From<u1> for FieldFlag {
+    fn from(val: u1) -> FieldFlag { FieldFlag::from_u1(val) }
+}
+
+impl From<&FieldFlag> for u1 {
+    fn from(flag: &FieldFlag) -> u1 { flag.as_u1() }
+}
+
+impl From<FieldFlag> for u1 {
+    fn from(flag: FieldFlag) -> u1 { flag.as_u1() }
+}
+
+/// Textual encoding of binary data used by [`EncOp`](super::EncOp) instructions
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum BaseFlag {
+    /// Hexadecimal encoding, producing lowercase digits
+    #[display("16")]
+    Base16 = 0,
+
+    /// RFC 4648 base32 encoding with padding
+    #[display("32")]
+    Base32 = 1,
+
+    /// Base58 encoding with bitcoin alphabet
+    #[display("58")]
+    Base58 = 2,
+
+    /// RFC 4648 base64 encoding with padding
+    #[display("64")]
+    Base64 = 3,
+}
+
+impl Flag for BaseFlag {}
+
+impl Default for BaseFlag {
+    #[inline]
+    fn default() -> Self { Self::Base16 }
+}
+
+impl FromStr for BaseFlag {
+    type Err = ParseFlagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(ParseFlagError::RequiredFlagAbsent("base encoding")),
+            "16" => Ok(BaseFlag::Base16),
+            "32" => Ok(BaseFlag::Base32),
+            "58" => Ok(BaseFlag::Base58),
+            "64" => Ok(BaseFlag::Base64),
+            _ => Err(ParseFlagError::UnknownFlags("base encoding", s.to_owned())),
+        }
+    }
+}
+
+impl BaseFlag {
+    /// Constructs base encoding flag from `u2` value (used in bytecode serialization)
+    pub fn from_u2(val: u2) -> Self {
+        match val.to_u8() {
+            v if v == BaseFlag::Base16 as u8 => BaseFlag::Base16,
+            v if v == BaseFlag::Base32 as u8 => BaseFlag::Base32,
+            v if v == BaseFlag::Base58 as u8 => BaseFlag::Base58,
+            v if v == BaseFlag::Base64 as u8 => BaseFlag::Base64,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns `u2` representation of base encoding flag (used in bytecode serialization).
+    pub fn as_u2(self) -> u2 { u2::with(self as u8) }
+}
+
+impl From<u2> for BaseFlag {
+    fn from(val: u2) -> BaseFlag { BaseFlag::from_u2(val) }
+}
+
+impl From<&BaseFlag> for u2 {
+    fn from(flag: &BaseFlag) -> u2 { flag.as_u2() }
+}
+
+impl From<BaseFlag> for u2 {
+    fn from(flag: BaseFlag) -> u2 { flag.as_u2() }
+}
@@ -22,15 +22,23 @@
 // limitations under the License.
 
 use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
 
+use super::asm::NumberLiteral;
 use super::{
-    DeleteFlag, FloatEqFlag, InsertFlag, InstructionSet, IntFlags, MergeFlag, RoundingFlag,
-    SignFlag, SplitFlag,
+    BaseFlag, DeleteFlag, FieldFlag, FloatEqFlag, InsertFlag, InstructionSet, IntFlags, MergeFlag,
+    RoundingFlag, SignFlag, SplitFlag,
 };
-use crate::data::{ByteStr, MaybeNumber, Step};
+use crate::data::{ByteStr, Layout, MaybeNumber, Number, Step};
 use crate::isa::{ExtendFlag, NoneEqFlag};
 use crate::library::LibSite;
-use crate::reg::{Reg16, Reg32, Reg8, RegA, RegA2, RegAF, RegAR, RegBlockAR, RegF, RegR, RegS};
+use crate::reg::{
+    NumericRegister, Reg16, Reg32, Reg8, RegA, RegA2, RegAF, RegAFR, RegAR, RegBlockAR, RegF, RegR,
+    RegS,
+};
 
 /// Reserved instruction, which equal to [`ControlFlowOp::Fail`].
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display, Default)]
@@ -103,6 +111,7 @@ where
     // 0x11_***_***
     /// No-operation instruction.
     // #[value = 0b11_111_111]
+    #[display("nop")]
     Nop,
 }
 
@@ -150,18 +159,15 @@ pub enum ControlFlowOp {
 }
 
 /// Instructions setting register values
-#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum PutOp {
     /// Cleans a value of `A` register (sets it to undefined state)
-    #[display("clr     {0}{1}")]
     ClrA(RegA, Reg32),
 
     /// Cleans a value of `F` register (sets it to undefined state)
-    #[display("clr     {0}{1}")]
     ClrF(RegF, Reg32),
 
     /// Cleans a value of `R` register (sets it to undefined state)
-    #[display("clr     {0}{1}")]
     ClrR(RegR, Reg32),
 
     /// Unconditionally assigns a value to `A` register.
@@ -170,7 +176,6 @@ pub enum PutOp {
     ///     this instruction is assembled and the data are not present in the data segment (their
     ///     offset + length exceeds data segment size) the operation will set destination register
     ///     into undefined state and `st0` to `false`. Otherwise, `st0` value is not affected.
-    #[display("put     {0}{1},{2}")]
     PutA(RegA, Reg32, Box<MaybeNumber>),
 
     /// Unconditionally assigns a value to `F` register
@@ -179,7 +184,6 @@ pub enum PutOp {
     ///     this instruction is assembled and the data are not present in the data segment (their
     ///     offset + length exceeds data segment size) the operation will set destination register
     ///     into undefined state and `st0` to `false`. Otherwise, `st0` value is not affected.
-    #[display("put     {0}{1},{2}")]
     PutF(RegF, Reg32, Box<MaybeNumber>),
 
     /// Unconditionally assigns a value to `R` register
@@ -188,7 +192,6 @@ pub enum PutOp {
     ///     this instruction is assembled and the data are not present in the data segment (their
     ///     offset + length exceeds data segment size) the operation will set destination register
     ///     into undefined state and `st0` to `false`. Otherwise, `st0` value is not affected.
-    #[display("put     {0}{1},{2}")]
     PutR(RegR, Reg32, Box<MaybeNumber>),
 
     /// Conditionally assigns a value to `A` register if the register is in uninitialized state.
@@ -199,7 +202,6 @@ pub enum PutOp {
     ///     offset + length exceeds data segment size) _and_ the destination register is
     ///     initialized, the operation will set destination register into undefined state and `st0`
     ///     to `false`. Otherwise, `st0` value is changed according to the general operation rules.
-    #[display("putif   {0}{1},{2}")]
     PutIfA(RegA, Reg32, Box<MaybeNumber>),
 
     /// Conditionally assigns a value to `R` register if the register is in uninitialized state.
@@ -210,10 +212,34 @@ pub enum PutOp {
     ///     offset + length exceeds data segment size) _and_ the destination register is
     ///     initialized, the operation will set destination register into undefined state and `st0`
     ///     to `false`. Otherwise, `st0` value is changed according to the general operation rules.
-    #[display("putif   {0}{1},{2}")]
     PutIfR(RegR, Reg32, Box<MaybeNumber>),
 }
 
+impl Display for PutOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PutOp::ClrA(reg, idx) => write!(f, "clr     {}{}", reg, idx),
+            PutOp::ClrF(reg, idx) => write!(f, "clr     {}{}", reg, idx),
+            PutOp::ClrR(reg, idx) => write!(f, "clr     {}{}", reg, idx),
+            PutOp::PutA(reg, idx, val) => {
+                write!(f, "put     {}{},{}", reg, idx, NumberLiteral(val))
+            }
+            PutOp::PutF(reg, idx, val) => {
+                write!(f, "put     {}{},{}", reg, idx, NumberLiteral(val))
+            }
+            PutOp::PutR(reg, idx, val) => {
+                write!(f, "put     {}{},{}", reg, idx, NumberLiteral(val))
+            }
+            PutOp::PutIfA(reg, idx, val) => {
+                write!(f, "putif   {}{},{}", reg, idx, NumberLiteral(val))
+            }
+            PutOp::PutIfR(reg, idx, val) => {
+                write!(f, "putif   {}{},{}", reg, idx, NumberLiteral(val))
+            }
+        }
+    }
+}
+
 /// Instructions moving and swapping register values
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
 pub enum MoveOp {
@@ -418,6 +444,11 @@ pub enum CmpOp {
 /// All operations modify the value of `st0` register, setting it to `false` if the destination
 /// is set to `None`. Otherwise, `st0` value is `true`, even if the overflow has occurred (when
 /// `wrap` flag is provided).
+///
+/// Integer operations, as well as negation and absolute value operations, set `co0` register to
+/// `true` if the result has not fit into the destination layout, and to `false` otherwise. Float
+/// addition, subtraction, multiplication and division do not modify `co0`, accumulating IEEE-754
+/// exception flags into `fs0` register instead.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
 pub enum ArithmeticOp {
     /// Adds values from two integer arithmetic registers and puts result into destination.
@@ -470,7 +501,7 @@ pub enum ArithmeticOp {
     /// Increment/decrement register value on a given signed step.
     ///
     /// Sets the destination to `None` and `st0` to `false` in case of overflow.
-    #[display("{2:#}     {0}{1},{2}")]
+    #[display("{2:#}     {0}{1}{2}")]
     Stp(RegA, Reg32, Step),
 
     /// Negates most significant bit
@@ -631,7 +662,7 @@ pub enum BytesOp {
     ///
     /// If any of the offsets or value registers are unset, sets `st0` to `false` and does not
     /// change destination value.
-    #[display("fill.{4}    {0},a16{1},a16{2},a8{3}")]
+    #[display("fill.{4}  {0},a16{1},a16{2},a8{3}")]
     Fill(
         /** `s` register index */ RegS,
         /** `a16` register holding first offset */ Reg32,
@@ -699,7 +730,7 @@ pub enum BytesOp {
     /// are initialized with zeros. If the length of the inserted string plus insert offset exceeds
     /// the maximum string register length (2^16 bytes), than the destination register is set to
     /// `None` state and `st0` is set to `false`. Otherwise, `st0` value is not modified.
-    #[display("inj     {0},{1}{2},{1}{3}")]
+    #[display("inj     {0},{1}{2},a16{3}")]
     Inj(
         /** `s` register index acting as the source and destination */ RegS,
         RegR,
@@ -747,7 +778,7 @@ pub enum BytesOp {
     /// Rule on `st0` changes: if at least one of the destination registers is set to `None`, or
     /// `offset` value exceeds source string length, `st0` is set to `false`; otherwise its value
     /// is not modified
-    #[display("splt.{2}  {0},a16{1},{3},{4}")]
+    #[display("splt.{0} {2},a16{1},{3},{4}")]
     Splt(
         SplitFlag,
         /** `a16` register index with offset value */ Reg32,
@@ -785,7 +816,7 @@ pub enum BytesOp {
     /// </pre>
     ///
     /// In all of these cases `st0` is set to `false`. Otherwise, `st0` value is not modified.
-    #[display("ins.{3}   {0},{1},a16{2}")]
+    #[display("ins.{0}   {2},{3},a16{1}")]
     Ins(
         InsertFlag,
         /** `a16` register index with offset value for insert location */ Reg32,
@@ -943,3 +974,844 @@ pub enum Curve25519Op {
     #[display("edneg   r512{0},r512{1}")]
     Neg(/** Register hilding EC point to negate */ Reg32, /** Destination register */ Reg8),
 }
+
+/// Instructions reading runtime environment values (`ALURE` ISA extension).
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<EnvOp>`. The values which are not known from the currently executed
+/// code are provided by the embedder via [`Env`] execution context.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum EnvOp {
+    /// Loads id of the library containing currently executed code into `r256` register.
+    #[display("envlib  r256{0}")]
+    Lib(/** Destination `r256` register */ Reg32),
+
+    /// Loads offset of the currently executed instruction within its library code segment into
+    /// `a16` register.
+    #[display("envpos  a16{0}")]
+    Pos(/** Destination `a16` register */ Reg32),
+
+    /// Loads space-separated list of ISA extensions supported by the runtime into a string
+    /// register.
+    #[display("envisa  {0}")]
+    Isa(/** Destination string register */ RegS),
+
+    /// Loads 256-bit context value provided by the embedder into `r256` register.
+    ///
+    /// If the embedder has not provided the value, sets destination register to `None` and
+    /// `st0` to `false`.
+    #[display("envctx  r256{0}")]
+    Ctx(/** Destination `r256` register */ Reg32),
+}
+
+/// Runtime environment values provided by the embedder to [`EnvOp`] instructions as the
+/// execution context.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Env {
+    /// ISA extensions supported by the runtime, as a space-separated string
+    pub isae: String,
+
+    /// Arbitrary 256-bit value, for instance a commitment to an external state the program must
+    /// be bound to
+    pub value: Option<[u8; 32]>,
+}
+
+impl Env {
+    /// Constructs environment for a runtime using `Isa` instruction set, providing it with the
+    /// 256-bit context value.
+    pub fn with<Isa: InstructionSet>(value: impl Into<Option<[u8; 32]>>) -> Env {
+        Env { isae: Isa::isa_string(), value: value.into() }
+    }
+}
+
+/// Constant-time comparison instructions for registers holding secret data (`ALUCT` ISA
+/// extension).
+///
+/// Unlike [`CmpOp`] and [`BytesOp::Eq`], execution time of these instructions does not depend on
+/// the values of the compared registers: only on the register sizes, string lengths and whether
+/// the registers are set. The instructions are not a part of the core instruction set and must
+/// be used as an ISA extension, i.e. `Instr<CtOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum CtOp {
+    /// Checks equality of the values in two `R` registers of the same type, putting the result
+    /// into `st0`. If at least one of the registers is set to `None`, sets `st0` to `false`.
+    #[display("cteq    {0}{1},{0}{2}")]
+    EqR(RegR, Reg32, Reg32),
+
+    /// Checks whether the value of the first `R` register, interpreted as a little-endian
+    /// unsigned number, is greater than the value of the second one, putting the result into
+    /// `st0`. If at least one of the registers is set to `None`, sets `st0` to `false`.
+    #[display("ctgt    {0}{1},{0}{2}")]
+    GtR(RegR, Reg32, Reg32),
+
+    /// Checks whether the value of the first `R` register, interpreted as a little-endian
+    /// unsigned number, is less than the value of the second one, putting the result into
+    /// `st0`. If at least one of the registers is set to `None`, sets `st0` to `false`.
+    #[display("ctlt    {0}{1},{0}{2}")]
+    LtR(RegR, Reg32, Reg32),
+
+    /// Checks equality of two strings, putting the result into `st0`. If at least one of the
+    /// registers is set to `None`, sets `st0` to `false`. Strings of different length are not
+    /// equal, and the execution time depends on the lengths of the strings.
+    #[display("cteq    {0},{1}")]
+    EqS(RegS, RegS),
+}
+
+/// Deterministic pseudo-random number generation (`ALURND` ISA extension).
+///
+/// The instructions generate a ChaCha20 key stream (as defined in RFC 8439, with the all-zero
+/// nonce) keyed with the value of an `r256` register, starting from the block counter taken from
+/// an `a32` register. Generated bytes are put into the destination register in little-endian
+/// order, and the counter register is advanced by the number of the 64-byte blocks used, such that
+/// a sequence of instructions produces a continuous key stream. If the key or the counter is not
+/// set, or the counter overflows, the destination is set to `None`, the counter is not modified
+/// and `st0` is set to `false`; otherwise, `st0` is not affected.
+///
+/// The counter register is updated before the destination is written, thus using it as a
+/// destination leaves the generated value in the register. The instructions are not a part of the
+/// core instruction set and must be used as an ISA extension, i.e. `Instr<PrngOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum PrngOp {
+    /// Puts pseudo-random value into `A` register.
+    #[display("rnd     {0}{1},r256{2},a32{3}")]
+    RndA(
+        RegA,
+        /** Destination register index */ Reg32,
+        /** Index of `r256` register with the key */ Reg32,
+        /** Index of `a32` register with the block counter */ Reg32,
+    ),
+
+    /// Puts pseudo-random value into `R` register.
+    #[display("rnd     {0}{1},r256{2},a32{3}")]
+    RndR(
+        RegR,
+        /** Destination register index */ Reg32,
+        /** Index of `r256` register with the key */ Reg32,
+        /** Index of `a32` register with the block counter */ Reg32,
+    ),
+}
+
+/// Modular arithmetic instructions (`ALUMOD` ISA extension).
+///
+/// The instructions operate on the values of `R` registers of the same type, interpreted as
+/// little-endian unsigned numbers, computing the result modulo the value of the third register
+/// and putting it into the destination register. If any of the source registers is not set, or
+/// the modulus is zero, the destination is set to `None` and `st0` is set to `false`; otherwise,
+/// `st0` is not affected.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<ModOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum ModOp {
+    /// Modular addition: `dst = (src1 + src2) mod modulus`.
+    #[display("addm    {0}{1},{0}{2},{0}{3},{0}{4}")]
+    AddMod(
+        RegR,
+        /** Source 1 */ Reg32,
+        /** Source 2 */ Reg32,
+        /** Modulus */ Reg32,
+        /** Operation destination */ Reg32,
+    ),
+
+    /// Modular multiplication: `dst = (src1 * src2) mod modulus`.
+    #[display("mulm    {0}{1},{0}{2},{0}{3},{0}{4}")]
+    MulMod(
+        RegR,
+        /** Source 1 */ Reg32,
+        /** Source 2 */ Reg32,
+        /** Modulus */ Reg32,
+        /** Operation destination */ Reg32,
+    ),
+
+    /// Modular exponentiation: `dst = base ^ exp mod modulus`. Zero power of any value is one
+    /// (or zero, if the modulus is one).
+    #[display("expm    {0}{1},{0}{2},{0}{3},{0}{4}")]
+    ExpMod(
+        RegR,
+        /** Base */ Reg32,
+        /** Exponent */ Reg32,
+        /** Modulus */ Reg32,
+        /** Operation destination */ Reg32,
+    ),
+}
+
+/// Conversion between binary data and its textual encodings in `S` registers (`ALUENC` ISA
+/// extension).
+///
+/// Decoding accepts only canonical encodings. If the source register is not set, the source can't
+/// be decoded or the result exceeds the maximum length of a string register, the destination is
+/// set to `None` and `st0` is set to `false`; otherwise, `st0` is not affected.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<EncOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum EncOp {
+    /// Encodes binary data from the source register into text using the given encoding.
+    #[display("benc.{0} {1},{2}")]
+    Enc(BaseFlag, /** Source */ RegS, /** Destination */ RegS),
+
+    /// Decodes text from the source register encoded with the given encoding into binary data.
+    #[display("bdec.{0} {1},{2}")]
+    Dec(BaseFlag, /** Source */ RegS, /** Destination */ RegS),
+
+    /// Encodes binary data into bech32m string (BIP-350) using the human-readable part from the
+    /// first register, which must consist of lowercase printable ASCII characters.
+    #[display("bechenc {0},{1},{2}")]
+    Bech32Enc(/** Human-readable part */ RegS, /** Data */ RegS, /** Destination */ RegS),
+
+    /// Decodes bech32m string (BIP-350), putting its lowercase human-readable part into the second
+    /// register and the data into the third one. The string must not mix lowercase and uppercase
+    /// letters. If both destinations are the same register, it receives the data. On failure,
+    /// both destinations are set to `None`.
+    #[display("bechdec {0},{1},{2}")]
+    Bech32Dec(/** Source */ RegS, /** Human-readable part */ RegS, /** Data */ RegS),
+}
+
+/// Jumps by a signed displacement relative to the offset of the jump instruction itself
+/// (`ALUREL` ISA extension).
+///
+/// Unlike [`ControlFlowOp`] jumps, relative jumps don't depend on the location of the code in the
+/// code segment, allowing code fragments to be moved and patched without rewriting them. If the
+/// destination lies outside of the offsets addressable by the code segment, the execution stops
+/// and `st0` is set to `false`.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<RelJumpOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum RelJumpOp {
+    /// Unconditionally jumps by a displacement. Increments `cy0`.
+    #[display("rjmp    {0:+}")]
+    Jmp(i16),
+
+    /// Jumps by a displacement if `st0` == true, otherwise does nothing. Increments `cy0`.
+    #[display("rjif    {0:+}")]
+    Jif(i16),
+
+    /// Calls a subroutine located by a displacement. Increments `cy0` and pushes offset of the
+    /// instruction which follows current one to `cs0`.
+    #[display("rroutine {0:+}")]
+    Routine(i16),
+}
+
+/// Puts values of up to 8 bytes kept inline in the code segment into the registers (`ALUIMM` ISA
+/// extension).
+///
+/// Each instruction is equivalent to the [`PutOp`] instruction returned by [`ImmOp::to_put`],
+/// but reads its value from the code segment instead of the data segment, saving the data segment
+/// space and the indirection when the instruction is decoded. The values are kept as little-endian
+/// integers, which are zero-extended (or truncated) to the register bit dimension.
+///
+/// The instructions are not a part of the core instruction set; once [`Instr`] uses this
+/// extension, i.e. `Instr<ImmOp>`, its `put` instructions with small values are encoded as
+/// `ALUIMM` instructions automatically. The instructions are displayed in the same way as the
+/// equivalent `put` instructions.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum ImmOp {
+    /// Unconditionally assigns the value to `A` register.
+    PutA(RegA, Reg32, u64),
+
+    /// Unconditionally assigns the value to `F` register.
+    PutF(RegF, Reg32, u64),
+
+    /// Unconditionally assigns the value to `R` register.
+    PutR(RegR, Reg32, u64),
+
+    /// Assigns the value to `A` register if the register is in uninitialized state, like
+    /// [`PutOp::PutIfA`].
+    PutIfA(RegA, Reg32, u64),
+
+    /// Assigns the value to `R` register if the register is in uninitialized state, like
+    /// [`PutOp::PutIfR`].
+    PutIfR(RegR, Reg32, u64),
+}
+
+impl ImmOp {
+    /// Returns layout of the destination register and its value size in bytes.
+    fn reg_layout(&self) -> (Layout, usize) {
+        match self {
+            ImmOp::PutA(reg, _, _) | ImmOp::PutIfA(reg, _, _) => {
+                (reg.layout(), reg.bytes() as usize)
+            }
+            ImmOp::PutF(reg, _, _) => (reg.layout(), reg.bytes() as usize),
+            ImmOp::PutR(reg, _, _) | ImmOp::PutIfR(reg, _, _) => {
+                (reg.layout(), reg.bytes() as usize)
+            }
+        }
+    }
+
+    /// Returns the inline value truncated to the bit dimension of the destination register.
+    pub fn value(&self) -> u64 {
+        let val = match self {
+            ImmOp::PutA(_, _, val)
+            | ImmOp::PutF(_, _, val)
+            | ImmOp::PutR(_, _, val)
+            | ImmOp::PutIfA(_, _, val)
+            | ImmOp::PutIfR(_, _, val) => *val,
+        };
+        match self.reg_layout().1 {
+            len if len >= 8 => val,
+            len => val & ((1u64 << (len * 8)) - 1),
+        }
+    }
+
+    /// Returns number of bytes taken by the inline value in the code segment.
+    pub(crate) fn value_len(&self) -> u16 { (8 - self.value().leading_zeros() as u16 / 8).max(1) }
+
+    /// Returns the inline value as a number in the layout of the destination register.
+    pub fn number(&self) -> Number {
+        let (layout, len) = self.reg_layout();
+        let mut bytes = [0u8; 1024];
+        bytes[..8].copy_from_slice(&self.value().to_le_bytes());
+        Number::with(&bytes[..len], layout).expect("register layout matches its byte length")
+    }
+
+    /// Converts the instruction into the equivalent [`PutOp`] instruction, which keeps its value
+    /// in the data segment.
+    pub fn to_put(&self) -> PutOp {
+        let value = Box::new(MaybeNumber::some(self.number()));
+        match *self {
+            ImmOp::PutA(reg, idx, _) => PutOp::PutA(reg, idx, value),
+            ImmOp::PutF(reg, idx, _) => PutOp::PutF(reg, idx, value),
+            ImmOp::PutR(reg, idx, _) => PutOp::PutR(reg, idx, value),
+            ImmOp::PutIfA(reg, idx, _) => PutOp::PutIfA(reg, idx, value),
+            ImmOp::PutIfR(reg, idx, _) => PutOp::PutIfR(reg, idx, value),
+        }
+    }
+
+    /// Converts `put` instruction into the instruction keeping its value inline, if the value
+    /// fits into 8 bytes once converted to the layout of the destination register. Returns `None`
+    /// for the `clr` instructions, for the values which do not fit and for the `put`
+    /// instructions without value.
+    pub fn with_put(op: &PutOp) -> Option<ImmOp> {
+        fn inline(reg: impl NumericRegister, val: &MaybeNumber) -> Option<u64> {
+            let mut val = (**val)?;
+            if !val.reshape(reg.layout().using_sign(val.layout())) {
+                return None;
+            }
+            let bytes = &val[..];
+            let len = bytes.len().min(8);
+            if bytes[len..].iter().any(|byte| *byte != 0) {
+                return None;
+            }
+            let mut le = [0u8; 8];
+            le[..len].copy_from_slice(&bytes[..len]);
+            Some(u64::from_le_bytes(le))
+        }
+        Some(match op {
+            PutOp::ClrA(_, _) | PutOp::ClrF(_, _) | PutOp::ClrR(_, _) => return None,
+            PutOp::PutA(reg, idx, val) => ImmOp::PutA(*reg, *idx, inline(*reg, val)?),
+            PutOp::PutF(reg, idx, val) => ImmOp::PutF(*reg, *idx, inline(*reg, val)?),
+            PutOp::PutR(reg, idx, val) => ImmOp::PutR(*reg, *idx, inline(*reg, val)?),
+            PutOp::PutIfA(reg, idx, val) => ImmOp::PutIfA(*reg, *idx, inline(*reg, val)?),
+            PutOp::PutIfR(reg, idx, val) => ImmOp::PutIfR(*reg, *idx, inline(*reg, val)?),
+        })
+    }
+}
+
+impl Display for ImmOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Display::fmt(&self.to_put(), f) }
+}
+
+/// Saving and restoring ranges of registers around routine calls (`ALUFRM` ISA extension).
+///
+/// The instructions operate on a frame stack kept by the VM next to the call stack: `pushr` saves
+/// values of the registers from the first to the last one (inclusive) of a register bank as a new
+/// frame, and `popr` restores the registers from the frame on the top of the stack, including
+/// the registers which had no value. Routines which save the registers they use at their entry
+/// and restore them before returning may be composed without clobbering registers of their
+/// callers.
+///
+/// If the range is empty, the frame stack is full (see [`FRAME_STACK_SIZE`]), or the top frame
+/// was saved for a different range of registers than the one being restored, the instructions
+/// set `st0` to `false` and do nothing; otherwise, `st0` is not affected. The frame stack is
+/// independent from the call stack, thus `ret` does not restore the frames.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<FrameOp>`.
+///
+/// [`FRAME_STACK_SIZE`]: crate::reg::FRAME_STACK_SIZE
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum FrameOp {
+    /// Saves values of a range of `A` registers to the frame stack.
+    #[display("pushr   {0}{1},{0}{2}")]
+    PushA(RegA, /** First register */ Reg32, /** Last register */ Reg32),
+
+    /// Saves values of a range of `F` registers to the frame stack.
+    #[display("pushr   {0}{1},{0}{2}")]
+    PushF(RegF, /** First register */ Reg32, /** Last register */ Reg32),
+
+    /// Saves values of a range of `R` registers to the frame stack.
+    #[display("pushr   {0}{1},{0}{2}")]
+    PushR(RegR, /** First register */ Reg32, /** Last register */ Reg32),
+
+    /// Restores values of a range of `A` registers from the frame stack.
+    #[display("popr    {0}{1},{0}{2}")]
+    PopA(RegA, /** First register */ Reg32, /** Last register */ Reg32),
+
+    /// Restores values of a range of `F` registers from the frame stack.
+    #[display("popr    {0}{1},{0}{2}")]
+    PopF(RegF, /** First register */ Reg32, /** Last register */ Reg32),
+
+    /// Restores values of a range of `R` registers from the frame stack.
+    #[display("popr    {0}{1},{0}{2}")]
+    PopR(RegR, /** First register */ Reg32, /** Last register */ Reg32),
+}
+
+/// Stack of fixed-size values kept in a string register (`ALUSTK` ISA extension).
+///
+/// A string register is used as a stack, which top is at the end of the string: `spush` appends
+/// little-endian value of an `A` or `R` register to the string, `spop` removes the value of the
+/// register size from the end of the string, putting it into the register, and `speek` reads the
+/// same value without removing it. A string register set to `None` is an empty stack. `sdepth`
+/// puts number of the values of the given register size kept in the stack into `a16` register.
+///
+/// If the pushed register is not set, or the string would exceed either its maximum length or
+/// [`RunLimits::max_str_bytes`] (stack overflow), `spush` does not modify the stack. If the
+/// stack has less bytes than the register size (stack underflow), `spop` and `speek` do not
+/// modify the stack and set the destination register to `None`. In all these cases `st0` is set
+/// to `false`; otherwise, `st0` is not affected.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<StackOp>`.
+///
+/// [`RunLimits::max_str_bytes`]: crate::RunLimits::max_str_bytes
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum StackOp {
+    /// Pushes value of `A` register to the stack.
+    #[display("spush   {0},{1}{2}")]
+    PushA(/** Stack register */ RegS, RegA, Reg32),
+
+    /// Pushes value of `R` register to the stack.
+    #[display("spush   {0},{1}{2}")]
+    PushR(/** Stack register */ RegS, RegR, Reg32),
+
+    /// Pops value from the stack into `A` register.
+    #[display("spop    {0},{1}{2}")]
+    PopA(/** Stack register */ RegS, RegA, Reg32),
+
+    /// Pops value from the stack into `R` register.
+    #[display("spop    {0},{1}{2}")]
+    PopR(/** Stack register */ RegS, RegR, Reg32),
+
+    /// Copies value from the top of the stack into `A` register.
+    #[display("speek   {0},{1}{2}")]
+    PeekA(/** Stack register */ RegS, RegA, Reg32),
+
+    /// Copies value from the top of the stack into `R` register.
+    #[display("speek   {0},{1}{2}")]
+    PeekR(/** Stack register */ RegS, RegR, Reg32),
+
+    /// Puts number of the values of `A` register size kept in the stack into `a16` register.
+    #[display("sdepth  {0},{1},a16{2}")]
+    DepthA(/** Stack register */ RegS, /** Value size */ RegA, /** Destination */ Reg32),
+
+    /// Puts number of the values of `R` register size kept in the stack into `a16` register.
+    #[display("sdepth  {0},{1},a16{2}")]
+    DepthR(/** Stack register */ RegS, /** Value size */ RegR, /** Destination */ Reg32),
+}
+
+/// Arithmetic over prime fields used by zero-knowledge proof systems (`ALUFLD` ISA extension).
+///
+/// The instructions operate on `r256` registers, which values are interpreted as little-endian
+/// elements of the prime field selected by the instruction flag (see [`FieldFlag`]). Values must
+/// be canonical, i.e. less than the field modulus. If any of the source registers is not set or
+/// has non-canonical value, or the inverted value is zero, the destination is set to `None` and
+/// `st0` is set to `false`; otherwise, `st0` is not affected.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<FieldOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum FieldOp {
+    /// Field addition: `dst = src1 + src2`.
+    #[display("addf.{0} r256{1},r256{2},r256{3}")]
+    Add(
+        FieldFlag,
+        /** Source 1 */ Reg32,
+        /** Source 2 */ Reg32,
+        /** Operation destination */ Reg32,
+    ),
+
+    /// Field multiplication: `dst = src1 * src2`.
+    #[display("mulf.{0} r256{1},r256{2},r256{3}")]
+    Mul(
+        FieldFlag,
+        /** Source 1 */ Reg32,
+        /** Source 2 */ Reg32,
+        /** Operation destination */ Reg32,
+    ),
+
+    /// Multiplicative inverse: `dst = src ^ -1`.
+    #[display("invf.{0} r256{1},r256{2}")]
+    Inv(FieldFlag, /** Source */ Reg32, /** Operation destination */ Reg32),
+
+    /// Exponentiation: `dst = base ^ exp`. The exponent is an arbitrary unsigned 256-bit number,
+    /// which is not required to be less than the field modulus. Zero power of any value is one.
+    #[display("expf.{0} r256{1},r256{2},r256{3}")]
+    Exp(FieldFlag, /** Base */ Reg32, /** Exponent */ Reg32, /** Operation destination */ Reg32),
+}
+
+/// Calls of the functions provided by the host environment (`ALUHOST` ISA extension).
+///
+/// Host functions are identified by 16-bit numbers assigned by the embedder, which also defines
+/// the registers the functions take their arguments from. A function returns a single value,
+/// which is put into the destination register of the instruction.
+///
+/// When the program is executed in chunks with [`Vm::start`] and [`Vm::resume`], `hcall`
+/// suspends the execution returning [`Execution::Pending`]. The embedder computes the function
+/// result, which may be done asynchronously, and continues the execution from the next
+/// instruction by completing the returned [`PendingCall`]. Other execution methods, like
+/// [`Vm::run`], can't call the host: the instruction sets the destination register to `None` and
+/// `st0` to `false`.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<HostOp>`.
+///
+/// [`Vm::start`]: crate::Vm::start
+/// [`Vm::resume`]: crate::Vm::resume
+/// [`Vm::run`]: crate::Vm::run
+/// [`Execution::Pending`]: crate::Execution::Pending
+/// [`PendingCall`]: crate::PendingCall
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum HostOp {
+    /// Calls host function returning its result in `A` register.
+    #[display("hcall   {0:#06X},{1}{2}")]
+    CallA(/** Host function */ u16, /** Result destination */ RegA, Reg32),
+
+    /// Calls host function returning its result in `R` register.
+    #[display("hcall   {0:#06X},{1}{2}")]
+    CallR(/** Host function */ u16, /** Result destination */ RegR, Reg32),
+}
+
+/// Bulk transfer of values between sets of registers of the same family and bit size
+/// (`ALUXFR` ISA extension).
+///
+/// Each instruction takes two sets of register indexes, encoded as 32-bit masks, pairing the
+/// registers of both sets in the increasing order of their indexes: `movm a64[1-4],a64[5-8]`
+/// moves values of `a64[1]`..`a64[4]` into `a64[5]`..`a64[8]`. All values are read before any
+/// register is written, thus the sets may overlap.
+///
+/// `movm` sets source registers which are not in the destination set to `None`, in the same way
+/// as `mov` does. `swpm` swaps values of the paired registers and requires the sets to be
+/// disjoint. If the sets have different number of registers, or `swpm` sets overlap, the
+/// instructions set `st0` to `false` and do nothing; otherwise, `st0` is not affected.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<XferOp>`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum XferOp {
+    /// Moves values of a set of `A` registers into another set of `A` registers.
+    MovA(RegA, /** Sources */ BTreeSet<Reg32>, /** Destinations */ BTreeSet<Reg32>),
+
+    /// Moves values of a set of `F` registers into another set of `F` registers.
+    MovF(RegF, /** Sources */ BTreeSet<Reg32>, /** Destinations */ BTreeSet<Reg32>),
+
+    /// Moves values of a set of `R` registers into another set of `R` registers.
+    MovR(RegR, /** Sources */ BTreeSet<Reg32>, /** Destinations */ BTreeSet<Reg32>),
+
+    /// Swaps values of two disjoint sets of `A` registers.
+    SwpA(RegA, BTreeSet<Reg32>, BTreeSet<Reg32>),
+
+    /// Swaps values of two disjoint sets of `F` registers.
+    SwpF(RegF, BTreeSet<Reg32>, BTreeSet<Reg32>),
+
+    /// Swaps values of two disjoint sets of `R` registers.
+    SwpR(RegR, BTreeSet<Reg32>, BTreeSet<Reg32>),
+}
+
+impl Display for XferOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (mnemonic, reg, set1, set2) = match self {
+            XferOp::MovA(reg, set1, set2) => ("movm", RegAFR::A(*reg), set1, set2),
+            XferOp::MovF(reg, set1, set2) => ("movm", RegAFR::F(*reg), set1, set2),
+            XferOp::MovR(reg, set1, set2) => ("movm", RegAFR::R(*reg), set1, set2),
+            XferOp::SwpA(reg, set1, set2) => ("swpm", RegAFR::A(*reg), set1, set2),
+            XferOp::SwpF(reg, set1, set2) => ("swpm", RegAFR::F(*reg), set1, set2),
+            XferOp::SwpR(reg, set1, set2) => ("swpm", RegAFR::R(*reg), set1, set2),
+        };
+        write!(f, "{:8}{},{}", mnemonic, RegSet(reg, set1), RegSet(reg, set2))
+    }
+}
+
+/// Displays set of registers of the same family, merging consecutive indexes into ranges, like
+/// `a64[1-4,7]`.
+struct RegSet<'a>(RegAFR, &'a BTreeSet<Reg32>);
+
+impl Display for RegSet<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[", self.0)?;
+        let mut ranges = Vec::<(u8, u8)>::new();
+        for idx in self.1.iter().map(|idx| u8::from(*idx)) {
+            match ranges.last_mut() {
+                Some((_, last)) if *last + 1 == idx => *last = idx,
+                _ => ranges.push((idx, idx)),
+            }
+        }
+        for (no, (first, last)) in ranges.into_iter().enumerate() {
+            if no > 0 {
+                f.write_str(",")?;
+            }
+            match first == last {
+                true => write!(f, "{}", first)?,
+                false => write!(f, "{}-{}", first, last)?,
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// Increments, decrements, negation and absolute value with explicit overflow handling (`ALUSTEP`
+/// ISA extension).
+///
+/// The instructions complement core [`ArithmeticOp::Stp`], which always checks for unsigned
+/// overflow and can't be applied to `F` registers, and [`ArithmeticOp::Neg`] and
+/// [`ArithmeticOp::Abs`], which treat `A` register values as unsigned integers. Integer operations
+/// take [`IntFlags`], such that the value may be treated as a signed (two's complement) or
+/// unsigned integer, and the overflow may either set the register to `None` or wrap around.
+///
+/// All operations set `co0` to `true` if the result has not fit into the register, and to `false`
+/// otherwise; float operations accumulate IEEE-754 exception flags into `fs0` instead. `st0` is
+/// set to `false` if the register is left in `None` state, and to `true` otherwise.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<StepOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum StepOp {
+    /// Adds signed step to the integer register value.
+    #[display("stpa.{0} {1}{2},{3}")]
+    StpA(IntFlags, RegA, Reg32, /** Step */ i8),
+
+    /// Adds signed step to the float register value, rounding the result according to the flag.
+    #[display("stpf.{0}  {1}{2},{3}")]
+    StpF(RoundingFlag, RegF, Reg32, /** Step */ i8),
+
+    /// Negates the integer register value.
+    ///
+    /// Unsigned negation succeeds without wrapping only for zero value.
+    #[display("nega.{0} {1}{2}")]
+    NegA(IntFlags, RegA, Reg32),
+
+    /// Replaces the integer register value with its absolute value.
+    ///
+    /// Unsigned values are always left unchanged.
+    #[display("absa.{0} {1}{2}")]
+    AbsA(IntFlags, RegA, Reg32),
+}
+
+/// Runtime access to the data segment of the executed library (`ALUDATA` ISA extension).
+///
+/// Core instructions can only use data at the offsets fixed in the bytecode. These instructions
+/// read the length of the data segment and load values at the offsets computed by the program,
+/// taking them from an `A` register, which allows lookups in the tables placed into the data
+/// segment (see [`Lib::assemble_with_tables`](crate::library::Lib::assemble_with_tables)).
+///
+/// Loads read as many bytes as the destination register has, interpreting them as a
+/// little-endian value. If the offset register is not set, or the data to load exceed the data
+/// segment, the destination register is set to `None` and `st0` to `false`; otherwise, `st0` is
+/// not affected. The same applies to `ldlen` if the length doesn't fit into the register.
+///
+/// The instructions access the data segment provided by the library running them with
+/// [`InstructionSet::exec_with_data`]. Executed with [`InstructionSet::exec`], they operate on an
+/// empty data segment.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<DataOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum DataOp {
+    /// Puts length of the data segment into the register.
+    #[display("ldlen   {0}{1}")]
+    Len(RegA, Reg32),
+
+    /// Loads value of the data segment at the offset from the second register into the first
+    /// `A` register.
+    #[display("ldd     {0}{1},{2}{3}")]
+    LoadA(RegA, Reg32, /** Offset register */ RegA, Reg32),
+
+    /// Loads value of the data segment at the offset from the `A` register into the `R`
+    /// register.
+    #[display("ldd     {0}{1},{2}{3}")]
+    LoadR(RegR, Reg32, /** Offset register */ RegA, Reg32),
+}
+
+/// Computed jumps through the tables of code offsets (`ALUSWCH` ISA extension).
+///
+/// The instruction takes an index from an `A` register and jumps to the code offset at that index
+/// in the table, providing constant-time dispatch for `match`-like constructs instead of chains of
+/// comparisons and conditional jumps. If the register is not set or the index exceeds the table,
+/// the instruction sets `st0` to `false` and passes execution to the next instruction, which thus
+/// may handle the default case; otherwise, `st0` is not affected.
+///
+/// The table is kept in the data segment as a sequence of little-endian 16-bit offsets; a table
+/// which exceeds the data segment or has odd length makes the instruction undecodable. All table
+/// entries are reported by [`Bytecode::jump_table`](super::Bytecode::jump_table), such that they
+/// are validated by the [`Linter`](crate::library::lint::Linter) and followed by the code analysis
+/// in the same way as the other jump destinations. Libraries assembled with
+/// [`Lib::assemble`](crate::library::Lib::assemble) have all table entries pointing to the
+/// instructions of the library.
+///
+/// The table is read from the data segment each time the instruction is executed, thus the
+/// instruction complexity grows with the table length.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<SwitchOp>`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum SwitchOp {
+    /// Jumps to the code offset from the table at the index from the register. Increments `cy0`
+    /// if the jump is taken.
+    Switch(RegA, Reg32, /** Table of code offsets */ Vec<u16>),
+}
+
+impl Display for SwitchOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let SwitchOp::Switch(reg, idx, table) = self;
+        write!(f, "switch  {}{}", reg, idx)?;
+        for offset in table {
+            write!(f, ",{:#06X}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loops with statically bounded number of iterations (`ALULOOP` ISA extension).
+///
+/// The instruction closes a loop, counting its iterations in an `a16` register: it decrements the
+/// counter and jumps back to the start of the loop unless the counter reaches zero, in the same way
+/// as `loop` instructions of the hardware processors do. Thus, a loop whose counter is set to `n`
+/// before the loop start executes its body `n` times (or once, if `n` is zero).
+///
+/// The maximal number of iterations is encoded in the instruction, such that the loops are bounded
+/// by the code itself and static analysis knows their cost without running the code (see
+/// [`Bytecode::loop_bound`](super::Bytecode::loop_bound)). If the counter is not set or exceeds the
+/// maximum, the instruction doesn't change the counter, sets `st0` to `false` and exits the loop;
+/// otherwise, `st0` is not affected.
+///
+/// The maximum bounds a single run of the loop only if the instruction jumps backward and the loop
+/// body doesn't write the counter, which is checked by the
+/// [`LintRule::UnboundedLoop`](crate::library::lint::LintRule::UnboundedLoop) lint rule rather than
+/// by the virtual machine.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<LoopOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum LoopOp {
+    /// Decrements the counter and jumps to the code offset if the counter is not zero afterwards.
+    /// Increments `cy0` if the jump is taken.
+    #[display("loop    a16{0},{1},{2:#06X}")]
+    Loop(/** Counter `a16` register */ Reg32, /** Maximal number of iterations */ u16, u16),
+}
+
+/// Fixed-point arithmetics (`ALUFIX` ISA extension).
+///
+/// The instructions treat values of integer arithmetic registers as fixed-point numbers having
+/// the number of fractional bits specified by the instruction (see
+/// [`FixedLayout`](crate::data::FixedLayout)) and scale the results of multiplication and division
+/// accordingly. This allows to perform computations on fractional values, like monetary amounts,
+/// with exact results reproducible across all platforms, which is hard to achieve with floats.
+///
+/// Both instructions set `co0` if the result does not fit the register layout, following the rules
+/// of the core integer arithmetics; the number of fractional bits which is not less than the
+/// register bit dimension always sets destination to `None`.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<FixedOp>`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum FixedOp {
+    /// Multiplies fixed-point values from two integer arithmetic registers and puts result into
+    /// destination. The fractional bits of the product beyond the precision are truncated towards
+    /// zero.
+    #[display("xmul.{0} {1}{2},{1}{3},{4}")]
+    MulA(IntFlags, RegA, Reg32, Reg32, /** Fractional bits */ u16),
+
+    /// Divides fixed-point values from two integer arithmetic registers and puts result into
+    /// destination. Uses Euclidean division if the wrap flag is set.
+    ///
+    /// Division by zero sets destination to `None`, without setting `co0`.
+    #[display("xdiv.{0} {1}{2},{1}{3},{4}")]
+    DivA(IntFlags, RegA, Reg32, Reg32, /** Fractional bits */ u16),
+}
+
+/// Bitcoin script-like checks (`BITCOIN` ISA extension).
+///
+/// The instructions verify hash locks and signatures in the same way as bitcoin script opcodes
+/// `OP_HASH160 OP_EQUAL`, `OP_HASH256 OP_EQUAL`, `OP_CHECKSIG` and its tapscript version do,
+/// allowing applications to express script conditions in AluVM. Signature hashes are not computed
+/// by the instructions and must be supplied in an `r256` register.
+///
+/// Each of the instructions sets `st0` to the result of the check; a check of a register which
+/// does not contain a value fails.
+///
+/// The instructions are not a part of the core instruction set and must be used as an ISA
+/// extension, i.e. `Instr<BitcoinOp>`.
+#[cfg(feature = "bitcoin")]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+#[non_exhaustive]
+pub enum BitcoinOp {
+    /// Checks that `RIPEMD160(SHA256(s))` of the string register equals to the value of `r160`
+    /// register, which is kept in the same byte order as produced by `ripemd` instruction.
+    #[display("chkh160 {0},r160{1}")]
+    Hash160(/** Preimage */ RegS, /** Hash */ Reg32),
+
+    /// Checks that `SHA256(SHA256(s))` of the string register equals to the value of `r256`
+    /// register, which is kept in the same byte order as produced by `sha2` instruction.
+    #[display("chkh256 {0},r256{1}")]
+    Hash256(/** Preimage */ RegS, /** Hash */ Reg32),
+
+    /// Verifies ECDSA signature over secp256k1 curve, like `OP_CHECKSIG` of legacy and segwit v0
+    /// scripts.
+    ///
+    /// The public key is taken in compressed or uncompressed serialization from the first string
+    /// register. The signature in the second string register must be DER-encoded and followed by
+    /// the sighash type byte, as it is serialized in bitcoin scripts; the sighash type is not
+    /// interpreted. The signature is parsed with lax DER rules and high `S` values are accepted,
+    /// matching bitcoin consensus rules. The signed message hash is taken from `r256` register.
+    #[display("chksig  {0},{1},r256{2}")]
+    CheckSig(/** Public key */ RegS, /** Signature */ RegS, /** Message hash */ Reg32),
+
+    /// Verifies BIP-340 Schnorr signature, like `OP_CHECKSIG` of tapscript.
+    ///
+    /// The x-only public key is taken from the first `r256` register. The signature in the string
+    /// register must be 64 bytes long, optionally followed by the sighash type byte, which is not
+    /// interpreted. The signed message hash is taken from the second `r256` register.
+    #[display("chkschn r256{0},{1},r256{2}")]
+    CheckSchnorr(/** Public key */ Reg32, /** Signature */ RegS, /** Message hash */ Reg32),
+}
+
+/// Composition of two ISA extensions, allowing to use both of them in a single instruction set,
+/// i.e. `Instr<Extensions<EnvOp, CtOp>>`. More extensions are composed by nesting, such as
+/// `Extensions<EnvOp, Extensions<CtOp, PrngOp>>`.
+///
+/// Decoder dispatches opcodes according to [`Bytecode::instr_range`] of the extensions; if their
+/// opcode ranges overlap, the first extension takes precedence. The ISA ids of the composition are
+/// the union of the ids of both extensions, and the context is a tuple of the contexts of the
+/// extensions.
+///
+/// [`Bytecode::instr_range`]: crate::isa::Bytecode::instr_range
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(inner)]
+pub enum Extensions<First, Second>
+where
+    First: InstructionSet,
+    Second: InstructionSet,
+{
+    /// Instruction of the first extension
+    First(First),
+
+    /// Instruction of the second extension
+    Second(Second),
+}
@@ -0,0 +1,236 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Macros for implementing bytecode encoding of ISA extensions
+
+/// Implements [`Bytecode`](crate::isa::Bytecode) for an ISA extension enum from a table of its
+/// opcodes and operand bit layouts.
+///
+/// Each line of the table assigns an opcode to an enum variant, lists the names of the variant
+/// fields (in the order of their declaration), and then the order and bit sizes in which the
+/// fields are encoded after the opcode byte. Supported sizes are `u1`-`u8`, `u16` and `u24`; field
+/// types must be `Copy` and convertible to and from the integer of the given size. The encoded
+/// operands are padded with zero bits to the byte boundary, and the instruction byte count is
+/// computed from the layout.
+///
+/// The generated decoder returns [`CodeEofError`](crate::library::CodeEofError) for the opcodes
+/// which are not in the table, since an extension receives all opcodes from the extension range.
+///
+/// # Example
+///
+/// ```
+/// # use aluvm::reg::{Reg32, RegR};
+/// #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// pub enum MyOp {
+///     Zero(RegR, Reg32),
+///     Swap(RegR, Reg32, Reg32),
+///     Halt,
+/// }
+///
+/// aluvm::bytecode! {
+///     MyOp {
+///         0x80 => Zero(reg, idx) [reg: u3, idx: u5],
+///         0x81 => Swap(reg, idx1, idx2) [idx1: u5, idx2: u5, reg: u3],
+///         0x82 => Halt [],
+///     }
+/// }
+///
+/// use aluvm::isa::Bytecode;
+/// assert_eq!(MyOp::Zero(RegR::R128, Reg32::Reg1).byte_count(), 2);
+/// assert_eq!(MyOp::Swap(RegR::R128, Reg32::Reg1, Reg32::Reg2).byte_count(), 3);
+/// assert_eq!(MyOp::Halt.instr_byte(), 0x82);
+/// assert_eq!(MyOp::instr_range(), 0x80..=0x82);
+/// ```
+#[macro_export]
+macro_rules! bytecode {
+    ($ty:ident {
+        $(
+            $opcode:expr => $variant:ident $(( $( $field:ident ),* ))?
+                [ $( $arg:ident : $bits:ident ),* ]
+        ),+ $(,)?
+    }) => {
+        impl $crate::isa::Bytecode for $ty {
+            fn byte_count(&self) -> u16 {
+                match self {
+                    $( $ty::$variant { .. } => {
+                        1 + (0 $( + $crate::__bytecode_bits!($bits) )* + 7) / 8
+                    } )+
+                }
+            }
+
+            fn instr_range() -> ::core::ops::RangeInclusive<u8> {
+                let opcodes = [$( $opcode ),+];
+                let min = opcodes.iter().copied().min().unwrap_or_default();
+                let max = opcodes.iter().copied().max().unwrap_or_default();
+                min..=max
+            }
+
+            fn instr_byte(&self) -> u8 {
+                match self {
+                    $( $ty::$variant { .. } => $opcode, )+
+                }
+            }
+
+            fn claims_opcode(opcode: u8) -> bool { [$( $opcode ),+].contains(&opcode) }
+
+            #[allow(unused_variables)]
+            fn encode_args<W>(&self, writer: &mut W) -> Result<(), $crate::isa::BytecodeError>
+            where
+                W: $crate::library::Write,
+            {
+                match *self {
+                    $( $ty::$variant $(( $( $field ),* ))? => {
+                        let bits = 0 $( + $crate::__bytecode_bits!($bits) )*;
+                        $( $crate::__bytecode_write!(writer, $bits, $arg); )*
+                        for _ in bits..(bits + 7) / 8 * 8 {
+                            writer.write_bool(false)?;
+                        }
+                    } )+
+                }
+                Ok(())
+            }
+
+            fn decode<R>(reader: &mut R) -> Result<Self, $crate::library::CodeEofError>
+            where
+                Self: Sized,
+                R: $crate::library::Read,
+            {
+                let instr = reader.read_u8()?;
+                $( if instr == $opcode {
+                    let bits = 0 $( + $crate::__bytecode_bits!($bits) )*;
+                    $( let $arg = $crate::__bytecode_read!(reader, $bits); )*
+                    for _ in bits..(bits + 7) / 8 * 8 {
+                        reader.read_bool()?;
+                    }
+                    return Ok($ty::$variant $(( $( $field.into() ),* ))?);
+                } )+
+                Err($crate::library::CodeEofError)
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __bytecode_bits {
+    (u1) => {
+        1u16
+    };
+    (u2) => {
+        2u16
+    };
+    (u3) => {
+        3u16
+    };
+    (u4) => {
+        4u16
+    };
+    (u5) => {
+        5u16
+    };
+    (u6) => {
+        6u16
+    };
+    (u7) => {
+        7u16
+    };
+    (u8) => {
+        8u16
+    };
+    (u16) => {
+        16u16
+    };
+    (u24) => {
+        24u16
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __bytecode_write {
+    ($writer:ident,u1, $arg:ident) => {
+        $writer.write_u1($arg)?
+    };
+    ($writer:ident,u2, $arg:ident) => {
+        $writer.write_u2($arg)?
+    };
+    ($writer:ident,u3, $arg:ident) => {
+        $writer.write_u3($arg)?
+    };
+    ($writer:ident,u4, $arg:ident) => {
+        $writer.write_u4($arg)?
+    };
+    ($writer:ident,u5, $arg:ident) => {
+        $writer.write_u5($arg)?
+    };
+    ($writer:ident,u6, $arg:ident) => {
+        $writer.write_u6($arg)?
+    };
+    ($writer:ident,u7, $arg:ident) => {
+        $writer.write_u7($arg)?
+    };
+    ($writer:ident,u8, $arg:ident) => {
+        $writer.write_u8($arg)?
+    };
+    ($writer:ident,u16, $arg:ident) => {
+        $writer.write_u16($arg)?
+    };
+    ($writer:ident,u24, $arg:ident) => {
+        $writer.write_u24($arg)?
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __bytecode_read {
+    ($reader:ident,u1) => {
+        $reader.read_u1()?
+    };
+    ($reader:ident,u2) => {
+        $reader.read_u2()?
+    };
+    ($reader:ident,u3) => {
+        $reader.read_u3()?
+    };
+    ($reader:ident,u4) => {
+        $reader.read_u4()?
+    };
+    ($reader:ident,u5) => {
+        $reader.read_u5()?
+    };
+    ($reader:ident,u6) => {
+        $reader.read_u6()?
+    };
+    ($reader:ident,u7) => {
+        $reader.read_u7()?
+    };
+    ($reader:ident,u8) => {
+        $reader.read_u8()?
+    };
+    ($reader:ident,u16) => {
+        $reader.read_u16()?
+    };
+    ($reader:ident,u24) => {
+        $reader.read_u24()?
+    };
+}
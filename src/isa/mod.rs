@@ -23,21 +23,29 @@
 
 //! AluVM instruction set architecture
 
+#[macro_use]
+mod macros;
+mod asm;
 mod bytecode;
 mod exec;
 mod flags;
 mod instr;
 pub mod opcodes;
 
-pub use bytecode::{Bytecode, BytecodeError};
+pub(crate) use asm::is_identifier;
+pub use asm::{operand, Line, LiteralPool, ParseInstrError, SourceError};
+pub use bytecode::{core_opcode_owner, Bytecode, BytecodeError, OpcodeCollision};
 pub use exec::{ExecStep, InstructionSet};
 pub use flags::{
-    DeleteFlag, ExtendFlag, Flag, FloatEqFlag, InsertFlag, IntFlags, MergeFlag, NoneEqFlag,
-    ParseFlagError, RoundingFlag, SignFlag, SplitFlag,
+    BaseFlag, DeleteFlag, ExtendFlag, FieldFlag, Flag, FloatEqFlag, InsertFlag, IntFlags,
+    MergeFlag, NoneEqFlag, ParseFlagError, RoundingFlag, SignFlag, SplitFlag,
 };
+#[cfg(feature = "bitcoin")]
+pub use instr::BitcoinOp;
 pub use instr::{
-    ArithmeticOp, BitwiseOp, BytesOp, CmpOp, ControlFlowOp, Curve25519Op, DigestOp, Instr, MoveOp,
-    PutOp, ReservedOp, Secp256k1Op,
+    ArithmeticOp, BitwiseOp, BytesOp, CmpOp, ControlFlowOp, CtOp, Curve25519Op, DataOp, DigestOp,
+    EncOp, Env, EnvOp, Extensions, FieldOp, FixedOp, FrameOp, HostOp, ImmOp, Instr, LoopOp, ModOp,
+    MoveOp, PrngOp, PutOp, RelJumpOp, ReservedOp, Secp256k1Op, StackOp, StepOp, SwitchOp, XferOp,
 };
 
 /// List of standardised ISA extensions.
@@ -132,6 +132,126 @@ pub const INSTR_ED_MUL: u8 = 0b10_001_101;
 pub const INSTR_ED_ADD: u8 = 0b10_001_110;
 pub const INSTR_ED_NEG: u8 = 0b10_001_111;
 
+// ### Runtime environment (ALURE)
+
+pub const INSTR_ENV_LIB: u8 = 0b10_010_000;
+pub const INSTR_ENV_POS: u8 = 0b10_010_001;
+pub const INSTR_ENV_ISA: u8 = 0b10_010_010;
+pub const INSTR_ENV_CTX: u8 = 0b10_010_011;
+
+// ### Constant-time comparison (ALUCT)
+
+pub const INSTR_CT_EQR: u8 = 0b10_010_100;
+pub const INSTR_CT_GTR: u8 = 0b10_010_101;
+pub const INSTR_CT_LTR: u8 = 0b10_010_110;
+pub const INSTR_CT_EQS: u8 = 0b10_010_111;
+
+// ### Pseudo-random number generation (ALURND)
+
+pub const INSTR_RND_A: u8 = 0b10_011_000;
+pub const INSTR_RND_R: u8 = 0b10_011_001;
+
+// ### Modular arithmetic (ALUMOD)
+
+pub const INSTR_MOD_ADD: u8 = 0b10_011_010;
+pub const INSTR_MOD_MUL: u8 = 0b10_011_011;
+pub const INSTR_MOD_EXP: u8 = 0b10_011_100;
+
+// ### Textual encodings of binary data (ALUENC)
+
+pub const INSTR_ENC: u8 = 0b10_100_000;
+pub const INSTR_DEC: u8 = 0b10_100_001;
+pub const INSTR_BECH32_ENC: u8 = 0b10_100_010;
+pub const INSTR_BECH32_DEC: u8 = 0b10_100_011;
+
+// ### Relative jumps (ALUREL)
+
+pub const INSTR_RJMP: u8 = 0b10_100_100;
+pub const INSTR_RJIF: u8 = 0b10_100_101;
+pub const INSTR_RROUTINE: u8 = 0b10_100_110;
+
+// ### Inline immediate values (ALUIMM)
+
+pub const INSTR_IMM_A: u8 = 0b10_100_111;
+pub const INSTR_IMM_F: u8 = 0b10_101_000;
+pub const INSTR_IMM_R: u8 = 0b10_101_001;
+pub const INSTR_IMMIF_A: u8 = 0b10_101_010;
+pub const INSTR_IMMIF_R: u8 = 0b10_101_011;
+
+// ### Register frames (ALUFRM)
+
+pub const INSTR_PUSHR_A: u8 = 0b10_101_100;
+pub const INSTR_PUSHR_F: u8 = 0b10_101_101;
+pub const INSTR_PUSHR_R: u8 = 0b10_101_110;
+pub const INSTR_POPR_A: u8 = 0b10_101_111;
+pub const INSTR_POPR_F: u8 = 0b10_110_000;
+pub const INSTR_POPR_R: u8 = 0b10_110_001;
+
+// ### Stacks in string registers (ALUSTK)
+
+pub const INSTR_SPUSH_A: u8 = 0b10_110_010;
+pub const INSTR_SPUSH_R: u8 = 0b10_110_011;
+pub const INSTR_SPOP_A: u8 = 0b10_110_100;
+pub const INSTR_SPOP_R: u8 = 0b10_110_101;
+pub const INSTR_SPEEK_A: u8 = 0b10_110_110;
+pub const INSTR_SPEEK_R: u8 = 0b10_110_111;
+pub const INSTR_SDEPTH_A: u8 = 0b10_111_000;
+pub const INSTR_SDEPTH_R: u8 = 0b10_111_001;
+
+// ### Bitcoin script checks (BITCOIN)
+
+pub const INSTR_CHKH160: u8 = 0b10_111_010;
+pub const INSTR_CHKH256: u8 = 0b10_111_011;
+pub const INSTR_CHKSIG: u8 = 0b10_111_100;
+pub const INSTR_CHKSCHN: u8 = 0b10_111_101;
+
+// ### Prime field arithmetic (ALUFLD)
+
+pub const INSTR_ADDF: u8 = 0b10_111_110;
+pub const INSTR_MULF: u8 = 0b10_111_111;
+pub const INSTR_INVF: u8 = 0b11_000_000;
+pub const INSTR_EXPF: u8 = 0b11_000_001;
+
+// ### Host function calls (ALUHOST)
+
+pub const INSTR_HCALL_A: u8 = 0b11_000_010;
+pub const INSTR_HCALL_R: u8 = 0b11_000_011;
+
+// ### Bulk register transfers (ALUXFR)
+
+pub const INSTR_MOVM_A: u8 = 0b11_000_100;
+pub const INSTR_MOVM_F: u8 = 0b11_000_101;
+pub const INSTR_MOVM_R: u8 = 0b11_000_110;
+pub const INSTR_SWPM_A: u8 = 0b11_000_111;
+pub const INSTR_SWPM_F: u8 = 0b11_001_000;
+pub const INSTR_SWPM_R: u8 = 0b11_001_001;
+
+// ### Increments and negation with overflow handling (ALUSTEP)
+
+pub const INSTR_STP_A: u8 = 0b11_001_010;
+pub const INSTR_STP_F: u8 = 0b11_001_011;
+pub const INSTR_NEG_A: u8 = 0b11_001_100;
+pub const INSTR_ABS_A: u8 = 0b11_001_101;
+
+// ### Runtime data segment access (ALUDATA)
+
+pub const INSTR_LDLEN: u8 = 0b11_001_110;
+pub const INSTR_LDD_A: u8 = 0b11_001_111;
+pub const INSTR_LDD_R: u8 = 0b11_010_000;
+
+// ### Computed jumps (ALUSWCH)
+
+pub const INSTR_SWITCH: u8 = 0b11_010_001;
+
+// ### Bounded loops (ALULOOP)
+
+pub const INSTR_LOOP: u8 = 0b11_010_010;
+
+// ### Fixed-point arithmetics (ALUFIX)
+
+pub const INSTR_FMUL: u8 = 0b11_010_011;
+pub const INSTR_FDIV: u8 = 0b11_010_100;
+
 // Opcodes with may be used by ISA extensions
 pub const INSTR_ISAE_FROM: u8 = 0b10_000_000;
 pub const INSTR_ISAE_TO: u8 = 0b11_111_110;
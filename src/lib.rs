@@ -107,6 +107,7 @@
 //!   - one for signed/unsigned variant of the encoding
 //!   - one for checked or wrapped variant of exception handling
 //! * Float encoding has 4 variants of rounding, matching IEEE-754 options
+//!
 //! Thus, many arithmetic instructions have 8 variants, indicating the used encoding (unsigned,
 //! signed integer or float) and operation behavior in situation when resulting value does not fit
 //! into the register (overflow or wrap for integers and one of four rounding options for floats).
@@ -152,21 +153,38 @@ extern crate strict_encoding;
 extern crate serde_crate as serde;
 extern crate core;
 
+#[cfg(feature = "deterministic-audit")]
+pub mod audit;
 pub mod data;
+pub mod export;
 #[macro_use]
 pub mod isa;
 pub mod library;
 mod program;
+pub mod receipt;
 pub mod reg;
+pub mod replay;
+pub mod selftest;
 #[cfg(feature = "stl")]
 pub mod stl;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod test_utils;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+#[cfg(any(test, feature = "test_utils"))]
+pub mod vectors;
 mod vm;
 
 pub use isa::Isa;
 #[doc(hidden)]
 pub use paste::paste;
-pub use program::{Prog, ProgError, Program};
-pub use vm::Vm;
+pub use program::{LinkError, Prog, ProgError, Program, StoreProg};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub use vm::DEADLINE_CHECK_STEPS;
+pub use vm::{
+    verify_batch, CallHook, ExecStats, Execution, FailureHook, PendingCall, RunError,
+    RunLimitError, RunLimits, Vm, VmState,
+};
 
 /// Struct types library name.
 pub const LIB_NAME_ALUVM: &str = "AluVM";
@@ -0,0 +1,418 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Descriptors of the inputs and outputs of library routines.
+//!
+//! An [`Abi`] declares registers which a routine expects to be set by the caller and registers
+//! which keep the routine results, together with the layouts of their values. Descriptors are
+//! attached to the exported routines with [`Lib::set_abi`](super::Lib::set_abi) and are serialized
+//! with the library routine table.
+//!
+//! Descriptors are not committed by the library id, thus they never affect the program execution.
+//! Hosts which trust the descriptors may check the registers against them with
+//! [`crate::Vm::check_inputs`] before running the routine and with [`crate::Vm::check_outputs`]
+//! after it.
+//!
+//! Hosts may use the descriptor to marshal routine inputs into the registers with
+//! [`Abi::load_inputs`] and to read the results with [`Abi::read_outputs`], without knowing
+//! which registers keep them.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use crate::data::{ByteStr, Layout, Number};
+use crate::reg::{CoreRegs, MarshalError, NumericRegister, Reg};
+
+/// Errors of routine ABI descriptors
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum AbiError {
+    /// register {0} can't keep a value with {1} layout
+    LayoutMismatch(Reg, Layout),
+
+    /// register {0} is declared more than once
+    RepeatedRegister(Reg),
+
+    /// routine input register {0} is not set
+    InputNotSet(Reg),
+
+    /// routine output register {0} is not set
+    OutputNotSet(Reg),
+}
+
+/// Routine input or output kept in a register
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct AbiParam {
+    /// Register keeping the value
+    pub reg: Reg,
+
+    /// Layout of the value. If `None`, the value uses the layout of the register itself, which is
+    /// the only option for the string (`S`) registers.
+    pub layout: Option<Layout>,
+}
+
+impl AbiParam {
+    /// Constructs parameter keeping value in the register layout.
+    #[inline]
+    pub fn new(reg: impl Into<Reg>) -> Self { AbiParam { reg: reg.into(), layout: None } }
+
+    /// Constructs parameter keeping value with a specific layout, for instance a signed integer
+    /// in an `A` register.
+    #[inline]
+    pub fn with(reg: impl Into<Reg>, layout: Layout) -> Self {
+        AbiParam { reg: reg.into(), layout: Some(layout) }
+    }
+
+    /// Returns layout of the parameter value, or `None` for the byte strings.
+    pub fn value_layout(&self) -> Option<Layout> {
+        self.layout.or(match self.reg {
+            Reg::A(a, _) => Some(a.layout()),
+            Reg::F(f, _) => Some(f.layout()),
+            Reg::R(r, _) => Some(r.layout()),
+            Reg::S(_) => None,
+        })
+    }
+
+    /// Checks that the value layout matches the register.
+    ///
+    /// # Errors
+    ///
+    /// If the layout is provided for the string register, if an integer layout has a bit
+    /// dimension different from the `A` or `R` register or if a float layout doesn't match the
+    /// `F` register.
+    pub fn check(&self) -> Result<(), AbiError> {
+        let layout = match self.layout {
+            None => return Ok(()),
+            Some(layout) => layout,
+        };
+        let valid = match (self.reg, layout) {
+            (Reg::A(a, _), Layout::Integer(int)) => int.bytes == a.bytes(),
+            (Reg::R(r, _), Layout::Integer(int)) => int.bytes == r.bytes(),
+            (Reg::F(f, _), layout) => f.layout() == layout,
+            _ => false,
+        };
+        if !valid {
+            return Err(AbiError::LayoutMismatch(self.reg, layout));
+        }
+        Ok(())
+    }
+}
+
+impl Display for AbiParam {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.layout {
+            None => Display::fmt(&self.reg, f),
+            Some(layout) => write!(f, "{}:{}", self.reg, layout),
+        }
+    }
+}
+
+/// Value of a routine input or output
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(inner)]
+pub enum AbiValue {
+    /// Value of a numeric register
+    #[from]
+    Number(Number),
+
+    /// Value of a string register
+    #[from]
+    Bytes(ByteStr),
+}
+
+/// Descriptor of registers taken and produced by a library routine
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Abi {
+    inputs: Vec<AbiParam>,
+    outputs: Vec<AbiParam>,
+}
+
+impl Abi {
+    /// Constructs descriptor of a routine taking `inputs` and producing `outputs`.
+    ///
+    /// # Errors
+    ///
+    /// If some of the parameter layouts doesn't match its register, or if a register is repeated
+    /// among the inputs or among the outputs.
+    pub fn new(
+        inputs: impl IntoIterator<Item = AbiParam>,
+        outputs: impl IntoIterator<Item = AbiParam>,
+    ) -> Result<Self, AbiError> {
+        fn params(iter: impl IntoIterator<Item = AbiParam>) -> Result<Vec<AbiParam>, AbiError> {
+            let mut regs = BTreeSet::new();
+            iter.into_iter()
+                .map(|param| {
+                    param.check()?;
+                    if !regs.insert(param.reg) {
+                        return Err(AbiError::RepeatedRegister(param.reg));
+                    }
+                    Ok(param)
+                })
+                .collect()
+        }
+        Ok(Abi { inputs: params(inputs)?, outputs: params(outputs)? })
+    }
+
+    /// Returns routine inputs.
+    #[inline]
+    pub fn inputs(&self) -> &[AbiParam] { &self.inputs }
+
+    /// Returns routine outputs.
+    #[inline]
+    pub fn outputs(&self) -> &[AbiParam] { &self.outputs }
+
+    /// Checks that all routine inputs are set in the registers.
+    ///
+    /// # Errors
+    ///
+    /// With [`AbiError::InputNotSet`] for the first input which doesn't have a value.
+    pub fn check_inputs(&self, regs: &CoreRegs) -> Result<(), AbiError> {
+        match self.inputs.iter().find(|param| !regs.is_set(param.reg)) {
+            Some(param) => Err(AbiError::InputNotSet(param.reg)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that all routine outputs are set in the registers.
+    ///
+    /// # Errors
+    ///
+    /// With [`AbiError::OutputNotSet`] for the first output which doesn't have a value.
+    pub fn check_outputs(&self, regs: &CoreRegs) -> Result<(), AbiError> {
+        match self.outputs.iter().find(|param| !regs.is_set(param.reg)) {
+            Some(param) => Err(AbiError::OutputNotSet(param.reg)),
+            None => Ok(()),
+        }
+    }
+
+    /// Puts values of the routine inputs into the registers, in the order in which the inputs
+    /// are declared. Integer values are converted into the layouts of the inputs. The registers
+    /// are not modified unless all the values fit into them.
+    ///
+    /// # Errors
+    ///
+    /// If the number of values doesn't match the number of inputs, if a byte string is provided
+    /// for a numeric input or a number for a string input, or if a number doesn't fit into the
+    /// input layout (see [`CoreRegs::marshal`]).
+    pub fn load_inputs(
+        &self,
+        regs: &mut CoreRegs,
+        values: impl IntoIterator<Item = impl Into<AbiValue>>,
+    ) -> Result<(), MarshalError> {
+        let values = values.into_iter().map(Into::into).collect::<Vec<AbiValue>>();
+        if values.len() != self.inputs.len() {
+            return Err(MarshalError::InputCount {
+                expected: self.inputs.len(),
+                provided: values.len(),
+            });
+        }
+        let mut numbers = Vec::with_capacity(values.len());
+        let mut strings = Vec::new();
+        for (param, value) in self.inputs.iter().zip(values) {
+            match (param.reg, value) {
+                (Reg::S(s), AbiValue::Bytes(bytes)) => strings.push((s, bytes)),
+                (reg @ Reg::S(_), AbiValue::Number(_)) => {
+                    return Err(MarshalError::NotNumeric(reg))
+                }
+                (reg, AbiValue::Bytes(_)) => return Err(MarshalError::NotString(reg)),
+                (reg, AbiValue::Number(number)) => {
+                    let number = match param.layout {
+                        Some(Layout::Integer(layout)) => number
+                            .fitted(layout)
+                            .ok_or(MarshalError::Overflow { reg, layout: number.layout() })?,
+                        _ => number,
+                    };
+                    numbers.push((reg, CoreRegs::marshal(reg, number)?));
+                }
+            }
+        }
+        regs.load_inputs(&numbers)?;
+        for (s, bytes) in strings {
+            regs.set_s(s, Some(bytes));
+        }
+        Ok(())
+    }
+
+    /// Reads values of the routine outputs from the registers, in the order in which the
+    /// outputs are declared, interpreting numbers with the layouts of the outputs.
+    ///
+    /// # Errors
+    ///
+    /// With [`MarshalError::OutputNotSet`] for the first output which doesn't have a value.
+    pub fn read_outputs(&self, regs: &CoreRegs) -> Result<Vec<AbiValue>, MarshalError> {
+        self.outputs
+            .iter()
+            .map(|param| {
+                let value = match param.reg {
+                    Reg::S(s) => regs.get_s(s).cloned().map(AbiValue::Bytes),
+                    reg => regs.get_reg(reg).map(|number| match param.layout {
+                        Some(layout) => Number::with(number, layout)
+                            .expect("ABI parameter layout matches the register")
+                            .into(),
+                        None => number.into(),
+                    }),
+                };
+                value.ok_or(MarshalError::OutputNotSet(param.reg))
+            })
+            .collect()
+    }
+}
+
+impl Display for Abi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut Formatter<'_>, params: &[AbiParam]| -> fmt::Result {
+            for (no, param) in params.iter().enumerate() {
+                if no > 0 {
+                    f.write_str(", ")?;
+                }
+                Display::fmt(param, f)?;
+            }
+            Ok(())
+        };
+        f.write_str("(")?;
+        list(f, &self.inputs)?;
+        f.write_str(") -> (")?;
+        list(f, &self.outputs)?;
+        f.write_str(")")
+    }
+}
+
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde_crate::de::Error;
+    use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Abi;
+    use crate::data::encoding::{Decode, Encode};
+
+    impl Serialize for Abi {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Encode::serialize(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Abi {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let data = Vec::<u8>::deserialize(deserializer)?;
+            <Abi as Decode>::deserialize(data).map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::{ByteStr, FloatLayout};
+    use crate::reg::{Reg32, RegA, RegF, RegR, RegS};
+
+    #[test]
+    fn layouts() {
+        let a = Reg::A(RegA::A16, Reg32::Reg1);
+        assert_eq!(AbiParam::new(a).value_layout(), Some(Layout::unsigned(2)));
+        assert_eq!(AbiParam::with(a, Layout::signed(2)).check(), Ok(()));
+        assert_eq!(
+            AbiParam::with(a, Layout::signed(4)).check(),
+            Err(AbiError::LayoutMismatch(a, Layout::signed(4)))
+        );
+        let f = Reg::F(RegF::F32, Reg32::Reg0);
+        assert_eq!(AbiParam::with(f, Layout::float(FloatLayout::IeeeSingle)).check(), Ok(()));
+        assert!(AbiParam::with(f, Layout::unsigned(4)).check().is_err());
+        assert!(AbiParam::with(Reg::R(RegR::R256, Reg32::Reg0), Layout::unsigned(32))
+            .check()
+            .is_ok());
+        let s = Reg::S(RegS::from(2));
+        assert_eq!(AbiParam::new(s).value_layout(), None);
+        assert!(AbiParam::with(s, Layout::unsigned(1)).check().is_err());
+    }
+
+    #[test]
+    fn inputs() {
+        let a = Reg::A(RegA::A64, Reg32::Reg0);
+        let s = Reg::S(RegS::from(0));
+        assert_eq!(
+            Abi::new([AbiParam::new(a), AbiParam::with(a, Layout::signed(8))], []),
+            Err(AbiError::RepeatedRegister(a))
+        );
+        let abi =
+            Abi::new([AbiParam::with(a, Layout::signed(8)), AbiParam::new(s)], [AbiParam::new(a)])
+                .unwrap();
+        assert_eq!(abi.to_string(), "(a64[0]:i64, s16[0]) -> (a64[0])");
+
+        let mut regs = CoreRegs::default();
+        assert_eq!(abi.check_inputs(&regs), Err(AbiError::InputNotSet(a)));
+        regs.set(RegA::A64, Reg32::Reg0, 5u64);
+        assert_eq!(abi.check_inputs(&regs), Err(AbiError::InputNotSet(s)));
+        regs.set_s(0, Some(ByteStr::with(b"abc")));
+        assert_eq!(abi.check_inputs(&regs), Ok(()));
+        assert_eq!(abi.check_outputs(&regs), Ok(()));
+        assert_eq!(abi.check_outputs(&CoreRegs::default()), Err(AbiError::OutputNotSet(a)));
+    }
+
+    #[test]
+    fn marshaling() {
+        let a = Reg::A(RegA::A16, Reg32::Reg0);
+        let s = Reg::S(RegS::from(1));
+        let abi = Abi::new([AbiParam::with(a, Layout::signed(2)), AbiParam::new(s)], [
+            AbiParam::with(a, Layout::signed(2)),
+            AbiParam::new(s),
+        ])
+        .unwrap();
+
+        let mut regs = CoreRegs::default();
+        assert_eq!(
+            abi.load_inputs(&mut regs, [AbiValue::from(Number::from(1u8))]),
+            Err(MarshalError::InputCount { expected: 2, provided: 1 })
+        );
+        assert_eq!(
+            abi.load_inputs(&mut regs, [Number::from(1u8), Number::from(2u8)]),
+            Err(MarshalError::NotNumeric(s))
+        );
+        let bytes = ByteStr::with(b"abc");
+        assert_eq!(
+            abi.load_inputs(&mut regs, [
+                AbiValue::from(Number::from(40000u16)),
+                bytes.clone().into()
+            ]),
+            Err(MarshalError::Overflow { reg: a, layout: Layout::unsigned(2) })
+        );
+        assert!(!regs.is_set(s));
+        assert_eq!(abi.read_outputs(&regs), Err(MarshalError::OutputNotSet(a)));
+
+        abi.load_inputs(&mut regs, [AbiValue::from(Number::from(-5i64)), bytes.clone().into()])
+            .unwrap();
+        assert_eq!(regs.get_a(RegA::A16, Reg32::Reg0), Some(Number::from(0xFFFBu16)));
+        assert_eq!(
+            abi.read_outputs(&regs),
+            Ok(vec![AbiValue::Number(Number::from(-5i16)), AbiValue::Bytes(bytes)])
+        );
+    }
+}
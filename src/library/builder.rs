@@ -0,0 +1,144 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental construction of libraries by the compilers targeting AluVM.
+
+use alloc::vec::Vec;
+
+use super::{AssembleOpts, AssemblerError, DebugInfo, Lib, SourceSpan};
+use crate::isa::InstructionSet;
+
+/// Builder of a library from the instructions emitted one by one, optionally with the spans of
+/// their sources, which are put into the [`SourceMap`](super::SourceMap) of the library
+/// [`DebugInfo`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct LibBuilder<Isa>
+where
+    Isa: InstructionSet,
+{
+    code: Vec<Isa>,
+    spans: Vec<(usize, SourceSpan)>,
+    opts: AssembleOpts,
+}
+
+impl<Isa> Default for LibBuilder<Isa>
+where
+    Isa: InstructionSet,
+{
+    fn default() -> Self { LibBuilder { code: Vec::new(), spans: Vec::new(), opts: default!() } }
+}
+
+impl<Isa> LibBuilder<Isa>
+where
+    Isa: InstructionSet,
+{
+    /// Constructs builder with no instructions, using the default assembly options
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Constructs builder with no instructions, using the provided assembly options
+    #[inline]
+    pub fn with_opts(opts: AssembleOpts) -> Self { LibBuilder { opts, ..Self::default() } }
+
+    /// Appends instruction which has no source span
+    #[inline]
+    pub fn push(&mut self, instr: Isa) -> &mut Self {
+        self.code.push(instr);
+        self
+    }
+
+    /// Appends instruction compiled from the source at `span`
+    pub fn push_spanned(&mut self, instr: Isa, span: SourceSpan) -> &mut Self {
+        self.spans.push((self.code.len(), span));
+        self.push(instr)
+    }
+
+    /// Returns number of the instructions appended to the builder
+    #[inline]
+    pub fn len(&self) -> usize { self.code.len() }
+
+    /// Detects whether no instructions were appended to the builder
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.code.is_empty() }
+
+    /// Assembles the library from the appended instructions, attaching the source spans of the
+    /// instructions as the library debug information.
+    ///
+    /// # Errors
+    ///
+    /// Errors returned by [`Lib::assemble_with_opts`].
+    pub fn build(&self) -> Result<Lib<'static>, AssemblerError> {
+        let mut lib = Lib::assemble_with_opts(&self.code, self.opts)?;
+        let mut debug = DebugInfo::new();
+        let mut pos = 0u16;
+        let mut spans = self.spans.iter().peekable();
+        for (index, instr) in self.code.iter().enumerate() {
+            while let Some((_, span)) = spans.next_if(|(at, _)| *at == index) {
+                debug.spans.insert(pos, span.clone());
+            }
+            // The code size is checked by the assembler
+            pos = pos.wrapping_add(instr.byte_count());
+        }
+        lib.debug = Some(debug);
+        Ok(lib)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::encoding::{Decode, Encode};
+    use crate::isa::{ControlFlowOp, Instr};
+
+    #[test]
+    fn source_map() {
+        let mut builder = LibBuilder::<Instr>::new();
+        builder
+            .push_spanned(Instr::Nop, SourceSpan::with("main.rs", 1, 5, 3))
+            .push(Instr::ControlFlow(ControlFlowOp::Jmp(0)))
+            .push_spanned(
+                Instr::ControlFlow(ControlFlowOp::Succ),
+                SourceSpan::with("main.rs", 2, 1, 4),
+            );
+        assert_eq!(builder.len(), 3);
+
+        let lib = builder.build().unwrap();
+        assert_eq!(lib.id(), Lib::assemble(&builder.code).unwrap().id());
+        let spans = &lib.debug.as_ref().unwrap().spans;
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans.span_at(0).unwrap().to_string(), "main.rs:1:5");
+        assert_eq!(spans.span_at(1), None);
+        assert_eq!(spans.span_at(4), Some(&SourceSpan::with("main.rs", 2, 1, 4)));
+
+        let decoded = Lib::deserialize(lib.serialize()).unwrap();
+        assert_eq!(decoded.debug, lib.debug);
+
+        let mut patched = lib;
+        patched.patch::<Instr>(1..4, &[]).unwrap();
+        let spans = &patched.debug.as_ref().unwrap().spans;
+        assert_eq!(spans.iter().map(|(pos, span)| (pos, span.line)).collect::<Vec<_>>(), [
+            (0, 1),
+            (1, 2)
+        ]);
+    }
+}
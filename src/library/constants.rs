@@ -25,8 +25,12 @@
 
 #![allow(missing_docs)]
 
+/// Size of the code segment address space, in bytes. Length of the code segment is limited by
+/// [`SegmentLimits::MAX`](super::SegmentLimits::MAX), which is one byte less.
 pub const CODE_SEGMENT_MAX_LEN: usize = 1 << 16;
 
+/// Size of the data segment address space, in bytes. Length of the data segment is limited by
+/// [`SegmentLimits::MAX`](super::SegmentLimits::MAX), which is one byte less.
 pub const DATA_SEGMENT_MAX_LEN: usize = 1 << 16;
 
 /// Maximum number of libraries that may be referenced (used by) any other library; i.e. limit for
@@ -37,6 +41,18 @@ pub const LIBS_SEGMENT_MAX_COUNT: usize = 1 << 8;
 /// nodes in a library dependency tree.
 pub const LIBS_MAX_TOTAL: u16 = 1024;
 
+/// Number of bytes occupied in the code segment by a reference to a library from the libs
+/// segment, written with [`Write::write_lib`](super::Write::write_lib).
+pub const LIB_REF_LEN: u16 = 1;
+
+/// Number of bytes occupied in the code segment by a reference to a byte string in the data
+/// segment (its offset and length), written with [`Write::write_data`](super::Write::write_data).
+pub const DATA_REF_LEN: u16 = 4;
+
+/// Number of bytes occupied in the code segment by a reference to a number in the data segment
+/// (its offset), written with [`Write::write_number`](super::Write::write_number).
+pub const NUMBER_REF_LEN: u16 = 2;
+
 pub const ISAE_SEGMENT_MAX_LEN: usize = 0xFF;
 
 pub const ISAE_SEGMENT_MAX_COUNT: usize = 32;
@@ -60,6 +76,22 @@ pub const ISA_ID_SECP256K: &str = "SECP256K";
 pub const ISA_ID_ED25519: &str = "ED25519";
 
 pub const ISA_ID_ALURE: &str = "ALURE";
+pub const ISA_ID_ALUCT: &str = "ALUCT";
+pub const ISA_ID_ALURND: &str = "ALURND";
+pub const ISA_ID_ALUMOD: &str = "ALUMOD";
+pub const ISA_ID_ALUENC: &str = "ALUENC";
+pub const ISA_ID_ALUREL: &str = "ALUREL";
+pub const ISA_ID_ALUIMM: &str = "ALUIMM";
+pub const ISA_ID_ALUFRM: &str = "ALUFRM";
+pub const ISA_ID_ALUSTK: &str = "ALUSTK";
+pub const ISA_ID_ALUFLD: &str = "ALUFLD";
+pub const ISA_ID_ALUHOST: &str = "ALUHOST";
+pub const ISA_ID_ALUXFR: &str = "ALUXFR";
+pub const ISA_ID_ALUSTEP: &str = "ALUSTEP";
+pub const ISA_ID_ALUDATA: &str = "ALUDATA";
+pub const ISA_ID_ALUSWCH: &str = "ALUSWCH";
+pub const ISA_ID_ALULOOP: &str = "ALULOOP";
+pub const ISA_ID_ALUFIX: &str = "ALUFIX";
 pub const ISA_ID_SIMD: &str = "SIMD";
 pub const ISA_ID_INET2: &str = "INET4";
 pub const ISA_ID_WEB4: &str = "WEB4";
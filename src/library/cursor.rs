@@ -21,20 +21,114 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use core::convert::TryInto;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
 #[cfg(feature = "std")]
 use core::fmt::{self, Debug, Display, Formatter};
+use core::ops::Range;
 
 use amplify::num::{u1, u2, u24, u3, u4, u5, u6, u7};
 
-use super::{CodeEofError, LibId, LibSeg, Read, Write, WriteError};
+use super::{CodeEofError, LibId, LibSeg, Read, SegmentLimits, Write, WriteError};
 use crate::data::Number;
-use crate::isa::{Bytecode, Instr, InstructionSet};
-use crate::library::constants::{CODE_SEGMENT_MAX_LEN, DATA_SEGMENT_MAX_LEN};
+use crate::isa::{Bytecode, BytecodeError, Instr, InstructionSet};
+use crate::library::constants::{
+    CODE_SEGMENT_MAX_LEN, DATA_REF_LEN, DATA_SEGMENT_MAX_LEN, LIB_REF_LEN, NUMBER_REF_LEN,
+};
 use crate::reg::NumericRegister;
 
+/// Maximal length of the byte sequence prefixes indexed by [`DataIndex`]
+const INDEX_PREFIX_MAX: usize = 4;
+
+/// Number of hash buckets in each of [`DataIndex`] tables
+const INDEX_BUCKETS: usize = 1 << 12;
+
+/// Data segment length below which [`DataIndex`] is not built and the data are scanned instead
+const INDEX_MIN_LEN: usize = 1 << 10;
+
+/// Marker of the chain end in [`DataIndex`]
+const CHAIN_END: u32 = u32::MAX;
+
+/// Index of the data segment allowing to find the first occurrence of a byte sequence without
+/// scanning the whole segment.
+///
+/// For each prefix length up to [`INDEX_PREFIX_MAX`] the index keeps hash chains linking the
+/// positions of the segment substrings with the same hash in increasing order. A sequence is
+/// searched among the positions in the chain of its longest indexed prefix only. Short data
+/// segments, which are faster to scan, are not indexed.
+#[derive(Clone, Debug, Default)]
+struct DataIndex {
+    /// Length of the indexed data
+    len: usize,
+    /// First and last position of each chain, for each prefix length
+    chains: Vec<[u32; 2]>,
+    /// Next position in the chain, for each position and prefix length
+    next: Vec<[u32; INDEX_PREFIX_MAX]>,
+}
+
+impl DataIndex {
+    fn bucket(prefix: &[u8]) -> usize {
+        let hash =
+            prefix.iter().fold(0u32, |hash, byte| hash.wrapping_mul(0x0100_0193) ^ *byte as u32);
+        (prefix.len() - 1) * INDEX_BUCKETS + (hash as usize % INDEX_BUCKETS)
+    }
+
+    /// Indexes the data added since the last update. The data must not change except by
+    /// appending new bytes; otherwise the index is rebuilt.
+    fn update(&mut self, data: &[u8]) {
+        if data.len() < self.len || self.chains.is_empty() {
+            *self = DataIndex {
+                len: 0,
+                chains: vec![[CHAIN_END; 2]; INDEX_PREFIX_MAX * INDEX_BUCKETS],
+                next: Vec::with_capacity(data.len()),
+            };
+        }
+        self.next.resize(data.len(), [CHAIN_END; INDEX_PREFIX_MAX]);
+        for size in 1..=INDEX_PREFIX_MAX {
+            for pos in (self.len + 1).saturating_sub(size)..(data.len() + 1).saturating_sub(size) {
+                let chain = &mut self.chains[DataIndex::bucket(&data[pos..pos + size])];
+                match chain[1] {
+                    CHAIN_END => chain[0] = pos as u32,
+                    last => self.next[last as usize][size - 1] = pos as u32,
+                }
+                chain[1] = pos as u32;
+            }
+        }
+        self.len = data.len();
+    }
+
+    /// Returns the first position of the non-empty byte sequence in the data.
+    fn find(&mut self, data: &[u8], bytes: &[u8]) -> Option<usize> {
+        if data.len() < INDEX_MIN_LEN {
+            return data.windows(bytes.len()).position(|window| window == bytes);
+        }
+        self.update(data);
+        let size = bytes.len().min(INDEX_PREFIX_MAX);
+        let mut pos = self.chains[DataIndex::bucket(&bytes[..size])][0];
+        while pos != CHAIN_END {
+            let start = pos as usize;
+            if data.get(start..start + bytes.len()) == Some(bytes) {
+                return Some(start);
+            }
+            pos = self.next[start][size - 1];
+        }
+        None
+    }
+}
+
+/// Proof that the cursor is positioned at a byte boundary, which can be obtained only when the bit
+/// position is zero and is consumed by the operations moving the cursor by whole bytes. This makes
+/// byte-aligned operations at non-aligned positions impossible to express.
+struct ByteAligned(());
+
 /// Cursor for accessing bytecode bounded by [`CODE_SEGMENT_MAX_LEN`] length and data segment
 /// bounded by [`DATA_SEGMENT_MAX_LEN`]
+///
+/// Cursor position consists of byte position and bit position within that byte. Reads and writes
+/// may start at any bit position; operations which move the cursor by whole bytes require a proof
+/// of the cursor being byte-aligned, and [`Cursor::align_to_byte`] moves it to the next byte
+/// boundary.
 pub struct Cursor<'a, T, D>
 where
     T: AsRef<[u8]>,
@@ -46,6 +140,10 @@ where
     byte_pos: u16,
     data: D,
     libs: &'a LibSeg,
+    padded: bool,
+    dedup: bool,
+    index: DataIndex,
+    tables: BTreeMap<Vec<u8>, u16>,
 }
 
 #[cfg(feature = "std")]
@@ -96,7 +194,17 @@ where
     /// segment
     #[inline]
     pub fn new(bytecode: T, libs: &'a LibSeg) -> Cursor<'a, T, D> {
-        Cursor { bytecode, byte_pos: 0, bit_pos: u3::MIN, data: D::default(), libs }
+        Cursor {
+            bytecode,
+            byte_pos: 0,
+            bit_pos: u3::MIN,
+            data: D::default(),
+            libs,
+            padded: false,
+            dedup: true,
+            index: DataIndex::default(),
+            tables: BTreeMap::new(),
+        }
     }
 }
 
@@ -116,24 +224,67 @@ where
     pub fn with(bytecode: T, data: D, libs: &'a LibSeg) -> Cursor<'a, T, D> {
         assert!(bytecode.as_ref().len() <= CODE_SEGMENT_MAX_LEN);
         assert!(data.as_ref().len() <= DATA_SEGMENT_MAX_LEN);
-        Cursor { bytecode, byte_pos: 0, bit_pos: u3::MIN, data, libs }
+        Cursor {
+            bytecode,
+            byte_pos: 0,
+            bit_pos: u3::MIN,
+            data,
+            libs,
+            padded: false,
+            dedup: true,
+            index: DataIndex::default(),
+            tables: BTreeMap::new(),
+        }
     }
 
     /// Converts writer into data segment
     #[inline]
     pub fn into_data_segment(self) -> D { self.data }
 
+    /// Sets whether the data written by the cursor reuse equal byte sequences already present in
+    /// the data segment (the default) or are always appended to it.
+    ///
+    /// Searching for duplicates takes time proportional to the product of the data segment size
+    /// and the size of the written value, thus it may be disabled when writing large data.
+    #[inline]
+    pub fn set_dedup(&mut self, dedup: bool) { self.dedup = dedup }
+
     #[inline]
     fn as_ref(&self) -> &[u8] { self.bytecode.as_ref() }
 
-    fn read(&mut self, bit_count: u5) -> Result<u32, CodeEofError> {
+    /// Returns length of the code accessible to the cursor, which for the cursors padding the
+    /// code with zero bytes is the maximal length addressable with `u16` offsets.
+    #[inline]
+    fn code_len(&self) -> usize {
+        match self.padded {
+            true => u16::MAX as usize,
+            false => self.as_ref().len(),
+        }
+    }
+
+    #[inline]
+    fn code_byte(&self, pos: u16) -> u8 { self.as_ref().get(pos as usize).copied().unwrap_or(0) }
+
+    /// Returns bit offset of the cursor within the current byte
+    #[inline]
+    pub fn bit_pos(&self) -> u3 { self.bit_pos }
+
+    /// Reads `bit_count` bits (up to 31) starting from the current bit position, without
+    /// requiring the cursor to be byte-aligned. Bits are read starting from the least significant
+    /// bit of each byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CodeEofError`] if the code segment does not contain enough bits; in this case
+    /// the cursor position is undefined.
+    pub fn read_bits(&mut self, bit_count: u5) -> Result<u32, CodeEofError> {
         let mut ret = 0u32;
         let mut cnt = bit_count.to_u8();
         while cnt > 0 {
             if self.is_eof() {
                 return Err(CodeEofError);
             }
-            let byte = self.as_ref()[self.byte_pos as usize];
+            let byte = self.code_byte(self.byte_pos);
             let remaining_bits = 8 - self.bit_pos.to_u8();
             let mask = match remaining_bits < cnt {
                 true => 0xFFu8 << self.bit_pos.to_u8(),
@@ -141,31 +292,54 @@ where
             };
             let value = ((byte & mask) >> self.bit_pos.to_u8()) as u32;
             ret |= value << (bit_count.to_u8() - cnt);
-            match remaining_bits.min(cnt) {
-                8 => {
-                    self.inc_bytes(1)?;
-                }
-                _ => {
-                    self.inc_bits(u3::with(remaining_bits.min(cnt)))?;
-                }
+            match (self.byte_aligned(), remaining_bits.min(cnt)) {
+                (Some(aligned), 8) => self.inc_bytes(aligned, 1)?,
+                (_, bits) => self.inc_bits(u3::with(bits))?,
             }
             cnt = cnt.saturating_sub(remaining_bits);
         }
         Ok(ret)
     }
 
+    /// Skips the remaining bits of the current byte, if the cursor is not already at a byte
+    /// boundary. When writing, skipped bits are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CodeEofError`] if moving to the next byte overflows maximal code segment
+    /// length.
+    pub fn align_to_byte(&mut self) -> Result<(), CodeEofError> {
+        if self.bit_pos == u3::MIN {
+            return Ok(());
+        }
+        self.inc_bits(u3::with(8 - self.bit_pos.to_u8()))
+    }
+
+    /// Checks that `bit_count` bits, starting from the current bit position, fit into the code
+    /// segment, such that writing them will not fail half-way.
+    fn check_code_space(&self, bit_count: u16) -> Result<(), CodeEofError> {
+        let n_bytes = (self.bit_pos.to_u8() as usize + bit_count as usize + 7) / 8;
+        if self.byte_pos as usize + n_bytes > self.code_len() {
+            return Err(CodeEofError);
+        }
+        Ok(())
+    }
+
     fn inc_bits(&mut self, bit_count: u3) -> Result<(), CodeEofError> {
         let pos = self.bit_pos.to_u8() + bit_count.to_u8();
         self.bit_pos = u3::with(pos % 8);
         self._inc_bytes_inner(pos as u16 / 8)
     }
 
-    fn inc_bytes(&mut self, byte_count: u16) -> Result<(), CodeEofError> {
-        assert_eq!(
-            self.bit_pos.to_u8(),
-            0,
-            "attempt to access (multiple) bytes at a non-byte aligned position"
-        );
+    /// Returns proof that the cursor is at a byte boundary, if it is.
+    #[inline]
+    fn byte_aligned(&self) -> Option<ByteAligned> {
+        (self.bit_pos == u3::MIN).then_some(ByteAligned(()))
+    }
+
+    /// Moves cursor by whole bytes, which requires it to be at a byte boundary.
+    #[inline]
+    fn inc_bytes(&mut self, _: ByteAligned, byte_count: u16) -> Result<(), CodeEofError> {
         self._inc_bytes_inner(byte_count)
     }
 
@@ -184,25 +358,38 @@ where
 {
     fn as_mut(&mut self) -> &mut [u8] { self.bytecode.as_mut() }
 
+    /// Writes lowest `bit_count` bits (up to 31) of the `value` starting from the current bit
+    /// position, without requiring the cursor to be byte-aligned. The written bits are combined
+    /// with the existing code segment content using bitwise OR, thus the target area must be
+    /// zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WriteError::ValueExceedsBitCount`] if the value has non-zero bits above
+    /// `bit_count`, and [`WriteError::CodeNotFittingSegment`] if the code segment does not have
+    /// enough space.
+    pub fn write_bits(&mut self, bit_count: u5, value: u32) -> Result<(), WriteError> {
+        if value.checked_shr(bit_count.to_u8() as u32).unwrap_or_default() != 0 {
+            return Err(WriteError::ValueExceedsBitCount { value, bits: bit_count.to_u8() });
+        }
+        self.write(value, bit_count).map_err(WriteError::from)
+    }
+
     fn write(&mut self, value: u32, bit_count: u5) -> Result<(), CodeEofError> {
+        // We check the space before modifying anything, such that a failed write does not leave
+        // the code segment with some of the value bits written
+        self.check_code_space(bit_count.to_u8() as u16)?;
         let mut cnt = bit_count.to_u8();
         let value = ((value as u64) << (self.bit_pos.to_u8())).to_le_bytes();
         let n_bytes = (cnt + self.bit_pos.to_u8() + 7) / 8;
         for i in 0..n_bytes {
-            if self.is_eof() {
-                return Err(CodeEofError);
-            }
             let byte_pos = self.byte_pos as usize;
             let bit_pos = self.bit_pos.to_u8();
             let byte = &mut self.as_mut()[byte_pos];
             *byte |= value[i as usize];
-            match (bit_pos, cnt) {
-                (0, cnt) if cnt >= 8 => {
-                    self.inc_bytes(1)?;
-                }
-                (_, cnt) => {
-                    self.inc_bits(u3::with(cnt.min(8 - bit_pos)))?;
-                }
+            match self.byte_aligned() {
+                Some(aligned) if cnt >= 8 => self.inc_bytes(aligned, 1)?,
+                _ => self.inc_bits(u3::with(cnt.min(8 - bit_pos)))?,
             }
             cnt = cnt.saturating_sub(cnt.min(8 - bit_pos));
         }
@@ -216,17 +403,53 @@ where
     D: AsRef<[u8]> + AsMut<[u8]> + Extend<u8>,
     Self: 'a,
 {
+    /// Writes table of constants into the data segment at the offset aligned to `align` bytes,
+    /// padding the data segment with zero bytes. Alignment of zero is treated as one.
+    ///
+    /// Data equal to the table, written afterwards with [`Write::write_data`], always reference
+    /// the table offset, even if the same bytes are present in the data segment before it. Thus
+    /// tables must have distinct data, such that each instruction referencing table data resolves
+    /// to a single table.
+    ///
+    /// # Returns
+    ///
+    /// Offset of the table in the data segment.
+    ///
+    /// # Errors
+    ///
+    /// If the padded table does not fit into the data segment, or if a table with the same data
+    /// was already written.
+    pub fn write_table(&mut self, bytes: &[u8], align: u16) -> Result<u16, WriteError> {
+        if let Some(offset) = self.tables.get(bytes) {
+            return Err(WriteError::DuplicateTable(*offset));
+        }
+        let len = self.data.as_ref().len();
+        let align = align.max(1) as usize;
+        let padding = (align - len % align) % align;
+        if !SegmentLimits::MAX.fits_data(len + padding + bytes.len()) {
+            return Err(WriteError::DataNotFittingSegment);
+        }
+        let offset = u16::try_from(len + padding).map_err(|_| WriteError::DataNotFittingSegment)?;
+        self.data.extend(core::iter::repeat(0u8).take(padding));
+        self.data.extend(bytes.iter().copied());
+        self.tables.insert(bytes.to_vec(), offset);
+        Ok(offset)
+    }
+
     fn write_unique(&mut self, bytes: &[u8]) -> Result<u16, WriteError> {
         // We write the value only if the value is not yet present in the data segment
         let len = bytes.len();
         let offset = self.data.as_ref().len();
+        let existing = match self.tables.get(bytes) {
+            Some(pos) => Some(*pos as usize),
+            None if self.dedup && len > 0 => self.index.find(self.data.as_ref(), bytes),
+            None => None,
+        };
         if len == 0 {
             Ok(offset as u16)
-        } else if let Some(offset) =
-            self.data.as_ref().windows(len).position(|window| window == bytes)
-        {
+        } else if let Some(offset) = existing {
             Ok(offset as u16)
-        } else if offset + len > DATA_SEGMENT_MAX_LEN {
+        } else if !SegmentLimits::MAX.fits_data(offset + len) {
             Err(WriteError::DataNotFittingSegment)
         } else {
             self.data.extend(bytes.iter().copied());
@@ -242,14 +465,14 @@ where
     Self: 'a,
 {
     #[inline]
-    fn is_eof(&self) -> bool { self.byte_pos as usize >= self.as_ref().len() }
+    fn is_eof(&self) -> bool { self.byte_pos as usize >= self.code_len() }
 
     #[inline]
     fn pos(&self) -> u16 { self.byte_pos }
 
     #[inline]
     fn seek(&mut self, byte_pos: u16) -> Result<u16, CodeEofError> {
-        if byte_pos as usize >= self.as_ref().len() {
+        if byte_pos as usize >= self.code_len() {
             return Err(CodeEofError);
         }
         let old_pos = self.byte_pos;
@@ -261,68 +484,70 @@ where
         if self.is_eof() {
             return Err(CodeEofError);
         }
-        Ok(self.as_ref()[self.byte_pos as usize])
+        Ok(self.code_byte(self.byte_pos))
     }
 
-    fn read_bool(&mut self) -> Result<bool, CodeEofError> { Ok(self.read(u5::with(1))? == 0x01) }
+    fn read_bool(&mut self) -> Result<bool, CodeEofError> {
+        Ok(self.read_bits(u5::with(1))? == 0x01)
+    }
 
     fn read_u1(&mut self) -> Result<u1, CodeEofError> {
-        let res = self.read(u5::with(1))? as u8;
+        let res = self.read_bits(u5::with(1))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u2(&mut self) -> Result<u2, CodeEofError> {
-        let res = self.read(u5::with(2))? as u8;
+        let res = self.read_bits(u5::with(2))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u3(&mut self) -> Result<u3, CodeEofError> {
-        let res = self.read(u5::with(3))? as u8;
+        let res = self.read_bits(u5::with(3))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u4(&mut self) -> Result<u4, CodeEofError> {
-        let res = self.read(u5::with(4))? as u8;
+        let res = self.read_bits(u5::with(4))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u5(&mut self) -> Result<u5, CodeEofError> {
-        let res = self.read(u5::with(5))? as u8;
+        let res = self.read_bits(u5::with(5))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u6(&mut self) -> Result<u6, CodeEofError> {
-        let res = self.read(u5::with(6))? as u8;
+        let res = self.read_bits(u5::with(6))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u7(&mut self) -> Result<u7, CodeEofError> {
-        let res = self.read(u5::with(7))? as u8;
+        let res = self.read_bits(u5::with(7))? as u8;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
     fn read_u8(&mut self) -> Result<u8, CodeEofError> {
-        let res = self.read(u5::with(8))? as u8;
+        let res = self.read_bits(u5::with(8))? as u8;
         Ok(res)
     }
 
     fn read_i8(&mut self) -> Result<i8, CodeEofError> {
-        let res = self.read(u5::with(8))? as i8;
+        let res = self.read_bits(u5::with(8))? as i8;
         Ok(res)
     }
 
     fn read_u16(&mut self) -> Result<u16, CodeEofError> {
-        let res = self.read(u5::with(16))? as u16;
+        let res = self.read_bits(u5::with(16))? as u16;
         Ok(res)
     }
 
     fn read_i16(&mut self) -> Result<i16, CodeEofError> {
-        let res = self.read(u5::with(16))? as i16;
+        let res = self.read_bits(u5::with(16))? as i16;
         Ok(res)
     }
 
     fn read_u24(&mut self) -> Result<u24, CodeEofError> {
-        let res = self.read(u5::with(24))?;
+        let res = self.read_bits(u5::with(24))?;
         Ok(res.try_into().expect("bit extractor failure"))
     }
 
@@ -332,16 +557,39 @@ where
     }
 
     fn read_data(&mut self) -> Result<(&[u8], bool), CodeEofError> {
-        let offset = self.read_u16()? as usize;
-        let end = offset + self.read_u16()? as usize;
-        let max = DATA_SEGMENT_MAX_LEN;
-        let st0 = end > self.data.as_ref().len();
-        let data = &self.data.as_ref()[offset.min(max)..end.min(max)];
-        Ok((data, st0))
+        let range = self.read_data_range()?;
+        Ok(self.data_slice(range))
     }
 
     fn read_number(&mut self, reg: impl NumericRegister) -> Result<Number, CodeEofError> {
+        let offset = self.read_u16()?;
+        self.number_at(offset, reg)
+    }
+}
+
+impl<'a, T, D> Cursor<'a, T, D>
+where
+    T: AsRef<[u8]>,
+    D: AsRef<[u8]>,
+    Self: 'a,
+{
+    /// Reads offset and length of the data referenced by the code
+    fn read_data_range(&mut self) -> Result<Range<usize>, CodeEofError> {
         let offset = self.read_u16()? as usize;
+        let end = offset + self.read_u16()? as usize;
+        Ok(offset..end)
+    }
+
+    /// Returns data segment slice for the `range` clipped by the data segment length, together
+    /// with the flag indicating whether the range exceeds the data segment length.
+    fn data_slice(&self, range: Range<usize>) -> (&[u8], bool) {
+        let data = self.data.as_ref();
+        let max = data.len();
+        (&data[range.start.min(max)..range.end.min(max)], range.end > max)
+    }
+
+    fn number_at(&self, offset: u16, reg: impl NumericRegister) -> Result<Number, CodeEofError> {
+        let offset = offset as usize;
         let end = offset + reg.bytes() as usize;
         if end > self.data.as_ref().len() {
             return Err(CodeEofError);
@@ -354,7 +602,7 @@ where
 impl<'a, T, D> Write for Cursor<'a, T, D>
 where
     T: AsRef<[u8]> + AsMut<[u8]>,
-    D: AsRef<[u8]> + AsMut<[u8]> + Extend<u8>,
+    D: AsRef<[u8]> + AsMut<[u8]> + Extend<u8> + Default,
     Self: 'a,
 {
     fn write_bool(&mut self, data: bool) -> Result<(), WriteError> {
@@ -423,6 +671,8 @@ where
         if len >= u16::MAX as usize {
             return Err(WriteError::DataExceedsLimit(len));
         }
+        // All checks happen before the data segment is extended, so nothing is modified on failure
+        self.check_code_space(DATA_REF_LEN * 8)?;
         let offset = self.write_unique(bytes)?;
         self.write_u16(offset)?;
         self.write_u16(len as u16)
@@ -439,10 +689,42 @@ where
             "value for the register has larger bit length than the register"
         );
         value.reshape(reg.layout().using_sign(value.layout()));
+        self.check_code_space(NUMBER_REF_LEN * 8)?;
         let offset = self.write_unique(&value[..])?;
         self.write_u16(offset)
     }
 
+    fn write_instr<I>(&mut self, instr: &I) -> Result<(), BytecodeError>
+    where
+        I: Bytecode + ?Sized,
+    {
+        // We keep the code bytes the instruction may cover (including the byte with its trailing
+        // bits) together with the writer state, and restore all of them if any of the arguments
+        // fails to be written
+        let (byte_pos, bit_pos) = (self.byte_pos, self.bit_pos);
+        let data_len = self.data.as_ref().len();
+        let start = (byte_pos as usize).min(self.as_ref().len());
+        let end = (start + instr.byte_count() as usize + 1).min(self.as_ref().len());
+        let code = self.as_ref()[start..end].to_vec();
+
+        let res = self
+            .write_u8(instr.instr_byte())
+            .map_err(BytecodeError::from)
+            .and_then(|_| instr.encode_args(self));
+        if res.is_err() {
+            self.as_mut()[start..end].copy_from_slice(&code);
+            self.byte_pos = byte_pos;
+            self.bit_pos = bit_pos;
+            if self.data.as_ref().len() > data_len {
+                // The data segment type can only be extended, so we rebuild it from the data
+                // preceding the instruction. The data index is rebuilt on its next use.
+                let data = core::mem::take(&mut self.data);
+                self.data.extend(data.as_ref()[..data_len].iter().copied());
+            }
+        }
+        res
+    }
+
     fn edit<F, E, S>(&mut self, pos: u16, editor: F) -> Result<(), E>
     where
         F: FnOnce(&mut Instr<S>) -> Result<(), E>,
@@ -459,13 +741,308 @@ where
     }
 }
 
+/// Read-only cursor used for the library code execution and disassembly.
+///
+/// Unlike [`Cursor`], which is also used for assembling the code and thus may write both code
+/// and data segments, the execution cursor borrows both segments immutably and implements only
+/// [`Read`], such that executed instructions can't modify the library. The only mutable state
+/// during the execution are the registers.
+///
+/// ```compile_fail
+/// use aluvm::library::{ExecCursor, Write};
+///
+/// fn patch(cursor: &mut ExecCursor) { cursor.write_u8(0u8).unwrap(); }
+/// ```
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ExecCursor<'a> {
+    cursor: Cursor<'a, &'a [u8], &'a [u8]>,
+    /// Number of data segment reads not yet collected with [`ExecCursor::take_data_reads`]
+    data_reads: u32,
+}
+
+impl<'a> ExecCursor<'a> {
+    /// Creates cursor reading the provided code and data segments.
+    ///
+    /// # Panics
+    ///
+    /// If the length of the code exceeds [`CODE_SEGMENT_MAX_LEN`] or length of the data
+    /// [`DATA_SEGMENT_MAX_LEN`]
+    #[inline]
+    pub fn with(code: &'a [u8], data: &'a [u8], libs: &'a LibSeg) -> ExecCursor<'a> {
+        ExecCursor { cursor: Cursor::with(code, data, libs), data_reads: 0 }
+    }
+
+    /// Creates cursor reading the provided code and data segments, where the code is padded with
+    /// zero bytes up to the maximal code segment length, matching the way the code is seen by
+    /// the executed program.
+    ///
+    /// # Panics
+    ///
+    /// If the length of the code exceeds [`CODE_SEGMENT_MAX_LEN`] or length of the data
+    /// [`DATA_SEGMENT_MAX_LEN`]
+    #[inline]
+    pub(crate) fn padded(code: &'a [u8], data: &'a [u8], libs: &'a LibSeg) -> ExecCursor<'a> {
+        let mut cursor = Cursor::with(code, data, libs);
+        cursor.padded = true;
+        ExecCursor { cursor, data_reads: 0 }
+    }
+
+    /// Returns number of reads from the data segment made since the previous call to this
+    /// method, resetting the counter.
+    #[inline]
+    pub(crate) fn take_data_reads(&mut self) -> u32 { core::mem::take(&mut self.data_reads) }
+}
+
+impl<'a> Read for ExecCursor<'a> {
+    #[inline]
+    fn pos(&self) -> u16 { self.cursor.pos() }
+    #[inline]
+    fn seek(&mut self, byte_pos: u16) -> Result<u16, CodeEofError> { self.cursor.seek(byte_pos) }
+    #[inline]
+    fn is_eof(&self) -> bool { self.cursor.is_eof() }
+    #[inline]
+    fn peek_u8(&self) -> Result<u8, CodeEofError> { self.cursor.peek_u8() }
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, CodeEofError> { self.cursor.read_bool() }
+    #[inline]
+    fn read_u1(&mut self) -> Result<u1, CodeEofError> { self.cursor.read_u1() }
+    #[inline]
+    fn read_u2(&mut self) -> Result<u2, CodeEofError> { self.cursor.read_u2() }
+    #[inline]
+    fn read_u3(&mut self) -> Result<u3, CodeEofError> { self.cursor.read_u3() }
+    #[inline]
+    fn read_u4(&mut self) -> Result<u4, CodeEofError> { self.cursor.read_u4() }
+    #[inline]
+    fn read_u5(&mut self) -> Result<u5, CodeEofError> { self.cursor.read_u5() }
+    #[inline]
+    fn read_u6(&mut self) -> Result<u6, CodeEofError> { self.cursor.read_u6() }
+    #[inline]
+    fn read_u7(&mut self) -> Result<u7, CodeEofError> { self.cursor.read_u7() }
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, CodeEofError> { self.cursor.read_u8() }
+    #[inline]
+    fn read_i8(&mut self) -> Result<i8, CodeEofError> { self.cursor.read_i8() }
+    #[inline]
+    fn read_u16(&mut self) -> Result<u16, CodeEofError> { self.cursor.read_u16() }
+    #[inline]
+    fn read_i16(&mut self) -> Result<i16, CodeEofError> { self.cursor.read_i16() }
+    #[inline]
+    fn read_u24(&mut self) -> Result<u24, CodeEofError> { self.cursor.read_u24() }
+    #[inline]
+    fn read_lib(&mut self) -> Result<LibId, CodeEofError> { self.cursor.read_lib() }
+    #[inline]
+    fn read_data(&mut self) -> Result<(&[u8], bool), CodeEofError> {
+        self.data_reads = self.data_reads.saturating_add(1);
+        self.cursor.read_data()
+    }
+    #[inline]
+    fn read_number(&mut self, reg: impl NumericRegister) -> Result<Number, CodeEofError> {
+        self.data_reads = self.data_reads.saturating_add(1);
+        self.cursor.read_number(reg)
+    }
+}
+
+/// Cursor decoding library code, which records ranges of the data segment referenced by the
+/// decoded instructions. Used for building [`DataMap`](super::DataMap).
+pub(crate) struct XrefCursor<'a> {
+    inner: ExecCursor<'a>,
+    refs: Vec<Range<usize>>,
+}
+
+impl<'a> XrefCursor<'a> {
+    #[inline]
+    pub(crate) fn with(code: &'a [u8], data: &'a [u8], libs: &'a LibSeg) -> XrefCursor<'a> {
+        XrefCursor { inner: ExecCursor::with(code, data, libs), refs: empty!() }
+    }
+
+    /// Returns data segment ranges referenced since the previous call to this method
+    #[inline]
+    pub(crate) fn take_refs(&mut self) -> Vec<Range<usize>> { core::mem::take(&mut self.refs) }
+}
+
+impl<'a> Read for XrefCursor<'a> {
+    #[inline]
+    fn pos(&self) -> u16 { self.inner.pos() }
+    #[inline]
+    fn seek(&mut self, byte_pos: u16) -> Result<u16, CodeEofError> { self.inner.seek(byte_pos) }
+    #[inline]
+    fn is_eof(&self) -> bool { self.inner.is_eof() }
+    #[inline]
+    fn peek_u8(&self) -> Result<u8, CodeEofError> { self.inner.peek_u8() }
+    #[inline]
+    fn read_bool(&mut self) -> Result<bool, CodeEofError> { self.inner.read_bool() }
+    #[inline]
+    fn read_u1(&mut self) -> Result<u1, CodeEofError> { self.inner.read_u1() }
+    #[inline]
+    fn read_u2(&mut self) -> Result<u2, CodeEofError> { self.inner.read_u2() }
+    #[inline]
+    fn read_u3(&mut self) -> Result<u3, CodeEofError> { self.inner.read_u3() }
+    #[inline]
+    fn read_u4(&mut self) -> Result<u4, CodeEofError> { self.inner.read_u4() }
+    #[inline]
+    fn read_u5(&mut self) -> Result<u5, CodeEofError> { self.inner.read_u5() }
+    #[inline]
+    fn read_u6(&mut self) -> Result<u6, CodeEofError> { self.inner.read_u6() }
+    #[inline]
+    fn read_u7(&mut self) -> Result<u7, CodeEofError> { self.inner.read_u7() }
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, CodeEofError> { self.inner.read_u8() }
+    #[inline]
+    fn read_i8(&mut self) -> Result<i8, CodeEofError> { self.inner.read_i8() }
+    #[inline]
+    fn read_u16(&mut self) -> Result<u16, CodeEofError> { self.inner.read_u16() }
+    #[inline]
+    fn read_i16(&mut self) -> Result<i16, CodeEofError> { self.inner.read_i16() }
+    #[inline]
+    fn read_u24(&mut self) -> Result<u24, CodeEofError> { self.inner.read_u24() }
+    #[inline]
+    fn read_lib(&mut self) -> Result<LibId, CodeEofError> { self.inner.read_lib() }
+
+    fn read_data(&mut self) -> Result<(&[u8], bool), CodeEofError> {
+        let range = self.inner.cursor.read_data_range()?;
+        self.refs.push(range.clone());
+        Ok(self.inner.cursor.data_slice(range))
+    }
+
+    fn read_number(&mut self, reg: impl NumericRegister) -> Result<Number, CodeEofError> {
+        let offset = self.inner.cursor.read_u16()?;
+        self.refs.push(offset as usize..offset as usize + reg.bytes() as usize);
+        self.inner.cursor.number_at(offset, reg)
+    }
+}
+
+/// Writer measuring code and data segment sizes of the encoded instructions without producing
+/// the code segment. Unlike [`Cursor`], the sizes are not limited by the segment limits.
+pub(crate) struct SizeCounter {
+    bits: usize,
+    data: Vec<u8>,
+    index: DataIndex,
+}
+
+impl SizeCounter {
+    pub(crate) fn new() -> SizeCounter {
+        SizeCounter { bits: 0, data: Vec::new(), index: DataIndex::default() }
+    }
+
+    /// Size of the code written so far, in bytes
+    pub(crate) fn code_len(&self) -> usize { (self.bits + 7) / 8 }
+
+    /// Size of the data segment written so far, in bytes
+    pub(crate) fn data_len(&self) -> usize { self.data.len() }
+
+    fn count(&mut self, bits: usize) -> Result<(), WriteError> {
+        self.bits += bits;
+        Ok(())
+    }
+
+    fn write_unique(&mut self, bytes: &[u8]) {
+        if !bytes.is_empty() && self.index.find(&self.data, bytes).is_none() {
+            self.data.extend_from_slice(bytes);
+        }
+    }
+}
+
+impl Write for SizeCounter {
+    fn write_bool(&mut self, _: bool) -> Result<(), WriteError> { self.count(1) }
+    fn write_u1(&mut self, _: impl Into<u1>) -> Result<(), WriteError> { self.count(1) }
+    fn write_u2(&mut self, _: impl Into<u2>) -> Result<(), WriteError> { self.count(2) }
+    fn write_u3(&mut self, _: impl Into<u3>) -> Result<(), WriteError> { self.count(3) }
+    fn write_u4(&mut self, _: impl Into<u4>) -> Result<(), WriteError> { self.count(4) }
+    fn write_u5(&mut self, _: impl Into<u5>) -> Result<(), WriteError> { self.count(5) }
+    fn write_u6(&mut self, _: impl Into<u6>) -> Result<(), WriteError> { self.count(6) }
+    fn write_u7(&mut self, _: impl Into<u7>) -> Result<(), WriteError> { self.count(7) }
+    fn write_u8(&mut self, _: impl Into<u8>) -> Result<(), WriteError> { self.count(8) }
+    fn write_i8(&mut self, _: impl Into<i8>) -> Result<(), WriteError> { self.count(8) }
+    fn write_u16(&mut self, _: impl Into<u16>) -> Result<(), WriteError> { self.count(16) }
+    fn write_i16(&mut self, _: impl Into<i16>) -> Result<(), WriteError> { self.count(16) }
+    fn write_u24(&mut self, _: impl Into<u24>) -> Result<(), WriteError> { self.count(24) }
+    fn write_lib(&mut self, _: LibId) -> Result<(), WriteError> {
+        self.count(LIB_REF_LEN as usize * 8)
+    }
+
+    fn write_data(&mut self, bytes: impl AsRef<[u8]>) -> Result<(), WriteError> {
+        let bytes = bytes.as_ref();
+        if bytes.len() >= u16::MAX as usize {
+            return Err(WriteError::DataExceedsLimit(bytes.len()));
+        }
+        self.write_unique(bytes);
+        self.count(DATA_REF_LEN as usize * 8)
+    }
+
+    fn write_number(
+        &mut self,
+        reg: impl NumericRegister,
+        mut value: Number,
+    ) -> Result<(), WriteError> {
+        value.reshape(reg.layout().using_sign(value.layout()));
+        self.write_unique(&value[..]);
+        self.count(NUMBER_REF_LEN as usize * 8)
+    }
+
+    fn write_instr<I>(&mut self, instr: &I) -> Result<(), BytecodeError>
+    where
+        I: Bytecode + ?Sized,
+    {
+        let (bits, data_len) = (self.bits, self.data.len());
+        let res = self
+            .write_u8(instr.instr_byte())
+            .map_err(BytecodeError::from)
+            .and_then(|_| instr.encode_args(self));
+        if res.is_err() {
+            self.bits = bits;
+            self.data.truncate(data_len);
+        }
+        res
+    }
+
+    fn edit<F, E, S>(&mut self, _: u16, _: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Instr<S>) -> Result<(), E>,
+        E: From<CodeEofError>,
+        S: InstructionSet,
+    {
+        Err(CodeEofError.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use core::ops::RangeInclusive;
+
     use amplify::num::{u2, u3, u5, u7};
 
-    use super::Cursor;
-    use crate::data::ByteStr;
-    use crate::library::{LibSeg, Read, Write};
+    use super::{Cursor, DataIndex, ExecCursor, INDEX_MIN_LEN};
+    use crate::data::{ByteStr, Number};
+    use crate::isa::{Bytecode, BytecodeError, ControlFlowOp, Instr};
+    use crate::library::{CodeEofError, LibId, LibSeg, LibSite, Read, Write, WriteError};
+    use crate::reg::RegA;
+
+    /// Instruction writing its data before referencing a library absent from the library segment
+    struct DataThenLib;
+
+    impl Bytecode for DataThenLib {
+        fn byte_count(&self) -> u16 { 6 }
+
+        fn instr_range() -> RangeInclusive<u8> { 0xFF..=0xFF }
+
+        fn instr_byte(&self) -> u8 { 0xFF }
+
+        fn encode_args<W>(&self, writer: &mut W) -> Result<(), BytecodeError>
+        where
+            W: Write,
+        {
+            writer.write_data(b"xyz")?;
+            writer.write_lib(LibId::from([0xA5; 32]))?;
+            Ok(())
+        }
+
+        fn decode<R>(_: &mut R) -> Result<Self, CodeEofError>
+        where
+            R: Read,
+        {
+            Err(CodeEofError)
+        }
+    }
 
     #[test]
     fn read() {
@@ -487,7 +1064,7 @@ mod tests {
 
         let bytes = 0b11101011_11110000_01110111;
         let mut cursor = Cursor::<_, ByteStr>::new(u32::to_le_bytes(bytes), &libseg);
-        assert_eq!(cursor.read(u5::with(24)).unwrap(), bytes);
+        assert_eq!(cursor.read_bits(u5::with(24)).unwrap(), bytes);
     }
 
     #[test]
@@ -518,11 +1095,129 @@ mod tests {
         assert_eq!(cursor.read_u3().unwrap().to_u8(), 0b00000101);
         assert_eq!(cursor.read_u7().unwrap().to_u8(), 0b01011111);
         assert_eq!(cursor.read_u8().unwrap(), 0b11100111);
-        assert_eq!(cursor.read_bool().unwrap(), true);
+        assert!(cursor.read_bool().unwrap());
         assert_eq!(cursor.read_u3().unwrap().to_u8(), 0b00000110);
         assert_eq!(cursor.read_u16().unwrap(), two_bytes);
     }
 
+    #[test]
+    fn write_eof_atomic() {
+        let libseg = LibSeg::default();
+        let mut code = [0u8; 3];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.write_u2(u2::with(0b00000011)).unwrap();
+        cursor.write_u8(0xFFu8).unwrap();
+        assert!(cursor.write_u16(0xFFFFu16).is_err());
+        assert_eq!((cursor.pos(), cursor.write_u3(u3::with(0b00000111))), (1, Ok(())));
+        assert!(cursor.write_data(b"abcd").is_err());
+        assert!(cursor.write_number(RegA::A8, Number::from(1u8)).is_err());
+        assert_eq!(cursor.into_data_segment().len(), 0);
+        assert_eq!(code, [0xFF, 0b00011111, 0]);
+    }
+
+    #[test]
+    fn write_dedup() {
+        let libseg = LibSeg::default();
+        let mut code = [0u8; 16];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.write_data(b"abcd").unwrap();
+        cursor.write_data(b"bc").unwrap();
+        assert_eq!(cursor.into_data_segment().as_ref(), b"abcd");
+
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.set_dedup(false);
+        cursor.write_data(b"abcd").unwrap();
+        cursor.write_data(b"bc").unwrap();
+        assert_eq!(cursor.into_data_segment().as_ref(), b"abcdbc");
+    }
+
+    #[test]
+    fn reg_set() {
+        use alloc::collections::BTreeSet;
+
+        use crate::reg::Reg32;
+
+        let libseg = LibSeg::default();
+        let mut code = [0u8; 16];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        let regs = BTreeSet::from([Reg32::Reg0, Reg32::Reg3, Reg32::Reg16, Reg32::Reg31]);
+        cursor.write_bool(true).unwrap();
+        cursor.write_reg_set(regs.iter().copied()).unwrap();
+        cursor.write_reg_set([]).unwrap();
+
+        let data = cursor.into_data_segment();
+        let mut cursor = Cursor::with(&code, data, &libseg);
+        assert!(cursor.read_bool().unwrap());
+        assert_eq!(cursor.read_reg_set().unwrap(), regs);
+        assert_eq!(cursor.read_reg_set().unwrap(), BTreeSet::new());
+        assert_eq!(code[..5], [0x13, 0, 0x02, 0, 0x01]);
+    }
+
+    #[test]
+    fn write_table() {
+        let libseg = LibSeg::default();
+        let mut code = [0u8; 16];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        assert_eq!(cursor.write_table(b"abc", 0), Ok(0));
+        assert_eq!(cursor.write_table(b"bc", 8), Ok(8));
+        assert_eq!(cursor.write_table(b"bc", 2), Err(WriteError::DuplicateTable(8)));
+        cursor.write_data(b"bc").unwrap();
+        cursor.set_dedup(false);
+        cursor.write_data(b"bc").unwrap();
+        assert_eq!(cursor.into_data_segment().as_ref(), b"abc\0\0\0\0\0bc");
+        assert_eq!(code[..8], [8, 0, 2, 0, 8, 0, 2, 0]);
+
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.write_table(&[1u8; u16::MAX as usize], 0).unwrap();
+        assert_eq!(cursor.write_table(b"a", 2), Err(WriteError::DataNotFittingSegment));
+        assert_eq!(cursor.write_table(b"a", 0), Err(WriteError::DataNotFittingSegment));
+        assert_eq!(cursor.write_data([2u8]), Err(WriteError::DataNotFittingSegment));
+    }
+
+    #[test]
+    fn write_instr_atomic() {
+        let libseg = LibSeg::default();
+        let lib = LibId::from([0xA5; 32]);
+        let mut code = [0u8; 16];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.write_bool(true).unwrap();
+        cursor.write_data(b"ab").unwrap();
+        let call = Instr::<ControlFlowOp>::ControlFlow(ControlFlowOp::Call(LibSite::with(7, lib)));
+        assert_eq!(call.encode(&mut cursor), Err(WriteError::LibAbsent(lib).into()));
+        assert_eq!(DataThenLib.encode(&mut cursor), Err(WriteError::LibAbsent(lib).into()));
+        assert_eq!((cursor.pos(), cursor.bit_pos().to_u8()), (4, 1));
+        cursor.write_data(b"b").unwrap();
+        assert_eq!(cursor.into_data_segment().as_ref(), b"ab");
+        assert_eq!(code[..8], [0x01, 0x00, 0x04, 0x00, 0x02, 0x00, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn data_index() {
+        let mut data = Vec::new();
+        let mut index = DataIndex::default();
+        let mut seed = 0x2545_F491u32;
+        let first = |data: &[u8], bytes: &[u8]| {
+            data.windows(bytes.len()).position(|window| window == bytes)
+        };
+        while data.len() < INDEX_MIN_LEN * 2 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            data.push((seed >> 16) as u8 % 4);
+            if data.len() % 97 != 0 {
+                continue;
+            }
+            for len in 1..=12 {
+                for start in (0..data.len().saturating_sub(len)).step_by(31) {
+                    let bytes = &data[start..start + len];
+                    assert_eq!(index.find(&data, bytes), first(&data, bytes));
+                }
+                assert_eq!(index.find(&data, &[4u8; 12][..len]), None);
+            }
+        }
+        data.truncate(INDEX_MIN_LEN + 10);
+        let bytes = data[INDEX_MIN_LEN..].to_vec();
+        assert_eq!(index.find(&data, &bytes), first(&data, &bytes));
+    }
+
     #[test]
     fn write_eof() {
         let libseg = LibSeg::default();
@@ -533,4 +1228,50 @@ mod tests {
         cursor.write_u7(u7::with(0b01011111)).unwrap();
         assert!(cursor.write_u8(0b11100111).is_err());
     }
+
+    #[test]
+    fn bits() {
+        let libseg = LibSeg::default();
+        let mut code = [0u8; 8];
+        let mut cursor = Cursor::<_, ByteStr>::new(&mut code, &libseg);
+        cursor.write_bits(u5::with(3), 0b101).unwrap();
+        assert_eq!(cursor.bit_pos().to_u8(), 3);
+        cursor.write_bits(u5::with(17), 0x1_5A5A).unwrap();
+        assert_eq!(
+            cursor.write_bits(u5::with(4), 0x1F),
+            Err(WriteError::ValueExceedsBitCount { value: 0x1F, bits: 4 })
+        );
+        cursor.align_to_byte().unwrap();
+        assert_eq!((cursor.pos(), cursor.bit_pos().to_u8()), (3, 0));
+        cursor.align_to_byte().unwrap();
+        assert_eq!(cursor.pos(), 3);
+        cursor.write_bits(u5::with(31), 0x7FFF_0001).unwrap();
+        cursor.align_to_byte().unwrap();
+        cursor.write_u8(0xAB).unwrap();
+
+        let mut cursor = Cursor::<_, ByteStr>::new(code, &libseg);
+        assert_eq!(cursor.read_bits(u5::with(3)).unwrap(), 0b101);
+        assert_eq!(cursor.read_bits(u5::with(17)).unwrap(), 0x1_5A5A);
+        assert_eq!(cursor.read_bits(u5::with(4)).unwrap(), 0);
+        cursor.align_to_byte().unwrap();
+        assert_eq!(cursor.read_bits(u5::with(31)).unwrap(), 0x7FFF_0001);
+        assert_eq!(cursor.read_bits(u5::with(1)).unwrap(), 0);
+        cursor.align_to_byte().unwrap();
+        assert_eq!(cursor.read_u8().unwrap(), 0xAB);
+        assert!(cursor.read_bits(u5::with(1)).is_err());
+    }
+
+    #[test]
+    fn exec_cursor() {
+        let libseg = LibSeg::default();
+        let code = [0x5A, 0x01, 0x00, 0x02, 0x00];
+        let data = [0xAA, 0xBB, 0xCC];
+        let mut cursor = ExecCursor::with(&code, &data, &libseg);
+        assert_eq!(cursor.peek_u8(), Ok(0x5A));
+        assert_eq!(cursor.read_u8(), Ok(0x5A));
+        assert_eq!(cursor.read_data(), Ok((&data[1..], false)));
+        assert!(cursor.is_eof());
+        assert_eq!(cursor.seek(1), Ok(5));
+        assert_eq!(cursor.read_u16(), Ok(1));
+    }
 }
@@ -0,0 +1,186 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Debug information of the libraries assembled from the assembly source.
+//!
+//! [`DebugInfo`] maps code offsets to the source lines and label names and registers to the names
+//! of the variables they keep. It is produced by
+//! [`Lib::assemble_instrumented`](super::Lib::assemble_instrumented) and kept in
+//! [`Lib::debug`](super::Lib::debug), which is serialized together with the library, but is not
+//! committed to by the [`LibId`](super::LibId). The disassembler uses it to annotate the listing
+//! with the labels, source lines and variable names.
+//!
+//! Compilers of higher-level languages targeting AluVM may attach [`SourceSpan`]s of their own
+//! sources to the instructions with [`LibBuilder`](super::LibBuilder). The spans are kept in the
+//! [`SourceMap`] of the debug information and are reported by [`crate::export::Trace`] for each
+//! of the executed instructions.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter};
+
+use crate::reg::Reg;
+
+/// Debug information of a library
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct DebugInfo {
+    /// Source line numbers, starting from 1, of the instructions keyed by their code offsets
+    pub lines: BTreeMap<u16, u32>,
+
+    /// Names of the labels keyed by the code offsets they mark
+    pub labels: BTreeMap<u16, String>,
+
+    /// Names of the variables kept in the registers
+    pub vars: BTreeMap<Reg, String>,
+
+    /// Spans of the user-provided sources of the instructions
+    pub spans: SourceMap,
+}
+
+impl DebugInfo {
+    /// Constructs empty debug information
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns source line of the instruction at the code offset `pos`, if known
+    #[inline]
+    pub fn line_at(&self, pos: u16) -> Option<u32> { self.lines.get(&pos).copied() }
+
+    /// Returns name of the label marking the code offset `pos`, if any
+    #[inline]
+    pub fn label_at(&self, pos: u16) -> Option<&str> { self.labels.get(&pos).map(String::as_str) }
+
+    /// Returns name of the variable kept in the register, if any
+    #[inline]
+    pub fn var(&self, reg: Reg) -> Option<&str> { self.vars.get(&reg).map(String::as_str) }
+
+    /// Moves the code offsets of the lines and labels with `lines` and `labels` functions,
+    /// removing the entries for which the functions return `None`.
+    pub(crate) fn relocate(
+        &mut self,
+        lines: impl Fn(u16) -> Option<u16>,
+        labels: impl Fn(u16) -> Option<u16>,
+    ) {
+        self.lines =
+            self.lines.iter().filter_map(|(pos, line)| Some((lines(*pos)?, *line))).collect();
+        self.spans.0 =
+            self.spans.iter().filter_map(|(pos, span)| Some((lines(pos)?, span.clone()))).collect();
+        self.labels = self
+            .labels
+            .iter()
+            .filter_map(|(pos, name)| Some((labels(*pos)?, name.clone())))
+            .collect();
+    }
+}
+
+/// Location of the source code compiled into an instruction
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct SourceSpan {
+    /// Name of the source file
+    pub file: String,
+
+    /// Line number, starting from 1
+    pub line: u32,
+
+    /// Column number, starting from 1
+    pub column: u32,
+
+    /// Length of the span, in characters
+    pub len: u32,
+}
+
+impl SourceSpan {
+    /// Constructs span of `len` characters starting at the given line and column of the file
+    pub fn with(file: impl Into<String>, line: u32, column: u32, len: u32) -> Self {
+        SourceSpan { file: file.into(), line, column, len }
+    }
+}
+
+impl Display for SourceSpan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Map from the code offsets of the instructions to the spans of their sources
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SourceMap(BTreeMap<u16, SourceSpan>);
+
+impl SourceMap {
+    /// Constructs empty source map
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Sets source span of the instruction at the code offset `pos`, returning the previous span
+    #[inline]
+    pub fn insert(&mut self, pos: u16, span: SourceSpan) -> Option<SourceSpan> {
+        self.0.insert(pos, span)
+    }
+
+    /// Returns source span of the instruction at the code offset `pos`, if known
+    #[inline]
+    pub fn span_at(&self, pos: u16) -> Option<&SourceSpan> { self.0.get(&pos) }
+
+    /// Returns iterator over the code offsets and the spans of the instructions
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &SourceSpan)> {
+        self.0.iter().map(|(pos, span)| (*pos, span))
+    }
+
+    /// Returns number of the instructions with known source spans
+    #[inline]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Detects whether source spans are not known for any of the instructions
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+#[cfg(feature = "serde")]
+mod _serde {
+    use serde_crate::de::Error;
+    use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::DebugInfo;
+    use crate::data::encoding::{Decode, Encode};
+
+    impl Serialize for DebugInfo {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Encode::serialize(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for DebugInfo {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let data = Vec::<u8>::deserialize(deserializer)?;
+            <DebugInfo as Decode>::deserialize(data).map_err(D::Error::custom)
+        }
+    }
+}
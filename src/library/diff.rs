@@ -0,0 +1,459 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differences between two versions of a library, for reviewing changes to the programs.
+//!
+//! [`diff`] aligns disassembled instructions of two libraries, reporting the inserted, deleted
+//! and modified instructions with their offsets in the respective code segments, as well as the
+//! changed range of the data segment. Instructions are compared in their assembly form, thus
+//! instructions jumping to the offsets shifted by a change are reported as modified.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::iter;
+use core::ops::{Index, IndexMut};
+
+use amplify::hex::ToHex;
+
+use crate::isa::InstructionSet;
+use crate::library::{DisassembleError, Lib};
+
+/// Change of a single instruction
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum InstrChange {
+    /// Instruction present only in the new library
+    Inserted {
+        /// Offset of the instruction in the new code segment
+        pos: u16,
+        /// Instruction in assembly format
+        instr: String,
+    },
+
+    /// Instruction present only in the old library
+    Deleted {
+        /// Offset of the instruction in the old code segment
+        pos: u16,
+        /// Instruction in assembly format
+        instr: String,
+    },
+
+    /// Instruction replaced with a different one
+    Modified {
+        /// Offset of the instruction in the old code segment
+        old_pos: u16,
+        /// Offset of the instruction in the new code segment
+        new_pos: u16,
+        /// Old instruction in assembly format
+        old: String,
+        /// New instruction in assembly format
+        new: String,
+    },
+}
+
+impl Display for InstrChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InstrChange::Inserted { pos, instr } => write!(f, "+        {:#06X}: {}", pos, instr),
+            InstrChange::Deleted { pos, instr } => write!(f, "- {:#06X}        : {}", pos, instr),
+            InstrChange::Modified { old_pos, new_pos, old, new } => {
+                writeln!(f, "- {:#06X} {:#06X}: {}", old_pos, new_pos, old)?;
+                write!(f, "+ {:#06X} {:#06X}: {}", old_pos, new_pos, new)
+            }
+        }
+    }
+}
+
+/// Changed range of the data segment, which is the longest range between the common prefix and
+/// suffix of the old and new data segments
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct DataChange {
+    /// Offset of the changed range, which is the same in the old and new data segments
+    pub pos: u16,
+
+    /// Old bytes in the range
+    pub old: Vec<u8>,
+
+    /// New bytes in the range
+    pub new: Vec<u8>,
+}
+
+impl Display for DataChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "- {:#06X}: {}", self.pos, self.old.to_hex())?;
+        write!(f, "+ {:#06X}: {}", self.pos, self.new.to_hex())
+    }
+}
+
+/// Differences between two libraries, produced by [`diff`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct LibDiff {
+    /// Changed instructions, ordered by their offsets
+    pub code: Vec<InstrChange>,
+
+    /// Changed range of the data segment, if the data segments differ
+    pub data: Option<DataChange>,
+}
+
+impl LibDiff {
+    /// Detects whether the libraries have the same instructions and data segments
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.code.is_empty() && self.data.is_none() }
+}
+
+impl Display for LibDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.code.is_empty() {
+            writeln!(f, "@@ code @@")?;
+            for change in &self.code {
+                writeln!(f, "{}", change)?;
+            }
+        }
+        if let Some(data) = &self.data {
+            writeln!(f, "@@ data @@")?;
+            writeln!(f, "{}", data)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes instruction-level differences between the code of the `old` and `new` libraries,
+/// decoded with the instruction set `Isa`, and the changed range of their data segments.
+///
+/// The instructions are aligned with the shortest edit script found by the linear space variant
+/// of the Myers algorithm, which requires `O((N + M) D)` time for `N` and `M` instructions in the
+/// libraries and `D` changed instructions. Adjacent deletions and insertions are reported as
+/// modifications.
+///
+/// # Errors
+///
+/// If the code of any of the libraries can't be decoded with the instruction set `Isa`.
+pub fn diff<Isa>(old: &Lib, new: &Lib) -> Result<LibDiff, DisassembleError>
+where
+    Isa: InstructionSet,
+{
+    let disassemble = |lib: &Lib| -> Result<Vec<(u16, String)>, DisassembleError> {
+        lib.disassemble_iter::<Isa>()
+            .map(|res| res.map(|(pos, instr)| (pos, instr.to_string())))
+            .collect()
+    };
+    let old_code = disassemble(old)?;
+    let new_code = disassemble(new)?;
+
+    let old_instrs = old_code.iter().map(|(_, instr)| instr.as_str()).collect::<Vec<_>>();
+    let new_instrs = new_code.iter().map(|(_, instr)| instr.as_str()).collect::<Vec<_>>();
+
+    let mut code = Vec::new();
+    let (mut deleted, mut inserted) = (Vec::new(), Vec::new());
+    let mut flush = |deleted: &mut Vec<&(u16, String)>, inserted: &mut Vec<&(u16, String)>| {
+        let modified = deleted.len().min(inserted.len());
+        for (old, new) in deleted.iter().zip(inserted.iter()) {
+            code.push(InstrChange::Modified {
+                old_pos: old.0,
+                new_pos: new.0,
+                old: old.1.clone(),
+                new: new.1.clone(),
+            });
+        }
+        for (pos, instr) in deleted.drain(..).skip(modified) {
+            code.push(InstrChange::Deleted { pos: *pos, instr: instr.clone() });
+        }
+        for (pos, instr) in inserted.drain(..).skip(modified) {
+            code.push(InstrChange::Inserted { pos: *pos, instr: instr.clone() });
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    for edit in edit_script(&old_instrs, &new_instrs) {
+        match edit {
+            Edit::Keep => {
+                flush(&mut deleted, &mut inserted);
+                i += 1;
+                j += 1;
+            }
+            Edit::Delete => {
+                deleted.push(&old_code[i]);
+                i += 1;
+            }
+            Edit::Insert => {
+                inserted.push(&new_code[j]);
+                j += 1;
+            }
+        }
+    }
+    flush(&mut deleted, &mut inserted);
+
+    Ok(LibDiff { code, data: diff_data(old.data_segment(), new.data_segment()) })
+}
+
+/// Step of the edit script transforming one sequence into another
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Furthest reaching positions in the first sequence, indexed by diagonals `k = x - y`, which may
+/// be negative
+struct Frontier {
+    offset: isize,
+    pos: Vec<isize>,
+}
+
+impl Frontier {
+    fn new(max: usize) -> Frontier {
+        Frontier { offset: max as isize + 1, pos: vec![0; 2 * max + 3] }
+    }
+}
+
+impl Index<isize> for Frontier {
+    type Output = isize;
+
+    #[inline]
+    fn index(&self, k: isize) -> &isize { &self.pos[(k + self.offset) as usize] }
+}
+
+impl IndexMut<isize> for Frontier {
+    #[inline]
+    fn index_mut(&mut self, k: isize) -> &mut isize { &mut self.pos[(k + self.offset) as usize] }
+}
+
+/// Computes the shortest edit script transforming `a` into `b` with the linear space variant of
+/// the Myers algorithm
+fn edit_script<T: Eq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let max = (a.len() + b.len() + 1) / 2;
+    let (mut fwd, mut bwd) = (Frontier::new(max), Frontier::new(max));
+    let mut script = Vec::with_capacity(a.len() + b.len());
+    conquer(a, b, &mut fwd, &mut bwd, &mut script);
+    script
+}
+
+fn common_prefix<T: Eq>(a: &[T], b: &[T]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn common_suffix<T: Eq>(a: &[T], b: &[T]) -> usize {
+    a.iter().rev().zip(b.iter().rev()).take_while(|(a, b)| a == b).count()
+}
+
+/// Splits the sequences at the middle snake of their shortest edit script and recursively
+/// computes scripts for the parts
+fn conquer<T: Eq>(
+    a: &[T],
+    b: &[T],
+    fwd: &mut Frontier,
+    bwd: &mut Frontier,
+    script: &mut Vec<Edit>,
+) {
+    let prefix = common_prefix(a, b);
+    script.extend(iter::repeat(Edit::Keep).take(prefix));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = common_suffix(a, b);
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    if a.is_empty() || b.is_empty() {
+        script.extend(iter::repeat(Edit::Delete).take(a.len()));
+        script.extend(iter::repeat(Edit::Insert).take(b.len()));
+    } else {
+        let (x, y) = middle_snake(a, b, fwd, bwd);
+        conquer(&a[..x], &b[..y], fwd, bwd, script);
+        conquer(&a[x..], &b[y..], fwd, bwd, script);
+    }
+    script.extend(iter::repeat(Edit::Keep).take(suffix));
+}
+
+/// Finds the middle snake of the shortest edit script for non-empty sequences without common
+/// prefix and suffix, returning the position splitting the script into two parts, each being
+/// strictly shorter than both sequences together
+fn middle_snake<T: Eq>(a: &[T], b: &[T], fwd: &mut Frontier, bwd: &mut Frontier) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta & 1 == 1;
+    fwd[1] = 0;
+    bwd[1] = 0;
+    for d in 0..=(n + m + 1) / 2 {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && fwd[k - 1] < fwd[k + 1]) {
+                fwd[k + 1]
+            } else {
+                fwd[k - 1] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            if x < n && y0 < m {
+                x += common_prefix(&a[x as usize..], &b[y0 as usize..]) as isize;
+            }
+            fwd[k] = x;
+            if odd && (k - delta).abs() < d && fwd[k] + bwd[delta - k] >= n {
+                return (x0 as usize, y0 as usize);
+            }
+        }
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && bwd[k - 1] < bwd[k + 1]) {
+                bwd[k + 1]
+            } else {
+                bwd[k - 1] + 1
+            };
+            let mut y = x - k;
+            if x < n && y < m {
+                let len = common_suffix(&a[..(n - x) as usize], &b[..(m - y) as usize]) as isize;
+                x += len;
+                y += len;
+            }
+            bwd[k] = x;
+            if !odd && (k - delta).abs() <= d && bwd[k] + fwd[delta - k] >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    // The paths always meet before `d` exceeds half of the maximal script length
+    (a.len(), 0)
+}
+
+fn diff_data(old: &[u8], new: &[u8]) -> Option<DataChange> {
+    if old == new {
+        return None;
+    }
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(DataChange {
+        // Data segment length is limited by u16
+        pos: prefix as u16,
+        old: old[prefix..old.len() - suffix].to_vec(),
+        new: new[prefix..new.len() - suffix].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::Instr;
+
+    #[test]
+    fn lib_diff() {
+        let old = Lib::assemble_source::<Instr>(
+            "
+            put     a8[0],1
+            put     s16[0],\"old\"
+            add.uc  a8[0],a8[1]
+            nop
+            ret
+        ",
+        )
+        .unwrap();
+        let new = Lib::assemble_source::<Instr>(
+            "
+            put     a8[0],1
+            put     s16[0],\"new\"
+            sub.uc  a8[0],a8[1]
+            ret
+            succ
+        ",
+        )
+        .unwrap();
+
+        assert!(diff::<Instr>(&old, &old).unwrap().is_empty());
+        let changes = diff::<Instr>(&old, &new).unwrap();
+        assert_eq!(changes.code, [
+            InstrChange::Modified {
+                old_pos: 0x04,
+                new_pos: 0x04,
+                old: s!("put     s16[0],\"old\""),
+                new: s!("put     s16[0],\"new\"")
+            },
+            InstrChange::Modified {
+                old_pos: 0x0A,
+                new_pos: 0x0A,
+                old: s!("add.uc  a8[0],a8[1]"),
+                new: s!("sub.uc  a8[0],a8[1]")
+            },
+            InstrChange::Deleted { pos: 0x0D, instr: s!("nop") },
+            InstrChange::Inserted { pos: 0x0E, instr: s!("succ") },
+        ]);
+        let data = changes.data.as_ref().unwrap();
+        assert_eq!(
+            (data.pos, data.old.as_slice(), data.new.as_slice()),
+            (1, &b"old"[..], &b"new"[..])
+        );
+        assert_eq!(changes.to_string().lines().skip(3).take(2).collect::<Vec<_>>(), [
+            "- 0x000A 0x000A: add.uc  a8[0],a8[1]",
+            "+ 0x000A 0x000A: sub.uc  a8[0],a8[1]"
+        ]);
+    }
+
+    #[test]
+    fn shortest_edit_script() {
+        // Length of the longest common subsequence, computed with the quadratic algorithm
+        fn lcs(a: &[u8], b: &[u8]) -> usize {
+            let mut row = vec![0usize; b.len() + 1];
+            for x in a {
+                let mut diag = 0;
+                for (j, y) in b.iter().enumerate() {
+                    let prev = row[j + 1];
+                    row[j + 1] = if x == y { diag + 1 } else { row[j + 1].max(row[j]) };
+                    diag = prev;
+                }
+            }
+            row[b.len()]
+        }
+
+        let mut seed = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = |max: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % max) as u8
+        };
+        for _ in 0..500 {
+            let len = next(24);
+            let a = (0..len).map(|_| next(4)).collect::<Vec<_>>();
+            let len = next(24);
+            let b = (0..len).map(|_| next(4)).collect::<Vec<_>>();
+
+            let script = edit_script(&a, &b);
+            let (mut i, mut j, mut out) = (0, 0, vec![]);
+            for edit in &script {
+                match edit {
+                    Edit::Keep => {
+                        assert_eq!(a[i], b[j]);
+                        out.push(a[i]);
+                        i += 1;
+                        j += 1;
+                    }
+                    Edit::Delete => i += 1,
+                    Edit::Insert => {
+                        out.push(b[j]);
+                        j += 1;
+                    }
+                }
+            }
+            assert_eq!((i, out.as_slice()), (a.len(), b.as_slice()));
+            let keep = script.iter().filter(|edit| **edit == Edit::Keep).count();
+            assert_eq!(keep, lcs(&a, &b), "{:?} -> {:?}", a, b);
+        }
+    }
+}
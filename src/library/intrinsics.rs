@@ -0,0 +1,287 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Intrinsic libraries implementing common 256-bit math routines.
+//!
+//! Each intrinsic is a separate library written with the core ALU instruction set and the relative
+//! jumps of [`RelJumpOp`], such that the code does not depend on its placement, which is called
+//! by user programs at offset 0 (see [`Intrinsic::site`]) and returns with `ret`. The programs
+//! calling intrinsics must be executed with an instruction set supporting [`RelJumpOp`]. Since
+//! the library sources are fixed, their ids are stable and may be hardcoded into the user
+//! programs. Intrinsics take arguments and return results in `A` registers, and may overwrite
+//! other registers listed in their description; the value of `st0` is set to `true` if the
+//! result is defined and to `false` otherwise.
+
+use crate::isa::{Instr, RelJumpOp};
+use crate::library::constants::{ISA_ID_ALU, ISA_ID_ALUREL};
+use crate::library::{IsaSeg, Lib, LibId, LibSite};
+
+/// Intrinsic library implementing a math routine
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Intrinsic {
+    /// Name of the routine
+    pub name: &'static str,
+
+    /// Description of the routine, including its calling convention
+    pub description: &'static str,
+
+    /// Assembly source code of the library
+    pub source: &'static str,
+}
+
+impl Intrinsic {
+    /// Assembles the intrinsic library.
+    pub fn lib(&self) -> Lib<'static> {
+        let mut lib = Lib::assemble_source::<Instr<RelJumpOp>>(self.source)
+            .expect("invalid intrinsic source code");
+        // The ISAE segment lists just the instruction sets used by the intrinsics, such that the
+        // library ids do not depend on the crate features enabling other instruction sets
+        lib.isae = IsaSeg::from_iter([ISA_ID_ALU, ISA_ID_ALUREL]).expect("invalid ISA ids");
+        lib
+    }
+
+    /// Returns id of the intrinsic library.
+    #[inline]
+    pub fn id(&self) -> LibId { self.lib().id() }
+
+    /// Returns location of the intrinsic routine entry point to be used in `call` instructions.
+    #[inline]
+    pub fn site(&self) -> LibSite { LibSite::with(0, self.id()) }
+}
+
+/// Multiplication of two unsigned 256-bit numbers followed by a division, with a 512-bit
+/// intermediate result.
+pub const MULDIV: Intrinsic = Intrinsic {
+    name: "muldiv",
+    description: "a256[0] := a256[0] * a256[1] / a256[2]; undefined if the divisor is zero or the \
+                  result does not fit 256 bits. Overwrites a512[0..=2].",
+    source: "
+        cpy     a256[0],a512[0]
+        cpy     a256[1],a512[1]
+        cpy     a256[2],a512[2]
+        mul.uc  a512[1],a512[0]
+        div.uc  a512[0],a512[2]
+        ; st0 is set to false if the result does not fit 256 bits
+        cpy     a512[2],a256[0]
+        rjif    done
+        clr     a256[0]
+        done:
+        ifn     a256[0]
+        stinv
+        ret
+    ",
+};
+
+/// Modular exponentiation of unsigned 256-bit numbers.
+pub const MODEXP: Intrinsic = Intrinsic {
+    name: "modexp",
+    description: "a256[0] := a256[0] ^ a256[1] mod a256[2]; undefined if the modulus is zero. \
+                  Overwrites a256[3..=6], a512[0..=1] and a8[0].",
+    source: "
+        ifn     a256[1]
+        rjif    undefined
+        ; e := exp, r := 1 mod m, b := base mod m
+        dup     a256[1],a256[4]
+        put     a256[3],1
+        put     a8[0],1
+        dup     a256[2],a256[5]
+        rem     a256[3],a256[5]
+        mov     a256[5],a256[3]
+        dup     a256[2],a256[5]
+        rem     a256[0],a256[5]
+        mov     a256[5],a256[6]
+        ; loop over exponent bits, from the least significant one
+        loop:
+        ifz     a256[4]
+        rjif    done
+        shr.u   a8[0],a256[4]
+        rjif    mul
+        rjmp    square
+        ; r := r * b mod m
+        mul:
+        cpy     a256[3],a512[0]
+        cpy     a256[6],a512[1]
+        mul.uc  a512[1],a512[0]
+        dup     a256[2],a256[5]
+        rem     a512[0],a256[5]
+        mov     a256[5],a256[3]
+        ; b := b * b mod m
+        square:
+        cpy     a256[6],a512[0]
+        dup     a512[0],a512[1]
+        mul.uc  a512[1],a512[0]
+        dup     a256[2],a256[5]
+        rem     a512[0],a256[5]
+        mov     a256[5],a256[6]
+        rjmp    loop
+        ; the result is undefined
+        undefined:
+        clr     a256[3]
+        done:
+        mov     a256[3],a256[0]
+        ifn     a256[0]
+        stinv
+        ret
+    ",
+};
+
+/// Comparison of two unsigned 512-bit numbers, each represented by a chain of two 256-bit limbs.
+pub const CMP512: Intrinsic = Intrinsic {
+    name: "cmp512",
+    description: "a8[0] := 0 if a256[0]:a256[1] = a256[2]:a256[3], 1 if it is greater and 2 if it \
+                  is less, where the first register holds the most significant limb; undefined if \
+                  any of the limbs is not set.",
+    source: "
+        clr     a8[0]
+        ifn     a256[0]
+        rjif    done
+        ifn     a256[1]
+        rjif    done
+        ifn     a256[2]
+        rjif    done
+        ifn     a256[3]
+        rjif    done
+        put     a8[0],1
+        gt.u    a256[0],a256[2]
+        rjif    done
+        put     a8[0],2
+        lt.u    a256[0],a256[2]
+        rjif    done
+        put     a8[0],1
+        gt.u    a256[1],a256[3]
+        rjif    done
+        put     a8[0],2
+        lt.u    a256[1],a256[3]
+        rjif    done
+        put     a8[0],0
+        done:
+        ifn     a8[0]
+        stinv
+        ret
+    ",
+};
+
+/// All intrinsics shipped with the library
+pub const INTRINSICS: [Intrinsic; 3] = [MULDIV, MODEXP, CMP512];
+
+/// Returns intrinsic with the given name, if it exists.
+pub fn intrinsic(name: &str) -> Option<Intrinsic> {
+    INTRINSICS.iter().copied().find(|intrinsic| intrinsic.name == name)
+}
+
+#[cfg(test)]
+mod test {
+    use amplify::num::u256;
+
+    use super::*;
+    use crate::reg::{CoreRegs, Reg32, RegA};
+
+    fn call(intrinsic: Intrinsic, args: &[(RegA, Reg32, u256)]) -> CoreRegs {
+        let mut regs = CoreRegs::default();
+        for (reg, idx, val) in args {
+            regs.set(reg, idx, *val);
+        }
+        intrinsic.lib().run::<Instr<RelJumpOp>>(0, &mut regs, &()).unwrap();
+        regs
+    }
+
+    fn binary(intrinsic: Intrinsic, vals: [u256; 3]) -> Option<u256> {
+        let args = [
+            (RegA::A256, Reg32::Reg0, vals[0]),
+            (RegA::A256, Reg32::Reg1, vals[1]),
+            (RegA::A256, Reg32::Reg2, vals[2]),
+        ];
+        let regs = call(intrinsic, &args);
+        let res = regs.get(RegA::A256, Reg32::Reg0).map(u256::from);
+        assert_eq!(regs.status(), res.is_some());
+        res
+    }
+
+    #[test]
+    fn muldiv() {
+        let pow = |exp: usize| u256::ONE << exp;
+        assert_eq!(binary(MULDIV, [pow(200), pow(100), pow(60)]), Some(pow(240)));
+        assert_eq!(binary(MULDIV, [u256::MAX, u256::MAX, u256::MAX]), Some(u256::MAX));
+        assert_eq!(
+            binary(MULDIV, [u256::from(7u8), u256::from(5u8), u256::from(3u8)]),
+            Some(u256::from(11u8))
+        );
+        assert_eq!(binary(MULDIV, [pow(200), pow(100), pow(40)]), None);
+        assert_eq!(binary(MULDIV, [pow(200), pow(100), u256::ZERO]), None);
+    }
+
+    #[test]
+    fn modexp() {
+        let num = |val: u64| u256::from(val);
+        assert_eq!(binary(MODEXP, [num(4), num(13), num(497)]), Some(num(445)));
+        assert_eq!(binary(MODEXP, [num(5), num(117), num(19)]), Some(num(1)));
+        assert_eq!(binary(MODEXP, [num(3), num(0), num(7)]), Some(num(1)));
+        assert_eq!(binary(MODEXP, [num(3), num(0), num(1)]), Some(num(0)));
+        // Fermat's little theorem for the secp256k1 field prime
+        let p = u256::MAX - num(0x1_0000_03D0);
+        assert_eq!(binary(MODEXP, [num(0xDEADBEEF), p - num(1), p]), Some(num(1)));
+        assert_eq!(binary(MODEXP, [num(3), num(3), num(0)]), None);
+    }
+
+    #[test]
+    fn cmp512() {
+        let cmp = |limbs: [u64; 4]| {
+            let args = [
+                (RegA::A256, Reg32::Reg0, u256::from(limbs[0])),
+                (RegA::A256, Reg32::Reg1, u256::from(limbs[1])),
+                (RegA::A256, Reg32::Reg2, u256::from(limbs[2])),
+                (RegA::A256, Reg32::Reg3, u256::from(limbs[3])),
+            ];
+            let regs = call(CMP512, &args);
+            assert!(regs.status());
+            regs.get(RegA::A8, Reg32::Reg0).map(u8::from).unwrap()
+        };
+        assert_eq!(cmp([1, 2, 1, 2]), 0);
+        assert_eq!(cmp([2, 0, 1, 9]), 1);
+        assert_eq!(cmp([1, 9, 2, 0]), 2);
+        assert_eq!(cmp([1, 3, 1, 2]), 1);
+        assert_eq!(cmp([1, 2, 1, 3]), 2);
+
+        let regs = call(CMP512, &[(RegA::A256, Reg32::Reg0, u256::ONE)]);
+        assert!(!regs.status());
+    }
+
+    #[test]
+    fn ids() {
+        let ids = INTRINSICS.map(|intrinsic| intrinsic.id().to_string());
+        assert_eq!(ids, [
+            "urn:ubideco:alu:95XD36hqdKCf6GfGVYVMBoADDEPwdZmNzutJw8ekkKku#maestro-postal-forever",
+            "urn:ubideco:alu:x4zLSGJ6F8zkZSnCxnXotEPxMmfMrhmwHobEwVKcxfp#needle-cigar-tropic",
+            "urn:ubideco:alu:AyEnxHyR2yXddqVqcrfjLuaRpcN2tYh7knS9AGVrqahC#null-ginger-indigo",
+        ]);
+    }
+
+    #[test]
+    fn enumerate() {
+        for intrinsic in INTRINSICS {
+            assert_eq!(self::intrinsic(intrinsic.name), Some(intrinsic));
+            assert_eq!(intrinsic.site().pos, 0);
+        }
+        assert_eq!(intrinsic("unknown"), None);
+    }
+}
@@ -21,30 +21,53 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::convert::TryFrom;
 use core::fmt::{self, Display, Formatter};
 use core::hash::{Hash as RustHash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Range;
 use core::str::FromStr;
 
+use amplify::hex::{FromHex, ToHex};
+use amplify::num::u24;
 use amplify::{ByteArray, Bytes32};
 use baid58::{Baid58ParseError, FromBaid58, ToBaid58};
 use sha2::{Digest, Sha256};
+#[cfg(feature = "ct")]
+use subtle::{Choice, ConstantTimeEq};
 
-use super::{Cursor, Read};
+use super::cursor::{SizeCounter, XrefCursor};
+use super::{
+    Abi, Cursor, DataMap, DebugInfo, ExecCursor, InstrSize, LibMeta, LibStats, Read, WriteError,
+};
 use crate::data::ByteStr;
-use crate::isa::{BytecodeError, ExecStep, InstructionSet};
+use crate::export::Cfg;
+use crate::isa::{
+    is_identifier, BytecodeError, ExecStep, Instr, InstructionSet, LiteralPool, ParseInstrError,
+    SourceError,
+};
 use crate::library::segs::IsaSeg;
-use crate::library::{CodeEofError, LibSeg, LibSegOverflow, SegmentError};
+use crate::library::{
+    IsaId, LibSeg, LibSegOverflow, SegmentError, SegmentLimits, SegmentParseError,
+};
+use crate::receipt::CallRecord;
 use crate::reg::CoreRegs;
-use crate::LIB_NAME_ALUVM;
+use crate::{ExecStats, RunError, RunLimitError, LIB_NAME_ALUVM};
 
+/// Versioned domain separator of the [`LibId`] hash preimage. Any change to the data committed to
+/// by the library id must change the version, such that ids computed under different schemes can
+/// never collide.
 pub const LIB_ID_TAG: [u8; 32] = *b"urn:ubideco:aluvm:lib:v01#230304";
 
 /// Unique identifier for a library.
-#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Default, Debug, From)]
+#[derive(Wrapper, Copy, Clone, Ord, PartialOrd, Eq, Hash, Default, Debug, From)]
+#[cfg_attr(not(feature = "ct"), derive(PartialEq))]
+#[cfg_attr(feature = "ct", allow(clippy::derived_hash_with_manual_eq))]
 #[wrapper(Deref, BorrowSlice, Hex, Index, RangeOps)]
 #[derive(StrictType, StrictDecode)]
 #[cfg_attr(feature = "std", derive(StrictEncode))]
@@ -60,6 +83,22 @@ pub struct LibId(
     Bytes32,
 );
 
+/// With `ct` feature the equality of library ids is checked in constant time, such that call
+/// resolution by [`crate::Prog`] and the library stores does not leak via timing side channels how
+/// much of the id is matching. The ordering and hashing of the ids are not constant-time, thus
+/// lookups of the ids in other ordered or hashed collections remain subject to the timing leaks.
+#[cfg(feature = "ct")]
+impl PartialEq for LibId {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool { bool::from(self.ct_eq(other)) }
+}
+
+#[cfg(feature = "ct")]
+impl ConstantTimeEq for LibId {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> Choice { self.as_slice().ct_eq(other.as_slice()) }
+}
+
 impl ToBaid58<32> for LibId {
     const HRI: &'static str = "alu";
     fn to_baid58_payload(&self) -> [u8; 32] { self.to_byte_array() }
@@ -117,95 +156,600 @@ impl LibId {
 }
 
 /// AluVM executable code library
+///
+/// Code and data segments may be either owned by the library or borrowed from an existing byte
+/// buffer with [`Lib::with_borrowed`], such that libraries kept in a memory-mapped storage can be
+/// decoded and executed without copying their segments.
+///
+/// Library may export named routines, which are looked up with [`Lib::entrypoint`]. The routine
+/// table is serialized together with the library segments, but it is not committed to by the
+/// [`LibId`]: libraries which differ only in their routine tables have the same id and are
+/// equal. Thus, the routine names are a convenience for the callers, and the code offsets they
+/// resolve to must not be trusted unless the table comes from a trusted source. The same applies
+/// to the [`Abi`] descriptors of the routine inputs and outputs attached with [`Lib::set_abi`],
+/// to the [`DebugInfo`] of the instrumented builds and to the [`LibMeta`] metadata.
+///
+/// Equality, ordering and hashing of libraries are defined by their [`LibId`], such that runtimes
+/// can deduplicate libraries kept in sets and maps regardless of whether the libraries own or
+/// borrow their segments.
 #[derive(Clone, Debug, Default)]
 // #[cfg_attr(feature = "strict_encoding", derive(StrictEncode, StrictDecode))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
-pub struct Lib {
+pub struct Lib<'lib> {
     /// ISA segment
     pub isae: IsaSeg,
     /// Code segment
-    pub code: ByteStr,
+    pub code: Cow<'lib, [u8]>,
     /// Data segment
-    pub data: ByteStr,
+    pub data: Cow<'lib, [u8]>,
     /// Libs segment
     pub libs: LibSeg,
+    /// Routines exported by the library, mapping their names to the code offsets
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub routines: BTreeMap<String, u16>,
+    /// Descriptors of inputs and outputs of the exported routines, keyed by the routine names
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub abis: BTreeMap<String, Abi>,
+    /// Debug information produced by [`Lib::assemble_instrumented`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub debug: Option<DebugInfo>,
+    /// Descriptive metadata identifying the library for humans
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub meta: Option<LibMeta>,
 }
 
-impl Display for Lib {
+impl Display for Lib<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(meta) = self.meta.as_ref().filter(|meta| !meta.is_empty()) {
+            writeln!(f, "META:   {}", meta)?;
+        }
         writeln!(f, "ISAE:   {}", &self.isae)?;
-        write!(f, "CODE:\n{:#10}", self.code)?;
-        write!(f, "DATA:\n{:#10}", self.data)?;
+        write!(f, "CODE:\n{:#10}", ByteStr::with(&self.code))?;
+        write!(f, "DATA:\n{:#10}", ByteStr::with(&self.data))?;
+        // Data references can be detected only by decoding the code with the library ISA, which
+        // is known here just for the libraries using the core instruction set
+        let core_isae = IsaSeg::from_iter(<Instr>::isa_set().iter().map(IsaId::to_string));
+        if core_isae.as_ref() == Ok(&self.isae) {
+            match self.data_map::<Instr>() {
+                Ok(map) if !map.is_empty() => write!(f, "XREF:\n{}", map)?,
+                _ => {}
+            }
+        }
         write!(f, "LIBS:   {:8}", self.libs)
     }
 }
 
-impl PartialEq for Lib {
+impl PartialEq for Lib<'_> {
     #[inline]
     fn eq(&self, other: &Self) -> bool { self.id().eq(&other.id()) }
 }
 
-impl Eq for Lib {}
+impl Eq for Lib<'_> {}
 
-impl PartialOrd for Lib {
+impl PartialOrd for Lib<'_> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
 }
 
-impl Ord for Lib {
+impl Ord for Lib<'_> {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering { self.id().cmp(&other.id()) }
 }
 
-impl RustHash for Lib {
+impl RustHash for Lib<'_> {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) { state.write(&self.id()[..]) }
 }
 
 /// Errors while assembling library from the instruction set
-#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
-#[display(inner)]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(doc_comments)]
 pub enum AssemblerError {
-    /// Error assembling code and data segments
-    #[from]
-    Bytecode(BytecodeError),
+    /// unable to encode instruction #{index} `{instr}` (opcode {opcode:#04x}) at code offset
+    /// {pos}: {source}
+    Bytecode {
+        /// Index of the instruction in the assembled code
+        index: usize,
+        /// Offset of the instruction in the code segment
+        pos: u16,
+        /// Instruction opcode
+        opcode: u8,
+        /// Instruction in assembly format
+        instr: String,
+        /// Underlying encoding error
+        source: BytecodeError,
+    },
 
-    /// Error assembling library segment
+    /// {0}
     #[from]
     LibSegOverflow(LibSegOverflow),
+
+    /// code segment size {len} exceeds the limit of {max} bytes set by the assembly options.
+    CodeLimitExceeded {
+        /// Size of the assembled code segment
+        len: usize,
+        /// Maximal code segment size set by the assembly options
+        max: u16,
+    },
+
+    /// data segment size {len} exceeds the limit of {max} bytes set by the assembly options.
+    DataLimitExceeded {
+        /// Size of the assembled data segment
+        len: usize,
+        /// Maximal data segment size set by the assembly options
+        max: u24,
+    },
+
+    /// unable to place data table #{index} into the data segment: {source}
+    Table {
+        /// Index of the table
+        index: usize,
+        /// Underlying encoding error
+        source: WriteError,
+    },
+
+    /// jump table of instruction #{index} at code offset {pos} has entry {target:#06X}, which is
+    /// not an offset of an assembled instruction.
+    JumpTable {
+        /// Index of the instruction in the assembled code
+        index: usize,
+        /// Offset of the instruction in the code segment
+        pos: u16,
+        /// Invalid jump table entry
+        target: u16,
+    },
 }
 
 #[cfg(feature = "std")]
 impl ::std::error::Error for AssemblerError {
     fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
         match self {
-            AssemblerError::Bytecode(err) => Some(err),
+            AssemblerError::Bytecode { source, .. } => Some(source),
             AssemblerError::LibSegOverflow(err) => Some(err),
+            AssemblerError::Table { source, .. } => Some(source),
+            AssemblerError::CodeLimitExceeded { .. }
+            | AssemblerError::DataLimitExceeded { .. }
+            | AssemblerError::JumpTable { .. } => None,
+        }
+    }
+}
+
+/// Errors while assembling library from the instructions produced by a fallible iterator with
+/// [`Lib::assemble_streaming`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, From)]
+pub enum StreamingError<E> {
+    /// Instruction producer has failed
+    Producer {
+        /// Index of the instruction in the assembled code
+        index: usize,
+        /// Offset at which the instruction would be placed in the code segment
+        pos: usize,
+        /// Error returned by the instruction producer
+        error: E,
+    },
+
+    /// Produced instructions can't be assembled
+    #[from]
+    Assembler(AssemblerError),
+}
+
+impl<E> Display for StreamingError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamingError::Producer { index, pos, error } => write!(
+                f,
+                "unable to produce instruction #{} at code offset {}: {}",
+                index, pos, error
+            ),
+            StreamingError::Assembler(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E> ::std::error::Error for StreamingError<E>
+where
+    E: ::std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            StreamingError::Producer { error, .. } => Some(error),
+            StreamingError::Assembler(err) => Some(err),
+        }
+    }
+}
+
+/// Options controlling library assembly with [`Lib::assemble_with_opts`]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct AssembleOpts {
+    /// Whether the data used by instructions reuse equal byte sequences already present in the
+    /// data segment. Searching for duplicates takes time proportional to the product of the data
+    /// segment size and the size of the data, thus it may be disabled when assembling the code
+    /// with large data.
+    pub dedup: bool,
+
+    /// Maximal size of the data segment, in bytes (inclusive). Values above
+    /// [`SegmentLimits::MAX`] have no effect, since the data segment can't exceed it.
+    pub max_data: u24,
+
+    /// Maximal size of the code segment, in bytes (inclusive)
+    pub max_code: u16,
+}
+
+impl Default for AssembleOpts {
+    /// Options used by [`Lib::assemble`]: deduplication enabled and segment limits set to the
+    /// maximal segment sizes.
+    fn default() -> Self {
+        AssembleOpts {
+            dedup: true,
+            max_data: u24::with(SegmentLimits::MAX.data as u32),
+            max_code: SegmentLimits::MAX.code,
+        }
+    }
+}
+
+/// Table of constants placed into the data segment at an aligned offset by
+/// [`Lib::assemble_with_tables`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct DataTable {
+    /// Table data
+    pub data: ByteStr,
+
+    /// Alignment of the table offset in the data segment, in bytes. Zero and one mean no
+    /// alignment.
+    pub align: u16,
+}
+
+impl DataTable {
+    /// Constructs table with the given data and alignment.
+    ///
+    /// Panics if the length of the data is greater than `u16::MAX` bytes.
+    #[inline]
+    pub fn with(data: impl AsRef<[u8]>, align: u16) -> Self {
+        DataTable { data: ByteStr::with(data), align }
+    }
+}
+
+/// Sizes of the library segments, computed by [`Lib::assemble_dry_run`]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct SegmentSizes {
+    /// Size of the code segment, in bytes
+    pub code: usize,
+
+    /// Size of the data segment, in bytes
+    pub data: usize,
+
+    /// Number of the libraries referenced by the code
+    pub libs: usize,
+}
+
+impl SegmentSizes {
+    /// Checks whether the segments fit into the library [`SegmentLimits::MAX`].
+    pub fn fits(&self) -> bool {
+        let limits = SegmentLimits::MAX;
+        limits.fits_code(self.code) && limits.fits_data(self.data) && limits.fits_libs(self.libs)
+    }
+}
+
+/// Errors adding routine to the library routine table with [`Lib::export`]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum RoutineError {
+    /// routine name `{0}` is not a valid identifier
+    InvalidName(String),
+
+    /// routine `{name}` starts at offset {pos}, which is outside of the code segment
+    OutOfCode {
+        /// Routine name
+        name: String,
+        /// Routine code offset
+        pos: u16,
+    },
+
+    /// routine `{0}` is not exported by the library
+    NotExported(String),
+}
+
+/// Errors while disassembling library bytecode into instructions.
+///
+/// The error locates the failing instruction by its code offset and opcode. The lower-level
+/// bytecode readers ([`Read`]) report just a [`crate::library::CodeEofError`] without this
+/// context, which is attached here, at the level of the library.
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum DisassembleError {
+    /// instruction with opcode {opcode:#04x} at code offset {pos} is truncated: its operands
+    /// extend beyond the end of the code segment (read failed at offset {failed_at})
+    CodeTruncated {
+        /// Offset of the instruction in the code segment
+        pos: u16,
+        /// Instruction opcode
+        opcode: u8,
+        /// Code segment offset at which the read operation has failed
+        failed_at: u16,
+    },
+
+    /// instruction with opcode {opcode:#04x} at code offset {pos} references data outside of
+    /// the data segment
+    DataOutOfRange {
+        /// Offset of the instruction in the code segment
+        pos: u16,
+        /// Instruction opcode
+        opcode: u8,
+    },
+}
+
+impl DisassembleError {
+    /// Detects the reason of the decoding failure of an instruction at `pos` from the state of
+    /// the reader after the failure.
+    fn with_reader(reader: &impl Read, pos: u16, opcode: u8) -> DisassembleError {
+        let failed_at = reader.pos();
+        if reader.is_eof() {
+            DisassembleError::CodeTruncated { pos, opcode, failed_at }
+        } else {
+            DisassembleError::DataOutOfRange { pos, opcode }
+        }
+    }
+
+    /// Returns offset of the instruction which has failed to decode
+    pub fn pos(self) -> u16 {
+        match self {
+            DisassembleError::CodeTruncated { pos, .. }
+            | DisassembleError::DataOutOfRange { pos, .. } => pos,
+        }
+    }
+
+    /// Returns opcode of the instruction which has failed to decode
+    pub fn opcode(self) -> u8 {
+        match self {
+            DisassembleError::CodeTruncated { opcode, .. }
+            | DisassembleError::DataOutOfRange { opcode, .. } => opcode,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for DisassembleError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            DisassembleError::CodeTruncated { .. } => Some(&crate::library::CodeEofError),
+            DisassembleError::DataOutOfRange { .. } => None,
+        }
+    }
+}
+
+/// Errors of the entry point validation performed by [`Lib::check_entrypoint`]
+#[derive(Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum EntrypointError {
+    /// entry point {pos} is outside of the code segment of {code_len} bytes
+    OutOfCode {
+        /// Entry point offset
+        pos: u16,
+        /// Length of the code segment
+        code_len: usize,
+    },
+
+    /// entry point {pos} falls into the interior of the instruction at code offset {instr_pos}
+    InsideInstruction {
+        /// Entry point offset
+        pos: u16,
+        /// Offset of the instruction containing the entry point
+        instr_pos: u16,
+    },
+
+    /// code preceding the entry point can't be decoded: {0}
+    #[from]
+    Decode(DisassembleError),
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for EntrypointError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            EntrypointError::Decode(err) => Some(err),
+            EntrypointError::OutOfCode { .. } | EntrypointError::InsideInstruction { .. } => None,
+        }
+    }
+}
+
+/// Errors while patching library code with [`Lib::patch`]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum PatchError {
+    /// {0}
+    #[from]
+    Decode(DisassembleError),
+
+    /// patched code range {start}..{end} does not match instruction boundaries of the code
+    /// segment
+    InvalidRange {
+        /// Start offset of the patched range
+        start: u16,
+        /// End offset of the patched range (exclusive)
+        end: u16,
+    },
+
+    /// instruction at code offset {pos} jumps to offset {target} lying inside the patched code
+    /// range
+    JumpIntoPatch {
+        /// Offset of the jump instruction in the original code segment
+        pos: u16,
+        /// Jump target offset
+        target: u16,
+    },
+
+    /// jump target {target} of the instruction at code offset {pos} exceeds code segment
+    /// limits after patching
+    OffsetOverflow {
+        /// Offset of the jump instruction in the original code segment
+        pos: u16,
+        /// Jump target offset
+        target: u16,
+    },
+
+    /// routine `{name}` starts at code offset {pos} lying inside the patched code range
+    RoutineInPatch {
+        /// Routine name
+        name: String,
+        /// Routine code offset in the original code segment
+        pos: u16,
+    },
+
+    /// instruction at code offset {pos} changes its length from {len} to {new_len} bytes when
+    /// re-encoded
+    LengthChanged {
+        /// Offset of the instruction in the original code segment
+        pos: u16,
+        /// Length of the instruction in the original code segment
+        len: u16,
+        /// Length of the re-encoded instruction
+        new_len: u16,
+    },
+
+    /// {0}
+    #[from]
+    Assembler(AssemblerError),
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            PatchError::Decode(err) => Some(err),
+            PatchError::Assembler(err) => Some(err),
+            PatchError::InvalidRange { .. }
+            | PatchError::JumpIntoPatch { .. }
+            | PatchError::OffsetOverflow { .. }
+            | PatchError::RoutineInPatch { .. }
+            | PatchError::LengthChanged { .. } => None,
+        }
+    }
+}
+
+/// Errors while collecting library statistics with [`Lib::statistics`]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum StatsError {
+    /// {0}
+    #[from]
+    Decode(DisassembleError),
+
+    /// {0}
+    #[from]
+    Assembler(AssemblerError),
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for StatsError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            StatsError::Decode(err) => Some(err),
+            StatsError::Assembler(err) => Some(err),
         }
     }
 }
 
-impl Lib {
+impl Lib<'static> {
     /// Constructs library from raw data split into segments
     pub fn with(
         isa: &str,
         bytecode: Vec<u8>,
         data: Vec<u8>,
         libs: LibSeg,
-    ) -> Result<Lib, SegmentError> {
-        let isae = IsaSeg::from_iter(isa.split(' '))?;
-        Ok(Self {
-            isae,
-            libs,
-            code: ByteStr::try_from(bytecode.as_slice())
-                .map_err(|_| SegmentError::CodeSegmentTooLarge(bytecode.len()))?,
-            data: ByteStr::try_from(data.as_slice())
-                .map_err(|_| SegmentError::DataSegmentTooLarge(bytecode.len()))?,
-        })
+    ) -> Result<Lib<'static>, SegmentError> {
+        Lib::with_segments(isa, Cow::Owned(bytecode), Cow::Owned(data), libs)
+    }
+
+    /// Constructs library from the code and data segments encoded as hexadecimal strings, as
+    /// produced by [`Lib::code_hex`] and [`Lib::data_hex`].
+    pub fn with_hex(
+        isa: &str,
+        code: &str,
+        data: &str,
+        libs: LibSeg,
+    ) -> Result<Lib<'static>, SegmentParseError> {
+        let code = Vec::from_hex(code).map_err(|err| SegmentParseError::Hex("code", err))?;
+        let data = Vec::from_hex(data).map_err(|err| SegmentParseError::Hex("data", err))?;
+        Ok(Lib::with(isa, code, data, libs)?)
+    }
+
+    /// Constructs library from the code and data segments encoded with the standard base64
+    /// alphabet, as produced by [`Lib::code_base64`] and [`Lib::data_base64`].
+    #[cfg(feature = "base64")]
+    pub fn with_base64(
+        isa: &str,
+        code: &str,
+        data: &str,
+        libs: LibSeg,
+    ) -> Result<Lib<'static>, SegmentParseError> {
+        use base64::Engine;
+
+        let engine = base64::engine::general_purpose::STANDARD;
+        let code = engine.decode(code).map_err(|err| SegmentParseError::Base64("code", err))?;
+        let data = engine.decode(data).map_err(|err| SegmentParseError::Base64("data", err))?;
+        Ok(Lib::with(isa, code, data, libs)?)
+    }
+
+    /// Assembles library from the provided instructions by encoding them into bytecode.
+    ///
+    /// Assembly is deterministic, since [`LibId`]s are used as consensus-critical identifiers:
+    /// the same instructions are encoded into byte-identical segments on all platforms. The data
+    /// of the instructions are placed into the data segment in the order of their first use, with
+    /// repeated data referencing their first occurrence, and the called libraries are ordered by
+    /// their ids.
+    ///
+    /// # Errors
+    ///
+    /// If an instruction can't be encoded, if the called libraries don't fit into the library
+    /// segment, or if a [jump table](crate::isa::Bytecode::jump_table) of an instruction has an
+    /// entry which is not an offset of one of the instructions.
+    pub fn assemble<Isa>(code: &[Isa]) -> Result<Lib<'static>, AssemblerError>
+    where
+        Isa: InstructionSet,
+    {
+        Lib::assemble_with_opts(code, AssembleOpts::default())
+    }
+
+    /// Assembles library from the provided instructions by encoding them into bytecode, using
+    /// the provided assembly options.
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by [`Lib::assemble`], if the size of the code or data
+    /// segment exceeds the limit set in the options.
+    pub fn assemble_with_opts<Isa>(
+        code: &[Isa],
+        opts: AssembleOpts,
+    ) -> Result<Lib<'static>, AssemblerError>
+    where
+        Isa: InstructionSet,
+    {
+        Lib::assemble_with_tables(code, &[], opts)
     }
 
-    /// Assembles library from the provided instructions by encoding them into bytecode
-    pub fn assemble<Isa>(code: &[Isa]) -> Result<Lib, AssemblerError>
+    /// Assembles library from the provided instructions by encoding them into bytecode, using
+    /// the provided assembly options and placing the constant tables into the beginning of the
+    /// data segment.
+    ///
+    /// Each table is placed at the offset aligned according to the table alignment, padding the
+    /// data segment with zero bytes. Instructions using data equal to a table reference the
+    /// table, such that the data of the instructions can be given aligned locations, like the
+    /// ones required for the hash preimages or key material.
+    ///
+    /// # Errors
+    ///
+    /// In addition to the errors returned by [`Lib::assemble_with_opts`], if the tables don't fit
+    /// into the data segment.
+    pub fn assemble_with_tables<Isa>(
+        code: &[Isa],
+        tables: &[DataTable],
+        opts: AssembleOpts,
+    ) -> Result<Lib<'static>, AssemblerError>
     where
         Isa: InstructionSet,
     {
@@ -214,176 +758,2861 @@ impl Lib {
 
         let mut code_segment = ByteStr::default();
         let mut writer = Cursor::<_, ByteStr>::new(&mut code_segment.bytes[..], &libs_segment);
-        for instr in code.iter() {
-            instr.encode(&mut writer)?;
+        writer.set_dedup(opts.dedup);
+        for (index, table) in tables.iter().enumerate() {
+            writer
+                .write_table(table.data.as_ref(), table.align)
+                .map_err(|source| AssemblerError::Table { index, source })?;
+        }
+        let mut positions = Vec::with_capacity(code.len());
+        for (index, instr) in code.iter().enumerate() {
+            let pos = writer.pos();
+            positions.push(pos);
+            instr.encode(&mut writer).map_err(|source| AssemblerError::Bytecode {
+                index,
+                pos,
+                opcode: instr.instr_byte(),
+                instr: instr.to_string(),
+                source,
+            })?;
+        }
+        for (index, (instr, pos)) in code.iter().zip(&positions).enumerate() {
+            let table = instr.jump_table().unwrap_or_default();
+            if let Some(target) =
+                table.iter().find(|target| positions.binary_search(target).is_err())
+            {
+                return Err(AssemblerError::JumpTable { index, pos: *pos, target: *target });
+            }
         }
         let pos = writer.pos();
         let data_segment = writer.into_data_segment();
         code_segment.adjust_len(pos);
+        if pos > opts.max_code {
+            return Err(AssemblerError::CodeLimitExceeded {
+                len: pos as usize,
+                max: opts.max_code,
+            });
+        }
+        if data_segment.len() as u32 > opts.max_data.to_u32() {
+            return Err(AssemblerError::DataLimitExceeded {
+                len: data_segment.len() as usize,
+                max: opts.max_data,
+            });
+        }
 
         Ok(Lib {
-            isae: IsaSeg::from_iter(Isa::isa_ids())
+            isae: IsaSeg::from_iter(Isa::isa_set().iter().map(IsaId::to_string))
                 .expect("ISA instruction set contains incorrect ISAE ids"),
             libs: libs_segment,
-            code: code_segment,
-            data: data_segment,
+            code: Cow::Owned(code_segment.to_vec()),
+            data: Cow::Owned(data_segment.to_vec()),
+            routines: BTreeMap::new(),
+            abis: BTreeMap::new(),
+            debug: None,
+            meta: None,
         })
     }
 
-    /// Disassembles library into a set of instructions
-    pub fn disassemble<Isa>(&self) -> Result<Vec<Isa>, CodeEofError>
+    /// Assembles library from the instructions produced by a fallible iterator, like the ones
+    /// returned by compiler frontends generating code lazily.
+    ///
+    /// The instructions are collected until the iterator is exhausted, since the library
+    /// segment must be known before encoding the code, and then assembled with
+    /// [`Lib::assemble`].
+    ///
+    /// # Errors
+    ///
+    /// The first error returned by the iterator, reported with the index of the instruction which
+    /// failed to be produced and the code offset it would take, or an error assembling the
+    /// collected instructions.
+    pub fn assemble_streaming<Isa, E>(
+        code: impl IntoIterator<Item = Result<Isa, E>>,
+    ) -> Result<Lib<'static>, StreamingError<E>>
     where
         Isa: InstructionSet,
     {
-        let mut code = Vec::new();
-        let mut reader = Cursor::with(&self.code, &self.data, &self.libs);
-        while !reader.is_eof() {
-            code.push(Isa::decode(&mut reader)?);
+        let mut instrs = Vec::new();
+        let mut pos = 0usize;
+        for (index, item) in code.into_iter().enumerate() {
+            let instr = item.map_err(|error| StreamingError::Producer { index, pos, error })?;
+            pos += instr.byte_count() as usize;
+            instrs.push(instr);
         }
-        Ok(code)
+        Lib::assemble(&instrs).map_err(StreamingError::from)
     }
 
-    /// Returns hash identifier [`LibId`], representing the library in a unique way.
+    /// Computes sizes of the library segments which would be produced by [`Lib::assemble`]
+    /// without assembling the library.
     ///
-    /// Lib ID is computed as SHA256 tagged hash of the serialized library segments (ISAE, code,
-    /// data).
-    #[inline]
-    pub fn id(&self) -> LibId {
-        LibId::with(self.isae_segment(), &self.code, &self.data, &self.libs)
+    /// Unlike [`Lib::assemble`], the sizes are computed even if they exceed the segment limits,
+    /// such that code generators can check whether the code fits the library with
+    /// [`SegmentSizes::fits`] before committing to a code layout.
+    ///
+    /// # Errors
+    ///
+    /// If some of the instructions can't be encoded for reasons other than the segment limits.
+    pub fn assemble_dry_run<Isa>(code: &[Isa]) -> Result<SegmentSizes, AssemblerError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut counter = SizeCounter::new();
+        for (index, instr) in code.iter().enumerate() {
+            let pos = counter.code_len().min(u16::MAX as usize) as u16;
+            instr.encode(&mut counter).map_err(|source| AssemblerError::Bytecode {
+                index,
+                pos,
+                opcode: instr.instr_byte(),
+                instr: instr.to_string(),
+                source,
+            })?;
+        }
+        let libs = code.iter().filter_map(|instr| instr.call_site()).map(|site| site.lib);
+        Ok(SegmentSizes {
+            code: code.iter().map(|instr| instr.byte_count() as usize).sum(),
+            data: counter.data_len(),
+            libs: libs.collect::<BTreeSet<_>>().len(),
+        })
     }
 
-    /// Returns ISA data
-    #[inline]
-    pub fn isae_segment(&self) -> String { self.isae.to_string() }
+    /// Parses assembly source text and assembles library from it.
+    ///
+    /// The source may declare named string literals referenced by multiple instructions, aligned
+    /// constant tables, label jump destinations and export routines with `.entry` declarations,
+    /// which are added to the library routine table; see [`LiteralPool`] for the details.
+    pub fn assemble_source<Isa>(source: &str) -> Result<Lib<'static>, SourceError>
+    where
+        Isa: InstructionSet + FromStr<Err = ParseInstrError>,
+    {
+        let mut lib = Lib::assemble_instrumented::<Isa>(source)?;
+        lib.debug = None;
+        Ok(lib)
+    }
 
-    /// Returns reference to code segment
-    #[inline]
-    pub fn code_segment(&self) -> &[u8] { self.code.as_ref() }
+    /// Parses assembly source text and assembles library from it in the same way as
+    /// [`Lib::assemble_source`], attaching [`DebugInfo`] with the source lines of the
+    /// instructions, label names and the names of the register variables declared with `.var`
+    /// directives.
+    ///
+    /// The debug information is not committed to by the [`LibId`], so the instrumented library
+    /// has the same id as the one assembled with [`Lib::assemble_source`].
+    pub fn assemble_instrumented<Isa>(source: &str) -> Result<Lib<'static>, SourceError>
+    where
+        Isa: InstructionSet + FromStr<Err = ParseInstrError>,
+    {
+        let mut pool = LiteralPool::new();
+        let (code, routines, debug) = pool.parse_instrumented::<Isa>(source)?;
+        let mut lib = Lib::assemble_with_tables(&code, pool.tables(), default!())?;
+        for (name, index) in routines {
+            let pos = code[..index].iter().map(|instr| instr.byte_count() as u32).sum::<u32>();
+            lib.export(name, pos as u16)?;
+        }
+        lib.debug = Some(debug);
+        Ok(lib)
+    }
+}
 
-    /// Returns reference to data segment
-    #[inline]
-    pub fn data_segment(&self) -> &[u8] { self.data.as_ref() }
+impl<'lib> Lib<'lib> {
+    /// Constructs library borrowing code and data segments from existing byte slices without
+    /// copying them.
+    pub fn with_borrowed(
+        isa: &str,
+        bytecode: &'lib [u8],
+        data: &'lib [u8],
+        libs: LibSeg,
+    ) -> Result<Lib<'lib>, SegmentError> {
+        Lib::with_segments(isa, Cow::Borrowed(bytecode), Cow::Borrowed(data), libs)
+    }
 
-    /// Returns reference to libraries segment
+    fn with_segments(
+        isa: &str,
+        bytecode: Cow<'lib, [u8]>,
+        data: Cow<'lib, [u8]>,
+        libs: LibSeg,
+    ) -> Result<Lib<'lib>, SegmentError> {
+        let isae = IsaSeg::from_iter(isa.split(' '))?;
+        SegmentLimits::MAX.check_code(bytecode.len())?;
+        SegmentLimits::MAX.check_data(data.len())?;
+        Ok(Self {
+            isae,
+            libs,
+            code: bytecode,
+            data,
+            routines: BTreeMap::new(),
+            abis: BTreeMap::new(),
+            debug: None,
+            meta: None,
+        })
+    }
+
+    /// Converts library into the library owning its segments, copying the borrowed ones.
+    pub fn into_owned(self) -> Lib<'static> {
+        Lib {
+            isae: self.isae,
+            code: Cow::Owned(self.code.into_owned()),
+            data: Cow::Owned(self.data.into_owned()),
+            libs: self.libs,
+            routines: self.routines,
+            abis: self.abis,
+            debug: self.debug,
+            meta: self.meta,
+        }
+    }
+
+    /// Exports routine starting at the code offset `pos` under the given name, replacing
+    /// previously exported routine with the same name.
+    ///
+    /// The routine table is not committed to by the [`LibId`], so exporting routines doesn't
+    /// change the library id.
+    ///
+    /// # Returns
+    ///
+    /// Offset of the replaced routine, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the name is not a valid identifier (an ASCII letter or underscore followed by ASCII
+    /// alphanumerics or underscores, up to 255 bytes long) or if the offset lies outside of the
+    /// code segment.
+    pub fn export(
+        &mut self,
+        name: impl Into<String>,
+        pos: u16,
+    ) -> Result<Option<u16>, RoutineError> {
+        let name = name.into();
+        if !is_identifier(&name) {
+            return Err(RoutineError::InvalidName(name));
+        }
+        if pos as usize >= self.code.len() {
+            return Err(RoutineError::OutOfCode { name, pos });
+        }
+        Ok(self.routines.insert(name, pos))
+    }
+
+    /// Returns code offset of the routine exported under the given name.
     #[inline]
-    pub fn libs_segment(&self) -> &LibSeg { &self.libs }
+    pub fn entrypoint(&self, name: &str) -> Option<u16> { self.routines.get(name).copied() }
 
-    /// Executes library code starting at entrypoint
+    /// Attaches descriptor of the routine inputs and outputs to the exported routine, replacing
+    /// previously attached descriptor.
+    ///
+    /// Like the routine table, descriptors are not committed to by the [`LibId`].
     ///
     /// # Returns
     ///
-    /// Location for the external code jump, if any
-    pub fn exec<Isa>(
-        &self,
-        entrypoint: u16,
-        registers: &mut CoreRegs,
-        context: &Isa::Context<'_>,
-    ) -> Option<LibSite>
+    /// Replaced descriptor, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the library doesn't export routine with the given name.
+    pub fn set_abi(
+        &mut self,
+        name: impl Into<String>,
+        abi: Abi,
+    ) -> Result<Option<Abi>, RoutineError> {
+        let name = name.into();
+        if !self.routines.contains_key(&name) {
+            return Err(RoutineError::NotExported(name));
+        }
+        Ok(self.abis.insert(name, abi))
+    }
+
+    /// Returns descriptor of inputs and outputs of the exported routine, if it was attached with
+    /// [`Lib::set_abi`].
+    #[inline]
+    pub fn abi(&self, name: &str) -> Option<&Abi> { self.abis.get(name) }
+
+    /// Returns descriptors of all the routines starting at the code offset `pos`.
+    pub fn abis_at(&self, pos: u16) -> impl Iterator<Item = &Abi> {
+        self.abis
+            .iter()
+            .filter(move |(name, _)| self.entrypoint(name) == Some(pos))
+            .map(|(_, abi)| abi)
+    }
+
+    /// Decodes instruction at the given code offset, if it can be decoded.
+    pub(crate) fn instr_at<Isa>(&self, pos: u16) -> Option<Isa>
     where
         Isa: InstructionSet,
     {
-        let mut cursor = Cursor::with(&self.code.bytes[..], &self.data, &self.libs);
-        let lib_hash = self.id();
-        cursor.seek(entrypoint).ok()?;
-
-        while !cursor.is_eof() {
-            let pos = cursor.pos();
+        let mut cursor = ExecCursor::padded(&self.code, &self.data, &self.libs);
+        cursor.seek(pos).ok()?;
+        Isa::decode(&mut cursor).ok()
+    }
 
-            let instr = Isa::decode(&mut cursor).ok()?;
-            let next = instr.exec(registers, LibSite::with(pos, lib_hash), context);
+    /// Disassembles library into a set of instructions
+    pub fn disassemble<Isa>(&self) -> Result<Vec<Isa>, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        self.disassemble_iter().map(|res| res.map(|(_, instr)| instr)).collect()
+    }
 
-            #[cfg(all(debug_assertions, feature = "std"))]
-            eprint!("\n@{:06}> {:48}; st0={}", pos, instr, registers.st0);
+    /// Returns iterator lazily decoding library code into instructions, together with their
+    /// offsets in the code segment. Decoding borrows existing library segments and does not
+    /// allocate intermediary collections.
+    ///
+    /// The iterator stops after the first decoding error.
+    #[inline]
+    pub fn disassemble_iter<Isa>(&self) -> Disassembler<'_, Isa>
+    where
+        Isa: InstructionSet,
+    {
+        Disassembler {
+            reader: ExecCursor::with(self.code.as_ref(), self.data.as_ref(), &self.libs),
+            failed: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Disassembles library into a set of instructions together with their offsets in the code
+    /// segment, recovering from decoding errors.
+    ///
+    /// Unlike [`Lib::disassemble`], which fails on the first instruction which can't be decoded,
+    /// the undecodable bytes are returned as [`Disassembled::Unknown`] placeholder, and the
+    /// decoding is resumed from the next code offset at which an instruction can be decoded. This
+    /// allows inspection of partially corrupted libraries, or libraries using ISA extensions
+    /// unknown to `Isa`. Since the code offset at which the decoding resumes is guessed, the
+    /// instructions following a placeholder may not match the ones produced by the compiler.
+    pub fn disassemble_lenient<Isa>(&self) -> Vec<(u16, Disassembled<Isa>)>
+    where
+        Isa: InstructionSet,
+    {
+        let code = self.code.as_ref();
+        let data = self.data.as_ref();
+        let libs = &self.libs;
+        let decode_at = |pos: usize| -> Option<Isa> {
+            let mut reader = ExecCursor::with(code, data, libs);
+            reader.seek(pos as u16).ok()?;
+            Isa::decode(&mut reader).ok()
+        };
+
+        let mut instrs = Vec::new();
+        let mut pos = 0usize;
+        while pos < code.len() {
+            if let Some(instr) = decode_at(pos) {
+                let next = pos + instr.byte_count() as usize;
+                instrs.push((pos as u16, Disassembled::Instr(instr)));
+                pos = next;
+                continue;
+            }
+            let start = pos;
+            pos += 1;
+            while pos < code.len() && decode_at(pos).is_none() {
+                pos += 1;
+            }
+            let bytes = code[start..pos].to_vec();
+            instrs.push((start as u16, Disassembled::Unknown { opcode: bytes[0], bytes }));
+        }
+        instrs
+    }
+
+    /// Disassembles library into text listing, containing a line per instruction prefixed with
+    /// its code offset. Relative jumps are annotated with the offset of their destination.
+    ///
+    /// If the library has [`DebugInfo`], the listing is symbolic: labels are put before the
+    /// instructions they mark, and the instructions are annotated with their source lines, the
+    /// labels of their jump destinations and the names of the variables kept in the registers
+    /// they use.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set.
+    pub fn disassemble_listing<Isa>(&self) -> Result<String, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut listing = String::new();
+        for item in self.disassemble_iter::<Isa>() {
+            let (pos, instr) = item?;
+            let text = instr.to_string();
+            let mut notes = Vec::new();
+            let debug = self.debug.as_ref();
+            let label = |target| debug.and_then(|debug| debug.label_at(target));
+            if let Some(name) = label(pos) {
+                listing.push_str(&format!("{}:\n", name));
+            }
+            let target = instr.jump_destination(pos);
+            match (target.and_then(label), instr.jump_displacement(), target) {
+                (Some(name), ..) => notes.push(format!("-> {}", name)),
+                (None, Some(_), Some(target)) => notes.push(format!("-> {:#06X}", target)),
+                (None, Some(_), None) => notes.push(s!("-> out of code")),
+                (None, None, _) => {}
+            }
+            let table = instr.jump_table().unwrap_or_default();
+            if table.iter().any(|target| label(*target).is_some()) {
+                let targets = table.iter().map(|target| match label(*target) {
+                    Some(name) => name.to_string(),
+                    None => format!("{:#06X}", target),
+                });
+                notes.push(format!("-> {}", targets.collect::<Vec<_>>().join(" | ")));
+            }
+            if let Some(debug) = debug {
+                notes.extend(debug.line_at(pos).map(|line| format!("line {}", line)));
+                let regs = instr.src_regs().into_iter().chain(instr.dst_regs());
+                let vars = regs
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .filter_map(|reg| Some(format!("{}={}", debug.var(reg)?, reg)));
+                notes.extend(vars);
+            }
+            let line = match notes.is_empty() {
+                true => format!("{:#06X}: {}", pos, text),
+                false => format!("{:#06X}: {:32}; {}", pos, text, notes.join(", ")),
+            };
+            listing.push_str(&line);
+            listing.push('\n');
+        }
+        Ok(listing)
+    }
+
+    /// Collects statistics on the library code composition: number of instructions per opcode,
+    /// size of the code per instruction class, data segment utilization and the largest
+    /// instructions.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set, or if the decoded
+    /// instructions can't be encoded back.
+    pub fn statistics<Isa>(&self) -> Result<LibStats, StatsError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut stats =
+            LibStats { code_size: self.code.len(), data_size: self.data.len(), ..default!() };
+
+        // Data used by the code is measured by re-encoding the instructions into an empty data
+        // segment
+        let mut code_segment = ByteStr::default();
+        let mut writer = Cursor::<_, ByteStr>::new(&mut code_segment.bytes[..], &self.libs);
+        for (index, item) in self.disassemble_iter::<Isa>().enumerate() {
+            let (pos, instr) = item?;
+            instr.encode(&mut writer).map_err(|source| AssemblerError::Bytecode {
+                index,
+                pos,
+                opcode: instr.instr_byte(),
+                instr: instr.to_string(),
+                source,
+            })?;
+            let info = InstrSize { pos, size: instr.byte_count(), instr: instr.to_string() };
+            stats.add(instr.instr_byte(), info);
+        }
+        stats.data_used = writer.into_data_segment().len() as usize;
+
+        Ok(stats)
+    }
+
+    /// Maps ranges of the data segment to the code offsets of the instructions referencing them,
+    /// allowing to find which constants are used by which instructions, and which parts of the
+    /// data segment are not used by the code.
+    ///
+    /// [`Display`] of the library includes the map only for the libraries using exactly the ISA
+    /// extensions of the core [`Instr`] instruction set, and omits it if the code can't be
+    /// decoded.
+    ///
+    /// # Errors
+    ///
+    /// If the library code can't be decoded with the provided instruction set.
+    pub fn data_map<Isa>(&self) -> Result<DataMap, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut map = DataMap::new(self.data.len());
+        let mut reader = XrefCursor::with(&self.code, &self.data, &self.libs);
+        while !reader.is_eof() {
+            let pos = reader.pos();
+            let opcode = reader.peek_u8().unwrap_or_default();
+            Isa::decode(&mut reader)
+                .map_err(|_| DisassembleError::with_reader(&reader, pos, opcode))?;
+            for range in reader.take_refs() {
+                map.add(range, pos);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Replaces instructions occupying `range` of the code segment with `replacement`
+    /// instructions and re-encodes the library, rewriting jump offsets and relative jump
+    /// displacements of all instructions outside of the patched range to account for the changed
+    /// code length. An empty range inserts `replacement` at the given offset.
+    ///
+    /// Jumps to the start of the range land on the first replacement instruction. Jump offsets
+    /// inside `replacement` must already be given in terms of the patched code and are left
+    /// intact. Since patching changes [`LibId`], calls referencing the library by its previous
+    /// id are not rewritten.
+    ///
+    /// # Errors
+    ///
+    /// The library is left unmodified if its code can't be decoded, if the range does not match
+    /// instruction boundaries, if some instruction outside of the range jumps into its middle,
+    /// if a rewritten jump offset exceeds code segment limits or if the patched code can't be
+    /// assembled.
+    pub fn patch<Isa>(&mut self, range: Range<u16>, replacement: &[Isa]) -> Result<(), PatchError>
+    where
+        Isa: InstructionSet + Clone,
+    {
+        let code = self.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        let is_boundary =
+            |pos: u16| pos as usize == self.code.len() || code.iter().any(|(p, _)| *p == pos);
+        if range.start > range.end || !is_boundary(range.start) || !is_boundary(range.end) {
+            return Err(PatchError::InvalidRange { start: range.start, end: range.end });
+        }
+
+        let patch_end = range.start as u32
+            + replacement.iter().map(|instr| instr.byte_count() as u32).sum::<u32>();
+        let relocate = |pos: u16, target: u16| -> Result<u16, PatchError> {
+            if target <= range.start {
+                Ok(target)
+            } else if target < range.end {
+                Err(PatchError::JumpIntoPatch { pos, target })
+            } else {
+                u16::try_from(target as u32 - range.end as u32 + patch_end)
+                    .map_err(|_| PatchError::OffsetOverflow { pos, target })
+            }
+        };
+
+        let mut routines = BTreeMap::new();
+        for (name, pos) in &self.routines {
+            let pos = relocate(*pos, *pos).map_err(|err| match err {
+                PatchError::JumpIntoPatch { pos, .. } => {
+                    PatchError::RoutineInPatch { name: name.clone(), pos }
+                }
+                err => err,
+            })?;
+            routines.insert(name.clone(), pos);
+        }
+
+        let mut instrs = Vec::with_capacity(code.len() + replacement.len());
+        let mut tail = Vec::new();
+        for (pos, mut instr) in code {
+            if range.start <= pos && pos < range.end {
+                continue;
+            }
+            if let Some(target) = instr.jump_target_mut() {
+                *target = relocate(pos, *target)?;
+            }
+            for target in instr.jump_table_mut().into_iter().flatten() {
+                *target = relocate(pos, *target)?;
+            }
+            if let (Some(target), true) =
+                (instr.jump_destination(pos), instr.jump_displacement().is_some())
+            {
+                let new_pos = if pos < range.start {
+                    pos as i64
+                } else {
+                    pos as i64 - range.end as i64 + patch_end as i64
+                };
+                let shift = relocate(pos, target)? as i64 - new_pos;
+                if let Some(displacement) = instr.jump_displacement_mut() {
+                    *displacement = i16::try_from(shift)
+                        .map_err(|_| PatchError::OffsetOverflow { pos, target })?;
+                }
+            }
+            if pos < range.start {
+                instrs.push(instr);
+            } else {
+                tail.push(instr);
+            }
+        }
+        instrs.extend_from_slice(replacement);
+        instrs.extend(tail);
+
+        let patched = Lib::assemble(&instrs)?;
+        self.code = patched.code;
+        self.data = patched.data;
+        self.libs = patched.libs;
+        self.routines = routines;
+        if let Some(debug) = &mut self.debug {
+            // Source lines of the replaced instructions are lost, while the labels marking the
+            // start of the patched range mark the start of the replacement
+            let shift = |pos: u16| (pos as u32 - range.end as u32 + patch_end) as u16;
+            debug.relocate(
+                |pos| match pos {
+                    pos if pos < range.start => Some(pos),
+                    pos if pos < range.end => None,
+                    pos => Some(shift(pos)),
+                },
+                |pos| relocate(pos, pos).ok(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Removes basic blocks of the code which can't be reached from any of the `entries` and from
+    /// the exported routines, shrinking the library. The control flow graph is built with
+    /// [`Cfg`]; calls to the same library are not followed, so their destinations must be listed
+    /// among the `entries`.
+    ///
+    /// Jump offsets and relative jump displacements of the remaining instructions, exported
+    /// routines and the library debug information are rewritten for the new code layout, and the
+    /// data and libs segments are repacked to contain only the data and libraries used by the
+    /// remaining code. Since the code changes [`LibId`], calls referencing the library by its
+    /// previous id are not rewritten.
+    ///
+    /// # Returns
+    ///
+    /// Offsets of the `entries` in the shrunk code segment, in the same order.
+    ///
+    /// # Errors
+    ///
+    /// The library is left unmodified if its code can't be decoded, or if the remaining code can't
+    /// be assembled.
+    pub fn strip_unreachable<Isa>(&mut self, entries: &[u16]) -> Result<Vec<u16>, PatchError>
+    where
+        Isa: InstructionSet,
+    {
+        let cfg = Cfg::with::<Isa>(self)?;
+        let code_len = self.code.len() as u32;
+        let block_at = |pos: u16| cfg.blocks.iter().position(|b| b.start <= pos && pos < b.end);
+
+        let mut reachable = vec![false; cfg.blocks.len()];
+        let mut queue = entries
+            .iter()
+            .chain(self.routines.values())
+            .filter_map(|pos| block_at(*pos))
+            .collect::<Vec<_>>();
+        while let Some(index) = queue.pop() {
+            if reachable[index] {
+                continue;
+            }
+            reachable[index] = true;
+            queue.extend(cfg.blocks[index].successors.iter().filter_map(|pos| block_at(*pos)));
+        }
+
+        // New offsets of the starts of the remaining blocks
+        let mut starts = Vec::with_capacity(cfg.blocks.len());
+        let mut new_len = 0u32;
+        for (block, reachable) in cfg.blocks.iter().zip(&reachable) {
+            starts.push(reachable.then_some(new_len as u16));
+            if *reachable {
+                new_len += (block.end - block.start) as u32;
+            }
+        }
+        // Offsets past the code segment are kept past it, and offsets inside the removed blocks
+        // are never referenced by the remaining code
+        let relocate = |pos: u16| -> Option<u16> {
+            if pos as u32 >= code_len {
+                return Some((pos as u32 - code_len + new_len) as u16);
+            }
+            let index = block_at(pos)?;
+            Some(starts[index]? + (pos - cfg.blocks[index].start))
+        };
+
+        let mut instrs = Vec::new();
+        for (pos, mut instr) in self.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()? {
+            let Some(new_pos) = relocate(pos) else {
+                continue;
+            };
+            if let Some(target) = instr.jump_target_mut() {
+                *target = relocate(*target).unwrap_or(*target);
+            }
+            for target in instr.jump_table_mut().into_iter().flatten() {
+                *target = relocate(*target).unwrap_or(*target);
+            }
+            if let (Some(target), true) =
+                (instr.jump_destination(pos), instr.jump_displacement().is_some())
+            {
+                let shift = relocate(target).unwrap_or(target) as i64 - new_pos as i64;
+                if let Some(displacement) = instr.jump_displacement_mut() {
+                    *displacement = i16::try_from(shift)
+                        .map_err(|_| PatchError::OffsetOverflow { pos, target })?;
+                }
+            }
+            instrs.push(instr);
+        }
+
+        let stripped = Lib::assemble(&instrs)?;
+        self.code = stripped.code;
+        self.data = stripped.data;
+        self.libs = stripped.libs;
+        for pos in self.routines.values_mut() {
+            *pos = relocate(*pos).expect("routines are reachable");
+        }
+        if let Some(debug) = &mut self.debug {
+            debug.relocate(relocate, relocate);
+        }
+        Ok(entries.iter().map(|pos| relocate(*pos).expect("entries are reachable")).collect())
+    }
+
+    /// Inlines calls to the routines of the same library whose code is not larger than
+    /// `max_size` bytes, replacing each routine call instruction with a copy of the routine code.
+    ///
+    /// A routine is inlined if it is a straight sequence of instructions ending with the return
+    /// instruction, which does not contain other terminal instructions, jumps or routine calls.
+    /// The routines themselves are kept in the code, so they remain available to the other
+    /// callers and may be removed with [`Lib::strip_unreachable`] afterwards.
+    ///
+    /// Jump offsets and relative jump displacements of the instructions, exported routines and
+    /// the library debug information are rewritten for the new code layout. The inlined code
+    /// executes the same operations on the registers, but does not use the call stack, thus it
+    /// executes less instructions and is not limited by the call stack size. Since the code
+    /// changes [`LibId`], calls referencing the library by its previous id are not rewritten.
+    ///
+    /// # Returns
+    ///
+    /// Number of the inlined routine calls.
+    ///
+    /// # Errors
+    ///
+    /// The library is left unmodified if its code can't be decoded, if some instruction jumps
+    /// into the middle of an inlined routine call, if a rewritten jump offset exceeds code segment
+    /// limits or if the code can't be assembled.
+    pub fn inline_routines<Isa>(&mut self, max_size: u16) -> Result<usize, PatchError>
+    where
+        Isa: InstructionSet + Clone,
+    {
+        let code = self.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        let code_len = self.code.len() as u32;
+        let index_of = |pos: u16| code.binary_search_by_key(&pos, |(pos, _)| *pos).ok();
+
+        // Returns range of the instructions of the routine at `pos` excluding the return
+        let body = |pos: u16| -> Option<Range<usize>> {
+            let start = index_of(pos)?;
+            let mut size = 0u16;
+            for (index, (pos, instr)) in code.iter().enumerate().skip(start) {
+                if instr.is_return() {
+                    return Some(start..index);
+                }
+                size = size.saturating_add(instr.byte_count());
+                if size > max_size
+                    || instr.is_terminal()
+                    || instr.is_routine_call()
+                    || !instr.jump_destinations(*pos).is_empty()
+                {
+                    return None;
+                }
+            }
+            None
+        };
+        let inlined = |pos: u16, instr: &Isa| -> Option<Range<usize>> {
+            if !instr.is_routine_call() {
+                return None;
+            }
+            body(instr.jump_destination(pos)?)
+        };
+
+        // New offsets of the instructions, followed by the new code length
+        let mut starts = Vec::with_capacity(code.len() + 1);
+        let mut new_len = 0u32;
+        for (pos, instr) in &code {
+            starts.push(new_len);
+            new_len += match inlined(*pos, instr) {
+                Some(body) => code[body].iter().map(|(_, instr)| instr.byte_count() as u32).sum(),
+                None => instr.byte_count() as u32,
+            };
+        }
+        starts.push(new_len);
+        let relocate = |pos: u16, target: u16| -> Result<u16, PatchError> {
+            let new_target = if target as u32 >= code_len {
+                target as u32 - code_len + new_len
+            } else {
+                let index = code.partition_point(|(pos, _)| *pos <= target) - 1;
+                let (start, instr) = &code[index];
+                if *start != target && inlined(*start, instr).is_some() {
+                    return Err(PatchError::JumpIntoPatch { pos, target });
+                }
+                starts[index] + (target - start) as u32
+            };
+            u16::try_from(new_target).map_err(|_| PatchError::OffsetOverflow { pos, target })
+        };
+
+        let mut count = 0usize;
+        let mut instrs = Vec::with_capacity(code.len());
+        for (index, (pos, instr)) in code.iter().enumerate() {
+            if let Some(body) = inlined(*pos, instr) {
+                instrs.extend(code[body].iter().map(|(_, instr)| instr.clone()));
+                count += 1;
+                continue;
+            }
+            let mut instr = instr.clone();
+            if let Some(target) = instr.jump_target_mut() {
+                *target = relocate(*pos, *target)?;
+            }
+            for target in instr.jump_table_mut().into_iter().flatten() {
+                *target = relocate(*pos, *target)?;
+            }
+            if let (Some(target), true) =
+                (instr.jump_destination(*pos), instr.jump_displacement().is_some())
+            {
+                let shift = relocate(*pos, target)? as i64 - starts[index] as i64;
+                if let Some(displacement) = instr.jump_displacement_mut() {
+                    *displacement = i16::try_from(shift)
+                        .map_err(|_| PatchError::OffsetOverflow { pos: *pos, target })?;
+                }
+            }
+            instrs.push(instr);
+        }
+
+        let mut routines = self.routines.clone();
+        for pos in routines.values_mut() {
+            *pos = relocate(*pos, *pos)?;
+        }
+        let inlined = Lib::assemble(&instrs)?;
+        self.code = inlined.code;
+        self.data = inlined.data;
+        self.libs = inlined.libs;
+        self.routines = routines;
+        if let Some(debug) = &mut self.debug {
+            let relocate = |pos: u16| relocate(pos, pos).ok();
+            debug.relocate(relocate, relocate);
+        }
+        Ok(count)
+    }
+
+    /// Re-encodes the library code, producing library whose data segment contains only the data
+    /// referenced by the instructions, and whose libs segment contains only the libraries called
+    /// by the instructions. Each piece of data is kept once, in the order of the first reference
+    /// from the code.
+    ///
+    /// The code segment layout is not changed, so the exported routines, their ABIs and ISA
+    /// extensions segment are preserved; however, since the data segment is rewritten, the
+    /// repacked library has a different [`LibId`].
+    ///
+    /// # Errors
+    ///
+    /// If the code can't be decoded or assembled back with the instruction set `Isa`, or if some
+    /// of the instructions are re-encoded with different length (for instance, when the library
+    /// was assembled with a different instruction set).
+    pub fn repack<Isa>(&self) -> Result<Lib<'static>, PatchError>
+    where
+        Isa: InstructionSet,
+    {
+        self.reassemble::<Isa>(|_| ())
+    }
+
+    /// Re-encodes the library code in the canonical form, where each instruction is replaced with
+    /// its [canonical form](InstructionSet::canonicalize), and the data and libs segments are
+    /// repacked in the same way as with [`Lib::repack`].
+    ///
+    /// Libraries produced by different compilers or compiler versions from the same program
+    /// differ in their [`LibId`], but have the same canonical form, which makes it possible to
+    /// verify reproducible builds with [`Lib::semantically_eq`].
+    ///
+    /// # Errors
+    ///
+    /// The same as for [`Lib::repack`].
+    pub fn canonicalize<Isa>(&self) -> Result<Lib<'static>, PatchError>
+    where
+        Isa: InstructionSet,
+    {
+        self.reassemble::<Isa>(Isa::canonicalize)
+    }
+
+    /// Compares the programs of two libraries modulo their encoding: the libraries are equal if
+    /// their code decodes into the same sequences of instructions, after bringing them into the
+    /// [canonical form](InstructionSet::canonicalize). The layouts of the data and libs segments,
+    /// as well as the routine tables and ISA extension segments are not compared.
+    ///
+    /// # Errors
+    ///
+    /// If the code of any of the libraries can't be decoded with the instruction set `Isa`.
+    pub fn semantically_eq<Isa>(&self, other: &Lib) -> Result<bool, DisassembleError>
+    where
+        Isa: InstructionSet + PartialEq,
+    {
+        let canonical = |lib: &Lib| {
+            lib.disassemble_iter::<Isa>()
+                .map(|res| {
+                    res.map(|(pos, mut instr)| {
+                        instr.canonicalize();
+                        (pos, instr)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(self.code.len() == other.code.len() && canonical(self)? == canonical(other)?)
+    }
+
+    /// Decodes the library code, applies `f` to each of the instructions and assembles it back,
+    /// keeping the code layout.
+    fn reassemble<Isa>(&self, mut f: impl FnMut(&mut Isa)) -> Result<Lib<'static>, PatchError>
+    where
+        Isa: InstructionSet,
+    {
+        let mut code = self.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        code.iter_mut().for_each(|(_, instr)| f(instr));
+        let mut next = self.code.len();
+        for (pos, instr) in code.iter().rev() {
+            let len = (next - *pos as usize) as u16;
+            if instr.byte_count() != len {
+                return Err(PatchError::LengthChanged {
+                    pos: *pos,
+                    len,
+                    new_len: instr.byte_count(),
+                });
+            }
+            next = *pos as usize;
+        }
+
+        let instrs = code.into_iter().map(|(_, instr)| instr).collect::<Vec<_>>();
+        let repacked = Lib::assemble(&instrs)?;
+        Ok(Lib {
+            isae: self.isae.clone(),
+            code: repacked.code,
+            data: repacked.data,
+            libs: repacked.libs,
+            routines: self.routines.clone(),
+            abis: self.abis.clone(),
+            debug: self.debug.clone(),
+            meta: self.meta.clone(),
+        })
+    }
+
+    /// Returns hash identifier [`LibId`], representing the library in a unique way.
+    ///
+    /// Lib ID is computed as SHA256 tagged hash of the serialized library segments (ISAE, code,
+    /// data, libs). Since the same bytecode decodes differently under different instruction set
+    /// extensions, the ISAE segment is a part of the hash preimage: libraries with identical code
+    /// requiring different ISAs never share an id. Programs check that the ISAs required by the
+    /// library are supported before running it (see [`crate::Prog::add_lib`]).
+    #[inline]
+    pub fn id(&self) -> LibId {
+        LibId::with(self.isae_segment(), &self.code, &self.data, &self.libs)
+    }
+
+    /// Returns ISA data
+    #[inline]
+    pub fn isae_segment(&self) -> String { self.isae.to_string() }
+
+    /// Returns reference to code segment
+    #[inline]
+    pub fn code_segment(&self) -> &[u8] { self.code.as_ref() }
+
+    /// Returns reference to data segment
+    #[inline]
+    pub fn data_segment(&self) -> &[u8] { self.data.as_ref() }
+
+    /// Returns code segment encoded as a lowercase hexadecimal string
+    #[inline]
+    pub fn code_hex(&self) -> String { self.code.to_hex() }
+
+    /// Returns data segment encoded as a lowercase hexadecimal string
+    #[inline]
+    pub fn data_hex(&self) -> String { self.data.to_hex() }
+
+    /// Returns code segment encoded with the standard base64 alphabet
+    #[cfg(feature = "base64")]
+    pub fn code_base64(&self) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(&self.code)
+    }
+
+    /// Returns data segment encoded with the standard base64 alphabet
+    #[cfg(feature = "base64")]
+    pub fn data_base64(&self) -> String {
+        use base64::Engine;
+
+        base64::engine::general_purpose::STANDARD.encode(&self.data)
+    }
+
+    /// Returns reference to libraries segment
+    #[inline]
+    pub fn libs_segment(&self) -> &LibSeg { &self.libs }
+
+    /// Returns ids of the libraries called by this library code.
+    ///
+    /// The dependencies are extracted from the call instructions when the library is assembled,
+    /// and all of them must be known to a program executing the library.
+    #[inline]
+    pub fn dependencies(&self) -> impl Iterator<Item = LibId> + '_ { self.libs.iter().copied() }
+
+    /// Checks that the entry point lies within the code segment at the start of an instruction,
+    /// by disassembling the code preceding it with the instruction set `Isa`.
+    ///
+    /// An instruction starting at the entry point is not required to be decodable, such that the
+    /// decoding failures are reported by the code execution.
+    ///
+    /// # Errors
+    ///
+    /// If the entry point is outside of the code segment, falls into the interior of an
+    /// instruction, or the code preceding it can't be decoded.
+    pub fn check_entrypoint<Isa>(&self, entrypoint: u16) -> Result<(), EntrypointError>
+    where
+        Isa: InstructionSet,
+    {
+        let code_len = self.code.len();
+        if entrypoint as usize >= code_len {
+            return Err(EntrypointError::OutOfCode { pos: entrypoint, code_len });
+        }
+        let mut prev = 0u16;
+        for item in self.disassemble_iter::<Isa>() {
+            let pos = match item {
+                Ok((pos, _)) => pos,
+                Err(err) if err.pos() >= entrypoint => break,
+                Err(err) => return Err(err.into()),
+            };
+            if pos > entrypoint {
+                return Err(EntrypointError::InsideInstruction {
+                    pos: entrypoint,
+                    instr_pos: prev,
+                });
+            }
+            if pos == entrypoint {
+                break;
+            }
+            prev = pos;
+        }
+        Ok(())
+    }
+
+    /// Executes library code starting at entrypoint
+    ///
+    /// # Returns
+    ///
+    /// Location for the external code jump, if any
+    pub fn exec<Isa>(
+        &self,
+        entrypoint: u16,
+        registers: &mut CoreRegs,
+        context: &Isa::Context<'_>,
+    ) -> Option<LibSite>
+    where
+        Isa: InstructionSet,
+    {
+        self.exec_loop::<Isa>(entrypoint, registers, context, |_, _, _| ())
+            .ok()
+            .and_then(|(res, _)| res.next)
+    }
+
+    /// Executes library code starting at entrypoint, checking execution limits set in the
+    /// registers with [`CoreRegs::set_limits`].
+    ///
+    /// # Returns
+    ///
+    /// Structured result of the execution, including location for the external code jump, if
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// If the entry point is not a valid start of an instruction (see [`Lib::check_entrypoint`]),
+    /// returns [`RunError::Entrypoint`] without executing the code. If the execution was
+    /// terminated due to one of the [`RunLimits`] being exceeded, returns [`RunError::Limit`];
+    /// in this case `st0` register is set to `false`.
+    ///
+    /// [`RunLimits`]: crate::RunLimits
+    pub fn run<Isa>(
+        &self,
+        entrypoint: u16,
+        registers: &mut CoreRegs,
+        context: &Isa::Context<'_>,
+    ) -> Result<ExecResult, RunError>
+    where
+        Isa: InstructionSet,
+    {
+        self.check_entrypoint::<Isa>(entrypoint)
+            .map_err(|err| RunError::Entrypoint(err, LibSite::with(entrypoint, self.id())))?;
+        self.exec_loop::<Isa>(entrypoint, registers, context, |_, _, _| ())
+            .map(|(res, _)| res)
+            .map_err(|(err, site)| RunError::Limit(err, site))
+    }
+
+    /// Executes library code starting at entrypoint, checking execution limits set in the
+    /// registers with [`CoreRegs::set_limits`] and reporting failures to decode the code as
+    /// errors.
+    ///
+    /// Unlike [`Lib::run`], which treats code which can't be decoded as the end of the
+    /// execution, this method allows to detect corrupted bytecode. The execution result may still
+    /// have [`ExecOutcome::DecodeFailure`] outcome if a jump destination lies outside of the code
+    /// segment.
+    ///
+    /// # Returns
+    ///
+    /// Structured result of the execution, including location for the external code jump, if
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// If the entry point is not a valid start of an instruction (see [`Lib::check_entrypoint`]),
+    /// returns [`RunError::Entrypoint`]. If an instruction can't be decoded, returns
+    /// [`RunError::Decode`] with the location of the instruction. If the execution was terminated
+    /// due to one of the [`RunLimits`] being exceeded, returns [`RunError::Limit`] with the
+    /// location of the instruction which has exceeded the limit; in this case `st0` register is
+    /// set to `false`.
+    ///
+    /// [`RunLimits`]: crate::RunLimits
+    pub fn try_run<Isa>(
+        &self,
+        entrypoint: u16,
+        registers: &mut CoreRegs,
+        context: &Isa::Context<'_>,
+    ) -> Result<ExecResult, RunError>
+    where
+        Isa: InstructionSet,
+    {
+        self.check_entrypoint::<Isa>(entrypoint)
+            .map_err(|err| RunError::Entrypoint(err, LibSite::with(entrypoint, self.id())))?;
+        self.try_run_hooked::<Isa>(entrypoint, registers, context, |_, _, _| ())
+    }
+
+    /// Version of [`Lib::try_run`] calling `st0_cleared` each time an instruction changes `st0`
+    /// register from `true` to `false`.
+    ///
+    /// The entry point is not checked with [`Lib::check_entrypoint`], since it is used for the
+    /// entries from calls and returns of other libraries, which must not cost more than a jump.
+    pub(crate) fn try_run_hooked<Isa>(
+        &self,
+        entrypoint: u16,
+        registers: &mut CoreRegs,
+        context: &Isa::Context<'_>,
+        st0_cleared: impl FnMut(&Isa, LibSite, &CoreRegs),
+    ) -> Result<ExecResult, RunError>
+    where
+        Isa: InstructionSet,
+    {
+        match self.exec_loop::<Isa>(entrypoint, registers, context, st0_cleared) {
+            Err((err, site)) => Err(RunError::Limit(err, site)),
+            Ok((_, Some(err))) => Err(RunError::Decode(err, LibSite::with(err.pos(), self.id()))),
+            Ok((res, None)) => Ok(res),
+        }
+    }
+
+    fn exec_loop<Isa>(
+        &self,
+        entrypoint: u16,
+        registers: &mut CoreRegs,
+        context: &Isa::Context<'_>,
+        mut st0_cleared: impl FnMut(&Isa, LibSite, &CoreRegs),
+    ) -> Result<(ExecResult, Option<DisassembleError>), (RunLimitError, LibSite)>
+    where
+        Isa: InstructionSet,
+    {
+        let mut cursor = ExecCursor::padded(self.code.as_ref(), self.data.as_ref(), &self.libs);
+        let lib_hash = self.id();
+        let steps = registers.steps();
+        let calls = registers.receipt().len();
+        let stats = registers.begin_stats();
+        let mut res = ExecResult {
+            outcome: ExecOutcome::DecodeFailure,
+            next: None,
+            steps: 0,
+            last_site: LibSite::with(entrypoint, lib_hash),
+            calls: Vec::new(),
+            stats: default!(),
+        };
+        let finish = |mut res: ExecResult, registers: &mut CoreRegs, decoded: bool| {
+            res.steps = registers.steps() - steps;
+            res.calls = registers.receipt().records().get(calls..).unwrap_or_default().to_vec();
+            res.stats = registers.end_stats(stats);
+            res.outcome = match (decoded, registers.st0) {
+                (false, _) => ExecOutcome::DecodeFailure,
+                (true, true) => ExecOutcome::Success,
+                (true, false) => ExecOutcome::Failure,
+            };
+            res
+        };
+
+        if cursor.seek(entrypoint).is_err() {
+            return Ok((finish(res, registers, false), None));
+        }
+
+        while !cursor.is_eof() {
+            let pos = cursor.pos();
+            if registers.is_suspended() {
+                let mut res = finish(res, registers, true);
+                res.outcome = ExecOutcome::Suspended;
+                res.next = Some(LibSite::with(pos, lib_hash));
+                return Ok((res, None));
+            }
+            let opcode = cursor.peek_u8().unwrap_or_default();
+
+            let decoded = Isa::decode(&mut cursor);
+            registers.acc_data_reads(cursor.take_data_reads());
+            let Ok(instr) = decoded else {
+                let err = DisassembleError::with_reader(&cursor, pos, opcode);
+                return Ok((finish(res, registers, false), Some(err)));
+            };
+            res.last_site = LibSite::with(pos, lib_hash);
+            #[cfg(feature = "deterministic-audit")]
+            registers.audit_instr(&instr, res.last_site);
+            let st0 = registers.st0;
+            let next = instr.exec_with_data(registers, res.last_site, self.data.as_ref(), context);
+            if st0 && !registers.st0 {
+                st0_cleared(&instr, res.last_site, registers);
+            }
+
+            #[cfg(all(debug_assertions, feature = "std"))]
+            eprint!("\n@{:06}> {:48}; st0={}", pos, instr, registers.st0);
+
+            if !registers.acc_complexity(instr) {
+                #[cfg(all(debug_assertions, feature = "std"))]
+                eprintln!();
+                return Ok((finish(res, registers, true), None));
+            }
+            if let ExecStep::Call(site) = next {
+                registers.log_call(CallRecord::Lib { site: res.last_site, target: site });
+            }
+            let backward_jump = matches!(next, ExecStep::Jump(to) if to <= pos);
+            registers.acc_step(backward_jump).map_err(|err| (err, res.last_site))?;
+            match next {
+                ExecStep::Stop => {
+                    #[cfg(all(debug_assertions, feature = "std"))]
+                    eprintln!();
+                    return Ok((finish(res, registers, true), None));
+                }
+                ExecStep::Next => continue,
+                ExecStep::Jump(pos) => {
+                    #[cfg(all(debug_assertions, feature = "std"))]
+                    eprint!(" -> {}", pos);
+                    if cursor.seek(pos).is_err() {
+                        return Ok((finish(res, registers, false), None));
+                    }
+                }
+                ExecStep::Call(site) => {
+                    #[cfg(all(debug_assertions, feature = "std"))]
+                    eprint!(" -> {}", site);
+                    res.next = Some(site);
+                    return Ok((finish(res, registers, true), None));
+                }
+                ExecStep::Yield(code) => {
+                    #[cfg(all(debug_assertions, feature = "std"))]
+                    eprintln!(" yield {:#06X}", code);
+                    let mut res = finish(res, registers, true);
+                    res.outcome = ExecOutcome::Yielded(code);
+                    res.next = Some(LibSite::with(cursor.pos(), lib_hash));
+                    return Ok((res, None));
+                }
+            }
+        }
+
+        Ok((finish(res, registers, true), None))
+    }
+}
+
+/// Outcome of the library code execution, reported by [`ExecResult`]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum ExecOutcome {
+    /// Execution has completed (or was passed to an external call) with `st0` set to `true`
+    Success,
+
+    /// Execution has halted (or was passed to an external call) with `st0` set to `false`
+    Failure,
+
+    /// Execution has stopped since the code at the entry point or jump destination can't be
+    /// decoded
+    DecodeFailure,
+
+    /// Execution was suspended by [`crate::Vm::start`] or [`crate::Vm::resume`] before
+    /// executing the instruction at the location provided in [`ExecResult::next`]
+    Suspended,
+
+    /// Execution was suspended by an instruction returning [`ExecStep::Yield`] with the provided
+    /// embed-defined code; it may be resumed from the location provided in [`ExecResult::next`]
+    #[display("yielded({0:#06X})")]
+    Yielded(u16),
+}
+
+/// Structured result of the library code execution, returned by [`Lib::run`]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct ExecResult {
+    /// Execution outcome
+    pub outcome: ExecOutcome,
+
+    /// Location for the external code jump, if the execution has stopped on a call, or the
+    /// location to resume the execution from, if it was suspended
+    pub next: Option<LibSite>,
+
+    /// Number of instructions executed by this library run
+    pub steps: u64,
+
+    /// Location of the last executed instruction, or the entry point if no instructions were
+    /// executed
+    pub last_site: LibSite,
+
+    /// External calls made by this library run, in the order they were made; they are also
+    /// logged into the registers [`CoreRegs::receipt`]
+    pub calls: Vec<CallRecord>,
+
+    /// Resources used by this library run; they are also accumulated in the registers
+    /// [`CoreRegs::stats`]
+    pub stats: ExecStats,
+}
+
+impl ExecResult {
+    /// Detects whether the execution has stopped on an external call
+    #[inline]
+    pub fn is_call(&self) -> bool {
+        self.next.is_some()
+            && !matches!(self.outcome, ExecOutcome::Suspended | ExecOutcome::Yielded(_))
+    }
+
+    /// Detects whether the execution was successful
+    #[inline]
+    pub fn is_success(&self) -> bool { self.outcome == ExecOutcome::Success }
+}
+
+/// Iterator decoding library code into instructions, returned by [`Lib::disassemble_iter`].
+pub struct Disassembler<'lib, Isa>
+where
+    Isa: InstructionSet,
+{
+    reader: ExecCursor<'lib>,
+    failed: bool,
+    _phantom: PhantomData<Isa>,
+}
+
+impl<'lib, Isa> Iterator for Disassembler<'lib, Isa>
+where
+    Isa: InstructionSet,
+{
+    type Item = Result<(u16, Isa), DisassembleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.reader.is_eof() {
+            return None;
+        }
+        let pos = self.reader.pos();
+        let opcode = self.reader.peek_u8().ok()?;
+        let res = Isa::decode(&mut self.reader).map(|instr| (pos, instr)).map_err(|_| {
+            self.failed = true;
+            DisassembleError::with_reader(&self.reader, pos, opcode)
+        });
+        Some(res)
+    }
+}
+
+/// Item produced by the lenient disassembly with [`Lib::disassemble_lenient`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+pub enum Disassembled<Isa>
+where
+    Isa: InstructionSet,
+{
+    /// Successfully decoded instruction
+    #[display(inner)]
+    Instr(Isa),
+
+    /// Bytes which can't be decoded as an instruction
+    #[display("unknown  {opcode:#04X}")]
+    Unknown {
+        /// First of the undecodable bytes, which would be the instruction opcode
+        opcode: u8,
+        /// Undecodable bytes, up to the next code offset where an instruction can be decoded
+        bytes: Vec<u8>,
+    },
+}
+
+/// Location within a library
+#[derive(Copy, Clone, Eq, PartialOrd, Ord, Hash, Debug, Default, Display)]
+#[cfg_attr(not(feature = "ct"), derive(PartialEq))]
+#[cfg_attr(feature = "ct", allow(clippy::derived_hash_with_manual_eq))]
+#[derive(StrictType, StrictDecode)]
+#[strict_type(lib = LIB_NAME_ALUVM)]
+#[cfg_attr(feature = "std", derive(StrictEncode))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+#[display("{pos} @ {lib}")]
+pub struct LibSite {
+    /// Library hash
+    pub lib: LibId,
+
+    /// Offset from the beginning of the code, in bytes
+    pub pos: u16,
+}
+
+/// With `ct` feature the equality of library sites is checked in constant time (see [`LibId`]).
+#[cfg(feature = "ct")]
+impl PartialEq for LibSite {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool { bool::from(self.ct_eq(other)) }
+}
+
+#[cfg(feature = "ct")]
+impl ConstantTimeEq for LibSite {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.lib.ct_eq(&other.lib) & self.pos.ct_eq(&other.pos)
+    }
+}
+
+impl LibSite {
+    /// Constricts library site reference from a given position and library hash
+    /// value
+    pub fn with(pos: u16, lib: LibId) -> LibSite { LibSite { lib, pos } }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lib_id_display() {
+        let id = LibId::with("FLOAT", b"", b"", &none!());
+        assert_eq!(
+            format!("{id}"),
+            "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#pinball-eternal-colombo"
+        );
+        assert_eq!(
+            format!("{id:-}"),
+            "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"
+        );
+    }
+
+    #[test]
+    fn nop_decode() {
+        use crate::isa::opcodes::{INSTR_NOP, INSTR_SUCC};
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let code = [Instr::Nop, Instr::ControlFlow(ControlFlowOp::Succ)];
+        let lib = Lib::assemble(&code).unwrap();
+        assert_eq!(lib.code.as_ref(), &[INSTR_NOP, INSTR_SUCC]);
+        assert_eq!(lib.disassemble::<Instr>().unwrap(), code);
+    }
+
+    #[test]
+    fn byte_count() {
+        use crate::data::Number;
+        use crate::isa::{
+            Bytecode, BytesOp, DeleteFlag, DigestOp, ExtendFlag, InsertFlag, Instr, PutOp,
+        };
+        use crate::reg::{Reg16, Reg32, RegA2, RegR, RegS};
+
+        let code: [Instr; 5] = [
+            Instr::Put(PutOp::PutIfR(
+                RegR::R128,
+                Reg32::Reg1,
+                Box::new(Number::from(1u128).into()),
+            )),
+            Instr::Bytes(BytesOp::Fill(
+                RegS::from(1),
+                Reg32::Reg1,
+                Reg32::Reg2,
+                Reg32::Reg3,
+                ExtendFlag::Fail,
+            )),
+            Instr::Bytes(BytesOp::Ins(
+                InsertFlag::FailOnLen,
+                Reg32::Reg1,
+                RegS::from(1),
+                RegS::from(2),
+            )),
+            Instr::Bytes(BytesOp::Del(
+                DeleteFlag::None,
+                RegA2::A8,
+                Reg32::Reg1,
+                RegA2::A16,
+                Reg32::Reg2,
+                true,
+                false,
+                RegS::from(1),
+                RegS::from(2),
+            )),
+            Instr::Digest(DigestOp::Sha256(RegS::from(1), Reg16::Reg1)),
+        ];
+        for instr in &code {
+            let lib = Lib::assemble(core::slice::from_ref(instr)).unwrap();
+            assert_eq!(lib.code.len(), usize::from(instr.byte_count()), "{instr}");
+        }
+        assert_eq!(Lib::assemble(&code).unwrap().disassemble::<Instr>().unwrap(), code);
+    }
+
+    #[test]
+    fn lib_id_commits_to_isae() {
+        let code = [0xFFu8];
+        let alu = LibId::with("ALU", code, b"", &none!());
+        assert_ne!(alu, LibId::with("ALU FLOAT", code, b"", &none!()));
+        assert_ne!(alu, LibId::with("FLOAT", code, b"", &none!()));
+        assert_eq!(alu, LibId::with("ALU", code, b"", &none!()));
+    }
+
+    #[test]
+    fn lib_id_from_str() {
+        let id = LibId::with("FLOAT", b"", b"", &none!());
+        assert_eq!(
+            Ok(id),
+            LibId::from_str(
+                "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#\
+                 pinball-eternal-colombo"
+            )
+        );
+        assert_eq!(
+            Ok(id),
+            LibId::from_str("urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ")
+        );
+        assert_eq!(
+            Ok(id),
+            LibId::from_str(
+                "alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#pinball-eternal-colombo"
+            )
+        );
+        assert_eq!(Ok(id), LibId::from_str("alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"));
+
+        assert_eq!(Ok(id), LibId::from_str("GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"));
+    }
+
+    #[test]
+    fn assemble_error_context() {
+        use crate::data::MaybeNumber;
+        use crate::isa::{Bytecode, ControlFlowOp, Instr, PutOp};
+        use crate::reg::{Reg32, RegA};
+
+        let code: [Instr; 3] = [
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::Nop,
+            Instr::Put(PutOp::PutA(RegA::A8, Reg32::Reg1, Box::new(MaybeNumber::none()))),
+        ];
+        let err = Lib::assemble(&code).unwrap_err();
+        assert_eq!(err, AssemblerError::Bytecode {
+            index: 2,
+            pos: 2,
+            opcode: code[2].instr_byte(),
+            instr: code[2].to_string(),
+            source: BytecodeError::PutNoNumber,
+        });
+    }
+
+    #[test]
+    fn assemble_opts() {
+        use crate::data::MaybeNumber;
+        use crate::isa::{Instr, PutOp};
+        use crate::reg::{Reg32, RegA};
+
+        let put = |idx| Instr::Put(PutOp::PutA(RegA::A64, idx, Box::new(MaybeNumber::from(7u64))));
+        let code: [Instr; 2] = [put(Reg32::Reg0), put(Reg32::Reg1)];
+
+        let lib = Lib::assemble(&code).unwrap();
+        assert_eq!(lib, Lib::assemble_with_opts(&code, AssembleOpts::default()).unwrap());
+        assert_eq!(lib.data.len(), 8);
+
+        let opts = AssembleOpts { dedup: false, ..default!() };
+        let lib = Lib::assemble_with_opts(&code, opts).unwrap();
+        assert_eq!(lib.data.len(), 16);
+        assert_eq!(lib.disassemble::<Instr>().unwrap(), code);
+
+        let opts = AssembleOpts { max_data: u24::with(15), ..opts };
+        assert_eq!(
+            Lib::assemble_with_opts(&code, opts),
+            Err(AssemblerError::DataLimitExceeded { len: 16, max: u24::with(15) })
+        );
+        let opts = AssembleOpts { max_code: 4, ..default!() };
+        assert_eq!(
+            Lib::assemble_with_opts(&code, opts),
+            Err(AssemblerError::CodeLimitExceeded { len: 8, max: 4 })
+        );
+    }
+
+    #[test]
+    fn assemble_streaming() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let code = [Instr::Nop, Instr::ControlFlow(ControlFlowOp::Jmp(0)), Instr::Nop];
+        let lib = Lib::assemble_streaming(code.iter().cloned().map(Ok::<_, String>)).unwrap();
+        assert_eq!(lib, Lib::assemble(&code).unwrap());
+
+        let stream = code.iter().cloned().map(Ok).chain([Err(s!("unknown label"))]);
+        assert_eq!(
+            Lib::assemble_streaming::<Instr, _>(stream).unwrap_err(),
+            StreamingError::Producer { index: 3, pos: 5, error: s!("unknown label") }
+        );
+
+        let too_long = (0..=u16::MAX).map(|_| Ok::<Instr, String>(Instr::Nop));
+        assert!(matches!(
+            Lib::assemble_streaming(too_long).unwrap_err(),
+            StreamingError::Assembler(_)
+        ));
+    }
+
+    #[test]
+    fn disassemble_error_context() {
+        use crate::isa::{Bytecode, ControlFlowOp, Instr};
+
+        let code: [Instr; 2] = [
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::ControlFlow(ControlFlowOp::Jmp(0x1234)),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let mut bytecode = lib.code.as_ref().to_vec();
+        bytecode.pop();
+        let truncated = Lib::with("ALU", bytecode, vec![], none!()).unwrap();
+        let err = truncated.disassemble::<Instr>().unwrap_err();
+        assert_eq!(err, DisassembleError::CodeTruncated {
+            pos: 1,
+            opcode: code[1].instr_byte(),
+            failed_at: 3,
+        });
+        assert_eq!(err.pos(), 1);
+    }
+
+    #[test]
+    fn disassemble_iter() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let code: [Instr; 3] = [
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Jmp(0x1234)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let decoded = lib.disassemble_iter::<Instr>().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(decoded, vec![(0, code[0].clone()), (1, code[1].clone()), (4, code[2].clone())]);
+
+        let truncated = Lib::with("ALU", vec![0xFF, 0x02, 0x34], vec![], none!()).unwrap();
+        let mut iter = truncated.disassemble_iter::<Instr>();
+        assert_eq!(iter.next(), Some(Ok((0, Instr::Nop))));
+        assert!(matches!(iter.next(), Some(Err(DisassembleError::CodeTruncated { pos: 1, .. }))));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn disassemble_lenient() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let code = [
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Jmp(0x1234)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let decoded = lib.disassemble_lenient::<Instr>();
+        assert_eq!(decoded, vec![
+            (0, Disassembled::Instr(code[0].clone())),
+            (1, Disassembled::Instr(code[1].clone())),
+            (4, Disassembled::Instr(code[2].clone())),
+        ]);
+
+        let truncated = Lib::with("ALU", vec![0xFF, 0x00, 0x02, 0x34], vec![], none!()).unwrap();
+        assert_eq!(truncated.disassemble_lenient::<Instr>(), vec![
+            (0, Disassembled::Instr(Instr::Nop)),
+            (1, Disassembled::Instr(Instr::ControlFlow(ControlFlowOp::Fail))),
+            (2, Disassembled::Unknown { opcode: 0x02, bytes: vec![0x02, 0x34] }),
+        ]);
+        assert!(truncated.disassemble::<Instr>().is_err());
+    }
+
+    #[test]
+    fn patch() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let mut lib = Lib::assemble::<Instr>(&[
+            Instr::ControlFlow(ControlFlowOp::Jmp(5)),
+            Instr::Nop,
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Jif(3)),
+            Instr::ControlFlow(ControlFlowOp::Routine(11)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ])
+        .unwrap();
+        let isae = lib.isae.clone();
+
+        lib.patch::<Instr>(4..5, &vec![Instr::ControlFlow(ControlFlowOp::Fail); 4]).unwrap();
+        assert_eq!(lib.isae, isae);
+        assert_eq!(lib.disassemble::<Instr>().unwrap(), vec![
+            Instr::ControlFlow(ControlFlowOp::Jmp(8)),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Jif(3)),
+            Instr::ControlFlow(ControlFlowOp::Routine(14)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ]);
+
+        lib.patch::<Instr>(4..8, &[]).unwrap();
+        assert_eq!(lib.disassemble::<Instr>().unwrap(), vec![
+            Instr::ControlFlow(ControlFlowOp::Jmp(4)),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Jif(3)),
+            Instr::ControlFlow(ControlFlowOp::Routine(10)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ]);
+
+        let id = lib.id();
+        assert_eq!(
+            lib.patch::<Instr>(1..4, &[]),
+            Err(PatchError::InvalidRange { start: 1, end: 4 })
+        );
+        assert_eq!(
+            lib.patch::<Instr>(3..7, &[]),
+            Err(PatchError::JumpIntoPatch { pos: 0, target: 4 })
+        );
+        assert_eq!(lib.id(), id);
+    }
+
+    #[test]
+    fn strip_unreachable() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let code = [
+            Instr::ControlFlow(ControlFlowOp::Jmp(5)),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Jif(10)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ];
+        let mut lib = Lib::assemble::<Instr>(&code).unwrap();
+        let mut stripped = lib.clone();
+        assert_eq!(stripped.strip_unreachable::<Instr>(&[0, 10]).unwrap(), [0, 7]);
+        assert_eq!(stripped.disassemble::<Instr>().unwrap(), vec![
+            Instr::ControlFlow(ControlFlowOp::Jmp(3)),
+            Instr::ControlFlow(ControlFlowOp::Jif(7)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ]);
+
+        lib.routines.insert(s!("aux"), 9);
+        assert_eq!(lib.strip_unreachable::<Instr>(&[0]).unwrap(), [0]);
+        assert_eq!(lib.routines["aux"], 7);
+        assert_eq!(lib.code.len(), 9);
+        assert_eq!(lib.disassemble::<Instr>().unwrap()[1..], [
+            Instr::ControlFlow(ControlFlowOp::Jif(8)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ]);
+    }
+
+    #[test]
+    fn inline_routines() {
+        use crate::isa::{ControlFlowOp, Instr, RelJumpOp};
+        use crate::{Prog, Vm};
+
+        type Isa = Instr<RelJumpOp>;
+
+        let source = "
+            put     a16[0],3
+            put     a16[1],2
+            routine square
+            rroutine square
+            routine twice
+            routine large
+            jmp     done
+        square:
+            mul.uw  a16[0],a16[0]
+            ret
+        twice:
+            add.uw  a16[0],a16[1]
+            jif     skip
+        skip:
+            ret
+        large:
+            add.uw  a16[0],a16[1]
+            add.uw  a16[0],a16[1]
+            add.uw  a16[0],a16[1]
+            ret
+        done:
+            rjif    end
+            fail
+        end:
+            succ
+        ";
+        let lib = Lib::assemble_source::<Isa>(source).unwrap();
+        let run = |lib: &Lib| {
+            let mut vm = Vm::<Isa>::new();
+            let success = vm.run(&Prog::<Isa>::new(lib.clone()), &());
+            (success, vm.registers.digest(), vm.registers.steps())
+        };
+
+        let mut small = lib.clone();
+        assert_eq!(small.inline_routines::<Isa>(4).unwrap(), 2);
+        let mut all = lib.clone();
+        assert_eq!(all.inline_routines::<Isa>(16).unwrap(), 3);
+        let (outcome, digest, steps) = run(&lib);
+        assert!(outcome);
+        for inlined in [&small, &all] {
+            let res = run(inlined);
+            assert_eq!((res.0, res.1), (outcome, digest));
+            assert!(res.2 < steps);
+        }
+
+        let code = small.disassemble::<Isa>().unwrap();
+        assert_eq!(code[2], code[3]);
+        assert_eq!(code[4], Instr::ControlFlow(ControlFlowOp::Routine(0x1B)));
+        assert_eq!(all.code.len(), lib.code.len() + 6);
+
+        let mut stripped = all.clone();
+        stripped.strip_unreachable::<Isa>(&[0]).unwrap();
+        assert_eq!(run(&stripped).1, digest);
+        assert!(stripped.code.len() < lib.code.len());
+    }
+
+    #[test]
+    fn repro() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        // Enough data to make the assembler use the data segment index
+        let mut code = Vec::<Instr>::new();
+        for i in 0..256u32 {
+            let line = match i % 4 {
+                0 => format!("put     a32[{}],{}", i % 32, i.wrapping_mul(7919) % 1000),
+                1 => format!("put     s16[{}],\"item{}\"", i % 16, i % 50),
+                2 => format!("put     f64[{}],{}.{}", i % 32, i, i % 7),
+                _ => format!("put     a128[{}],{}", i % 32, (i as u128) << 64 | i as u128),
+            };
+            code.push(Instr::from_str(&line).unwrap());
+        }
+        for i in [3u8, 1, 2, 1] {
+            let site = LibSite::with(i as u16, LibId::from_byte_array([i; 32]));
+            code.push(Instr::ControlFlow(ControlFlowOp::Call(site)));
+        }
+        code.push(Instr::ControlFlow(ControlFlowOp::Ret));
+
+        let lib = Lib::assemble(&code).unwrap();
+        assert!(lib.data.len() > 1024);
+        assert_eq!(Lib::assemble(&code).unwrap(), lib);
+        assert_eq!(lib.libs.iter().map(|id| id.to_byte_array()[0]).collect::<Vec<_>>(), [1, 2, 3]);
+        // Library ids are consensus-critical, so the segments must be the same for each platform
+        // and version of the assembler; ISA extensions segment depends on the crate features
+        let id = LibId::with("ALU", &lib.code, &lib.data, &lib.libs);
+        assert_eq!(
+            id.to_string(),
+            "urn:ubideco:alu:Mrzxy7gLsrrURf5t1DmkXXXTv88wmqwU2jjCDp1a6KN#symbol-kinetic-rubber"
+        );
+    }
+
+    #[test]
+    fn repack() {
+        use crate::isa::{ImmOp, Instr};
+
+        let site = LibSite::with(1, LibId::from([0x11; 32]));
+        let source = format!(
+            "
+            .entry  main
+            put     a64[0],0x1234
+            put     s16[0],\"hello\"
+            call    {site}
+            put     s16[1],\"hello\"
+            ret
+        "
+        );
+        let lib = Lib::assemble_source::<Instr>(&source).unwrap();
+        let mut data = lib.data.to_vec();
+        data.extend_from_slice(b"no longer referenced");
+        let mut libs = lib.libs.clone();
+        libs.add_lib(LibId::from([0xFF; 32])).unwrap();
+        let mut bloated = Lib::with(&lib.isae_segment(), lib.code.to_vec(), data, libs).unwrap();
+        bloated.routines = lib.routines.clone();
+
+        let repacked = bloated.repack::<Instr>().unwrap();
+        assert_eq!(repacked, lib);
+        assert_eq!(repacked.data, lib.data);
+        assert_eq!(repacked.libs, lib.libs);
+        assert_eq!(repacked.routines, bloated.routines);
+        assert_eq!(
+            repacked.disassemble::<Instr>().unwrap(),
+            bloated.disassemble::<Instr>().unwrap()
+        );
+
+        assert_eq!(
+            lib.repack::<Instr<ImmOp>>(),
+            Err(PatchError::LengthChanged { pos: 0, len: 4, new_len: 5 })
+        );
+    }
+
+    #[test]
+    fn canonicalize() {
+        use crate::isa::Instr;
+
+        let lib = Lib::assemble_source::<Instr>(
+            "
+            put     s16[0],\"ab\"
+            put     s16[1],\"b\"
+            eq.n    a8[1],a8[0]
+            gt.u    a8[0],a8[1]
+            and     a16[3],a16[2],a16[1]
+            ret
+        ",
+        )
+        .unwrap();
+        let code = Lib::assemble_source::<Instr>(
+            "
+            put     s16[0],\"ab\"
+            put     s16[1],\"b\"
+            eq.n    a8[0],a8[1]
+            lt.u    a8[1],a8[0]
+            and     a16[2],a16[3],a16[1]
+            ret
+        ",
+        )
+        .unwrap()
+        .disassemble::<Instr>()
+        .unwrap();
+        // The same code with a different data segment layout
+        let other =
+            Lib::assemble_with_tables(&code, &[DataTable::with(b"b", 0)], default!()).unwrap();
 
-            if !registers.acc_complexity(instr) {
-                #[cfg(all(debug_assertions, feature = "std"))]
-                eprintln!();
-                return None;
-            }
-            match next {
-                ExecStep::Stop => {
-                    #[cfg(all(debug_assertions, feature = "std"))]
-                    eprintln!();
-                    return None;
-                }
-                ExecStep::Next => continue,
-                ExecStep::Jump(pos) => {
-                    #[cfg(all(debug_assertions, feature = "std"))]
-                    eprint!(" -> {}", pos);
-                    cursor.seek(pos).ok()?;
-                }
-                ExecStep::Call(site) => {
-                    #[cfg(all(debug_assertions, feature = "std"))]
-                    eprint!(" -> {}", site);
-                    return Some(site);
-                }
+        assert_ne!(lib.id(), other.id());
+        assert_ne!(lib.data, other.data);
+        assert_eq!(lib.semantically_eq::<Instr>(&other), Ok(true));
+        assert_eq!(lib.canonicalize::<Instr>().unwrap(), other.canonicalize::<Instr>().unwrap());
+        assert_eq!(
+            lib.canonicalize::<Instr>().unwrap().disassemble::<Instr>().unwrap()[2..5]
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["eq.n    a8[0],a8[1]", "lt.u    a8[1],a8[0]", "and     a16[2],a16[3],a16[1]"]
+        );
+
+        let different = Lib::assemble_source::<Instr>("put s16[0],\"ab\"\nret").unwrap();
+        assert_eq!(lib.semantically_eq::<Instr>(&different), Ok(false));
+    }
+
+    #[test]
+    fn data_lookup() {
+        use crate::data::MaybeNumber;
+        use crate::isa::{DataOp, Instr};
+        use crate::reg::{Reg32, RegA, RegR};
+
+        let assemble = |source: &str, tables: &[DataTable]| {
+            let code = source
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(Instr::<DataOp>::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            Lib::assemble_with_tables(&code, tables, default!()).unwrap()
+        };
+        let run = |lib: &Lib| {
+            let mut regs = CoreRegs::new();
+            lib.run::<Instr<DataOp>>(0, &mut regs, &()).unwrap();
+            regs
+        };
+        let table = [DataTable::with([1u8, 2, 3, 4, 5, 6, 7, 8], 0)];
+
+        let lib = assemble(
+            "
+            put     a16[0],2
+            ldd     a8[1],a16[0]
+            ldd     a16[2],a16[0]
+            ldlen   a16[3]
+            ret
+        ",
+            &table,
+        );
+        assert_eq!(lib.data.len(), 10);
+        let regs = run(&lib);
+        assert!(regs.status());
+        assert_eq!(regs.get(RegA::A8, Reg32::Reg1), 3u8.into());
+        assert_eq!(regs.get(RegA::A16, Reg32::Reg2), 0x0403u16.into());
+        assert_eq!(regs.get(RegA::A16, Reg32::Reg3), 10u16.into());
+
+        // The value exceeds the data segment
+        let lib = assemble(
+            "
+            put     a16[0],2
+            ldd     r128[1],a16[0]
+            ret
+        ",
+            &table,
+        );
+        let regs = run(&lib);
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegR::R128, Reg32::Reg1), MaybeNumber::none());
+
+        // Offset register is not set
+        let regs = run(&assemble("ldd a8[1],a16[0]\nret", &table));
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A8, Reg32::Reg1), MaybeNumber::none());
+
+        // The length doesn't fit into the register
+        let lib = assemble("ldlen a8[1]\nret", &[DataTable::with([0u8; 300], 0)]);
+        let regs = run(&lib);
+        assert!(!regs.status());
+        assert_eq!(regs.get(RegA::A8, Reg32::Reg1), MaybeNumber::none());
+        let lib = assemble("ldlen a16[1]\nret", &[DataTable::with([0u8; 300], 0)]);
+        assert_eq!(run(&lib).get(RegA::A16, Reg32::Reg1), 300u16.into());
+    }
+
+    #[test]
+    fn jump_table() {
+        use crate::data::{MaybeNumber, Number};
+        use crate::export::Cfg;
+        use crate::isa::{ControlFlowOp, Instr, InstructionSet, SwitchOp};
+        use crate::reg::{Reg32, RegA};
+
+        let source = "
+            put     a16[1],0
+            switch  a8[0],zero,one
+            put     a16[1],30
+            ret
+            zero:
+            put     a16[1],10
+            ret
+            one:
+            put     a16[1],20
+            ret
+        ";
+        let mut lib = Lib::assemble_source::<Instr<SwitchOp>>(source).unwrap();
+        let run = |lib: &Lib, index: Option<u8>| {
+            let mut regs = CoreRegs::new();
+            regs.set(RegA::A8, Reg32::Reg0, MaybeNumber::from(index.map(Number::from)));
+            lib.run::<Instr<SwitchOp>>(0, &mut regs, &()).unwrap();
+            (regs.status(), regs.get(RegA::A16, Reg32::Reg1))
+        };
+        assert_eq!(run(&lib, Some(0)), (true, 10u16.into()));
+        assert_eq!(run(&lib, Some(1)), (true, 20u16.into()));
+        assert_eq!(run(&lib, Some(2)), (false, 30u16.into()));
+        assert_eq!(run(&lib, None), (false, 30u16.into()));
+
+        let cfg = Cfg::with::<Instr<SwitchOp>>(&lib).unwrap();
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(cfg.blocks[0].successors, bset![
+            cfg.blocks[1].start,
+            cfg.blocks[2].start,
+            cfg.blocks[3].start
+        ]);
+
+        // Table entries are relocated together with the code
+        let start = cfg.blocks[1].start;
+        lib.patch::<Instr<SwitchOp>>(start..start + 4, &[Instr::Nop]).unwrap();
+        assert_eq!(run(&lib, Some(0)), (true, 10u16.into()));
+        assert_eq!(run(&lib, Some(1)), (true, 20u16.into()));
+        assert_eq!(run(&lib, Some(2)), (false, 0u16.into()));
+
+        // Table entries must point to the assembled instructions
+        let switch =
+            |table: Vec<u16>| Instr::ExtensionCodes(SwitchOp::Switch(RegA::A8, Reg32::Reg0, table));
+        let ret = Instr::ControlFlow(ControlFlowOp::Ret);
+        assert!(Lib::assemble(&[switch(vec![6, 0]), ret.clone()]).is_ok());
+        assert_eq!(
+            Lib::assemble(&[switch(vec![6, 3]), ret]),
+            Err(AssemblerError::JumpTable { index: 0, pos: 0, target: 3 })
+        );
+        assert_eq!(SwitchOp::Switch(RegA::A8, Reg32::Reg0, vec![0; 40]).complexity(), 4);
+    }
+
+    #[test]
+    fn bounded_loop() {
+        use crate::data::{MaybeNumber, Number};
+        use crate::isa::{Bytecode, Instr, LoopOp};
+        use crate::reg::{Reg32, RegA};
+
+        let source = "
+            put     a16[1],0
+            body:
+            add.uc  a16[2],a16[1]
+            loop    a16[0],10,body
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<LoopOp>>(source).unwrap();
+        let code = lib.disassemble::<Instr<LoopOp>>().unwrap();
+        assert_eq!(code.iter().filter_map(Bytecode::loop_bound).collect::<Vec<_>>(), [10]);
+
+        let run = |counter: Option<u16>| {
+            let mut regs = CoreRegs::new();
+            regs.set(RegA::A16, Reg32::Reg0, MaybeNumber::from(counter.map(Number::from)));
+            regs.set(RegA::A16, Reg32::Reg2, 1u16);
+            let res = lib.run::<Instr<LoopOp>>(0, &mut regs, &()).unwrap();
+            (regs.status(), regs.get(RegA::A16, Reg32::Reg1), regs.get(RegA::A16, Reg32::Reg0), res)
+        };
+        for (counter, iterations) in [(0, 1u16), (1, 1), (3, 3), (10, 10)] {
+            let (st0, sum, counter, res) = run(Some(counter));
+            assert!(st0);
+            assert_eq!(sum, iterations.into());
+            assert_eq!(counter, 0u16.into());
+            assert_eq!(res.steps, 2 + 2 * iterations as u64);
+        }
+        // The counter exceeding the bound or not set terminates the loop
+        let (st0, sum, counter, _) = run(Some(11));
+        assert_eq!((st0, sum, counter), (false, 1u16.into(), 11u16.into()));
+        let (st0, sum, counter, _) = run(None);
+        assert_eq!((st0, sum, counter), (false, 1u16.into(), MaybeNumber::none()));
+    }
+
+    #[test]
+    fn fixed_point() {
+        use crate::data::MaybeNumber;
+        use crate::isa::{FixedOp, Instr};
+        use crate::reg::{Reg32, RegA};
+
+        let source = "
+            xmul.sc a64[1],a64[2],32
+            xdiv.sc a64[3],a64[2],32
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<FixedOp>>(source).unwrap();
+
+        let run = |x: f64, y: f64, z: f64| {
+            let fixed = |val: f64| (val * (1u64 << 32) as f64) as i64;
+            let mut regs = CoreRegs::new();
+            regs.set(RegA::A64, Reg32::Reg1, fixed(x));
+            regs.set(RegA::A64, Reg32::Reg2, fixed(y));
+            regs.set(RegA::A64, Reg32::Reg3, fixed(z));
+            lib.run::<Instr<FixedOp>>(0, &mut regs, &()).unwrap();
+            (regs.status(), regs.carry(), regs.get(RegA::A64, Reg32::Reg2))
+        };
+        // 75 / (-1.5 * 100) = -0.5
+        assert_eq!(run(-1.5, 100.0, 75.0), (true, false, (-1i64 << 31).into()));
+        assert_eq!(run(0.25, 0.5, 1.0), (true, false, (8i64 << 32).into()));
+        // Quotient of 2^20 and 2^-20 exceeds the range of 32 integer bits
+        assert_eq!(run(1.0, 1.0 / 1048576.0, 1048576.0), (false, true, MaybeNumber::none()));
+    }
+
+    #[test]
+    fn inline_immediates() {
+        use crate::isa::{ImmOp, Instr};
+        use crate::reg::{Reg32, RegA, RegR};
+        use crate::{Prog, Vm};
+
+        let source = "
+            put     a8[0],-1
+            put     a64[1],0x1234
+            putif   r256[2],7
+            put     a128[3],0x10000000000000000
+            add.uc  a64[1],a64[1]
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<ImmOp>>(source).unwrap();
+        let core = Lib::assemble_source::<Instr>(source).unwrap();
+        assert_eq!(lib.data.len(), 16);
+        assert_eq!(core.data.len(), 1 + 8 + 32 + 16);
+
+        let code = lib.disassemble::<Instr<ImmOp>>().unwrap();
+        assert_eq!(code[..3], [
+            Instr::ExtensionCodes(ImmOp::PutA(RegA::A8, Reg32::Reg0, 0xFF)),
+            Instr::ExtensionCodes(ImmOp::PutA(RegA::A64, Reg32::Reg1, 0x1234)),
+            Instr::ExtensionCodes(ImmOp::PutIfR(RegR::R256, Reg32::Reg2, 7)),
+        ]);
+        assert_eq!(
+            lib.disassemble::<Instr<ImmOp>>()
+                .unwrap()
+                .iter()
+                .map(Instr::to_string)
+                .collect::<Vec<_>>(),
+            core.disassemble::<Instr>().unwrap().iter().map(Instr::to_string).collect::<Vec<_>>(),
+        );
+
+        let mut vm = Vm::<Instr<ImmOp>>::new();
+        assert!(vm.run(&Prog::<Instr<ImmOp>>::new(lib), &()));
+        let mut core_vm = Vm::<Instr>::new();
+        assert!(core_vm.run(&Prog::<Instr>::new(core), &()));
+        for reg in [RegA::A8, RegA::A64, RegA::A128] {
+            for idx in [Reg32::Reg0, Reg32::Reg1, Reg32::Reg3] {
+                assert_eq!(vm.registers.get(reg, idx), core_vm.registers.get(reg, idx));
             }
         }
+        assert_eq!(
+            vm.registers.get(RegR::R256, Reg32::Reg2),
+            core_vm.registers.get(RegR::R256, Reg32::Reg2)
+        );
+        assert_eq!(vm.registers.get(RegA::A64, Reg32::Reg1), 0x2468u64.into());
+    }
 
-        None
+    #[test]
+    fn relative_jumps() {
+        use crate::isa::{Instr, RelJumpOp};
+
+        let mut lib = Lib::assemble::<Instr<RelJumpOp>>(&[
+            Instr::ExtensionCodes(RelJumpOp::Jmp(5)),
+            Instr::Nop,
+            Instr::Nop,
+            Instr::ExtensionCodes(RelJumpOp::Jif(-5)),
+            Instr::ExtensionCodes(RelJumpOp::Routine(-0x100)),
+        ])
+        .unwrap();
+        lib.patch::<Instr<RelJumpOp>>(3..4, &[Instr::Nop, Instr::Nop, Instr::Nop]).unwrap();
+        assert_eq!(lib.disassemble::<Instr<RelJumpOp>>().unwrap(), vec![
+            Instr::ExtensionCodes(RelJumpOp::Jmp(7)),
+            Instr::Nop,
+            Instr::Nop,
+            Instr::Nop,
+            Instr::Nop,
+            Instr::ExtensionCodes(RelJumpOp::Jif(-7)),
+            Instr::ExtensionCodes(RelJumpOp::Routine(-0x100)),
+        ]);
+        assert_eq!(
+            lib.disassemble_listing::<Instr<RelJumpOp>>().unwrap(),
+            "0x0000: rjmp    +7                      ; -> 0x0007
+0x0003: nop
+0x0004: nop
+0x0005: nop
+0x0006: nop
+0x0007: rjif    -7                      ; -> 0x0000
+0x000A: rroutine -256                   ; -> out of code
+"
+        );
     }
-}
 
-/// Location within a library
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default, Display)]
-#[derive(StrictType, StrictDecode)]
-#[strict_type(lib = LIB_NAME_ALUVM)]
-#[cfg_attr(feature = "std", derive(StrictEncode))]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
-#[display("{pos} @ {lib}")]
-pub struct LibSite {
-    /// Library hash
-    pub lib: LibId,
+    #[test]
+    fn statistics() {
+        use crate::isa::opcodes::{INSTR_PUT, INSTR_PUTA, INSTR_SUCC};
+        use crate::isa::{BytesOp, ControlFlowOp, Instr, PutOp};
+        use crate::library::InstrClass;
+        use crate::reg::{Reg32, RegA, RegS};
 
-    /// Offset from the beginning of the code, in bytes
-    pub pos: u16,
-}
+        let put = |s: u8, data: &[u8]| {
+            Instr::Bytes(BytesOp::Put(RegS::from(s), Box::new(ByteStr::with(data)), false))
+        };
+        let code: [Instr; 5] = [
+            put(0, b"hello"),
+            put(1, b"hello"),
+            Instr::Put(PutOp::PutA(RegA::A32, Reg32::Reg0, Box::new(1u32.into()))),
+            Instr::Nop,
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let mut lib = Lib::assemble(&code).unwrap();
+        lib.data.to_mut().extend(b"unused".iter().copied());
 
-impl LibSite {
-    /// Constricts library site reference from a given position and library hash
-    /// value
-    pub fn with(pos: u16, lib: LibId) -> LibSite { LibSite { lib, pos } }
-}
+        let stats = lib.statistics::<Instr>().unwrap();
+        assert_eq!(stats.instr_count, 5);
+        assert_eq!(stats.code_size, 6 + 6 + 4 + 1 + 1);
+        assert_eq!(stats.opcodes[&INSTR_PUT], 2);
+        assert_eq!(stats.opcodes[&INSTR_PUTA], 1);
+        assert_eq!(stats.opcodes[&INSTR_SUCC], 1);
+        assert_eq!(stats.class_sizes[&InstrClass::Bytes], 12);
+        assert_eq!(stats.class_sizes[&InstrClass::Put], 4);
+        assert_eq!(stats.class_sizes[&InstrClass::Nop], 1);
+        // "hello" and 32-bit value put into `a32[0]`, plus "unused"
+        assert_eq!((stats.data_size, stats.data_used), (15, 9));
+        assert_eq!(stats.largest.len(), 5);
+        assert_eq!((stats.largest[0].pos, stats.largest[0].size), (0, 6));
+        assert_eq!((stats.largest[1].pos, stats.largest[2].pos), (6, 12));
+        assert!(stats.to_string().starts_with("instructions: 5\n"));
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn data_map() {
+        use crate::isa::{BytesOp, ControlFlowOp, Instr, PutOp};
+        use crate::reg::{Reg32, RegA, RegS};
+
+        let put = |s: u8, data: &[u8]| {
+            Instr::Bytes(BytesOp::Put(RegS::from(s), Box::new(ByteStr::with(data)), false))
+        };
+        let code: [Instr; 4] = [
+            put(0, b"hello"),
+            Instr::Put(PutOp::PutA(RegA::A32, Reg32::Reg0, Box::new(1u32.into()))),
+            put(1, b"hello"),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let mut lib = Lib::assemble(&code).unwrap();
+        lib.data.to_mut().extend(b"unused".iter().copied());
+
+        let map = lib.data_map::<Instr>().unwrap();
+        assert_eq!(map.len(), 2);
+        let refs = map.iter().map(|(range, refs)| (range, refs.clone())).collect::<Vec<_>>();
+        assert_eq!(refs, vec![(0..5, bset![0, 10]), (5..9, bset![6])]);
+        assert_eq!(map.referencing(4), bset![0, 10]);
+        assert_eq!(map.referencing(9), bset![]);
+        assert_eq!(map.unreferenced(), vec![9..15]);
+        assert_eq!(
+            map.to_string(),
+            "0x0000..0x0005: 0x0000, 0x000A\n0x0005..0x0009: 0x0006\n0x0009..0x000F: \
+             unreferenced\n"
+        );
+        assert!(lib.to_string().contains("XREF:\n0x0000..0x0005: 0x0000, 0x000A\n"));
+
+        lib.data.to_mut().truncate(3);
+        let map = lib.data_map::<Instr>().unwrap();
+        assert!(map.to_string().starts_with("0x0000..0x0005: 0x0000, 0x000A (outside of data"));
+        assert!(map.unreferenced().is_empty());
+    }
 
     #[test]
-    fn lib_id_display() {
-        let id = LibId::with("FLOAT", &b"", &b"", &none!());
+    fn data_map_empty_data() {
+        use crate::isa::{ControlFlowOp, Instr, PutOp};
+        use crate::reg::{Reg32, RegA};
+
+        let code: [Instr; 3] = [
+            Instr::Put(PutOp::PutA(RegA::A32, Reg32::Reg0, Box::new(1u32.into()))),
+            Instr::Put(PutOp::PutA(RegA::A32, Reg32::Reg1, Box::new(2u32.into()))),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ];
+        let mut lib = Lib::assemble(&code).unwrap();
+        lib.code.to_mut().drain(..4);
+        lib.data.to_mut().clear();
+
+        let map = lib.data_map::<Instr>().unwrap();
+        let refs = map.iter().map(|(range, refs)| (range, refs.clone())).collect::<Vec<_>>();
+        assert_eq!(refs, vec![(4..8, bset![0])]);
+        assert!(map.unreferenced().is_empty());
+        assert_eq!(map.to_string(), "0x0004..0x0008: 0x0000 (outside of data segment)\n");
+    }
+
+    #[test]
+    fn data_past_end() {
+        use crate::isa::{BytesOp, Instr};
+        use crate::reg::RegS;
+
+        let put = |reg: u8, data: &[u8], truncated: bool| -> Instr {
+            Instr::Bytes(BytesOp::Put(RegS::from(reg), Box::new(ByteStr::with(data)), truncated))
+        };
+        let mut lib = Lib::assemble(&[put(0, b"data", false), put(1, b"more", false)]).unwrap();
+        lib.data.to_mut().truncate(2);
+        assert_eq!(lib.disassemble::<Instr>().unwrap()[..2], [
+            put(0, b"da", true),
+            put(1, b"", true)
+        ]);
+
+        let mut regs = CoreRegs::default();
+        let res = lib.run::<Instr>(0, &mut regs, &()).unwrap();
+        assert_eq!(res.outcome, ExecOutcome::Failure);
+        assert_eq!(regs.get_s(RegS::from(0u8)), Some(&ByteStr::with(b"da")));
+        assert_eq!(regs.get_s(RegS::from(1u8)), Some(&ByteStr::default()));
+    }
+
+    #[test]
+    fn data_map_extension_isa() {
+        use crate::isa::{ImmOp, Instr};
+
+        let source = "
+            put     a64[1],0x1234
+            put     a64[2],0x31415926
+            ret
+        ";
+        let lib = Lib::assemble_source::<Instr<ImmOp>>(source).unwrap();
+        assert!(lib.data_map::<Instr<ImmOp>>().unwrap().is_empty());
+        assert!(!lib.to_string().contains("XREF:"));
+    }
+
+    #[test]
+    fn exec_result() {
+        use crate::isa::{ControlFlowOp, Instr};
+
+        let run = |lib: &Lib| {
+            let mut regs = CoreRegs::default();
+            lib.run::<Instr>(0, &mut regs, &()).unwrap()
+        };
+
+        let lib =
+            Lib::assemble::<Instr>(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Fail)]).unwrap();
+        let id = lib.id();
+        assert_eq!(run(&lib), ExecResult {
+            outcome: ExecOutcome::Failure,
+            next: None,
+            steps: 2,
+            last_site: LibSite::with(1, id),
+            calls: vec![],
+            stats: default!(),
+        });
+
+        let site = LibSite::with(0x10, id);
+        let lib = Lib::assemble::<Instr>(&[Instr::ControlFlow(ControlFlowOp::Call(site))]).unwrap();
+        let res = run(&lib);
+        assert!(res.is_call() && res.is_success());
+        assert_eq!((res.next, res.steps), (Some(site), 1));
+
+        let lib =
+            Lib::assemble::<Instr>(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Ret)]).unwrap();
+        assert_eq!(run(&lib).outcome, ExecOutcome::Success);
+
+        // Jump instruction truncated by the end of the code segment
+        let mut code = vec![0xFF; u16::MAX as usize - 1];
+        code.push(0x02);
+        let truncated = Lib::with("ALU", code, vec![], none!()).unwrap();
+        let mut regs = CoreRegs::default();
+        let res = truncated.run::<Instr>(u16::MAX - 2, &mut regs, &()).unwrap();
+        assert_eq!(res.outcome, ExecOutcome::DecodeFailure);
+        assert_eq!((res.steps, res.last_site.pos), (1, u16::MAX - 2));
+    }
+
+    #[test]
+    fn exec_stats() {
+        use crate::isa::{BytesOp, ControlFlowOp, Instr, PutOp};
+        use crate::reg::{Reg32, RegA, RegS};
+
+        let put = |s: u8, data: &[u8]| {
+            Instr::Bytes(BytesOp::Put(RegS::from(s), Box::new(ByteStr::with(data)), false))
+        };
+        let lib = Lib::assemble::<Instr>(&[
+            put(0, b"hello"),
+            put(1, b"world!"),
+            Instr::Put(PutOp::PutA(RegA::A32, Reg32::Reg0, Box::new(1u32.into()))),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ])
+        .unwrap();
+        let mut regs = CoreRegs::default();
+        let res = lib.run::<Instr>(0, &mut regs, &()).unwrap();
+        let stats =
+            ExecStats { peak_str_bytes: 11, str_bytes_written: 11, reg_writes: 3, data_reads: 3 };
+        assert_eq!(res.stats, stats);
+        assert_eq!(regs.stats(), stats);
+
+        // Strings left in the registers by the previous run count towards the peak, while the
+        // counters include only the resources used by the run
+        let lib = Lib::assemble::<Instr>(&[put(0, b"hi")]).unwrap();
+        let res = lib.run::<Instr>(0, &mut regs, &()).unwrap();
+        let stats =
+            ExecStats { peak_str_bytes: 11, str_bytes_written: 2, reg_writes: 1, data_reads: 1 };
+        assert_eq!(res.stats, stats);
+        assert_eq!(regs.stats().str_bytes_written, 13);
+
+        regs.reset_stats();
+        assert_eq!(regs.stats(), ExecStats { peak_str_bytes: 8, ..default!() });
+    }
+
+    #[test]
+    fn borrowed_segments() {
+        use crate::data::MaybeNumber;
+        use crate::isa::Instr;
+        use crate::reg::{Reg32, RegA};
+
+        let owned = Lib::assemble_source::<Instr>("put a16[0],7\ninc a16[0]\n").unwrap();
+        let storage = (owned.code.to_vec(), owned.data.to_vec());
+        let isae = owned.isae_segment();
+        let borrowed =
+            Lib::with_borrowed(&isae, &storage.0, &storage.1, owned.libs.clone()).unwrap();
+        assert!(matches!(borrowed.code, Cow::Borrowed(_)));
+        assert_eq!(borrowed.id(), owned.id());
+        assert_eq!(borrowed.disassemble::<Instr>(), owned.disassemble::<Instr>());
+
+        // Execution continues into the zero padding after the code end, which is decoded as
+        // `fail` instruction
+        let mut regs = CoreRegs::default();
+        let res = borrowed.run::<Instr>(0, &mut regs, &()).unwrap();
+        assert_eq!(res.outcome, ExecOutcome::Failure);
+        assert_eq!(res.last_site.pos, borrowed.code.len() as u16);
+        assert_eq!(regs.get(RegA::A16, Reg32::Reg0), MaybeNumber::from(8u16));
+
+        let owned_copy = borrowed.clone().into_owned();
+        assert!(matches!(owned_copy.code, Cow::Owned(_)));
+        assert_eq!(owned_copy, borrowed);
+
+        let large = vec![0u8; u16::MAX as usize + 1];
         assert_eq!(
-            format!("{id}"),
-            "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#pinball-eternal-colombo"
+            Lib::with_borrowed("ALU", &large, &[], none!()).unwrap_err(),
+            SegmentError::CodeSegmentTooLarge { len: large.len(), max: u16::MAX }
         );
         assert_eq!(
-            format!("{id:-}"),
-            "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"
+            Lib::with_borrowed("ALU", &[], &large, none!()).unwrap_err(),
+            SegmentError::DataSegmentTooLarge { len: large.len(), max: u16::MAX }
         );
+        assert!(Lib::with_borrowed("ALU", &large[1..], &large[1..], none!()).is_ok());
     }
 
     #[test]
-    fn lib_id_from_str() {
-        let id = LibId::with("FLOAT", &b"", &b"", &none!());
+    fn text_segments() {
+        use amplify::hex;
+
+        use crate::isa::Instr;
+
+        let lib = Lib::assemble_source::<Instr>("put a16[0],7\ninc a16[0]\nret\n").unwrap();
+        let isae = lib.isae_segment();
+        let hex_lib =
+            Lib::with_hex(&isae, &lib.code_hex(), &lib.data_hex(), lib.libs.clone()).unwrap();
+        assert_eq!(hex_lib.code_segment(), lib.code_segment());
+        assert_eq!(hex_lib.data_segment(), lib.data_segment());
+        assert_eq!(hex_lib.id(), lib.id());
+        assert_eq!(lib.code_hex(), format!("{:x}", ByteStr::with(lib.code_segment())));
+
         assert_eq!(
-            Ok(id),
-            LibId::from_str(
-                "urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#\
-                 pinball-eternal-colombo"
-            )
+            Lib::with_hex(&isae, "0", "", none!()).unwrap_err(),
+            SegmentParseError::Hex("code", hex::Error::OddLengthString(1))
         );
         assert_eq!(
-            Ok(id),
-            LibId::from_str("urn:ubideco:alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ")
+            Lib::with_hex(&isae, "", "zz", none!()).unwrap_err(),
+            SegmentParseError::Hex("data", hex::Error::InvalidChar(b'z'))
         );
+        let large = "00".repeat(u16::MAX as usize + 1);
         assert_eq!(
-            Ok(id),
-            LibId::from_str(
-                "alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ#pinball-eternal-colombo"
+            Lib::with_hex(&isae, &large, "", none!()).unwrap_err(),
+            SegmentParseError::Segment(SegmentError::CodeSegmentTooLarge {
+                len: u16::MAX as usize + 1,
+                max: u16::MAX
+            })
+        );
+
+        #[cfg(feature = "base64")]
+        {
+            let code = lib.code_base64();
+            let b64_lib = Lib::with_base64(&isae, &code, &lib.data_base64(), lib.libs.clone());
+            assert_eq!(b64_lib.unwrap().id(), lib.id());
+            assert_eq!(code, ByteStr::with(lib.code_segment()).to_base64());
+            assert!(matches!(
+                Lib::with_base64(&isae, &code, "*", none!()),
+                Err(SegmentParseError::Base64("data", _))
+            ));
+        }
+    }
+
+    #[test]
+    fn identity() {
+        use crate::isa::Instr;
+
+        let lib1 = Lib::assemble_source::<Instr>("put a8[0],1\nret\n").unwrap();
+        let lib2 = Lib::assemble_source::<Instr>("put a8[0],2\nret\n").unwrap();
+        let mut exported = lib1.clone();
+        exported.export("main", 0).unwrap();
+        let borrowed =
+            Lib::with_borrowed(&lib1.isae_segment(), &lib1.code, &lib1.data, lib1.libs.clone())
+                .unwrap();
+
+        assert_eq!(exported, lib1);
+        assert_eq!(borrowed.cmp(&lib1), Ordering::Equal);
+        assert_ne!(lib1, lib2);
+        assert_eq!(lib1.cmp(&lib2), lib1.id().cmp(&lib2.id()));
+
+        let set = BTreeSet::from([lib1.clone(), lib2.clone(), exported.clone()]);
+        assert_eq!(set.len(), 2);
+        #[cfg(feature = "std")]
+        {
+            let set = std::collections::HashSet::from([lib1, lib2, exported]);
+            assert_eq!(set.len(), 2);
+        }
+    }
+
+    #[test]
+    fn instrumented() {
+        use crate::data::encoding::{Decode, Encode, EncodeError};
+        use crate::isa::Instr;
+        use crate::library::DebugInfo;
+        use crate::reg::{Reg, Reg32, RegA};
+
+        let source = "
+            .var    counter a8[0]
+            put     a8[0],3
+        loop:
+            dec     a8[0]
+            put     a8[1],0
+            eq.n    a8[0],a8[1]
+            jif     end
+            jmp     loop
+        end:
+            succ
+        ";
+        let lib = Lib::assemble_instrumented::<Instr>(source).unwrap();
+        let plain = Lib::assemble_source::<Instr>(source).unwrap();
+        assert_eq!(plain.debug, None);
+        assert_eq!(lib.id(), plain.id());
+
+        let debug = lib.debug.as_ref().unwrap();
+        assert_eq!(debug.line_at(0), Some(3));
+        assert_eq!(debug.line_at(4), Some(5));
+        assert_eq!(debug.label_at(4), Some("loop"));
+        assert_eq!(debug.label_at(0x14), Some("end"));
+        assert_eq!(debug.var(Reg::A(RegA::A8, Reg32::Reg0)), Some("counter"));
+
+        let listing = lib.disassemble_listing::<Instr>().unwrap();
+        assert_eq!(listing.lines().take(3).collect::<Vec<_>>(), [
+            "0x0000: put     a8[0],3                 ; line 3, counter=a8[0]",
+            "loop:",
+            "0x0004: dec     a8[0]                   ; line 5, counter=a8[0]",
+        ]);
+        assert!(listing.contains("jmp     0x0004                  ; -> loop, line 9"));
+        assert!(!plain.disassemble_listing::<Instr>().unwrap().contains("loop"));
+
+        let decoded = Lib::deserialize(lib.serialize()).unwrap();
+        assert_eq!(decoded.debug, lib.debug);
+        assert_eq!(Lib::deserialize(plain.serialize()).unwrap().debug, None);
+
+        // Debug tables keyed by all the code offsets do not fit the encoding
+        let mut overfull = plain.clone();
+        let mut debug = DebugInfo::new();
+        debug.lines = (0..=u16::MAX).map(|pos| (pos, 1)).collect();
+        overfull.debug = Some(debug);
+        assert_eq!(
+            overfull.encode(Vec::new()),
+            Err(EncodeError::WordLimitExceeded(u16::MAX as usize + 1))
+        );
+
+        assert!(matches!(
+            Lib::assemble_instrumented::<Instr>(".var counter x8[0]"),
+            Err(SourceError::Parse { line: 1, source: ParseInstrError::InvalidVariable(_) })
+        ));
+
+        let mut patched = lib.clone();
+        patched.patch::<Instr>(0..0, &[Instr::Nop]).unwrap();
+        let debug = patched.debug.unwrap();
+        assert_eq!(debug.line_at(1), Some(3));
+        assert_eq!(debug.label_at(5), Some("loop"));
+    }
+
+    #[cfg(feature = "ct")]
+    #[test]
+    fn ct_eq() {
+        use subtle::ConstantTimeEq;
+
+        let mut bytes = [7u8; 32];
+        let id1 = LibId::from(bytes);
+        bytes[31] = 8;
+        let id2 = LibId::from(bytes);
+        assert!(bool::from(id1.ct_eq(&LibId::from([7u8; 32]))));
+        assert!(!bool::from(id1.ct_eq(&id2)));
+        assert_ne!(id1, id2);
+
+        let site = LibSite::with(1, id1);
+        assert_eq!(site, LibSite::with(1, id1));
+        assert_ne!(site, LibSite::with(2, id1));
+        assert_ne!(site, LibSite::with(1, id2));
+    }
+
+    #[test]
+    fn metadata() {
+        use crate::data::encoding::{Decode, Encode};
+        use crate::isa::Instr;
+        use crate::library::{LibMeta, SemVer};
+
+        let plain = Lib::assemble::<Instr>(&[Instr::Nop]).unwrap();
+        let mut lib = plain.clone();
+        lib.meta = Some(
+            LibMeta::with_name("checks")
+                .with_version(SemVer::new(1, 2, 0))
+                .with_author("Alice")
+                .with_description("Input checks"),
+        );
+        assert_eq!(lib.id(), plain.id());
+        assert!(lib.to_string().starts_with("META:   checks v1.2.0 by Alice: Input checks\nISAE:"));
+        assert!(plain.to_string().starts_with("ISAE:"));
+
+        let decoded = Lib::deserialize(lib.serialize()).unwrap();
+        assert_eq!(decoded.meta, lib.meta);
+        assert_eq!(Lib::deserialize(plain.serialize()).unwrap().meta, None);
+
+        lib.meta = Some(LibMeta::with_name("x".repeat(256)));
+        assert!(lib.encode(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn baseline_encoding() {
+        use amplify::hex::FromHex;
+
+        use crate::data::encoding::{Decode, DecodeError, Encode};
+        use crate::isa::Instr;
+
+        // Library encoded before the library tables were introduced
+        let data =
+            Vec::<u8>::from_hex("0c414c5520425044494745535405000b030000010800341200000000000000")
+                .unwrap();
+        let lib = Lib::deserialize(&data).unwrap();
+        assert_eq!(
+            lib.id().to_string(),
+            "urn:ubideco:alu:B4oJibmbgv18PtYcqco6zuVRg9bZUMHTdsscquVJQvxY#toyota-alias-poker"
+        );
+        assert_eq!(lib.disassemble::<Instr>().unwrap().len(), 2);
+        assert!(lib.routines.is_empty());
+        assert_eq!(lib.serialize(), data);
+
+        let mut exported = lib.clone();
+        exported.export("main", 0).unwrap();
+        let encoded = exported.serialize();
+        assert_eq!(&encoded[..data.len()], &data[..]);
+        assert_eq!(encoded[data.len()], 1);
+        assert_eq!(Lib::deserialize(&encoded).unwrap().entrypoint("main"), Some(0));
+
+        let mut encoded = encoded;
+        encoded[data.len()] = 2;
+        assert_eq!(Lib::deserialize(&encoded), Err(DecodeError::LibVersion(2)));
+    }
+
+    #[test]
+    fn routines() {
+        use crate::data::encoding::{Decode, Encode};
+        use crate::isa::Instr;
+
+        let source = "
+            .entry  main
+            put     a8[0],1
+            .entry  verify
+            put     a8[1],2
+            ret
+        ";
+        let mut lib = Lib::assemble_source::<Instr>(source).unwrap();
+        assert_eq!(lib.entrypoint("main"), Some(0));
+        assert_eq!(lib.entrypoint("verify"), Some(4));
+        assert_eq!(lib.entrypoint("other"), None);
+        assert_eq!(
+            lib.id(),
+            Lib::assemble_source::<Instr>("put a8[0],1\nput a8[1],2\nret").unwrap().id()
+        );
+
+        let decoded = Lib::deserialize(lib.serialize()).unwrap();
+        assert_eq!(decoded.routines, lib.routines);
+
+        assert_eq!(lib.export("verify", 8), Ok(Some(4)));
+        assert_eq!(
+            lib.export("exit", 9),
+            Err(RoutineError::OutOfCode { name: s!("exit"), pos: 9 })
+        );
+        assert_eq!(lib.export("1st", 0), Err(RoutineError::InvalidName(s!("1st"))));
+
+        lib.patch::<Instr>(0..0, &[Instr::Nop]).unwrap();
+        assert_eq!(lib.entrypoint("main"), Some(0));
+        assert_eq!(lib.entrypoint("verify"), Some(9));
+        assert_eq!(
+            lib.patch::<Instr>(5..10, &[]),
+            Err(PatchError::RoutineInPatch { name: s!("verify"), pos: 9 })
+        );
+
+        assert_eq!(
+            Lib::assemble_source::<Instr>(".entry main\nret\n.entry main\nret"),
+            Err(SourceError::Parse {
+                line: 3,
+                source: ParseInstrError::DuplicateRoutine(s!("main"))
+            })
+        );
+        assert_eq!(
+            Lib::assemble_source::<Instr>("ret\n.entry end"),
+            Err(SourceError::Routine(RoutineError::OutOfCode { name: s!("end"), pos: 1 }))
+        );
+    }
+
+    #[test]
+    fn abis() {
+        use crate::data::encoding::{Decode, Encode};
+        use crate::data::Layout;
+        use crate::isa::Instr;
+        use crate::library::{Abi, AbiError, AbiParam};
+        use crate::reg::{Reg, Reg32, RegA, RegS};
+        use crate::{Prog, Vm};
+
+        let source = "
+            .entry  main
+            add.uc  a16[0],a16[1]
+            ret
+        ";
+        let mut lib = Lib::assemble_source::<Instr>(source).unwrap();
+        let (a0, a1) = (Reg::A(RegA::A16, Reg32::Reg0), Reg::A(RegA::A16, Reg32::Reg1));
+        let abi = Abi::new([AbiParam::new(a0), AbiParam::with(a1, Layout::signed(2))], [
+            AbiParam::new(a1),
+            AbiParam::new(Reg::S(RegS::from(15))),
+        ])
+        .unwrap();
+        assert_eq!(
+            lib.set_abi("verify", abi.clone()),
+            Err(RoutineError::NotExported(s!("verify")))
+        );
+        assert_eq!(lib.set_abi("main", abi.clone()), Ok(None));
+        assert_eq!(lib.abi("main"), Some(&abi));
+        assert_eq!(lib.abis_at(0).collect::<Vec<_>>(), [&abi]);
+
+        let decoded = Lib::deserialize(lib.serialize()).unwrap();
+        assert_eq!(decoded.abis, lib.abis);
+
+        let site = LibSite::with(0, lib.id());
+        let prog = Prog::<Instr>::new(lib);
+        let mut vm = Vm::<Instr>::new();
+        let err = vm.check_inputs(&prog, site).unwrap_err();
+        assert_eq!(err, RunError::Abi(AbiError::InputNotSet(a0), site));
+        assert_eq!(err.site(), Some(site));
+        // Descriptors do not affect the execution
+        assert_eq!(vm.try_run(&prog, &()), Ok(false));
+
+        vm.registers.set(RegA::A16, Reg32::Reg0, 1u16);
+        vm.registers.set(RegA::A16, Reg32::Reg1, 2u16);
+        assert_eq!(vm.check_inputs(&prog, site), Ok(()));
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+        let s15 = Reg::S(RegS::from(15));
+        assert_eq!(
+            vm.check_outputs(&prog, site),
+            Err(RunError::Abi(AbiError::OutputNotSet(s15), site))
+        );
+    }
+
+    #[test]
+    fn assemble_dry_run() {
+        use crate::isa::{BytesOp, ControlFlowOp, Instr};
+        use crate::reg::RegS;
+
+        let put = |idx: u8, data: &[u8]| {
+            Instr::Bytes(BytesOp::Put(RegS::from(idx), Box::new(ByteStr::with(data)), false))
+        };
+        let code: Vec<Instr> = vec![
+            put(0, b"hello"),
+            put(1, b"ell"),
+            put(2, b"world"),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ];
+        let sizes = Lib::assemble_dry_run(&code).unwrap();
+        let lib = Lib::assemble(&code).unwrap();
+        assert_eq!(sizes, SegmentSizes { code: lib.code.len(), data: lib.data.len(), libs: 0 });
+        assert_eq!(
+            sizes.code,
+            code.iter().map(|instr| instr.encoded_len() as usize).sum::<usize>()
+        );
+        assert!(sizes.fits());
+
+        let chunk = [0xA5u8; 0x8000];
+        let code = [put(0, &chunk), put(1, &chunk[1..]), put(2, &[0x5A; 0x8001])];
+        let sizes = Lib::assemble_dry_run(&code).unwrap();
+        assert_eq!(sizes.data, 0x10001);
+        assert!(!sizes.fits());
+        assert!(Lib::assemble(&code).is_err());
+    }
+
+    #[test]
+    fn try_run_decode_error() {
+        use crate::isa::Instr;
+
+        let mut code = vec![0xFF; u16::MAX as usize - 1];
+        code.push(0x02);
+        let lib = Lib::with("ALU", code, vec![], none!()).unwrap();
+        let entrypoint = u16::MAX - 3;
+
+        let mut regs = CoreRegs::default();
+        let res = lib.run::<Instr>(entrypoint, &mut regs, &()).unwrap();
+        assert_eq!((res.outcome, res.steps), (ExecOutcome::DecodeFailure, 2));
+
+        let mut regs = CoreRegs::default();
+        assert_eq!(
+            lib.try_run::<Instr>(entrypoint, &mut regs, &()),
+            Err(RunError::Decode(
+                DisassembleError::CodeTruncated {
+                    pos: u16::MAX - 1,
+                    opcode: 0x02,
+                    failed_at: u16::MAX
+                },
+                LibSite::with(u16::MAX - 1, lib.id())
+            ))
+        );
+        assert_eq!(regs.steps(), 2);
+    }
+
+    #[test]
+    fn entrypoint_validation() {
+        use crate::isa::Instr;
+
+        let lib = Lib::assemble_source::<Instr>("put a16[0],5\nsucc\n").unwrap();
+        assert_eq!(lib.check_entrypoint::<Instr>(0), Ok(()));
+        assert_eq!(lib.check_entrypoint::<Instr>(4), Ok(()));
+        assert_eq!(
+            lib.check_entrypoint::<Instr>(2),
+            Err(EntrypointError::InsideInstruction { pos: 2, instr_pos: 0 })
+        );
+        assert_eq!(
+            lib.check_entrypoint::<Instr>(5),
+            Err(EntrypointError::OutOfCode { pos: 5, code_len: 5 })
+        );
+
+        let mut regs = CoreRegs::default();
+        let err = lib.run::<Instr>(2, &mut regs, &()).unwrap_err();
+        assert_eq!(
+            err,
+            RunError::Entrypoint(
+                EntrypointError::InsideInstruction { pos: 2, instr_pos: 0 },
+                LibSite::with(2, lib.id())
             )
         );
-        assert_eq!(Ok(id), LibId::from_str("alu:GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"));
+        assert_eq!(regs.steps(), 0);
+        assert!(matches!(
+            lib.try_run::<Instr>(u16::MAX, &mut regs, &()),
+            Err(RunError::Entrypoint(EntrypointError::OutOfCode { .. }, _))
+        ));
+        assert!(lib.run::<Instr>(4, &mut regs, &()).unwrap().is_success());
 
-        assert_eq!(Ok(id), LibId::from_str("GrjjwmeTsibiEeYYtjokmc8j4Jn1KWL2SX8NugG6T5kZ"));
+        // Baseline execution API doesn't check the entry point
+        let mut regs = CoreRegs::default();
+        lib.exec::<Instr>(2, &mut regs, &());
+        assert!(regs.steps() > 0);
+    }
+
+    #[test]
+    fn entrypoint_external_only() {
+        use crate::isa::{ControlFlowOp, Instr};
+        use crate::{Prog, Vm};
+
+        let callee = Lib::assemble_source::<Instr>("put a16[0],5\nsucc\n").unwrap();
+        let caller = Lib::assemble::<Instr>(&[
+            Instr::ControlFlow(ControlFlowOp::Call(LibSite::with(2, callee.id()))),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ])
+        .unwrap();
+        let (caller_id, callee_id) = (caller.id(), callee.id());
+        let mut prog = Prog::<Instr>::new(caller);
+        prog.add_lib(callee).unwrap();
+
+        // Calls into other libraries are not checked to land at the start of an instruction
+        let mut vm = Vm::<Instr>::new();
+        let res = vm.try_call(&prog, LibSite::with(0, caller_id), &());
+        assert!(!matches!(res, Err(RunError::Entrypoint(..))));
+        assert!(vm.registers.steps() > 1);
+
+        let site = LibSite::with(2, callee_id);
+        assert_eq!(
+            Vm::<Instr>::new().try_call(&prog, site, &()),
+            Err(RunError::Entrypoint(
+                EntrypointError::InsideInstruction { pos: 2, instr_pos: 0 },
+                site
+            ))
+        );
     }
 }
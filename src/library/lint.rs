@@ -0,0 +1,480 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Static checks of library code detecting suspicious instruction sequences.
+//!
+//! Each check is a [`LintRule`] which may be allowed, or report its findings as warnings or
+//! errors, as configured with [`Linter`]. The resulting [`Diagnostic`]s carry the offset of the
+//! instruction in the code segment, such that they can be reported by the tooling of contract
+//! authors, and [`is_denied`] allows CI pipelines to fail on errors.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::isa::opcodes::{INSTR_FAIL, INSTR_SUCC};
+use crate::isa::InstructionSet;
+use crate::library::{DisassembleError, Lib};
+use crate::reg::Reg;
+
+/// Severity of a diagnostic reported by a lint rule
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum Severity {
+    /// The finding is reported, but does not prevent the code from being used
+    #[display("warning")]
+    Warn,
+
+    /// The finding is an error which must be fixed
+    #[display("error")]
+    Deny,
+}
+
+/// Rules checked by the [`Linter`]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+pub enum LintRule {
+    /// Instruction following an unconditional jump, return or program completion which is not a
+    /// target of any jump and not a declared entry point.
+    #[display("unreachable-code")]
+    UnreachableCode,
+
+    /// Jump targeting an offset which is not a start of an instruction: either the interior of
+    /// another instruction (including the data segment references of `put` instructions) or an
+    /// offset outside of the code segment.
+    #[display("jump-into-instruction")]
+    JumpIntoInstruction,
+
+    /// Register written by an instruction which is overwritten by the following instructions
+    /// before being read, or is not read before the program completes with `succ` or `fail`.
+    ///
+    /// The rule analyzes only straight-line code, and may report registers which are read by the
+    /// host after the program completion, or by extension instructions which do not report their
+    /// source registers via [`InstructionSet::src_regs`].
+    #[display("unused-write")]
+    UnusedWrite,
+
+    /// Instruction with a [loop bound](crate::isa::Bytecode::loop_bound) for which the bound does
+    /// not hold: the loop does not jump backward, or its body may write the loop counter, directly
+    /// or by calling other routines.
+    #[display("unbounded-loop")]
+    UnboundedLoop,
+}
+
+impl LintRule {
+    /// All lint rules
+    pub const ALL: [LintRule; 4] = [
+        LintRule::UnreachableCode,
+        LintRule::JumpIntoInstruction,
+        LintRule::UnusedWrite,
+        LintRule::UnboundedLoop,
+    ];
+
+    /// Severity with which the rule is reported by [`Linter::default`].
+    pub fn default_severity(self) -> Severity {
+        match self {
+            LintRule::UnreachableCode => Severity::Warn,
+            LintRule::JumpIntoInstruction => Severity::Deny,
+            LintRule::UnusedWrite => Severity::Warn,
+            LintRule::UnboundedLoop => Severity::Deny,
+        }
+    }
+}
+
+/// Finding reported by a lint rule
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{severity}[{rule}] at {pos:#06X}: {message}")]
+pub struct Diagnostic {
+    /// Offset of the instruction in the code segment
+    pub pos: u16,
+
+    /// Rule which produced the diagnostic
+    pub rule: LintRule,
+
+    /// Severity of the diagnostic
+    pub severity: Severity,
+
+    /// Human-readable description of the finding
+    pub message: String,
+}
+
+/// Checks whether any of the diagnostics is an error.
+pub fn is_denied(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Deny)
+}
+
+/// Returns registers written by the instruction which are not read by the straight-line code
+/// following it: either overwritten before being read, or not read up to the program completion.
+/// If the code branches, calls other routine or returns before completion, only the overwritten
+/// registers are returned.
+fn unused_writes<'a, Isa>(instr: &Isa, next: impl Iterator<Item = &'a Isa>) -> BTreeSet<Reg>
+where
+    Isa: InstructionSet + 'a,
+{
+    let mut unused = instr.dst_regs();
+    let mut overwritten = BTreeSet::new();
+    for instr in next {
+        if unused.is_empty() {
+            break;
+        }
+        if matches!(instr.instr_byte(), INSTR_FAIL | INSTR_SUCC) {
+            overwritten.extend(unused);
+            return overwritten;
+        }
+        let jumps = instr.jump_target().is_some()
+            || instr.jump_displacement().is_some()
+            || instr.jump_table().is_some();
+        if instr.is_terminal() || jumps || instr.call_site().is_some() {
+            break;
+        }
+        for reg in instr.src_regs() {
+            unused.remove(&reg);
+        }
+        for reg in instr.dst_regs() {
+            if unused.remove(&reg) {
+                overwritten.insert(reg);
+            }
+        }
+    }
+    overwritten
+}
+
+/// Checks whether the loop closed by the instruction at `pos`, preceded by the `code`, may run
+/// more iterations than its [loop bound](crate::isa::Bytecode::loop_bound), returning the reason.
+fn loop_violation<Isa>(pos: u16, instr: &Isa, code: &[(u16, Isa)]) -> Option<String>
+where
+    Isa: InstructionSet,
+{
+    let start = match instr.jump_destination(pos) {
+        Some(start) if start < pos => start,
+        Some(start) => return Some(format!("loop jumps forward to {:#06X}", start)),
+        None => return Some(s!("loop has no jump destination")),
+    };
+    let counters = instr.dst_regs();
+    code.iter().filter(|(body_pos, _)| *body_pos >= start).find_map(|(body_pos, body)| {
+        if body.is_routine_call() || body.call_site().is_some() {
+            Some(format!(
+                "loop body calls a routine at {:#06X}, which may write the counter",
+                body_pos
+            ))
+        } else {
+            let reg = body.dst_regs().intersection(&counters).next().copied()?;
+            Some(format!("loop counter {} is written by the loop body at {:#06X}", reg, body_pos))
+        }
+    })
+}
+
+/// Configurable set of lint rules.
+///
+/// The default configuration enables all the rules with their
+/// [default severities](LintRule::default_severity); code offset 0 is the only known entry point.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Linter {
+    rules: BTreeMap<LintRule, Severity>,
+    entries: BTreeSet<u16>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter {
+            rules: LintRule::ALL.iter().map(|rule| (*rule, rule.default_severity())).collect(),
+            entries: BTreeSet::from([0]),
+        }
+    }
+}
+
+impl Linter {
+    /// Constructs linter with the default configuration.
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Reports findings of the rule as warnings.
+    pub fn warn(&mut self, rule: LintRule) -> &mut Self {
+        self.rules.insert(rule, Severity::Warn);
+        self
+    }
+
+    /// Reports findings of the rule as errors.
+    pub fn deny(&mut self, rule: LintRule) -> &mut Self {
+        self.rules.insert(rule, Severity::Deny);
+        self
+    }
+
+    /// Disables the rule.
+    pub fn allow(&mut self, rule: LintRule) -> &mut Self {
+        self.rules.remove(&rule);
+        self
+    }
+
+    /// Declares code offset at which the library may be called from other libraries or the host,
+    /// such that it is not reported as unreachable.
+    pub fn entry(&mut self, pos: u16) -> &mut Self {
+        self.entries.insert(pos);
+        self
+    }
+
+    /// Returns severity of the rule, or `None` if the rule is disabled.
+    #[inline]
+    pub fn severity(&self, rule: LintRule) -> Option<Severity> { self.rules.get(&rule).copied() }
+
+    /// Checks library code, returning diagnostics ordered by their code offset and rule.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the library code can't be decoded with the instruction set `Isa`.
+    pub fn check<Isa>(&self, lib: &Lib) -> Result<Vec<Diagnostic>, DisassembleError>
+    where
+        Isa: InstructionSet,
+    {
+        let code = lib.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+        let code_len = lib.code.len();
+
+        let mut diagnostics = vec![];
+        let mut report = |pos: u16, rule: LintRule, message: String| {
+            if let Some(severity) = self.severity(rule) {
+                diagnostics.push(Diagnostic { pos, rule, severity, message });
+            }
+        };
+
+        let boundaries = code.iter().map(|(pos, _)| *pos).collect::<BTreeSet<_>>();
+        let targets = code
+            .iter()
+            .flat_map(|(pos, instr)| instr.jump_destinations(*pos))
+            .collect::<BTreeSet<_>>();
+
+        for (no, (pos, instr)) in code.iter().enumerate() {
+            if let (None, Some(shift)) = (instr.jump_destination(*pos), instr.jump_displacement()) {
+                report(
+                    *pos,
+                    LintRule::JumpIntoInstruction,
+                    format!("jump by {:+} is outside of the code segment", shift),
+                );
+            }
+            for target in instr.jump_destinations(*pos) {
+                if target as usize >= code_len {
+                    report(
+                        *pos,
+                        LintRule::JumpIntoInstruction,
+                        format!("jump to {:#06X} is outside of the code segment", target),
+                    );
+                } else if !boundaries.contains(&target) {
+                    let host = boundaries.range(..target).next_back().copied().unwrap_or_default();
+                    report(
+                        *pos,
+                        LintRule::JumpIntoInstruction,
+                        format!(
+                            "jump to {:#06X} targets the interior of the instruction at {:#06X}",
+                            target, host
+                        ),
+                    );
+                }
+            }
+
+            if instr.is_terminal() {
+                if let Some((next, _)) = code.get(no + 1) {
+                    if !targets.contains(next) && !self.entries.contains(next) {
+                        report(
+                            *next,
+                            LintRule::UnreachableCode,
+                            format!("code is unreachable after `{}`", instr),
+                        );
+                    }
+                }
+            }
+
+            if instr.loop_bound().is_some() {
+                if let Some(message) = loop_violation(*pos, instr, &code[..no]) {
+                    report(*pos, LintRule::UnboundedLoop, message);
+                }
+            }
+
+            for reg in unused_writes(instr, code[no + 1..].iter().map(|(_, next)| next)) {
+                report(
+                    *pos,
+                    LintRule::UnusedWrite,
+                    format!("value written to {} is never read", reg),
+                );
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.pos, diagnostic.rule));
+        Ok(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::Instr;
+
+    fn lint(linter: &Linter, source: &str) -> Vec<(u16, LintRule, Severity)> {
+        let lib = Lib::assemble_source::<Instr>(source).unwrap();
+        linter
+            .check::<Instr>(&lib)
+            .unwrap()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.pos, diagnostic.rule, diagnostic.severity))
+            .collect()
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = "
+            put     a8[0],1
+            jmp     0x0008
+            ret
+            ret
+        ";
+        assert_eq!(lint(&Linter::new(), source), [(
+            0x0007,
+            LintRule::UnreachableCode,
+            Severity::Warn
+        )]);
+        assert_eq!(lint(Linter::new().entry(0x0007), source), []);
+        assert_eq!(lint(Linter::new().allow(LintRule::UnreachableCode), source), []);
+    }
+
+    #[test]
+    fn jump_into_instruction() {
+        let source = "
+            jif     0x0004
+            put     a16[0],5
+            ret
+            jmp     0x0100
+        ";
+        let lib = Lib::assemble_source::<Instr>(source).unwrap();
+        let diagnostics = Linter::new().check::<Instr>(&lib).unwrap();
+        assert!(is_denied(&diagnostics));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[jump-into-instruction] at 0x0000: jump to 0x0004 targets the interior of the \
+             instruction at 0x0003"
+        );
+        assert_eq!(lint(&Linter::new(), source), [
+            (0x0000, LintRule::JumpIntoInstruction, Severity::Deny),
+            (0x0008, LintRule::UnreachableCode, Severity::Warn),
+            (0x0008, LintRule::JumpIntoInstruction, Severity::Deny),
+        ]);
+        let mut linter = Linter::new();
+        linter.warn(LintRule::JumpIntoInstruction).allow(LintRule::UnreachableCode);
+        let diagnostics = linter.check::<Instr>(&lib).unwrap();
+        assert!(!is_denied(&diagnostics));
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn jump_table() {
+        use crate::isa::SwitchOp;
+
+        let source = "
+            switch  a8[0],0x0006,0x0007,0x0006,0x0007
+            ret
+            ret
+        ";
+        let mut lib = Lib::assemble_source::<Instr<SwitchOp>>(source).unwrap();
+        // The assembler rejects invalid tables, so we corrupt the table in the data segment
+        lib.data.to_mut()[4..].copy_from_slice(&[0x05, 0x00, 0x00, 0x01]);
+        let diagnostics = Linter::new().check::<Instr<SwitchOp>>(&lib).unwrap();
+        assert_eq!(diagnostics.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+            "error[jump-into-instruction] at 0x0000: jump to 0x0005 targets the interior of the \
+             instruction at 0x0000",
+            "error[jump-into-instruction] at 0x0000: jump to 0x0100 is outside of the code segment"
+        ]);
+    }
+
+    #[test]
+    fn unbounded_loop() {
+        use crate::isa::LoopOp;
+
+        let lint = |source: &str| {
+            let lib = Lib::assemble_source::<Instr<LoopOp>>(source).unwrap();
+            let diagnostics = Linter::new().check::<Instr<LoopOp>>(&lib).unwrap();
+            diagnostics
+                .into_iter()
+                .filter(|diagnostic| diagnostic.rule == LintRule::UnboundedLoop)
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>()
+        };
+        let source = "
+            put     a16[1],0
+            body:
+            add.uc  a16[2],a16[1]
+            loop    a16[0],10,body
+            ret
+        ";
+        assert!(lint(source).is_empty());
+
+        let source = "
+            body:
+            put     a16[0],5
+            loop    a16[0],10,body
+            ret
+        ";
+        assert_eq!(lint(source), ["error[unbounded-loop] at 0x0004: loop counter a16[0] is \
+                                   written by the loop body at 0x0000"]);
+
+        let source = "
+            body:
+            routine 0x0000
+            loop    a16[0],10,body
+            ret
+        ";
+        assert_eq!(lint(source), ["error[unbounded-loop] at 0x0003: loop body calls a routine \
+                                   at 0x0000, which may write the counter"]);
+
+        let source = "
+            loop    a16[0],10,end
+            end:
+            ret
+        ";
+        assert_eq!(lint(source), ["error[unbounded-loop] at 0x0000: loop jumps forward to 0x0006"]);
+    }
+
+    #[test]
+    fn unused_write() {
+        let source = "
+            put     a8[0],1
+            put     a8[1],2
+            add.uc  a8[0],a8[1]
+            put     a16[0],3
+            succ
+        ";
+        assert_eq!(lint(&Linter::new(), source), [
+            (0x0008, LintRule::UnusedWrite, Severity::Warn),
+            (0x000B, LintRule::UnusedWrite, Severity::Warn),
+        ]);
+        let source = "
+            put     a8[0],1
+            jif     0x0008
+            succ
+            ret
+        ";
+        assert_eq!(lint(Linter::new().deny(LintRule::UnusedWrite), source), []);
+        let source = "
+            put     a8[0],1
+            put     a8[0],2
+            jif     0x000C
+            succ
+            ret
+        ";
+        assert_eq!(lint(&Linter::new(), source), [(0x0000, LintRule::UnusedWrite, Severity::Warn)]);
+    }
+}
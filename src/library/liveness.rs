@@ -0,0 +1,217 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Liveness analysis of the registers used by library code.
+//!
+//! A register is live at some point of the code if its value may be read by the instructions
+//! executed afterwards, before it is overwritten. [`analyze_liveness`] computes the registers
+//! live before and after each of the instructions over the control flow graph of the library (see
+//! [`Cfg`]), using the registers reported by [`InstructionSet::src_regs`] and
+//! [`InstructionSet::dst_regs`]. Registers live at an entry point are read before any value is
+//! written into them by the code, and thus must be set by the caller.
+//!
+//! The analysis is limited to the library code: calls to other libraries and returns are assumed
+//! to read no registers, and registers read by the host after the program completion are not
+//! live. Routine calls within the library may either pass the control to the routine or to the
+//! following instruction, which over-approximates the registers live after the call.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use crate::export::Cfg;
+use crate::isa::InstructionSet;
+use crate::library::{DisassembleError, Lib};
+use crate::reg::Reg;
+
+/// Registers live around an instruction
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct InstrLiveness {
+    /// Registers read by the instruction
+    pub uses: BTreeSet<Reg>,
+
+    /// Registers written by the instruction
+    pub defs: BTreeSet<Reg>,
+
+    /// Registers live before the instruction execution
+    pub live_in: BTreeSet<Reg>,
+
+    /// Registers live after the instruction execution
+    pub live_out: BTreeSet<Reg>,
+}
+
+/// Liveness of the registers at each instruction of library code, computed by
+/// [`analyze_liveness`]
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Liveness {
+    instrs: BTreeMap<u16, InstrLiveness>,
+}
+
+impl Liveness {
+    /// Returns liveness information of the instruction at the code offset `pos`, if any
+    #[inline]
+    pub fn at(&self, pos: u16) -> Option<&InstrLiveness> { self.instrs.get(&pos) }
+
+    /// Returns iterator over the code offsets of the instructions and their liveness information
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &InstrLiveness)> {
+        self.instrs.iter().map(|(pos, liveness)| (*pos, liveness))
+    }
+
+    /// Returns registers which are read by the code starting at the entry point `pos` before any
+    /// value is written into them, and thus must be initialized by the caller. Returns an empty
+    /// set if there is no instruction at `pos`.
+    pub fn inputs(&self, pos: u16) -> BTreeSet<Reg> {
+        self.at(pos).map(|liveness| liveness.live_in.clone()).unwrap_or_default()
+    }
+
+    /// Returns registers which are read by some of the instructions, but are not written by any of
+    /// them.
+    pub fn never_written(&self) -> BTreeSet<Reg> {
+        let defs =
+            self.instrs.values().flat_map(|liveness| &liveness.defs).collect::<BTreeSet<_>>();
+        self.instrs
+            .values()
+            .flat_map(|liveness| &liveness.uses)
+            .filter(|reg| !defs.contains(reg))
+            .copied()
+            .collect()
+    }
+}
+
+/// Computes liveness of the registers at each of the library instructions, decoded with the
+/// instruction set `Isa`.
+///
+/// # Errors
+///
+/// If the library code can't be decoded with the provided instruction set.
+pub fn analyze_liveness<Isa>(lib: &Lib) -> Result<Liveness, DisassembleError>
+where
+    Isa: InstructionSet,
+{
+    let cfg = Cfg::with::<Isa>(lib)?;
+    let code = lib.disassemble_iter::<Isa>().collect::<Result<Vec<_>, _>>()?;
+
+    // Instructions of each block, in the order of their offsets
+    let blocks = cfg
+        .blocks
+        .iter()
+        .map(|block| {
+            code.iter()
+                .filter(|(pos, _)| block.start <= *pos && *pos < block.end)
+                .map(|(pos, instr)| {
+                    let liveness = InstrLiveness {
+                        uses: instr.src_regs(),
+                        defs: instr.dst_regs(),
+                        ..default!()
+                    };
+                    (*pos, liveness)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let successors = cfg
+        .blocks
+        .iter()
+        .map(|block| {
+            block
+                .successors
+                .iter()
+                .filter_map(|pos| cfg.blocks.iter().position(|block| block.start == *pos))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // Registers live at the starts of the blocks, iterated until the fixed point is reached
+    let mut live_in = vec![BTreeSet::<Reg>::new(); blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in (0..blocks.len()).rev() {
+            let mut live = successors[index]
+                .iter()
+                .flat_map(|succ| live_in[*succ].iter().copied())
+                .collect::<BTreeSet<_>>();
+            for (_, instr) in blocks[index].iter().rev() {
+                live.retain(|reg| !instr.defs.contains(reg));
+                live.extend(&instr.uses);
+            }
+            if live != live_in[index] {
+                live_in[index] = live;
+                changed = true;
+            }
+        }
+    }
+
+    let mut instrs = BTreeMap::new();
+    for (index, block) in blocks.into_iter().enumerate() {
+        let mut live = successors[index]
+            .iter()
+            .flat_map(|succ| live_in[*succ].iter().copied())
+            .collect::<BTreeSet<_>>();
+        for (pos, mut instr) in block.into_iter().rev() {
+            instr.live_out = live.clone();
+            live.retain(|reg| !instr.defs.contains(reg));
+            live.extend(&instr.uses);
+            instr.live_in = live.clone();
+            instrs.insert(pos, instr);
+        }
+    }
+
+    Ok(Liveness { instrs })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::Instr;
+    use crate::reg::{Reg32, RegA};
+
+    #[test]
+    fn liveness() {
+        let lib = Lib::assemble_source::<Instr>(
+            "
+            put     a8[1],1
+        loop:
+            add.uc  a8[0],a8[1]
+            jif     loop
+            mov     a8[1],a8[2]
+            add.uc  a8[3],a8[2]
+            succ
+        ",
+        )
+        .unwrap();
+        let liveness = analyze_liveness::<Instr>(&lib).unwrap();
+        let reg = |idx: Reg32| Reg::A(RegA::A8, idx);
+
+        assert_eq!(liveness.inputs(0), BTreeSet::from([reg(Reg32::Reg0), reg(Reg32::Reg3)]));
+        let add = liveness.at(4).unwrap();
+        assert_eq!(
+            add.live_in,
+            BTreeSet::from([reg(Reg32::Reg0), reg(Reg32::Reg1), reg(Reg32::Reg3)])
+        );
+        assert!(add.live_out.contains(&reg(Reg32::Reg1)));
+        assert_eq!(liveness.never_written(), BTreeSet::from([reg(Reg32::Reg0), reg(Reg32::Reg3)]));
+        assert!(liveness.at(liveness.iter().last().unwrap().0).unwrap().live_out.is_empty());
+        assert_eq!(liveness.at(1), None);
+    }
+}
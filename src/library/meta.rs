@@ -0,0 +1,180 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Descriptive metadata of the libraries.
+//!
+//! [`LibMeta`] allows humans to identify a library beyond its [`LibId`](super::LibId): it keeps
+//! the library name, [`SemVer`] version, author and description. The metadata are kept in
+//! [`Lib::meta`](super::Lib::meta) and are serialized together with the library, but are not
+//! committed to by the library id, thus they must not be trusted unless the library comes from a
+//! trusted source.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+
+/// Semantic version of a library, consisting of major, minor and patch version numbers.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+#[display("{major}.{minor}.{patch}")]
+pub struct SemVer {
+    /// Major version, changed on incompatible changes of the library routines
+    pub major: u16,
+    /// Minor version, changed when routines are added in a compatible way
+    pub minor: u16,
+    /// Patch version, changed on compatible fixes
+    pub patch: u16,
+}
+
+impl SemVer {
+    /// Constructs version from its components.
+    #[inline]
+    pub const fn new(major: u16, minor: u16, patch: u16) -> Self { SemVer { major, minor, patch } }
+}
+
+/// Errors parsing [`SemVer`] from a string
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display("invalid semantic version `{0}`, which must have `major.minor.patch` form")]
+pub struct SemVerParseError(pub String);
+
+impl FromStr for SemVer {
+    type Err = SemVerParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.').map(u16::from_str);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) => {
+                Ok(SemVer { major, minor, patch })
+            }
+            _ => Err(SemVerParseError(s.to_owned())),
+        }
+    }
+}
+
+/// Descriptive metadata of a library.
+///
+/// Each of the string fields is limited to 255 bytes by the library serialization format, and
+/// empty strings are serialized as absent values.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct LibMeta {
+    /// Human-readable name of the library
+    pub name: Option<String>,
+    /// Version of the library
+    pub version: Option<SemVer>,
+    /// Author of the library
+    pub author: Option<String>,
+    /// Short description of the library
+    pub description: Option<String>,
+}
+
+impl LibMeta {
+    /// Constructs metadata with the library name only.
+    pub fn with_name(name: impl Into<String>) -> Self {
+        LibMeta { name: Some(name.into()), ..default!() }
+    }
+
+    /// Sets version of the library.
+    pub fn with_version(mut self, version: SemVer) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets author of the library.
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Sets description of the library.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Detects whether none of the metadata fields are set.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.version.is_none()
+            && self.author.is_none()
+            && self.description.is_none()
+    }
+}
+
+/// Displays metadata in a single line, like `name v1.0.0 by author: description`.
+impl Display for LibMeta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        if let Some(name) = &self.name {
+            f.write_str(name)?;
+            sep = " ";
+        }
+        if let Some(version) = &self.version {
+            write!(f, "{sep}v{version}")?;
+            sep = " ";
+        }
+        if let Some(author) = &self.author {
+            write!(f, "{sep}by {author}")?;
+        }
+        if let Some(description) = &self.description {
+            let sep = if self.name.is_some() || self.version.is_some() || self.author.is_some() {
+                ": "
+            } else {
+                ""
+            };
+            write!(f, "{sep}{description}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn semver() {
+        assert_eq!(SemVer::from_str("1.20.3"), Ok(SemVer::new(1, 20, 3)));
+        assert_eq!(SemVer::new(1, 20, 3).to_string(), "1.20.3");
+        for invalid in ["", "1.2", "1.2.3.4", "1.2.x", "1.2.65536", "v1.2.3"] {
+            assert_eq!(SemVer::from_str(invalid), Err(SemVerParseError(invalid.to_owned())));
+        }
+    }
+
+    #[test]
+    fn display() {
+        let meta = LibMeta::with_name("bp-core")
+            .with_version(SemVer::new(0, 10, 1))
+            .with_author("LNP/BP Standards Association")
+            .with_description("Bitcoin protocol validation routines");
+        assert_eq!(
+            meta.to_string(),
+            "bp-core v0.10.1 by LNP/BP Standards Association: Bitcoin protocol validation routines"
+        );
+        assert_eq!(LibMeta::default().to_string(), "");
+        assert!(LibMeta::default().is_empty());
+        let meta = LibMeta { description: Some(s!("helpers")), ..default!() };
+        assert_eq!(meta.to_string(), "helpers");
+    }
+}
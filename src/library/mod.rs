@@ -23,13 +23,40 @@
 
 //! Business logic and data structures for working with AluVM code libraries
 
+mod abi;
+mod builder;
 pub mod constants;
 mod cursor;
+mod debug;
+pub mod diff;
+pub mod intrinsics;
 mod lib;
+pub mod lint;
+pub mod liveness;
+mod meta;
 mod rw;
 mod segs;
+mod stats;
+pub mod store;
+mod xref;
 
-pub use cursor::Cursor;
-pub use lib::{AssemblerError, Lib, LibId, LibSite};
+pub use abi::{Abi, AbiError, AbiParam, AbiValue};
+pub use builder::LibBuilder;
+pub use cursor::{Cursor, ExecCursor};
+pub use debug::{DebugInfo, SourceMap, SourceSpan};
+pub use lib::{
+    AssembleOpts, AssemblerError, DataTable, DisassembleError, Disassembled, Disassembler,
+    EntrypointError, ExecOutcome, ExecResult, Lib, LibId, LibSite, PatchError, RoutineError,
+    SegmentSizes, StatsError, StreamingError,
+};
+pub use meta::{LibMeta, SemVer, SemVerParseError};
 pub use rw::{CodeEofError, Read, Write, WriteError};
-pub use segs::{IsaSeg, IsaSegError, LibSeg, LibSegOverflow, SegmentError};
+pub use segs::{
+    IsaId, IsaSeg, IsaSegError, LibSeg, LibSegOverflow, SegmentError, SegmentLimits,
+    SegmentParseError,
+};
+pub use stats::{InstrClass, InstrSize, LibStats, LARGEST_INSTR_COUNT};
+#[cfg(feature = "std")]
+pub use store::{DirStore, DirStoreError};
+pub use store::{LibStore, MemStore};
+pub use xref::DataMap;
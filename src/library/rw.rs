@@ -21,12 +21,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::collections::BTreeSet;
+
 use amplify::num::{u1, u2, u24, u3, u4, u5, u6, u7};
 
 use super::LibId;
 use crate::data::Number;
-use crate::isa::{Instr, InstructionSet};
-use crate::reg::NumericRegister;
+use crate::isa::{Bytecode, BytecodeError, Instr, InstructionSet};
+use crate::reg::{NumericRegister, Reg32};
 
 // I had an idea of putting Read/Write functionality into `amplify` crate,
 // but it is quire specific to the fact that it uses `u16`-sized underlying
@@ -57,13 +59,31 @@ pub enum WriteError {
     /// attempt to write library reference for the lib id {0} which is not a part of program
     /// segment
     LibAbsent(LibId),
+
+    /// data table duplicates the data of the table at data segment offset {0}
+    DuplicateTable(u16),
+
+    /// value {value:#x} does not fit into {bits} bits
+    ValueExceedsBitCount {
+        /// Value which was attempted to be written
+        value: u32,
+        /// Number of bits available for the value
+        bits: u8,
+    },
 }
 
 mod private {
-    use super::super::Cursor;
+    use super::super::cursor::{SizeCounter, XrefCursor};
+    use super::super::{Cursor, ExecCursor};
 
     pub trait Sealed {}
 
+    impl<'a> Sealed for ExecCursor<'a> {}
+
+    impl<'a> Sealed for XrefCursor<'a> {}
+
+    impl Sealed for SizeCounter {}
+
     impl<'a, T, D> Sealed for Cursor<'a, T, D>
     where
         T: AsRef<[u8]>,
@@ -121,6 +141,12 @@ pub trait Read: private::Sealed {
     fn read_data(&mut self) -> Result<(&[u8], bool), CodeEofError>;
     /// Reads number representation from a data segment
     fn read_number(&mut self, reg: impl NumericRegister) -> Result<Number, CodeEofError>;
+    /// Reads set of register indexes encoded by [`Write::write_reg_set`] as a 32-bit mask, where
+    /// the bit number `n` (starting from the least significant bit) is set for the index `n`
+    fn read_reg_set(&mut self) -> Result<BTreeSet<Reg32>, CodeEofError> {
+        let mask = self.read_u16()? as u32 | (self.read_u16()? as u32) << 16;
+        Ok(Reg32::ALL.iter().copied().filter(|idx| mask & (1 << u8::from(*idx)) != 0).collect())
+    }
 }
 
 /// Trait for writing instruction data into bytecode
@@ -157,6 +183,23 @@ pub trait Write: private::Sealed {
     fn write_data(&mut self, bytes: impl AsRef<[u8]>) -> Result<(), WriteError>;
     /// Writes number representation into data segment
     fn write_number(&mut self, reg: impl NumericRegister, value: Number) -> Result<(), WriteError>;
+    /// Writes set of register indexes as a 32-bit mask occupying four bytes, where the bit number
+    /// `n` (starting from the least significant bit) is set for the index `n`. Used by the
+    /// instructions operating on multiple registers of the same family.
+    fn write_reg_set(&mut self, regs: impl IntoIterator<Item = Reg32>) -> Result<(), WriteError> {
+        let mask = regs.into_iter().fold(0u32, |mask, idx| mask | 1 << u8::from(idx));
+        self.write_u16(mask as u16)?;
+        self.write_u16((mask >> 16) as u16)
+    }
+    /// Writes the instruction code byte followed by the instruction arguments.
+    ///
+    /// The write is atomic: if any of the instruction arguments fails to be written, the code
+    /// written for the instruction is erased, the data it has added to the data segment are
+    /// removed and the writer position is restored, such that the writer remains in the same state
+    /// as before the call.
+    fn write_instr<I>(&mut self, instr: &I) -> Result<(), BytecodeError>
+    where
+        I: Bytecode + ?Sized;
     /// In-place instruction editing
     fn edit<F, E, S>(&mut self, pos: u16, editor: F) -> Result<(), E>
     where
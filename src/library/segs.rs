@@ -28,6 +28,7 @@ use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
 
 use crate::library::constants::{
     ISAE_SEGMENT_MAX_COUNT, ISAE_SEGMENT_MAX_LEN, ISA_ID_ALLOWED_CHARS, ISA_ID_ALLOWED_FIRST_CHAR,
@@ -40,11 +41,21 @@ use crate::library::{LibId, LibSite};
 #[cfg_attr(feature = "std", derive(Error))]
 #[display(doc_comments)]
 pub enum SegmentError {
-    /// the size of the CODE segment is {0}, which exceeds `CODE_SEGMENT_MAX_LEN`
-    CodeSegmentTooLarge(usize),
-
-    /// the size of the DATA segment is {0}, which exceeds `DATA_SEGMENT_MAX_LEN`
-    DataSegmentTooLarge(usize),
+    /// the size of the CODE segment is {len} bytes, while at most {max} bytes are allowed
+    CodeSegmentTooLarge {
+        /// Size of the code segment
+        len: usize,
+        /// Maximal allowed size of the code segment (inclusive)
+        max: u16,
+    },
+
+    /// the size of the DATA segment is {len} bytes, while at most {max} bytes are allowed
+    DataSegmentTooLarge {
+        /// Size of the data segment
+        len: usize,
+        /// Maximal allowed size of the data segment (inclusive)
+        max: u16,
+    },
 
     /// ISA segment error
     #[display(inner)]
@@ -52,6 +63,96 @@ pub enum SegmentError {
     IsaeSegment(IsaSegError),
 }
 
+/// Errors constructing library from text-encoded segments
+#[derive(Clone, Eq, PartialEq, Debug, Display, From)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum SegmentParseError {
+    /// invalid hexadecimal encoding of the {0} segment: {1}
+    Hex(&'static str, amplify::hex::Error),
+
+    /// invalid base64 encoding of the {0} segment: {1}
+    #[cfg(feature = "base64")]
+    Base64(&'static str, base64::DecodeError),
+
+    /// invalid library segments
+    #[display(inner)]
+    #[from]
+    Segment(SegmentError),
+}
+
+/// Limits on the sizes of the library segments.
+///
+/// All the limits are inclusive: a segment may have exactly the maximal size. The limits are
+/// checked by [`Cursor`](super::Cursor) when it writes the data segment, by the library
+/// constructors and by the assembler, such that a library accepted by any of them is accepted by
+/// the others.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct SegmentLimits {
+    /// Maximal size of the code segment, in bytes
+    pub code: u16,
+
+    /// Maximal size of the data segment, in bytes
+    pub data: u16,
+
+    /// Maximal number of the libraries in the libs segment
+    pub libs: u16,
+}
+
+impl Default for SegmentLimits {
+    #[inline]
+    fn default() -> Self { SegmentLimits::MAX }
+}
+
+impl SegmentLimits {
+    /// Limits of the library segments defined by the library format: code and data segments are
+    /// addressed with 16-bit offsets, and libraries are referenced with 8-bit indexes.
+    ///
+    /// The code and data segments may take at most 65535 bytes, one byte less than their address
+    /// spaces ([`CODE_SEGMENT_MAX_LEN`] and [`DATA_SEGMENT_MAX_LEN`]).
+    ///
+    /// [`CODE_SEGMENT_MAX_LEN`]: crate::library::constants::CODE_SEGMENT_MAX_LEN
+    /// [`DATA_SEGMENT_MAX_LEN`]: crate::library::constants::DATA_SEGMENT_MAX_LEN
+    pub const MAX: SegmentLimits =
+        SegmentLimits { code: u16::MAX, data: u16::MAX, libs: LIBS_SEGMENT_MAX_COUNT as u16 };
+
+    /// Checks whether the code segment of `len` bytes fits the limit.
+    #[inline]
+    pub fn fits_code(&self, len: usize) -> bool { len <= self.code as usize }
+
+    /// Checks whether the data segment of `len` bytes fits the limit.
+    #[inline]
+    pub fn fits_data(&self, len: usize) -> bool { len <= self.data as usize }
+
+    /// Checks whether the libs segment of `count` libraries fits the limit.
+    #[inline]
+    pub fn fits_libs(&self, count: usize) -> bool { count <= self.libs as usize }
+
+    /// Checks the size of the code segment.
+    ///
+    /// # Errors
+    ///
+    /// [`SegmentError::CodeSegmentTooLarge`] if the code segment exceeds the limit.
+    pub fn check_code(&self, len: usize) -> Result<(), SegmentError> {
+        if !self.fits_code(len) {
+            return Err(SegmentError::CodeSegmentTooLarge { len, max: self.code });
+        }
+        Ok(())
+    }
+
+    /// Checks the size of the data segment.
+    ///
+    /// # Errors
+    ///
+    /// [`SegmentError::DataSegmentTooLarge`] if the data segment exceeds the limit.
+    pub fn check_data(&self, len: usize) -> Result<(), SegmentError> {
+        if !self.fits_data(len) {
+            return Err(SegmentError::DataSegmentTooLarge { len, max: self.data });
+        }
+        Ok(())
+    }
+}
+
 /// Errors while processing ISA extensions segment
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
 #[cfg_attr(feature = "std", derive(Error))]
@@ -70,6 +171,144 @@ pub enum IsaSegError {
     /// ISA id {0} includes wrong symbols (must contain only uppercase alphanumeric and start with
     /// letter)
     IsaIdWrongSymbols(String),
+
+    /// ISA id {0} has invalid version or feature list (the version must be a decimal number
+    /// without leading zeros above 0, and features must be unique and ordered lexicographically)
+    IsaIdWrongFormat(String),
+}
+
+/// Checks that the ISA extension name or feature name has a correct length and consists of
+/// allowed characters.
+fn check_isa_name(name: &str, id: &str) -> Result<(), IsaSegError> {
+    if !(ISA_ID_MIN_LEN..=ISA_ID_MAX_LEN).contains(&name.len()) {
+        return Err(IsaSegError::IsaIdWrongLength(id.to_owned()));
+    }
+    if name.chars().any(|ch| !ISA_ID_ALLOWED_CHARS.contains(&ch))
+        || name
+            .chars()
+            .next()
+            .map(|ch| !ISA_ID_ALLOWED_FIRST_CHAR.contains(&ch))
+            .unwrap_or_default()
+    {
+        return Err(IsaSegError::IsaIdWrongSymbols(id.to_owned()));
+    }
+    Ok(())
+}
+
+/// Structured ISA extension id, consisting of the ISA extension name, its version and a set of
+/// optional features used by the code.
+///
+/// The id is represented in the ISAE segment as `NAME[:VERSION][+FEATURE]*`, for instance `ALU`,
+/// `ALUIMM:2` or `ALU:3+BIGINT+SIMD`. Ids without version have version 0 and no features, such
+/// that the ISAE segments of the existing libraries keep their meaning. Each string
+/// representation is canonical: explicit zero version, leading zeros and unordered or repeated
+/// features are rejected.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct IsaId {
+    name: String,
+    version: u16,
+    features: BTreeSet<String>,
+}
+
+impl IsaId {
+    /// Constructs unversioned ISA extension id without features.
+    ///
+    /// # Error
+    ///
+    /// Errors with [`IsaSegError`] if the name has wrong length or symbols.
+    pub fn new(name: impl AsRef<str>) -> Result<Self, IsaSegError> {
+        IsaId::with(name, 0, [] as [&str; 0])
+    }
+
+    /// Constructs ISA extension id with a given version and features.
+    ///
+    /// # Error
+    ///
+    /// Errors with [`IsaSegError`] if the name or some of the features have wrong length or
+    /// symbols.
+    pub fn with(
+        name: impl AsRef<str>,
+        version: u16,
+        features: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self, IsaSegError> {
+        let name = name.as_ref();
+        check_isa_name(name, name)?;
+        let features = features
+            .into_iter()
+            .map(|feature| {
+                let feature = feature.as_ref();
+                check_isa_name(feature, feature).map(|_| feature.to_owned())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(IsaId { name: name.to_owned(), version, features })
+    }
+
+    /// Returns ISA extension name
+    #[inline]
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns ISA extension version, which is 0 for unversioned ids
+    #[inline]
+    pub fn version(&self) -> u16 { self.version }
+
+    /// Returns set of the ISA extension features
+    #[inline]
+    pub fn features(&self) -> &BTreeSet<String> { &self.features }
+
+    /// Checks whether the code requiring this ISA extension can be run by an instruction set
+    /// providing `provided` ISA extension. This requires both ids to have the same name, the
+    /// provided version to be the same or newer, and the provided features to include all the
+    /// features required by this id.
+    pub fn is_compatible_with(&self, provided: &IsaId) -> bool {
+        self.name == provided.name
+            && self.version <= provided.version
+            && self.features.is_subset(&provided.features)
+    }
+}
+
+impl Display for IsaId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if self.version > 0 {
+            write!(f, ":{}", self.version)?;
+        }
+        for feature in &self.features {
+            write!(f, "+{}", feature)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for IsaId {
+    type Err = IsaSegError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('+');
+        let head = parts.next().unwrap_or_default();
+        let (name, version) = match head.split_once(':') {
+            None => (head, 0),
+            Some((name, version)) => {
+                let canonical =
+                    !version.starts_with('0') && version.bytes().all(|b| b.is_ascii_digit());
+                match version.parse() {
+                    Ok(version) if canonical => (name, version),
+                    _ => return Err(IsaSegError::IsaIdWrongFormat(s.to_owned())),
+                }
+            }
+        };
+        check_isa_name(name, s)?;
+        let mut features = BTreeSet::new();
+        let mut last = "";
+        for feature in parts {
+            check_isa_name(feature, s)?;
+            if feature <= last {
+                return Err(IsaSegError::IsaIdWrongFormat(s.to_owned()));
+            }
+            features.insert(feature.to_owned());
+            last = feature;
+        }
+        Ok(IsaId { name: name.to_owned(), version, features })
+    }
 }
 
 /// ISA extensions segment
@@ -81,7 +320,7 @@ pub struct IsaSeg(BTreeSet<String>);
 impl IsaSeg {
     /// Returns iterator over unique ISA ids iterated in the deterministic (lexicographic) order
     #[inline]
-    pub fn iter(&self) -> ::alloc::collections::btree_set::Iter<String> { self.0.iter() }
+    pub fn iter(&self) -> ::alloc::collections::btree_set::Iter<'_, String> { self.0.iter() }
 }
 
 impl<'a> IntoIterator for &'a IsaSeg {
@@ -127,18 +366,7 @@ impl IsaSeg {
         }
         let mut total_len = 0usize;
         for isae in &isa_codes {
-            if !(ISA_ID_MIN_LEN..=ISA_ID_MAX_LEN).contains(&isae.len()) {
-                return Err(IsaSegError::IsaIdWrongLength(isae.to_owned()));
-            }
-            if isae.chars().any(|ch| !ISA_ID_ALLOWED_CHARS.contains(&ch))
-                || isae
-                    .chars()
-                    .next()
-                    .map(|ch| !ISA_ID_ALLOWED_FIRST_CHAR.contains(&ch))
-                    .unwrap_or_default()
-            {
-                return Err(IsaSegError::IsaIdWrongSymbols(isae.to_owned()));
-            }
+            IsaId::from_str(isae)?;
             total_len += isae.len();
         }
         if total_len > ISAE_SEGMENT_MAX_LEN {
@@ -156,6 +384,12 @@ impl IsaSeg {
     pub fn at(&self, index: u8) -> Option<String> {
         self.0.iter().enumerate().nth(index as usize).map(|(_, isa)| isa).cloned()
     }
+
+    /// Returns iterator over structured ISA ids from the segment, skipping ids which can't be
+    /// parsed
+    pub fn isa_ids(&self) -> impl Iterator<Item = IsaId> + '_ {
+        self.0.iter().filter_map(|isa| isa.parse().ok())
+    }
 }
 
 impl Display for IsaSeg {
@@ -205,7 +439,7 @@ pub struct LibSeg {
 impl LibSeg {
     /// Returns iterator over unique libraries iterated in the deterministic (lexicographic) order
     #[inline]
-    pub fn iter(&self) -> ::alloc::collections::btree_set::Iter<LibId> { self.into_iter() }
+    pub fn iter(&self) -> ::alloc::collections::btree_set::Iter<'_, LibId> { self.into_iter() }
 }
 
 impl<'a> IntoIterator for &'a LibSeg {
@@ -244,7 +478,7 @@ impl LibSeg {
     #[allow(clippy::should_implement_trait)]
     pub fn from_iter(source: impl IntoIterator<Item = LibId>) -> Result<Self, LibSegOverflow> {
         let set = source.into_iter().collect::<BTreeSet<LibId>>();
-        if set.len() > LIBS_SEGMENT_MAX_COUNT {
+        if !SegmentLimits::MAX.fits_libs(set.len()) {
             return Err(LibSegOverflow);
         }
         let table = set.iter().enumerate().map(|(index, id)| (index as u8, *id)).collect();
@@ -284,7 +518,7 @@ impl LibSeg {
     /// `true` if the library was already known and `false` otherwise.
     #[inline]
     pub fn add_lib(&mut self, id: LibId) -> Result<bool, LibSegOverflow> {
-        if self.set.len() >= LIBS_SEGMENT_MAX_COUNT {
+        if !SegmentLimits::MAX.fits_libs(self.set.len() + 1) {
             Err(LibSegOverflow)
         } else if self.index(id).is_some() {
             Ok(true)
@@ -310,3 +544,62 @@ impl Display for LibSeg {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn isa_id() {
+        let id = IsaId::from_str("ALU:3+BIGINT+SIMD").unwrap();
+        assert_eq!((id.name(), id.version()), ("ALU", 3));
+        assert_eq!(id.features().iter().collect::<Vec<_>>(), ["BIGINT", "SIMD"]);
+        assert_eq!(id, IsaId::with("ALU", 3, ["SIMD", "BIGINT"]).unwrap());
+        assert_eq!(id.to_string(), "ALU:3+BIGINT+SIMD");
+        assert_eq!(IsaId::from_str("ALU").unwrap(), IsaId::new("ALU").unwrap());
+        assert_eq!(IsaId::new("ALU").unwrap().to_string(), "ALU");
+
+        for wrong in
+            ["ALU:0", "ALU:01", "ALU:", "ALU:+1", "ALU:70000", "ALU+SIMD+BIGINT", "ALU+X1+X1"]
+        {
+            assert_eq!(
+                IsaId::from_str(wrong),
+                Err(IsaSegError::IsaIdWrongFormat(wrong.to_owned()))
+            );
+        }
+        assert_eq!(
+            IsaId::from_str("ALU:2+simd"),
+            Err(IsaSegError::IsaIdWrongSymbols(s!("ALU:2+simd")))
+        );
+        assert_eq!(IsaId::from_str("A:2"), Err(IsaSegError::IsaIdWrongLength(s!("A:2"))));
+        assert_eq!(IsaId::new("1ALU"), Err(IsaSegError::IsaIdWrongSymbols(s!("1ALU"))));
+    }
+
+    #[test]
+    fn isa_id_compatibility() {
+        let provided = IsaId::from_str("ALU:2+BIGINT+SIMD").unwrap();
+        for required in ["ALU", "ALU:2", "ALU+SIMD", "ALU:1+BIGINT+SIMD"] {
+            assert!(
+                IsaId::from_str(required).unwrap().is_compatible_with(&provided),
+                "{}",
+                required
+            );
+        }
+        for required in ["ALU:3", "ALU+FLOAT", "ALUCT", "ALU:2+BIGINT+FLOAT"] {
+            assert!(
+                !IsaId::from_str(required).unwrap().is_compatible_with(&provided),
+                "{}",
+                required
+            );
+        }
+        assert!(!provided.is_compatible_with(&IsaId::new("ALU").unwrap()));
+    }
+
+    #[test]
+    fn isa_seg() {
+        let seg = IsaSeg::with("ALU ALUIMM:2 BPDIGEST+X64").unwrap();
+        assert_eq!(seg.to_string(), "ALU ALUIMM:2 BPDIGEST+X64");
+        assert_eq!(seg.isa_ids().map(|id| id.version()).collect::<Vec<_>>(), [0, 2, 0]);
+        assert_eq!(IsaSeg::with("ALU ALU:00"), Err(IsaSegError::IsaIdWrongFormat(s!("ALU:00"))));
+    }
+}
@@ -0,0 +1,276 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics on library code composition, used for code size profiling
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+use crate::isa::opcodes::{INSTR_ISAE_FROM, INSTR_ISAE_TO, INSTR_NOP};
+use crate::isa::{
+    ArithmeticOp, BitwiseOp, Bytecode, BytesOp, CmpOp, ControlFlowOp, Curve25519Op, DigestOp,
+    MoveOp, PutOp, Secp256k1Op,
+};
+
+/// Number of the largest instructions reported by [`LibStats`]
+pub const LARGEST_INSTR_COUNT: usize = 8;
+
+/// Class of instructions, determined by the instruction opcode.
+///
+/// Classes and their lowercase names, used by [`Display`], are stable and may be used to map
+/// profiling and benchmark results onto the cost model constants.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(lowercase)]
+pub enum InstrClass {
+    /// Control flow instructions ([`ControlFlowOp`])
+    ControlFlow,
+
+    /// Instructions setting register values ([`PutOp`])
+    Put,
+
+    /// Instructions moving and copying register values ([`MoveOp`])
+    Move,
+
+    /// Instructions comparing register values ([`CmpOp`])
+    Cmp,
+
+    /// Arithmetic instructions ([`ArithmeticOp`])
+    Arithmetic,
+
+    /// Bit operations & boolean algebra instructions ([`BitwiseOp`])
+    Bitwise,
+
+    /// Operations on byte strings ([`BytesOp`])
+    Bytes,
+
+    /// Cryptographic hashing functions ([`DigestOp`])
+    Digest,
+
+    /// Operations on Secp256k1 elliptic curve ([`Secp256k1Op`])
+    Secp256k1,
+
+    /// Operations on Curve25519 elliptic curve ([`Curve25519Op`])
+    Curve25519,
+
+    /// Other ISA extension instructions
+    Extension,
+
+    /// Reserved instructions
+    Reserved,
+
+    /// No-operation instruction
+    Nop,
+}
+
+impl InstrClass {
+    /// All instruction classes, in the order of their opcode ranges
+    pub const ALL: [InstrClass; 13] = [
+        InstrClass::ControlFlow,
+        InstrClass::Put,
+        InstrClass::Move,
+        InstrClass::Cmp,
+        InstrClass::Arithmetic,
+        InstrClass::Bitwise,
+        InstrClass::Bytes,
+        InstrClass::Digest,
+        InstrClass::Secp256k1,
+        InstrClass::Curve25519,
+        InstrClass::Extension,
+        InstrClass::Reserved,
+        InstrClass::Nop,
+    ];
+
+    /// Detects class of the given instruction.
+    #[inline]
+    pub fn of(instr: &impl Bytecode) -> InstrClass { InstrClass::with(instr.instr_byte()) }
+
+    /// Detects class of an instruction with the given opcode.
+    pub fn with(opcode: u8) -> InstrClass {
+        if ControlFlowOp::instr_range().contains(&opcode) {
+            InstrClass::ControlFlow
+        } else if PutOp::instr_range().contains(&opcode) {
+            InstrClass::Put
+        } else if MoveOp::instr_range().contains(&opcode) {
+            InstrClass::Move
+        } else if CmpOp::instr_range().contains(&opcode) {
+            InstrClass::Cmp
+        } else if ArithmeticOp::instr_range().contains(&opcode) {
+            InstrClass::Arithmetic
+        } else if BitwiseOp::instr_range().contains(&opcode) {
+            InstrClass::Bitwise
+        } else if BytesOp::instr_range().contains(&opcode) {
+            InstrClass::Bytes
+        } else if DigestOp::instr_range().contains(&opcode) {
+            InstrClass::Digest
+        } else if Secp256k1Op::instr_range().contains(&opcode) {
+            InstrClass::Secp256k1
+        } else if Curve25519Op::instr_range().contains(&opcode) {
+            InstrClass::Curve25519
+        } else if (INSTR_ISAE_FROM..=INSTR_ISAE_TO).contains(&opcode) {
+            InstrClass::Extension
+        } else if opcode == INSTR_NOP {
+            InstrClass::Nop
+        } else {
+            InstrClass::Reserved
+        }
+    }
+}
+
+/// Information about a single instruction reported by [`LibStats`]
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct InstrSize {
+    /// Offset of the instruction in the code segment
+    pub pos: u16,
+
+    /// Size of the encoded instruction, in bytes
+    pub size: u16,
+
+    /// Instruction in assembly format
+    pub instr: String,
+}
+
+/// Statistics on library code composition, returned by [`crate::library::Lib::statistics`].
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct LibStats {
+    /// Total number of instructions in the code segment
+    pub instr_count: usize,
+
+    /// Size of the code segment, in bytes
+    pub code_size: usize,
+
+    /// Number of instructions per opcode
+    pub opcodes: BTreeMap<u8, usize>,
+
+    /// Total size of the encoded instructions per instruction class, in bytes
+    pub class_sizes: BTreeMap<InstrClass, usize>,
+
+    /// Size of the data segment, in bytes
+    pub data_size: usize,
+
+    /// Number of data segment bytes referenced by the library code
+    pub data_used: usize,
+
+    /// Largest instructions, ordered by their size (the largest first), at most
+    /// [`LARGEST_INSTR_COUNT`] items
+    pub largest: Vec<InstrSize>,
+}
+
+impl LibStats {
+    /// Registers instruction in the statistics.
+    pub(super) fn add(&mut self, opcode: u8, info: InstrSize) {
+        self.instr_count += 1;
+        *self.opcodes.entry(opcode).or_default() += 1;
+        *self.class_sizes.entry(InstrClass::with(opcode)).or_default() += info.size as usize;
+
+        let index = self
+            .largest
+            .iter()
+            .position(|item| item.size < info.size)
+            .unwrap_or(self.largest.len());
+        if index < LARGEST_INSTR_COUNT {
+            self.largest.insert(index, info);
+            self.largest.truncate(LARGEST_INSTR_COUNT);
+        }
+    }
+
+    /// Returns share of the data segment which is referenced by the library code, in percents.
+    /// Empty data segment is considered to be fully utilized.
+    pub fn data_utilization(&self) -> f64 {
+        if self.data_size == 0 {
+            return 100.0;
+        }
+        self.data_used as f64 * 100.0 / self.data_size as f64
+    }
+}
+
+impl Display for LibStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instructions: {}", self.instr_count)?;
+        writeln!(f, "code size:    {} bytes", self.code_size)?;
+        writeln!(
+            f,
+            "data size:    {} bytes, {} used ({:.1}%)",
+            self.data_size,
+            self.data_used,
+            self.data_utilization()
+        )?;
+
+        writeln!(f, "\nsize per class:")?;
+        for (class, size) in &self.class_sizes {
+            writeln!(f, "  {:12} {:>6} bytes", class.to_string(), size)?;
+        }
+
+        writeln!(f, "\nopcodes:")?;
+        for (opcode, count) in &self.opcodes {
+            writeln!(f, "  {:#04x}         {:>6}", opcode, count)?;
+        }
+
+        writeln!(f, "\nlargest instructions:")?;
+        for item in &self.largest {
+            writeln!(f, "  @{:06} {:>4} bytes  {}", item.pos, item.size, item.instr)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::opcodes::{INSTR_ENV_LIB, INSTR_PUTA, INSTR_RESV_FROM};
+    use crate::isa::{Instr, ReservedOp};
+
+    #[test]
+    fn instr_class() {
+        assert_eq!(InstrClass::with(0), InstrClass::ControlFlow);
+        assert_eq!(InstrClass::with(INSTR_PUTA), InstrClass::Put);
+        assert_eq!(InstrClass::with(INSTR_RESV_FROM), InstrClass::Reserved);
+        assert_eq!(InstrClass::with(INSTR_ENV_LIB), InstrClass::Extension);
+        assert_eq!(InstrClass::with(INSTR_NOP), InstrClass::Nop);
+        assert_eq!(InstrClass::of(&Instr::<ReservedOp>::Nop), InstrClass::Nop);
+    }
+
+    #[test]
+    fn instr_class_names() {
+        let names = InstrClass::ALL.iter().map(InstrClass::to_string).collect::<Vec<_>>();
+        assert_eq!(names, [
+            "controlflow",
+            "put",
+            "move",
+            "cmp",
+            "arithmetic",
+            "bitwise",
+            "bytes",
+            "digest",
+            "secp256k1",
+            "curve25519",
+            "extension",
+            "reserved",
+            "nop"
+        ]);
+        for opcode in 0..=u8::MAX {
+            assert!(InstrClass::ALL.contains(&InstrClass::with(opcode)));
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storages of code libraries used by the runtime to resolve calls.
+//!
+//! Libraries are kept by a [`LibStore`], which may be an in-memory [`MemStore`] or a
+//! file-directory [`DirStore`], persisting libraries across runs and loading them on demand.
+//! [`crate::StoreProg`] uses a store as a program executed by [`crate::Vm`].
+
+use alloc::collections::{btree_map, BTreeMap};
+use core::convert::Infallible;
+use core::iter::FromIterator;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use std::{fs, io};
+
+#[cfg(feature = "std")]
+use amplify::hex::ToHex;
+#[cfg(feature = "std")]
+use amplify::IoError;
+
+#[cfg(feature = "std")]
+use crate::data::encoding::{Decode, DecodeError, Encode, EncodeError};
+use crate::library::{Lib, LibId};
+
+/// Storage of code libraries identified by their [`LibId`]
+pub trait LibStore {
+    /// Errors happening when a library is added to the store
+    type Error;
+
+    /// Iterator type over libraries available from the store
+    type Iter<'a>: Iterator<Item = &'a Lib<'static>>
+    where
+        Self: 'a;
+
+    /// Returns library with the given id, if it is available from the store.
+    fn get(&self, id: LibId) -> Option<&Lib<'static>>;
+
+    /// Checks whether the store has library with the given id.
+    fn contains(&self, id: LibId) -> bool { self.get(id).is_some() }
+
+    /// Adds library to the store.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the library was not known to the store before, and `false` otherwise.
+    fn insert(&mut self, lib: Lib<'static>) -> Result<bool, Self::Error>;
+
+    /// Returns an iterator over libraries available from the store.
+    fn iter(&self) -> Self::Iter<'_>;
+}
+
+/// Resolves library with the given id from a map of libraries.
+///
+/// With `ct` feature all the map entries are compared to the id in constant time, such that the
+/// lookup time does not depend on how much of the id matches the ids of the known libraries.
+/// Otherwise, the lookup relies on the (short-circuiting) ordering of the ids.
+#[cfg(feature = "ct")]
+pub(crate) fn resolve<V>(libs: &BTreeMap<LibId, V>, id: LibId) -> Option<&V> {
+    use subtle::ConstantTimeEq;
+
+    let mut found = None;
+    for (key, lib) in libs {
+        if bool::from(key.ct_eq(&id)) {
+            found = Some(lib);
+        }
+    }
+    found
+}
+
+/// Resolves library with the given id from a map of libraries using the ordering of the ids.
+#[cfg(not(feature = "ct"))]
+#[inline]
+pub(crate) fn resolve<V>(libs: &BTreeMap<LibId, V>, id: LibId) -> Option<&V> { libs.get(&id) }
+
+/// In-memory library store
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct MemStore(BTreeMap<LibId, Lib<'static>>);
+
+impl MemStore {
+    /// Constructs empty store.
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns number of libraries in the store.
+    #[inline]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Detects whether the store has no libraries.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Removes library from the store, returning it if it was present.
+    #[inline]
+    pub fn remove(&mut self, id: LibId) -> Option<Lib<'static>> { self.0.remove(&id) }
+}
+
+impl FromIterator<Lib<'static>> for MemStore {
+    fn from_iter<T: IntoIterator<Item = Lib<'static>>>(iter: T) -> Self {
+        MemStore(iter.into_iter().map(|lib| (lib.id(), lib)).collect())
+    }
+}
+
+impl LibStore for MemStore {
+    type Error = Infallible;
+    type Iter<'a> = btree_map::Values<'a, LibId, Lib<'static>>;
+
+    #[inline]
+    fn get(&self, id: LibId) -> Option<&Lib<'static>> { resolve(&self.0, id) }
+
+    fn insert(&mut self, lib: Lib<'static>) -> Result<bool, Infallible> {
+        Ok(self.0.insert(lib.id(), lib).is_none())
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> { self.0.values() }
+}
+
+/// Errors of the file-directory library store
+#[cfg(feature = "std")]
+#[derive(Clone, Eq, PartialEq, Debug, Display, Error, From)]
+#[display(doc_comments)]
+pub enum DirStoreError {
+    /// library file I/O error ({0})
+    #[from]
+    #[from(io::Error)]
+    Io(IoError),
+
+    /// unable to encode library. Details: {0}
+    #[from]
+    Encode(EncodeError),
+
+    /// unable to decode library file. Details: {0}
+    #[from]
+    Decode(DecodeError),
+
+    /// file for library {expected} contains a different library {actual}
+    IdMismatch {
+        /// Library id derived from the file name
+        expected: LibId,
+        /// Id of the library stored in the file
+        actual: LibId,
+    },
+
+    /// library {0} is absent from the store
+    Absent(LibId),
+}
+
+/// Library store persisting libraries as files in a directory.
+///
+/// Each library is kept in a separate file named after the hex representation of its id, with
+/// `.alu` extension, containing the library in the binary [`Encode`] format.
+///
+/// The libraries are loaded lazily: [`LibStore::get`] and [`LibStore::contains`] account only for
+/// libraries which were added with [`LibStore::insert`] or read from the directory with
+/// [`DirStore::load`] or [`DirStore::load_with_dependencies`]. A library file which is present in
+/// the directory but can't be decoded is thus never reported by the store.
+///
+/// Adding a library which is already present in the store replaces its routine tables, debug
+/// information and metadata, which are not committed by the library id.
+#[cfg(feature = "std")]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DirStore {
+    dir: PathBuf,
+    cache: BTreeMap<LibId, Lib<'static>>,
+}
+
+#[cfg(feature = "std")]
+impl DirStore {
+    /// Opens the store at the given directory, creating the directory if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be created.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, DirStoreError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(DirStore { dir, cache: BTreeMap::new() })
+    }
+
+    /// Returns directory used by the store.
+    #[inline]
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// Returns path of the file keeping library with the given id.
+    pub fn lib_path(&self, id: LibId) -> PathBuf {
+        self.dir.join(format!("{}.alu", id.as_slice().to_hex()))
+    }
+
+    /// Reads library with the given id from the directory, unless it is already loaded.
+    ///
+    /// # Returns
+    ///
+    /// `false` if there is no file for the library in the directory, and `true` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// If the library file can't be read or decoded, or contains a library with a different id.
+    pub fn load(&mut self, id: LibId) -> Result<bool, DirStoreError> {
+        if self.cache.contains_key(&id) {
+            return Ok(true);
+        }
+        let data = match fs::read(self.lib_path(id)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        let lib = Lib::deserialize(data)?;
+        let actual = lib.id();
+        if actual != id {
+            return Err(DirStoreError::IdMismatch { expected: id, actual });
+        }
+        self.cache.insert(id, lib);
+        Ok(true)
+    }
+
+    /// Reads library with the given id from the directory together with all the libraries it
+    /// depends on, directly or transitively.
+    ///
+    /// # Errors
+    ///
+    /// If some of the libraries are absent from the store or can't be read.
+    pub fn load_with_dependencies(&mut self, id: LibId) -> Result<(), DirStoreError> {
+        let mut queue = vec![id];
+        while let Some(id) = queue.pop() {
+            if !self.load(id)? {
+                return Err(DirStoreError::Absent(id));
+            }
+            let cache = &self.cache;
+            queue.extend(cache[&id].dependencies().filter(|dep| !cache.contains_key(dep)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl LibStore for DirStore {
+    type Error = DirStoreError;
+    type Iter<'a> = btree_map::Values<'a, LibId, Lib<'static>>;
+
+    #[inline]
+    fn get(&self, id: LibId) -> Option<&Lib<'static>> { resolve(&self.cache, id) }
+
+    fn insert(&mut self, lib: Lib<'static>) -> Result<bool, DirStoreError> {
+        let id = lib.id();
+        let path = self.lib_path(id);
+        let known = self.cache.contains_key(&id) || path.is_file();
+        // The file is rewritten even for a known library, since the tables of the library, which
+        // are not committed by its id, may differ
+        let mut data = vec![];
+        lib.encode(&mut data)?;
+        fs::write(path, data)?;
+        self.cache.insert(id, lib);
+        Ok(!known)
+    }
+
+    #[inline]
+    fn iter(&self) -> Self::Iter<'_> { self.cache.values() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::{ControlFlowOp, Instr};
+    use crate::library::LibSite;
+    use crate::{LinkError, Program, RunError, StoreProg, Vm};
+
+    fn libs() -> (Lib<'static>, Lib<'static>) {
+        let callee = Lib::assemble::<Instr>(&[Instr::ControlFlow(ControlFlowOp::Succ)]).unwrap();
+        let caller = Lib::assemble::<Instr>(&[
+            Instr::ControlFlow(ControlFlowOp::Call(LibSite::with(0, callee.id()))),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ])
+        .unwrap();
+        (caller, callee)
+    }
+
+    #[test]
+    fn mem_store() {
+        let (caller, callee) = libs();
+        let (caller_id, callee_id) = (caller.id(), callee.id());
+        let mut store = MemStore::new();
+        assert_eq!(store.insert(caller.clone()), Ok(true));
+        assert_eq!(store.insert(caller), Ok(false));
+        assert!(store.contains(caller_id) && !store.contains(callee_id));
+
+        let mut vm = Vm::<Instr>::new();
+        let prog = StoreProg::<_, Instr>::new(&store, LibSite::with(0, caller_id)).unwrap();
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::Link(LinkError::MissingDependency {
+                dependent: caller_id,
+                dependency: callee_id
+            }))
+        );
+
+        store.insert(callee).unwrap();
+        let prog = StoreProg::<_, Instr>::new(&store, LibSite::with(0, caller_id)).unwrap();
+        assert_eq!(prog.lib_count(), 2);
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+    }
+
+    #[test]
+    fn resolve() {
+        let mut libs = BTreeMap::new();
+        let mut id = [7u8; 32];
+        libs.insert(LibId::from(id), 1);
+        id[31] = 8;
+        libs.insert(LibId::from(id), 2);
+        assert_eq!(super::resolve(&libs, LibId::from(id)), Some(&2));
+        assert_eq!(super::resolve(&libs, LibId::from([7u8; 32])), Some(&1));
+        id[0] = 8;
+        assert_eq!(super::resolve(&libs, LibId::from(id)), None);
+    }
+
+    #[test]
+    fn dir_store() {
+        let dir = std::env::temp_dir().join(format!("aluvm-dir-store-{}", std::process::id()));
+        let (caller, callee) = libs();
+        let (caller_id, callee_id) = (caller.id(), callee.id());
+
+        let mut store = DirStore::open(&dir).unwrap();
+        assert_eq!(store.insert(caller.clone()), Ok(true));
+        assert_eq!(store.insert(callee.clone()), Ok(true));
+        assert_eq!(store.insert(callee.clone()), Ok(false));
+        let mut exported = caller.clone();
+        exported.export("main", 0).unwrap();
+        assert_eq!(store.insert(exported), Ok(false));
+
+        // Libraries are loaded from the directory on demand
+        let mut store = DirStore::open(&dir).unwrap();
+        assert!(!store.contains(caller_id) && store.get(caller_id).is_none());
+        store.load_with_dependencies(caller_id).unwrap();
+        assert!(store.contains(caller_id) && store.contains(callee_id));
+        assert_eq!(store.get(caller_id), Some(&caller));
+        assert_eq!(store.get(caller_id).unwrap().entrypoint("main"), Some(0));
+        assert_eq!(store.get(callee_id), Some(&callee));
+        let prog = StoreProg::<_, Instr>::new(&store, LibSite::with(0, caller_id)).unwrap();
+        assert!(Vm::<Instr>::new().run(&prog, &()));
+
+        // File content must match the library id
+        fs::copy(store.lib_path(caller_id), store.lib_path(callee_id)).unwrap();
+        let mut store = DirStore::open(&dir).unwrap();
+        assert_eq!(
+            store.load_with_dependencies(caller_id),
+            Err(DirStoreError::IdMismatch { expected: callee_id, actual: caller_id })
+        );
+        assert!(!store.contains(callee_id) && store.get(callee_id).is_none());
+
+        // Undecodable files are not reported as present
+        fs::write(store.lib_path(callee_id), [0xFFu8; 4]).unwrap();
+        let mut store = DirStore::open(&dir).unwrap();
+        assert!(matches!(store.load(callee_id), Err(DirStoreError::Decode(_))));
+        assert!(!store.contains(callee_id) && store.get(callee_id).is_none());
+
+        fs::remove_file(store.lib_path(callee_id)).unwrap();
+        let mut store = DirStore::open(&dir).unwrap();
+        assert_eq!(store.load(callee_id), Ok(false));
+        assert_eq!(store.load_with_dependencies(caller_id), Err(DirStoreError::Absent(callee_id)));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
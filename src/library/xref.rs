@@ -0,0 +1,125 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cross-references between the library code and data segments.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::ops::Range;
+
+/// Map of the data segment ranges to the offsets of the instructions referencing them, produced
+/// by [`Lib::data_map`](super::Lib::data_map).
+///
+/// Ranges referenced by instructions may overlap, since the assembler reuses data shared by
+/// several constants. Ranges may also exceed the data segment, in which case the instructions
+/// referencing them set `st0` register to `false` when executed.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct DataMap {
+    data_len: usize,
+    refs: BTreeMap<(usize, usize), BTreeSet<u16>>,
+}
+
+impl DataMap {
+    /// Constructs empty map for the data segment of `data_len` bytes.
+    #[inline]
+    pub(crate) fn new(data_len: usize) -> DataMap { DataMap { data_len, refs: empty!() } }
+
+    /// Registers reference from the instruction at code offset `pos` to the data `range`
+    #[inline]
+    pub(crate) fn add(&mut self, range: Range<usize>, pos: u16) {
+        self.refs.entry((range.start, range.end)).or_default().insert(pos);
+    }
+
+    /// Returns number of distinct data ranges referenced by the code
+    #[inline]
+    pub fn len(&self) -> usize { self.refs.len() }
+
+    /// Detects whether the code references no data
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.refs.is_empty() }
+
+    /// Iterates over referenced data ranges, ordered by their start offset, together with the
+    /// code offsets of the instructions referencing them.
+    pub fn iter(&self) -> impl Iterator<Item = (Range<usize>, &BTreeSet<u16>)> {
+        self.refs.iter().map(|((start, end), refs)| (*start..*end, refs))
+    }
+
+    /// Returns code offsets of all instructions referencing data which include the data segment
+    /// byte at offset `pos`.
+    pub fn referencing(&self, pos: usize) -> BTreeSet<u16> {
+        self.iter()
+            .filter(|(range, _)| range.contains(&pos))
+            .flat_map(|(_, refs)| refs.iter().copied())
+            .collect()
+    }
+
+    /// Returns ranges of the data segment which are not referenced by any of the instructions.
+    pub fn unreferenced(&self) -> Vec<Range<usize>> {
+        let mut unused = Vec::new();
+        let mut pos = 0usize;
+        for (range, _) in self.iter() {
+            if range.start > pos && pos < self.data_len {
+                unused.push(pos..range.start.min(self.data_len));
+            }
+            pos = pos.max(range.end);
+            if pos >= self.data_len {
+                return unused;
+            }
+        }
+        if pos < self.data_len {
+            unused.push(pos..self.data_len);
+        }
+        unused
+    }
+}
+
+impl Display for DataMap {
+    /// Writes a line per data range, listing code offsets of the instructions referencing the
+    /// range. Unreferenced ranges of the data segment are listed in between.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let unused = self.unreferenced().into_iter().map(|range| (range, None));
+        let used = self.iter().map(|(range, refs)| (range, Some(refs)));
+        let mut lines = used.chain(unused).collect::<Vec<_>>();
+        lines.sort_by_key(|(range, _)| (range.start, range.end));
+        for (range, refs) in lines {
+            write!(f, "{:#06X}..{:#06X}: ", range.start, range.end)?;
+            match refs {
+                None => f.write_str("unreferenced")?,
+                Some(refs) => {
+                    for (no, pos) in refs.iter().enumerate() {
+                        if no > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{:#06X}", pos)?;
+                    }
+                }
+            }
+            if range.end > self.data_len {
+                f.write_str(" (outside of data segment)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -22,13 +22,15 @@
 // limitations under the License.
 
 use alloc::borrow::ToOwned;
-use alloc::collections::{btree_map, BTreeMap};
+use alloc::collections::{btree_map, BTreeMap, BTreeSet};
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::Map;
 use core::marker::PhantomData;
 
 use crate::isa::InstructionSet;
 use crate::library::constants::LIBS_MAX_TOTAL;
-use crate::library::{Lib, LibId, LibSite};
+use crate::library::{store, Lib, LibId, LibSite, LibStore};
 
 /// Trait for a concrete program implementation provided by a runtime environment.
 pub trait Program {
@@ -36,7 +38,7 @@ pub trait Program {
     type Isa: InstructionSet;
 
     /// Iterator type over libraries
-    type Iter<'a>: Iterator<Item = &'a Lib>
+    type Iter<'a>: Iterator<Item = &'a Lib<'a>>
     where
         Self: 'a;
 
@@ -48,10 +50,55 @@ pub trait Program {
 
     /// Returns library corresponding to the provided [`LibId`], if the library is known to the
     /// program.
-    fn lib(&self, id: LibId) -> Option<&Lib>;
+    fn lib(&self, id: LibId) -> Option<&Lib<'_>>;
 
     /// Main entry point into the program.
     fn entrypoint(&self) -> LibSite;
+
+    /// Checks that the library at the `entrypoint` and all the libraries it depends on,
+    /// directly or transitively, are known to the program.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LinkError`] for the first absent library which is found.
+    fn check_dependencies(&self, entrypoint: LibSite) -> Result<(), LinkError> {
+        let mut checked = BTreeSet::new();
+        let mut queue = Vec::new();
+        if self.lib(entrypoint.lib).is_none() {
+            return Err(LinkError::EntrypointAbsent(entrypoint.lib));
+        }
+        queue.push(entrypoint.lib);
+        while let Some(id) = queue.pop() {
+            if !checked.insert(id) {
+                continue;
+            }
+            let lib = self.lib(id).expect("presence is checked before adding to the queue");
+            for dependency in lib.dependencies() {
+                if self.lib(dependency).is_none() {
+                    return Err(LinkError::MissingDependency { dependent: id, dependency });
+                }
+                queue.push(dependency);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors detected by [`Program::check_dependencies`] before the program execution
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum LinkError {
+    /// entry point library {0} is absent from the program
+    EntrypointAbsent(LibId),
+
+    /// library {dependency} called by library {dependent} is absent from the program
+    MissingDependency {
+        /// Library calling the absent library
+        dependent: LibId,
+        /// Absent library
+        dependency: LibId,
+    },
 }
 
 /// Errors returned by [`Prog::add_lib`] method
@@ -79,12 +126,12 @@ pub enum ProgError {
 // #[cfg_attr(feature = "strict_encoding", derive(StrictEncode, StrictDecode))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
 // We need to hardcode generic as a literal, otherwise serde > 1.0.152 fails compilation
-pub struct Prog<Isa, const RUNTIME_MAX_TOTAL_LIBS: u16 = 1024>
+pub struct Prog<'lib, Isa, const RUNTIME_MAX_TOTAL_LIBS: u16 = 1024>
 where
     Isa: InstructionSet,
 {
     /// Libraries known to the runtime, identified by their hashes.
-    libs: BTreeMap<LibId, Lib>,
+    libs: BTreeMap<LibId, Lib<'lib>>,
 
     /// Entrypoint for the main function.
     entrypoint: LibSite,
@@ -94,7 +141,7 @@ where
     phantom: PhantomData<Isa>,
 }
 
-impl<Isa, const RUNTIME_MAX_TOTAL_LIBS: u16> Prog<Isa, RUNTIME_MAX_TOTAL_LIBS>
+impl<'lib, Isa, const RUNTIME_MAX_TOTAL_LIBS: u16> Prog<'lib, Isa, RUNTIME_MAX_TOTAL_LIBS>
 where
     Isa: InstructionSet,
 {
@@ -106,7 +153,7 @@ where
 
     /// Constructs new virtual machine runtime using provided single library. Entry point is set
     /// to zero offset by default.
-    pub fn new(lib: Lib) -> Self {
+    pub fn new(lib: Lib<'lib>) -> Self {
         let mut runtime = Self::empty_unchecked();
         let id = lib.id();
         runtime.add_lib(lib).expect("adding single library to lib segment overflows");
@@ -116,7 +163,7 @@ where
 
     /// Constructs new virtual machine runtime from a set of libraries with a given entry point.
     pub fn with(
-        libs: impl IntoIterator<Item = Lib>,
+        libs: impl IntoIterator<Item = Lib<'lib>>,
         entrypoint: LibSite,
     ) -> Result<Self, ProgError> {
         let mut runtime = Self::empty_unchecked();
@@ -141,7 +188,7 @@ where
     ///
     /// `true` if the library was already known and `false` otherwise.
     #[inline]
-    pub fn add_lib(&mut self, lib: Lib) -> Result<bool, ProgError> {
+    pub fn add_lib(&mut self, lib: Lib<'lib>) -> Result<bool, ProgError> {
         if self.lib_count() >= LIBS_MAX_TOTAL.min(Self::RUNTIME_MAX_TOTAL_LIBS) {
             return Err(ProgError::TooManyLibs);
         }
@@ -158,18 +205,90 @@ where
     pub fn set_entrypoint(&mut self, entrypoint: LibSite) { self.entrypoint = entrypoint; }
 }
 
-impl<Isa, const RUNTIME_MAX_TOTAL_LIBS: u16> Program for Prog<Isa, RUNTIME_MAX_TOTAL_LIBS>
+impl<'lib, Isa, const RUNTIME_MAX_TOTAL_LIBS: u16> Program
+    for Prog<'lib, Isa, RUNTIME_MAX_TOTAL_LIBS>
 where
     Isa: InstructionSet,
 {
     type Isa = Isa;
-    type Iter<'a> = btree_map::Values<'a, LibId, Lib> where Self: 'a;
+    type Iter<'a>
+        = btree_map::Values<'a, LibId, Lib<'a>>
+    where
+        Self: 'a;
 
     fn lib_count(&self) -> u16 { self.libs.len() as u16 }
 
     fn libs(&self) -> Self::Iter<'_> { self.libs.values() }
 
-    fn lib(&self, id: LibId) -> Option<&Lib> { self.libs.get(&id) }
+    fn lib(&self, id: LibId) -> Option<&Lib<'_>> { store::resolve(&self.libs, id) }
+
+    fn entrypoint(&self) -> LibSite { self.entrypoint }
+}
+
+/// Program resolving calls to the libraries kept by a [`LibStore`].
+///
+/// Unlike [`Prog`], the program doesn't own the libraries, such that the same store may be used
+/// to run multiple programs. Libraries which are absent from the store, including the libraries
+/// of a [`crate::library::DirStore`] which were not loaded yet, are reported by
+/// [`Program::check_dependencies`] and fail the calls.
+#[derive(Debug)]
+pub struct StoreProg<'store, Store, Isa>
+where
+    Store: LibStore,
+    Isa: InstructionSet,
+{
+    store: &'store Store,
+    entrypoint: LibSite,
+    phantom: PhantomData<Isa>,
+}
+
+impl<'store, Store, Isa> StoreProg<'store, Store, Isa>
+where
+    Store: LibStore,
+    Isa: InstructionSet,
+{
+    /// Constructs program running libraries from the store, starting at the given entry point.
+    ///
+    /// # Errors
+    ///
+    /// Checks that the ISA used by the VM supports ISA extensions specified by each of the
+    /// libraries available from the store, and returns [`ProgError::IsaNotSupported`]
+    /// otherwise.
+    pub fn new(store: &'store Store, entrypoint: LibSite) -> Result<Self, ProgError> {
+        for lib in store.iter() {
+            if let Some(isa) = lib.isae.iter().find(|isa| !Isa::is_supported(isa)) {
+                return Err(ProgError::IsaNotSupported(isa.to_owned()));
+            }
+        }
+        Ok(StoreProg { store, entrypoint, phantom: default!() })
+    }
+
+    /// Returns store used by the program.
+    #[inline]
+    pub fn store(&self) -> &'store Store { self.store }
+
+    /// Sets new entry point value (used when calling [`crate::Vm::run`])
+    pub fn set_entrypoint(&mut self, entrypoint: LibSite) { self.entrypoint = entrypoint; }
+}
+
+fn reborrow<'a>(lib: &'a Lib<'static>) -> &'a Lib<'a> { lib }
+
+impl<'store, Store, Isa> Program for StoreProg<'store, Store, Isa>
+where
+    Store: LibStore,
+    Isa: InstructionSet,
+{
+    type Isa = Isa;
+    type Iter<'a>
+        = Map<Store::Iter<'a>, fn(&'a Lib<'static>) -> &'a Lib<'a>>
+    where
+        Self: 'a;
+
+    fn lib_count(&self) -> u16 { self.store.iter().count() as u16 }
+
+    fn libs(&self) -> Self::Iter<'_> { self.store.iter().map(reborrow) }
+
+    fn lib(&self, id: LibId) -> Option<&Lib<'_>> { self.store.get(id) }
 
     fn entrypoint(&self) -> LibSite { self.entrypoint }
 }
@@ -0,0 +1,116 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Receipts of the external calls made by the programs.
+//!
+//! Each call passing the control to another location with [`ExecStep::Call`], i.e. made by
+//! `call`, `exec` and `ret` instructions, and each host function call requested by
+//! [`HostOp`](crate::isa::HostOp) instructions is logged into the [`CallReceipt`] kept with the VM
+//! registers (see [`CoreRegs::receipt`]), in the order of the calls. The calls made during a
+//! single library run are also reported by [`ExecResult::calls`], allowing auditing of everything a
+//! program has attempted to reach.
+//!
+//! Host function calls are logged even if they are rejected by the VM since the execution is not
+//! performed in chunks; the arguments of the host functions are kept in the registers, thus their
+//! receipts commit to the digest of the register state at the moment of the call. The cost of
+//! the digest is accounted in the complexity of the [`HostOp`](crate::isa::HostOp) instructions.
+//!
+//! The number of logged calls may be limited with [`RunLimits::max_calls`].
+//!
+//! [`ExecStep::Call`]: crate::isa::ExecStep::Call
+//! [`CoreRegs::receipt`]: crate::reg::CoreRegs::receipt
+//! [`RunLimits::max_calls`]: crate::RunLimits::max_calls
+//! [`ExecResult::calls`]: crate::library::ExecResult::calls
+
+use alloc::vec::Vec;
+use core::slice;
+
+use crate::library::LibSite;
+
+/// External call made by a program
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[display(doc_comments)]
+pub enum CallRecord {
+    /// call from {site} to {target}
+    Lib {
+        /// Location of the instruction making the call
+        site: LibSite,
+        /// Location the control was passed to
+        target: LibSite,
+    },
+
+    /// host function {func:#06X} called at {site}
+    Host {
+        /// Location of the instruction making the call
+        site: LibSite,
+        /// Host function identifier
+        func: u16,
+        /// SHA256 digest of the register state at the moment of the call, which contains the
+        /// function arguments
+        args: [u8; 32],
+    },
+}
+
+impl CallRecord {
+    /// Returns location of the instruction which has made the call
+    #[inline]
+    pub fn site(&self) -> LibSite {
+        match *self {
+            CallRecord::Lib { site, .. } | CallRecord::Host { site, .. } => site,
+        }
+    }
+}
+
+/// Ordered log of the external calls made by a program
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct CallReceipt(Vec<CallRecord>);
+
+impl CallReceipt {
+    /// Returns records of the calls, in the order they were made
+    #[inline]
+    pub fn records(&self) -> &[CallRecord] { &self.0 }
+
+    /// Returns iterator over the records of the calls, in the order they were made
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, CallRecord> { self.0.iter() }
+
+    /// Returns number of the logged calls
+    #[inline]
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Detects whether no calls were logged
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Logs the call
+    #[inline]
+    pub(crate) fn push(&mut self, record: CallRecord) { self.0.push(record) }
+}
+
+impl<'a> IntoIterator for &'a CallReceipt {
+    type Item = &'a CallRecord;
+    type IntoIter = slice::Iter<'a, CallRecord>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
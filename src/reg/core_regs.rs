@@ -22,25 +22,197 @@
 // limitations under the License.
 
 use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
 use alloc::string::ToString;
 use alloc::vec::Vec;
+use core::convert::TryFrom;
 use core::fmt::{self, Debug, Formatter};
 
 use amplify::hex::ToHex;
 use amplify::num::apfloat::{ieee, Float};
 use amplify::num::{u1024, u256, u512};
 use half::bf16;
+use sha2::{Digest, Sha256};
 
-use super::{Reg32, RegA, RegAFR, RegF, RegR, RegS};
-use crate::data::{ByteStr, MaybeNumber, Number};
+use super::{NumericRegister, Reg, Reg32, RegA, RegAFR, RegAR, RegF, RegR, RegS};
+#[cfg(feature = "deterministic-audit")]
+use crate::audit::Audit;
+use crate::data::{ByteStr, FloatFlags, Layout, MaybeNumber, Number};
 use crate::isa::InstructionSet;
 use crate::library::LibSite;
+use crate::receipt::{CallReceipt, CallRecord};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use crate::DEADLINE_CHECK_STEPS;
+use crate::{ExecStats, RunLimitError, RunLimits};
 
 /// Maximal size of call stack.
 ///
 /// Equals to 2^16 (limited by `cy0` and `cp0` bit size)
 pub const CALL_STACK_SIZE: usize = 1 << 16;
 
+/// Maximal number of register frames which may be saved at the same time with
+/// [`FrameOp`](crate::isa::FrameOp) instructions.
+pub const FRAME_STACK_SIZE: usize = 256;
+
+/// Values of a range of registers saved to the frame stack
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+struct RegFrame {
+    reg: RegAFR,
+    first: Reg32,
+    last: Reg32,
+    /// Bit mask of the registers from the range which had a value
+    mask: u32,
+    /// Little-endian values of the registers having a value
+    data: Vec<u8>,
+}
+
+/// Errors restoring register state from a snapshot produced by [`CoreRegs::snapshot`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum SnapshotError {
+    /// snapshot data are truncated.
+    Truncated,
+
+    /// snapshot format version {0} is not supported.
+    UnsupportedVersion(u8),
+
+    /// snapshot contains invalid value of {0}.
+    InvalidValue(&'static str),
+
+    /// snapshot contains {0} unexpected bytes after the end of the state data.
+    TrailingData(usize),
+}
+
+/// Errors marshaling values between the host and the registers.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum MarshalError {
+    /// value with {layout} layout can't be put into register {reg}.
+    LayoutMismatch {
+        /// Register receiving the value
+        reg: Reg,
+        /// Layout of the value
+        layout: Layout,
+    },
+
+    /// value with {layout} layout doesn't fit into register {reg}.
+    Overflow {
+        /// Register receiving the value
+        reg: Reg,
+        /// Layout of the value
+        layout: Layout,
+    },
+
+    /// register {0} doesn't keep numbers.
+    NotNumeric(Reg),
+
+    /// register {0} doesn't keep byte strings.
+    NotString(Reg),
+
+    /// {provided} values were provided for the routine taking {expected} inputs.
+    InputCount {
+        /// Number of the routine inputs
+        expected: usize,
+        /// Number of the provided values
+        provided: usize,
+    },
+
+    /// routine output register {0} is not set.
+    OutputNotSet(Reg),
+}
+
+/// Enumerates banks of the registers keeping numbers, in the order they are stored in the
+/// snapshots.
+fn numeric_banks() -> impl Iterator<Item = RegAFR> {
+    RegA::ALL
+        .iter()
+        .map(RegAFR::from)
+        .chain(RegF::ALL.iter().map(RegAFR::from))
+        .chain(RegR::ALL.iter().map(RegAFR::from))
+}
+
+/// Returns bit mask of the register indexes from `first` to `last` (inclusive).
+fn range_mask(first: Reg32, last: Reg32) -> u32 {
+    (u32::MAX >> (31 - u8::from(last))) & (u32::MAX << u8::from(first))
+}
+
+struct SnapshotReader<'a>(&'a [u8]);
+
+impl<'a> SnapshotReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.0.len() < len {
+            return Err(SnapshotError::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn array<const LEN: usize>(&mut self) -> Result<[u8; LEN], SnapshotError> {
+        let mut array = [0u8; LEN];
+        array.copy_from_slice(self.bytes(LEN)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> { self.array::<1>().map(|[byte]| byte) }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> { self.array().map(u16::from_le_bytes) }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> { self.array().map(u32::from_le_bytes) }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> { self.array().map(u64::from_le_bytes) }
+
+    fn bool(&mut self, name: &'static str) -> Result<bool, SnapshotError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::InvalidValue(name)),
+        }
+    }
+
+    fn option<T>(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<Option<T>, SnapshotError> {
+        if self.bool(name)? {
+            f(self).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn site(&mut self) -> Result<LibSite, SnapshotError> {
+        let pos = self.u16()?;
+        let lib = self.array::<32>()?;
+        Ok(LibSite::with(pos, lib.into()))
+    }
+
+    fn finish(self) -> Result<(), SnapshotError> {
+        match self.0.len() {
+            0 => Ok(()),
+            len => Err(SnapshotError::TrailingData(len)),
+        }
+    }
+}
+
+fn write_option<T>(buf: &mut Vec<u8>, val: Option<T>, f: impl FnOnce(&mut Vec<u8>, T)) {
+    match val {
+        None => buf.push(0),
+        Some(val) => {
+            buf.push(1);
+            f(buf, val);
+        }
+    }
+}
+
+fn write_site(buf: &mut Vec<u8>, site: LibSite) {
+    buf.extend(site.pos.to_le_bytes());
+    buf.extend(site.lib.to_byte_array());
+}
+
 /// Structure keeping state of all registers in a single microprosessor/VM core
 #[derive(Clone)]
 pub struct CoreRegs {
@@ -75,16 +247,28 @@ pub struct CoreRegs {
     pub(crate) r4096: Box<[Option<[u8; 512]>; 32]>,
     pub(crate) r8192: Box<[Option<[u8; 1024]>; 32]>,
 
-    /// String and bytestring registers
-    pub(crate) s16: Box<[Option<ByteStr>; 16]>,
+    /// String and bytestring registers, keyed by the register index. Only the registers having a
+    /// value are kept, such that the memory is allocated for the used registers only.
+    pub(crate) s16: BTreeMap<u8, ByteStr>,
 
     /// Control flow register which stores result of equality, comparison, boolean check and
     /// overflowing operations. Initialized with `true`.
     pub(crate) st0: bool,
 
+    /// Float status register, accumulating IEEE-754 exception flags raised by float arithmetic
+    /// operations. The flags are sticky: they are never cleared by the VM itself and must be
+    /// reset with [`CoreRegs::clear_float_flags`]. Initialized with no flags set.
+    pub(crate) fs0: FloatFlags,
+
+    /// Carry register, indicating that the result of the last integer arithmetic operation did
+    /// not fit into the layout of its destination register (overflow, carry or borrow). Set both
+    /// by wrapping operations (which still produce a value) and non-wrapping ones (which leave
+    /// the destination register in `None` state). Initialized with `false`.
+    pub(crate) co0: bool,
+
     /// Counts number of jumps (possible cycles). The number of jumps is limited by 2^16 per
     /// script.
-    cy0: u16,
+    pub(crate) cy0: u16,
 
     /// Complexity accumulator / counter.
     ///
@@ -95,13 +279,13 @@ pub struct CoreRegs {
     ///
     /// - [`CoreRegs::cy0`] register
     /// - [`CoreRegs::cl0`] register
-    ca0: u64,
+    pub(crate) ca0: u64,
 
     /// Complexity limit
     ///
     /// If this register has a value set, once [`CoreRegs::ca0`] will reach this value the VM will
     /// stop program execution setting `st0` to `false`.
-    cl0: Option<u64>,
+    pub(crate) cl0: Option<u64>,
 
     /// Call stack
     ///
@@ -112,7 +296,41 @@ pub struct CoreRegs {
     cs0: Vec<LibSite>,
 
     /// Defines "top" of the call stack
-    cp0: u16,
+    pub(crate) cp0: u16,
+
+    /// Stack of the register frames saved by [`FrameOp`](crate::isa::FrameOp) instructions
+    frames: Vec<RegFrame>,
+
+    /// Limits on the program execution
+    limits: RunLimits,
+
+    /// Counts number of executed instructions
+    steps: u64,
+
+    /// Counts number of jumps to the same or preceding code position
+    back_jumps: u32,
+
+    /// Value of the instruction counter at which the program execution is suspended
+    suspend_at: Option<u64>,
+
+    /// Host function call requested by [`HostOp`](crate::isa::HostOp) instruction, which is
+    /// pending until the program execution gets suspended
+    host_call: Option<(u16, RegAR, Reg32)>,
+
+    /// Wall-clock deadline of the program execution, checked every
+    /// [`DEADLINE_CHECK_STEPS`](crate::DEADLINE_CHECK_STEPS) instructions
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    deadline: Option<std::time::Instant>,
+
+    /// Log of the external calls made by the program
+    receipt: CallReceipt,
+
+    /// Statistics of the resources used by the program
+    stats: ExecStats,
+
+    /// Findings of the execution audit
+    #[cfg(feature = "deterministic-audit")]
+    audit: Audit,
 }
 
 impl Default for CoreRegs {
@@ -149,11 +367,25 @@ impl Default for CoreRegs {
             s16: Default::default(),
 
             st0: true,
+            fs0: FloatFlags::default(),
+            co0: false,
             cy0: 0,
             ca0: 0,
             cl0: None,
             cs0: vec![LibSite::default(); CALL_STACK_SIZE],
             cp0: 0,
+            frames: Vec::new(),
+            limits: RunLimits::unlimited(),
+            steps: 0,
+            back_jumps: 0,
+            suspend_at: None,
+            host_call: None,
+            #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+            deadline: None,
+            receipt: CallReceipt::default(),
+            stats: ExecStats::default(),
+            #[cfg(feature = "deterministic-audit")]
+            audit: Audit::default(),
         }
     }
 }
@@ -176,7 +408,11 @@ impl CoreRegs {
             .map(|_| ())
     }
 
-    pub(crate) fn call(&mut self, site: LibSite) -> Result<(), ()> {
+    pub(crate) fn call(&mut self, site: Option<LibSite>) -> Result<(), ()> {
+        let Some(site) = site else {
+            self.st0 = false;
+            return Err(());
+        };
         self.cy0
             .checked_add(1)
             .map(|cy| self.cy0 = cy)
@@ -187,12 +423,9 @@ impl CoreRegs {
                 self.cs0[self.cp0 as usize] = site;
             })
             .and_then(|_| {
-                self.cp0
-                    .checked_add(1)
-                    .ok_or_else(|| {
-                        self.st0 = false;
-                    })
-                    .map(|_| ())
+                self.cp0.checked_add(1).map(|cp| self.cp0 = cp).ok_or_else(|| {
+                    self.st0 = false;
+                })
             })
     }
 
@@ -276,7 +509,17 @@ impl CoreRegs {
     /// Returns value from one of `S`-registers
     #[inline]
     pub fn get_s(&self, index: impl Into<RegS>) -> Option<&ByteStr> {
-        self.s16[index.into().as_usize()].as_ref()
+        self.s16.get(&index.into().as_u8())
+    }
+
+    /// Checks whether the register has a value.
+    pub fn is_set(&self, reg: Reg) -> bool {
+        match reg {
+            Reg::A(a, idx) => self.get(a, idx).is_some(),
+            Reg::F(f, idx) => self.get(f, idx).is_some(),
+            Reg::R(r, idx) => self.get(r, idx).is_some(),
+            Reg::S(s) => self.get_s(s).is_some(),
+        }
     }
 
     /// Returns value from two registers only if both of them contain a value; otherwise returns
@@ -306,6 +549,10 @@ impl CoreRegs {
     /// Assigns the provided value to the register bit-wise. Silently discards most significant bits
     /// until the value fits register bit size.
     ///
+    /// Float values are canonicalized before being assigned to `F` registers: `NaN` values are
+    /// converted into `None` and negative zero is replaced with positive zero (see
+    /// [`Number::canonicalized`]).
+    ///
     /// Returns `true` if the value was not `None`
     pub fn set(
         &mut self,
@@ -314,8 +561,12 @@ impl CoreRegs {
         value: impl Into<MaybeNumber>,
     ) -> bool {
         let index = index.into() as usize;
-        let value: Option<Number> = value.into().into();
-        match reg.into() {
+        let mut value: Option<Number> = value.into().into();
+        let reg = reg.into();
+        if let RegAFR::F(_) = reg {
+            value = value.and_then(Number::canonicalized);
+        }
+        match reg {
             RegAFR::A(a) => match a {
                 RegA::A8 => self.a8[index] = value.map(Number::into),
                 RegA::A16 => self.a16[index] = value.map(Number::into),
@@ -347,6 +598,7 @@ impl CoreRegs {
                 RegF::F512 => self.f512[index] = value.map(Number::into),
             },
         }
+        self.stats.reg_writes += 1;
         value.is_some()
     }
 
@@ -374,10 +626,18 @@ impl CoreRegs {
     ///
     /// Returns `true` if the value was not `None`.
     pub fn set_s(&mut self, index: impl Into<RegS>, value: Option<impl Into<ByteStr>>) -> bool {
-        let reg = &mut self.s16[index.into().as_usize()];
-        let was_set = reg.is_some();
-        *reg = value.map(|v| v.into());
-        was_set
+        let index = index.into().as_u8();
+        self.stats.reg_writes += 1;
+        match value {
+            Some(value) => {
+                let value = value.into();
+                self.acc_str_write(value.len());
+                let prev = self.s16.insert(index, value).is_some();
+                self.update_str_peak();
+                prev
+            }
+            None => self.s16.remove(&index).is_some(),
+        }
     }
 
     /// Assigns the provided value to the string register if the register is not initialized.
@@ -392,6 +652,167 @@ impl CoreRegs {
         }
     }
 
+    /// Converts the value into the layout of the numeric register. Negative integers are kept in
+    /// the two's complement form extended to the register bit dimension.
+    ///
+    /// # Errors
+    ///
+    /// If the register is a string register, if the value is a float which doesn't match the
+    /// layout of the `F` register or an integer put into `F` register (and vice versa), or if an
+    /// integer value doesn't fit into the register bit dimension.
+    pub fn marshal(reg: Reg, value: Number) -> Result<Number, MarshalError> {
+        let layout = match reg {
+            Reg::A(a, _) => a.layout(),
+            Reg::F(f, _) => f.layout(),
+            Reg::R(r, _) => r.layout(),
+            Reg::S(_) => return Err(MarshalError::NotNumeric(reg)),
+        };
+        match (value.layout(), layout) {
+            (from, to) if from == to => Ok(value),
+            (Layout::Integer(from), Layout::Integer(to)) => value
+                .fitted(to.using_sign(from))
+                .and_then(|value| Number::with(value, to))
+                .ok_or(MarshalError::Overflow { reg, layout: from.into() }),
+            (from, _) => Err(MarshalError::LayoutMismatch { reg, layout: from }),
+        }
+    }
+
+    /// Assigns the value to the `A` register, failing if it doesn't fit (see
+    /// [`CoreRegs::marshal`]).
+    #[inline]
+    pub fn set_a(
+        &mut self,
+        reg: impl Into<RegA>,
+        index: impl Into<Reg32>,
+        value: impl Into<Number>,
+    ) -> Result<(), MarshalError> {
+        self.set_reg(Reg::A(reg.into(), index.into()), value.into())
+    }
+
+    /// Assigns the value to the `F` register, failing if it doesn't match the register layout
+    /// (see [`CoreRegs::marshal`]).
+    #[inline]
+    pub fn set_f(
+        &mut self,
+        reg: impl Into<RegF>,
+        index: impl Into<Reg32>,
+        value: impl Into<Number>,
+    ) -> Result<(), MarshalError> {
+        self.set_reg(Reg::F(reg.into(), index.into()), value.into())
+    }
+
+    /// Assigns the value to the `R` register, failing if it doesn't fit (see
+    /// [`CoreRegs::marshal`]).
+    #[inline]
+    pub fn set_r(
+        &mut self,
+        reg: impl Into<RegR>,
+        index: impl Into<Reg32>,
+        value: impl Into<Number>,
+    ) -> Result<(), MarshalError> {
+        self.set_reg(Reg::R(reg.into(), index.into()), value.into())
+    }
+
+    /// Assigns the value to the numeric register, failing if it doesn't fit (see
+    /// [`CoreRegs::marshal`]).
+    pub fn set_reg(&mut self, reg: Reg, value: Number) -> Result<(), MarshalError> {
+        let value = Self::marshal(reg, value)?;
+        match reg {
+            Reg::A(a, idx) => self.set(a, idx, value),
+            Reg::F(f, idx) => self.set(f, idx, value),
+            Reg::R(r, idx) => self.set(r, idx, value),
+            Reg::S(_) => unreachable!("marshaling checks register family"),
+        };
+        Ok(())
+    }
+
+    /// Returns value of the `A` register, if it is set.
+    #[inline]
+    pub fn get_a(&self, reg: impl Into<RegA>, index: impl Into<Reg32>) -> Option<Number> {
+        self.get(reg.into(), index).into()
+    }
+
+    /// Returns value of the `F` register, if it is set.
+    #[inline]
+    pub fn get_f(&self, reg: impl Into<RegF>, index: impl Into<Reg32>) -> Option<Number> {
+        self.get(reg.into(), index).into()
+    }
+
+    /// Returns value of the `R` register, if it is set.
+    #[inline]
+    pub fn get_r(&self, reg: impl Into<RegR>, index: impl Into<Reg32>) -> Option<Number> {
+        self.get(reg.into(), index).into()
+    }
+
+    /// Returns value of the numeric register, if it is set. Always returns `None` for the
+    /// string registers.
+    pub fn get_reg(&self, reg: Reg) -> Option<Number> {
+        match reg {
+            Reg::A(a, idx) => self.get(a, idx).into(),
+            Reg::F(f, idx) => self.get(f, idx).into(),
+            Reg::R(r, idx) => self.get(r, idx).into(),
+            Reg::S(_) => None,
+        }
+    }
+
+    /// Assigns values to numeric registers. The registers are not modified unless all the values
+    /// fit into them.
+    ///
+    /// # Errors
+    ///
+    /// With the error for the first value which can't be put into its register (see
+    /// [`CoreRegs::marshal`]).
+    pub fn load_inputs(&mut self, inputs: &[(Reg, Number)]) -> Result<(), MarshalError> {
+        let values = inputs
+            .iter()
+            .map(|(reg, value)| Self::marshal(*reg, *value).map(|value| (*reg, value)))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (reg, value) in values {
+            self.set_reg(reg, value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns aggregate length of the byte strings kept in the string registers.
+    pub fn str_bytes(&self) -> u32 { self.s16.values().map(|s| s.len() as u32).sum() }
+
+    /// Assigns the value to the string register on behalf of the executed program, checking it
+    /// against [`RunLimits::max_str_bytes`].
+    ///
+    /// Returns `false` if the limit would be exceeded by the value; in this case the register is
+    /// set to `None`.
+    pub(crate) fn put_s(&mut self, index: impl Into<RegS>, value: Option<ByteStr>) -> bool {
+        let index = index.into().as_u8();
+        self.stats.reg_writes += 1;
+        self.s16.remove(&index);
+        let Some(value) = value else {
+            return true;
+        };
+        if let Some(max) = self.limits.max_str_bytes {
+            if self.str_bytes() + value.len() as u32 > max {
+                return false;
+            }
+        }
+        self.acc_str_write(value.len());
+        self.s16.insert(index, value);
+        self.update_str_peak();
+        true
+    }
+
+    #[inline]
+    fn acc_str_write(&mut self, len: u16) { self.stats.str_bytes_written += len as u64 }
+
+    #[inline]
+    fn update_str_peak(&mut self) {
+        self.stats.peak_str_bytes = self.stats.peak_str_bytes.max(self.str_bytes())
+    }
+
+    /// Takes the value out of the string register, leaving it set to `None`.
+    #[inline]
+    pub(crate) fn take_s(&mut self, index: impl Into<RegS>) -> Option<ByteStr> {
+        self.s16.remove(&index.into().as_u8())
+    }
+
     /// Executes provided operation (as callback function) if and only if all the provided registers
     /// contain a value (initialized). Otherwise, sets destination to `None` and does not calls the
     /// callback.
@@ -441,6 +862,471 @@ impl CoreRegs {
     /// Returns vale of `st0` register
     #[inline]
     pub fn status(&self) -> bool { self.st0 }
+
+    /// Sets value of `st0` register
+    #[inline]
+    pub fn set_status(&mut self, status: bool) { self.st0 = status }
+
+    /// Returns value of `co0` register, indicating whether the last integer arithmetic operation
+    /// has overflowed.
+    #[inline]
+    pub fn carry(&self) -> bool { self.co0 }
+
+    /// Sets value of `co0` register
+    #[inline]
+    pub fn set_carry(&mut self, carry: bool) { self.co0 = carry }
+
+    /// Returns value of `fs0` register, containing IEEE-754 exception flags raised by float
+    /// arithmetic operations since the registers were initialized or the flags were cleared.
+    #[inline]
+    pub fn float_flags(&self) -> FloatFlags { self.fs0 }
+
+    /// Clears all flags in `fs0` register.
+    #[inline]
+    pub fn clear_float_flags(&mut self) { self.fs0 = FloatFlags::default() }
+
+    /// Returns limits applied to the program execution
+    #[inline]
+    pub fn limits(&self) -> RunLimits { self.limits }
+
+    /// Sets limits applied to the program execution
+    #[inline]
+    pub fn set_limits(&mut self, limits: RunLimits) { self.limits = limits }
+
+    /// Returns number of instructions executed since the start of the last program run by
+    /// [`crate::Vm`] or the last call to [`CoreRegs::reset_counters`].
+    #[inline]
+    pub fn steps(&self) -> u64 { self.steps }
+
+    /// Returns current depth of the call stack
+    #[inline]
+    pub fn call_depth(&self) -> u16 { self.cp0 }
+
+    /// Saves values of the registers from `first` to `last` index (inclusive) of the register
+    /// bank to the frame stack.
+    ///
+    /// Returns `false` if the range is empty or the frame stack is full (see
+    /// [`FRAME_STACK_SIZE`]), in which case the stack is not modified.
+    pub(crate) fn push_frame(&mut self, reg: impl Into<RegAFR>, first: Reg32, last: Reg32) -> bool {
+        let reg = reg.into();
+        if first > last || self.frames.len() >= FRAME_STACK_SIZE {
+            return false;
+        }
+        let mut frame = RegFrame { reg, first, last, mask: 0, data: Vec::new() };
+        for idx in Reg32::ALL.iter().filter(|idx| (first..=last).contains(*idx)) {
+            if let Some(val) = Option::<Number>::from(self.get(reg, *idx)) {
+                frame.mask |= 1 << u8::from(*idx);
+                frame.data.extend_from_slice(val.as_ref());
+            }
+        }
+        self.frames.push(frame);
+        true
+    }
+
+    /// Restores values of the registers from the frame on the top of the frame stack, removing
+    /// the frame from the stack. The registers which had no value when the frame was saved are
+    /// set to `None`.
+    ///
+    /// Returns `false` if the stack is empty or the top frame was saved for other registers than
+    /// the provided ones, in which case neither the registers nor the stack are modified.
+    pub(crate) fn pop_frame(&mut self, reg: impl Into<RegAFR>, first: Reg32, last: Reg32) -> bool {
+        let reg = reg.into();
+        match self.frames.last() {
+            Some(frame) if frame.reg == reg && frame.first == first && frame.last == last => {}
+            _ => return false,
+        }
+        let frame = self.frames.pop().expect("frame presence is checked above");
+        let mut data = frame.data.chunks(reg.bytes() as usize);
+        for idx in Reg32::ALL.iter().filter(|idx| (first..=last).contains(*idx)) {
+            let val = if frame.mask & (1 << u8::from(*idx)) != 0 {
+                let bytes = data.next().expect("frame data match its mask");
+                MaybeNumber::from(Number::with(bytes, reg.layout()).expect("register layout size"))
+            } else {
+                MaybeNumber::none()
+            };
+            self.set(reg, *idx, val);
+        }
+        true
+    }
+
+    /// Returns number of register frames saved to the frame stack by
+    /// [`FrameOp`](crate::isa::FrameOp) instructions.
+    #[inline]
+    pub fn frame_depth(&self) -> usize { self.frames.len() }
+
+    /// Accounts for a single executed instruction, checking it against execution limits.
+    ///
+    /// Sets `st0` to `false` if any of the limits is exceeded. Otherwise, does not modify `st0`
+    /// value.
+    ///
+    /// # Errors
+    ///
+    /// If the instruction has exceeded one of the limits.
+    pub(crate) fn acc_step(&mut self, backward_jump: bool) -> Result<(), RunLimitError> {
+        self.steps = self.steps.saturating_add(1);
+        if backward_jump {
+            self.back_jumps = self.back_jumps.saturating_add(1);
+        }
+        let res = match self.limits {
+            RunLimits { max_steps: Some(max), .. } if self.steps > max => {
+                Err(RunLimitError::StepsExceeded(max))
+            }
+            RunLimits { max_backward_jumps: Some(max), .. } if self.back_jumps > max => {
+                Err(RunLimitError::BackwardJumpsExceeded(max))
+            }
+            RunLimits { max_call_depth: Some(max), .. } if self.cp0 > max => {
+                Err(RunLimitError::CallDepthExceeded(max))
+            }
+            RunLimits { max_calls: Some(max), .. } if self.receipt.len() > max as usize => {
+                Err(RunLimitError::CallsExceeded(max))
+            }
+            #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+            _ if matches!(self.deadline, Some(deadline)
+                if self.steps % DEADLINE_CHECK_STEPS == 0 && std::time::Instant::now() >= deadline) =>
+            {
+                Err(RunLimitError::Timeout)
+            }
+            _ => Ok(()),
+        };
+        if res.is_err() {
+            self.st0 = false;
+        }
+        res
+    }
+
+    /// Sets value of the instruction counter at which the program execution will be suspended,
+    /// or removes the suspension point if `None` is provided.
+    #[inline]
+    pub(crate) fn set_suspend_at(&mut self, steps: Option<u64>) { self.suspend_at = steps }
+
+    /// Sets wall-clock deadline after which the program execution is terminated, or removes it if
+    /// `None` is provided.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    #[inline]
+    pub(crate) fn set_deadline(&mut self, deadline: Option<std::time::Instant>) {
+        self.deadline = deadline
+    }
+
+    /// Detects whether the program execution must be suspended before the next instruction.
+    #[inline]
+    pub(crate) fn is_suspended(&self) -> bool {
+        matches!(self.suspend_at, Some(steps) if self.steps >= steps)
+    }
+
+    /// Requests the host function call made by the instruction at `site`, which is passed to the
+    /// embedder once the execution gets suspended before the next instruction. Returns `false` if
+    /// the execution can't be suspended, i.e. it is not performed in chunks.
+    ///
+    /// The call is logged into the [`CoreRegs::receipt`] even if it is rejected.
+    pub(crate) fn request_host_call(
+        &mut self,
+        site: LibSite,
+        func: u16,
+        reg: RegAR,
+        dst: Reg32,
+    ) -> bool {
+        let args = self.digest();
+        self.receipt.push(CallRecord::Host { site, func, args });
+        if self.suspend_at.is_none() {
+            return false;
+        }
+        self.host_call = Some((func, reg, dst));
+        self.suspend_at = Some(self.steps);
+        true
+    }
+
+    /// Takes the host function call requested by the last executed instruction, if any.
+    #[inline]
+    pub(crate) fn take_host_call(&mut self) -> Option<(u16, RegAR, Reg32)> { self.host_call.take() }
+
+    /// Resets counters of the executed instructions and backward jumps, which are checked against
+    /// the [`RunLimits`].
+    #[inline]
+    pub fn reset_counters(&mut self) {
+        self.steps = 0;
+        self.back_jumps = 0;
+    }
+
+    /// Returns log of the external calls made since the start of the last program run by
+    /// [`crate::Vm`] or the last call to [`CoreRegs::reset_receipt`].
+    #[inline]
+    pub fn receipt(&self) -> &CallReceipt { &self.receipt }
+
+    /// Clears log of the external calls.
+    #[inline]
+    pub fn reset_receipt(&mut self) { self.receipt = CallReceipt::default() }
+
+    /// Logs the external call.
+    #[inline]
+    pub(crate) fn log_call(&mut self, record: CallRecord) { self.receipt.push(record) }
+
+    /// Returns statistics of the resources used since the start of the last program run by
+    /// [`crate::Vm`] or the last call to [`CoreRegs::reset_stats`].
+    #[inline]
+    pub fn stats(&self) -> ExecStats { self.stats }
+
+    /// Resets statistics of the used resources. The peak string register memory is set to the
+    /// current aggregate length of the strings in the registers.
+    #[inline]
+    pub fn reset_stats(&mut self) {
+        self.stats = ExecStats { peak_str_bytes: self.str_bytes(), ..default!() }
+    }
+
+    /// Accounts reads from the library data segment.
+    #[inline]
+    pub(crate) fn acc_data_reads(&mut self, count: u32) { self.stats.data_reads += count as u64 }
+
+    /// Starts collection of the statistics for a single library run, returning the statistics
+    /// to be passed to [`CoreRegs::end_stats`] once the run completes.
+    #[inline]
+    pub(crate) fn begin_stats(&mut self) -> ExecStats {
+        let stats = self.stats;
+        self.stats.peak_str_bytes = self.str_bytes();
+        stats
+    }
+
+    /// Completes collection of the statistics started with [`CoreRegs::begin_stats`], returning
+    /// the statistics of the library run.
+    #[inline]
+    pub(crate) fn end_stats(&mut self, start: ExecStats) -> ExecStats {
+        let stats = self.stats.since(start);
+        self.stats.peak_str_bytes = self.stats.peak_str_bytes.max(start.peak_str_bytes);
+        stats
+    }
+
+    /// Computes SHA256 digest of the values of all the registers and `st0` (see
+    /// [`CoreRegs::absorb`]).
+    pub(crate) fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::default();
+        self.absorb(&mut hasher);
+        hasher.finalize().into()
+    }
+
+    /// Feeds the values of all the registers and `st0` into the hasher; registers without a value
+    /// are represented by a zero byte, and registers with a value by a byte `1` followed by the
+    /// 16-bit length of the value and the value itself.
+    pub(crate) fn absorb(&self, hasher: &mut Sha256) {
+        let mut value = |bytes: Option<&[u8]>| match bytes {
+            None => hasher.update([0u8]),
+            Some(bytes) => {
+                hasher.update([1u8]);
+                hasher.update((bytes.len() as u16).to_le_bytes());
+                hasher.update(bytes);
+            }
+        };
+        for idx in Reg32::ALL {
+            for reg in RegA::ALL {
+                value(self.get(reg, idx).as_ref().map(|num| num.as_ref()));
+            }
+            for reg in RegF::ALL {
+                value(self.get(reg, idx).as_ref().map(|num| num.as_ref()));
+            }
+            for reg in RegR::ALL {
+                value(self.get(reg, idx).as_ref().map(|num| num.as_ref()));
+            }
+        }
+        for reg in RegS::ALL {
+            value(self.get_s(reg).map(|s| s.as_ref()));
+        }
+        hasher.update([self.st0 as u8]);
+    }
+
+    /// Returns findings of the execution audit, collected since the start of the last program run
+    /// by [`crate::Vm`] or the last call to [`CoreRegs::reset_audit`].
+    #[cfg(feature = "deterministic-audit")]
+    #[inline]
+    pub fn audit(&self) -> &Audit { &self.audit }
+
+    /// Clears findings of the execution audit. The registers having values at the moment of the
+    /// next audited instruction are considered initialized by the host.
+    #[cfg(feature = "deterministic-audit")]
+    #[inline]
+    pub fn reset_audit(&mut self) { self.audit = Audit::default() }
+
+    /// Audits instruction at the given location before its execution.
+    #[cfg(feature = "deterministic-audit")]
+    pub(crate) fn audit_instr<Isa: InstructionSet>(&mut self, instr: &Isa, site: LibSite) {
+        let mut audit = core::mem::take(&mut self.audit);
+        audit.check(instr, site, self);
+        self.audit = audit;
+    }
+
+    /// Serializes register state, including control registers, call stack, execution limits,
+    /// counters and saved register frames, into a compact binary representation, which may be
+    /// restored with [`CoreRegs::restore`].
+    ///
+    /// The snapshot does not include the data collected during a program run for the host: the
+    /// [receipt](CoreRegs::receipt) of the external calls, the [statistics](CoreRegs::stats) and
+    /// the audit findings. Neither it includes the wall-clock deadline, which is meaningful only
+    /// within the running process, and the host function call requested by the last executed
+    /// instruction, which is taken over by [`crate::PendingCall`] when [`crate::Vm`] suspends the
+    /// execution. These are reset in the restored registers.
+    ///
+    /// Each register bank is represented by a bit mask of the registers having a value, followed
+    /// by the little-endian values of these registers; the call stack is stored only up to its
+    /// current depth.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.push(self.st0 as u8);
+        buf.push(self.co0 as u8);
+        let fs0 = [
+            self.fs0.invalid,
+            self.fs0.div_by_zero,
+            self.fs0.overflow,
+            self.fs0.underflow,
+            self.fs0.inexact,
+        ];
+        buf.push(fs0.iter().rev().fold(0u8, |bits, flag| bits << 1 | *flag as u8));
+        buf.extend(self.cy0.to_le_bytes());
+        buf.extend(self.ca0.to_le_bytes());
+        write_option(&mut buf, self.cl0, |buf, cl0| buf.extend(cl0.to_le_bytes()));
+        buf.extend(self.cp0.to_le_bytes());
+        for site in &self.cs0[..self.cp0 as usize] {
+            write_site(&mut buf, *site);
+        }
+
+        let limits = self.limits;
+        write_option(&mut buf, limits.max_steps, |buf, max| buf.extend(max.to_le_bytes()));
+        write_option(&mut buf, limits.max_backward_jumps, |buf, max| buf.extend(max.to_le_bytes()));
+        write_option(&mut buf, limits.max_call_depth, |buf, max| buf.extend(max.to_le_bytes()));
+        write_option(&mut buf, limits.max_str_bytes, |buf, max| buf.extend(max.to_le_bytes()));
+        write_option(&mut buf, limits.max_calls, |buf, max| buf.extend(max.to_le_bytes()));
+        buf.extend(self.steps.to_le_bytes());
+        buf.extend(self.back_jumps.to_le_bytes());
+
+        for reg in numeric_banks() {
+            let vals = Reg32::ALL.map(|idx| Option::<Number>::from(self.get(reg, idx)));
+            let mask = vals
+                .iter()
+                .enumerate()
+                .fold(0u32, |mask, (idx, val)| mask | (val.is_some() as u32) << idx);
+            buf.extend(mask.to_le_bytes());
+            for val in vals.iter().flatten() {
+                buf.extend_from_slice(val.as_ref());
+            }
+        }
+
+        let mask = self.s16.keys().fold(0u16, |mask, idx| mask | 1 << idx);
+        buf.extend(mask.to_le_bytes());
+        for val in self.s16.values() {
+            buf.extend(val.len().to_le_bytes());
+            buf.extend_from_slice(val.as_ref());
+        }
+
+        let depth = u16::try_from(self.frames.len()).expect("frame stack exceeds FRAME_STACK_SIZE");
+        buf.extend(depth.to_le_bytes());
+        for frame in &self.frames {
+            let bank = numeric_banks().position(|reg| reg == frame.reg).expect("all banks");
+            buf.push(bank as u8);
+            buf.push(frame.first.into());
+            buf.push(frame.last.into());
+            buf.extend(frame.mask.to_le_bytes());
+            buf.extend_from_slice(&frame.data);
+        }
+
+        buf
+    }
+
+    /// Restores register state from the data produced by [`CoreRegs::snapshot`].
+    ///
+    /// # Errors
+    ///
+    /// If the data are truncated, contain invalid values or some extra bytes after the end of
+    /// the state data.
+    pub fn restore(data: &[u8]) -> Result<CoreRegs, SnapshotError> {
+        let mut reader = SnapshotReader(data);
+
+        let st0 = reader.bool("st0")?;
+        let co0 = reader.bool("co0")?;
+        let fs0 = reader.u8()?;
+        if fs0 >> 5 != 0 {
+            return Err(SnapshotError::InvalidValue("fs0"));
+        }
+        let fs0 = FloatFlags {
+            invalid: fs0 & 0x01 != 0,
+            div_by_zero: fs0 & 0x02 != 0,
+            overflow: fs0 & 0x04 != 0,
+            underflow: fs0 & 0x08 != 0,
+            inexact: fs0 & 0x10 != 0,
+        };
+        let cy0 = reader.u16()?;
+        let ca0 = reader.u64()?;
+        let cl0 = reader.option("cl0", SnapshotReader::u64)?;
+        let cp0 = reader.u16()?;
+        let mut cs0 = vec![LibSite::default(); CALL_STACK_SIZE];
+        for site in &mut cs0[..cp0 as usize] {
+            *site = reader.site()?;
+        }
+        let limits = RunLimits {
+            max_steps: reader.option("max_steps", SnapshotReader::u64)?,
+            max_backward_jumps: reader.option("max_backward_jumps", SnapshotReader::u32)?,
+            max_call_depth: reader.option("max_call_depth", SnapshotReader::u16)?,
+            max_str_bytes: reader.option("max_str_bytes", SnapshotReader::u32)?,
+            max_calls: reader.option("max_calls", SnapshotReader::u32)?,
+        };
+        let steps = reader.u64()?;
+        let back_jumps = reader.u32()?;
+
+        let mut regs = CoreRegs {
+            st0,
+            fs0,
+            co0,
+            cy0,
+            ca0,
+            cl0,
+            cs0,
+            cp0,
+            limits,
+            steps,
+            back_jumps,
+            ..default!()
+        };
+
+        for reg in numeric_banks() {
+            let mask = reader.u32()?;
+            for idx in Reg32::ALL.iter().filter(|idx| mask & (1 << u8::from(**idx)) != 0) {
+                let bytes = reader.bytes(reg.bytes() as usize)?;
+                let val = Number::with(bytes, reg.layout()).expect("register layout size");
+                if !regs.set(reg, *idx, val) {
+                    return Err(SnapshotError::InvalidValue("F register"));
+                }
+            }
+        }
+
+        let mask = reader.u16()?;
+        for idx in (0..16u8).filter(|idx| mask & (1 << idx) != 0) {
+            let len = reader.u16()?;
+            regs.s16.insert(idx, ByteStr::with(reader.bytes(len as usize)?));
+        }
+
+        let depth = reader.u16()? as usize;
+        if depth > FRAME_STACK_SIZE {
+            return Err(SnapshotError::InvalidValue("frame stack depth"));
+        }
+        for _ in 0..depth {
+            let reg = numeric_banks()
+                .nth(reader.u8()? as usize)
+                .ok_or(SnapshotError::InvalidValue("frame register bank"))?;
+            let mut index = || {
+                Reg32::ALL
+                    .get(reader.u8()? as usize)
+                    .copied()
+                    .ok_or(SnapshotError::InvalidValue("frame register index"))
+            };
+            let (first, last) = (index()?, index()?);
+            let mask = reader.u32()?;
+            if first > last || mask & !range_mask(first, last) != 0 {
+                return Err(SnapshotError::InvalidValue("frame register range"));
+            }
+            let len = mask.count_ones() as usize * reg.bytes() as usize;
+            let data = reader.bytes(len)?.to_vec();
+            regs.frames.push(RegFrame { reg, first, last, mask, data });
+        }
+
+        reader.finish()?;
+        Ok(regs)
+    }
 }
 
 impl Debug for CoreRegs {
@@ -453,6 +1339,8 @@ impl Debug for CoreRegs {
 
         write!(f, "{}CTRL:{}\t", sect, reset)?;
         write!(f, "{}st0{}={}{} ", reg, eq, val, self.st0)?;
+        write!(f, "{}co0{}={}{} ", reg, eq, val, self.co0)?;
+        write!(f, "{}fs0{}={}{} ", reg, eq, val, self.fs0)?;
         write!(f, "{}cy0{}={}{} ", reg, eq, val, self.cy0)?;
         write!(f, "{}ca0{}={}{} ", reg, eq, val, self.ca0)?;
         let cl = self.cl0.map(|v| v.to_string()).unwrap_or_else(|| "~".to_string());
@@ -763,10 +1651,8 @@ impl Debug for CoreRegs {
         }
 
         write!(f, "\n{}S-REG:{}\t", sect, reset)?;
-        for i in 0..16 {
-            if let Some(ref v) = self.s16[i] {
-                write!(f, "{}s16{}[{}{:02}{}]={}{}{}\n\t", reg, eq, reset, i, eq, val, v, reset)?;
-            }
+        for (i, v) in &self.s16 {
+            write!(f, "{}s16{}[{}{:02}{}]={}{}{}\n\t", reg, eq, reset, i, eq, val, v, reset)?;
         }
         Ok(())
     }
@@ -778,6 +1664,19 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn call_stack() {
+        let mut regs = CoreRegs::new();
+        let lib = LibSite::default().lib;
+        assert_eq!(regs.call(Some(LibSite::with(4, lib))), Ok(()));
+        assert_eq!(regs.call(Some(LibSite::with(8, lib))), Ok(()));
+        assert_eq!(regs.cp0, 2);
+        assert_eq!(regs.ret(), Some(LibSite::with(8, lib)));
+        assert_eq!(regs.ret(), Some(LibSite::with(4, lib)));
+        assert_eq!(regs.ret(), None);
+        assert_eq!(regs.cp0, 0);
+    }
+
     // Checks that we do not overflow the stack if using all registers
     #[test]
     fn init_all() {
@@ -807,4 +1706,97 @@ mod test {
 
         eprintln!("{regs:#?}");
     }
+
+    #[test]
+    fn snapshot_restore() {
+        let mut regs = CoreRegs::new();
+        assert_eq!(CoreRegs::restore(&regs.snapshot()).unwrap().snapshot(), regs.snapshot());
+
+        regs.set(RegA::A8, Reg32::Reg0, 0xA5u8);
+        regs.set(RegA::A1024, Reg32::Reg31, u1024::from(u64::MAX));
+        regs.set(
+            RegF::F64,
+            Reg32::Reg7,
+            ieee::Double::from_bits(u256::from(0x4000_0000_0000_0000u64)),
+        );
+        regs.set(RegR::R8192, Reg32::Reg3, Number::from_slice([0x77u8; 1024]));
+        regs.set_s(RegS::from(15), Some(ByteStr::with(b"snapshot")));
+        regs.set_s(RegS::from(1), Some(ByteStr::default()));
+        regs.st0 = false;
+        regs.co0 = true;
+        regs.fs0 = FloatFlags { overflow: true, inexact: true, ..default!() };
+        regs.cl0 = Some(1000);
+        regs.ca0 = 17;
+        regs.call(Some(LibSite::with(0x0102, [0xEE; 32].into()))).unwrap();
+        regs.set_limits(
+            RunLimits::unlimited()
+                .with_max_call_depth(4)
+                .with_max_str_bytes(1024)
+                .with_max_calls(8),
+        );
+        regs.steps = 42;
+        regs.back_jumps = 3;
+        assert!(regs.push_frame(RegA::A8, Reg32::Reg0, Reg32::Reg3));
+        assert!(regs.push_frame(RegR::R8192, Reg32::Reg3, Reg32::Reg31));
+
+        let snapshot = regs.snapshot();
+        let restored = CoreRegs::restore(&snapshot).unwrap();
+        assert_eq!(restored.snapshot(), snapshot);
+        assert_eq!(format!("{restored:?}"), format!("{regs:?}"));
+        assert_eq!(restored.call_depth(), 1);
+        assert_eq!(restored.limits(), regs.limits());
+        assert_eq!(restored.steps(), 42);
+        assert_eq!(restored.frame_depth(), 2);
+
+        assert_eq!(
+            CoreRegs::restore(&snapshot[..snapshot.len() - 1]).unwrap_err(),
+            SnapshotError::Truncated
+        );
+        let mut data = snapshot.clone();
+        data.push(0);
+        assert_eq!(CoreRegs::restore(&data).unwrap_err(), SnapshotError::TrailingData(1));
+        data[0] = 2;
+        assert_eq!(CoreRegs::restore(&data).unwrap_err(), SnapshotError::InvalidValue("st0"));
+    }
+
+    #[test]
+    fn marshaling() {
+        let mut regs = CoreRegs::new();
+        assert_eq!(regs.set_a(RegA::A16, Reg32::Reg1, 300u64), Ok(()));
+        assert_eq!(regs.get_a(RegA::A16, Reg32::Reg1), Some(Number::from(300u16)));
+        assert_eq!(regs.set_a(RegA::A8, Reg32::Reg0, -1i64), Ok(()));
+        assert_eq!(regs.get_a(RegA::A8, Reg32::Reg0), Some(Number::from(255u8)));
+
+        let a8 = Reg::A(RegA::A8, Reg32::Reg0);
+        assert_eq!(
+            regs.set_reg(a8, Number::from(300u16)),
+            Err(MarshalError::Overflow { reg: a8, layout: Layout::unsigned(2) })
+        );
+        assert_eq!(
+            regs.set_reg(a8, Number::from(128i16)),
+            Err(MarshalError::Overflow { reg: a8, layout: Layout::signed(2) })
+        );
+        let f32 = Reg::F(RegF::F32, Reg32::Reg0);
+        assert_eq!(
+            regs.set_reg(f32, Number::from(1u32)),
+            Err(MarshalError::LayoutMismatch { reg: f32, layout: Layout::unsigned(4) })
+        );
+        let s = Reg::S(RegS::from(0));
+        assert_eq!(regs.set_reg(s, Number::from(1u8)), Err(MarshalError::NotNumeric(s)));
+        assert_eq!(regs.get_reg(s), None);
+
+        let r128 = Reg::R(RegR::R128, Reg32::Reg3);
+        let a64 = Reg::A(RegA::A64, Reg32::Reg2);
+        assert_eq!(
+            regs.load_inputs(&[(a64, Number::from(7u8)), (a8, Number::from(256u16))]),
+            Err(MarshalError::Overflow { reg: a8, layout: Layout::unsigned(2) })
+        );
+        assert_eq!(regs.get_reg(a64), None);
+        assert_eq!(
+            regs.load_inputs(&[(a64, Number::from(7u8)), (r128, Number::from(9u8))]),
+            Ok(())
+        );
+        assert_eq!(regs.get_reg(a64), Some(Number::from(7u64)));
+        assert_eq!(regs.get_r(RegR::R128, Reg32::Reg3), Some(Number::from(9u128)));
+    }
 }
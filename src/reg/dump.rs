@@ -0,0 +1,228 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Human-oriented dumps of register values.
+
+use alloc::string::ToString;
+use core::fmt::{self, Display, Formatter, Write};
+
+use amplify::num::u4;
+
+use super::{CoreRegs, NumericRegister, Reg, Reg32, RegA, RegAFR, RegF, RegR, RegS};
+use crate::data::Number;
+
+const COLOR_CHANGED: &str = "\x1B[0;33m";
+const COLOR_CLEARED: &str = "\x1B[0;31m";
+const COLOR_RESET: &str = "\x1B[0m";
+
+/// Human-oriented dump of register values, omitting the registers which have no value set.
+///
+/// Each value is shown in hexadecimal form followed by its interpretation according to the
+/// register layout: unsigned decimal for `A` registers up to 128 bits (with the signed value in
+/// brackets if the most significant bit is set), float value for `F` registers and escaped string
+/// literal for `S` registers.
+///
+/// Dumps constructed with [`CoreRegs::diff`] mark the registers which values has changed since
+/// the previous state with `*`, and list the registers which were cleared with `-` mark. The
+/// alternate form (`{:#}`) highlights the changes with ANSI colors.
+#[derive(Copy, Clone, Debug)]
+pub struct RegDump<'regs> {
+    regs: &'regs CoreRegs,
+    prev: Option<&'regs CoreRegs>,
+}
+
+impl CoreRegs {
+    /// Returns human-oriented dump of the register values.
+    #[inline]
+    pub fn dump(&self) -> RegDump<'_> { RegDump { regs: self, prev: None } }
+
+    /// Returns human-oriented dump of the register values highlighting differences from the
+    /// `prev` state of the registers.
+    #[inline]
+    pub fn diff<'regs>(&'regs self, prev: &'regs CoreRegs) -> RegDump<'regs> {
+        RegDump { regs: self, prev: Some(prev) }
+    }
+}
+
+impl Display for CoreRegs {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Display::fmt(&self.dump(), f) }
+}
+
+impl<'regs> RegDump<'regs> {
+    fn fmt_mark(&self, f: &mut Formatter<'_>, changed: bool, cleared: bool) -> fmt::Result {
+        match (cleared, changed, f.alternate()) {
+            (true, _, true) => write!(f, "{}- ", COLOR_CLEARED),
+            (true, _, false) => f.write_str("- "),
+            (false, true, true) => write!(f, "{}* ", COLOR_CHANGED),
+            (false, true, false) => f.write_str("* "),
+            (false, false, _) => f.write_str("  "),
+        }
+    }
+
+    fn fmt_end(&self, f: &mut Formatter<'_>, highlighted: bool) -> fmt::Result {
+        if highlighted && f.alternate() {
+            f.write_str(COLOR_RESET)?;
+        }
+        f.write_char('\n')
+    }
+
+    fn fmt_ctrl(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let regs = self.regs;
+        let changed = self.prev.map_or(false, |prev| {
+            (prev.st0, prev.fs0, prev.co0, prev.cy0, prev.ca0, prev.cl0, prev.cp0)
+                != (regs.st0, regs.fs0, regs.co0, regs.cy0, regs.ca0, regs.cl0, regs.cp0)
+        });
+        self.fmt_mark(f, changed, false)?;
+        let cl0 = regs.cl0.map(|v| v.to_string()).unwrap_or_else(|| "~".to_string());
+        write!(
+            f,
+            "st0={} fs0={} co0={} cy0={} ca0={} cl0={} cp0={}",
+            regs.st0, regs.fs0, regs.co0, regs.cy0, regs.ca0, cl0, regs.cp0
+        )?;
+        self.fmt_end(f, changed)
+    }
+
+    fn fmt_number(&self, f: &mut Formatter<'_>, reg: RegAFR, idx: Reg32) -> fmt::Result {
+        let val = self.regs.get(reg, idx);
+        let prev = self.prev.map(|prev| prev.get(reg, idx));
+        let cleared = val.is_none() && prev.map_or(false, |prev| prev.is_some());
+        let Some(num) = *val else {
+            if cleared {
+                self.fmt_mark(f, false, true)?;
+                write!(f, "{:<10} = ~", Reg::new(reg, idx).to_string())?;
+                return self.fmt_end(f, true);
+            }
+            return Ok(());
+        };
+        let changed = prev.map_or(false, |prev| prev != val);
+        self.fmt_mark(f, changed, false)?;
+        write!(f, "{:<10} = ", Reg::new(reg, idx).to_string())?;
+        fmt_hex(f, &num)?;
+        match reg {
+            RegAFR::A(a) if a.bytes() <= 16 => {
+                write!(f, "  {}", u128::from(num))?;
+                if num.into_signed().is_negative() {
+                    write!(f, " ({})", i128::from(num.into_signed()))?;
+                }
+            }
+            RegAFR::A(_) => {}
+            RegAFR::F(_) => write!(f, "  {}", num)?,
+            RegAFR::R(_) => {}
+        }
+        self.fmt_end(f, changed)
+    }
+
+    fn fmt_str(&self, f: &mut Formatter<'_>, idx: RegS) -> fmt::Result {
+        let val = self.regs.get_s(idx);
+        let prev = self.prev.map(|prev| prev.get_s(idx));
+        let name = Reg::from(idx).to_string();
+        let Some(s) = val else {
+            if prev.map_or(false, |prev| prev.is_some()) {
+                self.fmt_mark(f, false, true)?;
+                write!(f, "{:<10} = ~", name)?;
+                return self.fmt_end(f, true);
+            }
+            return Ok(());
+        };
+        let changed = prev.map_or(false, |prev| prev != val);
+        self.fmt_mark(f, changed, false)?;
+        write!(f, "{:<10} = {} ({} bytes)", name, s, s.len())?;
+        self.fmt_end(f, changed)
+    }
+}
+
+/// Writes big-endian hexadecimal representation of the number bytes.
+fn fmt_hex(f: &mut Formatter<'_>, num: &Number) -> fmt::Result {
+    f.write_str("0x")?;
+    for byte in num.as_ref().iter().rev() {
+        write!(f, "{:02X}", byte)?;
+    }
+    Ok(())
+}
+
+impl<'regs> Display for RegDump<'regs> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_ctrl(f)?;
+        let regs = RegA::ALL
+            .iter()
+            .copied()
+            .map(RegAFR::from)
+            .chain(RegF::ALL.iter().copied().map(RegAFR::from))
+            .chain(RegR::ALL.iter().copied().map(RegAFR::from));
+        for reg in regs {
+            for idx in Reg32::ALL {
+                self.fmt_number(f, reg, idx)?;
+            }
+        }
+        for idx in 0..16u8 {
+            self.fmt_str(f, RegS::from(u4::with(idx)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::{ByteStr, MaybeNumber};
+
+    #[test]
+    fn dump() {
+        let mut regs = CoreRegs::new();
+        regs.set(RegA::A8, Reg32::Reg1, MaybeNumber::from(0xFFu8));
+        regs.set(RegA::A16, Reg32::Reg0, MaybeNumber::from(300u16));
+        regs.set(RegR::R128, Reg32::Reg2, MaybeNumber::from(Number::from(1u128)));
+        regs.set_s(RegS::from(u4::with(3)), Some(ByteStr::with(b"hi")));
+        assert_eq!(
+            regs.to_string(),
+            "  st0=true fs0=- co0=false cy0=0 ca0=0 cl0=~ cp0=0
+  a8[1]      = 0xFF  255 (-1)
+  a16[0]     = 0x012C  300
+  r128[2]    = 0x00000000000000000000000000000001
+  s16[3]     = \"hi\" (2 bytes)
+"
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let mut prev = CoreRegs::new();
+        prev.set(RegA::A8, Reg32::Reg1, MaybeNumber::from(1u8));
+        prev.set(RegA::A8, Reg32::Reg2, MaybeNumber::from(2u8));
+        prev.set(RegA::A8, Reg32::Reg3, MaybeNumber::from(3u8));
+        let mut regs = prev.clone();
+        regs.set(RegA::A8, Reg32::Reg2, MaybeNumber::from(4u8));
+        regs.set(RegA::A8, Reg32::Reg3, MaybeNumber::none());
+        regs.set_status(false);
+        assert_eq!(
+            regs.diff(&prev).to_string(),
+            "* st0=false fs0=- co0=false cy0=0 ca0=0 cl0=~ cp0=0
+  a8[1]      = 0x01  1
+* a8[2]      = 0x04  4
+- a8[3]      = ~
+"
+        );
+        assert!(format!("{:#}", regs.diff(&prev)).contains("\x1B[0;33m* a8[2]"));
+    }
+}
@@ -23,10 +23,11 @@
 
 use core::convert::TryFrom;
 
+use amplify::num::error::OverflowError;
 use amplify::num::{u1, u3, u4};
 
 use crate::data as number;
-use crate::reg::Register;
+use crate::reg::{Reg32, RegS, Register};
 
 /// Common set of methods handled by different sets and families of VM registers
 pub trait NumericRegister: Register {
@@ -145,6 +146,14 @@ impl From<RegA> for u3 {
     fn from(rega: RegA) -> Self { u3::with(rega as u8) }
 }
 
+impl TryFrom<u8> for RegA {
+    type Error = OverflowError;
+
+    /// Constructs register family from its bytecode representation, which must be less than 8.
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u3::try_from(value).map(RegA::from) }
+}
+
 impl From<u3> for RegA {
     fn from(val: u3) -> Self {
         match val {
@@ -381,6 +390,14 @@ impl From<RegF> for u3 {
     fn from(regf: RegF) -> Self { u3::with(regf as u8) }
 }
 
+impl TryFrom<u8> for RegF {
+    type Error = OverflowError;
+
+    /// Constructs register family from its bytecode representation, which must be less than 8.
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u3::try_from(value).map(RegF::from) }
+}
+
 impl From<u3> for RegF {
     fn from(val: u3) -> Self {
         match val {
@@ -506,6 +523,14 @@ impl From<RegR> for u3 {
     fn from(regr: RegR) -> Self { u3::with(regr as u8) }
 }
 
+impl TryFrom<u8> for RegR {
+    type Error = OverflowError;
+
+    /// Constructs register family from its bytecode representation, which must be less than 8.
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u3::try_from(value).map(RegR::from) }
+}
+
 impl From<u3> for RegR {
     fn from(val: u3) -> Self {
         match val {
@@ -1168,3 +1193,56 @@ impl From<RegAll> for RegBlock {
         }
     }
 }
+
+/// Specific register, identified by its family and index.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Display)]
+pub enum Reg {
+    /// Arithmetic integer register (`A` register)
+    #[display("{0}{1}")]
+    A(RegA, Reg32),
+
+    /// Arithmetic float register (`F` register)
+    #[display("{0}{1}")]
+    F(RegF, Reg32),
+
+    /// Non-arithmetic (general) register (`R` register)
+    #[display("{0}{1}")]
+    R(RegR, Reg32),
+
+    /// Byte-string register (`S` register)
+    #[display(inner)]
+    S(RegS),
+}
+
+impl Reg {
+    /// Constructs `A`, `F` or `R` register from its family and index.
+    pub fn new(reg: impl Into<RegAFR>, idx: impl Into<Reg32>) -> Self {
+        let idx = idx.into();
+        match reg.into() {
+            RegAFR::A(a) => Reg::A(a, idx),
+            RegAFR::F(f) => Reg::F(f, idx),
+            RegAFR::R(r) => Reg::R(r, idx),
+        }
+    }
+
+    /// Returns family of the register.
+    #[inline]
+    pub fn family(self) -> RegAll {
+        match self {
+            Reg::A(a, _) => RegAll::A(a),
+            Reg::F(f, _) => RegAll::F(f),
+            Reg::R(r, _) => RegAll::R(r),
+            Reg::S(_) => RegAll::S,
+        }
+    }
+}
+
+impl From<RegS> for Reg {
+    #[inline]
+    fn from(reg: RegS) -> Self { Reg::S(reg) }
+}
+
+impl From<&RegS> for Reg {
+    #[inline]
+    fn from(reg: &RegS) -> Self { Reg::S(*reg) }
+}
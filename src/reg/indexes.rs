@@ -235,6 +235,13 @@ impl From<&Reg32> for Reg32 {
     fn from(reg32: &Reg32) -> Self { *reg32 }
 }
 
+impl TryFrom<u8> for Reg32 {
+    type Error = OverflowError;
+
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u5::try_from(value).map(Reg32::from) }
+}
+
 impl From<u5> for Reg32 {
     fn from(val: u5) -> Self {
         match val {
@@ -384,6 +391,13 @@ impl From<Reg16> for u4 {
     fn from(reg16: Reg16) -> Self { u4::with(reg16 as u8) }
 }
 
+impl TryFrom<u8> for Reg16 {
+    type Error = OverflowError;
+
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u4::try_from(value).map(Reg16::from) }
+}
+
 impl From<u4> for Reg16 {
     fn from(val: u4) -> Self {
         match val {
@@ -495,6 +509,13 @@ impl From<Reg8> for u3 {
     fn from(reg8: Reg8) -> Self { u3::with(reg8 as u8) }
 }
 
+impl TryFrom<u8> for Reg8 {
+    type Error = OverflowError;
+
+    #[inline]
+    fn try_from(value: u8) -> Result<Self, Self::Error> { u3::try_from(value).map(Reg8::from) }
+}
+
 impl From<u3> for Reg8 {
     fn from(val: u3) -> Self {
         match val {
@@ -539,6 +560,33 @@ impl TryFrom<Reg32> for Reg8 {
 pub struct RegS(#[from] u4);
 
 impl RegS {
+    /// Set of all string registers
+    pub const ALL: [RegS; 16] = [
+        RegS(u4::with(0)),
+        RegS(u4::with(1)),
+        RegS(u4::with(2)),
+        RegS(u4::with(3)),
+        RegS(u4::with(4)),
+        RegS(u4::with(5)),
+        RegS(u4::with(6)),
+        RegS(u4::with(7)),
+        RegS(u4::with(8)),
+        RegS(u4::with(9)),
+        RegS(u4::with(10)),
+        RegS(u4::with(11)),
+        RegS(u4::with(12)),
+        RegS(u4::with(13)),
+        RegS(u4::with(14)),
+        RegS(u4::with(15)),
+    ];
+
+    /// Constructs string register from its index, which must be less than 16.
+    ///
+    /// Unlike `From<u8>` conversion, which takes the index modulo 16, the method fails on
+    /// out-of-range indexes.
+    #[inline]
+    pub fn checked(index: u8) -> Result<Self, OverflowError> { u4::try_from(index).map(RegS) }
+
     /// Returns `u8` value corresponding to the register number
     #[inline]
     pub fn as_u8(self) -> u8 { self.0.to_u8() }
@@ -574,6 +622,8 @@ impl From<RegS> for usize {
 }
 
 impl From<u8> for RegS {
+    /// Constructs string register from the index modulo 16; see [`RegS::checked`] for the
+    /// conversion failing on out-of-range indexes.
     #[inline]
     fn from(val: u8) -> Self { RegS(u4::with(val % 16)) }
 }
@@ -632,3 +682,33 @@ impl TryFrom<RegAll> for RegS {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reg::{RegA, RegF, RegR};
+
+    #[test]
+    fn checked_indexes() {
+        assert_eq!(Reg32::try_from(31u8), Ok(Reg32::Reg31));
+        assert!(Reg32::try_from(32u8).is_err());
+        assert_eq!(Reg16::try_from(15u8), Ok(Reg16::Reg15));
+        assert!(Reg16::try_from(16u8).is_err());
+        assert_eq!(Reg8::try_from(7u8), Ok(Reg8::Reg7));
+        assert!(Reg8::try_from(8u8).is_err());
+        assert_eq!(RegS::checked(15), Ok(RegS::from(15)));
+        assert!(RegS::checked(16).is_err());
+
+        assert_eq!(RegA::try_from(0u8), Ok(RegA::A8));
+        assert_eq!(RegF::try_from(7u8), Ok(RegF::F512));
+        assert_eq!(RegR::try_from(2u8), Ok(RegR::R256));
+        assert!(RegR::try_from(8u8).is_err());
+
+        for (idx, reg) in Reg32::ALL.iter().enumerate() {
+            assert_eq!(Reg32::try_from(idx as u8), Ok(*reg));
+        }
+        for (idx, reg) in RegS::ALL.iter().enumerate() {
+            assert_eq!(reg.as_usize(), idx);
+        }
+    }
+}
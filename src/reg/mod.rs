@@ -24,15 +24,19 @@
 //! AluVM registers system
 
 mod core_regs;
+mod dump;
 mod families;
 mod indexes;
+mod pool;
 
-pub use core_regs::{CoreRegs, CALL_STACK_SIZE};
+pub use core_regs::{CoreRegs, MarshalError, SnapshotError, CALL_STACK_SIZE, FRAME_STACK_SIZE};
+pub use dump::RegDump;
 pub use families::{
-    NumericRegister, RegA, RegA2, RegAF, RegAFR, RegAR, RegAll, RegBlock, RegBlockAFR, RegBlockAR,
-    RegF, RegR,
+    NumericRegister, Reg, RegA, RegA2, RegAF, RegAFR, RegAR, RegAll, RegBlock, RegBlockAFR,
+    RegBlockAR, RegF, RegR,
 };
 pub use indexes::{Reg16, Reg32, Reg8, RegS};
+pub use pool::RegPool;
 
 /// Trait marking all types representing register family, specific register or register index
 pub trait Register: Default {
@@ -0,0 +1,269 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Register allocation helper for code generators
+
+use alloc::collections::BTreeMap;
+
+use super::{Reg32, RegAR, RegS};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+enum Slot {
+    AR(RegAR, Reg32),
+    S(RegS),
+}
+
+/// Register pool tracking which `A`, `R` and `S` registers are used by the generated code.
+///
+/// Registers are acquired and released by the code generator; each of the registers may be
+/// acquired only once until it is released. When a register bank is exhausted, the pool
+/// suggests a register to spill, which is the least recently used register of the bank.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct RegPool {
+    in_use: BTreeMap<Slot, u64>,
+    clock: u64,
+}
+
+impl RegPool {
+    /// Constructs pool with all registers being free.
+    #[inline]
+    pub fn new() -> Self { Self::default() }
+
+    fn mark(&mut self, slot: Slot) {
+        self.clock += 1;
+        self.in_use.insert(slot, self.clock);
+    }
+
+    fn acquire_slot(&mut self, slots: impl IntoIterator<Item = Slot>) -> Option<Slot> {
+        let slot = slots.into_iter().find(|slot| !self.in_use.contains_key(slot))?;
+        self.mark(slot);
+        Some(slot)
+    }
+
+    fn reserve_slot(&mut self, slot: Slot) -> bool {
+        if self.in_use.contains_key(&slot) {
+            return false;
+        }
+        self.mark(slot);
+        true
+    }
+
+    fn touch_slot(&mut self, slot: Slot) -> bool {
+        if !self.in_use.contains_key(&slot) {
+            return false;
+        }
+        self.mark(slot);
+        true
+    }
+
+    fn spill_slot(&self, slots: impl IntoIterator<Item = Slot>) -> Option<Slot> {
+        slots
+            .into_iter()
+            .filter_map(|slot| self.in_use.get(&slot).map(|tick| (*tick, slot)))
+            .min()
+            .map(|(_, slot)| slot)
+    }
+
+    fn ar_slots(reg: RegAR) -> impl Iterator<Item = Slot> {
+        Reg32::ALL.iter().copied().map(move |idx| Slot::AR(reg, idx))
+    }
+
+    fn s_slots() -> impl Iterator<Item = Slot> { (0u8..16).map(|idx| Slot::S(RegS::from(idx))) }
+
+    /// Acquires free register with the lowest index from the `A` or `R` register bank.
+    ///
+    /// # Returns
+    ///
+    /// Index of the acquired register, or `None` if all registers of the bank are in use.
+    pub fn acquire(&mut self, reg: impl Into<RegAR>) -> Option<Reg32> {
+        match self.acquire_slot(Self::ar_slots(reg.into()))? {
+            Slot::AR(_, idx) => Some(idx),
+            Slot::S(_) => unreachable!("A/R register bank contains S register"),
+        }
+    }
+
+    /// Acquires free string register with the lowest index.
+    ///
+    /// # Returns
+    ///
+    /// Acquired register, or `None` if all string registers are in use.
+    pub fn acquire_s(&mut self) -> Option<RegS> {
+        match self.acquire_slot(Self::s_slots())? {
+            Slot::S(reg) => Some(reg),
+            Slot::AR(..) => unreachable!("S register bank contains A/R register"),
+        }
+    }
+
+    /// Marks specific `A` or `R` register as being in use.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register was already in use, `true` otherwise.
+    #[inline]
+    pub fn reserve(&mut self, reg: impl Into<RegAR>, idx: impl Into<Reg32>) -> bool {
+        self.reserve_slot(Slot::AR(reg.into(), idx.into()))
+    }
+
+    /// Marks specific string register as being in use.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register was already in use, `true` otherwise.
+    #[inline]
+    pub fn reserve_s(&mut self, reg: RegS) -> bool { self.reserve_slot(Slot::S(reg)) }
+
+    /// Releases `A` or `R` register, making it available for acquisition.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register was not in use, `true` otherwise.
+    #[inline]
+    pub fn release(&mut self, reg: impl Into<RegAR>, idx: impl Into<Reg32>) -> bool {
+        self.in_use.remove(&Slot::AR(reg.into(), idx.into())).is_some()
+    }
+
+    /// Releases string register, making it available for acquisition.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register was not in use, `true` otherwise.
+    #[inline]
+    pub fn release_s(&mut self, reg: RegS) -> bool { self.in_use.remove(&Slot::S(reg)).is_some() }
+
+    /// Marks `A` or `R` register as recently used, which makes it the last candidate for
+    /// spilling.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register is not in use, `true` otherwise.
+    #[inline]
+    pub fn touch(&mut self, reg: impl Into<RegAR>, idx: impl Into<Reg32>) -> bool {
+        self.touch_slot(Slot::AR(reg.into(), idx.into()))
+    }
+
+    /// Marks string register as recently used, which makes it the last candidate for spilling.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the register is not in use, `true` otherwise.
+    #[inline]
+    pub fn touch_s(&mut self, reg: RegS) -> bool { self.touch_slot(Slot::S(reg)) }
+
+    /// Checks whether `A` or `R` register is free.
+    #[inline]
+    pub fn is_free(&self, reg: impl Into<RegAR>, idx: impl Into<Reg32>) -> bool {
+        !self.in_use.contains_key(&Slot::AR(reg.into(), idx.into()))
+    }
+
+    /// Checks whether string register is free.
+    #[inline]
+    pub fn is_free_s(&self, reg: RegS) -> bool { !self.in_use.contains_key(&Slot::S(reg)) }
+
+    /// Returns number of free registers in `A` or `R` register bank.
+    pub fn free_count(&self, reg: impl Into<RegAR>) -> usize {
+        Self::ar_slots(reg.into()).filter(|slot| !self.in_use.contains_key(slot)).count()
+    }
+
+    /// Returns number of free string registers.
+    pub fn free_count_s(&self) -> usize {
+        Self::s_slots().filter(|slot| !self.in_use.contains_key(slot)).count()
+    }
+
+    /// Suggests register from `A` or `R` register bank to spill: the least recently acquired,
+    /// reserved or touched register in use.
+    ///
+    /// # Returns
+    ///
+    /// Index of the register, or `None` if no registers of the bank are in use.
+    pub fn spill_candidate(&self, reg: impl Into<RegAR>) -> Option<Reg32> {
+        match self.spill_slot(Self::ar_slots(reg.into()))? {
+            Slot::AR(_, idx) => Some(idx),
+            Slot::S(_) => unreachable!("A/R register bank contains S register"),
+        }
+    }
+
+    /// Suggests string register to spill: the least recently acquired, reserved or touched
+    /// string register in use.
+    ///
+    /// # Returns
+    ///
+    /// String register, or `None` if no string registers are in use.
+    pub fn spill_candidate_s(&self) -> Option<RegS> {
+        match self.spill_slot(Self::s_slots())? {
+            Slot::S(reg) => Some(reg),
+            Slot::AR(..) => unreachable!("S register bank contains A/R register"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reg::{RegA, RegR};
+
+    #[test]
+    fn acquire_release() {
+        let mut pool = RegPool::new();
+        assert!(pool.reserve(RegA::A64, Reg32::Reg0));
+        assert!(!pool.reserve(RegA::A64, Reg32::Reg0));
+        assert_eq!(pool.acquire(RegA::A64), Some(Reg32::Reg1));
+        assert_eq!(pool.acquire(RegA::A32), Some(Reg32::Reg0));
+        assert_eq!(pool.acquire(RegR::R256), Some(Reg32::Reg0));
+        assert_eq!(pool.free_count(RegA::A64), 30);
+
+        assert!(pool.release(RegA::A64, Reg32::Reg0));
+        assert!(!pool.release(RegA::A64, Reg32::Reg0));
+        assert!(pool.is_free(RegA::A64, Reg32::Reg0));
+        assert_eq!(pool.acquire(RegA::A64), Some(Reg32::Reg0));
+
+        for _ in 2..32 {
+            assert!(pool.acquire(RegA::A64).is_some());
+        }
+        assert_eq!(pool.acquire(RegA::A64), None);
+        assert_eq!(pool.free_count(RegA::A64), 0);
+    }
+
+    #[test]
+    fn string_regs() {
+        let mut pool = RegPool::new();
+        assert!(pool.reserve_s(RegS::from(0)));
+        assert_eq!(pool.acquire_s(), Some(RegS::from(1)));
+        assert_eq!(pool.free_count_s(), 14);
+        assert!(pool.release_s(RegS::from(0)));
+        assert!(pool.is_free_s(RegS::from(0)));
+        assert_eq!(pool.spill_candidate_s(), Some(RegS::from(1)));
+    }
+
+    #[test]
+    fn spill() {
+        let mut pool = RegPool::new();
+        assert_eq!(pool.spill_candidate(RegR::R128), None);
+        let r0 = pool.acquire(RegR::R128).unwrap();
+        let r1 = pool.acquire(RegR::R128).unwrap();
+        pool.acquire(RegR::R256).unwrap();
+        assert_eq!(pool.spill_candidate(RegR::R128), Some(r0));
+        assert!(pool.touch(RegR::R128, r0));
+        assert_eq!(pool.spill_candidate(RegR::R128), Some(r1));
+        assert!(!pool.touch(RegR::R128, Reg32::Reg5));
+    }
+}
@@ -0,0 +1,249 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+//! Recording of program executions and their deterministic replay.
+//!
+//! Given the same program, entry point, registers and context, an execution by AluVM is fully
+//! deterministic, except for the results of the host functions called by
+//! [`HostOp`](crate::isa::HostOp) instructions. [`Vm::record`] runs a program capturing its
+//! initial registers and all the results returned by the host into a [`Recording`], and
+//! [`Vm::replay`] re-executes the program from the recording, feeding it with the recorded results
+//! instead of calling the host and verifying that the execution makes the same host calls and
+//! ends with the same register state.
+//!
+//! A recording made by a node which has diverged from the consensus may be sent to the developers
+//! (with the `std` feature, recordings are serialized with
+//! [`Encode`](crate::data::encoding::Encode)) and replayed under a debugger or with tracing hooks
+//! to find the cause of the failure. Replays must use the same program and context as the recorded
+//! run, since they are not a part of the recording.
+//!
+//! [`Vm::record`]: crate::Vm::record
+//! [`Vm::replay`]: crate::Vm::replay
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::data::MaybeNumber;
+use crate::library::LibSite;
+use crate::reg::CoreRegs;
+use crate::RunError;
+
+/// Result of a host function call captured by a [`Recording`]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display)]
+#[display("{func:#06X} before {site} returning {value}")]
+pub struct HostResult {
+    /// Location of the instruction following the call, from which the execution was resumed
+    pub site: LibSite,
+
+    /// Host function identifier
+    pub func: u16,
+
+    /// Value returned by the host function
+    pub value: MaybeNumber,
+}
+
+/// Recording of a program execution made by [`Vm::record`](crate::Vm::record)
+#[derive(Clone, Debug)]
+pub struct Recording {
+    /// Entry point of the execution
+    pub method: LibSite,
+
+    /// Registers at the start of the execution.
+    ///
+    /// Serialized recordings keep the registers as [`CoreRegs::snapshot`] data, which does not
+    /// include the wall-clock deadline, the call receipt and the execution statistics; these are
+    /// reset in the registers of a deserialized recording.
+    pub registers: Box<CoreRegs>,
+
+    /// Results of the host function calls, in the order of the calls
+    pub host_results: Vec<HostResult>,
+
+    /// Value of `st0` register at the end of the execution
+    pub status: bool,
+
+    /// SHA256 digest of the registers at the end of the execution
+    pub digest: [u8; 32],
+}
+
+/// Errors replaying program execution with [`Vm::replay`](crate::Vm::replay)
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(doc_comments)]
+pub enum ReplayError {
+    /// replayed execution has failed: {0}
+    #[from]
+    Run(RunError),
+
+    /// host function {func:#06X} called before {site} is absent in the recording
+    Unrecorded {
+        /// Location of the instruction following the call
+        site: LibSite,
+        /// Host function identifier
+        func: u16,
+    },
+
+    /// host function {func:#06X} is called before {site}, while the recording has function
+    /// {recorded_func:#06X} called before {recorded_site}
+    CallMismatch {
+        /// Location of the instruction following the call
+        site: LibSite,
+        /// Host function identifier
+        func: u16,
+        /// Location of the recorded call at the same position in the call sequence
+        recorded_site: LibSite,
+        /// Identifier of the recorded host function
+        recorded_func: u16,
+    },
+
+    /// replayed execution has completed without making {0} recorded host function calls
+    Unused(usize),
+
+    /// replayed execution has completed with `st0` set to {0}, unlike the recorded one
+    StatusMismatch(bool),
+
+    /// replayed execution has completed with registers which differ from the recorded ones
+    StateMismatch,
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            ReplayError::Run(err) => Some(err),
+            ReplayError::Unrecorded { .. }
+            | ReplayError::CallMismatch { .. }
+            | ReplayError::Unused(_)
+            | ReplayError::StatusMismatch(_)
+            | ReplayError::StateMismatch => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::{HostOp, Instr};
+    use crate::library::Lib;
+    use crate::reg::{Reg32, RegA};
+    use crate::{Prog, Program, Vm};
+
+    type Isa = Instr<HostOp>;
+
+    fn prog() -> Prog<'static, Isa> {
+        let lib = Lib::assemble_source::<Isa>(
+            "
+            put     a16[0],7
+            add.uc  a16[2],a16[0]
+            hcall   0x0001,a16[1]
+            hcall   0x0002,r256[0]
+            ret
+        ",
+        )
+        .unwrap();
+        Prog::<Isa>::new(lib)
+    }
+
+    fn record(prog: &Prog<Isa>) -> Recording {
+        let mut vm = Vm::<Isa>::new();
+        vm.registers.set(RegA::A16, Reg32::Reg2, 5u16);
+        vm.record(prog, prog.entrypoint(), &(), |call| match call.func {
+            1 => {
+                let arg = call.registers().get(RegA::A16, Reg32::Reg0).unwrap();
+                (u16::from(arg) * 2).into()
+            }
+            _ => MaybeNumber::none(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn replay() {
+        let prog = prog();
+        let recording = record(&prog);
+        assert!(!recording.status);
+        assert_eq!(recording.host_results.len(), 2);
+        assert_eq!(recording.host_results[0].func, 1);
+        assert_eq!(recording.host_results[0].value, MaybeNumber::from(24u16));
+        assert_eq!(recording.host_results[1].value, MaybeNumber::none());
+
+        let mut vm = Vm::<Isa>::new();
+        assert_eq!(vm.replay(&prog, &recording, &()), Ok(false));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg1), MaybeNumber::from(24u16));
+        assert_eq!(vm.registers.receipt().len(), 2);
+    }
+
+    #[test]
+    fn divergence() {
+        let prog = prog();
+        let recording = record(&prog);
+        let mut vm = Vm::<Isa>::new();
+
+        let mut tampered = recording.clone();
+        tampered.host_results[0].value = MaybeNumber::from(25u16);
+        assert_eq!(vm.replay(&prog, &tampered, &()), Err(ReplayError::StateMismatch));
+
+        let mut tampered = recording.clone();
+        tampered.registers.set(RegA::A16, Reg32::Reg2, 6u16);
+        assert_eq!(vm.replay(&prog, &tampered, &()), Err(ReplayError::StateMismatch));
+
+        let mut tampered = recording.clone();
+        tampered.status = true;
+        assert_eq!(vm.replay(&prog, &tampered, &()), Err(ReplayError::StatusMismatch(false)));
+
+        let mut tampered = recording.clone();
+        let last = tampered.host_results.pop().unwrap();
+        assert_eq!(
+            vm.replay(&prog, &tampered, &()),
+            Err(ReplayError::Unrecorded { site: last.site, func: 2 })
+        );
+
+        let mut tampered = recording.clone();
+        tampered.host_results.push(last);
+        assert_eq!(vm.replay(&prog, &tampered, &()), Err(ReplayError::Unused(1)));
+
+        let mut tampered = recording;
+        tampered.host_results.swap(0, 1);
+        assert_eq!(
+            vm.replay(&prog, &tampered, &()),
+            Err(ReplayError::CallMismatch {
+                site: tampered.host_results[1].site,
+                func: 1,
+                recorded_site: tampered.host_results[0].site,
+                recorded_func: 2,
+            })
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn encoding() {
+        use crate::data::encoding::{Decode, DecodeError, Encode};
+
+        let prog = prog();
+        let recording = record(&prog);
+        let data = recording.serialize();
+        let decoded = Recording::deserialize(&data).unwrap();
+        assert_eq!(decoded.method, recording.method);
+        assert_eq!(decoded.host_results, recording.host_results);
+        assert_eq!(decoded.digest, recording.digest);
+        assert_eq!(decoded.registers.snapshot(), recording.registers.snapshot());
+        assert_eq!(Vm::<Isa>::new().replay(&prog, &decoded, &()), Ok(false));
+
+        assert!(matches!(Recording::deserialize(&data[..data.len() - 1]), Err(DecodeError::Io(_))));
+    }
+}
@@ -0,0 +1,223 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Self-test of the execution determinism on the host platform.
+//!
+//! [`selftest`] runs a built-in battery of integer, float, bitwise, byte string and digest
+//! operations and returns SHA256 digest of the register states produced by each of them. A
+//! platform executing the instructions in the way required by the specification produces
+//! [`SELFTEST_DIGEST`], thus embedders validating consensus-critical data may call
+//! [`is_deterministic`] at startup and refuse to proceed on a mismatch, which indicates a
+//! miscompilation or platform-dependent behavior (for instance, of the float arithmetic).
+//!
+//! Elliptic curve operations depend on the optional crate features and are not included into the
+//! battery, so the digest is the same for all the feature sets.
+
+use core::str::FromStr;
+
+use sha2::{Digest, Sha256};
+
+use crate::isa::{Instr, InstructionSet, ReservedOp};
+use crate::library::LibSite;
+use crate::reg::CoreRegs;
+
+/// Instructions of the self-test battery, executed one after another on the same registers.
+const BATTERY: &str = "
+    put     a8[0],0x7F
+    put     a8[1],0x81
+    put     a16[2],1234
+    put     a32[3],0xFFFFFFFE
+    put     a64[4],0xFFFFFFFFFFFFFFFF
+    put     a64[5],3
+    put     a128[6],0x0123456789ABCDEF0123456789ABCDEF
+    put     f32[0],1.5
+    put     f32[1],-0.1
+    put     f64[0],3.141592653589793
+    put     f64[1],2.718281828459045
+    put     f64[2],1e-300
+    put     f16[3],0.333
+    put     s16[0],\"AluVM determinism self-test\"
+    put     s16[1],\"\"
+
+    add.uc  a8[0],a8[1]
+    add.uw  a8[0],a8[1]
+    add.sc  a8[0],a8[1]
+    sub.sw  a64[5],a64[4]
+    mul.uw  a64[4],a64[5]
+    mul.sc  a128[6],a128[6]
+    div.uc  a64[4],a64[5]
+    div.sw  a32[3],a32[3]
+    rem     a16[2],a64[5]
+    inc     a16[2]
+    dec     a8[1]
+    neg     a32[3]
+    abs     a32[3]
+
+    add.n   f32[0],f32[1]
+    sub.z   f64[0],f64[1]
+    mul.c   f64[1],f64[2]
+    mul.f   f64[2],f64[2]
+    div.n   f64[0],f64[1]
+    div.z   f64[1],f64[0]
+    add.n   f16[3],f16[3]
+    neg     f64[0]
+    abs     f64[0]
+
+    gt.u    a8[0],a8[1]
+    lt.s    a32[3],a32[3]
+    eq.n    a64[4],a64[5]
+    gt.r    f64[0],f64[1]
+    eq.e    f32[0],f32[1]
+
+    put     a64[4],0xF0F0F0F00F0F0F0F
+    put     a64[5],0x0123456789ABCDEF
+    put     a8[0],0x35
+    put     a8[1],0x03
+    and     a64[4],a64[5],a64[7]
+    or      a64[4],a64[5],a64[8]
+    xor     a64[7],a64[8],a64[9]
+    not     a64[9]
+    shl     a8[1],a64[9]
+    shr.s   a8[1],a64[8]
+    scl     a8[1],a64[7]
+    scr     a8[1],a8[0]
+    rev     a64[9]
+
+    sha2    s16[0],r256[0]
+    sha2    s16[0],r512[0]
+    ripemd  s16[0],r160[0]
+    sha2    s16[1],r256[1]
+    mov     r256[0],r256[2]
+    eq.n    r256[0],r256[2]
+";
+
+/// Digest of the self-test battery results produced by the platforms executing the instructions
+/// according to the specification. The results it commits to are checked by the crate tests
+/// against the values derived independently of this implementation.
+pub const SELFTEST_DIGEST: [u8; 32] = [
+    0x2b, 0x47, 0xf3, 0x53, 0x6c, 0x50, 0xcb, 0x86, 0x2b, 0xd3, 0x20, 0xfb, 0x1e, 0xa2, 0x7c, 0x89,
+    0x17, 0xa5, 0xe0, 0x61, 0xee, 0x2b, 0xb7, 0x22, 0x86, 0xbc, 0x7b, 0xbc, 0xf8, 0xe9, 0xe8, 0xee,
+];
+
+/// Runs the self-test battery and returns SHA256 digest of the states of all the registers after
+/// each of the battery instructions.
+pub fn selftest() -> [u8; 32] {
+    let mut regs = CoreRegs::new();
+    let mut hasher = Sha256::default();
+    for line in BATTERY.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let instr = Instr::<ReservedOp>::from_str(line).expect("self-test battery is valid");
+        instr.exec(&mut regs, LibSite::default(), &());
+        regs.absorb(&mut hasher);
+    }
+    hasher.finalize().into()
+}
+
+/// Checks that the host platform executes the self-test battery with results required by the
+/// specification.
+pub fn is_deterministic() -> bool { selftest() == SELFTEST_DIGEST }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selftest_digest() {
+        assert_eq!(selftest(), SELFTEST_DIGEST);
+        assert!(is_deterministic());
+    }
+
+    /// Checks the battery results against the values derived independently from the virtual
+    /// machine implementation, with Rust native arithmetic and reference hash digests, such that
+    /// [`SELFTEST_DIGEST`] pins the behavior required by the specification.
+    #[test]
+    fn battery_results() {
+        use core::convert::TryFrom;
+
+        use amplify::hex::FromHex;
+
+        use crate::data::Number;
+        use crate::reg::{Reg32, RegA, RegAFR, RegF, RegR, RegS};
+
+        let mut regs = CoreRegs::new();
+        for line in BATTERY.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let instr = Instr::<ReservedOp>::from_str(line).expect("self-test battery is valid");
+            instr.exec(&mut regs, LibSite::default(), &());
+        }
+        let bytes = |reg: RegAFR, idx: u8| -> Option<Vec<u8>> {
+            let idx = Reg32::try_from(idx).unwrap();
+            Option::<Number>::from(regs.get(reg, idx)).map(|num| num.as_ref().to_vec())
+        };
+        let a = |reg: RegA, idx: u8| bytes(reg.into(), idx);
+        let f = |reg: RegF, idx: u8| bytes(reg.into(), idx);
+        let r = |reg: RegR, idx: u8| bytes(reg.into(), idx);
+        let hex = |s: &str| Some(Vec::<u8>::from_hex(s).unwrap());
+
+        // Integer arithmetic: overflowing operations clear their destinations
+        assert_eq!(a(RegA::A8, 0), Some(vec![0x35u8.rotate_right(3)]));
+        assert_eq!(a(RegA::A8, 1), Some(vec![3]));
+        assert_eq!(a(RegA::A16, 2), Some(1235u16.to_le_bytes().to_vec()));
+        assert_eq!(a(RegA::A32, 3), None);
+        assert_eq!(a(RegA::A128, 6), None);
+
+        // Bitwise operations
+        let (x, y) = (0xF0F0_F0F0_0F0F_0F0F_u64, 0x0123_4567_89AB_CDEF_u64);
+        assert_eq!(a(RegA::A64, 7), Some((x & y).rotate_left(3).to_le_bytes().to_vec()));
+        assert_eq!(a(RegA::A64, 8), Some((((x | y) as i64) >> 3).to_le_bytes().to_vec()));
+        let z = (!((x & y) ^ (x | y)) << 3).reverse_bits();
+        assert_eq!(a(RegA::A64, 9), Some(z.to_le_bytes().to_vec()));
+
+        // Float arithmetic: `pi - e` is exact; the last division is rounded towards zero and the
+        // underflowing multiplication clears its destination
+        let (pi, e) = (core::f64::consts::PI, core::f64::consts::E);
+        assert_eq!(f(RegF::F32, 0), Some(1.5f32.to_le_bytes().to_vec()));
+        assert_eq!(f(RegF::F32, 1), Some((1.5f32 + -0.1f32).to_le_bytes().to_vec()));
+        assert_eq!(f(RegF::F64, 1), Some((pi / (pi - e)).to_le_bytes().to_vec()));
+        assert_eq!(f(RegF::F64, 0), Some(2.3623303271092344_f64.to_le_bytes().to_vec()));
+        assert_eq!(f(RegF::F64, 2), None);
+        // Twice the half-precision value nearest to 0.333, which is 0x3554
+        assert_eq!(f(RegF::F16, 3), Some(0x3954u16.to_le_bytes().to_vec()));
+
+        // Digests of the string registers, taken from the reference implementations
+        let text = regs.get_s(RegS::from(0)).unwrap();
+        assert_eq!(text.as_ref(), b"AluVM determinism self-test");
+        assert_eq!(
+            r(RegR::R256, 1),
+            hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(
+            r(RegR::R256, 2),
+            hex("ae98e634f753159894737d3a434ec86ec8dbd1c31651132cf7a35f0f06d80ee4")
+        );
+        assert_eq!(
+            r(RegR::R512, 0),
+            hex("14a2ef5044a4ed202a618cc142189e6e4726ff23a5168beba650fa1c26be95bc\
+                 6a0ea82116f38311138bb73f22733ade3c68aed15f2a5d570004b44a4245d57b")
+        );
+        // RIPEMD-160 digest is kept in the register with the reversed byte order
+        let mut ripemd = hex("c066ab1d9786adf83d940c5cdc63333d4de7add1");
+        ripemd.as_mut().unwrap().reverse();
+        assert_eq!(r(RegR::R160, 0), ripemd);
+        assert_eq!(r(RegR::R256, 0), None);
+        assert!(!regs.status());
+    }
+}
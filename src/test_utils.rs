@@ -0,0 +1,1002 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for testing instruction set implementations.
+//!
+//! The module provides [`roundtrip`] checker validating that an instruction is encoded into
+//! bytecode and decoded back without any loss of information, plus generators of representative
+//! instructions for all core AluVM opcodes (see [`core_instrs`]). ISA extension authors may use
+//! them to validate their [`Bytecode`] implementations with a single call.
+//!
+//! [`Bytecode`]: crate::isa::Bytecode
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::slice;
+
+use amplify::num::{u1, u2, u3, u4};
+
+use crate::data::{ByteStr, MaybeNumber, Number, NumberLayout};
+#[cfg(feature = "bitcoin")]
+use crate::isa::BitcoinOp;
+#[cfg(feature = "curve25519")]
+use crate::isa::Curve25519Op;
+#[cfg(feature = "secp256k1")]
+use crate::isa::Secp256k1Op;
+use crate::isa::{
+    ArithmeticOp, BaseFlag, BitwiseOp, BytesOp, CmpOp, ControlFlowOp, CtOp, DataOp, DigestOp,
+    EncOp, EnvOp, FieldFlag, FieldOp, FixedOp, FrameOp, HostOp, ImmOp, Instr, InstructionSet,
+    LoopOp, ModOp, MoveOp, PrngOp, PutOp, RelJumpOp, StackOp, StepOp, SwitchOp, XferOp,
+};
+use crate::library::{AssemblerError, CodeEofError, Cursor, Lib, LibId, LibSite, Read};
+#[cfg(any(feature = "secp256k1", feature = "curve25519"))]
+use crate::reg::RegBlockAR;
+use crate::reg::{NumericRegister, Reg16, Reg32, Reg8, RegA, RegF, RegR, RegS};
+
+/// Errors detected by the [`roundtrip`] checker.
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum RoundtripError {
+    /// instruction `{0}` can't be encoded: {1}
+    Encode(String, AssemblerError),
+
+    /// instruction `{instr}` is encoded into {actual} bytes, while it reports byte count equal
+    /// to {expected}
+    ByteCount {
+        /// Instruction
+        instr: String,
+        /// Byte count reported by the instruction
+        expected: u16,
+        /// Actual length of the encoded instruction
+        actual: u16,
+    },
+
+    /// instruction `{instr}` is encoded with opcode {actual:#04x} instead of {expected:#04x}
+    Opcode {
+        /// Instruction
+        instr: String,
+        /// Opcode reported by the instruction
+        expected: u8,
+        /// Opcode used in the encoded instruction
+        actual: u8,
+    },
+
+    /// opcode {opcode:#04x} of instruction `{instr}` lies outside of the instruction set opcode
+    /// range
+    OutOfRange {
+        /// Instruction
+        instr: String,
+        /// Instruction opcode
+        opcode: u8,
+    },
+
+    /// instruction `{0}` can't be decoded: {1}
+    Decode(String, CodeEofError),
+
+    /// decoding instruction `{instr}` consumed {consumed} bytes out of {encoded} encoded
+    Consumed {
+        /// Instruction
+        instr: String,
+        /// Length of the encoded instruction
+        encoded: u16,
+        /// Number of bytes consumed by the decoder
+        consumed: u16,
+    },
+
+    /// instruction `{original}` was decoded as `{decoded}`
+    Mismatch {
+        /// Instruction which was encoded
+        original: String,
+        /// Instruction resulting from decoding
+        decoded: String,
+    },
+}
+
+/// Checks that the instruction is encoded into bytecode and decoded back into exactly the same
+/// instruction, and that the encoding is consistent with [`Bytecode::byte_count`],
+/// [`Bytecode::instr_byte`] and [`Bytecode::instr_range`] values.
+///
+/// [`Bytecode::byte_count`]: crate::isa::Bytecode::byte_count
+/// [`Bytecode::instr_byte`]: crate::isa::Bytecode::instr_byte
+/// [`Bytecode::instr_range`]: crate::isa::Bytecode::instr_range
+pub fn roundtrip<Isa>(instr: &Isa) -> Result<(), RoundtripError>
+where
+    Isa: InstructionSet + PartialEq,
+{
+    let lib = Lib::assemble(slice::from_ref(instr))
+        .map_err(|err| RoundtripError::Encode(instr.to_string(), err))?;
+
+    let encoded = lib.code.len() as u16;
+    let expected = instr.byte_count();
+    if encoded != expected {
+        return Err(RoundtripError::ByteCount {
+            instr: instr.to_string(),
+            expected,
+            actual: encoded,
+        });
+    }
+
+    let opcode = lib.code.as_ref()[0];
+    if opcode != instr.instr_byte() {
+        return Err(RoundtripError::Opcode {
+            instr: instr.to_string(),
+            expected: instr.instr_byte(),
+            actual: opcode,
+        });
+    }
+    if !Isa::instr_range().contains(&opcode) {
+        return Err(RoundtripError::OutOfRange { instr: instr.to_string(), opcode });
+    }
+
+    let mut reader = Cursor::with(&lib.code, &lib.data, &lib.libs);
+    let decoded =
+        Isa::decode(&mut reader).map_err(|err| RoundtripError::Decode(instr.to_string(), err))?;
+    if reader.pos() != encoded {
+        return Err(RoundtripError::Consumed {
+            instr: instr.to_string(),
+            encoded,
+            consumed: reader.pos(),
+        });
+    }
+    if &decoded != instr {
+        return Err(RoundtripError::Mismatch {
+            original: instr.to_string(),
+            decoded: decoded.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Runs [`roundtrip`] check for each of the provided instructions, returning the first detected
+/// error.
+pub fn roundtrip_all<'instr, Isa>(
+    instrs: impl IntoIterator<Item = &'instr Isa>,
+) -> Result<(), RoundtripError>
+where
+    Isa: InstructionSet + PartialEq + 'instr,
+{
+    instrs.into_iter().try_for_each(roundtrip)
+}
+
+/// Generates representative set of instructions covering all core AluVM opcodes (including
+/// enabled ISA extensions provided by this crate).
+pub fn core_instrs<Extension>() -> Vec<Instr<Extension>>
+where
+    Extension: InstructionSet,
+{
+    let mut instrs = Vec::new();
+    instrs.extend(control_flow_ops().into_iter().map(Instr::ControlFlow));
+    instrs.extend(put_ops().into_iter().map(Instr::Put));
+    instrs.extend(move_ops().into_iter().map(Instr::Move));
+    instrs.extend(cmp_ops().into_iter().map(Instr::Cmp));
+    instrs.extend(arithmetic_ops().into_iter().map(Instr::Arithmetic));
+    instrs.extend(bitwise_ops().into_iter().map(Instr::Bitwise));
+    instrs.extend(bytes_ops().into_iter().map(Instr::Bytes));
+    instrs.extend(digest_ops().into_iter().map(Instr::Digest));
+    #[cfg(feature = "secp256k1")]
+    instrs.extend(secp256k1_ops().into_iter().map(Instr::Secp256k1));
+    #[cfg(feature = "curve25519")]
+    instrs.extend(curve25519_ops().into_iter().map(Instr::Curve25519));
+    instrs.push(Instr::Nop);
+    instrs
+}
+
+const IDX32: [Reg32; 3] = [Reg32::Reg0, Reg32::Reg17, Reg32::Reg31];
+const IDX16: [Reg16; 2] = [Reg16::Reg0, Reg16::Reg15];
+const IDX8: [Reg8; 2] = [Reg8::Reg0, Reg8::Reg7];
+
+fn regs_s() -> [RegS; 2] { [RegS::from(0), RegS::from(15)] }
+
+fn site() -> LibSite { LibSite::with(0xAC01, LibId::from([0xA5; 32])) }
+
+fn value(reg: impl NumericRegister) -> Box<MaybeNumber> {
+    let layout = reg.layout();
+    let number = if layout.is_float() {
+        Number::zero(layout)
+    } else {
+        Number::masked_bit(layout.bits().min(1024) - 1, layout)
+    };
+    Box::new(MaybeNumber::from(number))
+}
+
+/// Generates representative set of [`ControlFlowOp`] instructions.
+pub fn control_flow_ops() -> Vec<ControlFlowOp> {
+    vec![
+        ControlFlowOp::Fail,
+        ControlFlowOp::Succ,
+        ControlFlowOp::Jmp(0),
+        ControlFlowOp::Jmp(0xFFFF),
+        ControlFlowOp::Jif(0xAC01),
+        ControlFlowOp::Routine(0x01AC),
+        ControlFlowOp::Call(site()),
+        ControlFlowOp::Exec(site()),
+        ControlFlowOp::Ret,
+    ]
+}
+
+/// Generates representative set of [`PutOp`] instructions.
+pub fn put_ops() -> Vec<PutOp> {
+    let mut ops = Vec::new();
+    for idx in IDX32 {
+        for reg in RegA::ALL {
+            ops.push(PutOp::ClrA(reg, idx));
+            ops.push(PutOp::PutA(reg, idx, value(reg)));
+            ops.push(PutOp::PutIfA(reg, idx, value(reg)));
+        }
+        for reg in RegF::ALL {
+            ops.push(PutOp::ClrF(reg, idx));
+            ops.push(PutOp::PutF(reg, idx, value(reg)));
+        }
+        for reg in RegR::ALL {
+            ops.push(PutOp::ClrR(reg, idx));
+            ops.push(PutOp::PutR(reg, idx, value(reg)));
+            ops.push(PutOp::PutIfR(reg, idx, value(reg)));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`MoveOp`] instructions.
+pub fn move_ops() -> Vec<MoveOp> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for reg in RegA::ALL {
+            ops.push(MoveOp::MovA(reg, idx1, idx2));
+            ops.push(MoveOp::DupA(reg, idx1, idx2));
+            ops.push(MoveOp::SwpA(reg, idx1, idx2));
+        }
+        for reg in RegF::ALL {
+            ops.push(MoveOp::MovF(reg, idx1, idx2));
+            ops.push(MoveOp::DupF(reg, idx1, idx2));
+            ops.push(MoveOp::SwpF(reg, idx1, idx2));
+        }
+        for reg in RegR::ALL {
+            ops.push(MoveOp::MovR(reg, idx1, idx2));
+            ops.push(MoveOp::DupR(reg, idx1, idx2));
+        }
+        for (reg1, reg2) in RegA::ALL.iter().copied().zip(RegA::ALL.iter().copied().rev()) {
+            ops.push(MoveOp::CpyA(reg1, idx1, reg2, idx2));
+            ops.push(MoveOp::CnvA(reg1, idx1, reg2, idx2));
+        }
+        for (reg1, reg2) in RegF::ALL.iter().copied().zip(RegF::ALL.iter().copied().rev()) {
+            ops.push(MoveOp::CnvF(reg1, idx1, reg2, idx2));
+        }
+        for (reg1, reg2) in RegR::ALL.iter().copied().zip(RegR::ALL.iter().copied().rev()) {
+            ops.push(MoveOp::CpyR(reg1, idx1, reg2, idx2));
+        }
+        for ((rega, regf), regr) in RegA::ALL.iter().copied().zip(RegF::ALL).zip(RegR::ALL) {
+            ops.push(MoveOp::SpyAR(rega, idx1, regr, idx2));
+            ops.push(MoveOp::CnvAF(rega, idx1, regf, idx2));
+            ops.push(MoveOp::CnvFA(regf, idx1, rega, idx2));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`CmpOp`] instructions.
+pub fn cmp_ops() -> Vec<CmpOp> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for flag in 0..2 {
+            let flag = u1::with(flag);
+            for reg in RegA::ALL {
+                ops.push(CmpOp::GtA(flag.into(), reg, idx1, idx2));
+                ops.push(CmpOp::LtA(flag.into(), reg, idx1, idx2));
+                ops.push(CmpOp::EqA(flag.into(), reg, idx1, idx2));
+            }
+            for reg in RegF::ALL {
+                ops.push(CmpOp::GtF(flag.into(), reg, idx1, idx2));
+                ops.push(CmpOp::LtF(flag.into(), reg, idx1, idx2));
+                ops.push(CmpOp::EqF(flag.into(), reg, idx1, idx2));
+            }
+            for reg in RegR::ALL {
+                ops.push(CmpOp::EqR(flag.into(), reg, idx1, idx2));
+            }
+        }
+        for reg in RegR::ALL {
+            ops.push(CmpOp::GtR(reg, idx1, idx2));
+            ops.push(CmpOp::LtR(reg, idx1, idx2));
+        }
+    }
+    for idx in IDX32 {
+        for reg in RegA::ALL {
+            ops.push(CmpOp::IfZA(reg, idx));
+            ops.push(CmpOp::IfNA(reg, idx));
+        }
+        for reg in RegR::ALL {
+            ops.push(CmpOp::IfZR(reg, idx));
+            ops.push(CmpOp::IfNR(reg, idx));
+        }
+    }
+    for flag in 0..4 {
+        for reg in RegA::ALL {
+            for idx in IDX8 {
+                ops.push(CmpOp::St(u2::with(flag).into(), reg, idx));
+            }
+        }
+    }
+    ops.push(CmpOp::StInv);
+    ops
+}
+
+/// Generates representative set of [`ArithmeticOp`] instructions.
+pub fn arithmetic_ops() -> Vec<ArithmeticOp> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for flags in 0..4 {
+            let flags = u2::with(flags);
+            for reg in RegA::ALL {
+                ops.push(ArithmeticOp::AddA(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::SubA(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::MulA(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::DivA(flags.into(), reg, idx1, idx2));
+            }
+            for reg in RegF::ALL {
+                ops.push(ArithmeticOp::AddF(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::SubF(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::MulF(flags.into(), reg, idx1, idx2));
+                ops.push(ArithmeticOp::DivF(flags.into(), reg, idx1, idx2));
+            }
+        }
+        for (reg1, reg2) in RegA::ALL.iter().copied().zip(RegA::ALL.iter().copied().rev()) {
+            ops.push(ArithmeticOp::Rem(reg1, idx1, reg2, idx2));
+        }
+        for reg in RegA::ALL {
+            for step in [i8::MIN, -1, 0, 1, i8::MAX] {
+                ops.push(ArithmeticOp::Stp(reg, idx1, step.into()));
+            }
+        }
+    }
+    for reg in 0..16 {
+        for idx in IDX16 {
+            ops.push(ArithmeticOp::Neg(u4::with(reg).into(), idx));
+            ops.push(ArithmeticOp::Abs(u4::with(reg).into(), idx));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`BitwiseOp`] instructions.
+pub fn bitwise_ops() -> Vec<BitwiseOp> {
+    let mut ops = Vec::new();
+    for reg in 0..16 {
+        let reg = u4::with(reg).into();
+        for (idx1, idx2) in IDX16.iter().copied().zip(IDX16.iter().copied().rev()) {
+            ops.push(BitwiseOp::And(reg, idx1, idx2, idx1));
+            ops.push(BitwiseOp::Or(reg, idx1, idx2, idx2));
+            ops.push(BitwiseOp::Xor(reg, idx1, idx2, idx1));
+            ops.push(BitwiseOp::Not(reg, idx1));
+        }
+        for a2 in 0..2 {
+            let a2 = u1::with(a2).into();
+            for (shift, idx) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+                ops.push(BitwiseOp::Shl(a2, shift, reg, idx));
+                ops.push(BitwiseOp::Scl(a2, shift, reg, idx));
+                ops.push(BitwiseOp::Scr(a2, shift, reg, idx));
+            }
+        }
+    }
+    for a2 in 0..2 {
+        let a2 = u1::with(a2).into();
+        for (shift, idx) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+            for reg in RegR::ALL {
+                ops.push(BitwiseOp::ShrR(a2, shift, reg, idx));
+            }
+        }
+        for sign in 0..2 {
+            for (shift, idx) in IDX16.iter().copied().zip(IDX32) {
+                for reg in RegA::ALL {
+                    ops.push(BitwiseOp::ShrA(u1::with(sign).into(), a2, shift, reg, idx));
+                }
+            }
+        }
+    }
+    for idx in IDX32 {
+        for reg in RegA::ALL {
+            ops.push(BitwiseOp::RevA(reg, idx));
+        }
+        for reg in RegR::ALL {
+            ops.push(BitwiseOp::RevR(reg, idx));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`BytesOp`] instructions.
+pub fn bytes_ops() -> Vec<BytesOp> {
+    let mut ops = Vec::new();
+    for (s1, s2) in regs_s().iter().copied().zip(regs_s().iter().copied().rev()) {
+        ops.push(BytesOp::Put(s1, Box::new(ByteStr::with(b"AluVM")), false));
+        ops.push(BytesOp::Mov(s1, s2));
+        ops.push(BytesOp::Swp(s1, s2));
+        ops.push(BytesOp::Eq(s1, s2));
+        ops.push(BytesOp::Find(s1, s2));
+        ops.push(BytesOp::Join(s1, s2, s1));
+        ops.push(BytesOp::Rev(s1, s2));
+        for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+            for flag in 0..2 {
+                ops.push(BytesOp::Fill(s1, idx1, idx2, idx1, u1::with(flag).into()));
+            }
+            ops.push(BytesOp::Con(s1, s2, idx1, idx2, idx1));
+            for flag in 0..8 {
+                ops.push(BytesOp::Splt(u3::with(flag).into(), idx1, s1, s2, s1));
+                ops.push(BytesOp::Ins(u3::with(flag).into(), idx1, s1, s2));
+            }
+            for flag in 0..4 {
+                for a2 in 0..2 {
+                    let a2 = u1::with(a2).into();
+                    for (flag1, flag2) in [(false, true), (true, false)] {
+                        ops.push(BytesOp::Del(
+                            u2::with(flag).into(),
+                            a2,
+                            idx1,
+                            a2,
+                            idx2,
+                            flag1,
+                            flag2,
+                            s1,
+                            s2,
+                        ));
+                    }
+                }
+            }
+        }
+        for reg in RegA::ALL {
+            for idx in IDX32 {
+                ops.push(BytesOp::Len(s1, reg, idx));
+            }
+        }
+        for (idx1, idx2) in IDX16.iter().copied().zip(IDX16.iter().copied().rev()) {
+            ops.push(BytesOp::Cnt(s1, idx1, idx2));
+            for reg in RegR::ALL {
+                ops.push(BytesOp::Extr(s1, reg, idx1, idx2));
+                ops.push(BytesOp::Inj(s1, reg, idx1, idx2));
+            }
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`DigestOp`] instructions.
+pub fn digest_ops() -> Vec<DigestOp> {
+    let mut ops = Vec::new();
+    for s in regs_s() {
+        for idx in IDX16 {
+            ops.push(DigestOp::Ripemd(s, idx));
+            ops.push(DigestOp::Sha256(s, idx));
+            ops.push(DigestOp::Sha512(s, idx));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`EnvOp`] instructions.
+pub fn env_ops() -> Vec<EnvOp> {
+    let mut ops = Vec::new();
+    for idx in IDX32 {
+        ops.push(EnvOp::Lib(idx));
+        ops.push(EnvOp::Pos(idx));
+        ops.push(EnvOp::Ctx(idx));
+    }
+    for s in regs_s() {
+        ops.push(EnvOp::Isa(s));
+    }
+    ops
+}
+
+/// Generates representative set of [`CtOp`] instructions.
+pub fn ct_ops() -> Vec<CtOp> {
+    let mut ops = Vec::new();
+    for reg in RegR::ALL {
+        for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+            ops.push(CtOp::EqR(reg, idx1, idx2));
+            ops.push(CtOp::GtR(reg, idx1, idx2));
+            ops.push(CtOp::LtR(reg, idx1, idx2));
+        }
+    }
+    for (s1, s2) in regs_s().iter().copied().zip(regs_s().iter().copied().rev()) {
+        ops.push(CtOp::EqS(s1, s2));
+    }
+    ops
+}
+
+/// Generates representative set of [`PrngOp`] instructions.
+pub fn prng_ops() -> Vec<PrngOp> {
+    let mut ops = Vec::new();
+    for (dst, key) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for reg in RegA::ALL {
+            ops.push(PrngOp::RndA(reg, dst, key, dst));
+        }
+        for reg in RegR::ALL {
+            ops.push(PrngOp::RndR(reg, dst, key, key));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`ModOp`] instructions.
+pub fn mod_ops() -> Vec<ModOp> {
+    let mut ops = Vec::new();
+    for reg in RegR::ALL {
+        for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+            ops.push(ModOp::AddMod(reg, idx1, idx2, idx1, idx2));
+            ops.push(ModOp::MulMod(reg, idx2, idx1, idx2, idx1));
+            ops.push(ModOp::ExpMod(reg, idx1, idx1, idx2, idx2));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`EncOp`] instructions.
+pub fn enc_ops() -> Vec<EncOp> {
+    let mut ops = Vec::new();
+    for (s1, s2) in regs_s().iter().copied().zip(regs_s().iter().copied().rev()) {
+        for base in [BaseFlag::Base16, BaseFlag::Base32, BaseFlag::Base58, BaseFlag::Base64] {
+            ops.push(EncOp::Enc(base, s1, s2));
+            ops.push(EncOp::Dec(base, s2, s1));
+        }
+        ops.push(EncOp::Bech32Enc(s1, s2, s1));
+        ops.push(EncOp::Bech32Dec(s2, s1, s2));
+    }
+    ops
+}
+
+/// Generates representative set of [`RelJumpOp`] instructions.
+pub fn rel_jump_ops() -> Vec<RelJumpOp> {
+    let mut ops = Vec::new();
+    for shift in [i16::MIN, -3, 0, 3, i16::MAX] {
+        ops.push(RelJumpOp::Jmp(shift));
+        ops.push(RelJumpOp::Jif(shift));
+        ops.push(RelJumpOp::Routine(shift));
+    }
+    ops
+}
+
+/// Generates representative set of [`ImmOp`] instructions.
+pub fn imm_ops() -> Vec<ImmOp> {
+    let mut ops = Vec::new();
+    for idx in IDX32 {
+        for reg in RegA::ALL {
+            let val = if reg.bytes() >= 8 { u64::MAX } else { (1 << reg.bits()) - 1 };
+            ops.push(ImmOp::PutA(reg, idx, 0));
+            ops.push(ImmOp::PutIfA(reg, idx, val));
+        }
+        for reg in RegF::ALL {
+            ops.push(ImmOp::PutF(reg, idx, 0));
+        }
+        for reg in RegR::ALL {
+            ops.push(ImmOp::PutR(reg, idx, 0x100));
+            ops.push(ImmOp::PutIfR(reg, idx, u64::MAX));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`FrameOp`] instructions.
+pub fn frame_ops() -> Vec<FrameOp> {
+    let mut ops = Vec::new();
+    for (first, last) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for reg in RegA::ALL {
+            ops.push(FrameOp::PushA(reg, first, last));
+            ops.push(FrameOp::PopA(reg, first, last));
+        }
+        for reg in RegF::ALL {
+            ops.push(FrameOp::PushF(reg, first, last));
+            ops.push(FrameOp::PopF(reg, first, last));
+        }
+        for reg in RegR::ALL {
+            ops.push(FrameOp::PushR(reg, first, last));
+            ops.push(FrameOp::PopR(reg, first, last));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`FieldOp`] instructions.
+pub fn field_ops() -> Vec<FieldOp> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX32.iter().copied().rev()) {
+        for field in [FieldFlag::Bls12_381, FieldFlag::Bn254] {
+            ops.push(FieldOp::Add(field, idx1, idx2, idx1));
+            ops.push(FieldOp::Mul(field, idx2, idx1, idx2));
+            ops.push(FieldOp::Inv(field, idx1, idx2));
+            ops.push(FieldOp::Exp(field, idx1, idx2, idx2));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`StackOp`] instructions.
+pub fn stack_ops() -> Vec<StackOp> {
+    let mut ops = Vec::new();
+    for (stack, idx) in regs_s().iter().copied().zip(IDX32) {
+        for reg in RegA::ALL {
+            ops.push(StackOp::PushA(stack, reg, idx));
+            ops.push(StackOp::PopA(stack, reg, idx));
+            ops.push(StackOp::PeekA(stack, reg, idx));
+            ops.push(StackOp::DepthA(stack, reg, idx));
+        }
+        for reg in RegR::ALL {
+            ops.push(StackOp::PushR(stack, reg, idx));
+            ops.push(StackOp::PopR(stack, reg, idx));
+            ops.push(StackOp::PeekR(stack, reg, idx));
+            ops.push(StackOp::DepthR(stack, reg, idx));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`HostOp`] instructions.
+pub fn host_ops() -> Vec<HostOp> {
+    let mut ops = Vec::new();
+    for (func, idx) in [0u16, 1, 0x7F00, u16::MAX].iter().copied().zip(IDX32.iter().copied()) {
+        for reg in RegA::ALL {
+            ops.push(HostOp::CallA(func, reg, idx));
+        }
+        for reg in RegR::ALL {
+            ops.push(HostOp::CallR(func, reg, idx));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`XferOp`] instructions.
+pub fn xfer_ops() -> Vec<XferOp> {
+    let sets: [BTreeSet<Reg32>; 4] = [
+        none!(),
+        Reg32::ALL.iter().copied().collect(),
+        IDX32.iter().copied().collect(),
+        [Reg32::Reg1, Reg32::Reg2, Reg32::Reg3, Reg32::Reg4, Reg32::Reg7].iter().copied().collect(),
+    ];
+    let mut ops = Vec::new();
+    for (set1, set2) in sets.iter().zip(sets.iter().rev()) {
+        for reg in RegA::ALL {
+            ops.push(XferOp::MovA(reg, set1.clone(), set2.clone()));
+            ops.push(XferOp::SwpA(reg, set2.clone(), set1.clone()));
+        }
+        for reg in RegF::ALL {
+            ops.push(XferOp::MovF(reg, set1.clone(), set2.clone()));
+            ops.push(XferOp::SwpF(reg, set2.clone(), set1.clone()));
+        }
+        for reg in RegR::ALL {
+            ops.push(XferOp::MovR(reg, set1.clone(), set2.clone()));
+            ops.push(XferOp::SwpR(reg, set2.clone(), set1.clone()));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`StepOp`] instructions.
+pub fn step_ops() -> Vec<StepOp> {
+    let mut ops = Vec::new();
+    for (idx, step) in IDX32.iter().copied().zip([i8::MIN, 0, i8::MAX]) {
+        for flags in 0..4 {
+            let flags = u2::with(flags);
+            for reg in RegA::ALL {
+                ops.push(StepOp::StpA(flags.into(), reg, idx, step));
+                ops.push(StepOp::NegA(flags.into(), reg, idx));
+                ops.push(StepOp::AbsA(flags.into(), reg, idx));
+            }
+            for reg in RegF::ALL {
+                ops.push(StepOp::StpF(flags.into(), reg, idx, -step.saturating_add(1)));
+            }
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`DataOp`] instructions.
+pub fn data_ops() -> Vec<DataOp> {
+    let mut ops = Vec::new();
+    for (idx, off) in IDX32.iter().copied().zip(IDX32.iter().rev().copied()) {
+        for reg in RegA::ALL {
+            ops.push(DataOp::Len(reg, idx));
+            ops.push(DataOp::LoadA(reg, idx, RegA::A16, off));
+        }
+        for reg in RegR::ALL {
+            ops.push(DataOp::LoadR(reg, idx, RegA::A64, off));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`SwitchOp`] instructions. The tables point to the offset of
+/// the instruction itself, which is the only instruction of the libraries assembled by
+/// [`roundtrip`].
+pub fn switch_ops() -> Vec<SwitchOp> {
+    let tables = [vec![], vec![0], vec![0; 4]];
+    let mut ops = Vec::new();
+    for (idx, table) in IDX32.iter().copied().zip(tables) {
+        for reg in RegA::ALL {
+            ops.push(SwitchOp::Switch(reg, idx, table.clone()));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`LoopOp`] instructions.
+pub fn loop_ops() -> Vec<LoopOp> {
+    let mut ops = Vec::new();
+    for idx in IDX32 {
+        for (max, pos) in [(0, 0), (1, 0x0010), (u16::MAX, u16::MAX)] {
+            ops.push(LoopOp::Loop(idx, max, pos));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`FixedOp`] instructions.
+pub fn fixed_ops() -> Vec<FixedOp> {
+    let mut ops = Vec::new();
+    for ((idx1, idx2), frac) in
+        IDX32.iter().copied().zip(IDX32.iter().copied().rev()).zip([0, 16, u16::MAX])
+    {
+        for flags in 0..4 {
+            let flags = u2::with(flags);
+            for reg in RegA::ALL {
+                ops.push(FixedOp::MulA(flags.into(), reg, idx1, idx2, frac));
+                ops.push(FixedOp::DivA(flags.into(), reg, idx1, idx2, frac));
+            }
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`BitcoinOp`] instructions.
+#[cfg(feature = "bitcoin")]
+pub fn bitcoin_ops() -> Vec<BitcoinOp> {
+    let mut ops = Vec::new();
+    for (s, idx) in regs_s().iter().copied().zip(IDX32) {
+        ops.push(BitcoinOp::Hash160(s, idx));
+        ops.push(BitcoinOp::Hash256(s, idx));
+        ops.push(BitcoinOp::CheckSig(s, s, idx));
+        ops.push(BitcoinOp::CheckSchnorr(idx, s, idx));
+    }
+    ops
+}
+
+/// Generates representative set of [`Secp256k1Op`] instructions.
+#[cfg(feature = "secp256k1")]
+pub fn secp256k1_ops() -> Vec<Secp256k1Op> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX8) {
+        ops.push(Secp256k1Op::Gen(idx1, idx2));
+        ops.push(Secp256k1Op::Add(idx1, idx2));
+        ops.push(Secp256k1Op::Neg(idx1, idx2));
+        for block in [RegBlockAR::A, RegBlockAR::R] {
+            ops.push(Secp256k1Op::Mul(block, idx1, idx1, idx1));
+        }
+    }
+    ops
+}
+
+/// Generates representative set of [`Curve25519Op`] instructions.
+#[cfg(feature = "curve25519")]
+pub fn curve25519_ops() -> Vec<Curve25519Op> {
+    let mut ops = Vec::new();
+    for (idx1, idx2) in IDX32.iter().copied().zip(IDX8) {
+        ops.push(Curve25519Op::Gen(idx1, idx2));
+        ops.push(Curve25519Op::Neg(idx1, idx2));
+        for block in [RegBlockAR::A, RegBlockAR::R] {
+            ops.push(Curve25519Op::Mul(block, idx1, idx1, idx1));
+        }
+        for overflow in [false, true] {
+            ops.push(Curve25519Op::Add(idx1, idx1, idx1, overflow));
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::{Extensions, ReservedOp};
+
+    #[test]
+    fn core_roundtrip() {
+        for instr in core_instrs::<ReservedOp>() {
+            assert_eq!(roundtrip(&instr), Ok(()));
+        }
+    }
+
+    #[test]
+    fn env_roundtrip() {
+        for op in env_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn ct_roundtrip() {
+        for op in ct_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn prng_roundtrip() {
+        for op in prng_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn mod_roundtrip() {
+        for op in mod_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn enc_roundtrip() {
+        for op in enc_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rel_jump_roundtrip() {
+        for op in rel_jump_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn imm_roundtrip() {
+        for op in imm_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        for op in frame_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn field_roundtrip() {
+        for op in field_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn stack_roundtrip() {
+        for op in stack_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn host_roundtrip() {
+        for op in host_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn xfer_roundtrip() {
+        for op in xfer_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+
+        let mut lib = Lib::assemble(&xfer_ops()[..1]).unwrap();
+        lib.code.to_mut()[1] |= 0x80;
+        assert!(lib.disassemble::<XferOp>().is_err());
+    }
+
+    #[test]
+    fn step_roundtrip() {
+        for op in step_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+
+        let mut lib = Lib::assemble(&step_ops()[..1]).unwrap();
+        lib.code.to_mut()[3] |= 0x80;
+        assert!(lib.disassemble::<StepOp>().is_err());
+    }
+
+    #[test]
+    fn data_roundtrip() {
+        for op in data_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn switch_roundtrip() {
+        for op in switch_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn loop_roundtrip() {
+        for op in loop_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+
+        let mut lib = Lib::assemble(&loop_ops()[..1]).unwrap();
+        lib.code.to_mut()[1] |= 0x80;
+        assert!(lib.disassemble::<LoopOp>().is_err());
+    }
+
+    #[test]
+    fn fixed_roundtrip() {
+        for op in fixed_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn bitcoin_roundtrip() {
+        for op in bitcoin_ops() {
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn extensions_roundtrip() {
+        for op in env_ops() {
+            let op = Extensions::<EnvOp, CtOp>::First(op);
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+        for op in ct_ops() {
+            let op = Extensions::<EnvOp, CtOp>::Second(op);
+            assert_eq!(roundtrip(&op), Ok(()));
+            assert_eq!(roundtrip(&Instr::ExtensionCodes(op)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn roundtrip_failure() {
+        let instr = Instr::<ReservedOp>::Put(PutOp::PutA(RegA::A8, Reg32::Reg0, none!()));
+        assert!(matches!(roundtrip_all(&[instr]), Err(RoundtripError::Encode(..))));
+    }
+}
@@ -0,0 +1,356 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//! Data-driven tests of AluVM programs.
+//!
+//! A [`Fixture`] specifies the library routine to run, the initial state of the registers and the
+//! expected state of the registers after the routine completes. Fixtures are kept in JSON files:
+//!
+//! ```json
+//! {
+//!   "routine": "main",
+//!   "pre": { "regs": { "a8[0]": "3", "s16[1]": "\"abc\"" } },
+//!   "post": { "regs": { "a8[0]": "0", "a16[2]": "~" }, "st0": true }
+//! }
+//! ```
+//!
+//! Registers use the assembly syntax (see [`crate::isa`]). Values of `A`, `F` and `R` registers
+//! use the syntax of `put` instruction values, and values of `S` registers are string literals;
+//! `~` means no value. The routine is looked up with [`Lib::entrypoint`], and the execution starts
+//! at the beginning of the code if it is not specified. The registers not mentioned in the `pre`
+//! state are not set and `st0` is `true`; registers not mentioned in the `post` state are not
+//! checked, and `st0` is checked only if it is mentioned.
+//!
+//! ```
+//! use aluvm::isa::Instr;
+//! use aluvm::library::Lib;
+//! use aluvm::testkit::{assert_exec, Fixture};
+//!
+//! let lib = Lib::assemble_source::<Instr>(".entry main\nadd.uc a8[0],a8[1]\nret").unwrap();
+//! let fixture = Fixture::from_json(
+//!     r#"{
+//!         "routine": "main",
+//!         "pre": { "regs": { "a8[0]": "1", "a8[1]": "2" } },
+//!         "post": { "regs": { "a8[1]": "3" }, "st0": true }
+//!     }"#,
+//! )
+//! .unwrap();
+//! assert_exec(&lib, &fixture);
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::data::{ByteStr, MaybeNumber};
+use crate::isa::{operand, Instr, InstructionSet, PutOp, ReservedOp};
+use crate::library::{Lib, LibSite};
+use crate::reg::{CoreRegs, Reg, RegS};
+use crate::{Prog, RunError, Vm};
+
+/// Errors parsing and running fixtures
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum FixtureError {
+    /// invalid fixture JSON: {0}
+    Json(String),
+
+    /// invalid value `{value}` of register `{reg}`.
+    Value {
+        /// Register name
+        reg: String,
+        /// Invalid value
+        value: String,
+    },
+
+    /// library does not export routine `{0}`.
+    Routine(String),
+
+    /// routine execution has failed: {0}
+    Run(RunError),
+
+    /// register {reg} has value {actual} instead of {expected}.
+    Register {
+        /// Register name
+        reg: String,
+        /// Value expected by the fixture
+        expected: String,
+        /// Value produced by the routine
+        actual: String,
+    },
+
+    /// st0 is {actual} instead of {expected}.
+    Status {
+        /// Value expected by the fixture
+        expected: bool,
+        /// Value produced by the routine
+        actual: bool,
+    },
+}
+
+/// State of the registers in a fixture
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", deny_unknown_fields)]
+pub struct RegFixture {
+    /// Values of the registers keyed by the register names
+    #[serde(default)]
+    pub regs: BTreeMap<String, String>,
+
+    /// Value of `st0` register, if specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub st0: Option<bool>,
+}
+
+/// Value of a single register in a fixture
+enum RegValue {
+    Put(PutOp),
+    Str(RegS, Option<ByteStr>),
+}
+
+impl RegValue {
+    fn parse(reg: &str, value: &str) -> Result<Self, FixtureError> {
+        let invalid = || FixtureError::Value { reg: reg.to_owned(), value: value.to_owned() };
+        if let Some(s) = operand::reg_s(reg) {
+            return match value {
+                "~" => Ok(RegValue::Str(s, None)),
+                _ => operand::byte_str(value)
+                    .map(|val| RegValue::Str(s, Some(val)))
+                    .map_err(|_| invalid()),
+            };
+        }
+        match format!("put {},{}", reg, value).parse::<Instr<ReservedOp>>() {
+            Ok(Instr::Put(op @ (PutOp::PutA(..) | PutOp::PutF(..) | PutOp::PutR(..)))) => {
+                Ok(RegValue::Put(op))
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    fn apply(&self, regs: &mut CoreRegs) {
+        match self {
+            RegValue::Put(op) => {
+                op.exec(regs, LibSite::default(), &());
+            }
+            RegValue::Str(s, val) => {
+                regs.set_s(*s, val.clone());
+            }
+        }
+    }
+
+    /// Returns textual representation of the register value in the registers.
+    fn read(&self, regs: &CoreRegs) -> String {
+        match self {
+            RegValue::Put(op) => {
+                let value = match op.dst_regs().into_iter().next() {
+                    Some(Reg::A(a, idx)) => regs.get(a, idx),
+                    Some(Reg::F(f, idx)) => regs.get(f, idx),
+                    Some(Reg::R(r, idx)) => regs.get(r, idx),
+                    _ => MaybeNumber::none(),
+                };
+                value.to_string()
+            }
+            RegValue::Str(s, _) => match regs.get_s(*s) {
+                Some(val) => format!("{:?}", val.to_string()),
+                None => s!("~"),
+            },
+        }
+    }
+}
+
+impl RegFixture {
+    fn parse(&self) -> Result<BTreeMap<&str, RegValue>, FixtureError> {
+        self.regs
+            .iter()
+            .map(|(reg, value)| RegValue::parse(reg, value).map(|val| (reg.as_str(), val)))
+            .collect()
+    }
+}
+
+/// Data-driven test of a library routine
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "serde_crate", deny_unknown_fields)]
+pub struct Fixture {
+    /// Name of the exported routine to run; if absent, the execution starts at the beginning of
+    /// the code
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routine: Option<String>,
+
+    /// State of the registers before the routine execution
+    #[serde(default)]
+    pub pre: RegFixture,
+
+    /// Expected state of the registers after the routine execution
+    #[serde(default)]
+    pub post: RegFixture,
+}
+
+impl Fixture {
+    /// Parses fixture from JSON.
+    ///
+    /// # Errors
+    ///
+    /// If the JSON does not match the fixture schema. Register values are checked when the
+    /// fixture is run.
+    pub fn from_json(json: &str) -> Result<Fixture, FixtureError> {
+        serde_json::from_str(json).map_err(|err| FixtureError::Json(err.to_string()))
+    }
+
+    /// Serializes the fixture into JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serialization into JSON never fails")
+    }
+
+    /// Runs the fixture routine of the library, parsed as `Isa` code, checking the resulting
+    /// state of the registers. Calls to other libraries fail.
+    ///
+    /// # Errors
+    ///
+    /// If the fixture has invalid register values or unknown routine, if the routine execution
+    /// fails, or if the resulting state of the registers doesn't match the fixture post-state.
+    pub fn check<Isa>(&self, lib: &Lib, context: &Isa::Context<'_>) -> Result<(), FixtureError>
+    where
+        Isa: InstructionSet,
+    {
+        let pre = self.pre.parse()?;
+        let post = self.post.parse()?;
+        let pos = match &self.routine {
+            Some(name) => {
+                lib.entrypoint(name).ok_or_else(|| FixtureError::Routine(name.clone()))?
+            }
+            None => 0,
+        };
+
+        let mut vm = Vm::<Isa>::new();
+        pre.values().for_each(|val| val.apply(&mut vm.registers));
+        vm.registers.st0 = self.pre.st0.unwrap_or(true);
+        let prog = Prog::<Isa>::new(lib.clone());
+        let st0 =
+            vm.try_call(&prog, LibSite::with(pos, lib.id()), context).map_err(FixtureError::Run)?;
+
+        let mut expected = CoreRegs::new();
+        post.values().for_each(|val| val.apply(&mut expected));
+        for (reg, val) in post {
+            let (expected, actual) = (val.read(&expected), val.read(&vm.registers));
+            if expected != actual {
+                return Err(FixtureError::Register { reg: reg.to_owned(), expected, actual });
+            }
+        }
+        match self.post.st0 {
+            Some(expected) if expected != st0 => {
+                Err(FixtureError::Status { expected, actual: st0 })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Runs the fixture routine of the library with the core instruction set, panicking if the
+/// resulting state of the registers doesn't match the fixture (see [`Fixture::check`]).
+///
+/// # Panics
+///
+/// If the fixture check fails.
+#[track_caller]
+pub fn assert_exec(lib: &Lib, fixture: &Fixture) {
+    if let Err(err) = fixture.check::<Instr>(lib, &()) {
+        panic!("fixture check failed: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lib() -> Lib<'static> {
+        Lib::assemble_source::<Instr>(
+            "
+            .entry  inc
+            add.uc  a8[0],a8[1]
+            ret
+            .entry  move
+            mov     s16[0],s16[1]
+            ret
+            ",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fixtures() {
+        let fixture = Fixture::from_json(
+            r#"{
+                "routine": "inc",
+                "pre": { "regs": { "a8[0]": "1", "a8[1]": "2" } },
+                "post": { "regs": { "a8[1]": "3", "a16[0]": "~" }, "st0": true }
+            }"#,
+        )
+        .unwrap();
+        assert_exec(&lib(), &fixture);
+        assert_eq!(Fixture::from_json(&fixture.to_json()), Ok(fixture.clone()));
+
+        let fixture = Fixture {
+            routine: Some(s!("move")),
+            pre: RegFixture { regs: bmap! { s!("s16[0]") => s!("\"abc\"") }, st0: None },
+            post: RegFixture {
+                regs: bmap! { s!("s16[0]") => s!("~"), s!("s16[1]") => s!("\"abc\"") },
+                st0: Some(true),
+            },
+        };
+        assert_exec(&lib(), &fixture);
+    }
+
+    #[test]
+    fn failures() {
+        let lib = lib();
+        let mut fixture = Fixture {
+            routine: Some(s!("inc")),
+            pre: RegFixture { regs: bmap! { s!("a8[0]") => s!("1") }, st0: None },
+            post: RegFixture { regs: bmap! { s!("a8[1]") => s!("3") }, st0: Some(true) },
+        };
+        assert_eq!(
+            fixture.check::<Instr>(&lib, &()),
+            Err(FixtureError::Register { reg: s!("a8[1]"), expected: s!("3"), actual: s!("~") })
+        );
+
+        fixture.post.regs.clear();
+        assert_eq!(
+            fixture.check::<Instr>(&lib, &()),
+            Err(FixtureError::Status { expected: true, actual: false })
+        );
+
+        fixture.routine = Some(s!("dec"));
+        assert_eq!(fixture.check::<Instr>(&lib, &()), Err(FixtureError::Routine(s!("dec"))));
+
+        fixture.pre.regs.insert(s!("x8[0]"), s!("1"));
+        assert_eq!(
+            fixture.check::<Instr>(&lib, &()),
+            Err(FixtureError::Value { reg: s!("x8[0]"), value: s!("1") })
+        );
+
+        assert!(matches!(Fixture::from_json(r#"{ "pre": 1 }"#), Err(FixtureError::Json(_))));
+        assert!(matches!(Fixture::from_json(r#"{ "routin": "inc" }"#), Err(FixtureError::Json(_))));
+    }
+}
@@ -0,0 +1,284 @@
+// Reference rust implementation of AluVM (arithmetic logic unit virtual machine).
+// To find more on AluVM please check <https://aluvm.org>
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Written in 2021-2023 by
+//     Dr Maxim Orlovsky <orlovsky@ubideco.org>
+//
+// Copyright (C) 2021-2022 LNP/BP Standards Association. All rights reserved.
+// Copyright (C) 2023 UBIDECO Institute. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Instruction-level test vectors for conformance testing of AluVM implementations.
+//!
+//! A test vector specifies a single instruction, the state of the registers before its execution
+//! and the expected state of the registers after it. Vectors are kept in text files with
+//! semicolon-separated columns, one vector per line:
+//!
+//! ```text
+//! vectors  := *( vector | comment | empty ) ; lines
+//! vector   := instr ';' state ';' state
+//! comment  := '#' *any
+//! state    := *( ws+ item )
+//! item     := register '=' value | 'st0=' bool
+//! ```
+//!
+//! Instructions use the assembly syntax (see [`crate::isa`]). Register values use the syntax of
+//! `put` instruction values, where `~` means no value. Only `A`, `F` and `R` registers are
+//! supported. The registers not mentioned in the pre-state are not set and `st0` is `true`;
+//! registers not mentioned in the post-state are not checked, and `st0` is checked only if it is
+//! mentioned.
+//!
+//! The crate publishes a vector set for the core arithmetic, comparison and bitwise instructions
+//! as [`CORE_VECTORS`].
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::data::MaybeNumber;
+use crate::isa::{Instr, InstructionSet, ParseInstrError, PutOp, ReservedOp};
+use crate::library::LibSite;
+use crate::reg::{CoreRegs, Reg};
+
+/// Test vectors for the core arithmetic, comparison and bitwise instructions.
+pub const CORE_VECTORS: &str = include_str!("../vectors/core.csv");
+
+/// Errors parsing and running test vectors
+#[derive(Clone, Eq, PartialEq, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum VectorError {
+    /// line {0}: the vector must contain instruction, pre-state and post-state separated by
+    /// semicolons.
+    Columns(usize),
+
+    /// line {line}: invalid register state `{item}`.
+    State {
+        /// Line of the test vector
+        line: usize,
+        /// Invalid state item
+        item: String,
+    },
+
+    /// line {0}: {1}
+    Instr(usize, ParseInstrError),
+
+    /// line {line}: register {reg} has value {actual} instead of {expected}.
+    Register {
+        /// Line of the test vector
+        line: usize,
+        /// Register with unexpected value
+        reg: Reg,
+        /// Value expected by the test vector
+        expected: Box<MaybeNumber>,
+        /// Value produced by the instruction
+        actual: Box<MaybeNumber>,
+    },
+
+    /// line {line}: st0 is {actual} instead of {expected}.
+    Status {
+        /// Line of the test vector
+        line: usize,
+        /// Value expected by the test vector
+        expected: bool,
+        /// Value produced by the instruction
+        actual: bool,
+    },
+}
+
+/// State of the registers in a test vector
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct RegState {
+    /// Values of the registers, as `put` operations
+    pub regs: Vec<PutOp>,
+
+    /// Value of `st0` register, if specified
+    pub st0: Option<bool>,
+}
+
+impl RegState {
+    fn parse(line: usize, text: &str) -> Result<Self, VectorError> {
+        let mut state = RegState::default();
+        for item in text.split_whitespace() {
+            let invalid = || VectorError::State { line, item: item.to_owned() };
+            let (reg, val) = item.split_once('=').ok_or_else(invalid)?;
+            match (reg, val) {
+                ("st0", "true") => state.st0 = Some(true),
+                ("st0", "false") => state.st0 = Some(false),
+                ("st0", _) => return Err(invalid()),
+                _ => match format!("put {},{}", reg, val).parse::<Instr<ReservedOp>>() {
+                    Ok(Instr::Put(op @ (PutOp::PutA(..) | PutOp::PutF(..) | PutOp::PutR(..)))) => {
+                        state.regs.push(op)
+                    }
+                    _ => return Err(invalid()),
+                },
+            }
+        }
+        Ok(state)
+    }
+
+    /// Puts the register values into the registers.
+    fn apply(&self, regs: &mut CoreRegs) {
+        for op in &self.regs {
+            op.exec(regs, LibSite::default(), &());
+        }
+    }
+}
+
+/// Instruction test vector
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TestVector {
+    /// Line of the vector in the source text, starting from 1
+    pub line: usize,
+
+    /// Tested instruction in assembly syntax
+    pub instr: String,
+
+    /// State of the registers before the instruction execution
+    pub pre: RegState,
+
+    /// Expected state of the registers after the instruction execution
+    pub post: RegState,
+}
+
+impl TestVector {
+    /// Parses test vectors from the text, skipping empty lines and comments.
+    ///
+    /// # Errors
+    ///
+    /// If any of the lines is not a valid test vector.
+    pub fn parse_all(text: &str) -> Result<Vec<TestVector>, VectorError> {
+        text.lines()
+            .enumerate()
+            .map(|(no, line)| (no + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line, text)| {
+                let mut cols = text.split(';');
+                let (Some(instr), Some(pre), Some(post), None) =
+                    (cols.next(), cols.next(), cols.next(), cols.next())
+                else {
+                    return Err(VectorError::Columns(line));
+                };
+                Ok(TestVector {
+                    line,
+                    instr: instr.trim().to_owned(),
+                    pre: RegState::parse(line, pre)?,
+                    post: RegState::parse(line, post)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Executes the vector instruction, parsed as `Isa` instruction, checking the resulting state
+    /// of the registers.
+    ///
+    /// # Errors
+    ///
+    /// If the instruction can't be parsed, or the resulting state of the registers doesn't match
+    /// the vector post-state.
+    pub fn run<Isa>(&self, context: &Isa::Context<'_>) -> Result<(), VectorError>
+    where
+        Isa: InstructionSet + core::str::FromStr<Err = ParseInstrError>,
+    {
+        let instr = self.instr.parse::<Isa>().map_err(|err| VectorError::Instr(self.line, err))?;
+
+        let mut regs = CoreRegs::new();
+        self.pre.apply(&mut regs);
+        regs.st0 = self.pre.st0.unwrap_or(true);
+        instr.exec(&mut regs, LibSite::default(), context);
+
+        let mut expected = CoreRegs::new();
+        self.post.apply(&mut expected);
+        for reg in self.post.regs.iter().flat_map(PutOp::dst_regs) {
+            let value = |regs: &CoreRegs| match reg {
+                Reg::A(a, idx) => regs.get(a, idx),
+                Reg::F(f, idx) => regs.get(f, idx),
+                Reg::R(r, idx) => regs.get(r, idx),
+                Reg::S(_) => unreachable!("string registers are not supported by the vectors"),
+            };
+            let (expected, actual) = (value(&expected), value(&regs));
+            if expected != actual {
+                return Err(VectorError::Register {
+                    line: self.line,
+                    reg,
+                    expected: Box::new(expected),
+                    actual: Box::new(actual),
+                });
+            }
+        }
+        match self.post.st0 {
+            Some(expected) if expected != regs.st0 => {
+                Err(VectorError::Status { line: self.line, expected, actual: regs.st0 })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn core_vectors() {
+        let vectors = TestVector::parse_all(CORE_VECTORS).unwrap();
+        assert!(vectors.len() > 50);
+        let errs: Vec<_> = vectors.iter().filter_map(|v| v.run::<Instr>(&()).err()).collect();
+        assert!(
+            errs.is_empty(),
+            "{}",
+            errs.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("\n")
+        );
+    }
+
+    #[test]
+    fn failures() {
+        let vectors = TestVector::parse_all(
+            "
+            # comment
+            add.uc a8[0],a8[1] ; a8[0]=1 a8[1]=2 ; a8[1]=4
+            add.uc a8[0],a8[1] ; a8[0]=1 a8[1]=2 ; st0=false
+            ",
+        )
+        .unwrap();
+        assert_eq!(vectors[0].line, 3);
+        assert_eq!(
+            vectors[0].run::<Instr>(&()),
+            Err(VectorError::Register {
+                line: 3,
+                reg: Reg::A(crate::reg::RegA::A8, crate::reg::Reg32::Reg1),
+                expected: Box::new(MaybeNumber::from(4u8)),
+                actual: Box::new(MaybeNumber::from(3u8)),
+            })
+        );
+        assert_eq!(
+            vectors[1].run::<Instr>(&()),
+            Err(VectorError::Status { line: 4, expected: false, actual: true })
+        );
+
+        assert_eq!(TestVector::parse_all("nop ; "), Err(VectorError::Columns(1)));
+        assert_eq!(
+            TestVector::parse_all("nop ; s16[0]=1 ;"),
+            Err(VectorError::State { line: 1, item: s!("s16[0]=1") })
+        );
+        assert!(matches!(
+            TestVector::parse_all("nope ; ;").unwrap()[0].run::<Instr>(&()),
+            Err(VectorError::Instr(1, _))
+        ));
+    }
+}
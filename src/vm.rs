@@ -24,15 +24,397 @@
 //! Alu virtual machine
 
 use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
 use core::marker::PhantomData;
+use core::mem;
 
+use crate::data::MaybeNumber;
 use crate::isa::{Instr, InstructionSet, ReservedOp};
-use crate::library::LibSite;
-use crate::reg::CoreRegs;
-use crate::Program;
+use crate::library::{
+    Abi, AbiError, DisassembleError, EntrypointError, ExecOutcome, ExecResult, LibId, LibSite,
+};
+use crate::reg::{CoreRegs, Reg32, RegAR, SnapshotError};
+use crate::replay::{HostResult, Recording, ReplayError};
+use crate::{LinkError, Program};
+
+/// Limits on program execution, providing protection against non-terminating or adversarial
+/// code even without configuring complexity metering.
+///
+/// Each of the limits is optional; a limit which is set to `None` is not checked. The default
+/// value has no limits set.
+///
+/// The limits apply to each program run separately: the counters checked against `max_steps` and
+/// `max_backward_jumps` are reset when [`Vm`] starts a program, and are carried over when a
+/// suspended execution is resumed with [`Vm::resume`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct RunLimits {
+    /// Maximum number of instructions which may be executed.
+    pub max_steps: Option<u64>,
+
+    /// Maximum number of jumps to the same or preceding code position within a library (i.e.
+    /// loop iterations).
+    pub max_backward_jumps: Option<u32>,
+
+    /// Maximum depth of the call stack.
+    pub max_call_depth: Option<u16>,
+
+    /// Maximum aggregate length of the byte strings kept in the string (`S`) registers. An
+    /// instruction which would exceed it leaves its destination string register set to `None`
+    /// and `st0` set to `false`.
+    pub max_str_bytes: Option<u32>,
+
+    /// Maximum number of external calls logged into the call receipt kept with the registers
+    /// (see [`CoreRegs::receipt`]). The call exceeding the limit is still logged, and the
+    /// execution is terminated right after it.
+    pub max_calls: Option<u32>,
+}
+
+impl RunLimits {
+    /// Constructs limits with none of the limits set.
+    #[inline]
+    pub const fn unlimited() -> Self {
+        RunLimits {
+            max_steps: None,
+            max_backward_jumps: None,
+            max_call_depth: None,
+            max_str_bytes: None,
+            max_calls: None,
+        }
+    }
+
+    /// Sets maximum number of executed instructions.
+    #[inline]
+    pub const fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Sets maximum number of backward jumps.
+    #[inline]
+    pub const fn with_max_backward_jumps(mut self, max_backward_jumps: u32) -> Self {
+        self.max_backward_jumps = Some(max_backward_jumps);
+        self
+    }
+
+    /// Sets maximum depth of the call stack.
+    #[inline]
+    pub const fn with_max_call_depth(mut self, max_call_depth: u16) -> Self {
+        self.max_call_depth = Some(max_call_depth);
+        self
+    }
+
+    /// Sets maximum aggregate length of the byte strings in the string registers.
+    #[inline]
+    pub const fn with_max_str_bytes(mut self, max_str_bytes: u32) -> Self {
+        self.max_str_bytes = Some(max_str_bytes);
+        self
+    }
+
+    /// Sets maximum number of the external calls logged into the call receipt.
+    #[inline]
+    pub const fn with_max_calls(mut self, max_calls: u32) -> Self {
+        self.max_calls = Some(max_calls);
+        self
+    }
+}
+
+/// Statistics of the resources used by the program execution, allowing hosts to tune
+/// [`RunLimits`] and to profile workloads.
+///
+/// The statistics are collected by [`CoreRegs`] since the start of the last program run by
+/// [`Vm`] (see [`CoreRegs::stats`]), and for each library run are reported by
+/// [`ExecResult::stats`](crate::library::ExecResult::stats).
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(crate = "serde_crate"))]
+pub struct ExecStats {
+    /// Maximal aggregate length of the byte strings kept in the string (`S`) registers.
+    pub peak_str_bytes: u32,
+
+    /// Total length of the byte strings written into the string registers.
+    pub str_bytes_written: u64,
+
+    /// Number of values written into the registers, including writes of `None`.
+    pub reg_writes: u64,
+
+    /// Number of values read from the library data segments.
+    pub data_reads: u64,
+}
+
+impl ExecStats {
+    /// Returns statistics accumulated since the `start` statistics were taken. The peak string
+    /// register memory is kept as is.
+    #[inline]
+    pub(crate) fn since(self, start: ExecStats) -> ExecStats {
+        ExecStats {
+            peak_str_bytes: self.peak_str_bytes,
+            str_bytes_written: self.str_bytes_written.saturating_sub(start.str_bytes_written),
+            reg_writes: self.reg_writes.saturating_sub(start.reg_writes),
+            data_reads: self.data_reads.saturating_sub(start.data_reads),
+        }
+    }
+}
+
+/// Number of executed instructions after which the deadline of the program execution started with
+/// [`Vm::run_with_deadline`] or [`Vm::call_with_deadline`] is checked.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub const DEADLINE_CHECK_STEPS: u64 = 1024;
+
+/// Errors happening when a program execution exceeds one of [`RunLimits`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display)]
+#[cfg_attr(feature = "std", derive(Error))]
+#[display(doc_comments)]
+pub enum RunLimitError {
+    /// program execution exceeded the limit of {0} instructions.
+    StepsExceeded(u64),
+
+    /// program execution exceeded the limit of {0} backward jumps.
+    BackwardJumpsExceeded(u32),
+
+    /// program execution exceeded the maximum call stack depth of {0}.
+    CallDepthExceeded(u16),
+
+    /// program execution exceeded the limit of {0} external calls.
+    CallsExceeded(u32),
+
+    /// program execution exceeded its deadline.
+    Timeout,
+}
+
+/// Errors terminating program execution by [`Vm::try_run`], [`Vm::try_call`],
+/// [`crate::library::Lib::run`] and [`crate::library::Lib::try_run`].
+///
+/// Errors happening during the execution carry the location at which the execution has failed,
+/// which is returned by [`RunError::site`].
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Display, From)]
+#[display(inner)]
+pub enum RunError {
+    /// Program can't be started since some of the libraries it calls are absent.
+    #[from]
+    Link(LinkError),
+
+    /// Program execution can't start at the provided site, since it is not a start of an
+    /// instruction in the library code segment.
+    #[display("{0} (at {1})")]
+    Entrypoint(EntrypointError, LibSite),
+
+    /// Program execution exceeded one of the [`RunLimits`] at the instruction located at the
+    /// provided site.
+    #[display("{0} (at {1})")]
+    Limit(RunLimitError, LibSite),
+
+    /// Program code can't be decoded; the site points to the instruction which can't be decoded.
+    #[display("{0} (at {1})")]
+    Decode(DisassembleError, LibSite),
+
+    /// Registers do not match the ABI descriptor of the routine at the provided site (see
+    /// [`Vm::check_inputs`] and [`Vm::check_outputs`]).
+    #[display("{0} (at {1})")]
+    Abi(AbiError, LibSite),
+
+    /// Call into another library was denied by the [`CallHook`].
+    #[display("call from {from} to {to} was denied")]
+    CallDenied {
+        /// Location of the call instruction
+        from: LibSite,
+        /// Location in the called library
+        to: LibSite,
+    },
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        match self {
+            RunError::Link(err) => Some(err),
+            RunError::Entrypoint(err, _) => Some(err),
+            RunError::Limit(err, _) => Some(err),
+            RunError::Decode(err, _) => Some(err),
+            RunError::Abi(err, _) => Some(err),
+            RunError::CallDenied { .. } => None,
+        }
+    }
+}
+
+impl RunError {
+    /// Returns location at which the execution has failed: the invalid entry point, the
+    /// instruction which exceeded the limits, can't be decoded or was denied to call other
+    /// library, or the called routine which inputs do not match its ABI descriptor.
+    ///
+    /// Returns `None` for [`RunError::Link`] errors, which are detected before the execution
+    /// starts.
+    pub fn site(&self) -> Option<LibSite> {
+        match self {
+            RunError::Link(_) => None,
+            RunError::Entrypoint(_, site)
+            | RunError::Limit(_, site)
+            | RunError::Decode(_, site)
+            | RunError::Abi(_, site) => Some(*site),
+            RunError::CallDenied { from, .. } => Some(*from),
+        }
+    }
+}
+
+/// Version of the binary format produced by [`VmState::to_bytes`]
+const VM_STATE_VERSION: u8 = 3;
+
+/// Complete state of a suspended program execution, which may be serialized and later resumed
+/// with [`Vm::resume`], including in another process.
+#[derive(Clone, Debug)]
+pub struct VmState {
+    /// Location of the instruction from which the execution is resumed
+    pub site: LibSite,
+
+    /// State of the registers, including the call stack and the instruction counter
+    pub registers: Box<CoreRegs>,
+}
+
+impl VmState {
+    /// Serializes execution state into a compact binary representation: format version byte,
+    /// followed by the resume location and [`CoreRegs::snapshot`] data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![VM_STATE_VERSION];
+        buf.extend(self.site.pos.to_le_bytes());
+        buf.extend(self.site.lib.to_byte_array());
+        buf.extend(self.registers.snapshot());
+        buf
+    }
+
+    /// Deserializes execution state from the data produced by [`VmState::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// If the data have unsupported format version, are truncated or contain invalid register
+    /// state.
+    pub fn from_bytes(data: &[u8]) -> Result<VmState, SnapshotError> {
+        let (&version, data) = data.split_first().ok_or(SnapshotError::Truncated)?;
+        if version != VM_STATE_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        if data.len() < 34 {
+            return Err(SnapshotError::Truncated);
+        }
+        let (site, data) = data.split_at(34);
+        let mut lib = [0u8; 32];
+        lib.copy_from_slice(&site[2..]);
+        let site = LibSite::with(u16::from_le_bytes([site[0], site[1]]), lib.into());
+        let registers = Box::new(CoreRegs::restore(data)?);
+        Ok(VmState { site, registers })
+    }
+}
+
+/// Result of a program execution performed in chunks with [`Vm::start`] and [`Vm::resume`]
+#[derive(Clone, Debug)]
+pub enum Execution {
+    /// Program execution has completed with the provided value of `st0` register
+    Complete(bool),
+
+    /// Program execution was suspended after executing the requested number of instructions
+    Suspended(VmState),
+
+    /// Program execution was suspended by a call of host function (see
+    /// [`HostOp`](crate::isa::HostOp)), which must be completed before resuming the execution
+    Pending(PendingCall),
+
+    /// Program execution was suspended by an extension instruction yielding to the host (see
+    /// [`ExecStep::Yield`](crate::isa::ExecStep::Yield)); it may be resumed with [`Vm::resume`]
+    Yielded {
+        /// Embed-defined code provided by the yielding instruction
+        code: u16,
+
+        /// State to resume the execution from, which starts at the instruction following the
+        /// yielding one
+        state: VmState,
+    },
+}
+
+/// Host function call requested by a suspended program, which is completed by the embedder with
+/// the function result.
+#[derive(Clone, Debug)]
+pub struct PendingCall {
+    /// Host function identifier
+    pub func: u16,
+
+    /// Register receiving the function result
+    pub reg: RegAR,
+
+    /// Index of the register receiving the function result
+    pub dst: Reg32,
+
+    state: VmState,
+}
+
+impl PendingCall {
+    /// Returns location of the instruction which will be executed after the call.
+    #[inline]
+    pub fn site(&self) -> LibSite { self.state.site }
+
+    /// Returns registers at the moment of the call, which contain function arguments.
+    #[inline]
+    pub fn registers(&self) -> &CoreRegs { &self.state.registers }
+
+    /// Completes the call by putting the function result into the destination register,
+    /// returning execution state to be resumed with [`Vm::resume`]. If the result is `None`,
+    /// `st0` register is set to `false`.
+    pub fn complete(self, value: impl Into<MaybeNumber>) -> VmState {
+        let mut state = self.state;
+        if !state.registers.set(self.reg, self.dst, value) {
+            state.registers.st0 = false;
+        }
+        state
+    }
+}
+
+/// Hook invoked by [`Vm`] when the execution passes between libraries, allowing embedders to
+/// log the inter-library control flow or to restrict the libraries which may be called.
+///
+/// Calls and returns within the same library (including the `call` and `exec` instructions
+/// targeting the library they belong to) are not reported.
+///
+/// The set of library ids implements the hook allowing calls only to the libraries from the set.
+pub trait CallHook: Debug + Send + Sync {
+    /// Invoked after `call` or `exec` instruction at `caller` site is executed, before passing
+    /// the execution to the `callee` site in another library.
+    ///
+    /// Returning `false` denies the call, terminating the program with
+    /// [`RunError::CallDenied`].
+    fn before_call(&mut self, caller: LibSite, callee: LibSite, registers: &CoreRegs) -> bool {
+        let _ = (caller, callee, registers);
+        true
+    }
+
+    /// Invoked after `ret` instruction at `callee` site returns the execution to the `caller`
+    /// site in another library.
+    fn after_return(&mut self, callee: LibSite, caller: LibSite, registers: &CoreRegs) {
+        let _ = (callee, caller, registers);
+    }
+}
+
+impl CallHook for BTreeSet<LibId> {
+    fn before_call(&mut self, _: LibSite, callee: LibSite, _: &CoreRegs) -> bool {
+        self.contains(&callee.lib)
+    }
+}
+
+/// Hook invoked by [`Vm`] each time an instruction clears `st0` register, allowing to locate the
+/// check which has failed in a long validation program without tracing its whole execution.
+///
+/// Instructions which leave `st0` register unchanged, or set it to `false` when it is already
+/// `false`, are not reported.
+pub trait FailureHook<Isa>: Debug + Send + Sync
+where
+    Isa: InstructionSet,
+{
+    /// Invoked after `instr` located at `site` was executed and has changed `st0` register value
+    /// from `true` to `false`.
+    fn st0_cleared(&mut self, instr: &Isa, site: LibSite, registers: &CoreRegs);
+}
 
 /// Alu virtual machine providing single-core execution environment
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Vm<Isa = Instr<ReservedOp>>
 where
     Isa: InstructionSet,
@@ -40,16 +422,83 @@ where
     /// A set of registers
     pub registers: Box<CoreRegs>,
 
+    call_hook: Option<Box<dyn CallHook>>,
+
+    failure_hook: Option<Box<dyn FailureHook<Isa>>>,
+
     phantom: PhantomData<Isa>,
 }
 
+impl<Isa> Default for Vm<Isa>
+where
+    Isa: InstructionSet,
+{
+    /// Constructs new virtual machine instance with [`Vm::new`].
+    ///
+    /// # Panics
+    ///
+    /// Same as for [`Vm::new`].
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
+
 /// Runtime for program execution.
 impl<Isa> Vm<Isa>
 where
     Isa: InstructionSet,
 {
     /// Constructs new virtual machine instance.
-    pub fn new() -> Self { Self { registers: Box::default(), phantom: Default::default() } }
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instruction set is composed from ISA extensions claiming the same opcodes as
+    /// the core instruction set or each other (see
+    /// [`Bytecode::opcode_collisions`](crate::isa::Bytecode::opcode_collisions)), since the
+    /// code using them would be silently mis-decoded.
+    pub fn new() -> Self {
+        let collisions = Isa::opcode_collisions();
+        if !collisions.is_empty() {
+            let report = collisions.iter().map(|c| format!("\n- {}", c)).collect::<String>();
+            panic!("instruction set has colliding opcodes:{}", report);
+        }
+        Self {
+            registers: Box::default(),
+            call_hook: None,
+            failure_hook: None,
+            phantom: Default::default(),
+        }
+    }
+
+    /// Constructs new virtual machine instance which will terminate program execution once any
+    /// of the provided execution limits is exceeded.
+    pub fn with_limits(limits: RunLimits) -> Self {
+        let mut vm = Self::new();
+        vm.registers.set_limits(limits);
+        vm
+    }
+
+    /// Sets hook invoked on the calls into other libraries and returns from them, returning the
+    /// previously set hook, if any.
+    pub fn set_call_hook(&mut self, hook: impl CallHook + 'static) -> Option<Box<dyn CallHook>> {
+        self.call_hook.replace(Box::new(hook))
+    }
+
+    /// Removes call hook from the virtual machine, returning it.
+    pub fn take_call_hook(&mut self) -> Option<Box<dyn CallHook>> { self.call_hook.take() }
+
+    /// Sets hook invoked when instructions clear `st0` register, returning the previously set
+    /// hook, if any.
+    pub fn set_failure_hook(
+        &mut self,
+        hook: impl FailureHook<Isa> + 'static,
+    ) -> Option<Box<dyn FailureHook<Isa>>> {
+        self.failure_hook.replace(Box::new(hook))
+    }
+
+    /// Removes failure hook from the virtual machine, returning it.
+    pub fn take_failure_hook(&mut self) -> Option<Box<dyn FailureHook<Isa>>> {
+        self.failure_hook.take()
+    }
 
     /// Executes the program starting from the provided entry point (set with
     /// [`Program::set_entrypoint`] and [`Program::with`], or initialized to 0 offset of the
@@ -73,16 +522,1319 @@ where
         method: LibSite,
         context: &Isa::Context<'_>,
     ) -> bool {
+        self.try_call(program, method, context).unwrap_or(false)
+    }
+
+    /// Executes the program starting from the provided entry point (set with
+    /// [`Program::set_entrypoint`] and [`Program::with`], or initialized to 0 offset of the
+    /// first used library if [`Program::new`] was used), checking execution limits.
+    ///
+    /// # Returns
+    ///
+    /// Value of the `st0` register at the end of the program execution.
+    ///
+    /// # Errors
+    ///
+    /// If some of the libraries required by the entry point are absent, if the program code can't
+    /// be decoded, or if the execution was terminated due to one of the [`RunLimits`] being
+    /// exceeded.
+    pub fn try_run(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        context: &Isa::Context<'_>,
+    ) -> Result<bool, RunError> {
+        self.try_call(program, program.entrypoint(), context)
+    }
+
+    /// Executes the program starting from the provided entry point, checking execution limits.
+    ///
+    /// # Returns
+    ///
+    /// Value of the `st0` register at the end of the program execution.
+    ///
+    /// # Errors
+    ///
+    /// If some of the libraries required by the `method` are absent (checked before the
+    /// execution starts with [`Program::check_dependencies`]), if the `method` is not a start of
+    /// an instruction (see [`Lib::check_entrypoint`](crate::library::Lib::check_entrypoint)), if
+    /// the program code can't be decoded, or if the execution was terminated due to one of the
+    /// [`RunLimits`] being exceeded.
+    ///
+    /// Only the `method` is checked to be a start of an instruction; calls and returns into other
+    /// libraries during the execution are not checked, matching the jumps within a library.
+    pub fn try_call(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        context: &Isa::Context<'_>,
+    ) -> Result<bool, RunError> {
+        self.try_exec(program, method, context)?;
+        Ok(self.registers.st0)
+    }
+
+    /// Executes the program starting from the provided entry point (see [`Vm::try_call`]),
+    /// returning structured result of the whole program execution.
+    ///
+    /// The result reports the instructions, external calls and resources of all the libraries
+    /// executed by the program, and the location of the last executed instruction;
+    /// [`ExecResult::next`] is always `None`.
+    ///
+    /// # Errors
+    ///
+    /// Same as for [`Vm::try_call`].
+    pub fn try_exec(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        context: &Isa::Context<'_>,
+    ) -> Result<ExecResult, RunError> {
+        program.check_dependencies(method)?;
+        check_entrypoint(program, method)?;
+        self.registers.reset_counters();
+        self.registers.reset_receipt();
+        self.registers.reset_stats();
+        #[cfg(feature = "deterministic-audit")]
+        self.registers.reset_audit();
+        let (_, last_site) = self.exec(program, method, context, false)?;
+        Ok(ExecResult {
+            outcome: if self.registers.st0 { ExecOutcome::Success } else { ExecOutcome::Failure },
+            next: None,
+            steps: self.registers.steps(),
+            last_site,
+            calls: self.registers.receipt().records().to_vec(),
+            stats: self.registers.stats(),
+        })
+    }
+
+    /// Executes the program starting from its entry point (see [`Vm::try_run`]), terminating it
+    /// once the wall-clock `deadline` is reached.
+    ///
+    /// # Errors
+    ///
+    /// Same as for [`Vm::call_with_deadline`].
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn run_with_deadline(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        deadline: std::time::Instant,
+        context: &Isa::Context<'_>,
+    ) -> Result<bool, RunError> {
+        self.call_with_deadline(program, program.entrypoint(), deadline, context)
+    }
+
+    /// Executes the program starting from the provided entry point (see [`Vm::try_call`]),
+    /// terminating it once the wall-clock `deadline` is reached.
+    ///
+    /// The deadline is checked every [`DEADLINE_CHECK_STEPS`] instructions, thus the program may
+    /// run slightly past it. This is a simpler protection against runaway programs than the
+    /// complexity metering for the tools which do not need deterministic termination.
+    ///
+    /// Not available on `wasm32` targets, where [`std::time::Instant`] is not supported and
+    /// panics on use.
+    ///
+    /// # Errors
+    ///
+    /// With [`RunLimitError::Timeout`] once the deadline is reached, and for the same reasons as
+    /// [`Vm::try_call`].
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn call_with_deadline(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        deadline: std::time::Instant,
+        context: &Isa::Context<'_>,
+    ) -> Result<bool, RunError> {
+        self.registers.set_deadline(Some(deadline));
+        let res = self.try_call(program, method, context);
+        self.registers.set_deadline(None);
+        res
+    }
+
+    /// Starts program execution from the program entry point (see [`Vm::run`]), suspending it
+    /// once `max_steps` instructions are executed.
+    ///
+    /// The returned [`Execution::Suspended`] state is a continuation which may be resumed with
+    /// [`Vm::resume`]. Since the state owns the registers, a single virtual machine may interleave
+    /// the execution of many programs, allowing cooperative scheduling of them within a single
+    /// thread.
+    ///
+    /// # Errors
+    ///
+    /// Same as for [`Vm::start`].
+    pub fn run_bounded(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        max_steps: u64,
+        context: &Isa::Context<'_>,
+    ) -> Result<Execution, RunError> {
+        self.start(program, program.entrypoint(), max_steps, context)
+    }
+
+    /// Starts program execution from the provided entry point, suspending it once `steps`
+    /// instructions are executed. The suspended execution may be continued with
+    /// [`Vm::resume`].
+    ///
+    /// # Errors
+    ///
+    /// If some of the libraries required by the `method` are absent or the `method` is not a
+    /// start of an instruction (see [`Vm::try_call`]), if the program code can't be decoded, or if
+    /// the execution was terminated due to one of the [`RunLimits`] being exceeded.
+    pub fn start(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        steps: u64,
+        context: &Isa::Context<'_>,
+    ) -> Result<Execution, RunError> {
+        program.check_dependencies(method)?;
+        check_entrypoint(program, method)?;
+        self.registers.reset_counters();
+        self.registers.reset_receipt();
+        self.registers.reset_stats();
+        #[cfg(feature = "deterministic-audit")]
+        self.registers.reset_audit();
+        self.exec_chunk(program, method, steps, context)
+    }
+
+    /// Checks that the registers set all the inputs declared by the ABI descriptors of the
+    /// routines starting at `method`.
+    ///
+    /// ABI descriptors are not committed by the library id, thus the execution doesn't depend on
+    /// them; the check is up to the hosts which trust the descriptors of the program libraries.
+    ///
+    /// # Errors
+    ///
+    /// With [`RunError::Abi`] for the first routine input which is not set.
+    pub fn check_inputs(
+        &self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+    ) -> Result<(), RunError> {
+        self.check_abi(program, method, Abi::check_inputs)
+    }
+
+    /// Checks that the registers set all the outputs declared by the ABI descriptors of the
+    /// routines starting at `method`, after the routine was executed (see
+    /// [`Vm::check_inputs`]).
+    ///
+    /// # Errors
+    ///
+    /// With [`RunError::Abi`] for the first routine output which is not set.
+    pub fn check_outputs(
+        &self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+    ) -> Result<(), RunError> {
+        self.check_abi(program, method, Abi::check_outputs)
+    }
+
+    fn check_abi(
+        &self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        check: impl Fn(&Abi, &CoreRegs) -> Result<(), AbiError>,
+    ) -> Result<(), RunError> {
+        let Some(lib) = program.lib(method.lib) else {
+            return Ok(());
+        };
+        lib.abis_at(method.pos)
+            .try_for_each(|abi| check(abi, &self.registers))
+            .map_err(|err| RunError::Abi(err, method))
+    }
+
+    /// Resumes suspended program execution from the provided state, replacing the virtual
+    /// machine registers, and suspends it again once `steps` more instructions are executed.
+    ///
+    /// # Errors
+    ///
+    /// If the program code can't be decoded, or if the execution was terminated due to one of
+    /// the [`RunLimits`] being exceeded.
+    pub fn resume(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        state: VmState,
+        steps: u64,
+        context: &Isa::Context<'_>,
+    ) -> Result<Execution, RunError> {
+        self.registers = state.registers;
+        self.exec_chunk(program, state.site, steps, context)
+    }
+
+    /// Executes program from the provided entry point until it completes, passing host function
+    /// calls to `host`, and records the execution for a later [`Vm::replay`].
+    ///
+    /// Yields of the extension instructions are ignored (see [`Execution::Yielded`]).
+    ///
+    /// # Errors
+    ///
+    /// Same as for [`Vm::start`].
+    pub fn record(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        context: &Isa::Context<'_>,
+        mut host: impl FnMut(&PendingCall) -> MaybeNumber,
+    ) -> Result<Recording, RunError> {
+        let registers = self.registers.clone();
+        let mut host_results = vec![];
+        let mut execution = self.start(program, method, u64::MAX, context)?;
+        loop {
+            let state = match execution {
+                Execution::Complete(status) => {
+                    let digest = self.registers.digest();
+                    return Ok(Recording { method, registers, host_results, status, digest });
+                }
+                Execution::Suspended(state) | Execution::Yielded { state, .. } => state,
+                Execution::Pending(call) => {
+                    let value = host(&call);
+                    host_results.push(HostResult { site: call.site(), func: call.func, value });
+                    call.complete(value)
+                }
+            };
+            execution = self.resume(program, state, u64::MAX, context)?;
+        }
+    }
+
+    /// Re-executes program from the recording made by [`Vm::record`], replacing the virtual
+    /// machine registers with the recorded ones and completing host function calls with the
+    /// recorded results. Returns value of `st0` register at the end of the execution.
+    ///
+    /// # Errors
+    ///
+    /// If the execution fails, makes host function calls which do not match the recorded ones,
+    /// or completes in a state different from the recorded one.
+    pub fn replay(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        recording: &Recording,
+        context: &Isa::Context<'_>,
+    ) -> Result<bool, ReplayError> {
+        self.registers = recording.registers.clone();
+        let mut results = recording.host_results.iter();
+        let mut execution = self.start(program, recording.method, u64::MAX, context)?;
+        let status = loop {
+            let state = match execution {
+                Execution::Complete(status) => break status,
+                Execution::Suspended(state) | Execution::Yielded { state, .. } => state,
+                Execution::Pending(call) => {
+                    let (site, func) = (call.site(), call.func);
+                    let recorded = results.next().ok_or(ReplayError::Unrecorded { site, func })?;
+                    if (recorded.site, recorded.func) != (site, func) {
+                        return Err(ReplayError::CallMismatch {
+                            site,
+                            func,
+                            recorded_site: recorded.site,
+                            recorded_func: recorded.func,
+                        });
+                    }
+                    call.complete(recorded.value)
+                }
+            };
+            execution = self.resume(program, state, u64::MAX, context)?;
+        };
+        if results.len() > 0 {
+            return Err(ReplayError::Unused(results.len()));
+        }
+        if status != recording.status {
+            return Err(ReplayError::StatusMismatch(status));
+        }
+        if self.registers.digest() != recording.digest {
+            return Err(ReplayError::StateMismatch);
+        }
+        Ok(status)
+    }
+
+    fn exec_chunk(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        site: LibSite,
+        steps: u64,
+        context: &Isa::Context<'_>,
+    ) -> Result<Execution, RunError> {
+        let suspend_at = self.registers.steps().saturating_add(steps);
+        self.registers.set_suspend_at(Some(suspend_at));
+        let res = self.exec(program, site, context, true);
+        self.registers.set_suspend_at(None);
+        let host_call = self.registers.take_host_call();
+        let (res, last_site) = res?;
+        Ok(match (res, host_call) {
+            (Some((site, ExecOutcome::Yielded(code))), _) => {
+                let state = VmState { site, registers: mem::take(&mut self.registers) };
+                Execution::Yielded { code, state }
+            }
+            (Some((site, _)), None) => {
+                Execution::Suspended(VmState { site, registers: mem::take(&mut self.registers) })
+            }
+            (Some((site, _)), Some((func, reg, dst))) => {
+                let state = VmState { site, registers: mem::take(&mut self.registers) };
+                Execution::Pending(PendingCall { func, reg, dst, state })
+            }
+            // Host call made by the last executed instruction is still passed to the embedder;
+            // the execution resumes past the end of the code segment and completes immediately
+            (None, Some((func, reg, dst))) => {
+                let site = LibSite::with(u16::MAX, last_site.lib);
+                let state = VmState { site, registers: mem::take(&mut self.registers) };
+                Execution::Pending(PendingCall { func, reg, dst, state })
+            }
+            (None, None) => Execution::Complete(self.registers.st0),
+        })
+    }
+
+    /// Executes the program until it completes or gets suspended, returning location to resume
+    /// the execution from together with the suspending outcome in the latter case, and the
+    /// location of the last executed instruction.
+    ///
+    /// Yields are ignored unless the program is executed in `chunked` mode.
+    fn exec(
+        &mut self,
+        program: &impl Program<Isa = Isa>,
+        method: LibSite,
+        context: &Isa::Context<'_>,
+        chunked: bool,
+    ) -> Result<(Option<(LibSite, ExecOutcome)>, LibSite), RunError> {
         let mut call = Some(method);
+        let mut last_site = method;
         while let Some(ref mut site) = call {
             if let Some(lib) = program.lib(site.lib) {
-                call = lib.exec::<Isa>(site.pos, &mut self.registers, context);
+                let failure_hook = &mut self.failure_hook;
+                let res = lib.try_run_hooked::<Isa>(
+                    site.pos,
+                    &mut self.registers,
+                    context,
+                    |instr, site, regs| {
+                        if let Some(hook) = failure_hook {
+                            hook.st0_cleared(instr, site, regs)
+                        }
+                    },
+                )?;
+                if res.steps > 0 {
+                    last_site = res.last_site;
+                }
+                match (res.outcome, res.next) {
+                    (ExecOutcome::Suspended, Some(next)) => {
+                        return Ok((Some((next, res.outcome)), last_site))
+                    }
+                    (ExecOutcome::Yielded(_), Some(next)) if chunked => {
+                        return Ok((Some((next, res.outcome)), last_site))
+                    }
+                    _ => {}
+                }
+                if let (Some(hook), Some(to)) = (&mut self.call_hook, res.next) {
+                    let from = res.last_site;
+                    if to.lib != from.lib {
+                        let instr = lib.instr_at::<Isa>(from.pos);
+                        if instr.and_then(|instr| instr.call_site()).is_some() {
+                            if !hook.before_call(from, to, &self.registers) {
+                                return Err(RunError::CallDenied { from, to });
+                            }
+                        } else {
+                            hook.after_return(from, to, &self.registers);
+                        }
+                    }
+                }
+                call = res.next;
             } else if let Some(pos) = site.pos.checked_add(1) {
                 site.pos = pos;
             } else {
                 call = None;
             };
         }
-        self.registers.st0
+        Ok((None, last_site))
+    }
+}
+
+/// Checks that the program entry point is a start of an instruction of its library, if the
+/// library is known to the program.
+fn check_entrypoint<Isa: InstructionSet>(
+    program: &impl Program<Isa = Isa>,
+    method: LibSite,
+) -> Result<(), RunError> {
+    match program.lib(method.lib) {
+        Some(lib) => {
+            lib.check_entrypoint::<Isa>(method.pos).map_err(|err| RunError::Entrypoint(err, method))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Executes independent programs in a batch, starting each of them from its entry point with a
+/// separate copy of the corresponding `inputs` registers, such that the runs are isolated from
+/// each other. With the `rayon` feature the programs are executed in parallel.
+///
+/// Execution limits are taken from the input registers (see [`CoreRegs::set_limits`]).
+///
+/// # Returns
+///
+/// Results of [`Vm::try_exec`] for each of the programs, in the order of `programs`.
+///
+/// # Panics
+///
+/// If the number of programs does not match the number of inputs, or if the instruction set has
+/// colliding opcodes (see [`Vm::new`]).
+pub fn verify_batch<'ctx, P>(
+    programs: &[P],
+    inputs: &[CoreRegs],
+    context: &<P::Isa as InstructionSet>::Context<'ctx>,
+) -> Vec<Result<ExecResult, RunError>>
+where
+    P: Program + Sync,
+    <P::Isa as InstructionSet>::Context<'ctx>: Sync,
+{
+    assert_eq!(programs.len(), inputs.len(), "number of programs must match number of inputs");
+    let run = |(program, regs): (&P, &CoreRegs)| {
+        let mut vm = Vm::<P::Isa>::new();
+        *vm.registers = regs.clone();
+        vm.try_exec(program, program.entrypoint(), context)
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        programs.par_iter().zip(inputs.par_iter()).map(run).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        programs.iter().zip(inputs).map(run).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::isa::{Bytecode, ControlFlowOp};
+    use crate::library::Lib;
+    use crate::receipt::CallRecord;
+    use crate::Prog;
+
+    fn prog(code: &[Instr]) -> Prog<'static, Instr> { Prog::new(Lib::assemble(code).unwrap()) }
+
+    #[test]
+    fn routine_return() {
+        let prog = prog(&[
+            Instr::ControlFlow(ControlFlowOp::Routine(5)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::ControlFlow(ControlFlowOp::Fail),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ]);
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.run(&prog, &()));
+    }
+
+    #[test]
+    fn call_return() {
+        let callee =
+            Lib::assemble(&[Instr::<ReservedOp>::ControlFlow(ControlFlowOp::Ret)]).unwrap();
+        let mut prog = prog(&[
+            Instr::ControlFlow(ControlFlowOp::Call(LibSite::with(0, callee.id()))),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+        ]);
+        prog.add_lib(callee).unwrap();
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.run(&prog, &()));
+    }
+
+    #[test]
+    fn unlimited_routine() {
+        let prog = prog(&[
+            Instr::ControlFlow(ControlFlowOp::Routine(4)),
+            Instr::ControlFlow(ControlFlowOp::Succ),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ]);
+        let mut vm = Vm::<Instr>::new();
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+        assert_eq!(vm.registers.steps(), 3);
+        assert_eq!(vm.registers.call_depth(), 0);
+    }
+
+    #[test]
+    fn max_steps() {
+        let prog =
+            prog(&[Instr::Nop, Instr::Nop, Instr::Nop, Instr::ControlFlow(ControlFlowOp::Succ)]);
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_steps(3));
+        let site = LibSite::with(3, prog.entrypoint().lib);
+        let err = vm.try_run(&prog, &()).unwrap_err();
+        assert_eq!(err, RunError::Limit(RunLimitError::StepsExceeded(3), site));
+        assert_eq!(err.site(), Some(site));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "program execution exceeded the limit of 3 instructions. (at 3 @ {})",
+                site.lib
+            )
+        );
+        assert!(!vm.registers.status());
+
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_steps(4));
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+    }
+
+    #[test]
+    fn limits_per_run() {
+        let prog = prog(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Succ)]);
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_steps(3));
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+        assert_eq!(vm.registers.steps(), 2);
+        assert_eq!(vm.try_call(&prog, prog.entrypoint(), &()), Ok(true));
+        assert_eq!(vm.registers.steps(), 2);
+        let Ok(Execution::Suspended(state)) = vm.start(&prog, prog.entrypoint(), 1, &()) else {
+            panic!("execution is not suspended");
+        };
+        assert_eq!(state.registers.steps(), 1);
+    }
+
+    #[test]
+    fn max_backward_jumps() {
+        let prog = prog(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Jmp(0))]);
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_backward_jumps(10));
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::Limit(
+                RunLimitError::BackwardJumpsExceeded(10),
+                LibSite::with(1, prog.entrypoint().lib)
+            ))
+        );
+        assert_eq!(vm.registers.steps(), 22);
+        assert!(!vm.run(&prog, &()));
+    }
+
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    #[test]
+    fn deadline() {
+        use std::time::{Duration, Instant};
+
+        let looping = prog(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Jmp(0))]);
+        let mut vm = Vm::<Instr>::new();
+        assert_eq!(
+            vm.run_with_deadline(&looping, Instant::now(), &()),
+            Err(RunError::Limit(
+                RunLimitError::Timeout,
+                LibSite::with(1, looping.entrypoint().lib)
+            ))
+        );
+        assert_eq!(vm.registers.steps(), DEADLINE_CHECK_STEPS);
+        assert!(!vm.registers.status());
+
+        let finite = prog(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Succ)]);
+        let mut vm = Vm::<Instr>::new();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        assert_eq!(vm.run_with_deadline(&finite, deadline, &()), Ok(true));
+    }
+
+    #[test]
+    fn max_call_depth() {
+        let prog = prog(&[Instr::ControlFlow(ControlFlowOp::Routine(0))]);
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_call_depth(8));
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::Limit(RunLimitError::CallDepthExceeded(8), prog.entrypoint()))
+        );
+        assert_eq!(vm.registers.call_depth(), 9);
+    }
+
+    #[test]
+    fn runtime_env() {
+        use crate::isa::{Env, EnvOp};
+        use crate::reg::{Reg32, RegA, RegR, RegS};
+
+        let code = [
+            Instr::Nop,
+            Instr::ExtensionCodes(EnvOp::Lib(Reg32::Reg1)),
+            Instr::ExtensionCodes(EnvOp::Pos(Reg32::Reg2)),
+            Instr::ExtensionCodes(EnvOp::Isa(RegS::from(3))),
+            Instr::ExtensionCodes(EnvOp::Ctx(Reg32::Reg4)),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ];
+        let lib = Lib::assemble(&code).unwrap();
+        let id = lib.id();
+        let prog = Prog::<Instr<EnvOp>>::new(lib);
+
+        let env = Env::with::<Instr<EnvOp>>([0xAB; 32]);
+        let mut vm = Vm::<Instr<EnvOp>>::new();
+        assert!(vm.run(&prog, &env));
+        assert_eq!(vm.registers.get(RegR::R256, Reg32::Reg1), id.to_byte_array().into());
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg2), 3u16.into());
+        assert_eq!(vm.registers.get_s(RegS::from(3)).unwrap().as_ref(), env.isae.as_bytes());
+        assert_eq!(vm.registers.get(RegR::R256, Reg32::Reg4), [0xAB; 32].into());
+
+        let mut vm = Vm::<Instr<EnvOp>>::new();
+        assert!(!vm.run(&prog, &Env::with::<Instr<EnvOp>>(None)));
+        assert_eq!(vm.registers.get(RegR::R256, Reg32::Reg4), none!());
+    }
+
+    #[test]
+    fn composed_extensions() {
+        use crate::isa::{CtOp, Env, EnvOp, Extensions, PrngOp};
+        use crate::reg::{Reg32, RegA};
+
+        type Isa = Instr<Extensions<EnvOp, Extensions<CtOp, PrngOp>>>;
+
+        let lib = Lib::assemble_source::<Isa>(
+            "
+            envpos  a16[0]
+            envctx  r256[0]
+            cteq    r256[0],r256[0]
+            put     a32[0],0
+            rnd     a8[1],r256[0],a32[0]
+            ret
+        ",
+        )
+        .unwrap();
+        for isa in ["ALUCT", "ALURE", "ALURND"] {
+            assert!(lib.isae.iter().any(|id| id == isa));
+        }
+        let prog = Prog::<Isa>::new(lib);
+
+        let env = Env::with::<Isa>([0xAB; 32]);
+        let mut vm = Vm::<Isa>::new();
+        assert!(vm.run(&prog, &(env, ((), ()))));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg0), 0u16.into());
+        assert_eq!(vm.registers.get(RegA::A32, Reg32::Reg0), 1u32.into());
+        assert!(vm.registers.get(RegA::A8, Reg32::Reg1).is_some());
+    }
+
+    #[test]
+    fn opcode_collisions() {
+        use alloc::collections::BTreeSet;
+
+        use crate::isa::{
+            Bytecode, CtOp, DataOp, EncOp, EnvOp, ExecStep, Extensions, FieldOp, FixedOp, FrameOp,
+            HostOp, ImmOp, LoopOp, ModOp, PrngOp, RelJumpOp, StackOp, StepOp, SwitchOp, XferOp,
+        };
+        use crate::reg::CoreRegs;
+
+        #[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
+        #[display("sha")]
+        enum ShaOp {
+            Sha,
+        }
+
+        bytecode! {
+            ShaOp {
+                crate::isa::opcodes::INSTR_SHA256 => Sha [],
+            }
+        }
+
+        impl InstructionSet for ShaOp {
+            type Context<'ctx> = ();
+
+            fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::new() }
+
+            fn exec(&self, _: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep { ExecStep::Next }
+        }
+
+        assert!(Instr::<ReservedOp>::opcode_collisions().is_empty());
+        assert!(
+            Instr::<Extensions<EnvOp, Extensions<CtOp, PrngOp>>>::opcode_collisions().is_empty()
+        );
+        assert!(
+            Instr::<Extensions<PrngOp, Extensions<ModOp, EncOp>>>::opcode_collisions().is_empty()
+        );
+        assert!(Instr::<Extensions<EncOp, RelJumpOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<RelJumpOp, ImmOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<ImmOp, FrameOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<FrameOp, StackOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<StackOp, FieldOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<FieldOp, HostOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<HostOp, XferOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<XferOp, StepOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<StepOp, DataOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<DataOp, SwitchOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<SwitchOp, LoopOp>>::opcode_collisions().is_empty());
+        assert!(Instr::<Extensions<LoopOp, FixedOp>>::opcode_collisions().is_empty());
+        #[cfg(feature = "bitcoin")]
+        assert!(Instr::<Extensions<StackOp, crate::isa::BitcoinOp>>::opcode_collisions().is_empty());
+
+        let collisions = Instr::<Extensions<EnvOp, Extensions<CtOp, CtOp>>>::opcode_collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].opcodes, 0x94..=0x97);
+        assert_eq!(
+            collisions[0].to_string(),
+            "opcodes 0x94..=0x97 claimed by `aluvm::isa::instr::CtOp` are already used by \
+             `aluvm::isa::instr::CtOp`"
+        );
+
+        let collisions = Instr::<Extensions<EnvOp, ShaOp>>::opcode_collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].opcodes, 0x81..=0x81);
+        assert!(collisions[0].owner.ends_with("DigestOp"));
+        assert!(collisions[0].to_string().starts_with("opcode 0x81 claimed by"));
+    }
+
+    #[test]
+    fn isa_versions() {
+        use alloc::collections::BTreeSet;
+
+        use crate::isa::ExecStep;
+        use crate::library::{IsaId, IsaSeg};
+        use crate::reg::CoreRegs;
+        use crate::ProgError;
+
+        #[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
+        #[display("bigop")]
+        enum BigOp {
+            Big,
+        }
+
+        bytecode! {
+            BigOp {
+                0xFF => Big [],
+            }
+        }
+
+        impl InstructionSet for BigOp {
+            type Context<'ctx> = ();
+
+            fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::from(["ALUBIG"]) }
+
+            fn isa_set() -> BTreeSet<IsaId> {
+                BTreeSet::from([
+                    IsaId::new("ALUBIG").unwrap(),
+                    IsaId::with("ALUBIG", 2, ["SIMD", "WIDE"]).unwrap(),
+                ])
+            }
+
+            fn exec(&self, _: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep { ExecStep::Next }
+        }
+
+        type Isa = Instr<BigOp>;
+        assert!(Isa::isa_string().starts_with("ALU ALUBIG ALUBIG:2+SIMD+WIDE BPDIGEST"));
+        assert!(Isa::is_supported("ALUBIG:1+WIDE"));
+        assert!(!Isa::is_supported("ALUBIG:3"));
+        assert!(!Isa::is_supported("ALUBIG:1+FLOAT"));
+        assert!(!Isa::is_supported("ALUBIG:01"));
+
+        let mut lib = Lib::assemble::<Isa>(&[Instr::Nop]).unwrap();
+        assert_eq!(lib.isae.to_string(), Isa::isa_string());
+        let mut prog = Prog::<Isa>::new(lib.clone());
+        lib.isae = IsaSeg::with("ALU ALUBIG:2+SIMD").unwrap();
+        assert_eq!(prog.add_lib(lib.clone()), Ok(true));
+        lib.isae = IsaSeg::with("ALU ALUBIG:3").unwrap();
+        assert_eq!(prog.add_lib(lib), Err(ProgError::IsaNotSupported(s!("ALUBIG:3"))));
+    }
+
+    #[test]
+    #[should_panic(expected = "instruction set has colliding opcodes")]
+    fn colliding_vm() {
+        use crate::isa::{CtOp, Extensions};
+        Vm::<Instr<Extensions<CtOp, CtOp>>>::new();
+    }
+
+    #[test]
+    #[should_panic(expected = "instruction set has colliding opcodes")]
+    fn colliding_vm_default() {
+        use crate::isa::{CtOp, Extensions};
+        Vm::<Instr<Extensions<CtOp, CtOp>>>::default();
+    }
+
+    #[test]
+    #[should_panic(expected = "instruction set has colliding opcodes")]
+    fn colliding_batch() {
+        use crate::isa::{CtOp, Extensions};
+        let prog = Prog::<Instr<Extensions<CtOp, CtOp>>>::new(Lib::default());
+        verify_batch(&[prog], &[CoreRegs::new()], &Default::default());
+    }
+
+    #[test]
+    fn string_memory_limit() {
+        use crate::reg::RegS;
+
+        let lib = Lib::assemble_source::<Instr>(
+            r#"
+            put     s16[0],"abc"
+            put     s16[1],"de"
+            mov     s16[1],s16[2]
+            put     s16[3],"fghi"
+            ret
+        "#,
+        )
+        .unwrap();
+        let prog = Prog::<Instr>::new(lib);
+
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_str_bytes(6));
+        assert!(!vm.run(&prog, &()));
+        assert_eq!(vm.registers.get_s(RegS::from(0)).unwrap().as_ref(), b"abc");
+        assert!(vm.registers.get_s(RegS::from(1)).is_none());
+        assert_eq!(vm.registers.get_s(RegS::from(2)).unwrap().as_ref(), b"de");
+        assert!(vm.registers.get_s(RegS::from(3)).is_none());
+        assert_eq!(vm.registers.str_bytes(), 5);
+
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.run(&prog, &()));
+        assert_eq!(vm.registers.str_bytes(), 9);
+    }
+
+    #[test]
+    fn missing_dependency() {
+        let callee = Lib::assemble::<Instr>(&[Instr::ControlFlow(ControlFlowOp::Succ)]).unwrap();
+        let callee_id = callee.id();
+        let caller = Lib::assemble::<Instr>(&[
+            Instr::ControlFlow(ControlFlowOp::Call(LibSite::with(0, callee_id))),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ])
+        .unwrap();
+        let caller_id = caller.id();
+        assert_eq!(caller.dependencies().collect::<Vec<_>>(), vec![callee_id]);
+
+        let mut prog = Prog::<Instr>::new(caller);
+        let mut vm = Vm::<Instr>::new();
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::Link(LinkError::MissingDependency {
+                dependent: caller_id,
+                dependency: callee_id
+            }))
+        );
+        assert_eq!(vm.registers.steps(), 0);
+        assert_eq!(
+            vm.try_call(&prog, LibSite::with(0, callee_id), &()),
+            Err(RunError::Link(LinkError::EntrypointAbsent(callee_id)))
+        );
+
+        prog.add_lib(callee).unwrap();
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+    }
+
+    #[test]
+    fn call_hook() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct Log(Arc<Mutex<Vec<(bool, LibSite, LibSite)>>>);
+        impl CallHook for Log {
+            fn before_call(&mut self, caller: LibSite, callee: LibSite, _: &CoreRegs) -> bool {
+                self.0.lock().unwrap().push((true, caller, callee));
+                true
+            }
+            fn after_return(&mut self, callee: LibSite, caller: LibSite, _: &CoreRegs) {
+                self.0.lock().unwrap().push((false, callee, caller));
+            }
+        }
+
+        let callee =
+            Lib::assemble::<Instr>(&[Instr::Nop, Instr::ControlFlow(ControlFlowOp::Ret)]).unwrap();
+        let callee_id = callee.id();
+        let caller = Lib::assemble::<Instr>(&[
+            Instr::ControlFlow(ControlFlowOp::Call(LibSite::with(0, callee_id))),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ])
+        .unwrap();
+        let caller_id = caller.id();
+        let prog = Prog::<Instr>::with([caller, callee], LibSite::with(0, caller_id)).unwrap();
+
+        let log = Log::default();
+        let records = log.0.clone();
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.set_call_hook(log).is_none());
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+        assert_eq!(*records.lock().unwrap(), vec![
+            (true, LibSite::with(0, caller_id), LibSite::with(0, callee_id)),
+            (false, LibSite::with(1, callee_id), LibSite::with(4, caller_id)),
+        ]);
+
+        let mut vm = Vm::<Instr>::new();
+        vm.set_call_hook(BTreeSet::from([caller_id]));
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::CallDenied {
+                from: LibSite::with(0, caller_id),
+                to: LibSite::with(0, callee_id)
+            })
+        );
+        assert!(vm.take_call_hook().is_some());
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+    }
+
+    #[test]
+    fn failure_hook() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct Log(Arc<Mutex<Vec<(String, LibSite)>>>);
+        impl FailureHook<Instr> for Log {
+            fn st0_cleared(&mut self, instr: &Instr, site: LibSite, _: &CoreRegs) {
+                self.0.lock().unwrap().push((instr.to_string(), site));
+            }
+        }
+
+        let lib = Lib::assemble_source::<Instr>(
+            "
+            put     a8[0],1
+            put     a8[1],2
+            eq.n    a8[0],a8[1]
+            eq.n    a8[0],a8[1]
+            put     a8[1],1
+            eq.n    a8[0],a8[1]
+            put     a8[1],3
+            eq.n    a8[0],a8[1]
+            ret
+        ",
+        )
+        .unwrap();
+        let id = lib.id();
+        let prog = Prog::<Instr>::new(lib);
+
+        let log = Log::default();
+        let records = log.0.clone();
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.set_failure_hook(log).is_none());
+        assert_eq!(vm.try_run(&prog, &()), Ok(false));
+        assert_eq!(*records.lock().unwrap(), vec![
+            (s!("eq.n    a8[0],a8[1]"), LibSite::with(8, id)),
+            (s!("eq.n    a8[0],a8[1]"), LibSite::with(25, id)),
+        ]);
+
+        assert!(vm.take_failure_hook().is_some());
+        assert_eq!(vm.try_run(&prog, &()), Ok(false));
+        assert_eq!(records.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn register_frames() {
+        use crate::isa::FrameOp;
+        use crate::reg::{Reg32, RegA};
+
+        let lib = Lib::assemble_source::<Instr<FrameOp>>(
+            "
+            put     a64[0],7
+            routine 0x0008
+            succ
+            pushr   a64[0],a64[1]
+            put     a64[0],100
+            put     a64[1],200
+            popr    a64[0],a64[1]
+            ret
+        ",
+        )
+        .unwrap();
+        let mut vm = Vm::<Instr<FrameOp>>::new();
+        assert!(vm.run(&Prog::<Instr<FrameOp>>::new(lib), &()));
+        assert_eq!(vm.registers.get(RegA::A64, Reg32::Reg0), 7u64.into());
+        assert!(vm.registers.get(RegA::A64, Reg32::Reg1).is_none());
+        assert_eq!(vm.registers.frame_depth(), 0);
+    }
+
+    #[test]
+    fn suspend_resume() {
+        use crate::reg::{Reg32, RegA};
+
+        let lib = Lib::assemble_source::<Instr>(
+            "
+            put     a16[0],0
+            put     a16[1],1
+            put     a16[2],20
+            routine 0x0011
+            stinv
+            ret
+            add.uc  a16[1],a16[0]
+            lt.u    a16[0],a16[2]
+            jif     0x0011
+            ret
+        ",
+        )
+        .unwrap();
+        let entrypoint = LibSite::with(0, lib.id());
+        let prog = Prog::<Instr>::new(lib);
+
+        let mut vm = Vm::<Instr>::new();
+        assert_eq!(vm.try_run(&prog, &()), Ok(true));
+        let steps = vm.registers.steps();
+        let snapshot = vm.registers.snapshot();
+
+        let mut vm = Vm::<Instr>::new();
+        let mut execution = vm.start(&prog, entrypoint, 7, &()).unwrap();
+        let mut chunks = 1;
+        while let Execution::Suspended(state) = execution {
+            assert_eq!(state.registers.steps(), chunks * 7);
+            let state = VmState::from_bytes(&state.to_bytes()).unwrap();
+            execution = Vm::<Instr>::new().resume(&prog, state, 7, &()).unwrap();
+            chunks += 1;
+        }
+        assert!(matches!(execution, Execution::Complete(true)));
+        assert_eq!(chunks, (steps + 6) / 7);
+
+        let mut vm = Vm::<Instr>::new();
+        let Execution::Suspended(state) = vm.start(&prog, entrypoint, 4, &()).unwrap() else {
+            panic!("execution was not suspended");
+        };
+        assert_eq!(state.site, LibSite::with(0x0011, entrypoint.lib));
+        assert_eq!(state.registers.call_depth(), 1);
+        assert!(matches!(vm.resume(&prog, state, u64::MAX, &()), Ok(Execution::Complete(true))));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg0), 20u16.into());
+        assert_eq!(vm.registers.snapshot(), snapshot);
+
+        assert_eq!(VmState::from_bytes(&[2]).unwrap_err(), SnapshotError::UnsupportedVersion(2));
+        assert_eq!(VmState::from_bytes(&[3, 0]).unwrap_err(), SnapshotError::Truncated);
+    }
+
+    #[test]
+    fn run_bounded() {
+        use alloc::collections::VecDeque;
+
+        let progs = [3u8, 20, 1].map(|count| {
+            let lib = Lib::assemble_source::<Instr>(&format!(
+                "
+                put     a8[0],{}
+                dec     a8[0]
+                put     a8[1],0
+                eq.n    a8[0],a8[1]
+                jif     0x0014
+                jmp     0x0004
+                ret
+            ",
+                count
+            ))
+            .unwrap();
+            Prog::<Instr>::new(lib)
+        });
+
+        let mut vm = Vm::<Instr>::new();
+        let mut queue = VecDeque::new();
+        for (no, prog) in progs.iter().enumerate() {
+            queue.push_back((no, vm.run_bounded(prog, 5, &()).unwrap()));
+        }
+        let mut completed = vec![];
+        while let Some((no, execution)) = queue.pop_front() {
+            match execution {
+                Execution::Complete(st0) => completed.push((no, st0)),
+                Execution::Suspended(state) => {
+                    queue.push_back((no, vm.resume(&progs[no], state, 5, &()).unwrap()))
+                }
+                Execution::Pending(_) | Execution::Yielded { .. } => {
+                    unreachable!("programs do not call host functions or yield")
+                }
+            }
+        }
+        assert_eq!(completed, vec![(2, true), (0, true), (1, true)]);
+    }
+
+    #[test]
+    fn host_calls() {
+        use crate::isa::HostOp;
+        use crate::reg::{Reg32, RegA};
+
+        type Isa = Instr<HostOp>;
+
+        let lib = Lib::assemble_source::<Isa>(
+            "
+            put     a16[0],7
+            hcall   0x0001,a16[1]
+            add.uc  a16[0],a16[1]
+            hcall   0x0002,r256[0]
+            ret
+        ",
+        )
+        .unwrap();
+        let prog = Prog::<Isa>::new(lib);
+
+        let mut vm = Vm::<Isa>::new();
+        let Execution::Pending(call) = vm.run_bounded(&prog, u64::MAX, &()).unwrap() else {
+            panic!("host function was not called");
+        };
+        assert_eq!((call.func, call.reg, call.dst), (1, RegA::A16.into(), Reg32::Reg1));
+        assert_eq!(call.site().pos, 8);
+        let arg = call.registers().get(RegA::A16, Reg32::Reg0);
+        let res = u16::from(arg.unwrap()) * 2;
+        let Execution::Pending(call) = vm.resume(&prog, call.complete(res), u64::MAX, &()).unwrap()
+        else {
+            panic!("host function was not called");
+        };
+        assert_eq!(call.func, 2);
+        let state = call.complete(MaybeNumber::none());
+        assert!(!state.registers.st0);
+        assert!(matches!(
+            vm.resume(&prog, state, u64::MAX, &()).unwrap(),
+            Execution::Complete(false)
+        ));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg1), 21u16.into());
+        let receipt = vm.registers.receipt().clone();
+        assert_eq!(receipt.len(), 2);
+        assert!(matches!(receipt.records()[0], CallRecord::Host { func: 1, .. }));
+        assert_eq!(receipt.records()[0].site().pos, 4);
+        assert_eq!(receipt.records()[1].site().pos, 11);
+
+        // Host call made by the last instruction of the program is passed to the embedder as well
+        let hcall = Lib::assemble_source::<Isa>("hcall 0x0003,a16[2]").unwrap();
+        let nop = Lib::assemble::<Isa>(&[Instr::Nop]).unwrap().code_segment()[0];
+        let mut code = vec![nop; u16::MAX as usize - 4];
+        code.extend_from_slice(hcall.code_segment());
+        let last = Lib::with(&hcall.isae_segment(), code, vec![], default!()).unwrap();
+        let last_prog = Prog::<Isa>::new(last);
+        let mut vm = Vm::<Isa>::new();
+        let Execution::Pending(call) = vm.run_bounded(&last_prog, u64::MAX, &()).unwrap() else {
+            panic!("host function was not called");
+        };
+        assert_eq!(call.func, 3);
+        assert_eq!(call.site().pos, u16::MAX);
+        let state = call.complete(5u16);
+        assert!(matches!(
+            vm.resume(&last_prog, state, u64::MAX, &()).unwrap(),
+            Execution::Complete(true)
+        ));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg2), 5u16.into());
+
+        let mut vm = Vm::<Isa>::new();
+        assert!(!vm.run(&prog, &()));
+        assert_eq!(vm.registers.get(RegA::A16, Reg32::Reg1), MaybeNumber::none());
+        // Rejected calls are logged as well, with the same arguments
+        assert_eq!(vm.registers.receipt().len(), 2);
+        assert_eq!(vm.registers.receipt().records()[0], receipt.records()[0]);
+    }
+
+    #[test]
+    fn yields() {
+        use alloc::collections::BTreeSet;
+
+        use crate::isa::{ExecStep, PutOp};
+        use crate::library::ExecOutcome;
+        use crate::reg::{Reg32, RegA};
+
+        #[derive(Copy, Clone, Eq, PartialEq, Debug, Display)]
+        #[display("yield")]
+        enum YieldOp {
+            Yield,
+        }
+
+        bytecode! {
+            YieldOp {
+                0xFE => Yield [],
+            }
+        }
+
+        impl InstructionSet for YieldOp {
+            type Context<'ctx> = ();
+
+            fn isa_ids() -> BTreeSet<&'static str> { BTreeSet::from(["ALUYIELD"]) }
+
+            fn exec(&self, _: &mut CoreRegs, _: LibSite, _: &()) -> ExecStep {
+                ExecStep::Yield(0x2A)
+            }
+        }
+
+        type Isa = Instr<YieldOp>;
+
+        let put = |val: u8| {
+            Instr::Put(PutOp::PutA(RegA::A8, Reg32::Reg0, Box::new(MaybeNumber::from(val))))
+        };
+        let code = [
+            put(1),
+            Instr::ExtensionCodes(YieldOp::Yield),
+            put(2),
+            Instr::ExtensionCodes(YieldOp::Yield),
+            Instr::ControlFlow(ControlFlowOp::Ret),
+        ];
+        let lib = Lib::assemble::<Isa>(&code).unwrap();
+        let entrypoint = LibSite::with(0, lib.id());
+        let after = LibSite::with(code[0].byte_count() + 1, lib.id());
+        let last = LibSite::with(after.pos + code[2].byte_count() + 1, lib.id());
+
+        let mut regs = CoreRegs::new();
+        let res = lib.run::<Isa>(0, &mut regs, &()).unwrap();
+        assert_eq!(res.outcome, ExecOutcome::Yielded(0x2A));
+        assert_eq!(res.outcome.to_string(), "yielded(0x002A)");
+        assert_eq!(res.next, Some(after));
+        assert!(!res.is_call());
+        let res = lib.run::<Isa>(after.pos, &mut regs, &()).unwrap();
+        assert_eq!((res.outcome, res.next), (ExecOutcome::Yielded(0x2A), Some(last)));
+
+        let prog = Prog::<Isa>::new(lib);
+        let mut vm = Vm::<Isa>::new();
+        let Execution::Yielded { code, state } = vm.start(&prog, entrypoint, 100, &()).unwrap()
+        else {
+            panic!("execution has not yielded");
+        };
+        assert_eq!((code, state.site), (0x2A, after));
+        assert_eq!(state.registers.get(RegA::A8, Reg32::Reg0), 1u8.into());
+        assert_eq!(state.registers.steps(), 2);
+        // Yield made by the last instruction before the program end is reported as well
+        let Execution::Yielded { code, state } = vm.resume(&prog, state, 100, &()).unwrap() else {
+            panic!("execution has not yielded");
+        };
+        assert_eq!((code, state.site), (0x2A, last));
+        assert_eq!(state.registers.get(RegA::A8, Reg32::Reg0), 2u8.into());
+        assert!(matches!(vm.resume(&prog, state, 100, &()), Ok(Execution::Complete(true))));
+        assert_eq!(vm.registers.get(RegA::A8, Reg32::Reg0), 2u8.into());
+
+        let mut vm = Vm::<Isa>::new();
+        assert!(vm.run(&prog, &()));
+        assert_eq!(vm.registers.get(RegA::A8, Reg32::Reg0), 2u8.into());
+        assert_eq!(vm.registers.steps(), 5);
+    }
+
+    #[test]
+    fn call_receipts() {
+        let callee = Lib::assemble::<Instr>(&[Instr::ControlFlow(ControlFlowOp::Ret)]).unwrap();
+        let to = LibSite::with(0, callee.id());
+        let call = Instr::ControlFlow(ControlFlowOp::Call(to));
+        let caller =
+            Lib::assemble::<Instr>(&[call.clone(), Instr::ControlFlow(ControlFlowOp::Succ)])
+                .unwrap();
+        let from = LibSite::with(0, caller.id());
+        let mut prog = Prog::<Instr>::new(caller.clone());
+        prog.add_lib(callee).unwrap();
+
+        let mut vm = Vm::<Instr>::new();
+        assert!(vm.run(&prog, &()));
+        let back = LibSite::with(call.byte_count(), caller.id());
+        assert_eq!(vm.registers.receipt().records(), [
+            CallRecord::Lib { site: from, target: to },
+            CallRecord::Lib { site: to, target: back }
+        ]);
+        assert_eq!(
+            vm.registers.receipt().records()[0].to_string(),
+            format!("call from {from} to {to}")
+        );
+
+        let mut regs = CoreRegs::new();
+        let res = caller.run::<Instr>(0, &mut regs, &()).unwrap();
+        assert_eq!(res.calls, [CallRecord::Lib { site: from, target: to }]);
+        let res = caller.run::<Instr>(back.pos, &mut regs, &()).unwrap();
+        assert!(res.calls.is_empty());
+        assert_eq!(regs.receipt().len(), 1);
+
+        let mut vm = Vm::<Instr>::with_limits(RunLimits::unlimited().with_max_calls(1));
+        assert_eq!(
+            vm.try_run(&prog, &()),
+            Err(RunError::Limit(RunLimitError::CallsExceeded(1), to))
+        );
+        assert_eq!(vm.registers.receipt().len(), 2);
+    }
+
+    #[test]
+    fn batch() {
+        use crate::reg::{Reg32, RegA};
+
+        let check = Lib::assemble_source::<Instr>(
+            "
+            put     a64[1],100
+            lt.u    a64[0],a64[1]
+            ret
+        ",
+        )
+        .unwrap();
+        let programs = [
+            Prog::<Instr>::new(check.clone()),
+            Prog::<Instr>::new(check),
+            prog(&[Instr::ControlFlow(ControlFlowOp::Jmp(0))]),
+        ];
+        let mut inputs = [CoreRegs::new(), CoreRegs::new(), CoreRegs::new()];
+        inputs[0].set(RegA::A64, Reg32::Reg0, 10u64);
+        inputs[1].set(RegA::A64, Reg32::Reg0, 1000u64);
+        inputs[2].set_limits(RunLimits::unlimited().with_max_backward_jumps(100));
+
+        let results = verify_batch(&programs, &inputs, &());
+        let outcomes = results.iter().map(|res| res.as_ref().map(|res| res.outcome));
+        assert_eq!(outcomes.collect::<Vec<_>>(), [
+            Ok(ExecOutcome::Success),
+            Ok(ExecOutcome::Failure),
+            Err(&RunError::Limit(
+                RunLimitError::BackwardJumpsExceeded(100),
+                programs[2].entrypoint()
+            ))
+        ]);
+        let res = results[0].as_ref().unwrap();
+        assert_eq!(res.steps, 3);
+        assert_eq!(res.last_site, LibSite::with(0x0007, programs[0].entrypoint().lib));
+        assert_eq!(res.next, None);
+        assert_eq!(inputs[0].steps(), 0);
     }
 }